@@ -0,0 +1,21 @@
+__kernel void breadth_first_level(__global float *matrix, __global int *level, __global int *predecessor, __global int *changed, int vertex_count, int depth, int zero_edges) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+
+    // Only the vertices not reached yet can join the next level
+    if (level[gid] != -1) {
+        return;
+    }
+
+    // Join the next level through the first edge coming from the current one
+    for (int source = 0; source < vertex_count; source++) {
+        float weight = matrix[gid * vertex_count + source];
+
+        if (level[source] == depth && (weight != 0 || zero_edges) && weight != FLT_MAX) {
+            level[gid] = depth + 1;
+            predecessor[gid] = source;
+            changed[0] = 1;
+            return;
+        }
+    }
+}
@@ -0,0 +1,90 @@
+#ifndef MEETING_SIZE
+#define MEETING_SIZE 64
+#endif
+
+__kernel void relax_frontier(__global float *matrix, __global float *distance, __global int *parent, __global int *frontier, __global int *next_frontier, int vertex_count, int reverse, int zero_edges) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+    float best = distance[gid];
+    int through = -1;
+
+    // Relax the edges between the frontier and the vertex, leaving the frontier
+    // in the search from the source and reaching it in the search from the target
+    for (int vertex = 0; vertex < vertex_count; vertex++) {
+        if (!frontier[vertex] || vertex == gid) {
+            continue;
+        }
+
+        float weight = reverse ? matrix[vertex * vertex_count + gid] : matrix[gid * vertex_count + vertex];
+
+        if ((weight != 0 || zero_edges) && weight != FLT_MAX && distance[vertex] + weight < best) {
+            best = distance[vertex] + weight;
+            through = vertex;
+        }
+    }
+
+    // Only the vertices getting closer join the next frontier
+    next_frontier[gid] = through != -1;
+
+    if (through != -1) {
+        distance[gid] = best;
+        parent[gid] = through;
+    }
+}
+
+__kernel void meeting_point(__global float *forward, __global float *backward, __global int *forward_frontier, __global int *backward_frontier, __global float *bounds, __global int *meetings, int vertex_count) {
+    // Get the global id, as the last work group can have more threads than vertices left
+    int gid = get_global_id(0);
+    int lid = get_local_id(0);
+    __local float sums[MEETING_SIZE];
+    __local float forward_mins[MEETING_SIZE];
+    __local float backward_mins[MEETING_SIZE];
+    __local int vertices[MEETING_SIZE];
+
+    // Every vertex reached by both searches is a candidate meeting point
+    sums[lid] = FLT_MAX;
+    forward_mins[lid] = FLT_MAX;
+    backward_mins[lid] = FLT_MAX;
+    vertices[lid] = -1;
+
+    if (gid < vertex_count) {
+        if (forward[gid] != FLT_MAX && backward[gid] != FLT_MAX) {
+            sums[lid] = forward[gid] + backward[gid];
+            vertices[lid] = gid;
+        }
+
+        if (forward_frontier[gid]) {
+            forward_mins[lid] = forward[gid];
+        }
+
+        if (backward_frontier[gid]) {
+            backward_mins[lid] = backward[gid];
+        }
+    }
+
+    barrier(CLK_LOCAL_MEM_FENCE);
+
+    // Halve the candidates of the work group until the first thread holds the minimums
+    for (int stride = MEETING_SIZE / 2; stride > 0; stride /= 2) {
+        if (lid < stride) {
+            if (sums[lid + stride] < sums[lid]) {
+                sums[lid] = sums[lid + stride];
+                vertices[lid] = vertices[lid + stride];
+            }
+
+            forward_mins[lid] = min(forward_mins[lid], forward_mins[lid + stride]);
+            backward_mins[lid] = min(backward_mins[lid], backward_mins[lid + stride]);
+        }
+
+        barrier(CLK_LOCAL_MEM_FENCE);
+    }
+
+    // Leave the minimums of the work group for the host to reduce
+    if (lid == 0) {
+        int group = get_group_id(0);
+        bounds[group * 3] = sums[0];
+        bounds[group * 3 + 1] = forward_mins[0];
+        bounds[group * 3 + 2] = backward_mins[0];
+        meetings[group] = vertices[0];
+    }
+}
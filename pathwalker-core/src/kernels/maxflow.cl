@@ -0,0 +1,62 @@
+__kernel void push_excess(__global float *residual, __global float *excess, __global int *height, __global float *pushed, __global float *remaining, int vertex_count, int source, int sink) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+    float left = excess[gid];
+    int active = gid != source && gid != sink && left > 0;
+
+    // Push the excess through the admissible edges, one level downhill
+    for (int target = 0; target < vertex_count; target++) {
+        float capacity = residual[gid * vertex_count + target];
+        float delta = 0;
+
+        if (active && left > 0 && capacity > 0 && height[gid] == height[target] + 1) {
+            delta = min(left, capacity);
+            left = (delta == left) ? 0 : left - delta;
+        }
+
+        pushed[gid * vertex_count + target] = delta;
+    }
+
+    // Keep the excess that couldn't be pushed
+    remaining[gid] = left;
+}
+
+__kernel void apply_pushes(__global float *residual, __global float *excess, __global float *pushed, __global float *remaining, int vertex_count) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+    float received = 0;
+
+    // Update the residual edges leaving the vertex with the pushes of both ways
+    for (int other = 0; other < vertex_count; other++) {
+        float sent = pushed[gid * vertex_count + other];
+        float back = pushed[other * vertex_count + gid];
+        float capacity = residual[gid * vertex_count + other];
+
+        residual[gid * vertex_count + other] = (sent == capacity) ? back : capacity - sent + back;
+        received += back;
+    }
+
+    excess[gid] = remaining[gid] + received;
+}
+
+__kernel void relabel(__global float *residual, __global float *excess, __global int *height, __global int *next_height, int vertex_count, int source, int sink) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+    int lowest = INT_MAX;
+    int admissible = 0;
+
+    // Find the lowest vertex reached through a residual edge
+    for (int target = 0; target < vertex_count; target++) {
+        if (residual[gid * vertex_count + target] > 0) {
+            lowest = min(lowest, height[target]);
+            admissible |= height[gid] == height[target] + 1;
+        }
+    }
+
+    // Lift the vertices with excess and nowhere to push it
+    if (gid != source && gid != sink && excess[gid] > 0 && !admissible && lowest != INT_MAX) {
+        next_height[gid] = lowest + 1;
+    } else {
+        next_height[gid] = height[gid];
+    }
+}
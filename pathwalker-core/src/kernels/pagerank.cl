@@ -0,0 +1,13 @@
+__kernel void pagerank_iteration(__global float *transitions, __global float *rank, __global float *next, int vertex_count, float damping, float teleport) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+
+    // Gather the rank flowing into the vertex, stored in its row
+    float flow = 0;
+    for (int source = 0; source < vertex_count; source++) {
+        flow += transitions[gid * vertex_count + source] * rank[source];
+    }
+
+    // Add the rank of the jumps to random vertices
+    next[gid] = teleport + damping * flow;
+}
@@ -0,0 +1,5 @@
+__kernel void sentinel(__global int *values) {
+    // Write a known value for every thread
+    int gid = get_global_id(0);
+    values[gid] = gid * 2;
+}
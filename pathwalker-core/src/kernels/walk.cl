@@ -0,0 +1,168 @@
+__kernel void initialize_algorithm_buffers(__global real *result, __global real *distance, __global int *visited, __global real *vertex, __global real *vertex_temp, int source, int zero_edges) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+
+    // Initialize the buffers in parallel
+    if (gid == source) {
+        visited[gid] = 1;
+        result[gid] = 0;
+    } else {
+        visited[gid] = 0;
+        result[gid] = REAL_MAX;
+    }
+
+    // Without zero weight edges, a zero distance means it's not reached yet
+    distance[gid] = (zero_edges && gid != source) ? REAL_MAX : 0;
+    vertex[gid] = source;
+    vertex_temp[gid] = source;
+}
+
+__kernel void shortest_path_algorithm(__global real *result, __global real *matrix, __global real *distance, __global int *visited, __global real *vertex_temp, int vertex_count, int zero_edges) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+
+    // Validate if the vertex is not visited
+    if (visited[gid] != 1) {
+        // Mark the vertex as visited
+        visited[gid] = 1;
+
+        // Get the start edge
+        for(int edge = 0; edge < vertex_count; edge++) {
+            // Get the edge from adjacent matrix
+            real weight = matrix[gid * vertex_count + edge];
+
+            // Validate if the edge is valid
+            if ((weight != 0 || zero_edges) && weight != REAL_MAX && weight != FLT_MAX) {
+                // Get the distance
+                real dist = REAL_ADD(result[edge], weight);
+
+                // Keep the closest candidate, so every iteration extends the paths by one edge
+                if ((!zero_edges && distance[gid] == 0) || distance[gid] > dist) {
+                    distance[gid] = dist;
+                    vertex_temp[gid] = edge;
+                }
+            }
+        }
+    }
+}
+
+#ifdef VARIANT_TILED
+__kernel void shortest_path_tiled(__global real *result, __global real *matrix, __global real *distance, __global int *visited, __global real *vertex_temp, int vertex_count, int zero_edges) {
+    // Get the global id, as the last work group can have more threads than vertices left
+    int gid = get_global_id(0);
+    int lid = get_local_id(0);
+    __local real tile[TILE_SIZE];
+
+    // Only the vertices not visited are walked, but every thread loads its part of the tiles
+    int walking = gid < vertex_count && visited[gid] != 1;
+
+    if (walking) {
+        visited[gid] = 1;
+    }
+
+    for (int start = 0; start < vertex_count; start += TILE_SIZE) {
+        // Load the distances of the tile into the local memory
+        if (start + lid < vertex_count) {
+            tile[lid] = result[start + lid];
+        }
+
+        barrier(CLK_LOCAL_MEM_FENCE);
+
+        if (walking) {
+            int end = min(TILE_SIZE, vertex_count - start);
+
+            for (int offset = 0; offset < end; offset++) {
+                // Get the edge from adjacent matrix
+                int edge = start + offset;
+                real weight = matrix[gid * vertex_count + edge];
+
+                // Validate if the edge is valid
+                if ((weight != 0 || zero_edges) && weight != REAL_MAX && weight != FLT_MAX) {
+                    // Get the distance
+                    real dist = REAL_ADD(tile[offset], weight);
+
+                    // Keep the closest candidate, so every iteration extends the paths by one edge
+                    if ((!zero_edges && distance[gid] == 0) || distance[gid] > dist) {
+                        distance[gid] = dist;
+                        vertex_temp[gid] = edge;
+                    }
+                }
+            }
+        }
+
+        // Wait for every thread before loading the next tile over this one
+        barrier(CLK_LOCAL_MEM_FENCE);
+    }
+}
+#endif
+
+__kernel void merge_sortest_path(__global real *result, __global real *distance, __global int *visited, __global real *vertex, __global real *vertex_temp, int source) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+
+    // Get the result
+    if (result[gid] > distance[gid]) {
+        result[gid] = distance[gid];
+        vertex[gid] = vertex_temp[gid];
+    }
+
+    // Reset the visited flag
+    if (gid != source) {
+        visited[gid] = 0;
+    }
+}
+
+__kernel void order_predecessors(__global real *distance, __global real *matrix, __global real *vertex, int vertex_count, int source, int zero_edges) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+    real reached = distance[gid];
+
+    // Only the vertices reached keep a predecessor
+    if (gid == source || ((reached == 0 && !zero_edges) || reached == REAL_MAX || reached == FLT_MAX)) {
+        return;
+    }
+
+    // Take the lowest vertex closer to the source reaching this one at its distance, as the distances are only read
+    for (int edge = 0; edge < vertex_count; edge++) {
+        real weight = matrix[gid * vertex_count + edge];
+        real closer = distance[edge];
+
+        // Validate if the edge and its vertex are valid
+        if (edge == gid || ((weight == 0 && !zero_edges) || weight == REAL_MAX || weight == FLT_MAX)) {
+            continue;
+        }
+
+        if (edge != source && ((closer == 0 && !zero_edges) || closer == REAL_MAX || closer == FLT_MAX)) {
+            continue;
+        }
+
+        if (closer < reached && REAL_ADD(closer, weight) == reached) {
+            vertex[gid] = edge;
+            return;
+        }
+    }
+}
+
+#ifdef VARIANT_ATOMICS
+#ifndef CL_VERSION_1_1
+#pragma OPENCL EXTENSION cl_khr_global_int32_base_atomics : enable
+#define atomic_xchg atom_xchg
+#endif
+
+__kernel void merge_sortest_path_flagged(__global real *result, __global real *distance, __global int *visited, __global real *vertex, __global real *vertex_temp, int source, __global int *improved) {
+    // Get the global id based on count of vertexs and assigned for thread
+    int gid = get_global_id(0);
+
+    // Get the result, flagging the iteration as one that improves a distance
+    if (result[gid] > distance[gid]) {
+        result[gid] = distance[gid];
+        vertex[gid] = vertex_temp[gid];
+        atomic_xchg(improved, 1);
+    }
+
+    // Reset the visited flag
+    if (gid != source) {
+        visited[gid] = 0;
+    }
+}
+#endif
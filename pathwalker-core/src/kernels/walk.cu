@@ -0,0 +1,80 @@
+// The walk kernels of walk.cl ported to CUDA, for the cuda backend
+
+#define REAL_MAX 3.402823466e+38f
+
+__device__ int global_id() {
+    return blockIdx.x * blockDim.x + threadIdx.x;
+}
+
+extern "C" __global__ void initialize_algorithm_buffers(float *result, float *distance, int *visited, int *vertex, int *vertex_temp, int source, int zero_edges, int vertex_count) {
+    // Get the global id, as the last block can have more threads than vertices
+    int gid = global_id();
+
+    if (gid >= vertex_count) {
+        return;
+    }
+
+    // Initialize the buffers in parallel
+    if (gid == source) {
+        visited[gid] = 1;
+        result[gid] = 0;
+    } else {
+        visited[gid] = 0;
+        result[gid] = REAL_MAX;
+    }
+
+    // Without zero weight edges, a zero distance means it's not reached yet
+    distance[gid] = (zero_edges && gid != source) ? REAL_MAX : 0;
+    vertex[gid] = source;
+    vertex_temp[gid] = source;
+}
+
+extern "C" __global__ void shortest_path_algorithm(float *result, const float *matrix, float *distance, int *visited, int *vertex_temp, int vertex_count, int zero_edges) {
+    // Get the global id, as the last block can have more threads than vertices
+    int gid = global_id();
+
+    // Validate if the vertex is not visited
+    if (gid >= vertex_count || visited[gid] == 1) {
+        return;
+    }
+
+    // Mark the vertex as visited
+    visited[gid] = 1;
+
+    for (int edge = 0; edge < vertex_count; edge++) {
+        // Get the edge from adjacent matrix
+        float weight = matrix[(size_t) gid * vertex_count + edge];
+
+        // Validate if the edge is valid
+        if ((weight != 0 || zero_edges) && weight != REAL_MAX) {
+            // Get the distance
+            float dist = result[edge] + weight;
+
+            // Keep the closest candidate, so every iteration extends the paths by one edge
+            if ((!zero_edges && distance[gid] == 0) || distance[gid] > dist) {
+                distance[gid] = dist;
+                vertex_temp[gid] = edge;
+            }
+        }
+    }
+}
+
+extern "C" __global__ void merge_sortest_path(float *result, float *distance, int *visited, int *vertex, int *vertex_temp, int source, int vertex_count) {
+    // Get the global id, as the last block can have more threads than vertices
+    int gid = global_id();
+
+    if (gid >= vertex_count) {
+        return;
+    }
+
+    // Get the result
+    if (result[gid] > distance[gid]) {
+        result[gid] = distance[gid];
+        vertex[gid] = vertex_temp[gid];
+    }
+
+    // Reset the visited flag
+    if (gid != source) {
+        visited[gid] = 0;
+    }
+}
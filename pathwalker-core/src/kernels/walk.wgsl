@@ -0,0 +1,100 @@
+// The walk kernels of walk.cl ported to WGSL, for the wgpu backend
+
+const REAL_MAX: f32 = 3.40282347e+38;
+
+struct Params {
+    source: u32,
+    zero_edges: u32,
+    vertex_count: u32,
+    padding: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> matrix: array<f32>;
+@group(0) @binding(2) var<storage, read_write> result: array<f32>;
+@group(0) @binding(3) var<storage, read_write> distance: array<f32>;
+@group(0) @binding(4) var<storage, read_write> visited: array<u32>;
+@group(0) @binding(5) var<storage, read_write> vertex: array<i32>;
+@group(0) @binding(6) var<storage, read_write> vertex_temp: array<i32>;
+
+@compute @workgroup_size(64)
+fn initialize_algorithm_buffers(@builtin(global_invocation_id) id: vec3<u32>) {
+    // Get the global id, as the last work group can have more threads than vertices
+    let gid = id.x;
+
+    if (gid >= params.vertex_count) {
+        return;
+    }
+
+    // Initialize the buffers in parallel
+    if (gid == params.source) {
+        visited[gid] = 1u;
+        result[gid] = 0.0;
+    } else {
+        visited[gid] = 0u;
+        result[gid] = REAL_MAX;
+    }
+
+    // Without zero weight edges, a zero distance means it's not reached yet
+    if (params.zero_edges != 0u && gid != params.source) {
+        distance[gid] = REAL_MAX;
+    } else {
+        distance[gid] = 0.0;
+    }
+
+    vertex[gid] = i32(params.source);
+    vertex_temp[gid] = i32(params.source);
+}
+
+@compute @workgroup_size(64)
+fn shortest_path_algorithm(@builtin(global_invocation_id) id: vec3<u32>) {
+    // Get the global id, as the last work group can have more threads than vertices
+    let gid = id.x;
+    let zero_edges = params.zero_edges != 0u;
+
+    // Validate if the vertex is not visited
+    if (gid >= params.vertex_count || visited[gid] == 1u) {
+        return;
+    }
+
+    // Mark the vertex as visited
+    visited[gid] = 1u;
+
+    for (var edge = 0u; edge < params.vertex_count; edge++) {
+        // Get the edge from adjacent matrix
+        let weight = matrix[gid * params.vertex_count + edge];
+
+        // Validate if the edge is valid
+        if ((weight != 0.0 || zero_edges) && weight != REAL_MAX) {
+            // Get the distance
+            let dist = result[edge] + weight;
+
+            // Keep the closest candidate, so every iteration extends the paths by one edge
+            if ((!zero_edges && distance[gid] == 0.0) || distance[gid] > dist) {
+                distance[gid] = dist;
+                vertex_temp[gid] = i32(edge);
+            }
+        }
+    }
+}
+
+@compute @workgroup_size(64)
+fn merge_sortest_path(@builtin(global_invocation_id) id: vec3<u32>) {
+    // Get the global id, as the last work group can have more threads than vertices
+    let gid = id.x;
+
+    if (gid >= params.vertex_count) {
+        return;
+    }
+
+    // Get the result
+    if (result[gid] > distance[gid]) {
+        result[gid] = distance[gid];
+        vertex[gid] = vertex_temp[gid];
+    }
+
+    // Reset the visited flag
+    if (gid != params.source) {
+        visited[gid] = 0u;
+    }
+}
@@ -0,0 +1,24 @@
+//! Shortest path walks over dense weight matrices in OpenCL devices
+//!
+//! The algorithms of the PathWalker server, usable without running it:
+//!
+//! ```no_run
+//! use pathwalker_core::{Graph, ShortestPath, SolveOptions};
+//!
+//! let solver = ShortestPath::new().expect("No OpenCL device is available");
+//!
+//! let mut graph = Graph::new(3, 3, vec![0.0; 9]);
+//! graph.set_edge(0, 1, 2.0);
+//! graph.set_edge(1, 2, 3.0);
+//!
+//! let path = solver.solve(&graph, SolveOptions::default()).unwrap();
+//! assert_eq!(path[2].1, 5.0);
+//! ```
+pub mod models;
+pub mod services;
+pub mod solver;
+pub mod utils;
+
+pub use models::{PathResult, Precision, Real};
+pub use services::cancellation::Cancellation;
+pub use solver::{validate_matrix, Graph, ShortestPath, SolveOptions};
@@ -0,0 +1,1327 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use serde::{de::{DeserializeOwned, Error as _}, Deserialize, Deserializer, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use crate::utils::{round_decimals, round_decimals_f64};
+
+/// The precision of the numbers the walks are computed with
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// Single precision, supported by every device
+    #[default]
+    Single,
+    /// Double precision, for the devices with the `cl_khr_fp64` extension
+    Double,
+    /// Unsigned 32 bit integers, summed exactly
+    Integer,
+}
+
+impl Precision {
+    /// Parse the name of a precision
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name, `single`, `double` or `integer`
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Precision>` - The precision, if the name is known
+    ///
+    pub fn parse(name: &str) -> Option<Precision> {
+        match name.to_ascii_lowercase().as_str() {
+            "single" => Some(Precision::Single),
+            "double" => Some(Precision::Double),
+            "integer" => Some(Precision::Integer),
+            _ => None
+        }
+    }
+
+    /// Get the name of the precision, as parsed
+    pub fn name(&self) -> &'static str {
+        match self {
+            Precision::Single => "single",
+            Precision::Double => "double",
+            Precision::Integer => "integer"
+        }
+    }
+}
+
+/// The types the weights of a matrix can have
+///
+/// Besides the floating point types, the weights can be unsigned integers,
+/// whose distances are exact, saturating at `u32::MAX` when unreachable.
+pub trait Real: Copy + Default + PartialEq + PartialOrd + Into<f64> + Debug + Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// The precision of the type
+    const PRECISION: Precision;
+
+    /// The largest value of the type, which the kernels read as a missing edge
+    const MAX: Self;
+
+    /// Get the exact bits of the value, in little endian
+    fn to_le_bytes_vec(self) -> Vec<u8>;
+
+    /// Round the value to a number of decimals, or keep it as is when not given
+    fn round_decimals(self, decimals: Option<u32>) -> Self;
+
+    /// Convert a double precision number to the type, saturating when it doesn't fit
+    fn from_f64(value: f64) -> Self;
+
+    /// Add the weight of an edge to a distance, the same way the kernels do
+    fn add_weight(self, weight: Self) -> Self;
+}
+
+impl Real for f32 {
+    const PRECISION: Precision = Precision::Single;
+    const MAX: f32 = f32::MAX;
+
+    fn to_le_bytes_vec(self) -> Vec<u8> {
+        self.to_bits().to_le_bytes().to_vec()
+    }
+
+    fn round_decimals(self, decimals: Option<u32>) -> f32 {
+        round_decimals(self, decimals)
+    }
+
+    fn from_f64(value: f64) -> f32 {
+        value as f32
+    }
+
+    fn add_weight(self, weight: f32) -> f32 {
+        self + weight
+    }
+}
+
+impl Real for f64 {
+    const PRECISION: Precision = Precision::Double;
+    const MAX: f64 = f64::MAX;
+
+    fn to_le_bytes_vec(self) -> Vec<u8> {
+        self.to_bits().to_le_bytes().to_vec()
+    }
+
+    fn round_decimals(self, decimals: Option<u32>) -> f64 {
+        round_decimals_f64(self, decimals)
+    }
+
+    fn from_f64(value: f64) -> f64 {
+        value
+    }
+
+    fn add_weight(self, weight: f64) -> f64 {
+        self + weight
+    }
+}
+
+impl Real for u32 {
+    const PRECISION: Precision = Precision::Integer;
+    const MAX: u32 = u32::MAX;
+
+    fn to_le_bytes_vec(self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn round_decimals(self, _decimals: Option<u32>) -> u32 {
+        self
+    }
+
+    fn from_f64(value: f64) -> u32 {
+        value.round() as u32
+    }
+
+    fn add_weight(self, weight: u32) -> u32 {
+        self.saturating_add(weight)
+    }
+}
+
+/// Read the weights of a matrix, where the missing edges can be `null` or `"inf"`
+///
+/// The explicit missing edges are only accepted by the formats read by
+/// humans, and become the maximum of the type, as the kernels read it. The
+/// binary formats keep the plain weights.
+fn deserialize_weights<'de, T: Real, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<T>, D::Error> {
+    /// A weight, or an explicit missing edge
+    #[derive(Deserialize)]
+    #[serde(untagged, bound = "T: Real")]
+    enum Weight<T: Real> {
+        Value(T),
+        Missing(Option<String>),
+    }
+
+    if !deserializer.is_human_readable() {
+        return Vec::<T>::deserialize(deserializer);
+    }
+
+    Vec::<Weight<T>>::deserialize(deserializer)?.into_iter().map(|weight| match weight {
+        Weight::Value(value) => Ok(value),
+        Weight::Missing(None) => Ok(T::MAX),
+        Weight::Missing(Some(name)) if matches!(name.to_ascii_lowercase().as_str(), "inf" | "infinity") => Ok(T::MAX),
+        Weight::Missing(Some(name)) => Err(D::Error::custom(format!("The weight {} is not a number, null or \"inf\"", name)))
+    }).collect()
+}
+
+/// Read the weight layers of a matrix, where the missing edges can be `null` or `"inf"` as in the weights
+fn deserialize_layers<'de, T: Real, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<Vec<T>>, D::Error> {
+    /// The weights of a layer
+    #[derive(Deserialize)]
+    #[serde(bound = "T: Real")]
+    struct Layer<T: Real>(#[serde(deserialize_with = "deserialize_weights")] Vec<T>);
+
+    Ok(Vec::<Layer<T>>::deserialize(deserializer)?.into_iter().map(|Layer(weights)| weights).collect())
+}
+
+/// A square matrix of weights, where the row of a vertex holds the weights of the edges reaching it
+///
+/// The weights are single precision unless the matrix is walked in double precision or with integers.
+/// A weight of `0` means there is no edge, unless `zero_edges` is set, which makes it a free edge and
+/// leaves the maximum of the type, written as `null` or `"inf"` in JSON, as the only missing edge.
+/// The matrix is directed unless `directed` is unset, which makes every edge go both ways, so only
+/// one of its cells has to be filled. The `unit` of the weights, when declared, lets the costs of the
+/// responses be converted and labelled. The walks never go through the `forbidden_nodes` nor the
+/// `forbidden_edges`, given as `[source, target]` pairs, and only take paths of up to `max_hops` edges.
+/// Instead of the `data`, the weights can be given as `data_layers`, like the distance, the time and
+/// the toll of every edge, which are combined with their `coefficients` into the weights walked as
+/// soon as the matrix is read. The weights of the edges can change along the day too, given as the
+/// travel times in seconds of every `bucket_seconds`, hourly by default, in the `time_buckets`, like
+/// 24 hourly matrices, and the walks read the bucket of the time each edge is reached at, starting at
+/// the `departure_time`, in seconds since the start of the first bucket. The walks of a `deterministic`
+/// matrix give every vertex the lowest predecessor of its shortest paths, so the equally short paths
+/// are always broken the same way, whatever the backend or the kernels walking it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[serde(remote = "Self", bound = "T: Real")]
+pub struct Matrix<T: Real = f32> {
+    pub width: usize,
+    pub height: usize,
+    #[serde(default, deserialize_with = "deserialize_weights")]
+    pub data: Vec<T>,
+    #[serde(default)]
+    pub round: Option<u32>,
+    #[serde(default)]
+    pub zero_edges: bool,
+    #[serde(default = "default_directed")]
+    pub directed: bool,
+    #[serde(default)]
+    pub unit: Option<WeightUnit>,
+    #[serde(default)]
+    pub forbidden_nodes: Vec<usize>,
+    #[serde(default)]
+    pub forbidden_edges: Vec<[usize; 2]>,
+    #[serde(default)]
+    pub max_hops: Option<usize>,
+    #[serde(default, deserialize_with = "deserialize_layers")]
+    pub data_layers: Vec<Vec<T>>,
+    #[serde(default)]
+    pub coefficients: Vec<f64>,
+    #[serde(default, deserialize_with = "deserialize_layers")]
+    pub time_buckets: Vec<Vec<T>>,
+    #[serde(default)]
+    pub bucket_seconds: Option<f64>,
+    #[serde(default)]
+    pub departure_time: Option<f64>,
+    #[serde(default)]
+    pub deterministic: bool
+}
+
+/// The seconds of every time bucket by default, so 24 buckets cover a day
+pub const DEFAULT_BUCKET_SECONDS: f64 = 3600.0;
+
+impl<T: Real> Serialize for Matrix<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        Matrix::serialize(self, serializer)
+    }
+}
+
+impl<'de, T: Real> Deserialize<'de> for Matrix<T> {
+    /// Read a matrix, combining its weight layers into its weights, or taking the ones of its departure
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let mut matrix = Matrix::deserialize(deserializer)?;
+        matrix.mix_layers().map_err(D::Error::custom)?;
+        matrix.fill_departure_weights().map_err(D::Error::custom)?;
+
+        Ok(matrix)
+    }
+}
+
+/// What the weights of a matrix measure
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub enum WeightUnit {
+    /// Distances in meters
+    #[serde(rename = "m")]
+    Meters,
+    /// Times in seconds
+    #[serde(rename = "s")]
+    Seconds,
+    /// Any other unit, which can't be converted
+    #[serde(rename = "custom")]
+    Custom
+}
+
+/// The matrices are directed by default, as the kernels read them
+fn default_directed() -> bool {
+    true
+}
+
+impl<T: Real> Matrix<T> {
+    pub fn new(width: usize, height: usize, data: Vec<T>) -> Matrix<T> {
+        Matrix { width, height, data, round: None, zero_edges: false, directed: true, unit: None, forbidden_nodes: Vec::new(), forbidden_edges: Vec::new(), max_hops: None, data_layers: Vec::new(), coefficients: Vec::new(), time_buckets: Vec::new(), bucket_seconds: None, departure_time: None, deterministic: false }
+    }
+
+    /// Check if a weight is a missing edge, the same way the kernels read it
+    fn is_missing(&self, weight: T) -> bool {
+        weight == T::MAX || Into::<f64>::into(weight) == f32::MAX as f64 || (weight == T::default() && !self.zero_edges)
+    }
+
+    /// Combine the weight layers into the weights of the matrix
+    ///
+    /// The weight of every cell is the sum of its weights in the layers, each
+    /// one multiplied by the coefficient of its layer. The first layer tells
+    /// which edges exist, the same way the weights do, while the rest only
+    /// add their costs, so a `0` in them is a free criterion, like a road
+    /// without toll, and only the maximum of the type makes the edge missing
+    /// in them. The layers are dropped
+    /// once combined, so the matrix is walked, cached and identified by the
+    /// weights it's walked with. The matrices without layers are kept as
+    /// they are.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Nothing, or the reason why the layers can't be combined
+    ///
+    pub fn mix_layers(&mut self) -> std::result::Result<(), String> {
+        if self.data_layers.is_empty() {
+            return match self.coefficients.is_empty() {
+                true => Ok(()),
+                false => Err("The coefficients need data layers to be combined".to_string())
+            };
+        }
+
+        let cells = self.width.saturating_mul(self.height);
+
+        // Check the layers and their coefficients before combining them
+        if !self.data.is_empty() {
+            return Err("The matrix can't have both data and data layers".to_string());
+        } else if self.coefficients.len() != self.data_layers.len() {
+            return Err(format!("The matrix has {} data layers but {} coefficients", self.data_layers.len(), self.coefficients.len()));
+        } else if self.coefficients.iter().any(|coefficient| !coefficient.is_finite()) {
+            return Err("The coefficients must be finite numbers".to_string());
+        } else if let Some((index, layer)) = self.data_layers.iter().enumerate().find(|(_, layer)| layer.len() != cells) {
+            return Err(format!("The data layer {} has {} weights instead of {}", index, layer.len(), cells));
+        }
+
+        // Sum the weighted layers of every cell, unless the edge is missing in the first one or marked missing in any other
+        let layers = std::mem::take(&mut self.data_layers);
+        let marked_missing = |weight: T| weight == T::MAX || Into::<f64>::into(weight) == f32::MAX as f64;
+        self.data = (0..cells).map(|cell| {
+            match self.is_missing(layers[0][cell]) || layers[1..].iter().any(|layer| marked_missing(layer[cell])) {
+                true => T::MAX,
+                false => T::from_f64(layers.iter().zip(&self.coefficients).map(|(layer, coefficient)| Into::<f64>::into(layer[cell]) * coefficient).sum())
+            }
+        }).collect();
+        self.coefficients.clear();
+
+        Ok(())
+    }
+
+    /// Check if the weights of the matrix change with the time the edges are reached at
+    pub fn is_time_dependent(&self) -> bool {
+        !self.time_buckets.is_empty()
+    }
+
+    /// Get the time bucket of an instant
+    ///
+    /// The buckets repeat once the last one ends, so the hourly buckets of a
+    /// day are read again the next day.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The seconds since the start of the first bucket
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The index of the bucket, always `0` when the matrix isn't time dependent
+    ///
+    pub fn bucket_at(&self, time: f64) -> usize {
+        let buckets = self.time_buckets.len().max(1);
+        let seconds = self.bucket_seconds.unwrap_or(DEFAULT_BUCKET_SECONDS);
+
+        ((time / seconds).floor().rem_euclid(buckets as f64) as usize).min(buckets - 1)
+    }
+
+    /// Get the weights of the time bucket of an instant
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The seconds since the start of the first bucket
+    ///
+    /// # Returns
+    ///
+    /// * `&[T]` - The weights of the bucket, or the ones of the matrix when it isn't time dependent
+    ///
+    pub fn weights_at(&self, time: f64) -> &[T] {
+        self.time_buckets.get(self.bucket_at(time)).unwrap_or(&self.data)
+    }
+
+    /// Take the weights of the time bucket of the departure as the weights of the matrix
+    ///
+    /// The walks read every bucket, but the checks of the cells, the
+    /// reachability and the responses read the weights of the matrix, which
+    /// are the ones the first edges of the walk are reached with. The
+    /// matrices without time buckets are kept as they are.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Nothing, or the reason why the time buckets can't be walked
+    ///
+    pub fn fill_departure_weights(&mut self) -> std::result::Result<(), String> {
+        if self.time_buckets.is_empty() {
+            return Ok(());
+        }
+
+        let cells = self.width.saturating_mul(self.height);
+
+        // Check the buckets and the times before reading them
+        if !self.data.is_empty() {
+            return Err("The matrix can't have both data and time buckets".to_string());
+        } else if self.bucket_seconds.is_some_and(|seconds| !seconds.is_finite() || seconds <= 0.0) {
+            return Err("The seconds of the time buckets must be greater than zero".to_string());
+        } else if self.departure_time.is_some_and(|time| !time.is_finite()) {
+            return Err("The departure time must be a finite number".to_string());
+        } else if let Some((index, bucket)) = self.time_buckets.iter().enumerate().find(|(_, bucket)| bucket.len() != cells) {
+            return Err(format!("The time bucket {} has {} weights instead of {}", index, bucket.len(), cells));
+        }
+
+        self.data = self.weights_at(self.departure_time.unwrap_or_default()).to_vec();
+
+        Ok(())
+    }
+
+    /// Check if the walks of the matrix avoid some vertex or edge
+    pub fn has_forbidden(&self) -> bool {
+        !self.forbidden_nodes.is_empty() || !self.forbidden_edges.is_empty()
+    }
+
+    /// Check if the walks of the matrix are constrained, avoiding some vertex or edge or bounding their hops
+    pub fn has_constraints(&self) -> bool {
+        self.has_forbidden() || self.max_hops.is_some()
+    }
+
+    /// Check if the edge going from `source` to `target` can't be walked, by itself or by its vertices
+    fn is_forbidden(&self, source: usize, target: usize) -> bool {
+        self.forbidden_nodes.iter().any(|vertex| *vertex == source || *vertex == target)
+            || self.forbidden_edges.iter().any(|edge| *edge == [source, target] || (!self.directed && *edge == [target, source]))
+    }
+
+    /// Get the weight of the edge going from `source` to `target`, if there is one
+    ///
+    /// Reads the cells the same way the kernels do, so the maximum of the type
+    /// and the single precision maximum are missing edges, and so is `0`
+    /// unless the matrix has zero weight edges. The edges of an undirected
+    /// matrix can be in either of their cells, and when both are filled the
+    /// cheapest one is taken. The forbidden edges, and the ones of the
+    /// forbidden vertices, are missing too.
+    pub fn edge(&self, source: usize, target: usize) -> Option<T> {
+        self.edge_in(&self.data, source, target)
+    }
+
+    /// Get the weight of the edge going from `source` to `target` in some weights of the matrix, like a time bucket
+    fn edge_in(&self, data: &[T], source: usize, target: usize) -> Option<T> {
+        if self.has_forbidden() && self.is_forbidden(source, target) {
+            return None;
+        }
+
+        let cell = |source: usize, target: usize| {
+            let weight = data[target * self.width + source];
+            (!self.is_missing(weight)).then_some(weight)
+        };
+
+        match (cell(source, target), self.directed) {
+            (forward, true) => forward,
+            (Some(forward), false) => Some(cell(target, source).filter(|backward| *backward < forward).unwrap_or(forward)),
+            (None, false) => cell(target, source)
+        }
+    }
+
+    /// Get the weights as the kernels read them, where every edge has its own cell
+    ///
+    /// The weights of a directed matrix are kept as they are, while both
+    /// cells of every edge of an undirected matrix are filled with its weight.
+    /// The cells of the forbidden edges, and of every edge of the forbidden
+    /// vertices, are masked with the maximum of the type, so the kernels
+    /// never relax them.
+    pub fn directed_data(&self) -> Cow<'_, [T]> {
+        self.directed_weights(&self.data)
+    }
+
+    /// Get some weights of the matrix, like a time bucket, as the kernels read them
+    ///
+    /// Same as `directed_data`, for the weights given instead of the ones of the matrix.
+    pub fn directed_weights<'a>(&self, weights: &'a [T]) -> Cow<'a, [T]> {
+        if self.directed && !self.has_forbidden() {
+            return Cow::Borrowed(weights);
+        }
+
+        let mut data = weights.to_vec();
+        let width = self.width;
+
+        if !self.directed {
+            for target in 0..self.height.min(width) {
+                for source in 0..width {
+                    if let Some(weight) = self.edge_in(weights, source, target) {
+                        data[target * width + source] = weight;
+                    }
+                }
+            }
+        }
+
+        // Mask the row and the column of every forbidden vertex, and the cells of every forbidden edge
+        for vertex in self.forbidden_nodes.iter().copied().filter(|vertex| *vertex < width) {
+            for other in 0..width {
+                data[vertex * width + other] = T::MAX;
+                data[other * width + vertex] = T::MAX;
+            }
+        }
+
+        for [source, target] in self.forbidden_edges.iter().copied().filter(|edge| edge.iter().all(|vertex| *vertex < width)) {
+            data[target * width + source] = T::MAX;
+
+            if !self.directed {
+                data[source * width + target] = T::MAX;
+            }
+        }
+
+        Cow::Owned(data)
+    }
+
+    /// Get the iterations of a walk of the matrix, bounded by the hops of its paths
+    ///
+    /// Every iteration of the kernels relaxes the edges of the distances of
+    /// the previous one, so after `n` iterations the distances are the ones
+    /// of the paths of up to `n` edges.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The iterations, never more than the vertices of the matrix
+    ///
+    pub fn walk_rounds(&self) -> usize {
+        self.max_hops.map_or(self.width, |hops| hops.min(self.width))
+    }
+
+    /// Give every vertex of a walk the lowest of its shortest predecessors
+    ///
+    /// The backends keep the first predecessor they find at the shortest
+    /// distance, which depends on the order they relax the edges in, so the
+    /// equally short paths can be broken differently. The predecessor of every
+    /// reached vertex is replaced by the lowest vertex closer to the source
+    /// whose edge reaches it at its distance, the same way the
+    /// `order_predecessors` kernel does. The vertices only reached at their
+    /// distance through edges that don't make the path longer keep the
+    /// predecessor of the walk, as taking the lowest one could close a cycle.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The predecessor and the distance of every vertex, as walked
+    /// * `source` - The vertex the walk starts from
+    ///
+    pub fn order_predecessors(&self, path: &mut [PathResult<T>], source: usize) {
+        let data = self.directed_data();
+        let distances: Vec<Option<T>> = path.iter().enumerate()
+            .map(|(vertex, PathResult(_, distance))| (vertex == source || !self.is_missing(*distance)).then_some(*distance))
+            .collect();
+
+        for (vertex, result) in path.iter_mut().enumerate().filter(|(vertex, _)| *vertex != source) {
+            let Some(distance) = distances[vertex] else {
+                continue;
+            };
+
+            // Take the first vertex, in ascending order, closer to the source and reaching the vertex at its distance
+            let lowest = (0..self.width).find(|other| {
+                let weight = data[vertex * self.width + other];
+
+                *other != vertex && !self.is_missing(weight) && distances[*other]
+                    .is_some_and(|closer| closer < distance && closer.add_weight(weight) == distance)
+            });
+
+            if let Some(lowest) = lowest {
+                result.0 = lowest as i32;
+            }
+        }
+    }
+
+    /// Set the weight of the edge going from `source` to `target`
+    ///
+    /// The kernels read the row of a vertex to know how to reach it, so the
+    /// edge is stored in the row of the target and the column of the source,
+    /// and in the opposite cell too when the matrix is undirected.
+    pub fn set_edge(&mut self, source: usize, target: usize, weight: T) {
+        self.data[target * self.width + source] = weight;
+
+        if !self.directed {
+            self.data[source * self.width + target] = weight;
+        }
+    }
+
+    /// Check the weights of every cell of the matrix
+    ///
+    /// The cells that are not a number or infinite are always errors, as the
+    /// sums of the walks would spread them to every distance, while the
+    /// negative weights are only errors when the rules reject them. The
+    /// asymmetric cells are warnings, for the graphs expected to be
+    /// undirected. The maximum of the type isn't infinite, as it means there
+    /// is no edge. At most `MAX_DIAGNOSTICS` errors and warnings are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - What is checked besides the finite weights
+    ///
+    /// # Returns
+    ///
+    /// * `MatrixValidation` - The errors and the warnings of the cells, by row and column
+    ///
+    pub fn validate(&self, rules: ValidationRules) -> MatrixValidation {
+        let mut validation = MatrixValidation::default();
+        let width = self.width.max(1);
+
+        for (index, weight) in self.data.iter().enumerate() {
+            let (row, column) = (index / width, index % width);
+            let value: f64 = (*weight).into();
+
+            // Check the weight of the cell
+            let error = if value.is_nan() {
+                Some("The weight is not a number")
+            } else if value.is_infinite() {
+                Some("The weight is infinite")
+            } else if rules.reject_negative && value < 0.0 {
+                Some("The weight is negative, which the algorithm can't handle")
+            } else {
+                None
+            };
+
+            if let Some(reason) = error {
+                validation.push_error(row, column, reason);
+                continue;
+            }
+
+            // Compare the weight with the one of the opposite edge, once per pair
+            let opposite = column * width + row;
+
+            if rules.warn_asymmetric && column > row && opposite < self.data.len() && self.data[opposite] != *weight {
+                validation.push_warning(row, column, "The weight differs from the one of the opposite edge");
+            }
+        }
+
+        validation
+    }
+}
+
+/// The most errors and warnings kept by the validation of a matrix
+pub const MAX_DIAGNOSTICS: usize = 100;
+
+/// What the validation of the cells of a matrix checks besides the finite weights
+///
+/// # Fields
+///
+/// * `reject_negative` - If the negative weights are errors, for the algorithms that can't handle them
+/// * `warn_asymmetric` - If the asymmetric cells are warnings, for the graphs expected to be undirected
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ValidationRules {
+    pub reject_negative: bool,
+    pub warn_asymmetric: bool
+}
+
+/// A problem found in a cell of a matrix
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CellDiagnostic {
+    pub row: usize,
+    pub column: usize,
+    pub reason: String
+}
+
+impl std::fmt::Display for CellDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at row {} and column {}", self.reason, self.row, self.column)
+    }
+}
+
+/// The problems found in the cells of a matrix
+///
+/// The matrix is valid when it has no errors, even with warnings.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct MatrixValidation {
+    pub errors: Vec<CellDiagnostic>,
+    pub warnings: Vec<CellDiagnostic>
+}
+
+impl MatrixValidation {
+    /// Check if the matrix has no errors
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Keep an error of a cell, unless there are already too many
+    fn push_error(&mut self, row: usize, column: usize, reason: &str) {
+        if self.errors.len() < MAX_DIAGNOSTICS {
+            self.errors.push(CellDiagnostic { row, column, reason: reason.to_string() });
+        }
+    }
+
+    /// Keep a warning of a cell, unless there are already too many
+    fn push_warning(&mut self, row: usize, column: usize, reason: &str) {
+        if self.warnings.len() < MAX_DIAGNOSTICS {
+            self.warnings.push(CellDiagnostic { row, column, reason: reason.to_string() });
+        }
+    }
+}
+
+/// The predecessor and the distance of a vertex in the walk
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[serde(bound = "T: Real")]
+pub struct PathResult<T: Real = f32>(pub i32, pub T);
+
+impl<T: Real> PathResult<T> {
+    pub fn round(&self, decimals: Option<u32>) -> PathResult<T> {
+        PathResult(self.0, self.1.round_decimals(decimals))
+    }
+}
+
+/// The response of a walk, with the path or the reason why it failed
+///
+/// The run id names the input, the parameters and the backend of the walk,
+/// so equal ids mean the paths have been computed the same way, and the code
+/// tells the failures apart without parsing their messages.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[serde(bound = "T: Real")]
+pub struct Result<T: Real = f32> {
+    pub path: Option<Vec<PathResult<T>>>,
+    pub status: String,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub run_id: Option<String>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ValidateRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    #[serde(default)]
+    pub source: usize
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExplainRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    pub path: Vec<PathResult>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BudgetRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    pub resources: Vec<f32>,
+    pub budget: f32,
+    #[serde(default)]
+    pub source: usize,
+    pub target: usize,
+    #[serde(default)]
+    pub postprocess: Vec<Transform>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BudgetPath {
+    pub route: Vec<usize>,
+    pub cost: f32,
+    pub resource: f32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChargingStation {
+    pub vertex: usize,
+    pub rate: f32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    pub consumption: Vec<f32>,
+    pub capacity: f32,
+    #[serde(default)]
+    pub initial_charge: Option<f32>,
+    #[serde(default)]
+    pub stations: Vec<ChargingStation>,
+    #[serde(default)]
+    pub source: usize,
+    pub target: usize
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChargingStop {
+    pub vertex: usize,
+    pub energy: f32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EvRoute {
+    pub route: Vec<usize>,
+    pub cost: f32,
+    pub stops: Vec<ChargingStop>,
+    pub remaining_charge: f32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, PartialOrd)]
+pub struct Candidate {
+    pub vertex: usize,
+    pub weight: f32,
+    pub cost: f32,
+    pub chosen: bool
+}
+
+impl Candidate {
+    pub fn round(&self, decimals: Option<u32>) -> Candidate {
+        Candidate {
+            weight: round_decimals(self.weight, decimals),
+            cost: round_decimals(self.cost, decimals),
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, PartialOrd)]
+pub struct HopExplanation {
+    pub vertex: usize,
+    pub predecessor: i32,
+    pub distance: f32,
+    pub candidates: Vec<Candidate>
+}
+
+impl HopExplanation {
+    pub fn round(&self, decimals: Option<u32>) -> HopExplanation {
+        HopExplanation {
+            distance: round_decimals(self.distance, decimals),
+            candidates: self.candidates.iter().map(|candidate| candidate.round(decimals)).collect(),
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct KernelLaunch {
+    pub name: String,
+    pub global_work_size: usize,
+    pub args: Vec<String>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplayBundle {
+    pub reason: String,
+    pub timestamp: i64,
+    pub platform: String,
+    pub device: String,
+    pub kernels: Vec<KernelLaunch>,
+    pub matrix: Matrix,
+    pub path: Option<Vec<PathResult>>,
+    pub error: Option<String>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CostRequest {
+    pub width: usize,
+    pub height: usize,
+    pub attributes: BTreeMap<String, Vec<f32>>,
+    pub cost: String,
+    #[serde(default)]
+    pub round: Option<u32>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportedGraph {
+    pub nodes: Vec<String>,
+    pub matrix: Matrix
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GraphInfo {
+    pub name: String,
+    pub vertices: usize,
+    pub device_bytes: usize,
+    pub created_at: i64
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GridTopology {
+    #[default]
+    Square,
+    Hex
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GridRequest {
+    #[serde(default)]
+    pub topology: GridTopology,
+    pub width: usize,
+    pub height: usize,
+    pub costs: Vec<f32>,
+    pub start: [usize; 2],
+    pub goal: [usize; 2],
+    #[serde(default)]
+    pub weight: Option<f32>,
+    #[serde(default)]
+    pub round: Option<u32>,
+    #[serde(default)]
+    pub postprocess: Vec<Transform>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GridPath {
+    pub cells: Vec<[usize; 2]>,
+    pub cost: f32,
+    pub expanded: usize,
+    pub bound: f32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RouteRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    #[serde(default)]
+    pub source: usize,
+    pub target: usize
+}
+
+/// The map of a replanning session, with the start and the goal of the robot
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplanRequest {
+    Grid(GridRequest),
+    Graph(RouteRequest)
+}
+
+/// A place in a replanning map, as the coordinates of a cell or the index of a vertex
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Position {
+    Cell([usize; 2]),
+    Vertex(usize)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CellChange {
+    pub cell: [usize; 2],
+    pub cost: f32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EdgeChange {
+    pub source: usize,
+    pub target: usize,
+    pub weight: f32
+}
+
+/// The changes observed by the robot since the last plan
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ReplanUpdate {
+    #[serde(default)]
+    pub start: Option<Position>,
+    #[serde(default)]
+    pub cells: Vec<CellChange>,
+    #[serde(default)]
+    pub edges: Vec<EdgeChange>
+}
+
+/// The weights of the edges of a stored graph that have changed, like after a traffic update
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EdgesPatch {
+    pub edges: Vec<EdgeChange>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Replan {
+    pub path: Vec<Position>,
+    pub cost: f32,
+    pub expanded: usize
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FlowGridRequest {
+    #[serde(default)]
+    pub topology: GridTopology,
+    pub width: usize,
+    pub height: usize,
+    pub costs: Vec<f32>,
+    pub goal: [usize; 2]
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FlowGraphRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    pub target: usize
+}
+
+/// The map of a flow field, with the goal every agent heads to
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum FlowFieldRequest {
+    Grid(FlowGridRequest),
+    Graph(FlowGraphRequest)
+}
+
+/// The best next step of every cell or vertex towards the goal
+///
+/// The steps of a grid are the indexes of the neighbour offsets of its
+/// topology, and the steps of a graph are the indexes of the next vertices.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FlowField {
+    pub width: usize,
+    pub height: usize,
+    pub steps: Vec<u8>
+}
+
+/// The number of walks started from every vertex by default
+fn default_walks_per_vertex() -> usize {
+    10
+}
+
+/// The number of vertices of every walk by default
+fn default_walk_length() -> usize {
+    80
+}
+
+/// The return and in-out parameters by default, which make the walks unbiased
+fn default_walk_bias() -> f64 {
+    1.0
+}
+
+/// The request of a corpus of node2vec random walks over a graph
+///
+/// The weights of the edges are the unnormalized probabilities of taking
+/// them, biased by the return parameter `p` and the in-out parameter `q`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WalkRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    #[serde(default = "default_walks_per_vertex")]
+    pub walks_per_vertex: usize,
+    #[serde(default = "default_walk_length")]
+    pub length: usize,
+    #[serde(default = "default_walk_bias")]
+    pub p: f64,
+    #[serde(default = "default_walk_bias")]
+    pub q: f64,
+    #[serde(default)]
+    pub seed: Option<u64>
+}
+
+/// The volume of the trips of a demand by default
+fn default_volume() -> f64 {
+    1.0
+}
+
+/// The trips going from an origin to a destination
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Demand {
+    pub source: usize,
+    pub target: usize,
+    #[serde(default = "default_volume")]
+    pub volume: f64
+}
+
+/// The request of the load that a set of trips puts on every edge of a graph
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EdgeLoadRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    pub demands: Vec<Demand>
+}
+
+/// The shortest paths and the trips going through an edge
+///
+/// # Fields
+///
+/// * `source` - The vertex the edge leaves
+/// * `target` - The vertex the edge reaches
+/// * `paths` - The shortest paths of the demands going through the edge
+/// * `load` - The volume of the demands going through the edge, split evenly between their shortest paths
+///
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EdgeLoad {
+    pub source: usize,
+    pub target: usize,
+    pub paths: f64,
+    pub load: f64
+}
+
+/// The load of the edges used by a set of trips
+///
+/// # Fields
+///
+/// * `loads` - The edges with any load, by source and target
+/// * `unreachable` - The indexes of the demands whose destination can't be reached
+///
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct EdgeLoads {
+    pub loads: Vec<EdgeLoad>,
+    pub unreachable: Vec<usize>
+}
+
+/// The number of trials of a failure simulation by default
+fn default_failure_trials() -> usize {
+    100
+}
+
+/// The request of a simulation of random failures of a graph
+///
+/// Every trial removes every edge with the probability `edge_failure`, and
+/// every vertex, along with its edges, with the probability `node_failure`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailureRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    #[serde(default = "default_failure_trials")]
+    pub trials: usize,
+    #[serde(default)]
+    pub edge_failure: f64,
+    #[serde(default)]
+    pub node_failure: f64,
+    #[serde(default)]
+    pub seed: Option<u64>
+}
+
+/// The mean and the bounds of a statistic over the trials of a simulation
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct TrialSummary {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64
+}
+
+/// The statistics of a simulation of random failures of a graph
+///
+/// # Fields
+///
+/// * `trials` - The number of trials simulated
+/// * `connected_pairs` - The pairs of vertices connected in the intact graph
+/// * `mean_distance` - The mean distance of the connected pairs in the intact graph
+/// * `connectivity` - The fraction of the connected pairs still connected after the failures
+/// * `stretch` - The mean ratio between the distances after the failures and the intact ones, of the pairs still connected
+/// * `disconnected_trials` - The trials where some connected pair has been disconnected
+///
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct FailureStats {
+    pub trials: usize,
+    pub connected_pairs: usize,
+    pub mean_distance: f64,
+    pub connectivity: TrialSummary,
+    pub stretch: TrialSummary,
+    pub disconnected_trials: usize
+}
+
+/// A change of a graph, applied to a temporary copy of it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Perturbation {
+    /// Add an edge, or change its weight when it exists
+    AddEdge { source: usize, target: usize, weight: f32 },
+    /// Remove an edge
+    RemoveEdge { source: usize, target: usize },
+    /// Remove a vertex, along with its edges
+    RemoveNode { vertex: usize },
+    /// Scale the weights of the edges leaving or reaching any vertex of a region
+    ScaleWeights { vertices: Vec<usize>, factor: f32 }
+}
+
+/// The request of a query against a stored graph changed by some perturbations
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WhatIfRequest {
+    pub perturbations: Vec<Perturbation>,
+    #[serde(default)]
+    pub source: usize,
+    #[serde(default)]
+    pub target: Option<usize>
+}
+
+/// The request of the walks of a graph from several sources
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MultiSourceRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    pub sources: Vec<usize>
+}
+
+/// A named step of the post-processing pipeline of a route
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    /// Merge the consecutive legs going in the same direction of a grid
+    SimplifyCollinear,
+    /// Merge the consecutive legs entering places with the same label
+    AggregateLegs { labels: Vec<String> },
+    /// Multiply the costs of the legs by a factor, naming the new unit
+    ConvertUnits {
+        factor: f64,
+        #[serde(default)]
+        unit: Option<String>
+    }
+}
+
+/// A stretch of a route between two places, made of one or more steps
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Leg {
+    pub from: Position,
+    pub to: Position,
+    pub cost: f64,
+    pub steps: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>
+}
+
+/// A route after running its post-processing pipeline
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ProcessedRoute {
+    pub legs: Vec<Leg>,
+    pub cost: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>
+}
+
+/// The post-processing pipeline of the route to a target, read from the body of a walk along with its matrix
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct RoutePipeline {
+    #[serde(default)]
+    pub target: Option<usize>,
+    #[serde(default)]
+    pub postprocess: Vec<Transform>
+}
+
+/// The probability of following an edge instead of jumping to a random vertex by default
+fn default_damping() -> f32 {
+    0.85
+}
+
+/// The change of the scores between iterations that stops the iterations by default
+fn default_rank_tolerance() -> f32 {
+    1e-6
+}
+
+/// The most iterations of the power method by default
+fn default_rank_iterations() -> usize {
+    100
+}
+
+/// The request of the PageRank of the vertices of a graph
+///
+/// Every edge leaving a vertex is followed with the same probability,
+/// whatever its weight, and the iterations stop when the scores change less
+/// than `tolerance` in total, or after `max_iterations`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageRankRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    #[serde(default = "default_damping")]
+    pub damping: f32,
+    #[serde(default = "default_rank_tolerance")]
+    pub tolerance: f32,
+    #[serde(default = "default_rank_iterations")]
+    pub max_iterations: usize
+}
+
+/// The PageRank of the vertices of a graph
+///
+/// # Fields
+///
+/// * `scores` - The score of every vertex, adding up to one
+/// * `iterations` - The iterations of the power method actually run
+/// * `converged` - If the scores changed less than the tolerance in the last iteration
+///
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct PageRank {
+    pub scores: Vec<f32>,
+    pub iterations: usize,
+    pub converged: bool
+}
+
+/// An edge of the minimum cut of a flow network
+///
+/// # Fields
+///
+/// * `source` - The vertex the edge leaves, on the side of the source
+/// * `target` - The vertex the edge reaches, on the side of the sink
+/// * `capacity` - The capacity of the edge, saturated by the maximum flow
+///
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CutEdge {
+    pub source: usize,
+    pub target: usize,
+    pub capacity: f32
+}
+
+/// The maximum flow of a network and its minimum cut
+///
+/// # Fields
+///
+/// * `flow` - The value of the maximum flow from the source to the sink
+/// * `cut` - The edges of the minimum cut, whose capacities add up to the flow
+/// * `rounds` - The rounds of pushes and relabels run in the device
+///
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct MaxFlow {
+    pub flow: f32,
+    pub cut: Vec<CutEdge>,
+    pub rounds: usize
+}
+
+/// What the paths of a directed acyclic graph optimize
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    /// The paths with the lowest total weight
+    #[default]
+    Shortest,
+    /// The paths with the highest total weight, like the critical paths of a schedule
+    Longest
+}
+
+/// The request of the paths of a directed acyclic graph from a source vertex
+///
+/// The paths are found in the topological order of the graph, so they can
+/// be the `longest` ones and the weights can be negative.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DagRequest {
+    #[serde(flatten)]
+    pub matrix: Matrix,
+    #[serde(default)]
+    pub source: usize,
+    #[serde(default)]
+    pub target: Option<usize>,
+    #[serde(default)]
+    pub objective: Objective
+}
+
+/// The paths of a directed acyclic graph from a source vertex
+///
+/// # Fields
+///
+/// * `order` - The vertices in topological order
+/// * `distances` - The distance of every vertex, if it can be reached from the source
+/// * `predecessors` - The previous vertex of the path to every vertex, if it can be reached from the source
+///
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct DagPaths {
+    pub order: Vec<usize>,
+    pub distances: Vec<Option<f32>>,
+    pub predecessors: Vec<Option<usize>>
+}
@@ -0,0 +1,162 @@
+use std::ops::Range;
+
+use log::trace;
+
+use crate::models::PathResult;
+use crate::services::cancellation::Cancellation;
+use crate::services::opencl::Result;
+use crate::services::sortest_path::{DeviceGraph, SortestPath};
+
+/// The number of source rows computed per block by default
+pub const DEFAULT_BLOCK_SIZE: usize = 64;
+
+/// The quantized distance of the vertices that can't be reached
+pub const QUANTIZED_UNREACHABLE: u16 = u16::MAX;
+
+/// The rows of the all-pairs result, as the source and its path
+pub type AllPairsBlock = Vec<(usize, Vec<PathResult>)>;
+
+/// Split the sources of a graph in blocks
+///
+/// # Arguments
+///
+/// * `width` - The number of vertices of the graph
+/// * `block_size` - The number of sources per block
+///
+/// # Returns
+///
+/// * `Vec<Range<usize>>` - The sources of every block, in order
+///
+pub fn source_blocks(width: usize, block_size: usize) -> Vec<Range<usize>> {
+    let block_size = block_size.max(1);
+
+    (0..width).step_by(block_size)
+        .map(|start| start..(start + block_size).min(width))
+        .collect()
+}
+
+/// Compute the all-pairs sortest paths of a graph as a blocked pipeline
+///
+/// Only one block of `block_size` source rows is kept in memory at a time:
+/// every block is computed, handed to `emit`, and freed before computing the
+/// next one, so the memory used is bounded by the block size instead of the
+/// square of the vertices.
+///
+/// # Arguments
+///
+/// * `walker` - The walker running the kernels
+/// * `graph` - The matrix resident in the device
+/// * `block_size` - The number of sources per block
+/// * `cancellation` - Stops the computation between the walks
+/// * `emit` - Receives every block, returning `false` to stop the computation
+///
+/// # Returns
+///
+/// * `Result<()>` - Nothing, or the error of the kernels
+///
+pub fn all_pairs_blocks(walker: &SortestPath, graph: &DeviceGraph, block_size: usize, cancellation: &Cancellation, mut emit: impl FnMut(AllPairsBlock) -> bool) -> Result<()> {
+    for sources in source_blocks(graph.width, block_size) {
+        // Print the block being computed
+        trace!("Computing all-pairs block for sources {:?}", sources);
+
+        // Compute the rows of the block
+        let block = sources
+            .map(|source| walker.walk(graph, source, cancellation, |_, _| {}).map(|path| (source, path)))
+            .collect::<Result<AllPairsBlock>>()?;
+
+        // Stream the block out and stop if nobody is listening anymore
+        if !emit(block) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Quantize a distance to an unsigned 16 bit integer against a scale
+///
+/// The distance is counted in steps of `scale`, rounded to the nearest one,
+/// so clients read it back multiplying by the scale. The distances beyond
+/// the range of the type saturate just below it, keeping its largest value
+/// for the vertices that can't be reached.
+///
+/// # Arguments
+///
+/// * `distance` - The distance of the walk
+/// * `scale` - The validated distance of every step of the quantized value
+///
+/// # Returns
+///
+/// * `u16` - The quantized distance
+///
+pub fn quantize(distance: f32, scale: f32) -> u16 {
+    if distance == f32::MAX {
+        QUANTIZED_UNREACHABLE
+    } else {
+        (distance / scale).round().clamp(0.0, (QUANTIZED_UNREACHABLE - 1) as f32) as u16
+    }
+}
+
+/// Quantize the distances of a path to unsigned 16 bit integers against a scale
+///
+/// The vertices that can't be reached get the largest value of the type,
+/// telling them apart by the zero distance the kernels leave them, unless
+/// the matrix has zero weight edges, which leave them the largest distance.
+///
+/// # Arguments
+///
+/// * `path` - The path computed from the source
+/// * `source` - The vertex the path was computed from
+/// * `scale` - The validated distance of every step of the quantized values
+/// * `zero_edges` - If the matrix of the path has zero weight edges
+///
+/// # Returns
+///
+/// * `Vec<(i32, u16)>` - The predecessor and the quantized distance of every vertex
+///
+pub fn quantize_path(path: &[PathResult], source: usize, scale: f32, zero_edges: bool) -> Vec<(i32, u16)> {
+    path.iter().enumerate().map(|(vertex, hop)| {
+        let unreachable = !zero_edges && hop.1 == 0.0 && vertex != source;
+        (hop.0, if unreachable { QUANTIZED_UNREACHABLE } else { quantize(hop.1, scale) })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_blocks() {
+        // Get the result
+        let blocks = source_blocks(10, 4);
+
+        // Check if the result is correct
+        assert_eq!(blocks, vec![0..4, 4..8, 8..10]);
+        assert_eq!(source_blocks(3, 0), vec![0..1, 1..2, 2..3]);
+        assert!(source_blocks(0, 4).is_empty());
+    }
+
+    #[test]
+    fn test_quantize() {
+        // Get the result
+        let quantized = [1.26, 0.0, 1e9, f32::MAX].map(|distance| quantize(distance, 0.5));
+
+        // Check if the result is correct
+        assert_eq!(quantized, [3, 0, 65534, QUANTIZED_UNREACHABLE]);
+    }
+
+    #[test]
+    fn test_quantize_path() {
+        // Prepare a path from vertex 0 where vertex 2 can't be reached
+        let path = [PathResult(0, 0.0), PathResult(0, 1.26), PathResult(-1, 0.0)];
+        let free = [PathResult(0, 0.0), PathResult(0, 0.0), PathResult(-1, f32::MAX)];
+
+        // Get the result
+        let quantized = quantize_path(&path, 0, 0.5, false);
+        let quantized_free = quantize_path(&free, 0, 0.5, true);
+
+        // Check if the result is correct
+        assert_eq!(quantized, vec![(0, 0), (0, 3), (-1, QUANTIZED_UNREACHABLE)]);
+        assert_eq!(quantized_free, vec![(0, 0), (0, 0), (-1, QUANTIZED_UNREACHABLE)]);
+    }
+}
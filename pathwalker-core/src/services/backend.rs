@@ -0,0 +1,168 @@
+use serde::Serialize;
+
+use crate::models::{Matrix, PathResult};
+use crate::services::cancellation::Cancellation;
+use crate::services::sortest_path::SortestPath;
+
+/// A compute backend walking single precision graphs
+///
+/// The OpenCL walker implements every algorithm of the crate, while the
+/// other backends only implement the weighted walks, so the services that
+/// can run in any of them take a backend instead of the OpenCL walker.
+pub trait Backend: Send + Sync {
+    /// Returns the kind of the backend
+    fn kind(&self) -> BackendKind;
+
+    /// Returns the name of the device the backend runs on
+    fn device_name(&self) -> String;
+
+    /// Returns the versions of everything that can change the results, for the run ids
+    fn backend_version(&self) -> &str;
+
+    /// Check that the device can run a tiny walk
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Nothing, or why the walk has failed
+    ///
+    fn probe(&self) -> Result<(), String>;
+
+    /// Walk a graph from a source vertex
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix` - The matrix to walk
+    /// * `source` - The vertex to start the walk from
+    /// * `cancellation` - Aborts the walk between iterations
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<PathResult>, String>` - The predecessor and the distance of every vertex, or why the walk has failed
+    ///
+    fn walk_matrix(&self, matrix: &Matrix, source: usize, cancellation: &Cancellation) -> Result<Vec<PathResult>, String>;
+}
+
+/// The compute backends the graphs can be walked with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// The OpenCL kernels, running every algorithm
+    OpenCl,
+    /// The wgpu compute shaders over Vulkan, Metal or DX12, running the weighted walks
+    Wgpu,
+    /// The CUDA kernels of the NVIDIA devices, running the weighted walks
+    Cuda,
+    /// Dijkstra in the threads of the CPU, running the weighted walks without negative weights
+    Cpu,
+}
+
+impl BackendKind {
+    /// Parse the name of a backend
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name, `opencl`, `wgpu`, `cuda` or `cpu`
+    ///
+    /// # Returns
+    ///
+    /// * `Option<BackendKind>` - The backend, if the name is known
+    ///
+    pub fn parse(name: &str) -> Option<BackendKind> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "opencl" => Some(BackendKind::OpenCl),
+            "wgpu" | "vulkan" => Some(BackendKind::Wgpu),
+            "cuda" => Some(BackendKind::Cuda),
+            "cpu" => Some(BackendKind::Cpu),
+            _ => None
+        }
+    }
+
+    /// Returns the name of the backend
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackendKind::OpenCl => "opencl",
+            BackendKind::Wgpu => "wgpu",
+            BackendKind::Cuda => "cuda",
+            BackendKind::Cpu => "cpu"
+        }
+    }
+
+    /// Check if the backend has been built in
+    pub fn is_built(&self) -> bool {
+        match self {
+            BackendKind::OpenCl => cfg!(feature = "opencl"),
+            BackendKind::Cpu => true,
+            BackendKind::Wgpu => cfg!(feature = "wgpu"),
+            BackendKind::Cuda => cfg!(feature = "cuda")
+        }
+    }
+
+    /// Choose the backend of the device of the host
+    ///
+    /// The CUDA backend is chosen when it has been built in and the driver
+    /// sees an NVIDIA device, and the OpenCL one otherwise, or the CPU one
+    /// when OpenCL hasn't been built in.
+    ///
+    /// # Returns
+    ///
+    /// * `BackendKind` - The fastest backend the host can run
+    ///
+    pub fn detect() -> BackendKind {
+        #[cfg(feature = "cuda")]
+        if crate::services::cuda_walker::CudaWalker::is_available() {
+            return BackendKind::Cuda;
+        }
+
+        if cfg!(feature = "opencl") {
+            BackendKind::OpenCl
+        } else {
+            BackendKind::Cpu
+        }
+    }
+}
+
+impl Backend for SortestPath {
+    fn kind(&self) -> BackendKind {
+        BackendKind::OpenCl
+    }
+
+    fn device_name(&self) -> String {
+        SortestPath::device_name(self)
+    }
+
+    fn backend_version(&self) -> &str {
+        SortestPath::backend_version(self)
+    }
+
+    fn probe(&self) -> Result<(), String> {
+        SortestPath::probe(self).map_err(|err| err.to_string())
+    }
+
+    fn walk_matrix(&self, matrix: &Matrix, source: usize, cancellation: &Cancellation) -> Result<Vec<PathResult>, String> {
+        self.upload(matrix)
+            .and_then(|graph| self.walk(&graph, source, cancellation, |_, _| {}))
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_kind() {
+        // Prepare the names of the backends
+        let names = ["opencl", " WGPU ", "vulkan", "cuda", "cpu", "metal"];
+
+        // Get the result
+        let kinds = names.map(BackendKind::parse);
+
+        // Check if the result is correct
+        assert_eq!(kinds, [Some(BackendKind::OpenCl), Some(BackendKind::Wgpu), Some(BackendKind::Wgpu), Some(BackendKind::Cuda), Some(BackendKind::Cpu), None]);
+        assert_eq!(BackendKind::Wgpu.name(), "wgpu");
+        assert_eq!(BackendKind::OpenCl.is_built(), cfg!(feature = "opencl"));
+        assert_eq!(BackendKind::Wgpu.is_built(), cfg!(feature = "wgpu"));
+        assert_eq!(BackendKind::Cuda.is_built(), cfg!(feature = "cuda"));
+        assert!(BackendKind::detect().is_built());
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::VecDeque;
+
+use crate::models::{BudgetPath, ChargingStation, ChargingStop, EvRoute, Matrix};
+
+/// The maximum number of labels created before giving up on a search
+pub const MAX_LABELS: usize = 1_000_000;
+
+/// A partial path reaching a vertex
+///
+/// # Fields
+///
+/// * `vertex` - The vertex reached
+/// * `cost` - The sum of the weights of the path
+/// * `resource` - The sum of the resources of the path, since the last recharge
+/// * `recharged` - The resource recharged at the vertex, if the label is a recharge
+/// * `parent` - The label of the previous vertex of the path
+///
+#[derive(Clone, Copy)]
+struct Label {
+    vertex: usize,
+    cost: f32,
+    resource: f32,
+    recharged: f32,
+    parent: Option<usize>,
+}
+
+/// The labels of a label correcting search
+///
+/// # Fields
+///
+/// * `budget` - The maximum resource of a label
+/// * `labels` - Every label created
+/// * `frontier` - The labels of every vertex that no other label beats
+/// * `dominated` - If every label has been beaten by a later one
+/// * `queue` - The labels to explore
+///
+struct Labels {
+    budget: f32,
+    labels: Vec<Label>,
+    frontier: Vec<Vec<usize>>,
+    dominated: Vec<bool>,
+    queue: VecDeque<usize>,
+}
+
+impl Labels {
+    /// Keep a new label unless it's over the budget or another label of its vertex beats it
+    fn push(&mut self, label: Label) -> Result<(), String> {
+        let (labels, dominated) = (&self.labels, &mut self.dominated);
+        let frontier = &mut self.frontier[label.vertex];
+
+        if label.resource > self.budget || frontier.iter().any(|other| labels[*other].cost <= label.cost && labels[*other].resource <= label.resource) {
+            return Ok(());
+        }
+
+        // Drop the labels the new one beats, and keep exploring from it
+        frontier.retain(|other| {
+            let beaten = label.cost <= labels[*other].cost && label.resource <= labels[*other].resource;
+            dominated[*other] |= beaten;
+            !beaten
+        });
+
+        if self.labels.len() >= MAX_LABELS {
+            return Err("The search has too many partial paths, try with a smaller budget".to_string());
+        }
+
+        frontier.push(self.labels.len());
+        self.queue.push_back(self.labels.len());
+        self.labels.push(label);
+        self.dominated.push(false);
+
+        Ok(())
+    }
+}
+
+/// The path found by the label correcting search
+///
+/// # Fields
+///
+/// * `route` - The vertices of the path, from the source to the target
+/// * `cost` - The sum of the weights of the path
+/// * `resource` - The resource used since the last recharge
+/// * `recharges` - The vertices where the resource has been recharged, with the amount recharged
+///
+struct SearchPath {
+    route: Vec<usize>,
+    cost: f32,
+    resource: f32,
+    recharges: Vec<(usize, f32)>,
+}
+
+/// Validate the resources and the budget of a constrained walk
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix of weights
+/// * `resources` - The resource consumed by every edge, laid out as the matrix
+/// * `budget` - The maximum resource the path can consume
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the walk isn't valid, if any
+///
+pub fn validate_budget(matrix: &Matrix, resources: &[f32], budget: f32) -> Option<&'static str> {
+    if resources.len() != matrix.data.len() {
+        Some("The resources size and matrix dimensions are not the same")
+    } else if resources.iter().any(|resource| !resource.is_finite() || *resource < 0.0) {
+        Some("The resources must be non-negative numbers")
+    } else if matrix.data.iter().any(|weight| *weight < 0.0) {
+        Some("The weights must be positive numbers")
+    } else if !budget.is_finite() || budget < 0.0 {
+        Some("The budget must be a non-negative number")
+    } else {
+        None
+    }
+}
+
+/// Search the cheapest path whose resource since the last recharge never exceeds the budget
+///
+/// A label correcting search keeps, for every vertex, the partial paths
+/// that no other partial path beats in both cost and resource, discarding
+/// the ones over the budget, so the cheapest label of the target is the
+/// cheapest path within the budget. At the vertices with a recharge rate,
+/// the resource used can also be recharged in full, paying the rate for
+/// every unit recharged.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix of weights
+/// * `resources` - The resource consumed by every edge, laid out as the matrix
+/// * `budget` - The maximum resource the path can consume between recharges
+/// * `used` - The resource already used at the source
+/// * `rates` - The cost of recharging a unit of resource at every vertex, if it can be recharged there
+/// * `source` - The vertex the path starts from
+/// * `target` - The vertex the path ends at
+///
+/// # Returns
+///
+/// * `Result<Option<SearchPath>, String>` - The path, nothing when no path respects the budget,
+///   or the reason why the search has been aborted
+///
+fn label_search(matrix: &Matrix, resources: &[f32], budget: f32, used: f32, rates: &[Option<f32>], source: usize, target: usize) -> Result<Option<SearchPath>, String> {
+    let width = matrix.width;
+    let mut search = Labels { budget, labels: vec![], frontier: vec![vec![]; width], dominated: vec![], queue: VecDeque::new() };
+
+    search.push(Label { vertex: source, cost: 0.0, resource: used, recharged: 0.0, parent: None })?;
+
+    while let Some(current) = search.queue.pop_front() {
+        if search.dominated[current] {
+            continue;
+        }
+
+        let Label { vertex, cost, resource, .. } = search.labels[current];
+
+        // Recharge the resource used when the vertex allows it
+        if let Some(rate) = rates.get(vertex).copied().flatten().filter(|_| resource > 0.0 && vertex != target) {
+            search.push(Label { vertex, cost: cost + resource * rate, resource: 0.0, recharged: resource, parent: Some(current) })?;
+        }
+
+        for next in (0..width).filter(|next| *next != vertex) {
+            // Read the edge the same way the kernels do
+            let Some(weight) = matrix.edge(vertex, next) else {
+                continue;
+            };
+
+            // The resources of an undirected edge can be in either of its cells too
+            let mut consumed = resources[next * width + vertex];
+
+            if !matrix.directed {
+                consumed = consumed.max(resources[vertex * width + next]);
+            }
+
+            search.push(Label { vertex: next, cost: cost + weight, resource: resource + consumed, recharged: 0.0, parent: Some(current) })?;
+        }
+    }
+
+    let Labels { labels, frontier, .. } = search;
+
+    // Take the cheapest label of the target and follow its parents back to the source
+    let best = frontier[target].iter()
+        .min_by(|a, b| labels[**a].cost.total_cmp(&labels[**b].cost).then(labels[**a].resource.total_cmp(&labels[**b].resource)));
+
+    Ok(best.map(|best| {
+        let (mut route, mut recharges) = (vec![], vec![]);
+        let mut label = Some(*best);
+
+        while let Some(current) = label {
+            // A recharge stays in the same vertex as its parent
+            if labels[current].recharged > 0.0 {
+                recharges.push((labels[current].vertex, labels[current].recharged));
+            } else {
+                route.push(labels[current].vertex);
+            }
+
+            label = labels[current].parent;
+        }
+
+        route.reverse();
+        recharges.reverse();
+        SearchPath { route, cost: labels[*best].cost, resource: labels[*best].resource, recharges }
+    }))
+}
+
+/// Compute the cheapest path that respects a resource budget
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix of weights
+/// * `resources` - The resource consumed by every edge, laid out as the matrix
+/// * `budget` - The maximum resource the path can consume
+/// * `source` - The vertex the path starts from
+/// * `target` - The vertex the path ends at
+///
+/// # Returns
+///
+/// * `Result<Option<BudgetPath>, String>` - The path, nothing when no path respects the budget,
+///   or the reason why the search has been aborted
+///
+pub fn budget_path(matrix: &Matrix, resources: &[f32], budget: f32, source: usize, target: usize) -> Result<Option<BudgetPath>, String> {
+    let path = label_search(matrix, resources, budget, 0.0, &[], source, target)?;
+
+    Ok(path.map(|path| BudgetPath { route: path.route, cost: path.cost, resource: path.resource }))
+}
+
+/// Compute the cheapest route of an electric vehicle, inserting charging stops
+///
+/// The battery is charged in full at every stop, paying the rate of the
+/// station for every unit of energy charged, and the route never lets the
+/// battery run out between stops.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix of weights
+/// * `consumption` - The energy consumed by every edge, laid out as the matrix
+/// * `capacity` - The energy of the battery when full
+/// * `charge` - The energy of the battery at the source
+/// * `stations` - The charging stations
+/// * `source` - The vertex the route starts from
+/// * `target` - The vertex the route ends at
+///
+/// # Returns
+///
+/// * `Result<Option<EvRoute>, String>` - The route, nothing when the battery can't reach the target,
+///   or the reason why the search has been aborted
+///
+pub fn ev_route(matrix: &Matrix, consumption: &[f32], capacity: f32, charge: f32, stations: &[ChargingStation], source: usize, target: usize) -> Result<Option<EvRoute>, String> {
+    let mut rates = vec![None; matrix.width];
+
+    for station in stations {
+        rates[station.vertex] = Some(station.rate);
+    }
+
+    let path = label_search(matrix, consumption, capacity, capacity - charge, &rates, source, target)?;
+
+    Ok(path.map(|path| EvRoute {
+        route: path.route,
+        cost: path.cost,
+        stops: path.recharges.into_iter().map(|(vertex, energy)| ChargingStop { vertex, energy }).collect(),
+        remaining_charge: capacity - path.resource
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_path() {
+        // Prepare a graph where the cheapest path has a toll and the detour is free
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        let mut tolls = vec![0.0; 16];
+
+        for (source, target, weight, toll) in [(0, 1, 1.0, 5.0), (1, 3, 1.0, 0.0), (0, 2, 2.0, 0.0), (2, 3, 2.0, 1.0)] {
+            matrix.set_edge(source, target, weight);
+            tolls[target * 4 + source] = toll;
+        }
+
+        // Get the result
+        let unconstrained = budget_path(&matrix, &tolls, 10.0, 0, 3).unwrap();
+        let constrained = budget_path(&matrix, &tolls, 2.0, 0, 3).unwrap();
+        let unreachable = budget_path(&matrix, &tolls, 0.5, 0, 3).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(unconstrained, Some(BudgetPath { route: vec![0, 1, 3], cost: 2.0, resource: 5.0 }));
+        assert_eq!(constrained, Some(BudgetPath { route: vec![0, 2, 3], cost: 4.0, resource: 1.0 }));
+        assert_eq!(unreachable, None);
+    }
+
+    #[test]
+    fn test_ev_route() {
+        // Prepare a line of vertices where the battery only covers two edges
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        let mut consumption = vec![0.0; 16];
+
+        for source in 0..3 {
+            matrix.set_edge(source, source + 1, 1.0);
+            consumption[(source + 1) * 4 + source] = 1.0;
+        }
+
+        let stations = vec![ChargingStation { vertex: 1, rate: 0.5 }, ChargingStation { vertex: 2, rate: 2.0 }];
+
+        // Get the result
+        let route = ev_route(&matrix, &consumption, 2.0, 2.0, &stations, 0, 3).unwrap();
+        let stranded = ev_route(&matrix, &consumption, 2.0, 2.0, &[], 0, 3).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(route, Some(EvRoute {
+            route: vec![0, 1, 2, 3],
+            cost: 3.5,
+            stops: vec![ChargingStop { vertex: 1, energy: 1.0 }],
+            remaining_charge: 0.0
+        }));
+        assert_eq!(stranded, None);
+    }
+}
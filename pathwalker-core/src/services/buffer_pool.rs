@@ -0,0 +1,291 @@
+#[cfg(feature = "opencl")]
+use std::any::{Any, TypeId};
+#[cfg(feature = "opencl")]
+use std::collections::HashMap;
+#[cfg(feature = "opencl")]
+use std::ops::Deref;
+#[cfg(feature = "opencl")]
+use std::sync::Arc;
+use std::sync::Mutex;
+
+#[cfg(feature = "opencl")]
+use log::debug;
+use log::warn;
+#[cfg(feature = "opencl")]
+use ocl::{Buffer, MemFlags, OclPrm, Queue, Result};
+
+use crate::services::metrics::add_buffer_pool_bytes;
+#[cfg(feature = "opencl")]
+use crate::services::metrics::count_buffer_pool;
+
+/// The environment variable with the bytes of idle buffers kept by the pool, or `0` to allocate every buffer again
+pub const BUFFER_POOL_VARIABLE: &str = "PATHWALKER_BUFFER_POOL_BYTES";
+
+/// The bytes of idle buffers kept by the pool by default
+pub const DEFAULT_BUFFER_POOL_BYTES: usize = 256 * 1024 * 1024;
+
+/// The elements of the smallest size class
+const MIN_SIZE_CLASS: usize = 64;
+
+/// Get the size class of a buffer
+///
+/// Every power of two is split in four classes, so a buffer is never more
+/// than a quarter larger than asked for, and buffers of similar lengths
+/// share a class.
+///
+/// # Arguments
+///
+/// * `len` - The elements asked for
+///
+/// # Returns
+///
+/// * `usize` - The elements of the buffers of the class
+///
+pub fn size_class(len: usize) -> usize {
+    let len = len.max(MIN_SIZE_CLASS);
+    let step = len.next_power_of_two() / 8;
+
+    len.div_ceil(step) * step
+}
+
+/// Parse the bytes of idle buffers kept by the pool
+///
+/// # Arguments
+///
+/// * `setting` - The value of `PATHWALKER_BUFFER_POOL_BYTES`, if it's set
+///
+/// # Returns
+///
+/// * `usize` - The bytes, or the default ones when the setting isn't a number
+///
+fn resolve_capacity(setting: Option<String>) -> usize {
+    match setting.map(|setting| setting.trim().parse::<usize>()) {
+        Some(Ok(bytes)) => bytes,
+        Some(Err(err)) => {
+            warn!("The {} setting isn't a number of bytes, keeping {} bytes: {}", BUFFER_POOL_VARIABLE, DEFAULT_BUFFER_POOL_BYTES, err);
+            DEFAULT_BUFFER_POOL_BYTES
+        },
+        None => DEFAULT_BUFFER_POOL_BYTES
+    }
+}
+
+/// The idle buffers of the pool
+///
+/// # Fields
+///
+/// * `buffers` - The buffers of every element type and size class
+/// * `bytes` - The bytes of all the buffers
+///
+#[derive(Default)]
+struct Idle {
+    #[cfg(feature = "opencl")]
+    buffers: HashMap<(TypeId, usize), Vec<Box<dyn Any + Send>>>,
+    bytes: usize,
+}
+
+/// A pool of pinned device buffers, reused across the requests
+///
+/// The buffers are allocated with `ALLOC_HOST_PTR`, so the driver can back
+/// them with pinned host memory and copy them without staging, and are
+/// rounded up to their size class, so the requests of similar dimensions
+/// reuse the buffers of the previous ones instead of allocating them again.
+/// The buffers released when the pool already keeps its capacity are freed.
+///
+/// # Fields
+///
+/// * `idle` - The buffers released and not taken again yet
+/// * `capacity` - The bytes of idle buffers kept at most
+///
+pub struct BufferPool {
+    idle: Mutex<Idle>,
+    capacity: usize,
+}
+
+impl BufferPool {
+    /// Create an empty pool
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The bytes of idle buffers kept at most, or zero to allocate every buffer again
+    ///
+    /// # Returns
+    ///
+    /// * `BufferPool` - The pool
+    ///
+    pub fn new(capacity: usize) -> BufferPool {
+        BufferPool { idle: Mutex::new(Idle::default()), capacity }
+    }
+
+    /// Create an empty pool with the capacity of `PATHWALKER_BUFFER_POOL_BYTES`
+    pub fn from_env() -> BufferPool {
+        BufferPool::new(resolve_capacity(std::env::var(BUFFER_POOL_VARIABLE).ok()))
+    }
+
+    /// Returns the bytes of idle buffers kept at most
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the bytes of idle buffers kept by the pool
+    pub fn idle_bytes(&self) -> usize {
+        self.idle.lock().unwrap().bytes
+    }
+
+    /// Take a buffer with at least some elements, reusing an idle one of its size class
+    ///
+    /// The contents of a reused buffer are the ones of its previous request,
+    /// so the callers must write or initialize every element they read. A
+    /// size class the device can't allocate falls back to a buffer of the
+    /// exact length, which isn't kept once released.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - The queue of the device
+    /// * `len` - The elements needed
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PooledBuffer<T>>` - The buffer, returned to the pool once dropped, or the error of the allocation
+    ///
+    #[cfg(feature = "opencl")]
+    pub fn take<T: OclPrm>(self: &Arc<Self>, queue: &Queue, len: usize) -> Result<PooledBuffer<T>> {
+        // Allocate every buffer again when the pool keeps none
+        if self.capacity == 0 {
+            return allocate::<T>(queue, len).map(|buffer| PooledBuffer { buffer: Some(buffer), pool: None });
+        }
+
+        // Reuse an idle buffer of the size class
+        let class = size_class(len);
+        let reused = {
+            let mut idle = self.idle.lock().unwrap();
+            let buffer = idle.buffers.get_mut(&(TypeId::of::<T>(), class))
+                .and_then(Vec::pop)
+                .and_then(|buffer| buffer.downcast::<Buffer<T>>().ok());
+
+            if buffer.is_some() {
+                idle.bytes -= class * std::mem::size_of::<T>();
+            }
+
+            buffer
+        };
+
+        if let Some(buffer) = reused {
+            count_buffer_pool(true);
+            add_buffer_pool_bytes(-((class * std::mem::size_of::<T>()) as i64));
+            return Ok(PooledBuffer { buffer: Some(*buffer), pool: Some(self.clone()) });
+        }
+
+        // Allocate a buffer of the size class, or of the exact length when the device can't fit the class
+        count_buffer_pool(false);
+
+        match allocate::<T>(queue, class) {
+            Ok(buffer) => Ok(PooledBuffer { buffer: Some(buffer), pool: Some(self.clone()) }),
+            Err(err) if class > len => {
+                debug!("The size class of {} elements can't be allocated, allocating {} elements: {}", class, len, err);
+                allocate::<T>(queue, len).map(|buffer| PooledBuffer { buffer: Some(buffer), pool: None })
+            },
+            Err(err) => Err(err)
+        }
+    }
+
+    /// Keep a released buffer, unless the pool already keeps its capacity
+    #[cfg(feature = "opencl")]
+    fn release<T: OclPrm>(&self, buffer: Buffer<T>) {
+        let bytes = buffer.len() * std::mem::size_of::<T>();
+        let mut idle = self.idle.lock().unwrap();
+
+        if idle.bytes + bytes > self.capacity {
+            return;
+        }
+
+        idle.bytes += bytes;
+        idle.buffers.entry((TypeId::of::<T>(), buffer.len())).or_default().push(Box::new(buffer));
+        add_buffer_pool_bytes(bytes as i64);
+    }
+
+    /// Free every idle buffer
+    pub fn clear(&self) {
+        let mut idle = self.idle.lock().unwrap();
+
+        add_buffer_pool_bytes(-(idle.bytes as i64));
+        *idle = Idle::default();
+    }
+}
+
+impl Drop for BufferPool {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Allocate a pinned buffer in the device
+#[cfg(feature = "opencl")]
+fn allocate<T: OclPrm>(queue: &Queue, len: usize) -> Result<Buffer<T>> {
+    Buffer::<T>::builder()
+        .queue(queue.clone()).len(len)
+        .flags(MemFlags::new().read_write().alloc_host_ptr())
+        .build()
+}
+
+/// A buffer taken from the pool, returned to it once dropped
+///
+/// The buffer can be longer than asked for, so the reads and the writes
+/// must be as long as the host slices instead of the whole buffer.
+///
+/// # Fields
+///
+/// * `buffer` - The device buffer
+/// * `pool` - The pool to return the buffer to, or none when it isn't kept
+///
+#[cfg(feature = "opencl")]
+pub struct PooledBuffer<T: OclPrm> {
+    buffer: Option<Buffer<T>>,
+    pool: Option<Arc<BufferPool>>,
+}
+
+#[cfg(feature = "opencl")]
+impl<T: OclPrm> PooledBuffer<T> {
+    /// Returns the bytes of device memory allocated for the buffer
+    pub fn size_in_bytes(&self) -> usize {
+        self.len() * std::mem::size_of::<T>()
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl<T: OclPrm> Deref for PooledBuffer<T> {
+    type Target = Buffer<T>;
+
+    fn deref(&self) -> &Buffer<T> {
+        self.buffer.as_ref().unwrap()
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl<T: OclPrm> Drop for PooledBuffer<T> {
+    fn drop(&mut self) {
+        if let (Some(buffer), Some(pool)) = (self.buffer.take(), &self.pool) {
+            pool.release(buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_class() {
+        // Prepare some lengths around the classes
+        let lengths = [0, 1, 64, 65, 80, 81, 100, 1000, 1024, 1025, 250_000];
+
+        // Get the result
+        let classes = lengths.map(size_class);
+        let capacities = [resolve_capacity(None), resolve_capacity(Some("0".to_string())), resolve_capacity(Some("1048576".to_string())), resolve_capacity(Some("lots".to_string()))];
+
+        // Check if the result is correct
+        assert_eq!(classes, [64, 64, 64, 80, 80, 96, 112, 1024, 1024, 1280, 262_144]);
+        assert!(lengths.iter().zip(classes).all(|(len, class)| class >= *len && class - len <= class / 4 + MIN_SIZE_CLASS));
+        assert_eq!(capacities, [DEFAULT_BUFFER_POOL_BYTES, 0, 1048576, DEFAULT_BUFFER_POOL_BYTES]);
+        assert_eq!(BufferPool::new(1024).idle_bytes(), 0);
+    }
+}
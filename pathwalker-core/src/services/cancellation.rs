@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Stops a computation between its iterations
+///
+/// The kernels can't be interrupted once enqueued, so the computations check
+/// the cancellation before every iteration, and give up when the timeout has
+/// expired or the client has gone away.
+///
+/// # Fields
+///
+/// * `timeout` - The time the computation can run for, if limited
+/// * `deadline` - When the timeout expires, if limited
+/// * `cancelled` - Set when the client of the computation has gone away
+///
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Cancels a computation when dropped, like when the request awaiting it is dropped
+pub struct CancelOnDrop(Cancellation);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+impl Cancellation {
+    /// Create a cancellation whose timeout starts now
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The time the computation can run for, unlimited when not given
+    ///
+    /// # Returns
+    ///
+    /// * `Cancellation` - The cancellation
+    ///
+    pub fn with_timeout(timeout: Option<Duration>) -> Cancellation {
+        Cancellation {
+            timeout,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            cancelled: Arc::new(AtomicBool::new(false))
+        }
+    }
+
+    /// Cancel the computation, as its client has gone away
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Get a guard cancelling the computation when dropped
+    pub fn on_drop(&self) -> CancelOnDrop {
+        CancelOnDrop(self.clone())
+    }
+
+    /// Check if the timeout has expired
+    pub fn is_expired(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Check if the computation can go on
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Nothing, or the reason why the computation must stop
+    ///
+    pub fn check(&self) -> Result<(), String> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            Err("The computation has been cancelled because the client has gone away".to_string())
+        } else if self.is_expired() {
+            Err(format!("The computation has been aborted after its timeout of {} seconds", self.timeout.unwrap_or_default().as_secs_f32()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation() {
+        // Prepare an expired, a cancelled and an unlimited cancellation
+        let expired = Cancellation::with_timeout(Some(Duration::ZERO));
+        let cancelled = Cancellation::with_timeout(Some(Duration::from_secs(60)));
+        let unlimited = Cancellation::default();
+
+        // Get the result
+        drop(cancelled.on_drop());
+
+        // Check if the result is correct
+        assert!(expired.is_expired());
+        assert_eq!(expired.check(), Err("The computation has been aborted after its timeout of 0 seconds".to_string()));
+        assert!(!cancelled.is_expired());
+        assert!(cancelled.check().unwrap_err().contains("gone away"));
+        assert_eq!(unlimited.check(), Ok(()));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::models::{Matrix, PathResult, ValidationRules};
+use crate::services::loads::{sort_shortest_paths, ShortestPaths};
+use crate::solver::validate_static_matrix;
+
+/// The rules of the weights of the graphs, as the accumulation can't handle negative ones
+const NON_NEGATIVE: ValidationRules = ValidationRules { reject_negative: true, warn_asymmetric: false };
+
+/// Validate a matrix for the centrality of its vertices
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the matrix isn't valid, if any
+///
+pub fn validate_centrality(matrix: &Matrix) -> Option<&'static str> {
+    validate_static_matrix(matrix)
+        .or_else(|| matrix.has_constraints().then_some("The centrality counts every shortest path, so it can't honor the forbidden nodes, the forbidden edges or the max hops"))
+        .or_else(|| (!matrix.validate(NON_NEGATIVE).is_valid()).then_some("The weights must be finite non-negative numbers"))
+}
+
+/// Accumulate the dependency of a source on every vertex, from the path of its walk
+///
+/// The edges lying on a shortest path are the ones whose weight, added to
+/// the distance of their source, gives the distance of their target. The
+/// sum is made in single precision like in the kernels, so the ties found
+/// by the walk are found again. The dependencies are then gathered walking
+/// the shortest paths backwards, as in the algorithm of Brandes.
+///
+/// # Arguments
+///
+/// * `matrix` - The validated matrix
+/// * `source` - The source of the walk
+/// * `path` - The path of the walk from the source
+///
+/// # Returns
+///
+/// * `Result<Vec<f64>, String>` - The dependency of the source on every vertex, or the reason why the paths can't be counted
+///
+pub fn dependencies(matrix: &Matrix, source: usize, path: &[PathResult]) -> Result<Vec<f64>, String> {
+    let missing = if matrix.zero_edges { f32::MAX } else { 0.0 };
+    let reachable: Vec<bool> = path.iter().enumerate().map(|(vertex, hop)| vertex == source || hop.1 != missing).collect();
+    let ShortestPaths { order, predecessors, counts } = sort_shortest_paths(matrix, source, &reachable, |vertex, next, weight| {
+        reachable[next] && path[vertex].1 + weight == path[next].1
+    })?;
+
+    // Walk the shortest paths backwards, so every vertex has gathered its dependency before passing it on
+    let mut dependencies = vec![0.0; matrix.width];
+
+    for &vertex in order.iter().rev() {
+        for &previous in &predecessors[vertex] {
+            dependencies[previous] += counts[previous] / counts[vertex] * (1.0 + dependencies[vertex]);
+        }
+    }
+
+    dependencies[source] = 0.0;
+    Ok(dependencies)
+}
+
+/// Compute the betweenness centrality of every vertex from the walks of every source
+///
+/// The betweenness of a vertex is the fraction of the shortest paths between
+/// every other pair of vertices that go through it, summed over the pairs.
+/// The pairs of undirected matrices are counted once, and the normalized
+/// scores are divided by the number of pairs, so they are between zero and
+/// one.
+///
+/// # Arguments
+///
+/// * `matrix` - The validated matrix
+/// * `paths` - The path of the walk from every vertex, in order
+/// * `normalized` - If the scores are divided by the number of pairs
+///
+/// # Returns
+///
+/// * `Result<Vec<f64>, String>` - The centrality of every vertex, or the reason why the paths can't be counted
+///
+pub fn betweenness(matrix: &Matrix, paths: &[Vec<PathResult>], normalized: bool) -> Result<Vec<f64>, String> {
+    let width = matrix.width;
+    let mut scores = vec![0.0; width];
+
+    for (source, path) in paths.iter().enumerate() {
+        for (score, dependency) in scores.iter_mut().zip(dependencies(matrix, source, path)?) {
+            *score += dependency;
+        }
+    }
+
+    // The searches of undirected matrices find every pair from both of its ends
+    let scale = match (normalized, matrix.directed) {
+        (true, _) if width > 2 => 1.0 / ((width - 1) * (width - 2)) as f64,
+        (true, _) => 0.0,
+        (false, true) => 1.0,
+        (false, false) => 0.5
+    };
+
+    Ok(scores.into_iter().map(|score| score * scale).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_betweenness() {
+        // Prepare a diamond from 0 to 3 with two shortest paths, walked from every vertex
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        matrix.set_edge(0, 1, 1.0);
+        matrix.set_edge(0, 2, 1.0);
+        matrix.set_edge(1, 3, 1.0);
+        matrix.set_edge(2, 3, 1.0);
+        let paths = vec![
+            vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(0, 1.0), PathResult(1, 2.0)],
+            vec![PathResult(1, 0.0), PathResult(1, 0.0), PathResult(2, 0.0), PathResult(1, 1.0)],
+            vec![PathResult(2, 0.0), PathResult(1, 0.0), PathResult(2, 0.0), PathResult(2, 1.0)],
+            vec![PathResult(3, 0.0), PathResult(1, 0.0), PathResult(2, 0.0), PathResult(3, 0.0)]
+        ];
+
+        // Get the result
+        let scores = betweenness(&matrix, &paths, false).unwrap();
+        let normalized = betweenness(&matrix, &paths, true).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(validate_centrality(&matrix), None);
+        assert!(validate_centrality(&Matrix { forbidden_edges: vec![[0, 1]], ..matrix.clone() }).is_some());
+        assert!(validate_centrality(&Matrix { max_hops: Some(1), ..matrix.clone() }).is_some());
+        assert_eq!(scores, vec![0.0, 0.5, 0.5, 0.0]);
+        assert_eq!(normalized, vec![0.0, 0.5 / 6.0, 0.5 / 6.0, 0.0]);
+    }
+}
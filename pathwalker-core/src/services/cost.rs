@@ -0,0 +1,348 @@
+/// The maximum length of the source of a cost function
+pub const MAX_COST_LENGTH: usize = 256;
+
+/// The maximum nesting of the expressions of a cost function
+const MAX_COST_DEPTH: usize = 16;
+
+/// The functions that can be called from a cost function
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CostCall {
+    Min,
+    Max,
+    Abs,
+    Sqrt,
+}
+
+/// The compiled expression of a cost function
+#[derive(Debug, Clone, PartialEq)]
+enum CostExpr {
+    Number(f32),
+    Attribute(usize),
+    Negate(Box<CostExpr>),
+    Binary(char, Box<CostExpr>, Box<CostExpr>),
+    Call(CostCall, Vec<CostExpr>),
+}
+
+/// The tokens of the source of a cost function
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Symbol(char),
+}
+
+/// Split the source of a cost function in tokens
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+
+    while let Some(&current) = chars.peek() {
+        if current.is_whitespace() {
+            chars.next();
+        } else if current.is_ascii_digit() || current == '.' {
+            let mut number = String::new();
+
+            while let Some(&digit) = chars.peek().filter(|digit| digit.is_ascii_digit() || **digit == '.') {
+                number.push(digit);
+                chars.next();
+            }
+
+            tokens.push(Token::Number(number.parse().map_err(|_| format!("The number {} is not valid", number))?));
+        } else if current.is_ascii_alphabetic() || current == '_' {
+            let mut name = String::new();
+
+            while let Some(&letter) = chars.peek().filter(|letter| letter.is_ascii_alphanumeric() || **letter == '_') {
+                name.push(letter);
+                chars.next();
+            }
+
+            tokens.push(Token::Name(name));
+        } else if "+-*/^(),".contains(current) {
+            tokens.push(Token::Symbol(current));
+            chars.next();
+        } else {
+            return Err(format!("The character {} is not allowed", current));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// The recursive descent parser of the cost functions
+///
+/// # Fields
+///
+/// * `tokens` - The tokens of the source
+/// * `position` - The position of the next token
+/// * `attributes` - The names of the edge attributes, by index
+/// * `depth` - The nesting of the expression being parsed
+///
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    attributes: &'a [String],
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// Take the next token if it's the given symbol
+    fn accept(&mut self, symbol: char) -> bool {
+        if self.tokens.get(self.position) == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Take the next token, which must be the given symbol
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.accept(symbol) {
+            Ok(())
+        } else {
+            Err(format!("Expected {} at token {}", symbol, self.position + 1))
+        }
+    }
+
+    /// Parse a sum or a subtraction
+    fn expression(&mut self) -> Result<CostExpr, String> {
+        self.depth += 1;
+
+        if self.depth > MAX_COST_DEPTH {
+            return Err("The cost function is nested too deeply".to_string());
+        }
+
+        let mut expr = self.term()?;
+
+        loop {
+            let operator = if self.accept('+') { '+' } else if self.accept('-') { '-' } else { break };
+            expr = CostExpr::Binary(operator, Box::new(expr), Box::new(self.term()?));
+        }
+
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    /// Parse a product or a division
+    fn term(&mut self) -> Result<CostExpr, String> {
+        let mut expr = self.unary()?;
+
+        loop {
+            let operator = if self.accept('*') { '*' } else if self.accept('/') { '/' } else { break };
+            expr = CostExpr::Binary(operator, Box::new(expr), Box::new(self.unary()?));
+        }
+
+        Ok(expr)
+    }
+
+    /// Parse a negation or a power
+    fn unary(&mut self) -> Result<CostExpr, String> {
+        if self.accept('-') {
+            return Ok(CostExpr::Negate(Box::new(self.unary()?)));
+        }
+
+        let base = self.atom()?;
+
+        if self.accept('^') {
+            Ok(CostExpr::Binary('^', Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// Parse a number, an attribute, a call or a parenthesized expression
+    fn atom(&mut self) -> Result<CostExpr, String> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+
+        match token {
+            Some(Token::Number(value)) => Ok(CostExpr::Number(value)),
+            Some(Token::Symbol('(')) => {
+                let expr = self.expression()?;
+                self.expect(')')?;
+                Ok(expr)
+            },
+            Some(Token::Name(name)) if self.accept('(') => {
+                let call = match name.as_str() {
+                    "min" => CostCall::Min,
+                    "max" => CostCall::Max,
+                    "abs" => CostCall::Abs,
+                    "sqrt" => CostCall::Sqrt,
+                    _ => return Err(format!("The function {} doesn't exist", name))
+                };
+
+                let mut args = vec![self.expression()?];
+
+                while self.accept(',') {
+                    args.push(self.expression()?);
+                }
+
+                self.expect(')')?;
+
+                match (call, args.len()) {
+                    (CostCall::Abs | CostCall::Sqrt, 1) | (CostCall::Min | CostCall::Max, 2..) => Ok(CostExpr::Call(call, args)),
+                    _ => Err(format!("The function {} can't take {} arguments", name, args.len()))
+                }
+            },
+            Some(Token::Name(name)) => self.attributes.iter()
+                .position(|attribute| *attribute == name)
+                .map(CostExpr::Attribute)
+                .ok_or_else(|| format!("The attribute {} doesn't exist", name)),
+            Some(Token::Symbol(symbol)) => Err(format!("Unexpected {} at token {}", symbol, self.position)),
+            None => Err("The cost function ends unexpectedly".to_string())
+        }
+    }
+}
+
+/// A cost function combining the attributes of an edge into its weight
+///
+/// The functions are written in a restricted expression language, with
+/// numbers, attribute names, the `+ - * / ^` operators, parentheses and the
+/// `min`, `max`, `abs` and `sqrt` functions, so they always terminate and
+/// can't reach anything outside of the edge.
+///
+/// # Fields
+///
+/// * `expr` - The compiled expression
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostFunction {
+    expr: CostExpr,
+}
+
+impl CostFunction {
+    /// Validate and compile a cost function
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source of the cost function
+    /// * `attributes` - The names of the edge attributes, in the order their values are given
+    ///
+    /// # Returns
+    ///
+    /// * `Result<CostFunction, String>` - The cost function, or the reason why it isn't valid
+    ///
+    pub fn compile(source: &str, attributes: &[String]) -> Result<CostFunction, String> {
+        if source.len() > MAX_COST_LENGTH {
+            return Err(format!("The cost function is longer than {} characters", MAX_COST_LENGTH));
+        }
+
+        let mut parser = Parser { tokens: tokenize(source)?, position: 0, attributes, depth: 0 };
+        let expr = parser.expression()?;
+
+        if parser.position < parser.tokens.len() {
+            return Err(format!("Unexpected input at token {}", parser.position + 1));
+        }
+
+        Ok(CostFunction { expr })
+    }
+
+    /// Compute the weight of an edge
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - The values of the attributes of the edge
+    ///
+    /// # Returns
+    ///
+    /// * `f32` - The weight of the edge
+    ///
+    pub fn evaluate(&self, values: &[f32]) -> f32 {
+        evaluate(&self.expr, values)
+    }
+}
+
+/// Compute the value of an expression
+fn evaluate(expr: &CostExpr, values: &[f32]) -> f32 {
+    match expr {
+        CostExpr::Number(value) => *value,
+        CostExpr::Attribute(index) => values[*index],
+        CostExpr::Negate(expr) => -evaluate(expr, values),
+        CostExpr::Binary(operator, left, right) => {
+            let (left, right) = (evaluate(left, values), evaluate(right, values));
+
+            match operator {
+                '+' => left + right,
+                '-' => left - right,
+                '*' => left * right,
+                '/' => left / right,
+                _ => left.powf(right)
+            }
+        },
+        CostExpr::Call(call, args) => {
+            let mut args = args.iter().map(|arg| evaluate(arg, values));
+
+            match call {
+                CostCall::Min => args.fold(f32::INFINITY, f32::min),
+                CostCall::Max => args.fold(f32::NEG_INFINITY, f32::max),
+                CostCall::Abs => args.next().unwrap_or_default().abs(),
+                CostCall::Sqrt => args.next().unwrap_or_default().sqrt()
+            }
+        }
+    }
+}
+
+/// Compute the weights of a matrix from the attributes of its edges
+///
+/// An edge exists where any of its attributes isn't zero, and the weights
+/// of the missing edges are left as zero, so the kernels skip them.
+///
+/// # Arguments
+///
+/// * `function` - The cost function
+/// * `width` - The number of vertices of the matrix
+/// * `attributes` - The values of every attribute, laid out as the matrix
+///
+/// # Returns
+///
+/// * `Result<Vec<f32>, String>` - The weights, or the reason why an edge has no valid weight
+///
+pub fn apply_cost(function: &CostFunction, width: usize, attributes: &[&[f32]]) -> Result<Vec<f32>, String> {
+    let size = attributes.first().map_or(0, |values| values.len());
+    let mut values = vec![0.0; attributes.len()];
+
+    (0..size).map(|index| {
+        for (value, attribute) in values.iter_mut().zip(attributes) {
+            *value = attribute[index];
+        }
+
+        if values.iter().all(|value| *value == 0.0) {
+            return Ok(0.0);
+        }
+
+        // Reject the weights the kernels would read as missing edges
+        let weight = function.evaluate(&values);
+
+        if weight.is_finite() && weight > 0.0 && weight != f32::MAX {
+            Ok(weight)
+        } else {
+            Err(format!("The cost of the edge from {} to {} is {}, but it must be a positive number", index % width, index / width, weight))
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cost_function() {
+        // Prepare the attributes
+        let names = vec!["distance".to_string(), "toll".to_string()];
+        let distance = [0.0, 4.0, 4.0, 0.0];
+        let toll = [0.0, 0.5, 0.0, 0.0];
+
+        // Get the result
+        let function = CostFunction::compile("distance + 2 * max(toll, 0.25) ^ 2", &names).unwrap();
+        let weights = apply_cost(&function, 2, &[&distance, &toll]).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(weights, vec![0.0, 4.5, 4.125, 0.0]);
+        assert_eq!(CostFunction::compile("-distance", &names).map(|function| apply_cost(&function, 2, &[&distance, &toll])),
+            Ok(Err("The cost of the edge from 1 to 0 is -4, but it must be a positive number".to_string())));
+        assert_eq!(CostFunction::compile("speed * 2", &names), Err("The attribute speed doesn't exist".to_string()));
+        assert_eq!(CostFunction::compile("exec(distance)", &names), Err("The function exec doesn't exist".to_string()));
+        assert!(CostFunction::compile("(distance", &names).is_err());
+        assert!(CostFunction::compile(&"(".repeat(20), &names).is_err());
+    }
+}
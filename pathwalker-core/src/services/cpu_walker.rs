@@ -0,0 +1,249 @@
+use rayon::prelude::*;
+use tracing::debug_span;
+
+use crate::models::{Matrix, PathResult};
+use crate::services::backend::{Backend, BackendKind};
+use crate::services::cancellation::Cancellation;
+use crate::services::metrics::count_walked_vertices;
+
+/// The vertices settled between the checks of the cancellation
+const VERTICES_PER_CHECK: usize = 32;
+
+/// Relax the edges of every vertex from the distances of the previous round
+///
+/// Every round extends the paths by one edge, so after `rounds` rounds the
+/// distances are the ones of the paths of up to that many edges, the same
+/// way the kernels bound the hops of the walks. The rounds stop early once
+/// one improves no distance.
+///
+/// # Arguments
+///
+/// * `width` - The number of vertices
+/// * `source` - The vertex the walk starts from
+/// * `rounds` - The most edges of a path
+/// * `weight` - The weight of the edge going from a vertex reached at a distance to another, if there is one
+/// * `cancellation` - Aborts the walk between rounds
+///
+/// # Returns
+///
+/// * `Result<(Vec<f32>, Vec<i32>), String>` - The distance and the predecessor of every vertex, or why the walk has been aborted
+///
+fn relax_rounds(width: usize, source: usize, rounds: usize, weight: impl Fn(usize, usize, f32) -> Option<f32> + Sync, cancellation: &Cancellation) -> Result<(Vec<f32>, Vec<i32>), String> {
+    let mut distances = vec![f32::INFINITY; width];
+    let mut predecessors = vec![source as i32; width];
+    distances[source] = 0.0;
+
+    for _ in 0..rounds {
+        cancellation.check()?;
+        let previous = distances.clone();
+
+        // Every target is written by one thread, reading only the distances of the previous round
+        distances.par_iter_mut().zip(predecessors.par_iter_mut()).enumerate().for_each(|(target, (best, predecessor))| {
+            for (vertex, distance) in previous.iter().enumerate().filter(|(_, distance)| distance.is_finite()) {
+                if let Some(weight) = weight(vertex, target, *distance).filter(|weight| distance + weight < *best) {
+                    *best = distance + weight;
+                    *predecessor = vertex as i32;
+                }
+            }
+        });
+
+        if distances == previous {
+            break;
+        }
+    }
+
+    Ok((distances, predecessors))
+}
+
+/// The walker of the CPU backend
+///
+/// Walks the graphs with Dijkstra over the dense matrix, picking the
+/// closest vertex and relaxing its edges in the threads of the rayon pool.
+/// It needs no device, so it's always available, and for the small graphs
+/// it returns before a device would have received the matrix. Dijkstra
+/// can't handle negative weights, so the matrices with any are rejected.
+/// The edges of the time dependent matrices weigh what their time bucket
+/// says at the time they are reached, the departure plus the distance of
+/// their source, which gives the fastest paths as long as leaving later
+/// never arrives earlier.
+///
+/// # Fields
+///
+/// * `backend_version` - The versions of the server and the walker
+///
+#[derive(Debug, Clone)]
+pub struct CpuWalker {
+    backend_version: String,
+}
+
+impl Default for CpuWalker {
+    fn default() -> CpuWalker {
+        CpuWalker::new()
+    }
+}
+
+impl CpuWalker {
+    /// Create the walker of the CPU
+    pub fn new() -> CpuWalker {
+        CpuWalker { backend_version: format!("path_walker {}; cpu dijkstra", env!("CARGO_PKG_VERSION")) }
+    }
+}
+
+impl Backend for CpuWalker {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Cpu
+    }
+
+    fn device_name(&self) -> String {
+        format!("CPU ({} threads)", rayon::current_num_threads())
+    }
+
+    fn backend_version(&self) -> &str {
+        &self.backend_version
+    }
+
+    fn probe(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn walk_matrix(&self, matrix: &Matrix, source: usize, cancellation: &Cancellation) -> Result<Vec<PathResult>, String> {
+        let _span = debug_span!("cpu_walk", width = matrix.width, source).entered();
+        let (width, zero_edges) = (matrix.width, matrix.zero_edges);
+        let departure = matrix.departure_time.unwrap_or_default();
+        let buckets = match matrix.is_time_dependent() {
+            true => matrix.time_buckets.iter().map(|bucket| matrix.directed_weights(bucket)).collect(),
+            false => vec![matrix.directed_data()]
+        };
+
+        // Check that the weights can be walked with Dijkstra
+        if buckets.iter().flat_map(|data| data.iter()).any(|weight| *weight < 0.0) {
+            return Err("The CPU walker can't walk negative weights".to_string());
+        }
+
+        // Account the vertices in the work of the thread
+        count_walked_vertices(width);
+
+        // Read the edges in the bucket of the time their source is reached at
+        let weight = |source: usize, target: usize, distance: f32| {
+            let weight = buckets[matrix.bucket_at(departure + distance as f64)][target * width + source];
+            ((weight != 0.0 || zero_edges) && weight != f32::MAX).then_some(weight)
+        };
+
+        // Mark the unreachable vertices like the kernels do
+        let unreachable = if zero_edges { f32::MAX } else { 0.0 };
+        let path = |distances: Vec<f32>, predecessors: Vec<i32>| distances.into_iter().zip(predecessors)
+            .map(|(distance, predecessor)| if distance.is_finite() { PathResult(predecessor, distance) } else { PathResult(source as i32, unreachable) })
+            .collect();
+
+        // Bound the hops of the paths with rounds of relaxations, as Dijkstra can't count them
+        if matrix.max_hops.is_some() {
+            return relax_rounds(width, source, matrix.walk_rounds(), weight, cancellation).map(|(distances, predecessors)| path(distances, predecessors));
+        }
+
+        // Settle the closest vertex not settled yet, until the rest are unreachable
+        let mut distances = vec![f32::INFINITY; width];
+        let mut predecessors = vec![source as i32; width];
+        let mut settled = vec![false; width];
+        distances[source] = 0.0;
+
+        for round in 0..width {
+            if round % VERTICES_PER_CHECK == 0 {
+                cancellation.check()?;
+            }
+
+            let closest = (0..width).into_par_iter()
+                .filter(|vertex| !settled[*vertex] && distances[*vertex].is_finite())
+                .min_by(|a, b| distances[*a].total_cmp(&distances[*b]).then(a.cmp(b)));
+
+            let Some(vertex) = closest else {
+                break;
+            };
+
+            settled[vertex] = true;
+            let distance = distances[vertex];
+
+            // Relax the edges of the vertex in parallel, as every target is written by one thread
+            distances.par_iter_mut().zip(predecessors.par_iter_mut()).enumerate()
+                .filter(|(target, _)| !settled[*target])
+                .for_each(|(target, (best, predecessor))| {
+                    if let Some(weight) = weight(vertex, target, distance).filter(|weight| distance + weight < *best) {
+                        *best = distance + weight;
+                        *predecessor = vertex as i32;
+                    }
+                });
+        }
+
+        Ok(path(distances, predecessors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_walk() {
+        // Prepare a chain of four vertices with a shortcut from the first to the last, and an isolated vertex
+        let walker = CpuWalker::new();
+        let mut matrix = Matrix::new(5, 5, vec![0.0; 25]);
+        for (source, target, weight) in [(0, 1, 1.0), (1, 2, 1.0), (2, 3, 1.0), (0, 3, 5.0)] {
+            matrix.data[target * 5 + source] = weight;
+        }
+
+        // Get the result
+        let path = walker.walk_matrix(&matrix, 0, &Cancellation::default()).unwrap();
+        matrix.data[1] = -1.0;
+        let negative = walker.walk_matrix(&matrix, 0, &Cancellation::default());
+
+        // Check if the result is correct
+        assert_eq!(path, vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(1, 2.0), PathResult(2, 3.0), PathResult(0, 0.0)]);
+        assert!(negative.is_err());
+    }
+
+    #[test]
+    fn test_cpu_constrained_walk() {
+        // Prepare a chain of four vertices with a shortcut from the first to the last
+        let walker = CpuWalker::new();
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        for (source, target, weight) in [(0, 1, 1.0), (1, 2, 1.0), (2, 3, 1.0), (0, 3, 5.0)] {
+            matrix.data[target * 4 + source] = weight;
+        }
+
+        // Get the result
+        let bounded = walker.walk_matrix(&Matrix { max_hops: Some(2), ..matrix.clone() }, 0, &Cancellation::default()).unwrap();
+        let detour = walker.walk_matrix(&Matrix { forbidden_nodes: vec![2], ..matrix.clone() }, 0, &Cancellation::default()).unwrap();
+        let cut = walker.walk_matrix(&Matrix { forbidden_edges: vec![[0, 3]], max_hops: Some(2), ..matrix }, 0, &Cancellation::default()).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(bounded, vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(1, 2.0), PathResult(0, 5.0)]);
+        assert_eq!(detour, vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(0, 0.0), PathResult(0, 5.0)]);
+        assert_eq!(cut[3], PathResult(0, 0.0));
+    }
+
+    #[test]
+    fn test_cpu_time_dependent_walk() {
+        // Prepare a road from the first vertex to the last and a faster detour through the middle one, both jammed in the second hour
+        let walker = CpuWalker::new();
+        let mut free = vec![0.0; 9];
+        for (source, target, weight) in [(0, 2, 1800.0), (0, 1, 600.0), (1, 2, 600.0)] {
+            free[target * 3 + source] = weight;
+        }
+        let mut jammed = free.clone();
+        jammed[2 * 3] = 7200.0;
+        jammed[2 * 3 + 1] = 1500.0;
+        let matrix = Matrix { time_buckets: vec![free, jammed], ..Matrix::new(3, 3, Vec::new()) };
+
+        // Get the result, departing at night, when the detour gets jammed on the way, in the rush hour and the next night
+        let walks = [0.0, 3300.0, 3600.0, 7200.0].map(|departure| {
+            let mut matrix = Matrix { departure_time: Some(departure), ..matrix.clone() };
+            matrix.fill_departure_weights().unwrap();
+            walker.walk_matrix(&matrix, 0, &Cancellation::default()).unwrap()
+        });
+
+        // Check if the result is correct
+        assert_eq!(walks[0][2], PathResult(1, 1200.0));
+        assert_eq!(walks[1][2], PathResult(0, 1800.0));
+        assert_eq!(walks[2][2], PathResult(1, 2100.0));
+        assert_eq!(walks[3], walks[0]);
+    }
+}
@@ -0,0 +1,209 @@
+use std::panic::catch_unwind;
+use std::sync::Arc;
+
+use cudarc::driver::{CudaContext, CudaFunction, CudaStream, LaunchConfig, PushKernelArg};
+use cudarc::nvrtc::compile_ptx;
+use log::{info, trace, warn};
+use sha2::{Digest, Sha256};
+use tracing::debug_span;
+
+use crate::models::{Matrix, PathResult};
+use crate::services::backend::{Backend, BackendKind};
+use crate::services::cancellation::Cancellation;
+use crate::services::metrics::{add_device_memory, QueuedWalk};
+use crate::services::shutdown::KERNEL_EXECUTIONS;
+
+/// The source of the walk kernels
+const WALK_KERNELS: &str = include_str!("../kernels/walk.cu");
+
+/// The threads of every block of the kernels
+const BLOCK_SIZE: u32 = 256;
+
+/// The iterations launched between the checks of the cancellation
+const ITERATIONS_PER_SYNC: usize = 32;
+
+/// The walker of the CUDA backend
+///
+/// Runs the weighted walk of the OpenCL kernels as CUDA kernels compiled
+/// with NVRTC when the walker is created, in the first NVIDIA device. The
+/// driver is loaded at runtime, so the servers built with the feature still
+/// start in the hosts without it. Only the single precision walks are
+/// ported, and the other algorithms stay in the OpenCL walker.
+///
+/// # Fields
+///
+/// * `stream` - The stream the kernels are launched in
+/// * `initialize` - The kernel initializing the buffers
+/// * `iterate` - The kernel relaxing the edges of the vertices not visited
+/// * `merge` - The kernel merging the relaxed distances
+/// * `device_name` - The name of the device
+/// * `backend_version` - The versions of the server, the kernels and the device
+///
+pub struct CudaWalker {
+    stream: Arc<CudaStream>,
+    initialize: CudaFunction,
+    iterate: CudaFunction,
+    merge: CudaFunction,
+    device_name: String,
+    backend_version: String,
+}
+
+impl CudaWalker {
+    /// Check if the CUDA driver is installed and sees an NVIDIA device
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If a walker can be created
+    ///
+    pub fn is_available() -> bool {
+        // Loading the symbols of a broken driver panics, so it counts as missing
+        let present = unsafe { cudarc::driver::sys::is_culib_present() };
+
+        present && catch_unwind(CudaContext::device_count).is_ok_and(|count| count.is_ok_and(|count| count > 0))
+    }
+
+    /// Open the first NVIDIA device and compile the walk kernels for it
+    ///
+    /// # Returns
+    ///
+    /// * `Result<CudaWalker, String>` - The walker, or why the device can't be set up
+    ///
+    pub fn new() -> Result<CudaWalker, String> {
+        // Open the device, without letting a missing driver panic
+        trace!("Initializing CUDA components before operations...");
+
+        if !CudaWalker::is_available() {
+            return Err("No CUDA device can be found".to_string());
+        }
+
+        let context = CudaContext::new(0).map_err(|err| format!("The CUDA device can't be opened: {}", err))?;
+        let device_name = context.name().unwrap_or_default();
+        let (major, minor) = context.compute_capability().unwrap_or_default();
+        info!("Using CUDA device: {} (compute capability {}.{})", device_name, major, minor);
+
+        // Compile the kernels for the device
+        let ptx = compile_ptx(WALK_KERNELS).map_err(|err| format!("The CUDA kernels can't be compiled: {}", err))?;
+        let module = context.load_module(ptx).map_err(|err| format!("The CUDA kernels can't be loaded: {}", err))?;
+        let function = |name: &str| module.load_function(name).map_err(|err| format!("The CUDA kernel {} can't be loaded: {}", name, err));
+
+        // Name the versions of everything that can change the results
+        let backend_version = format!(
+            "path_walker {}; cuda kernels {}; {} (compute capability {}.{})",
+            env!("CARGO_PKG_VERSION"),
+            &hex::encode(Sha256::digest(WALK_KERNELS.as_bytes()))[..16],
+            device_name,
+            major,
+            minor
+        );
+
+        Ok(CudaWalker {
+            stream: context.default_stream(),
+            initialize: function("initialize_algorithm_buffers")?,
+            iterate: function("shortest_path_algorithm")?,
+            merge: function("merge_sortest_path")?,
+            device_name,
+            backend_version
+        })
+    }
+}
+
+impl Backend for CudaWalker {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Cuda
+    }
+
+    fn device_name(&self) -> String {
+        self.device_name.clone()
+    }
+
+    fn backend_version(&self) -> &str {
+        &self.backend_version
+    }
+
+    fn probe(&self) -> Result<(), String> {
+        // Walk a chain of two vertices, which runs every kernel
+        let path = self.walk_matrix(&Matrix::new(2, 2, vec![0.0, 0.0, 1.0, 0.0]), 0, &Cancellation::default())?;
+
+        if path == [PathResult(0, 0.0), PathResult(0, 1.0)] {
+            Ok(())
+        } else {
+            Err(format!("The CUDA kernels have returned {:?}", path))
+        }
+    }
+
+    fn walk_matrix(&self, matrix: &Matrix, source: usize, cancellation: &Cancellation) -> Result<Vec<PathResult>, String> {
+        // Print the initialization
+        let width = matrix.width;
+        let _span = debug_span!("cuda_walk", width, source).entered();
+        trace!("Initializing buffers for the kernels...");
+
+        // Count the walk in the queue depth and keep the shutdown waiting until it finishes
+        let _queued = QueuedWalk::start(width);
+        let _execution = KERNEL_EXECUTIONS.start();
+
+        // Upload the matrix and allocate the buffers of the walk, which the init kernel fills
+        let device_error = |err: cudarc::driver::DriverError| format!("The CUDA device has failed: {}", err);
+        let matrix_buffer = self.stream.clone_htod(&*matrix.directed_data()).map_err(device_error)?;
+        let result = self.stream.alloc_zeros::<f32>(width).map_err(device_error)?;
+        let distance = self.stream.alloc_zeros::<f32>(width).map_err(device_error)?;
+        let visited = self.stream.alloc_zeros::<i32>(width).map_err(device_error)?;
+        let vertex = self.stream.alloc_zeros::<i32>(width).map_err(device_error)?;
+        let vertex_temp = self.stream.alloc_zeros::<i32>(width).map_err(device_error)?;
+
+        // Account the buffers of the walk
+        let bytes = ((matrix.data.len() + 5 * width) * 4) as i64;
+        add_device_memory(bytes);
+
+        let path = (|| {
+            let (source, zero_edges, vertex_count) = (source as i32, matrix.zero_edges as i32, width as i32);
+            let config = LaunchConfig { grid_dim: ((width as u32).div_ceil(BLOCK_SIZE), 1, 1), block_dim: (BLOCK_SIZE, 1, 1), shared_mem_bytes: 0 };
+
+            unsafe {
+                self.stream.launch_builder(&self.initialize)
+                    .arg(&result).arg(&distance).arg(&visited).arg(&vertex).arg(&vertex_temp)
+                    .arg(&source).arg(&zero_edges).arg(&vertex_count)
+                    .launch(config).map_err(device_error)?;
+            }
+
+            // Run the algorithm, waiting for the device only once per batch of iterations, up to the hops of the paths
+            let (mut iteration, rounds) = (0, matrix.walk_rounds());
+
+            while iteration < rounds {
+                // Give up before launching more kernels when the walk has been cancelled
+                if let Err(message) = cancellation.check() {
+                    warn!("Aborting the walk at iteration {} of {}: {}", iteration, rounds, message);
+                    return Err(message);
+                }
+
+                let batch = ITERATIONS_PER_SYNC.min(rounds - iteration);
+
+                for _ in 0..batch {
+                    unsafe {
+                        self.stream.launch_builder(&self.iterate)
+                            .arg(&result).arg(&matrix_buffer).arg(&distance).arg(&visited).arg(&vertex_temp)
+                            .arg(&vertex_count).arg(&zero_edges)
+                            .launch(config).map_err(device_error)?;
+                        self.stream.launch_builder(&self.merge)
+                            .arg(&result).arg(&distance).arg(&visited).arg(&vertex).arg(&vertex_temp)
+                            .arg(&source).arg(&vertex_count)
+                            .launch(config).map_err(device_error)?;
+                    }
+                }
+
+                self.stream.synchronize().map_err(device_error)?;
+                iteration += batch;
+            }
+
+            // Copy the predecessors and the distances to the host once the walk is complete
+            let vertex = self.stream.clone_dtoh(&vertex).map_err(device_error)?;
+            let distance = self.stream.clone_dtoh(&distance).map_err(device_error)?;
+
+            Ok(vertex.into_iter().zip(distance).map(|(vertex, distance)| PathResult(vertex, distance)).collect::<Vec<PathResult>>())
+        })();
+
+        // Release the buffers of the walk from the accounting
+        add_device_memory(-bytes);
+
+        path
+    }
+}
@@ -0,0 +1,175 @@
+use crate::models::{DagPaths, DagRequest, Matrix, Objective};
+use crate::solver::validate_static_matrix;
+
+/// Validate a request of the paths of a directed acyclic graph
+///
+/// # Arguments
+///
+/// * `request` - The request
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the request isn't valid, if any
+///
+pub fn validate_dag(request: &DagRequest) -> Option<&'static str> {
+    if let Some(message) = validate_static_matrix(&request.matrix) {
+        Some(message)
+    } else if !request.matrix.directed {
+        Some("The graph must be directed, as every undirected edge is a cycle")
+    } else if request.source >= request.matrix.width || request.target.is_some_and(|target| target >= request.matrix.width) {
+        Some("The vertices of the request are outside of the graph")
+    } else {
+        None
+    }
+}
+
+/// Sort the vertices of a graph in topological order
+///
+/// Every vertex comes after all the vertices with an edge to it, taking the
+/// vertices without pending edges in increasing order. The loops are left
+/// out, as in the walks, so the weights of the diagonal don't make a cycle.
+///
+/// # Arguments
+///
+/// * `matrix` - The validated matrix
+///
+/// # Returns
+///
+/// * `Result<Vec<usize>, String>` - The vertices in topological order, or the cycle that prevents it
+///
+pub fn topological_order(matrix: &Matrix) -> Result<Vec<usize>, String> {
+    let width = matrix.width;
+    let edges = |source: usize| (0..width).filter(move |target| *target != source && matrix.edge(source, *target).is_some());
+    let mut pending = vec![0; width];
+
+    for source in 0..width {
+        for target in edges(source) {
+            pending[target] += 1;
+        }
+    }
+
+    let mut order: Vec<usize> = Vec::with_capacity(width);
+    let mut ready: Vec<usize> = (0..width).rev().filter(|vertex| pending[*vertex] == 0).collect();
+
+    while let Some(vertex) = ready.pop() {
+        order.push(vertex);
+
+        for target in edges(vertex).collect::<Vec<_>>().into_iter().rev() {
+            pending[target] -= 1;
+
+            if pending[target] == 0 {
+                ready.push(target);
+            }
+        }
+    }
+
+    match (0..width).find(|vertex| pending[*vertex] > 0) {
+        Some(vertex) => Err(format!("The graph isn't acyclic, vertex {} is part of a cycle or only reachable through one", vertex)),
+        None => Ok(order)
+    }
+}
+
+/// Find the shortest or the longest paths of a directed acyclic graph
+///
+/// The vertices are relaxed in topological order, so every distance is final
+/// before the edges leaving it are followed, which works whatever the sign of
+/// the weights and for the longest paths, unlike the walks of the kernels.
+///
+/// # Arguments
+///
+/// * `request` - The validated request
+///
+/// # Returns
+///
+/// * `Result<DagPaths, String>` - The paths from the source, or the cycle that prevents finding them
+///
+pub fn dag_paths(request: &DagRequest) -> Result<DagPaths, String> {
+    let matrix = &request.matrix;
+    let order = topological_order(matrix)?;
+    let mut distances = vec![None; matrix.width];
+    let mut predecessors = vec![None; matrix.width];
+    distances[request.source] = Some(0.0);
+
+    for &vertex in &order {
+        let Some(distance) = distances[vertex] else {
+            continue;
+        };
+
+        for target in (0..matrix.width).filter(|target| *target != vertex) {
+            let Some(weight) = matrix.edge(vertex, target) else {
+                continue;
+            };
+            let candidate = distance + weight;
+            let better = match (distances[target], request.objective) {
+                (None, _) => true,
+                (Some(current), Objective::Shortest) => candidate < current,
+                (Some(current), Objective::Longest) => candidate > current
+            };
+
+            if better {
+                distances[target] = Some(candidate);
+                predecessors[target] = Some(vertex);
+            }
+        }
+    }
+
+    Ok(DagPaths { order, distances, predecessors })
+}
+
+impl DagPaths {
+    /// Reconstruct the route from the source to a vertex, following the predecessors
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The vertex to reach
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<usize>>` - The vertices from the source to the target, if it's reachable
+    ///
+    pub fn route(&self, target: usize) -> Option<Vec<usize>> {
+        self.distances.get(target)?.as_ref()?;
+
+        let mut route = vec![target];
+
+        while let Some(predecessor) = self.predecessors[*route.last()?] {
+            route.push(predecessor);
+        }
+
+        route.reverse();
+        Some(route)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dag_paths() {
+        // Prepare a project whose tasks 1 and 2 follow task 0 and precede task 3, with a loop on the diagonal
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        matrix.set_edge(0, 0, 9.0);
+        matrix.set_edge(0, 1, 3.0);
+        matrix.set_edge(0, 2, 1.0);
+        matrix.set_edge(1, 3, 2.0);
+        matrix.set_edge(2, 3, -1.0);
+        let request = DagRequest { matrix: matrix.clone(), source: 0, target: Some(3), objective: Objective::Longest };
+        let mut cyclic = matrix.clone();
+        cyclic.set_edge(3, 1, 1.0);
+
+        // Get the result
+        let longest = dag_paths(&request).unwrap();
+        let shortest = dag_paths(&DagRequest { objective: Objective::Shortest, ..request.clone() }).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(validate_dag(&request), None);
+        assert_eq!(longest.order, vec![0, 1, 2, 3]);
+        assert_eq!(longest.distances, vec![Some(0.0), Some(3.0), Some(1.0), Some(5.0)]);
+        assert_eq!(longest.route(3), Some(vec![0, 1, 3]));
+        assert_eq!(shortest.distances[3], Some(0.0));
+        assert_eq!(shortest.route(3), Some(vec![0, 2, 3]));
+        assert_eq!(dag_paths(&DagRequest { source: 3, ..request }).unwrap().route(0), None);
+        assert_eq!(topological_order(&cyclic), Err("The graph isn't acyclic, vertex 1 is part of a cycle or only reachable through one".to_string()));
+    }
+}
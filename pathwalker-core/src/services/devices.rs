@@ -0,0 +1,334 @@
+use std::env;
+use std::fs;
+#[cfg(feature = "opencl")]
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "opencl")]
+use ocl::{core, enums::{DeviceInfo, DeviceInfoResult}, Device, Platform};
+use serde::Serialize;
+
+#[cfg(feature = "opencl")]
+use crate::services::memory::global_memory;
+
+/// The environment variable overriding the directory of the installable client drivers
+pub const ICD_VENDORS_VARIABLE: &str = "OCL_ICD_VENDORS";
+
+/// The directory where the OpenCL loader looks for the installable client drivers
+const DEFAULT_ICD_DIR: &str = "/etc/OpenCL/vendors";
+
+/// The directories searched for the libraries of the drivers named without a path
+const LIBRARY_DIRS: [&str; 6] = ["/usr/lib", "/usr/lib64", "/usr/local/lib", "/usr/lib/x86_64-linux-gnu", "/usr/lib/aarch64-linux-gnu", "/lib/x86_64-linux-gnu"];
+
+/// An installable client driver registered in the OpenCL loader
+///
+/// # Fields
+///
+/// * `file` - The path of the `.icd` file registering the driver
+/// * `library` - The library of the driver named in the file
+/// * `found` - If the library of the driver has been found
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IcdEntry {
+    pub file: String,
+    pub library: String,
+    pub found: bool,
+}
+
+/// A device of an OpenCL platform
+///
+/// # Fields
+///
+/// * `name` - The name of the device
+/// * `vendor` - The vendor of the device
+/// * `kind` - The type of the device, like `GPU` or `CPU`
+/// * `version` - The OpenCL version supported by the device
+/// * `driver_version` - The version of the driver of the device
+/// * `compute_units` - The parallel compute units of the device, if the driver reports them
+/// * `max_work_group_size` - The most work items of a work group, if the driver reports them
+/// * `global_memory` - The bytes of global memory of the device, if the driver reports them
+/// * `selected` - If the walker runs its kernels in the device
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceReport {
+    pub name: String,
+    pub vendor: String,
+    pub kind: String,
+    pub version: String,
+    pub driver_version: String,
+    pub compute_units: Option<u32>,
+    pub max_work_group_size: Option<usize>,
+    pub global_memory: Option<u64>,
+    pub selected: bool,
+}
+
+/// An OpenCL platform, with its devices
+///
+/// # Fields
+///
+/// * `name` - The name of the platform
+/// * `vendor` - The vendor of the platform
+/// * `version` - The OpenCL version of the platform
+/// * `devices` - The devices of the platform
+/// * `error` - Why the devices of the platform can't be listed, if they can't
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlatformReport {
+    pub name: String,
+    pub vendor: String,
+    pub version: String,
+    pub devices: Vec<DeviceReport>,
+    pub error: Option<String>,
+}
+
+/// The diagnostics of the OpenCL drivers of the host
+///
+/// Enumerating the platforms of a host with a broken loader or driver fails
+/// with opaque errors, or even panics, so the enumeration is done step by
+/// step, recording what has been found along with hints to fix what hasn't.
+///
+/// # Fields
+///
+/// * `icd_dir` - The directory of the installable client drivers
+/// * `icds` - The installable client drivers registered in the directory
+/// * `platforms` - The platforms reported by the loader
+/// * `error` - Why the platforms can't be listed, if they can't
+/// * `hints` - The actions that may fix the drivers of the host
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceDiagnostics {
+    pub icd_dir: String,
+    pub icds: Vec<IcdEntry>,
+    pub platforms: Vec<PlatformReport>,
+    pub error: Option<String>,
+    pub hints: Vec<String>,
+}
+
+/// Check if the library of a driver can be found
+fn library_exists(library: &str) -> bool {
+    let path = Path::new(library);
+
+    if path.is_absolute() {
+        return path.exists();
+    }
+
+    env::var("LD_LIBRARY_PATH").unwrap_or_default().split(':')
+        .filter(|dir| !dir.is_empty())
+        .chain(LIBRARY_DIRS)
+        .any(|dir| Path::new(dir).join(library).exists())
+}
+
+/// List the installable client drivers registered in a directory
+fn list_icds(dir: &Path) -> Vec<IcdEntry> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "icd"))
+            .collect(),
+        Err(_) => vec![]
+    };
+    files.sort();
+
+    files.into_iter().map(|file| {
+        let library = fs::read_to_string(&file).unwrap_or_default().trim().to_string();
+        let found = !library.is_empty() && library_exists(&library);
+
+        IcdEntry { file: file.display().to_string(), library, found }
+    }).collect()
+}
+
+/// Describe a device, leaving empty what the driver can't report
+///
+/// # Arguments
+///
+/// * `device` - The device
+/// * `selected` - If the walker runs its kernels in the device
+///
+/// # Returns
+///
+/// * `DeviceReport` - The description of the device
+///
+#[cfg(feature = "opencl")]
+fn describe_device(device: Device, selected: bool) -> DeviceReport {
+    let info = |kind: DeviceInfo| device.info(kind).map(|value| value.to_string()).unwrap_or_default();
+
+    DeviceReport {
+        name: device.name().unwrap_or_default(),
+        vendor: device.vendor().unwrap_or_default(),
+        kind: info(DeviceInfo::Type),
+        version: info(DeviceInfo::Version),
+        driver_version: info(DeviceInfo::DriverVersion),
+        compute_units: match device.info(DeviceInfo::MaxComputeUnits) {
+            Ok(DeviceInfoResult::MaxComputeUnits(units)) => Some(units),
+            _ => None
+        },
+        max_work_group_size: match device.info(DeviceInfo::MaxWorkGroupSize) {
+            Ok(DeviceInfoResult::MaxWorkGroupSize(size)) => Some(size),
+            _ => None
+        },
+        global_memory: global_memory(device),
+        selected
+    }
+}
+
+/// Describe a platform and its devices, leaving empty what the driver can't report
+///
+/// # Arguments
+///
+/// * `platform` - The platform
+/// * `first` - If it's the first platform, whose first device the walker opens
+///
+/// # Returns
+///
+/// * `PlatformReport` - The description of the platform and its devices
+///
+#[cfg(feature = "opencl")]
+fn describe_platform(platform: Platform, first: bool) -> PlatformReport {
+    let (devices, error) = match core::get_device_ids(platform, None, None) {
+        Ok(ids) => (ids.into_iter().enumerate().map(|(index, id)| describe_device(Device::from(id), first && index == 0)).collect(), None),
+        Err(err) => (vec![], Some(err.to_string()))
+    };
+
+    PlatformReport {
+        name: platform.name().unwrap_or_default(),
+        vendor: platform.vendor().unwrap_or_default(),
+        version: platform.version().unwrap_or_default(),
+        devices,
+        error
+    }
+}
+
+/// Suggest the actions that may fix the drivers of the host
+///
+/// # Arguments
+///
+/// * `icds` - The installable client drivers found
+/// * `platforms` - The platforms reported by the loader
+/// * `error` - Why the platforms can't be listed, if they can't
+///
+/// # Returns
+///
+/// * `Vec<String>` - The hints, empty when a device is available
+///
+pub fn hints(icds: &[IcdEntry], platforms: &[PlatformReport], error: Option<&str>) -> Vec<String> {
+    let mut hints = vec![];
+
+    if platforms.iter().any(|platform| !platform.devices.is_empty()) {
+        return hints;
+    }
+
+    if icds.is_empty() {
+        hints.push(format!("No installable client driver is registered, install the OpenCL driver of the device (like nvidia-opencl-icd, intel-opencl-icd, mesa-opencl-icd or pocl-opencl-icd) or point {} to the directory of its .icd file", ICD_VENDORS_VARIABLE));
+    }
+
+    for icd in icds.iter().filter(|icd| !icd.found) {
+        hints.push(format!("The library {} registered in {} can't be found, reinstall its driver or add its directory to LD_LIBRARY_PATH", icd.library, icd.file));
+    }
+
+    if error.is_some() && !icds.is_empty() && icds.iter().all(|icd| icd.found) {
+        hints.push("The drivers are installed but the loader can't use them, check that they match the loader and the kernel module versions, and that the user can access /dev/dri or the device nodes of the vendor".to_string());
+    }
+
+    if error.is_none() && platforms.is_empty() && !icds.is_empty() {
+        hints.push("The loader has found no platform, check that the registered drivers support this host".to_string());
+    }
+
+    for platform in platforms.iter().filter(|platform| platform.devices.is_empty()) {
+        hints.push(format!("The platform {} has no devices, check that the device is plugged in and its kernel module is loaded", platform.name));
+    }
+
+    hints
+}
+
+impl DeviceDiagnostics {
+    /// Enumerate the drivers, the platforms and the devices of the host
+    ///
+    /// # Returns
+    ///
+    /// * `DeviceDiagnostics` - What has been found, and the hints to fix what hasn't
+    ///
+    pub fn collect() -> DeviceDiagnostics {
+        let icd_dir = env::var(ICD_VENDORS_VARIABLE).unwrap_or_else(|_| DEFAULT_ICD_DIR.to_string());
+        let icds = list_icds(Path::new(&icd_dir));
+
+        // Broken drivers can panic the bindings instead of failing, so contain them
+        #[cfg(feature = "opencl")]
+        let enumeration = catch_unwind(AssertUnwindSafe(|| core::get_platform_ids()
+            .map(|ids| Platform::list_from_core(ids).into_iter().enumerate().map(|(index, platform)| describe_platform(platform, index == 0)).collect::<Vec<_>>())
+            .map_err(|err| err.to_string().trim_end_matches('.').to_string())));
+
+        // The builds without OpenCL can't list the platforms, even if the drivers are installed
+        #[cfg(not(feature = "opencl"))]
+        let enumeration: std::thread::Result<Result<Vec<PlatformReport>, String>> = Ok(Err("The server has been built without OpenCL".to_string()));
+
+        let (platforms, error) = match enumeration {
+            Ok(Ok(platforms)) => (platforms, None),
+            Ok(Err(err)) => (vec![], Some(format!("The OpenCL platforms can't be listed: {}", err))),
+            Err(_) => (vec![], Some("The OpenCL loader has crashed while listing the platforms".to_string()))
+        };
+
+        // The drivers of the host can't fix a build without OpenCL
+        let hints = if cfg!(feature = "opencl") { hints(&icds, &platforms, error.as_deref()) } else { vec![] };
+
+        DeviceDiagnostics { icd_dir, icds, platforms, error, hints }
+    }
+
+    /// Get the device the walker runs its kernels in, if there is any
+    pub fn selected(&self) -> Option<&DeviceReport> {
+        self.platforms.iter().flat_map(|platform| &platform.devices).find(|device| device.selected)
+    }
+
+    /// Check if any device can run the kernels
+    pub fn has_devices(&self) -> bool {
+        self.platforms.iter().any(|platform| !platform.devices.is_empty())
+    }
+
+    /// Describe the drivers found and the hints, for the logs
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The description, in a single line
+    ///
+    pub fn summary(&self) -> String {
+        let icds: Vec<String> = self.icds.iter()
+            .map(|icd| format!("{} ({}{})", icd.file, icd.library, if icd.found { "" } else { ", missing" }))
+            .collect();
+        let platforms: Vec<String> = self.platforms.iter()
+            .map(|platform| format!("{} {} with {} devices", platform.name, platform.version, platform.devices.len()))
+            .collect();
+
+        format!(
+            "No OpenCL device is available. {}Drivers in {}: {}. Platforms: {}. {}",
+            self.error.as_ref().map(|err| format!("{}. ", err)).unwrap_or_default(),
+            self.icd_dir,
+            if icds.is_empty() { "none".to_string() } else { icds.join(", ") },
+            if platforms.is_empty() { "none".to_string() } else { platforms.join(", ") },
+            self.hints.join(". ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints() {
+        // Prepare a host without drivers, with a missing library, and with a device
+        let missing = IcdEntry { file: "/etc/OpenCL/vendors/nvidia.icd".to_string(), library: "libnvidia-opencl.so.1".to_string(), found: false };
+        let device = DeviceReport { name: "gfx1030".to_string(), vendor: "AMD".to_string(), kind: "GPU".to_string(), version: "OpenCL 2.0".to_string(), driver_version: "3614.0".to_string(), compute_units: Some(40), max_work_group_size: Some(256), global_memory: Some(17_163_091_968), selected: true };
+        let platform = PlatformReport { name: "AMD APP".to_string(), vendor: "AMD".to_string(), version: "OpenCL 2.1".to_string(), devices: vec![device], error: None };
+
+        // Get the result
+        let empty = hints(&[], &[], Some("Platform not found"));
+        let broken = hints(&[missing], &[], Some("Platform not found"));
+        let working = hints(&[], std::slice::from_ref(&platform), None);
+        let diagnostics = DeviceDiagnostics { icd_dir: DEFAULT_ICD_DIR.to_string(), icds: vec![], platforms: vec![platform], error: None, hints: vec![] };
+
+        // Check if the result is correct
+        assert_eq!(empty.len(), 1);
+        assert!(empty[0].contains(ICD_VENDORS_VARIABLE));
+        assert_eq!(broken, vec!["The library libnvidia-opencl.so.1 registered in /etc/OpenCL/vendors/nvidia.icd can't be found, reinstall its driver or add its directory to LD_LIBRARY_PATH".to_string()]);
+        assert!(working.is_empty());
+        assert_eq!(diagnostics.selected().map(|device| device.name.as_str()), Some("gfx1030"));
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::models::{Matrix, Precision, Real};
+use crate::services::backend::BackendKind;
+
+/// The vertices below which the graphs are walked in the CPU by default
+pub const DEFAULT_CPU_THRESHOLD: usize = 64;
+
+/// The vertices below which the graphs are walked in the CPU, never when zero
+static CPU_THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_CPU_THRESHOLD);
+
+/// Set the vertices below which the graphs are walked in the CPU
+///
+/// # Arguments
+///
+/// * `vertices` - The vertices of the smallest graph walked in the device, zero to walk every graph in the device
+///
+pub fn set_cpu_threshold(vertices: usize) {
+    CPU_THRESHOLD.store(vertices, Ordering::Relaxed);
+}
+
+/// Get the vertices below which the graphs are walked in the CPU
+pub fn cpu_threshold() -> usize {
+    CPU_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// The vertices from which the single pair walks of the stored graphs search from both ends by default
+pub const DEFAULT_BIDIRECTIONAL_THRESHOLD: usize = 1024;
+
+/// The vertices from which the single pair walks search from both ends, never when zero
+static BIDIRECTIONAL_THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_BIDIRECTIONAL_THRESHOLD);
+
+/// Set the vertices from which the single pair walks search from both ends
+///
+/// # Arguments
+///
+/// * `vertices` - The vertices of the smallest graph walked from both ends, zero to always walk from the source
+///
+pub fn set_bidirectional_threshold(vertices: usize) {
+    BIDIRECTIONAL_THRESHOLD.store(vertices, Ordering::Relaxed);
+}
+
+/// Get the vertices from which the single pair walks search from both ends
+pub fn bidirectional_threshold() -> usize {
+    BIDIRECTIONAL_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Check if the walk of a graph towards a target searches from both ends
+///
+/// Searching from the source and the target at once touches far fewer
+/// vertices than walking the whole graph when only a target is wanted, but
+/// it launches two kernels per turn, so it only pays off in large graphs.
+/// It needs the weights to be non-negative, and the walks bounded by their
+/// hops can't meet in the middle, so those are always walked from the source.
+///
+/// # Arguments
+///
+/// * `vertices` - The vertices of the graph
+/// * `rounds` - The iterations of its walks, bounded by the hops of the paths
+/// * `nonnegative` - If the graph has no negative weights
+///
+/// # Returns
+///
+/// * `bool` - If the walk searches from both ends
+///
+pub fn is_bidirectional(vertices: usize, rounds: usize, nonnegative: bool) -> bool {
+    let threshold = bidirectional_threshold();
+
+    threshold > 0 && vertices >= threshold && rounds >= vertices && nonnegative
+}
+
+/// Choose the backend a matrix is walked with
+///
+/// Copying a small matrix to the device and launching a kernel per vertex
+/// takes longer than walking it in the CPU, so the matrices with fewer
+/// vertices than the threshold are walked in the CPU, unless they have
+/// negative weights, which Dijkstra can't handle, or another precision than
+/// single. The rest are walked with the backend of the device, except the
+/// time dependent matrices, as only the CPU reads the weight of every edge
+/// at the time it's reached.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to walk
+/// * `device` - The backend of the device
+///
+/// # Returns
+///
+/// * `BackendKind` - The backend to walk the matrix with
+///
+pub fn dispatch<T: Real>(matrix: &Matrix<T>, device: BackendKind) -> BackendKind {
+    let small = matrix.width < cpu_threshold() && matrix.data.iter().all(|weight| *weight >= T::default());
+
+    if T::PRECISION == Precision::Single && (small || matrix.is_time_dependent()) {
+        BackendKind::Cpu
+    } else {
+        device
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dispatch() {
+        // Prepare a small matrix, a small one with a negative weight and a large one
+        let small: Matrix = Matrix::new(3, 3, vec![0.0, 1.0, 4.0, 1.0, 0.0, 2.0, 4.0, 2.0, 0.0]);
+        let negative: Matrix = Matrix::new(2, 2, vec![0.0, -1.0, 1.0, 0.0]);
+        let large: Matrix = Matrix::new(DEFAULT_CPU_THRESHOLD, DEFAULT_CPU_THRESHOLD, vec![1.0; DEFAULT_CPU_THRESHOLD * DEFAULT_CPU_THRESHOLD]);
+
+        // Get the result
+        let kinds = [&small, &negative, &large].map(|matrix| dispatch(matrix, BackendKind::OpenCl));
+        let double = dispatch(&Matrix::new(2, 2, vec![0.0f64, 1.0, 1.0, 0.0]), BackendKind::Wgpu);
+        let timed = dispatch(&Matrix { time_buckets: vec![large.data.clone()], ..large.clone() }, BackendKind::OpenCl);
+
+        // Check if the result is correct
+        assert_eq!(kinds, [BackendKind::Cpu, BackendKind::OpenCl, BackendKind::OpenCl]);
+        assert_eq!(double, BackendKind::Wgpu);
+        assert_eq!(timed, BackendKind::Cpu);
+    }
+
+    #[test]
+    fn test_is_bidirectional() {
+        // Get the result
+        let walks = [
+            is_bidirectional(DEFAULT_BIDIRECTIONAL_THRESHOLD, DEFAULT_BIDIRECTIONAL_THRESHOLD, true),
+            is_bidirectional(DEFAULT_BIDIRECTIONAL_THRESHOLD - 1, DEFAULT_BIDIRECTIONAL_THRESHOLD - 1, true),
+            is_bidirectional(DEFAULT_BIDIRECTIONAL_THRESHOLD, 8, true),
+            is_bidirectional(DEFAULT_BIDIRECTIONAL_THRESHOLD, DEFAULT_BIDIRECTIONAL_THRESHOLD, false)
+        ];
+
+        // Check if the result is correct
+        assert_eq!(walks, [true, false, false, false]);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::models::{Candidate, HopExplanation, Matrix, PathResult};
+
+/// Explain the decisions taken while computing a path
+///
+/// For every vertex of the path this method lists the edges that were competing
+/// to reach the vertex, with the cost of arriving through each of them, so
+/// the user can see why the chosen predecessor won over the rest.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix used to compute the path
+/// * `path` - The path computed for the matrix
+///
+/// # Returns
+///
+/// * `Vec<HopExplanation>` - The explanation of every hop of the path
+///
+pub fn explain_path(matrix: &Matrix, path: &[PathResult]) -> Vec<HopExplanation> {
+    path.iter().enumerate().map(|(vertex, hop)| {
+        // Collect the valid edges of the vertex, the same way the kernel reads them
+        let mut candidates: Vec<Candidate> = (0..matrix.width)
+            .filter_map(|edge| {
+                matrix.edge(edge, vertex).map(|weight| Candidate {
+                    vertex: edge,
+                    weight,
+                    cost: path[edge].1 + weight,
+                    chosen: edge as i32 == hop.0
+                })
+            })
+            .collect();
+
+        // Sort the candidates from the cheapest to the most expensive
+        candidates.sort_by(|a, b| a.cost.total_cmp(&b.cost).then(a.vertex.cmp(&b.vertex)));
+
+        HopExplanation { vertex, predecessor: hop.0, distance: hop.1, candidates }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_path() {
+        // Prepare the matrix
+        let matrix = Matrix::new(3, 3, vec![
+            00.0, 04.0, 01.0,
+            04.0, 00.0, 02.0,
+            01.0, 02.0, 00.0
+        ]);
+
+        // Prepare the path
+        let path = vec![
+            PathResult(0, 0.0),
+            PathResult(2, 3.0),
+            PathResult(0, 1.0)
+        ];
+
+        // Get the result
+        let result = explain_path(&matrix, &path);
+
+        // Check if the result is correct
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[1].candidates, vec![
+            Candidate { vertex: 2, weight: 2.0, cost: 3.0, chosen: true },
+            Candidate { vertex: 0, weight: 4.0, cost: 4.0, chosen: false }
+        ]);
+    }
+}
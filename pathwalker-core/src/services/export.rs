@@ -0,0 +1,147 @@
+use std::fmt::Write;
+
+use crate::models::Matrix;
+use crate::services::import::GraphFormat;
+
+/// Get the edges of a matrix as the walks see them
+///
+/// The loops are left out, as the walks never take them, and the edges of
+/// an undirected matrix are listed once, from the lowest vertex.
+fn edges(matrix: &Matrix) -> Vec<(usize, usize, f32)> {
+    let mut edges = Vec::new();
+
+    for source in 0..matrix.width {
+        for target in 0..matrix.width {
+            if source == target || (!matrix.directed && target < source) {
+                continue;
+            }
+
+            if let Some(weight) = matrix.edge(source, target) {
+                edges.push((source, target, weight));
+            }
+        }
+    }
+
+    edges
+}
+
+/// Escape a text to be written in a XML attribute
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+/// Export a matrix as a graph
+///
+/// The vertices are named by their index, and declared in order, with the
+/// edges as the walks see them in the `weight` attribute. Only the weights
+/// and the direction are written, so importing the graph back gives a matrix
+/// with the same vertices and edges, but not the same settings: the forbidden
+/// vertices and edges are left out instead of being listed, the cheapest way
+/// of the asymmetric edges of an undirected matrix is written for both, and
+/// `max_hops`, `unit`, the layers, the time buckets and `zero_edges` are
+/// lost, so the edges of weight zero are read back as missing. The CSV edge
+/// lists are directed, so the edges of an undirected matrix are written in
+/// both ways, and the vertices without edges are left out.
+///
+/// # Arguments
+///
+/// * `format` - The format of the graph
+/// * `name` - The name of the graph
+/// * `matrix` - The matrix of the graph
+///
+/// # Returns
+///
+/// * `String` - The serialized graph
+///
+pub fn export_graph(format: GraphFormat, name: &str, matrix: &Matrix) -> String {
+    match format {
+        GraphFormat::GraphMl => write_graphml(name, matrix),
+        GraphFormat::Dot => write_dot(name, matrix),
+        GraphFormat::Csv => write_csv(matrix)
+    }
+}
+
+/// Write a GraphML document
+fn write_graphml(name: &str, matrix: &Matrix) -> String {
+    let mut text = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    let direction = if matrix.directed { "directed" } else { "undirected" };
+
+    // Declare the weight key and the vertices in order
+    text.push_str("  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n");
+    let _ = writeln!(text, "  <graph id=\"{}\" edgedefault=\"{}\">", escape_xml(name), direction);
+
+    for vertex in 0..matrix.width {
+        let _ = writeln!(text, "    <node id=\"{}\"/>", vertex);
+    }
+
+    for (source, target, weight) in edges(matrix) {
+        let _ = writeln!(text, "    <edge source=\"{}\" target=\"{}\"><data key=\"weight\">{}</data></edge>", source, target, weight);
+    }
+
+    text.push_str("  </graph>\n</graphml>\n");
+    text
+}
+
+/// Write a DOT document
+fn write_dot(name: &str, matrix: &Matrix) -> String {
+    let (kind, operator) = if matrix.directed { ("digraph", "->") } else { ("graph", "--") };
+    let mut text = format!("{} \"{}\" {{\n", kind, name.replace('\\', "\\\\").replace('"', "\\\""));
+
+    // Declare the vertices in order, so the ones without edges are kept
+    for vertex in 0..matrix.width {
+        let _ = writeln!(text, "  {};", vertex);
+    }
+
+    for (source, target, weight) in edges(matrix) {
+        let _ = writeln!(text, "  {} {} {} [weight={}];", source, operator, target, weight);
+    }
+
+    text.push_str("}\n");
+    text
+}
+
+/// Write a CSV edge list
+fn write_csv(matrix: &Matrix) -> String {
+    let mut text = String::from("source,target,weight\n");
+
+    for (source, target, weight) in edges(matrix) {
+        let _ = writeln!(text, "{},{},{}", source, target, weight);
+
+        if !matrix.directed {
+            let _ = writeln!(text, "{},{},{}", target, source, weight);
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::import::import_graph;
+
+    #[test]
+    fn test_export_graph() {
+        // Prepare a directed matrix with an isolated vertex and an undirected one
+        let mut directed = Matrix::new(4, 4, vec![0.0; 16]);
+        for (source, target, weight) in [(0, 1, 4.0), (1, 2, 2.5), (2, 0, 1.0)] {
+            directed.set_edge(source, target, weight);
+        }
+        let undirected = Matrix { directed: false, ..Matrix::new(3, 3, vec![0.0, 4.0, 0.0, 4.0, 0.0, 2.5, 0.0, 2.5, 0.0]) };
+        let zero_edges = Matrix { zero_edges: true, max_hops: Some(1), ..Matrix::new(2, 2, vec![f32::MAX, 0.0, f32::MAX, f32::MAX]) };
+
+        // Get the result
+        let graphml = import_graph(GraphFormat::GraphMl, &export_graph(GraphFormat::GraphMl, "a<b", &directed)).unwrap();
+        let dot = import_graph(GraphFormat::Dot, &export_graph(GraphFormat::Dot, "roads", &undirected)).unwrap();
+        let csv = export_graph(GraphFormat::Csv, "roads", &undirected);
+        let settings = import_graph(GraphFormat::GraphMl, &export_graph(GraphFormat::GraphMl, "free", &zero_edges)).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(graphml.nodes, vec!["0", "1", "2", "3"]);
+        assert_eq!(graphml.matrix.data, directed.data);
+        assert_eq!(dot.matrix.data, undirected.data);
+        assert_eq!(csv, "source,target,weight\n0,1,4\n1,0,4\n1,2,2.5\n2,1,2.5\n");
+        assert_eq!((settings.matrix.max_hops, settings.matrix.edge(1, 0)), (None, None));
+        assert!(export_graph(GraphFormat::GraphMl, "a<b", &directed).contains("<graph id=\"a&lt;b\" edgedefault=\"directed\">"));
+    }
+}
@@ -0,0 +1,222 @@
+use crate::models::{FailureRequest, FailureStats, Matrix, PathResult, TrialSummary};
+use crate::services::cancellation::Cancellation;
+use crate::services::generator::SplitMix64;
+use crate::services::opencl::Result;
+use crate::services::sortest_path::SortestPath;
+use crate::solver::validate_static_matrix;
+
+/// The most walks of a simulation, counting the ones of the intact graph
+pub const MAX_FAILURE_WALKS: usize = 65_536;
+
+/// Validate a failure simulation request
+///
+/// # Arguments
+///
+/// * `request` - The failure simulation request
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the request isn't valid, if any
+///
+pub fn validate_failures(request: &FailureRequest) -> Option<&'static str> {
+    let walks = request.trials.saturating_add(1).saturating_mul(request.matrix.width);
+
+    if let Some(message) = validate_static_matrix(&request.matrix) {
+        Some(message)
+    } else if request.trials == 0 {
+        Some("The simulation must have at least one trial")
+    } else if walks > MAX_FAILURE_WALKS {
+        Some("The trials can't walk the graph from more than 65536 sources in total")
+    } else if !(0.0..=1.0).contains(&request.edge_failure) || !(0.0..=1.0).contains(&request.node_failure) {
+        Some("The failure probabilities must be between zero and one")
+    } else {
+        None
+    }
+}
+
+/// Remove random edges and vertices of a matrix
+///
+/// The edges are removed by setting them to `f32::MAX`, which is a missing
+/// edge with and without zero weight edges, and both cells of the edges of
+/// undirected matrices are removed together.
+///
+/// # Arguments
+///
+/// * `matrix` - The intact matrix
+/// * `edge_failure` - The probability of removing every edge
+/// * `node_failure` - The probability of removing every vertex, with its edges
+/// * `random` - The generator of the failures
+///
+/// # Returns
+///
+/// * `(Matrix, Vec<bool>)` - The matrix after the failures, and which vertices have been removed
+///
+pub fn fail_matrix(matrix: &Matrix, edge_failure: f64, node_failure: f64, random: &mut SplitMix64) -> (Matrix, Vec<bool>) {
+    let width = matrix.width;
+    let mut failed = matrix.clone();
+    let removed: Vec<bool> = (0..width).map(|_| random.chance(node_failure)).collect();
+
+    for target in 0..width {
+        // Every undirected edge is drawn once, from the cell of its lower source
+        let sources = if matrix.directed { 0..width } else { target..width };
+
+        for source in sources.filter(|source| *source != target) {
+            let lost = matrix.edge(source, target).is_some() && random.chance(edge_failure);
+
+            if lost || removed[source] || removed[target] {
+                failed.set_edge(source, target, f32::MAX);
+            }
+        }
+    }
+
+    (failed, removed)
+}
+
+/// Get the distance between every ordered pair of vertices from their paths
+///
+/// # Arguments
+///
+/// * `paths` - The path from every vertex, in order
+/// * `zero_edges` - If the matrix of the paths has zero weight edges
+///
+/// # Returns
+///
+/// * `Vec<Option<f64>>` - The distance of every pair, by source and target, if it's connected
+///
+pub fn pair_distances(paths: &[Vec<PathResult>], zero_edges: bool) -> Vec<Option<f64>> {
+    paths.iter().enumerate().flat_map(|(source, path)| {
+        path.iter().enumerate().map(move |(target, hop)| {
+            let unreachable = if zero_edges { hop.1 == f32::MAX } else { hop.1 == 0.0 };
+            (source != target && !unreachable).then_some(hop.1 as f64)
+        })
+    }).collect()
+}
+
+/// Summarize the distances of the trials of a simulation against the intact ones
+///
+/// # Arguments
+///
+/// * `intact` - The distances of the pairs in the intact graph
+/// * `trials` - The distances of the pairs in every trial
+///
+/// # Returns
+///
+/// * `FailureStats` - The statistics of the simulation
+///
+pub fn summarize_failures(intact: &[Option<f64>], trials: &[Vec<Option<f64>>]) -> FailureStats {
+    let connected: Vec<(usize, f64)> = intact.iter().enumerate().filter_map(|(pair, distance)| distance.map(|distance| (pair, distance))).collect();
+    let mut stats = FailureStats { trials: trials.len(), connected_pairs: connected.len(), ..FailureStats::default() };
+
+    if connected.is_empty() {
+        return stats;
+    }
+
+    stats.mean_distance = connected.iter().map(|(_, distance)| distance).sum::<f64>() / connected.len() as f64;
+
+    // Compare every trial with the intact graph, ignoring the free pairs whose stretch is unknown
+    let (mut connectivity, mut stretch) = (vec![], vec![]);
+
+    for trial in trials {
+        let kept: Vec<(f64, f64)> = connected.iter().filter_map(|(pair, distance)| trial[*pair].map(|failed| (failed, *distance))).collect();
+        let ratios: Vec<f64> = kept.iter().filter(|(_, distance)| *distance > 0.0).map(|(failed, distance)| failed / distance).collect();
+
+        connectivity.push(kept.len() as f64 / connected.len() as f64);
+
+        if !ratios.is_empty() {
+            stretch.push(ratios.iter().sum::<f64>() / ratios.len() as f64);
+        }
+
+        if kept.len() < connected.len() {
+            stats.disconnected_trials += 1;
+        }
+    }
+
+    stats.connectivity = summarize(&connectivity);
+    stats.stretch = summarize(&stretch);
+    stats
+}
+
+/// Get the mean and the bounds of the values of the trials, or zeros without them
+fn summarize(values: &[f64]) -> TrialSummary {
+    if values.is_empty() {
+        return TrialSummary::default();
+    }
+
+    TrialSummary {
+        mean: values.iter().sum::<f64>() / values.len() as f64,
+        min: values.iter().copied().fold(f64::INFINITY, f64::min),
+        max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+    }
+}
+
+/// Simulate random failures of a graph in the device
+///
+/// Walks the intact graph and then the graph of every trial, after removing
+/// random edges and vertices, from every vertex, so the connectivity and the
+/// distances of every pair can be compared. The walks of every source run in
+/// parallel in the device, and the same seed always fails the same edges.
+///
+/// # Arguments
+///
+/// * `walker` - The walker running the kernels
+/// * `request` - The validated failure simulation request
+/// * `cancellation` - Aborts the simulation between walks
+///
+/// # Returns
+///
+/// * `Result<FailureStats>` - The statistics of the simulation, or the error of the kernels
+///
+pub fn simulate_failures(walker: &SortestPath, request: &FailureRequest, cancellation: &Cancellation) -> Result<FailureStats> {
+    let zero_edges = request.matrix.zero_edges;
+    let all_pairs = |matrix: &Matrix| -> Result<Vec<Option<f64>>> {
+        let graph = walker.upload(matrix)?;
+        let paths = (0..matrix.width)
+            .map(|source| walker.walk(&graph, source, cancellation, |_, _| {}))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(pair_distances(&paths, zero_edges))
+    };
+
+    // Walk the intact graph, and then every trial
+    let intact = all_pairs(&request.matrix)?;
+    let mut random = SplitMix64(request.seed.unwrap_or_default());
+    let mut trials = Vec::with_capacity(request.trials);
+
+    for _ in 0..request.trials {
+        let (failed, _) = fail_matrix(&request.matrix, request.edge_failure, request.node_failure, &mut random);
+        trials.push(all_pairs(&failed)?);
+    }
+
+    Ok(summarize_failures(&intact, &trials))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_failures() {
+        // Prepare a path of three vertices, and a trial losing an edge and one with a longer detour
+        let mut matrix = Matrix { directed: false, ..Matrix::new(3, 3, vec![0.0; 9]) };
+        matrix.set_edge(0, 1, 1.0);
+        matrix.set_edge(1, 2, 1.0);
+        let intact = vec![None, Some(1.0), Some(2.0), Some(1.0), None, Some(1.0), Some(2.0), Some(1.0), None];
+        let broken = vec![None, Some(1.0), None, Some(1.0), None, None, None, None, None];
+        let detour = vec![None, Some(2.0), Some(4.0), Some(2.0), None, Some(2.0), Some(4.0), Some(2.0), None];
+
+        // Get the result
+        let (all_failed, removed) = fail_matrix(&matrix, 1.0, 0.0, &mut SplitMix64(1));
+        let stats = summarize_failures(&intact, &[broken, detour]);
+
+        // Check if the result is correct
+        assert_eq!(all_failed.edge(0, 1), None);
+        assert_eq!(all_failed.edge(2, 1), None);
+        assert_eq!(removed, vec![false; 3]);
+        assert_eq!(fail_matrix(&matrix, 0.0, 0.0, &mut SplitMix64(1)).0, matrix);
+        assert_eq!(pair_distances(&[vec![PathResult(0, 0.0), PathResult(0, 1.0)], vec![PathResult(1, 0.0), PathResult(1, 0.0)]], false), vec![None, Some(1.0), None, None]);
+        assert_eq!(stats.connected_pairs, 6);
+        assert_eq!(stats.connectivity, TrialSummary { mean: 2.0 / 3.0, min: 1.0 / 3.0, max: 1.0 });
+        assert_eq!(stats.stretch, TrialSummary { mean: 1.5, min: 1.0, max: 2.0 });
+        assert_eq!(stats.disconnected_trials, 1);
+    }
+}
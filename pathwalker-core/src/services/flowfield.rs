@@ -0,0 +1,242 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::models::{FlowField, FlowFieldRequest, FlowGridRequest, GridTopology, ValidationRules};
+use crate::services::grid::{is_blocked, HEX_NEIGHBOURS, MAX_GRID_CELLS, SQUARE_NEIGHBOURS};
+use crate::solver::validate_matrix;
+
+/// The step of the goal, where the agents stop
+pub const GOAL_STEP: u8 = 254;
+
+/// The step of the cells and vertices that can't reach the goal
+pub const NO_STEP: u8 = 255;
+
+/// The vertices of a graph flow field at most, as their steps are bytes below the goal step
+pub const MAX_FLOW_FIELD_VERTICES: usize = GOAL_STEP as usize;
+
+/// The rules of the weights of the graphs, as the search can't handle negative ones
+const NON_NEGATIVE: ValidationRules = ValidationRules { reject_negative: true, warn_asymmetric: false };
+
+/// A vertex waiting to be settled by the search, with its cost to the goal
+#[derive(PartialEq)]
+struct Open {
+    cost: f32,
+    vertex: usize,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, so the heap pops the cheapest vertex first
+        other.cost.total_cmp(&self.cost).then_with(|| other.vertex.cmp(&self.vertex))
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Validate a flow field request
+///
+/// # Arguments
+///
+/// * `request` - The flow field request
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the request isn't valid, if any
+///
+pub fn validate_flow_field(request: &FlowFieldRequest) -> Option<&'static str> {
+    match request {
+        FlowFieldRequest::Grid(grid) => {
+            if grid.width == 0 || grid.height == 0 || grid.width.saturating_mul(grid.height) > MAX_GRID_CELLS {
+                Some("The grid must have between one and 4194304 cells")
+            } else if grid.costs.len() != grid.width * grid.height {
+                Some("The costs size and grid dimensions are not the same")
+            } else if grid.costs.iter().any(|cost| cost.is_nan() || *cost < 0.0) {
+                Some("The costs must be non-negative numbers")
+            } else if grid.goal[0] >= grid.width || grid.goal[1] >= grid.height {
+                Some("The goal is outside of the grid")
+            } else if is_blocked(grid.costs[grid.goal[1] * grid.width + grid.goal[0]]) {
+                Some("The goal is blocked")
+            } else {
+                None
+            }
+        },
+        FlowFieldRequest::Graph(graph) => (graph.matrix.width > MAX_FLOW_FIELD_VERTICES).then_some("The graph must have at most 254 vertices")
+            .or_else(|| validate_matrix(&graph.matrix))
+            .or_else(|| (!graph.matrix.validate(NON_NEGATIVE).is_valid()).then_some("The weights must be finite non-negative numbers"))
+            .or_else(|| (graph.target >= graph.matrix.width).then_some("The target is outside of the graph"))
+    }
+}
+
+/// Compute the flow field of a grid
+///
+/// Searches the cost to the goal of every cell with a single Dijkstra from
+/// the goal, entering the cells backwards, and then points every cell to the
+/// neighbour it was reached from.
+///
+/// # Arguments
+///
+/// * `grid` - The validated grid
+///
+/// # Returns
+///
+/// * `FlowField` - The index of the neighbour offset to take from every cell
+///
+fn grid_flow_field(grid: &FlowGridRequest) -> FlowField {
+    let width = grid.width;
+    let neighbours: &[(isize, isize)] = match grid.topology {
+        GridTopology::Square => &SQUARE_NEIGHBOURS,
+        GridTopology::Hex => &HEX_NEIGHBOURS
+    };
+
+    // Start the search from the goal
+    let goal = grid.goal[1] * width + grid.goal[0];
+    let mut costs = vec![f32::INFINITY; grid.costs.len()];
+    let mut steps = vec![NO_STEP; grid.costs.len()];
+    let mut open = BinaryHeap::from([Open { cost: 0.0, vertex: goal }]);
+    costs[goal] = 0.0;
+    steps[goal] = GOAL_STEP;
+
+    while let Some(Open { cost, vertex }) = open.pop() {
+        // Skip the cells already settled with a cheaper cost
+        if cost > costs[vertex] {
+            continue;
+        }
+
+        // Walking from a neighbour into this cell costs entering it
+        for (dq, dr) in neighbours {
+            let (column, row) = ((vertex % width) as isize + dq, (vertex / width) as isize + dr);
+
+            if column < 0 || row < 0 || column as usize >= width || row as usize >= grid.height {
+                continue;
+            }
+
+            let previous = row as usize * width + column as usize;
+            let cost = costs[vertex] + grid.costs[vertex];
+
+            if !is_blocked(grid.costs[previous]) && cost < costs[previous] {
+                costs[previous] = cost;
+                steps[previous] = neighbours.iter().position(|offset| *offset == (-dq, -dr)).unwrap() as u8;
+                open.push(Open { cost, vertex: previous });
+            }
+        }
+    }
+
+    FlowField { width, height: grid.height, steps }
+}
+
+/// Compute the flow field of a map
+///
+/// The step of every cell or vertex is the first one of its cheapest path to
+/// the goal, so many agents can be steered by looking up where they stand
+/// instead of searching a path for each of them. The steps of a grid are
+/// the indexes of the neighbour offsets of its topology, and the steps of a
+/// graph are the indexes of the next vertices, which always fit a byte as
+/// graphs have at most 128 vertices. The goal has `GOAL_STEP`, and the
+/// places that can't reach it have `NO_STEP`.
+///
+/// # Arguments
+///
+/// * `request` - The validated flow field request
+///
+/// # Returns
+///
+/// * `FlowField` - The step of every cell or vertex, one byte each
+///
+pub fn flow_field(request: &FlowFieldRequest) -> FlowField {
+    let graph = match request {
+        FlowFieldRequest::Grid(grid) => return grid_flow_field(grid),
+        FlowFieldRequest::Graph(graph) => graph
+    };
+    let width = graph.matrix.width;
+
+    // Start the search from the target
+    let mut costs = vec![f32::INFINITY; width];
+    let mut steps = vec![NO_STEP; width];
+    let mut open = BinaryHeap::from([Open { cost: 0.0, vertex: graph.target }]);
+    costs[graph.target] = 0.0;
+    steps[graph.target] = GOAL_STEP;
+
+    while let Some(Open { cost, vertex }) = open.pop() {
+        // Skip the vertices already settled with a cheaper cost
+        if cost > costs[vertex] {
+            continue;
+        }
+
+        // Relax the edges reaching this vertex, stored in its row
+        for previous in 0..width {
+            let Some(weight) = graph.matrix.edge(previous, vertex).filter(|_| previous != vertex) else {
+                continue;
+            };
+            let cost = costs[vertex] + weight;
+
+            if cost < costs[previous] {
+                costs[previous] = cost;
+                steps[previous] = vertex as u8;
+                open.push(Open { cost, vertex: previous });
+            }
+        }
+    }
+
+    FlowField { width, height: 1, steps }
+}
+
+/// Encode a flow field in its compact binary layout
+///
+/// The layout is the width and the height as little endian 32 bit integers,
+/// followed by the step of every cell or vertex, one byte each, row by row.
+///
+/// # Arguments
+///
+/// * `field` - The flow field
+///
+/// # Returns
+///
+/// * `Vec<u8>` - The encoded flow field
+///
+pub fn encode_flow_field(field: &FlowField) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + field.steps.len());
+    bytes.extend_from_slice(&(field.width as u32).to_le_bytes());
+    bytes.extend_from_slice(&(field.height as u32).to_le_bytes());
+    bytes.extend_from_slice(&field.steps);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{FlowGraphRequest, Matrix};
+    use super::*;
+
+    #[test]
+    fn test_flow_field() {
+        // Prepare a square map with walls that leave a single way around, and a graph with a detour cheaper than the direct edge
+        let grid = FlowFieldRequest::Grid(FlowGridRequest {
+            topology: GridTopology::Square,
+            width: 3,
+            height: 3,
+            costs: vec![1.0, 1.0, 1.0, 1.0, 0.0, 1.0, 0.0, 1.0, 1.0],
+            goal: [2, 2]
+        });
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        matrix.set_edge(0, 2, 5.0);
+        matrix.set_edge(0, 1, 1.0);
+        matrix.set_edge(1, 2, 1.0);
+        let graph = FlowFieldRequest::Graph(FlowGraphRequest { matrix, target: 2 });
+
+        // Get the result
+        let grid_field = flow_field(&grid);
+        let graph_field = flow_field(&graph);
+
+        // Check if the result is correct
+        assert_eq!(validate_flow_field(&grid), None);
+        assert_eq!(grid_field.steps, vec![0, 0, 2, 3, NO_STEP, 2, NO_STEP, 0, GOAL_STEP]);
+        assert_eq!(graph_field.steps, vec![1, 2, GOAL_STEP, NO_STEP]);
+        assert_eq!(encode_flow_field(&graph_field), vec![4, 0, 0, 0, 1, 0, 0, 0, 1, 2, GOAL_STEP, NO_STEP]);
+        assert_eq!(validate_flow_field(&FlowFieldRequest::Graph(FlowGraphRequest { matrix: Matrix::new(255, 255, vec![0.0; 255 * 255]), target: 0 })), Some("The graph must have at most 254 vertices"));
+    }
+}
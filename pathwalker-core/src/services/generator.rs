@@ -0,0 +1,229 @@
+use serde::Deserialize;
+
+use crate::models::Matrix;
+
+/// The specification of a synthetic graph
+///
+/// # Fields
+///
+/// * `vertices` - The number of vertices
+/// * `seed` - The seed of the random numbers, so the same spec always gives the same graph
+/// * `directed` - If the edges only go one way, instead of both ways with the same weight
+/// * `topology` - How the vertices are connected
+/// * `weights` - How the weights of the edges are distributed
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GraphSpec {
+    pub vertices: usize,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub directed: bool,
+    pub topology: Topology,
+    #[serde(default)]
+    pub weights: WeightDistribution,
+}
+
+/// How the vertices of a synthetic graph are connected
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Topology {
+    /// Every pair of vertices is connected with the same probability
+    Uniform { probability: f64 },
+    /// The vertices are split in layers, and only consecutive layers are connected
+    Layered { layers: usize, probability: f64 },
+    /// The vertices are split in communities, densely connected inside and sparsely between them
+    Communities { communities: usize, intra_probability: f64, inter_probability: f64 },
+    /// The vertices form a grid with a diagonal in some of its cells, so the graph is planar
+    Planar { columns: usize, diagonal_probability: f64 },
+}
+
+/// How the weights of the edges of a synthetic graph are distributed
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "distribution", rename_all = "lowercase", deny_unknown_fields)]
+pub enum WeightDistribution {
+    Constant { value: f32 },
+    Uniform { min: f32, max: f32 },
+    Normal { mean: f32, stddev: f32 },
+    Exponential { mean: f32 },
+}
+
+impl Default for WeightDistribution {
+    fn default() -> Self {
+        WeightDistribution::Constant { value: 1.0 }
+    }
+}
+
+/// The smallest weight of a generated edge, as a zero weight means no edge
+const MIN_WEIGHT: f32 = 1e-3;
+
+/// A SplitMix64 generator of random numbers
+///
+/// Small and good enough for benchmarks, and stable across versions, so a
+/// seed always gives the same graph.
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    /// Get the next random number
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Get a random number between zero and one
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Check an event with the given probability
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+impl WeightDistribution {
+    /// Draw the weight of an edge
+    fn sample(&self, random: &mut SplitMix64) -> f32 {
+        let weight = match *self {
+            WeightDistribution::Constant { value } => value,
+            WeightDistribution::Uniform { min, max } => min + (max - min) * random.next_f64() as f32,
+            WeightDistribution::Normal { mean, stddev } => {
+                // Box-Muller transform
+                let (u, v) = (1.0 - random.next_f64(), random.next_f64());
+                mean + stddev * ((-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()) as f32
+            },
+            WeightDistribution::Exponential { mean } => -mean * (1.0 - random.next_f64()).ln() as f32
+        };
+
+        weight.max(MIN_WEIGHT)
+    }
+}
+
+impl GraphSpec {
+    /// Validate the specification
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` - The reason why the specification isn't valid, if any
+    ///
+    pub fn validate(&self) -> Option<&'static str> {
+        let probabilities = match self.topology {
+            Topology::Uniform { probability } | Topology::Layered { probability, .. } => vec![probability],
+            Topology::Communities { intra_probability, inter_probability, .. } => vec![intra_probability, inter_probability],
+            Topology::Planar { diagonal_probability, .. } => vec![diagonal_probability]
+        };
+
+        if self.vertices == 0 {
+            Some("The graph must have vertices")
+        } else if probabilities.iter().any(|probability| !(0.0..=1.0).contains(probability)) {
+            Some("The probabilities must be between zero and one")
+        } else if matches!(self.topology, Topology::Layered { layers: 0, .. } | Topology::Communities { communities: 0, .. } | Topology::Planar { columns: 0, .. }) {
+            Some("The layers, communities and columns must be more than zero")
+        } else {
+            None
+        }
+    }
+
+    /// Get the probability of an edge between two vertices in the topology
+    fn edge_probability(&self, source: usize, target: usize) -> f64 {
+        match self.topology {
+            Topology::Uniform { probability } => probability,
+            Topology::Layered { layers, probability } => {
+                let layer = |vertex: usize| vertex * layers / self.vertices;
+                if layer(target) == layer(source) + 1 { probability } else { 0.0 }
+            },
+            Topology::Communities { communities, intra_probability, inter_probability } => {
+                if source % communities == target % communities { intra_probability } else { inter_probability }
+            },
+            Topology::Planar { columns, .. } => {
+                let (row, column) = (source / columns, source % columns);
+                let (target_row, target_column) = (target / columns, target % columns);
+                let adjacent = (row == target_row && column.abs_diff(target_column) == 1) || (column == target_column && row.abs_diff(target_row) == 1);
+                if adjacent { 1.0 } else { 0.0 }
+            }
+        }
+    }
+
+    /// Generate the graph of the specification
+    ///
+    /// # Returns
+    ///
+    /// * `Matrix` - The adjacency matrix of the graph
+    ///
+    pub fn generate(&self) -> Matrix {
+        let mut random = SplitMix64(self.seed);
+        let mut matrix = Matrix::new(self.vertices, self.vertices, vec![0.0; self.vertices * self.vertices]);
+
+        for source in 0..self.vertices {
+            // Draw every pair once for undirected graphs, mirroring the edge
+            let targets = if self.directed { 0 } else { source + 1 }..self.vertices;
+
+            for target in targets.filter(|target| *target != source) {
+                if random.chance(self.edge_probability(source, target)) {
+                    let weight = self.weights.sample(&mut random);
+                    matrix.set_edge(source, target, weight);
+
+                    if !self.directed {
+                        matrix.set_edge(target, source, weight);
+                    }
+                }
+            }
+        }
+
+        // Add a diagonal to some cells of the grid, going the same way in every cell so edges never cross
+        if let Topology::Planar { columns, diagonal_probability } = self.topology {
+            for vertex in (0..self.vertices).filter(|vertex| vertex % columns + 1 < columns && vertex + columns + 1 < self.vertices) {
+                if random.chance(diagonal_probability) {
+                    let weight = self.weights.sample(&mut random);
+                    matrix.set_edge(vertex, vertex + columns + 1, weight);
+
+                    if !self.directed {
+                        matrix.set_edge(vertex + columns + 1, vertex, weight);
+                    }
+                }
+            }
+        }
+
+        matrix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_graph() {
+        // Prepare the specification
+        let spec: GraphSpec = toml::from_str(r#"
+            vertices = 12
+            seed = 7
+
+            [topology]
+            kind = "layered"
+            layers = 3
+            probability = 1.0
+
+            [weights]
+            distribution = "uniform"
+            min = 2.0
+            max = 4.0
+        "#).unwrap();
+
+        // Get the result
+        let matrix = spec.generate();
+        let edge = |source: usize, target: usize| matrix.data[target * matrix.width + source];
+
+        // Check if the result is correct
+        assert_eq!(spec.validate(), None);
+        assert_eq!(matrix, spec.generate());
+        assert!((2.0..=4.0).contains(&edge(0, 4)));
+        assert_eq!(edge(0, 4), edge(4, 0));
+        assert_eq!(edge(0, 1), 0.0);
+        assert_eq!(edge(0, 8), 0.0);
+    }
+}
@@ -0,0 +1,240 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::models::{GridPath, GridRequest, GridTopology};
+
+/// The maximum number of cells of a grid
+pub const MAX_GRID_CELLS: usize = 4_194_304;
+
+/// The neighbours of a cell in a square grid, as column and row offsets
+pub(crate) const SQUARE_NEIGHBOURS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// The neighbours of a cell in a hex grid, as axial `q` and `r` offsets
+pub(crate) const HEX_NEIGHBOURS: [(isize, isize); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+/// Get the offsets of the neighbours of a cell in a grid of a topology
+pub(crate) fn neighbours(topology: GridTopology) -> &'static [(isize, isize)] {
+    match topology {
+        GridTopology::Square => &SQUARE_NEIGHBOURS,
+        GridTopology::Hex => &HEX_NEIGHBOURS
+    }
+}
+
+/// A cell waiting to be expanded by the search
+///
+/// # Fields
+///
+/// * `priority` - The cost to reach the cell plus the estimate to the goal
+/// * `cell` - The index of the cell
+///
+#[derive(PartialEq)]
+struct Open {
+    priority: f32,
+    cell: usize,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, so the heap pops the lowest priority first
+        other.priority.total_cmp(&self.priority).then_with(|| other.cell.cmp(&self.cell))
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Check if a cell can't be entered, as a zero or maximum cost means a wall
+pub(crate) fn is_blocked(cost: f32) -> bool {
+    cost == 0.0 || cost == f32::MAX
+}
+
+/// Get the number of steps between two cells of a grid
+///
+/// # Arguments
+///
+/// * `topology` - The topology of the grid
+/// * `from` - The coordinates of the first cell
+/// * `to` - The coordinates of the second cell
+///
+/// # Returns
+///
+/// * `usize` - The steps of the shortest walk between the cells, ignoring their costs
+///
+pub fn grid_distance(topology: GridTopology, from: [usize; 2], to: [usize; 2]) -> usize {
+    let (dq, dr) = (to[0] as isize - from[0] as isize, to[1] as isize - from[1] as isize);
+
+    match topology {
+        GridTopology::Square => dq.unsigned_abs() + dr.unsigned_abs(),
+        GridTopology::Hex => (dq.unsigned_abs() + dr.unsigned_abs() + (dq + dr).unsigned_abs()) / 2
+    }
+}
+
+/// Validate a grid request
+///
+/// # Arguments
+///
+/// * `request` - The grid request
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the request isn't valid, if any
+///
+pub fn validate_grid(request: &GridRequest) -> Option<&'static str> {
+    let inside = |[column, row]: [usize; 2]| column < request.width && row < request.height;
+
+    if request.width == 0 || request.height == 0 || request.width.saturating_mul(request.height) > MAX_GRID_CELLS {
+        Some("The grid must have between one and 4194304 cells")
+    } else if request.costs.len() != request.width * request.height {
+        Some("The costs size and grid dimensions are not the same")
+    } else if request.costs.iter().any(|cost| cost.is_nan() || *cost < 0.0) {
+        Some("The costs must be non-negative numbers")
+    } else if request.weight.is_some_and(|weight| !weight.is_finite() || weight < 1.0) {
+        Some("The heuristic weight must be a number not lower than 1")
+    } else if !inside(request.start) || !inside(request.goal) {
+        Some("The start or the goal are outside of the grid")
+    } else if is_blocked(request.costs[request.start[1] * request.width + request.start[0]]) || is_blocked(request.costs[request.goal[1] * request.width + request.goal[0]]) {
+        Some("The start or the goal are blocked")
+    } else {
+        None
+    }
+}
+
+/// Search the cheapest path between two cells of a grid with A*
+///
+/// The cost of a step is the cost of the cell entered, and the estimate to
+/// the goal is the steps left times the cheapest cell, so the path found is
+/// always the cheapest one. The cells of square grids are addressed by
+/// column and row, with four neighbours, and the cells of hex grids by the
+/// axial `q` and `r` coordinates, with six neighbours.
+///
+/// A weight above one inflates the estimate, so the search heads to the goal
+/// expanding fewer cells, but the path found may cost up to the weight times
+/// the cheapest one. The bound reported is tighter: the estimates of the
+/// cells left open never exceed the cost of the cheapest path, so the
+/// cheapest of them tells how far from it the path found can be.
+///
+/// # Arguments
+///
+/// * `request` - The validated grid request
+///
+/// # Returns
+///
+/// * `Option<GridPath>` - The cells of the path from the start to the goal, if the goal can be reached
+///
+pub fn grid_path(request: &GridRequest) -> Option<GridPath> {
+    let (width, height) = (request.width, request.height);
+    let neighbours = neighbours(request.topology);
+    let cheapest = request.costs.iter().copied().filter(|cost| !is_blocked(*cost)).fold(f32::MAX, f32::min);
+    let weight = request.weight.unwrap_or(1.0);
+    let estimate = |cell: usize| grid_distance(request.topology, [cell % width, cell / width], request.goal) as f32 * cheapest;
+
+    // Start the search from the start cell
+    let (start, goal) = (request.start[1] * width + request.start[0], request.goal[1] * width + request.goal[0]);
+    let mut costs = vec![f32::INFINITY; width * height];
+    let mut parents = vec![usize::MAX; width * height];
+    let mut open = BinaryHeap::from([Open { priority: weight * estimate(start), cell: start }]);
+    let mut expanded = 0;
+    costs[start] = 0.0;
+
+    while let Some(Open { priority, cell }) = open.pop() {
+        // Skip the cells already expanded with a cheaper cost
+        if priority > costs[cell] + weight * estimate(cell) {
+            continue;
+        }
+
+        if cell == goal {
+            let mut cells = vec![[goal % width, goal / width]];
+            let mut current = goal;
+
+            while current != start {
+                current = parents[current];
+                cells.push([current % width, current / width]);
+            }
+
+            // Bound the cost of the cheapest path with the cells left open
+            let cheapest_path = open.iter().map(|open| costs[open.cell] + estimate(open.cell)).fold(costs[goal], f32::min);
+            let bound = if cheapest_path > 0.0 { (costs[goal] / cheapest_path).clamp(1.0, weight) } else { 1.0 };
+
+            cells.reverse();
+            return Some(GridPath { cells, cost: costs[goal], expanded, bound });
+        }
+
+        expanded += 1;
+
+        // Relax the neighbours inside the grid that can be entered
+        for (dq, dr) in neighbours {
+            let (column, row) = ((cell % width) as isize + dq, (cell / width) as isize + dr);
+
+            if column < 0 || row < 0 || column as usize >= width || row as usize >= height {
+                continue;
+            }
+
+            let next = row as usize * width + column as usize;
+            let cost = costs[cell] + request.costs[next];
+
+            if !is_blocked(request.costs[next]) && cost < costs[next] {
+                costs[next] = cost;
+                parents[next] = cell;
+                open.push(Open { priority: cost + weight * estimate(next), cell: next });
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_path() {
+        // Prepare a hex map with a wall between the start and the goal, and the same map as squares
+        let costs = vec![
+            1.0, 1.0, 1.0, 1.0,
+            1.0, 0.0, 0.0, 1.0,
+            1.0, 1.0, 1.0, 1.0,
+        ];
+        let hex = GridRequest { topology: GridTopology::Hex, width: 4, height: 3, costs, start: [0, 2], goal: [3, 0], weight: None, round: None, postprocess: vec![] };
+        let square = GridRequest { topology: GridTopology::Square, ..hex.clone() };
+
+        // Get the result
+        let hex_path = grid_path(&hex).unwrap();
+        let square_path = grid_path(&square).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(validate_grid(&hex), None);
+        assert_eq!(grid_distance(GridTopology::Hex, [0, 2], [3, 0]), 3);
+        assert_eq!(hex_path.cost, 4.0);
+        assert_eq!(hex_path.cells.first(), Some(&[0, 2]));
+        assert_eq!(hex_path.cells.last(), Some(&[3, 0]));
+        assert_eq!(square_path.cost, 5.0);
+        assert_eq!(hex_path.bound, 1.0);
+        assert_eq!(grid_path(&GridRequest { costs: vec![1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0], ..hex }), None);
+    }
+
+    #[test]
+    fn test_weighted_grid_path() {
+        // Prepare an open map with a costly band, searched exactly and with an inflated estimate
+        let mut costs = vec![1.0; 64 * 64];
+        costs.iter_mut().skip(30 * 64).take(60).for_each(|cost| *cost = 8.0);
+        let exact = GridRequest { topology: GridTopology::Square, width: 64, height: 64, costs, start: [2, 2], goal: [60, 60], weight: None, round: None, postprocess: vec![] };
+        let weighted = GridRequest { weight: Some(3.0), ..exact.clone() };
+
+        // Get the result
+        let exact_path = grid_path(&exact).unwrap();
+        let weighted_path = grid_path(&weighted).unwrap();
+
+        // Check if the result is correct
+        assert!(weighted_path.expanded < exact_path.expanded);
+        assert!(weighted_path.cost >= exact_path.cost);
+        assert!(weighted_path.cost <= weighted_path.bound * exact_path.cost + 1e-3);
+        assert!((1.0..=3.0).contains(&weighted_path.bound));
+        assert_eq!(validate_grid(&GridRequest { weight: Some(0.5), ..exact }), Some("The heuristic weight must be a number not lower than 1"));
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::models::{ImportedGraph, Matrix};
+
+/// The formats a graph can be imported from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    GraphMl,
+    Dot,
+    Csv,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_ascii_lowercase().as_str() {
+            "graphml" => Ok(GraphFormat::GraphMl),
+            "dot" | "gv" => Ok(GraphFormat::Dot),
+            "csv" => Ok(GraphFormat::Csv),
+            _ => Err(format!("The format {} is not supported", format))
+        }
+    }
+}
+
+impl GraphFormat {
+    /// Get the media type of the documents of the format
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            GraphFormat::GraphMl => "application/graphml+xml",
+            GraphFormat::Dot => "text/vnd.graphviz",
+            GraphFormat::Csv => "text/csv"
+        }
+    }
+}
+
+/// The edges of a graph while it's being parsed
+///
+/// Vertices get their index in order of appearance, and parallel edges
+/// are merged keeping the cheapest one.
+#[derive(Default)]
+struct GraphBuilder {
+    nodes: Vec<String>,
+    indexes: HashMap<String, usize>,
+    edges: Vec<(usize, usize, f32)>,
+}
+
+impl GraphBuilder {
+    /// Get the index of a vertex, registering it if it's new
+    fn node(&mut self, name: &str) -> usize {
+        if let Some(index) = self.indexes.get(name) {
+            return *index;
+        }
+
+        self.nodes.push(name.to_string());
+        self.indexes.insert(name.to_string(), self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// Register an edge between two vertices
+    fn edge(&mut self, source: &str, target: &str, weight: f32, directed: bool) {
+        let (source, target) = (self.node(source), self.node(target));
+        self.edges.push((source, target, weight));
+
+        if !directed {
+            self.edges.push((target, source, weight));
+        }
+    }
+
+    /// Build the adjacency matrix of the graph
+    fn build(self) -> ImportedGraph {
+        let size = self.nodes.len();
+        let mut matrix = Matrix::new(size, size, vec![0.0; size * size]);
+
+        for (source, target, weight) in self.edges {
+            let current = matrix.data[target * size + source];
+
+            if current == 0.0 || weight < current {
+                matrix.set_edge(source, target, weight);
+            }
+        }
+
+        ImportedGraph { nodes: self.nodes, matrix }
+    }
+}
+
+/// Import a graph into an adjacency matrix
+///
+/// # Arguments
+///
+/// * `format` - The format of the graph
+/// * `text` - The serialized graph
+///
+/// # Returns
+///
+/// * `Result<ImportedGraph, String>` - The graph or the reason why it can't be parsed
+///
+pub fn import_graph(format: GraphFormat, text: &str) -> Result<ImportedGraph, String> {
+    match format {
+        GraphFormat::GraphMl => parse_graphml(text),
+        GraphFormat::Dot => parse_dot(text),
+        GraphFormat::Csv => parse_csv(text)
+    }
+}
+
+/// Parse a weight, failing with a readable message
+fn parse_weight(value: &str) -> Result<f32, String> {
+    value.trim().parse::<f32>().map_err(|_| format!("The weight {} is not a number", value.trim()))
+}
+
+/// Get the value of an attribute of a XML element
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, String> {
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(|err| err.to_string())?;
+
+        if attribute.key.local_name().as_ref() == name.as_bytes() {
+            return Ok(Some(attribute.unescape_value().map_err(|err| err.to_string())?.into_owned()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Parse a GraphML document
+///
+/// The weight is read from the edge data whose key is named `weight`,
+/// defaulting to `1` when the edge has no weight.
+fn parse_graphml(text: &str) -> Result<ImportedGraph, String> {
+    let mut reader = Reader::from_str(text);
+    let mut graph = GraphBuilder::default();
+    let mut weight_keys = Vec::new();
+    let mut directed = true;
+
+    // The edge being parsed, with the data key being read
+    let mut edge: Option<(String, String, f32, Option<bool>)> = None;
+    let mut data_key: Option<String> = None;
+
+    loop {
+        match reader.read_event().map_err(|err| err.to_string())? {
+            Event::Start(element) | Event::Empty(element) if element.local_name().as_ref() == b"key"
+                && attribute(&element, "attr.name")?.as_deref() == Some("weight") => {
+                weight_keys.extend(attribute(&element, "id")?);
+            },
+            Event::Start(element) | Event::Empty(element) if element.local_name().as_ref() == b"graph" => {
+                directed = attribute(&element, "edgedefault")?.as_deref() != Some("undirected");
+            },
+            Event::Start(element) | Event::Empty(element) if element.local_name().as_ref() == b"node" => {
+                let id = attribute(&element, "id")?.ok_or("A node has no id")?;
+                graph.node(&id);
+            },
+            Event::Start(element) if element.local_name().as_ref() == b"edge" => {
+                edge = Some(read_graphml_edge(&element)?);
+            },
+            Event::Empty(element) if element.local_name().as_ref() == b"edge" => {
+                let (source, target, weight, edge_directed) = read_graphml_edge(&element)?;
+                graph.edge(&source, &target, weight, edge_directed.unwrap_or(directed));
+            },
+            Event::End(element) if element.local_name().as_ref() == b"edge" => {
+                if let Some((source, target, weight, edge_directed)) = edge.take() {
+                    graph.edge(&source, &target, weight, edge_directed.unwrap_or(directed));
+                }
+            },
+            Event::Start(element) if element.local_name().as_ref() == b"data" => {
+                data_key = attribute(&element, "key")?;
+            },
+            Event::End(element) if element.local_name().as_ref() == b"data" => {
+                data_key = None;
+            },
+            Event::Text(content) => {
+                if let (Some((_, _, weight, _)), Some(key)) = (edge.as_mut(), data_key.as_ref()) {
+                    if weight_keys.contains(key) {
+                        *weight = parse_weight(&content.unescape().map_err(|err| err.to_string())?)?;
+                    }
+                }
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(graph.build())
+}
+
+/// Read the source, target and direction of a GraphML edge
+fn read_graphml_edge(element: &BytesStart) -> Result<(String, String, f32, Option<bool>), String> {
+    let source = attribute(element, "source")?.ok_or("An edge has no source")?;
+    let target = attribute(element, "target")?.ok_or("An edge has no target")?;
+    let directed = attribute(element, "directed")?.map(|directed| directed == "true");
+
+    Ok((source, target, 1.0, directed))
+}
+
+/// Parse a DOT document
+///
+/// Supports the subset of DOT used to describe weighted graphs: node and
+/// edge statements, edge chains, and the `weight` attribute of the edges,
+/// defaulting to `1` when the edge has no weight.
+fn parse_dot(text: &str) -> Result<ImportedGraph, String> {
+    let mut graph = GraphBuilder::default();
+
+    // Remove the comments of the document
+    let text: String = text.lines()
+        .map(|line| line.split("//").next().unwrap_or_default())
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // Read the header and the body of the graph
+    let open = text.find('{').ok_or("The graph has no body")?;
+    let close = text.rfind('}').ok_or("The graph body is not closed")?;
+    let header = text[..open].to_ascii_lowercase();
+    let directed = header.split_whitespace().any(|word| word == "digraph");
+
+    for statement in text[open + 1..close].split([';', '\n']) {
+        let statement = statement.trim();
+
+        // Split the statement from its attributes
+        let (body, attributes) = match statement.find('[') {
+            Some(index) => (&statement[..index], &statement[index + 1..statement.rfind(']').unwrap_or(statement.len())]),
+            None => (statement, "")
+        };
+
+        // Skip the empty and graph wide statements
+        let body = body.trim();
+        if body.is_empty() || ["graph", "node", "edge"].contains(&body) || body.contains('=') {
+            continue;
+        }
+
+        // Read the weight of the edge
+        let weight = attributes.split([',', ' '])
+            .filter_map(|attribute| attribute.split_once('='))
+            .find(|(key, _)| key.trim() == "weight")
+            .map(|(_, value)| parse_weight(value.trim().trim_matches('"')))
+            .transpose()?
+            .unwrap_or(1.0);
+
+        // Register the vertices and the edges of the chain
+        let operator = if body.contains("->") { "->" } else { "--" };
+        let vertices: Vec<&str> = body.split(operator).map(|vertex| vertex.trim().trim_matches('"')).collect();
+
+        if vertices.iter().any(|vertex| vertex.is_empty()) {
+            return Err(format!("The statement {} is not valid", statement));
+        }
+
+        if vertices.len() == 1 {
+            graph.node(vertices[0]);
+        }
+
+        for pair in vertices.windows(2) {
+            graph.edge(pair[0], pair[1], weight, directed && operator == "->");
+        }
+    }
+
+    Ok(graph.build())
+}
+
+/// The names of the source and the target columns of the CSV headers, in lowercase
+const CSV_HEADERS: [[&str; 2]; 3] = [["source", "target"], ["from", "to"], ["src", "dst"]];
+
+/// Check if the first row of a CSV edge list is a header
+///
+/// The first two fields name the columns, like `source,target`, or the
+/// third one isn't a weight, as in `a,b,distance`.
+fn is_csv_header(columns: &[&str]) -> bool {
+    let named = match columns {
+        [source, target, ..] => CSV_HEADERS.iter().any(|[name, other]| source.eq_ignore_ascii_case(name) && target.eq_ignore_ascii_case(other)),
+        _ => false
+    };
+
+    named || columns.get(2).is_some_and(|weight| weight.parse::<f32>().is_err())
+}
+
+/// Parse a CSV edge list
+///
+/// Every row is an edge with the columns `source,target[,weight]`, with an
+/// optional header row. Edges without weight default to `1` and are directed.
+fn parse_csv(text: &str) -> Result<ImportedGraph, String> {
+    let mut graph = GraphBuilder::default();
+
+    for (number, line) in text.lines().enumerate() {
+        let columns: Vec<&str> = line.split(',').map(|column| column.trim().trim_matches('"')).collect();
+
+        // Skip the empty lines and the header
+        if line.trim().is_empty() || (number == 0 && is_csv_header(&columns)) {
+            continue;
+        }
+
+        match columns.as_slice() {
+            [source, target] => graph.edge(source, target, 1.0, true),
+            [source, target, weight, ..] => graph.edge(source, target, parse_weight(weight)?, true),
+            _ => return Err(format!("The line {} is not a valid edge", number + 1))
+        }
+    }
+
+    Ok(graph.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_graphml() {
+        // Prepare the graph
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+                <key id="d0" for="edge" attr.name="weight" attr.type="double"/>
+                <graph id="G" edgedefault="undirected">
+                    <node id="a"/>
+                    <node id="b"/>
+                    <node id="c"/>
+                    <edge source="a" target="b"><data key="d0">4</data></edge>
+                    <edge source="b" target="c"><data key="d0">2.5</data></edge>
+                </graph>
+            </graphml>"#;
+
+        // Get the result
+        let graph = import_graph(GraphFormat::GraphMl, text).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(graph.nodes, vec!["a", "b", "c"]);
+        assert_eq!(graph.matrix.data, vec![
+            0.0, 4.0, 0.0,
+            4.0, 0.0, 2.5,
+            0.0, 2.5, 0.0
+        ]);
+    }
+
+    #[test]
+    fn test_import_dot() {
+        // Prepare the graph
+        let text = r#"digraph roads {
+            a -> b [weight=3];
+            b -> c -> a [label="loop", weight=1.5]; // chain
+        }"#;
+
+        // Get the result
+        let graph = import_graph(GraphFormat::Dot, text).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(graph.nodes, vec!["a", "b", "c"]);
+        assert_eq!(graph.matrix.data, vec![
+            0.0, 0.0, 1.5,
+            3.0, 0.0, 0.0,
+            0.0, 1.5, 0.0
+        ]);
+    }
+
+    #[test]
+    fn test_import_csv() {
+        // Prepare the graph
+        let text = "source,target,weight\na,b,2\nb,a,5\na,b,1\n";
+        let unweighted = "Source,Target\na,b\nb,c\n";
+
+        // Get the result
+        let graph = import_graph(GraphFormat::Csv, text).unwrap();
+        let hops = import_graph(GraphFormat::Csv, unweighted).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(graph.nodes, vec!["a", "b"]);
+        assert_eq!(graph.matrix.data, vec![
+            0.0, 5.0,
+            1.0, 0.0
+        ]);
+        assert_eq!(hops.nodes, vec!["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use log::info;
+use serde::Serialize;
+
+/// The sources replacing the embedded ones, loaded once at startup
+static OVERRIDES: OnceLock<HashMap<KernelProgram, String>> = OnceLock::new();
+
+/// The programs of kernels run by the backend
+///
+/// Every program is embedded from its `.cl` file in `src/kernels`, and can
+/// be replaced at startup by a file with the same kernels and arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KernelProgram {
+    /// The kernels of the weighted walk, written for the `real` type of the precision of the walk
+    Walk,
+    /// The kernel of an iteration of the power method of PageRank, always in single precision
+    Pagerank,
+    /// The kernel of a level of the breadth first walks of the unweighted graphs, always in single precision
+    Bfs,
+    /// The kernels of a round of the synchronous push-relabel algorithm, always in single precision
+    ///
+    /// Every thread owns the residual edges leaving its vertex, and the pushes
+    /// of a round are staged in their own matrix, so a round is split in the
+    /// pushes, their application and the relabels, without atomics.
+    Maxflow,
+    /// The kernels of the bidirectional single pair walks, always in single precision
+    ///
+    /// A launch relaxes the edges leaving the frontier of the search from the
+    /// source, or the ones reaching the frontier of the search from the
+    /// target, and a reduction per work-group finds where both searches meet.
+    Bidirectional,
+    /// The kernel run by the readiness probe
+    Sentinel,
+}
+
+impl KernelProgram {
+    /// Every program, in the order they are described
+    pub const ALL: [KernelProgram; 6] = [KernelProgram::Walk, KernelProgram::Pagerank, KernelProgram::Bfs, KernelProgram::Maxflow, KernelProgram::Bidirectional, KernelProgram::Sentinel];
+
+    /// Parse the name of a program
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name, `walk`, `pagerank`, `bfs`, `maxflow`, `bidirectional` or `sentinel`
+    ///
+    /// # Returns
+    ///
+    /// * `Option<KernelProgram>` - The program, if the name is known
+    ///
+    pub fn parse(name: &str) -> Option<KernelProgram> {
+        KernelProgram::ALL.into_iter().find(|program| program.name() == name.trim().to_ascii_lowercase())
+    }
+
+    /// Get the name of the program, as parsed
+    pub fn name(&self) -> &'static str {
+        match self {
+            KernelProgram::Walk => "walk",
+            KernelProgram::Pagerank => "pagerank",
+            KernelProgram::Bfs => "bfs",
+            KernelProgram::Maxflow => "maxflow",
+            KernelProgram::Bidirectional => "bidirectional",
+            KernelProgram::Sentinel => "sentinel"
+        }
+    }
+
+    /// Get the source embedded in the crate
+    pub fn embedded(&self) -> &'static str {
+        match self {
+            KernelProgram::Walk => include_str!("../kernels/walk.cl"),
+            KernelProgram::Pagerank => include_str!("../kernels/pagerank.cl"),
+            KernelProgram::Bfs => include_str!("../kernels/bfs.cl"),
+            KernelProgram::Maxflow => include_str!("../kernels/maxflow.cl"),
+            KernelProgram::Bidirectional => include_str!("../kernels/bidirectional.cl"),
+            KernelProgram::Sentinel => include_str!("../kernels/sentinel.cl")
+        }
+    }
+
+    /// Get the source compiled for the program, the one loaded at startup or the embedded one
+    pub fn source(&self) -> &'static str {
+        OVERRIDES.get()
+            .and_then(|overrides| overrides.get(self))
+            .map_or_else(|| self.embedded(), String::as_str)
+    }
+
+    /// Check if the source of the program has been replaced at startup
+    pub fn is_overridden(&self) -> bool {
+        OVERRIDES.get().is_some_and(|overrides| overrides.contains_key(self))
+    }
+}
+
+/// Read the files replacing the sources of some programs
+///
+/// # Arguments
+///
+/// * `overrides` - The name of every program along with the path of its file
+///
+/// # Returns
+///
+/// * `Result<HashMap<KernelProgram, String>, String>` - The sources, or why a program or a file isn't valid
+///
+fn read_overrides(overrides: &[(String, PathBuf)]) -> Result<HashMap<KernelProgram, String>, String> {
+    overrides.iter()
+        .map(|(name, path)| {
+            let program = KernelProgram::parse(name).ok_or_else(|| format!("The kernel program {} is not known, it must be walk, pagerank, bfs, maxflow, bidirectional or sentinel", name))?;
+            let source = fs::read_to_string(path).map_err(|err| format!("The kernel file {} can't be read: {}", path.display(), err))?;
+
+            Ok((program, source))
+        })
+        .collect()
+}
+
+/// Replace the sources of some programs with the ones of user files
+///
+/// Must be called before the device is opened, as the programs are compiled
+/// then, and only once, so the sources never change while serving.
+///
+/// # Arguments
+///
+/// * `overrides` - The name of every program along with the path of its file
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Nothing, or why the sources can't be replaced
+///
+pub fn override_kernels(overrides: &[(String, PathBuf)]) -> Result<(), String> {
+    let sources = read_overrides(overrides)?;
+
+    for (name, path) in overrides {
+        info!("Using the {} kernels of {}", name, path.display());
+    }
+
+    OVERRIDES.set(sources).map_err(|_| "The kernel sources have already been replaced".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_overrides() {
+        // Prepare a tuned kernel in a file
+        let path = std::env::temp_dir().join(format!("pathwalker-kernel-{}.cl", std::process::id()));
+        fs::write(&path, "__kernel void sentinel(__global int *values) {}\n").unwrap();
+
+        // Get the result
+        let sources = read_overrides(&[("Sentinel".to_string(), path.clone())]);
+        let unknown = read_overrides(&[("dijkstra".to_string(), path.clone())]);
+        let missing = read_overrides(&[("walk".to_string(), path.with_extension("missing"))]);
+        fs::remove_file(&path).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(sources.unwrap()[&KernelProgram::Sentinel], "__kernel void sentinel(__global int *values) {}\n");
+        assert!(unknown.unwrap_err().contains("dijkstra"));
+        assert!(missing.is_err());
+        assert!(KernelProgram::Walk.embedded().contains("__kernel void initialize_algorithm_buffers"));
+        assert_eq!(KernelProgram::parse("maxflow"), Some(KernelProgram::Maxflow));
+    }
+}
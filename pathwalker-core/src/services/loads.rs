@@ -0,0 +1,278 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use crate::models::{EdgeLoad, EdgeLoadRequest, EdgeLoads, Matrix, ValidationRules};
+use crate::solver::validate_static_matrix;
+
+/// The most demands of a request, one for every pair of vertices of the largest graph
+pub const MAX_DEMANDS: usize = 16_384;
+
+/// The rules of the weights of the graphs, as the search can't handle negative ones
+const NON_NEGATIVE: ValidationRules = ValidationRules { reject_negative: true, warn_asymmetric: false };
+
+/// A vertex waiting to be settled by the search, with its distance from the origin
+#[derive(PartialEq)]
+struct Open {
+    distance: f64,
+    vertex: usize,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, so the heap pops the closest vertex first
+        other.distance.total_cmp(&self.distance).then_with(|| other.vertex.cmp(&self.vertex))
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Validate an edge load request
+///
+/// # Arguments
+///
+/// * `request` - The edge load request
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the request isn't valid, if any
+///
+pub fn validate_loads(request: &EdgeLoadRequest) -> Option<&'static str> {
+    let width = request.matrix.width;
+
+    if let Some(message) = validate_static_matrix(&request.matrix) {
+        Some(message)
+    } else if !request.matrix.validate(NON_NEGATIVE).is_valid() {
+        Some("The weights must be finite non-negative numbers")
+    } else if request.demands.is_empty() || request.demands.len() > MAX_DEMANDS {
+        Some("There must be between one and 16384 demands")
+    } else if request.demands.iter().any(|demand| demand.source >= width || demand.target >= width) {
+        Some("The source or the target of a demand are outside of the matrix")
+    } else if request.demands.iter().any(|demand| !demand.volume.is_finite() || demand.volume < 0.0) {
+        Some("The volumes of the demands must be finite non-negative numbers")
+    } else {
+        None
+    }
+}
+
+/// The edges of the shortest paths from an origin, sorted so every vertex comes after its predecessors
+///
+/// # Fields
+///
+/// * `order` - The reachable vertices, from the origin outwards
+/// * `predecessors` - The vertices every vertex is reached from through one of its shortest paths
+/// * `counts` - The number of shortest paths from the origin to every vertex
+///
+pub(crate) struct ShortestPaths {
+    pub order: Vec<usize>,
+    pub predecessors: Vec<Vec<usize>>,
+    pub counts: Vec<f64>,
+}
+
+/// Sort the edges lying on the shortest paths from an origin, counting the paths
+///
+/// The edges are sorted topologically, as the zero weight edges join
+/// vertices at the same distance in any order.
+///
+/// # Arguments
+///
+/// * `matrix` - The validated matrix
+/// * `origin` - The vertex the paths start from
+/// * `reachable` - If every vertex can be reached from the origin
+/// * `on_path` - If an edge, given by its source, target and weight, lies on a shortest path
+///
+/// # Returns
+///
+/// * `Result<ShortestPaths, String>` - The shortest paths, or the reason why they can't be counted
+///
+pub(crate) fn sort_shortest_paths(matrix: &Matrix, origin: usize, reachable: &[bool], on_path: impl Fn(usize, usize, f32) -> bool) -> Result<ShortestPaths, String> {
+    let width = matrix.width;
+    let mut predecessors = vec![vec![]; width];
+    let mut successors = vec![vec![]; width];
+
+    for vertex in (0..width).filter(|vertex| reachable[*vertex]) {
+        for next in (0..width).filter(|next| *next != vertex) {
+            if matrix.edge(vertex, next).is_some_and(|weight| on_path(vertex, next, weight)) {
+                predecessors[next].push(vertex);
+                successors[vertex].push(next);
+            }
+        }
+    }
+
+    let mut pending: Vec<usize> = predecessors.iter().map(Vec::len).collect();
+    let mut order = vec![origin];
+    let mut counts = vec![0.0; width];
+    counts[origin] = 1.0;
+
+    for index in 0.. {
+        let Some(&vertex) = order.get(index) else { break };
+
+        for &next in &successors[vertex] {
+            counts[next] += counts[vertex];
+            pending[next] -= 1;
+
+            if pending[next] == 0 {
+                order.push(next);
+            }
+        }
+    }
+
+    // The vertices left waiting are on a cycle of zero weight edges, with endless shortest paths
+    if order.len() < reachable.iter().filter(|reachable| **reachable).count() {
+        return Err(format!("The shortest paths from {} go through a cycle of zero weight edges, so they can't be counted", origin));
+    }
+
+    Ok(ShortestPaths { order, predecessors, counts })
+}
+
+/// Search the shortest paths from an origin, counting them
+///
+/// The distances are searched with Dijkstra, and the edges lying on a
+/// shortest path are sorted afterwards.
+///
+/// # Arguments
+///
+/// * `matrix` - The validated matrix
+/// * `origin` - The vertex the paths start from
+///
+/// # Returns
+///
+/// * `Result<ShortestPaths, String>` - The shortest paths, or the reason why they can't be counted
+///
+fn shortest_paths(matrix: &Matrix, origin: usize) -> Result<ShortestPaths, String> {
+    let width = matrix.width;
+    let edges = |vertex: usize| (0..width).filter(move |next| *next != vertex).filter_map(move |next| matrix.edge(vertex, next).map(|weight| (next, weight as f64)));
+
+    // Search the distance of every vertex
+    let mut distances = vec![f64::INFINITY; width];
+    let mut open = BinaryHeap::from([Open { distance: 0.0, vertex: origin }]);
+    distances[origin] = 0.0;
+
+    while let Some(Open { distance, vertex }) = open.pop() {
+        // Skip the vertices already settled with a shorter distance
+        if distance > distances[vertex] {
+            continue;
+        }
+
+        for (next, weight) in edges(vertex) {
+            if distance + weight < distances[next] {
+                distances[next] = distance + weight;
+                open.push(Open { distance: distances[next], vertex: next });
+            }
+        }
+    }
+
+    // Keep the edges lying on a shortest path
+    let reachable: Vec<bool> = distances.iter().map(|distance| distance.is_finite()).collect();
+    sort_shortest_paths(matrix, origin, &reachable, |vertex, next, weight| distances[vertex] + weight as f64 == distances[next])
+}
+
+/// Compute the load that a set of trips puts on every edge of a graph
+///
+/// Every demand goes through all the shortest paths between its vertices,
+/// and its volume is split evenly between them, like the betweenness of the
+/// edges restricted to the demands. The paths of the demands sharing their
+/// origin are counted with a single search, walking the shortest paths
+/// backwards from their destinations. The search runs in the CPU, as the
+/// kernels only keep one predecessor for every vertex.
+///
+/// # Arguments
+///
+/// * `request` - The validated edge load request
+///
+/// # Returns
+///
+/// * `Result<EdgeLoads, String>` - The loads of the edges, or the reason why the paths can't be counted
+///
+pub fn edge_loads(request: &EdgeLoadRequest) -> Result<EdgeLoads, String> {
+    let width = request.matrix.width;
+    let mut origins: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    let mut loads: BTreeMap<(usize, usize), (f64, f64)> = BTreeMap::new();
+    let mut unreachable = vec![];
+
+    for (index, demand) in request.demands.iter().enumerate() {
+        origins.entry(demand.source).or_default().push(index);
+    }
+
+    for (origin, demands) in origins {
+        let ShortestPaths { order, predecessors, counts } = shortest_paths(&request.matrix, origin)?;
+
+        // Seed the destinations with the paths and the volume reaching them
+        let mut paths = vec![0.0; width];
+        let mut volumes = vec![0.0; width];
+
+        for index in demands {
+            let demand = &request.demands[index];
+
+            if counts[demand.target] == 0.0 {
+                unreachable.push(index);
+            } else if demand.target != origin {
+                paths[demand.target] += 1.0;
+                volumes[demand.target] += demand.volume / counts[demand.target];
+            }
+        }
+
+        // Walk the shortest paths backwards, so every vertex has gathered its destinations before passing them on
+        for &vertex in order.iter().rev() {
+            for &previous in &predecessors[vertex] {
+                let load = loads.entry((previous, vertex)).or_default();
+                load.0 += counts[previous] * paths[vertex];
+                load.1 += counts[previous] * volumes[vertex];
+
+                paths[previous] += paths[vertex];
+                volumes[previous] += volumes[vertex];
+            }
+        }
+    }
+
+    unreachable.sort_unstable();
+
+    Ok(EdgeLoads {
+        loads: loads.into_iter()
+            .filter(|(_, (paths, _))| *paths > 0.0)
+            .map(|((source, target), (paths, load))| EdgeLoad { source, target, paths, load })
+            .collect(),
+        unreachable
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::Demand;
+    use super::*;
+
+    #[test]
+    fn test_edge_loads() {
+        // Prepare a diamond with two shortest paths from 0 to 3, a longer direct edge, and an isolated vertex
+        let mut matrix = Matrix::new(5, 5, vec![0.0; 25]);
+        matrix.set_edge(0, 1, 1.0);
+        matrix.set_edge(0, 2, 1.0);
+        matrix.set_edge(1, 3, 1.0);
+        matrix.set_edge(2, 3, 1.0);
+        matrix.set_edge(0, 3, 5.0);
+        let demands = vec![
+            Demand { source: 0, target: 3, volume: 10.0 },
+            Demand { source: 0, target: 1, volume: 1.0 },
+            Demand { source: 0, target: 4, volume: 1.0 }
+        ];
+        let request = EdgeLoadRequest { matrix, demands };
+
+        // Get the result
+        let result = edge_loads(&request).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(validate_loads(&request), None);
+        assert_eq!(result.loads, vec![
+            EdgeLoad { source: 0, target: 1, paths: 2.0, load: 6.0 },
+            EdgeLoad { source: 0, target: 2, paths: 1.0, load: 5.0 },
+            EdgeLoad { source: 1, target: 3, paths: 1.0, load: 5.0 },
+            EdgeLoad { source: 2, target: 3, paths: 1.0, load: 5.0 }
+        ]);
+        assert_eq!(result.unreachable, vec![2]);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::models::{CutEdge, Matrix};
+use crate::solver::validate_static_matrix;
+
+/// Validate a maximum flow request
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix of capacities
+/// * `source` - The vertex the flow leaves
+/// * `sink` - The vertex the flow reaches
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the request isn't valid, if any
+///
+pub fn validate_maxflow(matrix: &Matrix, source: usize, sink: usize) -> Option<&'static str> {
+    if let Some(message) = validate_static_matrix(matrix) {
+        Some(message)
+    } else if matrix.has_constraints() {
+        Some("The flows use every edge of the graph, so they can't honor the forbidden nodes, the forbidden edges or the max hops")
+    } else if source >= matrix.width || sink >= matrix.width {
+        Some("The source and the sink must be vertices of the graph")
+    } else if source == sink {
+        Some("The source and the sink must be different vertices")
+    } else {
+        None
+    }
+}
+
+/// Read the capacities of the edges of a matrix
+///
+/// Unlike the weights, the capacities are stored in the row of the vertex
+/// the edge leaves, so every thread of the kernels owns the residual edges
+/// leaving its vertex. The loops are left out, as they carry no flow.
+///
+/// # Arguments
+///
+/// * `matrix` - The validated matrix
+///
+/// # Returns
+///
+/// * `Vec<f32>` - The capacity of every edge, by source and target
+///
+pub fn capacities(matrix: &Matrix) -> Vec<f32> {
+    let width = matrix.width;
+    let mut capacities = vec![0.0; width * width];
+
+    for source in 0..width {
+        for target in (0..width).filter(|target| *target != source) {
+            capacities[source * width + target] = matrix.edge(source, target).unwrap_or_default();
+        }
+    }
+
+    capacities
+}
+
+/// Build the initial preflow of the push-relabel algorithm
+///
+/// Every edge leaving the source is saturated, leaving its capacity as the
+/// excess of the vertex it reaches and as the residual capacity of the edge
+/// going back.
+///
+/// # Arguments
+///
+/// * `capacities` - The capacity of every edge, by source and target
+/// * `width` - The number of vertices
+/// * `source` - The vertex the flow leaves
+///
+/// # Returns
+///
+/// * `(Vec<f32>, Vec<f32>)` - The residual capacities and the excess of every vertex
+///
+pub fn preflow(capacities: &[f32], width: usize, source: usize) -> (Vec<f32>, Vec<f32>) {
+    let mut residual = capacities.to_vec();
+    let mut excess = vec![0.0; width];
+
+    for target in 0..width {
+        let capacity = capacities[source * width + target];
+
+        residual[source * width + target] = 0.0;
+        residual[target * width + source] += capacity;
+        excess[target] += capacity;
+    }
+
+    (residual, excess)
+}
+
+/// Find the minimum cut left by a maximum flow
+///
+/// The side of the source are the vertices it still reaches through the
+/// residual edges, and the cut are the edges leaving that side, which the
+/// flow has saturated.
+///
+/// # Arguments
+///
+/// * `capacities` - The capacity of every edge, by source and target
+/// * `residual` - The residual capacities after the maximum flow
+/// * `width` - The number of vertices
+/// * `source` - The vertex the flow leaves
+///
+/// # Returns
+///
+/// * `Vec<CutEdge>` - The edges of the cut, by source and target
+///
+pub fn min_cut(capacities: &[f32], residual: &[f32], width: usize, source: usize) -> Vec<CutEdge> {
+    let mut reached = vec![false; width];
+    let mut pending = vec![source];
+    reached[source] = true;
+
+    while let Some(vertex) = pending.pop() {
+        for target in 0..width {
+            if !reached[target] && residual[vertex * width + target] > 0.0 {
+                reached[target] = true;
+                pending.push(target);
+            }
+        }
+    }
+
+    (0..width * width)
+        .map(|index| (index / width, index % width))
+        .filter(|(source, target)| reached[*source] && !reached[*target] && capacities[source * width + target] > 0.0)
+        .map(|(source, target)| CutEdge { source, target, capacity: capacities[source * width + target] })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_cut() {
+        // Prepare a network with two parallel routes from 0 to 3, the one through 1 with a bottleneck
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        matrix.set_edge(0, 1, 5.0);
+        matrix.set_edge(1, 3, 2.0);
+        matrix.set_edge(0, 2, 1.0);
+        matrix.set_edge(2, 3, 4.0);
+        let capacities = capacities(&matrix);
+
+        // Get the result, pushing the excess of the preflow to the sink by hand
+        let (mut residual, excess) = preflow(&capacities, 4, 0);
+        residual[7] -= 2.0;
+        residual[13] += 2.0;
+        residual[11] -= 1.0;
+        residual[14] += 1.0;
+        residual[1] += 3.0;
+        residual[4] -= 3.0;
+        let cut = min_cut(&capacities, &residual, 4, 0);
+
+        // Check if the result is correct
+        assert_eq!(validate_maxflow(&matrix, 0, 3), None);
+        assert_eq!(validate_maxflow(&matrix, 2, 2), Some("The source and the sink must be different vertices"));
+        assert!(validate_maxflow(&Matrix { forbidden_nodes: vec![1], ..matrix.clone() }, 0, 3).is_some());
+        assert!(validate_maxflow(&Matrix { max_hops: Some(2), ..matrix.clone() }, 0, 3).is_some());
+        assert_eq!(excess, vec![0.0, 5.0, 1.0, 0.0]);
+        assert_eq!(cut, vec![
+            CutEdge { source: 0, target: 2, capacity: 1.0 },
+            CutEdge { source: 1, target: 3, capacity: 2.0 }
+        ]);
+    }
+}
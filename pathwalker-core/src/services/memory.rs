@@ -0,0 +1,160 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+#[cfg(feature = "opencl")]
+use ocl::{enums::{DeviceInfo, DeviceInfoResult}, Device, Platform};
+use serde::Serialize;
+
+use crate::services::buffer_pool::size_class;
+
+/// The bytes of device memory a walk can use, the memory of the device when zero
+static MEMORY_BUDGET: AtomicU64 = AtomicU64::new(0);
+
+/// The global memory of the first device of the first platform, if there is one
+static FIRST_DEVICE_MEMORY: OnceLock<Option<u64>> = OnceLock::new();
+
+/// The device memory a walk needs and the memory it can use
+///
+/// # Fields
+///
+/// * `required` - The bytes of the matrix and the buffers of the walk
+/// * `available` - The bytes of the budget, or of the device when it's larger
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MemoryShortfall {
+    pub required: u64,
+    pub available: u64,
+}
+
+/// Set the device memory a walk can use
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes a walk can use, or none to use all the memory of the device
+///
+pub fn set_memory_budget(bytes: Option<u64>) {
+    MEMORY_BUDGET.store(bytes.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Get the device memory a walk can use, if it has been limited
+pub fn memory_budget() -> Option<u64> {
+    Some(MEMORY_BUDGET.load(Ordering::Relaxed)).filter(|bytes| *bytes > 0)
+}
+
+/// Get the global memory a device reports
+///
+/// # Arguments
+///
+/// * `device` - The device
+///
+/// # Returns
+///
+/// * `Option<u64>` - The bytes of global memory, if the driver reports them
+///
+#[cfg(feature = "opencl")]
+pub fn global_memory(device: Device) -> Option<u64> {
+    match device.info(DeviceInfo::GlobalMemSize) {
+        Ok(DeviceInfoResult::GlobalMemSize(bytes)) => Some(bytes),
+        _ => None
+    }
+}
+
+/// Get the global memory of the first device of the first platform, the one the walker opens
+///
+/// The device is only queried, not opened, so the memory can be checked
+/// before the first walk compiles the kernels. The builds without OpenCL
+/// walk in the CPU, whose memory isn't checked.
+pub fn first_device_memory() -> Option<u64> {
+    *FIRST_DEVICE_MEMORY.get_or_init(|| {
+        #[cfg(feature = "opencl")]
+        let memory = Platform::first().and_then(Device::first).ok().and_then(global_memory);
+        #[cfg(not(feature = "opencl"))]
+        let memory = None;
+
+        memory
+    })
+}
+
+/// Get the device memory needed to walk a matrix
+///
+/// Counts the matrix, the four weight buffers, the visited buffer and the
+/// flag of the atomic variant, rounded up to the size classes of the pool.
+///
+/// # Arguments
+///
+/// * `width` - The vertices of the matrix
+///
+/// # Returns
+///
+/// * `u64` - The bytes of device memory
+///
+pub fn walk_bytes<T>(width: usize) -> u64 {
+    let weight = std::mem::size_of::<T>();
+    let int = std::mem::size_of::<i32>();
+
+    (size_class(width * width) * weight + 4 * size_class(width) * weight + size_class(width) * int + size_class(1) * int) as u64
+}
+
+/// Get the device memory a walk can use, the smallest of the budget and the memory of the device
+///
+/// # Arguments
+///
+/// * `device_memory` - The bytes of global memory of the device, if they are known
+///
+/// # Returns
+///
+/// * `Option<u64>` - The bytes a walk can use, if the budget or the memory of the device are known
+///
+pub fn available_memory(device_memory: Option<u64>) -> Option<u64> {
+    match (memory_budget(), device_memory) {
+        (Some(budget), Some(device)) => Some(budget.min(device)),
+        (Some(bytes), None) | (None, Some(bytes)) => Some(bytes),
+        (None, None) => None
+    }
+}
+
+/// Check if a walk fits in the device memory and the budget
+///
+/// # Arguments
+///
+/// * `required` - The bytes the walk needs
+/// * `device_memory` - The bytes of global memory of the device, if they are known
+///
+/// # Returns
+///
+/// * `Result<(), MemoryShortfall>` - Nothing, or the memory needed and the memory available when the walk doesn't fit
+///
+pub fn check_fit(required: u64, device_memory: Option<u64>) -> Result<(), MemoryShortfall> {
+    let Some(available) = available_memory(device_memory) else {
+        return Ok(());
+    };
+
+    if required > available {
+        Err(MemoryShortfall { required, available })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_fit() {
+        // Prepare the memory of a walk of 128 vertices in single precision
+        let required = walk_bytes::<f32>(128);
+
+        // Get the result
+        let fits = check_fit(required, Some(required));
+        let short = check_fit(required, Some(required - 1));
+        let unknown = check_fit(required, None);
+
+        // Check if the result is correct
+        assert_eq!(required, (128 * 128 * 4 + 4 * 128 * 4 + 128 * 4 + 64 * 4) as u64);
+        assert!(walk_bytes::<f64>(128) > required);
+        assert_eq!(fits, Ok(()));
+        assert_eq!(short, Err(MemoryShortfall { required, available: required - 1 }));
+        assert_eq!(unknown, Ok(()));
+    }
+}
@@ -0,0 +1,254 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry};
+use tracing::debug_span;
+
+/// The buckets of the kernel timings, from 10µs to 1s
+const KERNEL_BUCKETS: &[f64] = &[0.00001, 0.00005, 0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0];
+
+lazy_static! {
+    /// The registry of the metrics, shared with the metrics of the embedding application
+    pub static ref REGISTRY: Registry = Registry::new();
+
+    static ref KERNEL_DURATION: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new("pathwalker_kernel_duration_seconds", "The execution time of the kernels in the device")
+            .buckets(KERNEL_BUCKETS.to_vec()),
+        &["kernel"]
+    ).unwrap());
+
+    static ref DEVICE_MEMORY: IntGauge = register(IntGauge::new(
+        "pathwalker_device_memory_bytes", "The bytes of device memory used by the buffers"
+    ).unwrap());
+
+    static ref QUEUE_DEPTH: IntGauge = register(IntGauge::new(
+        "pathwalker_queue_depth", "The number of walks running in the device"
+    ).unwrap());
+
+    static ref BUFFER_POOL_REQUESTS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("pathwalker_buffer_pool_requests_total", "The buffers taken from the pool, by whether an idle one was reused"),
+        &["result"]
+    ).unwrap());
+
+    static ref BUFFER_POOL_BYTES: IntGauge = register(IntGauge::new(
+        "pathwalker_buffer_pool_bytes", "The bytes of idle device buffers kept by the pool"
+    ).unwrap());
+}
+
+thread_local! {
+    /// The work of the walks run by the thread since it was last taken
+    static THREAD_USAGE: Cell<WalkUsage> = const { Cell::new(WalkUsage { vertices: 0, kernel_time: Duration::ZERO }) };
+}
+
+/// The work of the walks run by a thread
+///
+/// The walks run in the thread that asked for them, so the work a thread
+/// has done between two takes belongs to the request it was running.
+///
+/// # Fields
+///
+/// * `vertices` - The vertices of the graphs walked
+/// * `kernel_time` - The execution time of the kernels
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WalkUsage {
+    pub vertices: u64,
+    pub kernel_time: Duration,
+}
+
+/// The kernels whose execution time is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelStage {
+    Init,
+    Iterate,
+    Merge,
+}
+
+impl KernelStage {
+    /// Get the label of the stage in the metrics
+    fn label(&self) -> &'static str {
+        match self {
+            KernelStage::Init => "init",
+            KernelStage::Iterate => "iterate",
+            KernelStage::Merge => "merge"
+        }
+    }
+}
+
+/// Register a metric in the shared registry
+///
+/// # Arguments
+///
+/// * `metric` - The metric to register
+///
+/// # Returns
+///
+/// * `T` - The registered metric
+///
+pub fn register<T: prometheus::core::Collector + Clone + 'static>(metric: T) -> T {
+    REGISTRY.register(Box::new(metric.clone())).unwrap();
+    metric
+}
+
+/// Register the metrics of the kernels that haven't been observed yet
+pub fn initialize() {
+    lazy_static::initialize(&KERNEL_DURATION);
+    lazy_static::initialize(&DEVICE_MEMORY);
+    lazy_static::initialize(&QUEUE_DEPTH);
+    lazy_static::initialize(&BUFFER_POOL_REQUESTS);
+    lazy_static::initialize(&BUFFER_POOL_BYTES);
+}
+
+/// Measure the execution time of a kernel, inside a span of its stage
+///
+/// # Arguments
+///
+/// * `stage` - The kernel being run
+/// * `run` - Enqueues the kernel and waits for it to finish
+///
+/// # Returns
+///
+/// * `T` - The result of the kernel
+///
+pub fn time_kernel<T>(stage: KernelStage, run: impl FnOnce() -> T) -> T {
+    let _span = debug_span!("kernel", stage = stage.label()).entered();
+    let started_at = Instant::now();
+    let result = run();
+
+    observe_kernel(stage, started_at.elapsed());
+    result
+}
+
+/// Account the execution time of a kernel measured by the device
+///
+/// # Arguments
+///
+/// * `stage` - The kernel that has run
+/// * `duration` - The time it has run for
+///
+pub fn observe_kernel(stage: KernelStage, duration: Duration) {
+    KERNEL_DURATION.with_label_values(&[stage.label()]).observe(duration.as_secs_f64());
+    THREAD_USAGE.with(|usage| usage.set(WalkUsage { kernel_time: usage.get().kernel_time + duration, ..usage.get() }));
+}
+
+/// Account the vertices of a graph walked by the current thread
+///
+/// # Arguments
+///
+/// * `vertices` - The vertices of the graph
+///
+pub fn count_walked_vertices(vertices: usize) {
+    THREAD_USAGE.with(|usage| usage.set(WalkUsage { vertices: usage.get().vertices + vertices as u64, ..usage.get() }));
+}
+
+/// Take the work of the walks the current thread has run since the last take
+///
+/// # Returns
+///
+/// * `WalkUsage` - The vertices walked and the execution time of the kernels
+///
+pub fn take_walk_usage() -> WalkUsage {
+    THREAD_USAGE.with(Cell::take)
+}
+
+/// Account the device memory allocated or freed by a buffer
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes allocated, or freed when negative
+///
+pub fn add_device_memory(bytes: i64) {
+    DEVICE_MEMORY.add(bytes);
+}
+
+/// Get the device memory used by the buffers
+///
+/// # Returns
+///
+/// * `i64` - The bytes of device memory
+///
+pub fn device_memory() -> i64 {
+    DEVICE_MEMORY.get()
+}
+
+/// Count a buffer taken from the pool
+///
+/// # Arguments
+///
+/// * `hit` - If an idle buffer has been reused instead of allocating one
+///
+pub fn count_buffer_pool(hit: bool) {
+    BUFFER_POOL_REQUESTS.with_label_values(&[if hit { "hit" } else { "miss" }]).inc();
+}
+
+/// Account the bytes of idle buffers kept or taken from the pool
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes kept, or taken when negative
+///
+pub fn add_buffer_pool_bytes(bytes: i64) {
+    BUFFER_POOL_BYTES.add(bytes);
+}
+
+/// A walk running in the device, counted in the queue depth until dropped
+pub struct QueuedWalk;
+
+impl QueuedWalk {
+    /// Count a walk in the queue depth, and its vertices in the work of the thread
+    ///
+    /// # Arguments
+    ///
+    /// * `vertices` - The vertices of the graph walked
+    ///
+    pub fn start(vertices: usize) -> QueuedWalk {
+        QUEUE_DEPTH.inc();
+        count_walked_vertices(vertices);
+        QueuedWalk
+    }
+}
+
+impl Drop for QueuedWalk {
+    fn drop(&mut self) {
+        QUEUE_DEPTH.dec();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_kernel() {
+        // Prepare the metrics
+        initialize();
+
+        // Get the result
+        let result = time_kernel(KernelStage::Init, || 42);
+        let families = REGISTRY.gather();
+
+        // Check if the result is correct
+        assert_eq!(result, 42);
+        assert!(families.iter().any(|family| family.name() == "pathwalker_kernel_duration_seconds"));
+        assert!(families.iter().any(|family| family.name() == "pathwalker_queue_depth"));
+        assert!(families.iter().any(|family| family.name() == "pathwalker_buffer_pool_bytes"));
+    }
+
+    #[test]
+    fn test_take_walk_usage() {
+        // Prepare the work of two walks
+        take_walk_usage();
+        drop(QueuedWalk::start(4));
+        count_walked_vertices(3);
+        observe_kernel(KernelStage::Iterate, Duration::from_millis(5));
+
+        // Get the result
+        let usage = take_walk_usage();
+        let taken = take_walk_usage();
+
+        // Check if the result is correct
+        assert_eq!(usage, WalkUsage { vertices: 7, kernel_time: Duration::from_millis(5) });
+        assert_eq!(taken, WalkUsage::default());
+    }
+}
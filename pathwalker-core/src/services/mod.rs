@@ -0,0 +1,41 @@
+pub mod apsp;
+pub mod backend;
+pub mod budget;
+pub mod buffer_pool;
+pub mod cancellation;
+pub mod centrality;
+pub mod cost;
+pub mod cpu_walker;
+#[cfg(feature = "cuda")]
+pub mod cuda_walker;
+pub mod dag;
+pub mod devices;
+pub mod dispatch;
+pub mod explain;
+pub mod export;
+pub mod failures;
+pub mod flowfield;
+pub mod generator;
+pub mod grid;
+pub mod import;
+pub mod kernels;
+pub mod loads;
+pub mod maxflow;
+pub mod memory;
+pub mod metrics;
+pub mod opencl;
+pub mod overlay;
+pub mod pagerank;
+pub mod postprocess;
+pub mod preflight;
+pub mod program_cache;
+pub mod replan;
+pub mod run_id;
+pub mod shutdown;
+pub mod sortest_path;
+pub mod units;
+pub mod variants;
+pub mod unweighted;
+pub mod walks;
+#[cfg(feature = "wgpu")]
+pub mod wgpu_walker;
@@ -0,0 +1,80 @@
+/// The error, the result and the buffer element types of the OpenCL bindings
+///
+/// The walkers and the services return the errors of the OpenCL bindings,
+/// so the builds without the `opencl` feature replace them with types of
+/// the same shape, and the crates using them build the same either way.
+#[cfg(feature = "opencl")]
+pub use ocl::{Error, OclPrm, Result};
+
+/// The stand-ins of the types of the OpenCL bindings, in the builds without them
+#[cfg(not(feature = "opencl"))]
+pub use unavailable::{Error, OclPrm, Result};
+
+#[cfg(not(feature = "opencl"))]
+mod unavailable {
+    use std::fmt::{self, Debug, Display};
+
+    /// The error of a walk, in the builds without OpenCL
+    ///
+    /// # Fields
+    ///
+    /// * `0` - The message of the error
+    ///
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Error(String);
+
+    impl Error {
+        /// Returns the status of the OpenCL call that has failed, which never fails without OpenCL
+        pub fn api_status(&self) -> Option<&'static str> {
+            None
+        }
+    }
+
+    impl Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    impl From<String> for Error {
+        fn from(message: String) -> Error {
+            Error(message)
+        }
+    }
+
+    impl From<&str> for Error {
+        fn from(message: &str) -> Error {
+            Error(message.to_string())
+        }
+    }
+
+    /// The result of a walk, in the builds without OpenCL
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    /// The element types of the device buffers, in the builds without OpenCL
+    pub trait OclPrm: Debug + Clone + Copy + Default + PartialEq + Send + Sync + 'static {}
+
+    impl OclPrm for f32 {}
+    impl OclPrm for f64 {}
+    impl OclPrm for u32 {}
+    impl OclPrm for i32 {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error() {
+        // Prepare an error from a message
+        let error = Error::from("The device has failed".to_string());
+
+        // Get the result
+        let message = error.to_string();
+
+        // Check if the result is correct
+        assert!(message.contains("The device has failed"));
+    }
+}
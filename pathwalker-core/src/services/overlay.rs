@@ -0,0 +1,111 @@
+use crate::models::{Matrix, Perturbation};
+
+/// The most perturbations of a query
+pub const MAX_PERTURBATIONS: usize = 1024;
+
+/// Check the weight of an edge added by a perturbation
+fn valid_weight(matrix: &Matrix, weight: f32) -> bool {
+    weight.is_finite() && weight != f32::MAX && (weight > 0.0 || (weight == 0.0 && matrix.zero_edges))
+}
+
+/// Apply some perturbations to a copy of a matrix
+///
+/// The perturbations are applied in order, so a later one sees the changes
+/// of the earlier ones, and the matrix itself is never changed, so what-if
+/// queries can be asked against a graph without changing it. The removed
+/// edges are set to `f32::MAX`, which is a missing edge with and without
+/// zero weight edges.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix of the graph
+/// * `perturbations` - The changes to apply
+///
+/// # Returns
+///
+/// * `Result<Matrix, String>` - The changed copy of the matrix, or the reason why a perturbation isn't valid
+///
+pub fn apply_perturbations(matrix: &Matrix, perturbations: &[Perturbation]) -> Result<Matrix, String> {
+    let width = matrix.width;
+    let mut overlay = matrix.clone();
+
+    if perturbations.len() > MAX_PERTURBATIONS {
+        return Err(format!("There can't be more than {} perturbations", MAX_PERTURBATIONS));
+    }
+
+    for (index, perturbation) in perturbations.iter().enumerate() {
+        // Check the vertices of the perturbation before applying it
+        let vertices = match perturbation {
+            Perturbation::AddEdge { source, target, .. } | Perturbation::RemoveEdge { source, target } => vec![*source, *target],
+            Perturbation::RemoveNode { vertex } => vec![*vertex],
+            Perturbation::ScaleWeights { vertices, .. } => vertices.clone()
+        };
+
+        if let Some(vertex) = vertices.iter().find(|vertex| **vertex >= width) {
+            return Err(format!("The vertex {} of the perturbation {} is outside of the graph", vertex, index));
+        }
+
+        match perturbation {
+            Perturbation::AddEdge { weight, .. } if !valid_weight(&overlay, *weight) => {
+                return Err(format!("The weight {} of the perturbation {} isn't a valid edge weight", weight, index));
+            },
+            Perturbation::AddEdge { source, target, weight } => overlay.set_edge(*source, *target, *weight),
+            Perturbation::RemoveEdge { source, target } => overlay.set_edge(*source, *target, f32::MAX),
+            Perturbation::RemoveNode { vertex } => {
+                for other in (0..width).filter(|other| other != vertex) {
+                    overlay.set_edge(*vertex, other, f32::MAX);
+                    overlay.set_edge(other, *vertex, f32::MAX);
+                }
+            },
+            Perturbation::ScaleWeights { factor, .. } if !factor.is_finite() || *factor <= 0.0 => {
+                return Err(format!("The factor {} of the perturbation {} must be a positive number", factor, index));
+            },
+            Perturbation::ScaleWeights { vertices, factor } => {
+                let mut region = vec![false; width];
+                vertices.iter().for_each(|vertex| region[*vertex] = true);
+
+                // Read every edge before scaling any, as undirected edges share their cells
+                let edges: Vec<(usize, usize, f32)> = (0..width)
+                    .flat_map(|source| (0..width).map(move |target| (source, target)))
+                    .filter(|(source, target)| source != target && (region[*source] || region[*target]))
+                    .filter_map(|(source, target)| overlay.edge(source, target).map(|weight| (source, target, weight)))
+                    .collect();
+
+                for (source, target, weight) in edges {
+                    overlay.set_edge(source, target, (weight * factor).min(f32::MAX.next_down()));
+                }
+            }
+        }
+    }
+
+    Ok(overlay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_perturbations() {
+        // Prepare a directed path of three vertices and some changes
+        let mut matrix = Matrix::new(3, 3, vec![0.0; 9]);
+        matrix.set_edge(0, 1, 1.0);
+        matrix.set_edge(1, 2, 2.0);
+        let perturbations = vec![
+            Perturbation::AddEdge { source: 0, target: 2, weight: 5.0 },
+            Perturbation::ScaleWeights { vertices: vec![2], factor: 2.0 },
+            Perturbation::RemoveEdge { source: 0, target: 1 }
+        ];
+
+        // Get the result
+        let overlay = apply_perturbations(&matrix, &perturbations).unwrap();
+        let removed = apply_perturbations(&matrix, &[Perturbation::RemoveNode { vertex: 1 }]).unwrap();
+
+        // Check if the result is correct
+        assert_eq!([overlay.edge(0, 1), overlay.edge(1, 2), overlay.edge(0, 2)], [None, Some(4.0), Some(10.0)]);
+        assert_eq!([removed.edge(0, 1), removed.edge(1, 2)], [None, None]);
+        assert_eq!(matrix.edge(0, 1), Some(1.0));
+        assert_eq!(apply_perturbations(&matrix, &[Perturbation::RemoveNode { vertex: 3 }]), Err("The vertex 3 of the perturbation 0 is outside of the graph".to_string()));
+        assert!(apply_perturbations(&matrix, &[Perturbation::AddEdge { source: 0, target: 2, weight: 0.0 }]).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::models::{Matrix, PageRankRequest};
+use crate::solver::validate_static_matrix;
+
+/// The most iterations of the power method a request can ask for
+pub const MAX_RANK_ITERATIONS: usize = 10_000;
+
+/// Validate a PageRank request
+///
+/// # Arguments
+///
+/// * `request` - The PageRank request
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the request isn't valid, if any
+///
+pub fn validate_pagerank(request: &PageRankRequest) -> Option<&'static str> {
+    if let Some(message) = validate_static_matrix(&request.matrix) {
+        Some(message)
+    } else if !(0.0..1.0).contains(&request.damping) {
+        Some("The damping factor must be at least zero and lower than one")
+    } else if !request.tolerance.is_finite() || request.tolerance <= 0.0 {
+        Some("The tolerance must be a positive number")
+    } else if request.max_iterations == 0 || request.max_iterations > MAX_RANK_ITERATIONS {
+        Some("There must be between one and 10000 iterations")
+    } else {
+        None
+    }
+}
+
+/// Build the transition matrix of the random surfer of a graph
+///
+/// The transition from a vertex to every vertex it has an edge to is the
+/// inverse of its edges, stored in the row of the target like the weights,
+/// so the kernel gathers the rank flowing into a vertex from its own row.
+/// The loops are left out, as in the walks.
+///
+/// # Arguments
+///
+/// * `matrix` - The validated matrix
+///
+/// # Returns
+///
+/// * `(Vec<f32>, Vec<bool>)` - The transitions, and which vertices have no edges to leave through
+///
+pub fn transition_matrix(matrix: &Matrix) -> (Vec<f32>, Vec<bool>) {
+    let width = matrix.width;
+    let edges = |source: usize| (0..width).filter(move |target| *target != source && matrix.edge(source, *target).is_some());
+    let mut transitions = vec![0.0; width * width];
+    let mut dangling = vec![false; width];
+
+    for source in 0..width {
+        let degree = edges(source).count();
+        dangling[source] = degree == 0;
+
+        for target in edges(source) {
+            transitions[target * width + source] = 1.0 / degree as f32;
+        }
+    }
+
+    (transitions, dangling)
+}
+
+/// Get the rank every vertex receives without following an edge
+///
+/// Every vertex receives an equal share of the jumps to random vertices,
+/// along with the rank of the vertices without edges, whose surfers always
+/// jump.
+///
+/// # Arguments
+///
+/// * `ranks` - The rank of every vertex in the last iteration
+/// * `dangling` - Which vertices have no edges to leave through
+/// * `damping` - The probability of following an edge
+///
+/// # Returns
+///
+/// * `f32` - The rank teleported to every vertex
+///
+pub fn teleport(ranks: &[f32], dangling: &[bool], damping: f32) -> f32 {
+    let width = ranks.len() as f32;
+    let stranded: f32 = ranks.iter().zip(dangling).filter(|(_, dangling)| **dangling).map(|(rank, _)| rank).sum();
+
+    (1.0 - damping) / width + damping * stranded / width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transition_matrix() {
+        // Prepare a vertex with edges to two others, one of them going back, and a dead end
+        let mut matrix = Matrix::new(3, 3, vec![0.0; 9]);
+        matrix.set_edge(0, 1, 2.0);
+        matrix.set_edge(0, 2, 5.0);
+        matrix.set_edge(1, 0, 1.0);
+        let request = PageRankRequest { matrix: matrix.clone(), damping: 0.85, tolerance: 1e-6, max_iterations: 100 };
+
+        // Get the result
+        let (transitions, dangling) = transition_matrix(&matrix);
+
+        // Check if the result is correct
+        assert_eq!(validate_pagerank(&request), None);
+        assert_eq!(validate_pagerank(&PageRankRequest { damping: 1.0, ..request }), Some("The damping factor must be at least zero and lower than one"));
+        assert_eq!(transitions, vec![0.0, 1.0, 0.0, 0.5, 0.0, 0.0, 0.5, 0.0, 0.0]);
+        assert_eq!(dangling, vec![false, false, true]);
+        assert!((teleport(&[0.25, 0.25, 0.5], &dangling, 0.5) - 0.25).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::models::{GridRequest, GridTopology, Leg, Matrix, Position, ProcessedRoute, Transform};
+use crate::services::grid::neighbours;
+use crate::utils::round_decimals_f64;
+
+/// The most transforms of a pipeline
+pub const MAX_TRANSFORMS: usize = 16;
+
+impl Transform {
+    /// Get the name of the transform, as written in the requests
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transform::SimplifyCollinear => "simplify_collinear",
+            Transform::AggregateLegs { .. } => "aggregate_legs",
+            Transform::ConvertUnits { .. } => "convert_units"
+        }
+    }
+
+    /// Run the transform over a route
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - The route, as left by the previous transforms
+    /// * `columns` - The columns of the grid of the route, to find the labels of its cells
+    /// * `topology` - The topology of the grid of the route, to find the steps of its cells
+    ///
+    /// # Returns
+    ///
+    /// * `ProcessedRoute` - The transformed route
+    ///
+    pub fn apply(&self, mut route: ProcessedRoute, columns: usize, topology: GridTopology) -> ProcessedRoute {
+        match self {
+            Transform::SimplifyCollinear => {
+                route.legs = merge_legs(route.legs, |previous, leg| direction(previous, topology).is_some() && direction(previous, topology) == direction(leg, topology));
+            },
+            Transform::AggregateLegs { labels } => {
+                for leg in &mut route.legs {
+                    leg.label = Some(labels[place(leg.to, columns)].clone());
+                }
+
+                route.legs = merge_legs(route.legs, |previous, leg| previous.label == leg.label);
+            },
+            Transform::ConvertUnits { factor, unit } => {
+                for leg in &mut route.legs {
+                    leg.cost *= factor;
+                }
+
+                route.unit = unit.clone().or(route.unit);
+            }
+        }
+
+        route.cost = route.legs.iter().map(|leg| leg.cost).sum();
+        route
+    }
+}
+
+impl ProcessedRoute {
+    /// Round the costs of the legs and the route to a number of decimals
+    ///
+    /// # Arguments
+    ///
+    /// * `decimals` - The decimals to keep, keeping every one when not given
+    ///
+    /// # Returns
+    ///
+    /// * `ProcessedRoute` - The route with its costs rounded
+    ///
+    pub fn round(mut self, decimals: Option<u32>) -> ProcessedRoute {
+        for leg in &mut self.legs {
+            leg.cost = round_decimals_f64(leg.cost, decimals);
+        }
+
+        self.cost = round_decimals_f64(self.cost, decimals);
+        self
+    }
+}
+
+/// Get the index of a place, counting the cells of a grid row by row
+fn place(position: Position, columns: usize) -> usize {
+    match position {
+        Position::Cell([column, row]) => row * columns + column,
+        Position::Vertex(vertex) => vertex
+    }
+}
+
+/// Get the direction of a straight leg of a grid, as the step of the topology it repeats
+///
+/// The legs that turn, like the ones merged by their labels, have no
+/// direction, and neither have the offsets that aren't a step between
+/// neighbours, like the diagonals of the square grids or the `(1, 1)` axial
+/// offsets of the hex grids, which take two steps.
+fn direction(leg: &Leg, topology: GridTopology) -> Option<(isize, isize)> {
+    let (Position::Cell(from), Position::Cell(to)) = (leg.from, leg.to) else {
+        return None;
+    };
+    let (dx, dy) = (to[0] as isize - from[0] as isize, to[1] as isize - from[1] as isize);
+    let (mut a, mut b) = (dx.unsigned_abs(), dy.unsigned_abs());
+
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    if a == 0 || a != leg.steps {
+        return None;
+    }
+
+    let step = (dx / a as isize, dy / a as isize);
+    neighbours(topology).contains(&step).then_some(step)
+}
+
+/// Merge the consecutive legs of a route that satisfy a condition
+///
+/// The merged legs add up their costs and steps, and keep their label when
+/// all of them share it.
+///
+/// # Arguments
+///
+/// * `legs` - The legs of the route
+/// * `mergeable` - If a leg can be merged into the previous one
+///
+/// # Returns
+///
+/// * `Vec<Leg>` - The merged legs
+///
+fn merge_legs(legs: Vec<Leg>, mergeable: impl Fn(&Leg, &Leg) -> bool) -> Vec<Leg> {
+    let mut merged: Vec<Leg> = Vec::with_capacity(legs.len());
+
+    for leg in legs {
+        match merged.last_mut() {
+            Some(previous) if mergeable(previous, &leg) => {
+                previous.to = leg.to;
+                previous.cost += leg.cost;
+                previous.steps += leg.steps;
+
+                if previous.label != leg.label {
+                    previous.label = None;
+                }
+            },
+            _ => merged.push(leg)
+        }
+    }
+
+    merged
+}
+
+/// Validate a post-processing pipeline
+///
+/// # Arguments
+///
+/// * `transforms` - The transforms of the pipeline, in order
+/// * `places` - The number of vertices or cells of the map of the route
+/// * `grid` - If the route goes through the cells of a grid
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the pipeline isn't valid, if any
+///
+pub fn validate_pipeline(transforms: &[Transform], places: usize, grid: bool) -> Option<&'static str> {
+    if transforms.len() > MAX_TRANSFORMS {
+        return Some("The pipeline can't have more than 16 transforms");
+    }
+
+    transforms.iter().find_map(|transform| match transform {
+        Transform::SimplifyCollinear if !grid => Some("The simplify_collinear transform needs the cells of a grid"),
+        Transform::AggregateLegs { labels } if labels.len() != places => Some("The aggregate_legs transform needs a label for every vertex or cell"),
+        Transform::ConvertUnits { factor, .. } if !factor.is_finite() || *factor <= 0.0 => Some("The factor of the convert_units transform must be a positive number"),
+        _ => None
+    })
+}
+
+/// Split the cells of a grid path into legs, costing the cells they enter
+///
+/// # Arguments
+///
+/// * `request` - The grid request of the path
+/// * `cells` - The cells of the path, from the start to the goal
+///
+/// # Returns
+///
+/// * `Vec<Leg>` - A leg for every step of the path
+///
+pub fn grid_legs(request: &GridRequest, cells: &[[usize; 2]]) -> Vec<Leg> {
+    cells.windows(2).map(|step| Leg {
+        from: Position::Cell(step[0]),
+        to: Position::Cell(step[1]),
+        cost: request.costs[step[1][1] * request.width + step[1][0]] as f64,
+        steps: 1,
+        label: None
+    }).collect()
+}
+
+/// Split a route of vertices into legs, costing the edges they go through
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix of the route
+/// * `route` - The vertices of the route, from the source to the target
+///
+/// # Returns
+///
+/// * `Vec<Leg>` - A leg for every edge of the route
+///
+pub fn route_legs(matrix: &Matrix, route: &[usize]) -> Vec<Leg> {
+    route.windows(2).map(|step| Leg {
+        from: Position::Vertex(step[0]),
+        to: Position::Vertex(step[1]),
+        cost: matrix.edge(step[0], step[1]).unwrap_or_default() as f64,
+        steps: 1,
+        label: None
+    }).collect()
+}
+
+/// Run a post-processing pipeline over the legs of a route
+///
+/// The transforms are composed in order, each one receiving the route left
+/// by the previous one, so a route can be simplified before its legs are
+/// aggregated, and its units converted at the end.
+///
+/// # Arguments
+///
+/// * `legs` - The legs of the route
+/// * `transforms` - The validated transforms of the pipeline
+/// * `columns` - The columns of the grid of the route, or zero for a graph
+/// * `topology` - The topology of the grid of the route, ignored for a graph
+///
+/// # Returns
+///
+/// * `ProcessedRoute` - The route after the pipeline
+///
+pub fn postprocess(legs: Vec<Leg>, transforms: &[Transform], columns: usize, topology: GridTopology) -> ProcessedRoute {
+    let cost = legs.iter().map(|leg| leg.cost).sum();
+
+    transforms.iter().fold(ProcessedRoute { legs, cost, unit: None }, |route, transform| transform.apply(route, columns, topology))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_postprocess() {
+        // Prepare an L shaped path of a square grid, with a swamp along its second arm
+        let request = GridRequest {
+            topology: Default::default(),
+            width: 3,
+            height: 3,
+            costs: vec![1.0, 1.0, 1.0, 1.0, 1.0, 4.0, 1.0, 1.0, 4.0],
+            start: [0, 0],
+            goal: [2, 2],
+            weight: None,
+            round: None,
+            postprocess: vec![]
+        };
+        let legs = grid_legs(&request, &[[0, 0], [1, 0], [2, 0], [2, 1], [2, 2]]);
+        let labels = ["road", "road", "road", "road", "road", "swamp", "road", "road", "swamp"].map(String::from).to_vec();
+        let transforms = vec![
+            Transform::AggregateLegs { labels },
+            Transform::SimplifyCollinear,
+            Transform::ConvertUnits { factor: 0.5, unit: Some("hours".to_string()) }
+        ];
+
+        // Get the result
+        let simplified = postprocess(legs.clone(), &transforms[1..2], 3, GridTopology::Square);
+        let processed = postprocess(legs, &transforms, 3, GridTopology::Square);
+
+        // Check if the result is correct
+        assert_eq!(validate_pipeline(&transforms, 9, true), None);
+        assert_eq!(validate_pipeline(&transforms, 9, false), Some("The simplify_collinear transform needs the cells of a grid"));
+        assert_eq!(simplified.legs.iter().map(|leg| (leg.to, leg.steps)).collect::<Vec<_>>(), vec![(Position::Cell([2, 0]), 2), (Position::Cell([2, 2]), 2)]);
+        assert_eq!(simplified.cost, 10.0);
+        assert_eq!(processed.legs, vec![
+            Leg { from: Position::Cell([0, 0]), to: Position::Cell([2, 0]), cost: 1.0, steps: 2, label: Some("road".to_string()) },
+            Leg { from: Position::Cell([2, 0]), to: Position::Cell([2, 2]), cost: 4.0, steps: 2, label: Some("swamp".to_string()) }
+        ]);
+        assert_eq!(processed.cost, 5.0);
+        assert_eq!(processed.unit.as_deref(), Some("hours"));
+    }
+
+    #[test]
+    fn test_simplify_collinear_steps() {
+        // Prepare a straight run of a hex grid, and a staircase of a square grid labelled by its turns
+        let hex = GridRequest {
+            topology: GridTopology::Hex,
+            width: 4,
+            height: 3,
+            costs: vec![1.0; 12],
+            start: [0, 2],
+            goal: [3, 0],
+            weight: None,
+            round: None,
+            postprocess: vec![]
+        };
+        let square = GridRequest { topology: GridTopology::Square, width: 3, height: 3, costs: vec![1.0; 9], start: [0, 0], goal: [2, 2], ..hex.clone() };
+        let labels = ["a", "a", "a", "a", "a", "b", "a", "b", "b"].map(String::from).to_vec();
+        let staircase = vec![Transform::AggregateLegs { labels }, Transform::SimplifyCollinear];
+
+        // Get the result
+        let run = postprocess(grid_legs(&hex, &[[0, 2], [1, 1], [2, 0], [3, 0]]), &[Transform::SimplifyCollinear], 4, GridTopology::Hex);
+        let stairs = postprocess(grid_legs(&square, &[[0, 0], [1, 0], [1, 1], [2, 1], [2, 2]]), &staircase, 3, GridTopology::Square);
+
+        // Check if the result is correct
+        assert_eq!(run.legs.iter().map(|leg| (leg.to, leg.steps)).collect::<Vec<_>>(), vec![(Position::Cell([2, 0]), 2), (Position::Cell([3, 0]), 1)]);
+        assert_eq!(stairs.legs.iter().map(|leg| (leg.to, leg.steps)).collect::<Vec<_>>(), vec![(Position::Cell([1, 1]), 2), (Position::Cell([2, 2]), 2)]);
+    }
+}
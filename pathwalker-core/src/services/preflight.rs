@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::models::{CellDiagnostic, Matrix, ValidationRules, MAX_DIAGNOSTICS};
+use crate::services::memory::{available_memory, walk_bytes};
+use crate::solver::validate_matrix;
+
+/// The diagnostics of a matrix checked before walking it
+///
+/// The checks run in the CPU without opening the device, so a client can
+/// find out whether a walk would be rejected before paying for it. The
+/// cells and the connectivity are only checked when the dimensions are
+/// valid, as they can't be read otherwise.
+///
+/// # Fields
+///
+/// * `valid` - If the matrix would be walked, with valid dimensions and weights fitting in the memory
+/// * `dimensions` - Why the dimensions of the matrix or the source aren't valid, if they aren't
+/// * `errors` - The cells whose weights can't be walked
+/// * `warnings` - The cells whose weights are suspicious
+/// * `reachable` - The vertices reachable from the source, the source included
+/// * `unreachable` - The first vertices that can't be reached from the source
+/// * `required_bytes` - The device memory the walk needs
+/// * `available_bytes` - The device memory a walk can use, if it's known
+/// * `fits` - If the walk fits in the device memory
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Preflight {
+    pub valid: bool,
+    pub dimensions: Option<String>,
+    pub errors: Vec<CellDiagnostic>,
+    pub warnings: Vec<CellDiagnostic>,
+    pub reachable: usize,
+    pub unreachable: Vec<usize>,
+    pub required_bytes: u64,
+    pub available_bytes: Option<u64>,
+    pub fits: bool,
+}
+
+/// Find the vertices reachable from a source
+///
+/// The edges are read the same way the kernels do, so the vertices the walk
+/// would leave without a distance are the ones not reachable.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix, with valid dimensions
+/// * `source` - The vertex the walk starts from
+///
+/// # Returns
+///
+/// * `Vec<bool>` - If every vertex is reachable from the source
+///
+pub fn reachable_vertices(matrix: &Matrix, source: usize) -> Vec<bool> {
+    let mut reached = vec![false; matrix.width];
+    let mut pending = VecDeque::from([source]);
+    reached[source] = true;
+
+    // Visit the vertices in breadth first order, queueing the targets of their edges
+    while let Some(vertex) = pending.pop_front() {
+        for (target, reached) in reached.iter_mut().enumerate() {
+            if !*reached && matrix.edge(vertex, target).is_some() {
+                *reached = true;
+                pending.push_back(target);
+            }
+        }
+    }
+
+    reached
+}
+
+/// Check a matrix before walking it
+///
+/// Runs the checks of the walks, the dimensions, the weights of the cells
+/// and the memory the walk needs, and finds the vertices the source can't
+/// reach, without launching any kernel.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to check
+/// * `source` - The vertex the walk would start from
+/// * `rules` - What is checked in the cells besides the finite weights
+/// * `device_memory` - The bytes of global memory of the device, if they are known
+///
+/// # Returns
+///
+/// * `Preflight` - The diagnostics of the matrix
+///
+pub fn preflight(matrix: &Matrix, source: usize, rules: ValidationRules, device_memory: Option<u64>) -> Preflight {
+    let required_bytes = walk_bytes::<f32>(matrix.width);
+    let available_bytes = available_memory(device_memory);
+    let fits = available_bytes.is_none_or(|available| required_bytes <= available);
+
+    // Check the dimensions, without which the cells can't be read
+    let dimensions = validate_matrix(matrix)
+        .map(str::to_string)
+        .or_else(|| (source >= matrix.width).then(|| format!("The source {} is outside of the matrix", source)));
+
+    if let Some(message) = dimensions {
+        return Preflight {
+            valid: false,
+            dimensions: Some(message),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            reachable: 0,
+            unreachable: Vec::new(),
+            required_bytes,
+            available_bytes,
+            fits
+        };
+    }
+
+    // Check the weights and the vertices the walk would reach
+    let validation = matrix.validate(rules);
+    let reached = reachable_vertices(matrix, source);
+
+    Preflight {
+        valid: validation.is_valid() && fits,
+        dimensions: None,
+        reachable: reached.iter().filter(|reached| **reached).count(),
+        unreachable: (0..matrix.width).filter(|vertex| !reached[*vertex]).take(MAX_DIAGNOSTICS).collect(),
+        errors: validation.errors,
+        warnings: validation.warnings,
+        required_bytes,
+        available_bytes,
+        fits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preflight() {
+        // Prepare a chain of three vertices with an isolated one, and a matrix with an invalid weight
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        for (source, target) in [(0, 1), (1, 2)] {
+            matrix.data[target * 4 + source] = 1.0;
+        }
+        let mut invalid = matrix.clone();
+        invalid.data[5] = f32::NAN;
+
+        // Get the result
+        let report = preflight(&matrix, 0, ValidationRules::default(), Some(1 << 20));
+        let outside = preflight(&matrix, 4, ValidationRules::default(), None);
+        let rejected = preflight(&invalid, 0, ValidationRules::default(), None);
+        let small = preflight(&matrix, 0, ValidationRules::default(), Some(64));
+
+        // Check if the result is correct
+        assert!(report.valid);
+        assert_eq!((report.reachable, report.unreachable.clone()), (3, vec![3]));
+        assert_eq!(report.available_bytes, Some(1 << 20));
+        assert_eq!(outside.dimensions.as_deref(), Some("The source 4 is outside of the matrix"));
+        assert!(!rejected.valid && rejected.errors.len() == 1);
+        assert!(!small.valid && !small.fits);
+    }
+}
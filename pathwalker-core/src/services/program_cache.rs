@@ -0,0 +1,208 @@
+use std::env;
+#[cfg(feature = "opencl")]
+use std::fs;
+#[cfg(feature = "opencl")]
+use std::path::Path;
+use std::path::PathBuf;
+
+#[cfg(feature = "opencl")]
+use log::{debug, warn};
+#[cfg(feature = "opencl")]
+use ocl::{enums::{DeviceInfo, ProgramInfo, ProgramInfoResult}, Context, Device, Program, Result};
+use sha2::{Digest, Sha256};
+
+/// The environment variable with the directory of the compiled programs, or `none` to compile them on every boot
+pub const PROGRAM_CACHE_VARIABLE: &str = "PATHWALKER_PROGRAM_CACHE";
+
+/// The directory of the compiled programs inside the cache directory of the user
+const PROGRAM_CACHE_DIR: &str = "pathwalker/programs";
+
+/// Find the directory of the compiled programs
+///
+/// # Arguments
+///
+/// * `setting` - The value of `PATHWALKER_PROGRAM_CACHE`, if it's set
+/// * `cache_home` - The cache directory of the user, if it has one
+///
+/// # Returns
+///
+/// * `Option<PathBuf>` - The directory, or none when the programs aren't cached
+///
+fn resolve_cache_dir(setting: Option<String>, cache_home: Option<PathBuf>) -> Option<PathBuf> {
+    match setting {
+        Some(dir) if dir.is_empty() || dir.eq_ignore_ascii_case("none") => None,
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => cache_home.map(|dir| dir.join(PROGRAM_CACHE_DIR))
+    }
+}
+
+/// Find the directory of the compiled programs from the environment
+///
+/// The directory is `PATHWALKER_PROGRAM_CACHE`, or `pathwalker/programs`
+/// inside `XDG_CACHE_HOME` or `~/.cache` when it isn't set.
+///
+/// # Returns
+///
+/// * `Option<PathBuf>` - The directory, or none when the programs aren't cached
+///
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache_home = env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").filter(|dir| !dir.is_empty()).map(|home| PathBuf::from(home).join(".cache")));
+
+    resolve_cache_dir(env::var(PROGRAM_CACHE_VARIABLE).ok(), cache_home)
+}
+
+/// Get the key of a program compiled for a device
+///
+/// # Arguments
+///
+/// * `device` - The name of the device
+/// * `driver` - The version of the driver of the device
+/// * `source` - The source of the program, with its definitions
+///
+/// # Returns
+///
+/// * `String` - The SHA-256 digest of the device, the driver and the source
+///
+pub fn cache_key(device: &str, driver: &str, source: &str) -> String {
+    hex::encode(Sha256::new()
+        .chain_update(device).chain_update([0])
+        .chain_update(driver).chain_update([0])
+        .chain_update(source)
+        .finalize())
+}
+
+/// Compile a program for a device, reusing the binary compiled on an earlier boot
+///
+/// The binaries are kept in the directory of `cache_dir`, keyed by the
+/// device, its driver and the source, so an upgrade of any of them compiles
+/// the program again. A binary the driver rejects is compiled again from
+/// the source and replaced, and a directory that can't be written only
+/// leaves the program uncached.
+///
+/// # Arguments
+///
+/// * `context` - The context of the device
+/// * `device` - The device to compile the program for
+/// * `source` - The source of the program
+///
+/// # Returns
+///
+/// * `Result<Program>` - The program, or the error of the compiler
+///
+#[cfg(feature = "opencl")]
+pub fn build_program(context: &Context, device: Device, source: &str) -> Result<Program> {
+    match cache_dir() {
+        Some(dir) => build_cached_program(&dir, context, device, source),
+        None => compile_program(context, device, source)
+    }
+}
+
+/// Compile a program for a device from its source
+#[cfg(feature = "opencl")]
+fn compile_program(context: &Context, device: Device, source: &str) -> Result<Program> {
+    Program::builder()
+        .src(source).devices(device)
+        .build(context)
+}
+
+/// Compile a program for a device, reusing the binary kept in a directory
+///
+/// # Arguments
+///
+/// * `dir` - The directory of the compiled programs
+/// * `context` - The context of the device
+/// * `device` - The device to compile the program for
+/// * `source` - The source of the program
+///
+/// # Returns
+///
+/// * `Result<Program>` - The program, or the error of the compiler
+///
+#[cfg(feature = "opencl")]
+fn build_cached_program(dir: &Path, context: &Context, device: Device, source: &str) -> Result<Program> {
+    let driver = device.info(DeviceInfo::DriverVersion).map(|version| version.to_string()).unwrap_or_default();
+    let key = cache_key(&device.name().unwrap_or_default(), &driver, source);
+    let path = dir.join(format!("{}.bin", key));
+
+    // Load the binary compiled on an earlier boot, if the driver still accepts it
+    if let Ok(binary) = fs::read(&path) {
+        let binaries = [binary.as_slice()];
+
+        match Program::builder().binaries(&binaries).devices(device).build(context) {
+            Ok(program) => {
+                debug!("Loaded the compiled program {}", path.display());
+                return Ok(program);
+            },
+            Err(err) => warn!("The compiled program {} can't be loaded, compiling it again: {}", path.display(), err)
+        }
+    }
+
+    // Compile the source and keep its binary for the next boot
+    let program = compile_program(context, device, source)?;
+
+    if let Err(err) = store_binary(&program, dir, &key) {
+        warn!("The compiled program can't be cached in {}: {}", dir.display(), err);
+    }
+
+    Ok(program)
+}
+
+/// Write the binary of a compiled program to the cache
+///
+/// The binary is written to a temporary file first and then renamed, so
+/// another process booting at the same time never loads half a binary.
+///
+/// # Arguments
+///
+/// * `program` - The compiled program, for a single device
+/// * `dir` - The directory of the compiled programs
+/// * `key` - The key of the program
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Nothing, or why the binary can't be written
+///
+#[cfg(feature = "opencl")]
+fn store_binary(program: &Program, dir: &Path, key: &str) -> std::result::Result<(), String> {
+    let binary = match program.info(ProgramInfo::Binaries).map_err(|err| err.to_string())? {
+        ProgramInfoResult::Binaries(binaries) => binaries.into_iter().next().filter(|binary| !binary.is_empty()),
+        _ => None
+    }.ok_or("the driver doesn't return the binary of the program")?;
+
+    let temporary = dir.join(format!("{}.{}.tmp", key, std::process::id()));
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&temporary, binary))
+        .and_then(|_| fs::rename(&temporary, dir.join(format!("{}.bin", key))))
+        .map_err(|err| {
+            let _ = fs::remove_file(&temporary);
+            err.to_string()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program_cache_key() {
+        // Prepare the program compiled for two drivers of a device and a changed source
+        let key = cache_key("Device", "1.0", "__kernel void a() {}");
+        let upgraded = cache_key("Device", "1.1", "__kernel void a() {}");
+        let changed = cache_key("Device", "1.0", "__kernel void b() {}");
+
+        // Get the result
+        let dirs = [
+            resolve_cache_dir(None, Some(PathBuf::from("/home/user/.cache"))),
+            resolve_cache_dir(Some("/var/cache/programs".to_string()), None),
+            resolve_cache_dir(Some("none".to_string()), Some(PathBuf::from("/home/user/.cache"))),
+            resolve_cache_dir(None, None)
+        ];
+
+        // Check if the result is correct
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key("Device", "1.0", "__kernel void a() {}"));
+        assert!(key != upgraded && key != changed);
+        assert_eq!(dirs, [Some(PathBuf::from("/home/user/.cache/pathwalker/programs")), Some(PathBuf::from("/var/cache/programs")), None, None]);
+    }
+}
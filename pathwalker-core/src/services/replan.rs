@@ -0,0 +1,455 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::models::{GridTopology, Matrix, Position, Replan, ReplanRequest, ReplanUpdate, ValidationRules};
+use crate::services::grid::{grid_distance, is_blocked, validate_grid, HEX_NEIGHBOURS, SQUARE_NEIGHBOURS};
+use crate::solver::validate_matrix;
+
+/// The map walked by a replanner
+///
+/// The cost of entering a cell of a grid is the cost of the cell, and the
+/// cost of an edge of a graph is its weight in the matrix, read the same way
+/// the kernels do.
+#[derive(Debug, Clone)]
+enum ReplanMap {
+    Grid { topology: GridTopology, width: usize, height: usize, costs: Vec<f32> },
+    Graph(Matrix),
+}
+
+impl ReplanMap {
+    /// Get the number of vertices of the map
+    fn len(&self) -> usize {
+        match self {
+            ReplanMap::Grid { costs, .. } => costs.len(),
+            ReplanMap::Graph(matrix) => matrix.width
+        }
+    }
+
+    /// Get the cells next to a cell of a grid, or every vertex of a graph
+    fn adjacent(&self, vertex: usize) -> Vec<usize> {
+        match self {
+            ReplanMap::Grid { topology, width, height, .. } => {
+                let neighbours: &[(isize, isize)] = match topology {
+                    GridTopology::Square => &SQUARE_NEIGHBOURS,
+                    GridTopology::Hex => &HEX_NEIGHBOURS
+                };
+
+                neighbours.iter().filter_map(|(dq, dr)| {
+                    let (column, row) = ((vertex % width) as isize + dq, (vertex / width) as isize + dr);
+                    (column >= 0 && row >= 0 && (column as usize) < *width && (row as usize) < *height).then(|| row as usize * width + column as usize)
+                }).collect()
+            },
+            ReplanMap::Graph(matrix) => (0..matrix.width).filter(|other| *other != vertex).collect()
+        }
+    }
+
+    /// Get the cost of going from a vertex to an adjacent one, infinite when it can't be done
+    fn cost(&self, from: usize, to: usize) -> f32 {
+        match self {
+            ReplanMap::Grid { costs, .. } if is_blocked(costs[to]) => f32::INFINITY,
+            ReplanMap::Grid { costs, .. } => costs[to],
+            ReplanMap::Graph(matrix) => matrix.edge(from, to).unwrap_or(f32::INFINITY)
+        }
+    }
+
+    /// Get the fewest edges between two vertices, or zero for graphs, where it's unknown
+    fn steps(&self, from: usize, to: usize) -> usize {
+        match self {
+            ReplanMap::Grid { topology, width, .. } => grid_distance(*topology, [from % width, from / width], [to % width, to / width]),
+            ReplanMap::Graph(_) => 0
+        }
+    }
+
+    /// Get the cheapest cost of entering a cell, which scales the estimates of the search
+    fn cheapest(&self) -> f32 {
+        match self {
+            ReplanMap::Grid { costs, .. } => costs.iter().copied().filter(|cost| !is_blocked(*cost)).fold(f32::MAX, f32::min),
+            ReplanMap::Graph(_) => 0.0
+        }
+    }
+}
+
+/// The priority of a vertex in the queue, compared lexicographically
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Key(f32, f32);
+
+impl Key {
+    fn compare(&self, other: &Key) -> Ordering {
+        self.0.total_cmp(&other.0).then_with(|| self.1.total_cmp(&other.1))
+    }
+}
+
+/// A vertex waiting in the queue with the key it was queued with
+#[derive(Debug, PartialEq)]
+struct Queued {
+    key: Key,
+    vertex: usize,
+}
+
+impl Eq for Queued {}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, so the heap pops the lowest key first
+        other.key.compare(&self.key).then_with(|| other.vertex.cmp(&self.vertex))
+    }
+}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// An incremental planner with D* Lite
+///
+/// The search goes backwards from the goal, so the costs to the goal found
+/// by a plan are still valid when the robot moves, and only the vertices
+/// whose costs are affected by the changes observed are expanded again.
+///
+/// # Fields
+///
+/// * `map` - The map being walked, with the changes observed
+/// * `start` - The vertex of the robot
+/// * `goal` - The vertex the robot goes to
+/// * `scale` - The cost of a step in the estimates, the cheapest cell when the search started
+/// * `offset` - The sum of the estimates between the starts, which keeps the queued keys valid when the robot moves
+/// * `costs` - The cost to the goal of every vertex, as of its last expansion
+/// * `lookahead` - The cost to the goal of every vertex through its best neighbour
+/// * `queued` - The key every vertex has in the queue, if it's queued
+/// * `queue` - The inconsistent vertices, with outdated entries skipped when popped
+/// * `expanded` - The vertices expanded since the search started
+///
+#[derive(Debug)]
+pub struct Replanner {
+    map: ReplanMap,
+    start: usize,
+    goal: usize,
+    scale: f32,
+    offset: f32,
+    costs: Vec<f32>,
+    lookahead: Vec<f32>,
+    queued: Vec<Option<Key>>,
+    queue: BinaryHeap<Queued>,
+    expanded: usize,
+}
+
+impl Replanner {
+    /// Create the planner of a grid or a graph
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The map, the start and the goal
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Replanner, String>` - The planner, or the reason why the request isn't valid
+    ///
+    pub fn new(request: ReplanRequest) -> Result<Replanner, String> {
+        let (map, start, goal) = match request {
+            ReplanRequest::Grid(grid) => {
+                if let Some(message) = validate_grid(&grid) {
+                    return Err(message.to_string());
+                }
+
+                let (start, goal) = (grid.start[1] * grid.width + grid.start[0], grid.goal[1] * grid.width + grid.goal[0]);
+                (ReplanMap::Grid { topology: grid.topology, width: grid.width, height: grid.height, costs: grid.costs }, start, goal)
+            },
+            ReplanRequest::Graph(route) => {
+                if let Some(message) = validate_matrix(&route.matrix) {
+                    return Err(message.to_string());
+                }
+
+                if let Some(error) = route.matrix.validate(ValidationRules { reject_negative: true, warn_asymmetric: false }).errors.first() {
+                    return Err(error.to_string());
+                }
+
+                if route.source >= route.matrix.width || route.target >= route.matrix.width {
+                    return Err("The source or the target are outside of the graph".to_string());
+                }
+
+                (ReplanMap::Graph(route.matrix), route.source, route.target)
+            }
+        };
+
+        let mut replanner = Replanner {
+            map, start, goal,
+            scale: 0.0,
+            offset: 0.0,
+            costs: vec![],
+            lookahead: vec![],
+            queued: vec![],
+            queue: BinaryHeap::new(),
+            expanded: 0
+        };
+
+        replanner.restart();
+        Ok(replanner)
+    }
+
+    /// Forget the costs found and search again from the goal
+    fn restart(&mut self) {
+        let vertices = self.map.len();
+
+        self.scale = self.map.cheapest();
+        self.offset = 0.0;
+        self.costs = vec![f32::INFINITY; vertices];
+        self.lookahead = vec![f32::INFINITY; vertices];
+        self.queued = vec![None; vertices];
+        self.queue.clear();
+
+        self.lookahead[self.goal] = 0.0;
+        self.enqueue(self.goal);
+    }
+
+    /// Get the estimate of the cost from the start to a vertex, never above the real one
+    fn estimate(&self, vertex: usize) -> f32 {
+        self.map.steps(self.start, vertex) as f32 * self.scale
+    }
+
+    /// Get the key a vertex has to be queued with
+    fn key(&self, vertex: usize) -> Key {
+        let cost = self.costs[vertex].min(self.lookahead[vertex]);
+        Key(cost + self.estimate(vertex) + self.offset, cost)
+    }
+
+    /// Queue a vertex with its current key
+    fn enqueue(&mut self, vertex: usize) {
+        let key = self.key(vertex);
+        self.queued[vertex] = Some(key);
+        self.queue.push(Queued { key, vertex });
+    }
+
+    /// Recompute the lookahead of a vertex, queueing it while it's inconsistent
+    fn update(&mut self, vertex: usize) {
+        if vertex != self.goal {
+            self.lookahead[vertex] = self.map.adjacent(vertex).into_iter()
+                .map(|next| self.map.cost(vertex, next) + self.costs[next])
+                .fold(f32::INFINITY, f32::min);
+        }
+
+        if self.costs[vertex] != self.lookahead[vertex] {
+            self.enqueue(vertex);
+        } else {
+            self.queued[vertex] = None;
+        }
+    }
+
+    /// Get the vertex with the lowest key in the queue, dropping the outdated entries
+    fn peek(&mut self) -> Option<Key> {
+        while let Some(top) = self.queue.peek() {
+            if self.queued[top.vertex] == Some(top.key) {
+                return Some(top.key);
+            }
+
+            self.queue.pop();
+        }
+
+        None
+    }
+
+    /// Expand the vertices until the cost of the start is known
+    fn search(&mut self) {
+        while let Some(top) = self.peek() {
+            // Stop once no queued vertex can change the cost of the start
+            if top.compare(&self.key(self.start)) != Ordering::Less && self.lookahead[self.start] == self.costs[self.start] {
+                break;
+            }
+
+            let Queued { key, vertex } = self.queue.pop().unwrap();
+            self.queued[vertex] = None;
+
+            if key.compare(&self.key(vertex)) == Ordering::Less {
+                // The key is outdated by the moves of the robot, so queue it again
+                self.enqueue(vertex);
+            } else if self.costs[vertex] > self.lookahead[vertex] {
+                // The vertex has got cheaper, so its neighbours may too
+                self.costs[vertex] = self.lookahead[vertex];
+                self.expanded += 1;
+                self.map.adjacent(vertex).into_iter().for_each(|previous| self.update(previous));
+            } else {
+                // The vertex has got dearer, so its neighbours and itself are recomputed
+                self.costs[vertex] = f32::INFINITY;
+                self.expanded += 1;
+                self.map.adjacent(vertex).into_iter().for_each(|previous| self.update(previous));
+                self.update(vertex);
+            }
+        }
+    }
+
+    /// Get the index of a position in the map
+    fn vertex(&self, position: Position) -> Result<usize, String> {
+        match (&self.map, position) {
+            (ReplanMap::Grid { width, height, .. }, Position::Cell([column, row])) if column < *width && row < *height => Ok(row * width + column),
+            (ReplanMap::Graph(matrix), Position::Vertex(vertex)) if vertex < matrix.width => Ok(vertex),
+            (ReplanMap::Grid { .. }, Position::Cell(cell)) => Err(format!("The cell {:?} is outside of the grid", cell)),
+            (ReplanMap::Graph(_), Position::Vertex(vertex)) => Err(format!("The vertex {} is outside of the graph", vertex)),
+            (ReplanMap::Grid { .. }, Position::Vertex(_)) => Err("The positions in a grid are cells".to_string()),
+            (ReplanMap::Graph(_), Position::Cell(_)) => Err("The positions in a graph are vertices".to_string())
+        }
+    }
+
+    /// Get the position of a vertex of the map
+    fn position(&self, vertex: usize) -> Position {
+        match &self.map {
+            ReplanMap::Grid { width, .. } => Position::Cell([vertex % width, vertex / width]),
+            ReplanMap::Graph(_) => Position::Vertex(vertex)
+        }
+    }
+
+    /// Apply the changes observed by the robot
+    ///
+    /// The update is validated before changing anything, so an invalid update
+    /// leaves the planner as it was. A cell cheaper than the cheapest one the
+    /// search started with would make the estimates too high, so the search
+    /// starts again from the goal in that case.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The new position of the robot, and the costs of the cells or the weights of the edges that have changed
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Nothing, or the reason why the update isn't valid
+    ///
+    pub fn apply(&mut self, update: &ReplanUpdate) -> Result<(), String> {
+        // Validate the whole update before applying it
+        match self.map {
+            ReplanMap::Grid { .. } if !update.edges.is_empty() => return Err("The edges of a grid can't be changed, change the costs of its cells instead".to_string()),
+            ReplanMap::Graph(_) if !update.cells.is_empty() => return Err("The cells of a graph can't be changed, change the weights of its edges instead".to_string()),
+            _ => {}
+        }
+
+        let start = update.start.map(|start| self.vertex(start)).transpose()?;
+        let cells = update.cells.iter()
+            .map(|change| self.vertex(Position::Cell(change.cell)).map(|cell| (cell, change.cost)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let edges = update.edges.iter()
+            .map(|change| Ok((self.vertex(Position::Vertex(change.source))?, self.vertex(Position::Vertex(change.target))?, change.weight)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if cells.iter().map(|(_, cost)| cost).chain(edges.iter().map(|(_, _, weight)| weight)).any(|cost| cost.is_nan() || *cost < 0.0) {
+            return Err("The costs must be non-negative numbers".to_string());
+        }
+
+        // Move the robot, offsetting the keys queued from the previous start
+        if let Some(start) = start {
+            self.offset += self.estimate(start);
+            self.start = start;
+        }
+
+        // Change the map, recomputing the vertices whose edges have changed
+        let mut changed = vec![];
+
+        for (cell, cost) in cells {
+            if let ReplanMap::Grid { costs, .. } = &mut self.map {
+                costs[cell] = cost;
+            }
+
+            changed.extend(self.map.adjacent(cell));
+        }
+
+        for (source, target, weight) in edges {
+            if let ReplanMap::Graph(matrix) = &mut self.map {
+                matrix.set_edge(source, target, weight);
+
+                // The edge goes back too when the graph is undirected
+                if !matrix.directed {
+                    changed.push(target);
+                }
+            }
+
+            changed.push(source);
+        }
+
+        if self.map.cheapest() < self.scale {
+            self.restart();
+        } else {
+            changed.into_iter().for_each(|vertex| self.update(vertex));
+        }
+
+        Ok(())
+    }
+
+    /// Get the cheapest path from the start to the goal
+    ///
+    /// Only the vertices affected by the changes applied since the last plan
+    /// are expanded, so replanning after small changes is much cheaper than
+    /// searching again.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Replan>` - The positions of the path and the vertices expanded so far, if the goal can be reached
+    ///
+    pub fn plan(&mut self) -> Option<Replan> {
+        self.search();
+
+        // Follow the cheapest neighbours from the start to the goal
+        let mut path = vec![self.start];
+        let mut cost = 0.0;
+
+        while *path.last().unwrap() != self.goal {
+            let current = *path.last().unwrap();
+            let (next, total) = self.map.adjacent(current).into_iter()
+                .map(|next| (next, self.map.cost(current, next) + self.costs[next]))
+                .min_by(|first, second| first.1.total_cmp(&second.1))?;
+
+            if !total.is_finite() || path.len() > self.map.len() {
+                return None;
+            }
+
+            cost += self.map.cost(current, next);
+            path.push(next);
+        }
+
+        Some(Replan { path: path.into_iter().map(|vertex| self.position(vertex)).collect(), cost, expanded: self.expanded })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{CellChange, EdgeChange, GridRequest, RouteRequest};
+    use super::*;
+
+    #[test]
+    fn test_replan_grid() {
+        // Prepare an open square grid, where the robot goes along the first row
+        let grid = GridRequest { topology: GridTopology::Square, width: 5, height: 3, costs: vec![1.0; 15], start: [0, 0], goal: [4, 0], weight: None, round: None, postprocess: vec![] };
+        let mut replanner = Replanner::new(ReplanRequest::Grid(grid)).unwrap();
+
+        // Get the result, moving the robot and walling the first row in front of it
+        let first = replanner.plan().unwrap();
+        replanner.apply(&ReplanUpdate { start: Some(Position::Cell([1, 0])), cells: vec![CellChange { cell: [2, 0], cost: 0.0 }], edges: vec![] }).unwrap();
+        let second = replanner.plan().unwrap();
+
+        // Check if the result is correct
+        assert_eq!(first.cost, 4.0);
+        assert_eq!(first.path.len(), 5);
+        assert_eq!(second.cost, 5.0);
+        assert_eq!(second.path.first(), Some(&Position::Cell([1, 0])));
+        assert!(!second.path.contains(&Position::Cell([2, 0])));
+        assert!(second.expanded - first.expanded < 15);
+        assert_eq!(replanner.apply(&ReplanUpdate { start: Some(Position::Vertex(3)), ..Default::default() }), Err("The positions in a grid are cells".to_string()));
+    }
+
+    #[test]
+    fn test_replan_graph() {
+        // Prepare a graph with a direct edge and a detour from the source to the target
+        let mut matrix = Matrix::new(3, 3, vec![0.0; 9]);
+        matrix.set_edge(0, 2, 5.0);
+        matrix.set_edge(0, 1, 1.0);
+        matrix.set_edge(1, 2, 1.0);
+        let mut replanner = Replanner::new(ReplanRequest::Graph(RouteRequest { matrix, source: 0, target: 2 })).unwrap();
+
+        // Get the result, cutting the detour
+        let first = replanner.plan().unwrap();
+        replanner.apply(&ReplanUpdate { edges: vec![EdgeChange { source: 1, target: 2, weight: 0.0 }], ..Default::default() }).unwrap();
+        let second = replanner.plan().unwrap();
+
+        // Check if the result is correct
+        assert_eq!(first.path, vec![Position::Vertex(0), Position::Vertex(1), Position::Vertex(2)]);
+        assert_eq!(first.cost, 2.0);
+        assert_eq!(second.path, vec![Position::Vertex(0), Position::Vertex(2)]);
+        assert_eq!(second.cost, 5.0);
+    }
+}
@@ -0,0 +1,142 @@
+use sha2::{Digest, Sha256};
+
+use crate::models::{Matrix, Real, DEFAULT_BUCKET_SECONDS};
+
+/// The version of the scheme of the run ids, changed whenever what they cover changes
+pub const RUN_ID_SCHEME: &str = "pathwalker-run-v1";
+
+/// The bytes of the digest kept in a run id
+const RUN_ID_BYTES: usize = 16;
+
+/// Hash a field with its length, so the fields of a run id can't be shifted into each other
+fn hash_field(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+/// Get the digest of the weights of a matrix
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix
+///
+/// # Returns
+///
+/// * `[u8; 32]` - The SHA-256 digest of the dimensions and the exact bits of the weights
+///
+pub fn matrix_digest<T: Real>(matrix: &Matrix<T>) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update((matrix.width as u64).to_le_bytes());
+    hasher.update((matrix.height as u64).to_le_bytes());
+
+    for weight in &matrix.data {
+        hasher.update(weight.to_le_bytes_vec());
+    }
+
+    // Keep the digests of the matrices without zero weight edges as they were
+    if matrix.zero_edges {
+        hasher.update([1]);
+    }
+
+    // And the digests of the directed matrices too
+    if !matrix.directed {
+        hasher.update([2]);
+    }
+
+    // And the ones without a unit, as the unit changes the costs of the responses
+    if let Some(unit) = matrix.unit {
+        hasher.update([3, unit as u8]);
+    }
+
+    // And the ones without constraints on their paths
+    if matrix.has_forbidden() || matrix.max_hops.is_some() {
+        let nodes: Vec<u8> = matrix.forbidden_nodes.iter().flat_map(|vertex| (*vertex as u64).to_le_bytes()).collect();
+        let edges: Vec<u8> = matrix.forbidden_edges.iter().flatten().flat_map(|vertex| (*vertex as u64).to_le_bytes()).collect();
+
+        hasher.update([4]);
+        hash_field(&mut hasher, &nodes);
+        hash_field(&mut hasher, &edges);
+        hasher.update((matrix.max_hops.unwrap_or(0) as u64).to_le_bytes());
+    }
+
+    // And the ones whose weights don't change with the time
+    if matrix.is_time_dependent() {
+        hasher.update([5]);
+
+        for bucket in &matrix.time_buckets {
+            hash_field(&mut hasher, &bucket.iter().flat_map(|weight| weight.to_le_bytes_vec()).collect::<Vec<u8>>());
+        }
+
+        hasher.update(matrix.bucket_seconds.unwrap_or(DEFAULT_BUCKET_SECONDS).to_le_bytes());
+        hasher.update(matrix.departure_time.unwrap_or_default().to_le_bytes());
+    }
+
+    // And the ones whose ties are broken by the walk
+    if matrix.deterministic {
+        hasher.update([6]);
+    }
+
+    hasher.finalize().into()
+}
+
+/// Get the run id of a computation
+///
+/// The id is derived from the input, the parameters and the backend alone,
+/// so two computations share their id only when they have run the same
+/// code paths with the same parameters, no matter when or where.
+///
+/// # Arguments
+///
+/// * `input` - The digest of the input of the computation
+/// * `operation` - The name of the computation
+/// * `parameters` - The parameters of the computation, by name
+/// * `backend` - The version of the backend that has run the computation
+///
+/// # Returns
+///
+/// * `String` - The run id in hexadecimal
+///
+pub fn run_id(input: &[u8; 32], operation: &str, parameters: &[(&str, String)], backend: &str) -> String {
+    let mut hasher = Sha256::new();
+    let mut parameters = parameters.to_vec();
+    parameters.sort();
+
+    hash_field(&mut hasher, RUN_ID_SCHEME.as_bytes());
+    hash_field(&mut hasher, backend.as_bytes());
+    hash_field(&mut hasher, operation.as_bytes());
+    hash_field(&mut hasher, input);
+
+    for (name, value) in parameters {
+        hash_field(&mut hasher, name.as_bytes());
+        hash_field(&mut hasher, value.as_bytes());
+    }
+
+    hex::encode(&hasher.finalize()[..RUN_ID_BYTES])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_id() {
+        // Prepare the inputs
+        let matrix = matrix_digest(&Matrix::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]));
+        let other = matrix_digest(&Matrix::new(2, 2, vec![0.0, 1.0, 2.0, 0.0]));
+        let parameters = [("source", "0".to_string()), ("round", "2".to_string())];
+
+        // Get the result
+        let id = run_id(&matrix, "sortest", &parameters, "backend 1");
+        let reordered = run_id(&matrix, "sortest", &[parameters[1].clone(), parameters[0].clone()], "backend 1");
+
+        // Check if the result is correct
+        assert_eq!(id.len(), 2 * RUN_ID_BYTES);
+        assert_eq!(id, reordered);
+        assert_ne!(id, run_id(&other, "sortest", &parameters, "backend 1"));
+        assert_ne!(id, run_id(&matrix_digest(&Matrix { max_hops: Some(1), ..Matrix::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]) }), "sortest", &parameters, "backend 1"));
+        assert_ne!(id, run_id(&matrix_digest(&Matrix { time_buckets: vec![vec![0.0, 1.0, 1.0, 0.0]], ..Matrix::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]) }), "sortest", &parameters, "backend 1"));
+        assert_ne!(id, run_id(&matrix_digest(&Matrix { deterministic: true, ..Matrix::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]) }), "sortest", &parameters, "backend 1"));
+        assert_ne!(id, run_id(&matrix, "sortest", &parameters, "backend 2"));
+        assert_ne!(id, run_id(&matrix, "sortest", &[("source", "1".to_string()), ("round", "2".to_string())], "backend 1"));
+    }
+}
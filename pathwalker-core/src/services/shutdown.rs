@@ -0,0 +1,117 @@
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// The kernel executions of the server
+    pub static ref KERNEL_EXECUTIONS: KernelExecutions = KernelExecutions::default();
+}
+
+/// The kernel executions running in the device
+///
+/// Every execution is counted until its guard is dropped, so the shutdown
+/// can wait for the ones still running before releasing the device.
+///
+/// # Fields
+///
+/// * `running` - The number of executions running, and the number of executions started
+/// * `idle` - Notified every time an execution finishes
+///
+#[derive(Default)]
+pub struct KernelExecutions {
+    running: Mutex<(usize, usize)>,
+    idle: Condvar,
+}
+
+/// A kernel execution running in the device, counted until dropped
+pub struct KernelExecution<'a> {
+    executions: &'a KernelExecutions,
+}
+
+impl Drop for KernelExecution<'_> {
+    fn drop(&mut self) {
+        let mut running = self.executions.running.lock().unwrap();
+        running.0 -= 1;
+
+        if running.0 == 0 {
+            self.executions.idle.notify_all();
+        }
+    }
+}
+
+impl KernelExecutions {
+    /// Count a kernel execution until the returned guard is dropped
+    pub fn start(&self) -> KernelExecution<'_> {
+        let mut running = self.running.lock().unwrap();
+        running.0 += 1;
+        running.1 += 1;
+
+        KernelExecution { executions: self }
+    }
+
+    /// Returns the number of executions started since the server started
+    pub fn started(&self) -> usize {
+        self.running.lock().unwrap().1
+    }
+
+    /// Wait for the running executions to finish
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum time to wait
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The number of executions still running when the timeout expired
+    ///
+    pub fn wait_idle(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        let mut running = self.running.lock().unwrap();
+
+        while running.0 > 0 {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            if remaining.is_zero() {
+                break;
+            }
+
+            running = self.idle.wait_timeout(running, remaining).unwrap().0;
+        }
+
+        running.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use super::*;
+
+    #[test]
+    fn test_wait_idle() {
+        // Prepare an execution finishing in another thread
+        let executions = Arc::new(KernelExecutions::default());
+        let stuck = executions.start();
+        let worker = {
+            let executions = executions.clone();
+            thread::spawn(move || {
+                let _execution = executions.start();
+                thread::sleep(Duration::from_millis(50));
+            })
+        };
+
+        // Get the result
+        thread::sleep(Duration::from_millis(10));
+        let expired = executions.wait_idle(Duration::from_millis(200));
+        drop(stuck);
+        let idle = executions.wait_idle(Duration::from_millis(200));
+        worker.join().unwrap();
+
+        // Check if the result is correct
+        assert_eq!(expired, 1);
+        assert_eq!(idle, 0);
+        assert_eq!(executions.started(), 2);
+    }
+}
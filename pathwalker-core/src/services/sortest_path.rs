@@ -0,0 +1,1720 @@
+#[cfg(not(feature = "opencl"))]
+use std::convert::Infallible;
+#[cfg(not(feature = "opencl"))]
+use std::marker::PhantomData;
+#[cfg(feature = "opencl")]
+use std::sync::Arc;
+#[cfg(feature = "opencl")]
+use std::sync::OnceLock;
+#[cfg(feature = "opencl")]
+use std::time::Duration;
+
+#[cfg(feature = "opencl")]
+use log::{error, info, trace, warn};
+#[cfg(feature = "opencl")]
+use ocl::{enums::{DeviceInfo, DeviceInfoResult, ProfilingInfo, ProfilingInfoResult}, flags::QUEUE_PROFILING_ENABLE, Buffer, Context, Device, Event, Kernel, MemFlags, Platform, Program, Queue, SpatialDims};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "opencl")]
+use tracing::debug_span;
+
+use crate::models::{KernelLaunch, Matrix, MaxFlow, PageRank, PageRankRequest, PathResult, Real};
+use crate::services::buffer_pool::BufferPool;
+use crate::services::cancellation::Cancellation;
+use crate::services::kernels::KernelProgram;
+use crate::services::opencl::{OclPrm, Result};
+use crate::services::variants::{DeviceCapabilities, KernelVariant};
+#[cfg(feature = "opencl")]
+use crate::models::Precision;
+#[cfg(feature = "opencl")]
+use crate::services::buffer_pool::PooledBuffer;
+#[cfg(feature = "opencl")]
+use crate::services::devices::DeviceDiagnostics;
+#[cfg(feature = "opencl")]
+use crate::services::maxflow::{capacities, min_cut, preflow};
+#[cfg(feature = "opencl")]
+use crate::services::memory::global_memory;
+#[cfg(feature = "opencl")]
+use crate::services::pagerank::{teleport, transition_matrix};
+#[cfg(feature = "opencl")]
+use crate::services::program_cache::build_program;
+#[cfg(feature = "opencl")]
+use crate::services::metrics::{add_device_memory, observe_kernel, KernelStage, QueuedWalk};
+#[cfg(feature = "opencl")]
+use crate::services::shutdown::KERNEL_EXECUTIONS;
+#[cfg(feature = "opencl")]
+use crate::services::variants::{select_variant, FP64_EXTENSION, KERNEL_VARIANT_VARIABLE};
+
+/// The environment variable that copies the buffers of the walks to the host after every iteration, to trace them
+pub const DEBUG_READBACK_VARIABLE: &str = "PATHWALKER_DEBUG_READBACK";
+
+/// The iterations of a walk enqueued before waiting for the device, to check the cancellation and report the progress
+#[cfg(feature = "opencl")]
+const ITERATIONS_PER_SYNC: usize = 32;
+
+/// The definitions compiled before the kernel for single precision walks
+#[cfg(feature = "opencl")]
+const SINGLE_PRECISION_HEADER: &str = "#define real float\n#define REAL_MAX FLT_MAX\n#define REAL_ADD(a, b) ((a) + (b))\n";
+
+/// The definitions compiled before the kernel for double precision walks
+#[cfg(feature = "opencl")]
+const DOUBLE_PRECISION_HEADER: &str = "#pragma OPENCL EXTENSION cl_khr_fp64 : enable\n#define real double\n#define REAL_MAX DBL_MAX\n#define REAL_ADD(a, b) ((a) + (b))\n";
+
+/// The definitions compiled before the kernel for integer walks, whose sums saturate instead of wrapping
+#[cfg(feature = "opencl")]
+const INTEGER_HEADER: &str = "#define real uint\n#define REAL_MAX UINT_MAX\n#define REAL_ADD(a, b) add_sat(a, b)\n";
+
+/// The number of threads of the sentinel kernel
+#[cfg(feature = "opencl")]
+const SENTINEL_SIZE: usize = 4;
+
+/// The threads of every work group reducing the meeting point of the bidirectional walks, as defined in its kernel
+#[cfg(feature = "opencl")]
+const MEETING_SIZE: usize = 64;
+
+/// The sortest path service
+///
+/// # Fields
+///
+/// * `platform` - The platform to use
+/// * `device` - The device to use
+/// * `context` - The context to use
+/// * `capabilities` - What the device can run
+/// * `program` - The program to use for single precision walks
+/// * `double_program` - The program to use for double precision walks, compiled on the first one
+/// * `integer_program` - The program to use for integer walks, compiled on the first one
+/// * `pagerank_program` - The program to use for PageRank, compiled on the first ranking
+/// * `maxflow_program` - The program to use for the maximum flows, compiled on the first one
+/// * `bfs_program` - The program to use for the breadth first walks, compiled on the first one
+/// * `bidirectional_program` - The program to use for the bidirectional walks, compiled on the first one
+/// * `queue` - The queue to use
+/// * `backend_version` - The versions of the server, the kernels, the platform and the device
+/// * `debug_readback` - If the buffers of the walks are copied to the host after every iteration
+/// * `pool` - The pinned buffers reused by the uploads and the walks
+///
+#[cfg(feature = "opencl")]
+pub struct SortestPath {
+    queue: Queue,
+    platform: Platform,
+    device: Device,
+    context: Context,
+    capabilities: DeviceCapabilities,
+    program: WalkProgram,
+    double_program: OnceLock<std::result::Result<WalkProgram, String>>,
+    integer_program: OnceLock<std::result::Result<WalkProgram, String>>,
+    pagerank_program: OnceLock<std::result::Result<Program, String>>,
+    maxflow_program: OnceLock<std::result::Result<Program, String>>,
+    bfs_program: OnceLock<std::result::Result<Program, String>>,
+    bidirectional_program: OnceLock<std::result::Result<Program, String>>,
+    backend_version: String,
+    debug_readback: bool,
+    pool: Arc<BufferPool>,
+}
+
+/// The kernels of the weighted walk compiled for a precision
+///
+/// # Fields
+///
+/// * `program` - The compiled program
+/// * `variant` - The variant of the kernels in the program
+///
+#[cfg(feature = "opencl")]
+struct WalkProgram {
+    program: Program,
+    variant: KernelVariant,
+}
+
+/// Compile the kernels of the weighted walk
+///
+/// A variant that the driver can't compile falls back to the baseline
+/// kernels, so a driver bug in a faster variant doesn't take the device down.
+///
+/// # Arguments
+///
+/// * `context` - The context of the device
+/// * `device` - The device to compile the kernels for
+/// * `capabilities` - What the device can run
+/// * `header` - The definitions of the precision
+/// * `variant` - The variant to compile
+///
+/// # Returns
+///
+/// * `Result<WalkProgram, String>` - The program, or why not even the baseline kernels can be compiled
+///
+#[cfg(feature = "opencl")]
+fn build_walk_program(context: &Context, device: Device, capabilities: &DeviceCapabilities, header: &str, variant: KernelVariant) -> std::result::Result<WalkProgram, String> {
+    let build = |variant: KernelVariant| build_program(context, device, &format!("{}{}{}", header, variant.header(capabilities), KernelProgram::Walk.source()))
+        .map(|program| WalkProgram { program, variant });
+
+    match build(variant) {
+        Ok(program) => Ok(program),
+        Err(err) if variant != KernelVariant::Baseline => {
+            warn!("The {} kernels can't be compiled, falling back to the baseline ones: {}", variant.name(), err);
+            build(KernelVariant::Baseline).map_err(|err| err.to_string())
+        },
+        Err(err) => Err(err.to_string())
+    }
+}
+
+/// Log the error of a kernel that couldn't be enqueued or run
+#[cfg(feature = "opencl")]
+fn log_kernel_error(err: &ocl::Error) {
+    error!("Critical error occurred in kernel: {}", err);
+}
+
+/// Get the time a command has run in the device, from the profiling of its event
+#[cfg(feature = "opencl")]
+fn device_time(event: &Event) -> Option<Duration> {
+    match (event.profiling_info(ProfilingInfo::Start), event.profiling_info(ProfilingInfo::End)) {
+        (Ok(ProfilingInfoResult::Start(start)), Ok(ProfilingInfoResult::End(end))) => Some(Duration::from_nanos(end.saturating_sub(start))),
+        _ => None
+    }
+}
+
+/// Find what a device can run, assuming the least of what the driver can't report
+#[cfg(feature = "opencl")]
+fn device_capabilities(device: Device) -> DeviceCapabilities {
+    let version = match device.info(DeviceInfo::Version) {
+        Ok(DeviceInfoResult::Version(version)) => version.to_raw(),
+        _ => (1, 0)
+    };
+    let local_memory = match device.info(DeviceInfo::LocalMemSize) {
+        Ok(DeviceInfoResult::LocalMemSize(bytes)) => bytes,
+        _ => 0
+    };
+    let extensions = device.info(DeviceInfo::Extensions).map(|extensions| extensions.to_string()).unwrap_or_default();
+
+    DeviceCapabilities::new(version, &extensions, device.max_wg_size().unwrap_or(1), local_memory)
+}
+
+/// A matrix resident in the device memory
+///
+/// # Fields
+///
+/// * `width` - The number of vertices of the matrix
+/// * `zero_edges` - If the zero weights of the matrix are edges
+/// * `rounds` - The iterations of its walks, bounded by the hops of the paths
+/// * `nonnegative` - If the matrix has no negative weights, so its walks can search from both ends
+/// * `deterministic` - If its walks give every vertex the lowest of its shortest predecessors
+/// * `buffer` - The device buffer with the matrix, returned to the pool once dropped
+///
+#[cfg(feature = "opencl")]
+pub struct DeviceGraph<T: OclPrm = f32> {
+    pub width: usize,
+    pub zero_edges: bool,
+    pub rounds: usize,
+    pub nonnegative: bool,
+    pub deterministic: bool,
+    buffer: PooledBuffer<T>,
+}
+
+#[cfg(feature = "opencl")]
+impl<T: OclPrm> DeviceGraph<T> {
+    /// Returns the bytes of device memory used by the matrix
+    pub fn size_in_bytes(&self) -> usize {
+        self.buffer.size_in_bytes()
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl<T: OclPrm> Drop for DeviceGraph<T> {
+    fn drop(&mut self) {
+        add_device_memory(-(self.size_in_bytes() as i64));
+    }
+}
+
+/// Reconstruct the route between two vertices from a computed path
+///
+/// Follows the predecessors of the path from the target back to the source.
+/// The unreachable vertices have a zero distance, or the maximum of the type
+/// when the matrix has zero weight edges.
+///
+/// # Arguments
+///
+/// * `path` - The path computed from the source
+/// * `source` - The vertex the path was computed from
+/// * `target` - The vertex to reach
+/// * `zero_edges` - If the matrix of the path has zero weight edges
+///
+/// # Returns
+///
+/// * `Option<Vec<usize>>` - The vertices from the source to the target, if it's reachable
+///
+pub fn route<T: Real>(path: &[PathResult<T>], source: usize, target: usize, zero_edges: bool) -> Option<Vec<usize>> {
+    let mut route = vec![target];
+
+    while *route.last()? != source {
+        let predecessor = path.get(*route.last()?)?;
+
+        // The target is unreachable or the predecessors have a cycle
+        let unreachable = if zero_edges { predecessor.1 == T::MAX } else { predecessor.1 == T::default() };
+
+        if unreachable || route.len() > path.len() || predecessor.0 < 0 {
+            return None;
+        }
+
+        route.push(predecessor.0 as usize);
+    }
+
+    route.reverse();
+    Some(route)
+}
+
+/// Group the changed cells of a matrix in runs of contiguous cells
+///
+/// # Arguments
+///
+/// * `cells` - The index and the new weight of every changed cell, sorted by index
+///
+/// # Returns
+///
+/// * `Vec<(usize, Vec<T>)>` - The index of the first cell of every run and the weights of its cells
+///
+pub fn cell_runs<T: Copy>(cells: &[(usize, T)]) -> Vec<(usize, Vec<T>)> {
+    let mut runs: Vec<(usize, Vec<T>)> = Vec::new();
+
+    for (index, weight) in cells {
+        match runs.last_mut() {
+            Some((start, weights)) if *start + weights.len() == *index => weights.push(*weight),
+            _ => runs.push((*index, vec![*weight]))
+        }
+    }
+
+    runs
+}
+
+/// Check if the walk of a graph from a source can change when some of its edges change
+///
+/// The distances of a walk are kept when every changed edge is neither in
+/// the tree of its shortest paths nor a shorter way to reach its target, so
+/// only the walks whose tree goes through a changed edge, or that a cheaper
+/// edge improves, have to be walked again. The walks of the deterministic
+/// matrices are walked again for the edges as cheap as their paths too, as
+/// they can take a lower predecessor.
+///
+/// # Arguments
+///
+/// * `path` - The path of the walk, computed with the weights before the change
+/// * `source` - The vertex the walk started from
+/// * `before` - The matrix before the change
+/// * `after` - The matrix after the change
+/// * `edges` - The edges that may have changed, as `(source, target)` pairs
+///
+/// # Returns
+///
+/// * `bool` - If the walk has to be computed again
+///
+pub fn is_path_affected<T: Real>(path: &[PathResult<T>], source: usize, before: &Matrix<T>, after: &Matrix<T>, edges: &[(usize, usize)]) -> bool {
+    let distance = |vertex: usize| match vertex == source {
+        true => Some(0.0),
+        false => route(path, source, vertex, before.zero_edges).map(|_| Into::<f64>::into(path[vertex].1))
+    };
+
+    edges.iter().any(|(from, to)| {
+        let (old, new) = (before.edge(*from, *to), after.edge(*from, *to));
+
+        // The edges of the vertices the walk doesn't reach are never walked
+        let Some(start) = distance(*from).filter(|_| old != new) else {
+            return false;
+        };
+
+        let in_tree = *to != source && path[*to].0 == *from as i32 && old.is_some();
+        let shorter = new.is_some_and(|weight| distance(*to).is_none_or(|end| match after.deterministic {
+            true => start + Into::<f64>::into(weight) <= end,
+            false => start + Into::<f64>::into(weight) < end
+        }));
+
+        in_tree || shorter
+    })
+}
+
+/// Join the searches of a bidirectional walk into the path of a walk from the source
+///
+/// The route from the source to the meeting point follows the predecessors
+/// of the search from the source, and the route from the meeting point to
+/// the target follows the successors of the search from the target, which
+/// are turned into predecessors with the distances from the source. The rest
+/// of the vertices keep what the search from the source has reached, which
+/// can be longer than their shortest paths, as the walk stops once the
+/// route is known. The searches leave the vertices they haven't reached
+/// at the maximum of the type.
+///
+/// # Arguments
+///
+/// * `forward` - The predecessors and the distances of the search from the source
+/// * `backward` - The successors and the distances to the target of the search from the target
+/// * `meeting` - The vertex where the searches meet on the shortest route, if the target is reachable
+/// * `source` - The vertex the walk started from
+/// * `target` - The vertex the walk is searching for
+/// * `zero_edges` - If the matrix of the walk has zero weight edges
+///
+/// # Returns
+///
+/// * `Vec<PathResult>` - The path, with the unreachable vertices marked as the weighted walks do
+///
+pub fn join_searches(forward: &[PathResult], backward: &[PathResult], meeting: Option<usize>, source: usize, target: usize, zero_edges: bool) -> Vec<PathResult> {
+    let unreachable = if zero_edges { f32::MAX } else { 0.0 };
+    let mut path: Vec<PathResult> = forward.iter()
+        .map(|PathResult(predecessor, distance)| match *distance == f32::MAX {
+            true => PathResult(source as i32, unreachable),
+            false => PathResult(*predecessor, *distance)
+        })
+        .collect();
+
+    // Walk the successors from the meeting point to the target
+    if let Some(mut vertex) = meeting {
+        let total = forward[vertex].1 + backward[vertex].1;
+
+        for _ in 0..path.len() {
+            if vertex == target {
+                break;
+            }
+
+            let successor = backward[vertex].0 as usize;
+            path[successor] = PathResult(vertex as i32, total - backward[successor].1);
+            vertex = successor;
+        }
+    }
+
+    path
+}
+
+impl Default for SortestPath {
+    fn default() -> SortestPath {
+        SortestPath::new()
+    }
+}
+
+impl SortestPath {
+    /// Create a new instance of the walker
+    ///
+    /// Panics with the diagnostics of the drivers of the host when there is
+    /// no device to run the kernels on. Use `try_new` to handle the failure,
+    /// and to try again once the device is back.
+    ///
+    /// # Returns
+    ///
+    /// * `Walker` - The walker object
+    ///
+    pub fn new() -> SortestPath {
+        SortestPath::try_new().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Get the digests of the kernel programs compiled, which change with any edit of their sources or when they are replaced
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<(&str, String)>` - The name of every program and the prefix of its SHA-256 digest
+    ///
+    pub fn kernel_digests() -> Vec<(&'static str, String)> {
+        [KernelProgram::Walk, KernelProgram::Pagerank, KernelProgram::Bfs, KernelProgram::Maxflow, KernelProgram::Bidirectional]
+            .into_iter()
+            .map(|program| (program.name(), hex::encode(Sha256::digest(program.source().as_bytes()))[..16].to_string()))
+            .collect()
+    }
+
+    /// Describe the kernel launches needed to walk a matrix
+    ///
+    /// The description mirrors the arguments bound in `get_sortest_path_with_progress`,
+    /// so it can be stored along with the buffers to reproduce the computation.
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix` - The matrix to walk
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<KernelLaunch>` - The kernels in launch order
+    ///
+    pub fn kernel_launches(matrix: &Matrix) -> Vec<KernelLaunch> {
+        let launch = |name: &str, args: &[&str]| KernelLaunch {
+            name: name.to_string(),
+            global_work_size: matrix.width,
+            args: args.iter().map(|arg| arg.to_string()).collect()
+        };
+        let vertex_count = format!("vertex_count={}", matrix.width);
+        let zero_edges = format!("zero_edges={}", matrix.zero_edges as i32);
+
+        let mut launches = vec![
+            launch("initialize_algorithm_buffers", &["result", "distance", "visited", "vertex", "vertex_temp", "source=0"]),
+            launch("shortest_path_algorithm", &["result", "matrix", "distance", "visited", "vertex_temp", &vertex_count]),
+            launch("merge_sortest_path", &["result", "distance", "visited", "vertex", "vertex_temp", "source=0"])
+        ];
+
+        if matrix.deterministic {
+            launches.push(launch("order_predecessors", &["distance", "matrix", "vertex", &vertex_count, "source=0", &zero_edges]));
+        }
+
+        launches
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl SortestPath {
+    /// Create a new instance of the walker in the first device of the first platform
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SortestPath, String>` - The walker object, or the diagnostics of the drivers when there is no device
+    ///
+    pub fn try_new() -> std::result::Result<SortestPath, String> {
+        // Use the first device of the first platform
+        match Platform::first().and_then(|platform| Device::first(platform).map(|device| (platform, device))) {
+            Ok((platform, device)) => SortestPath::try_with_device(platform, device),
+            Err(err) => {
+                let diagnostics = DeviceDiagnostics::collect();
+                error!("The OpenCL device can't be opened: {}", err);
+                Err(diagnostics.summary())
+            }
+        }
+    }
+
+    /// Create a new instance of the walker for a specific device
+    ///
+    /// # Arguments
+    ///
+    /// * `platform` - The platform of the device
+    /// * `device` - The device to run the kernels on
+    ///
+    /// # Returns
+    ///
+    /// * `Walker` - The walker object
+    ///
+    pub fn with_device(platform: Platform, device: Device) -> SortestPath {
+        SortestPath::try_with_device(platform, device).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Create a new instance of the walker for a specific device, failing when the device can't be set up
+    ///
+    /// A device that is busy or whose driver is being reset fails to create
+    /// the context, the queue or the program, and can succeed when tried again.
+    ///
+    /// # Arguments
+    ///
+    /// * `platform` - The platform of the device
+    /// * `device` - The device to run the kernels on
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SortestPath, String>` - The walker object, or why the device can't be set up
+    ///
+    pub fn try_with_device(platform: Platform, device: Device) -> std::result::Result<SortestPath, String> {
+        // Print the initialization info
+        trace!("Initializing OpenCL components before operations...");
+        info!("Using platform: {}", platform.name().unwrap_or_default());
+
+        // Prepare OpenCL Elements
+        let context = Context::builder().platform(platform).devices(device).build()
+            .map_err(|err| format!("The OpenCL context can't be created: {}", err))?;
+        let queue = Queue::new(&context, device, Some(QUEUE_PROFILING_ENABLE))
+            .map_err(|err| format!("The OpenCL queue can't be created: {}", err))?;
+
+        // Compile the fastest variant of the kernels the device can run, unless another one is asked for
+        let capabilities = device_capabilities(device);
+        let variant = select_variant(&capabilities, std::env::var(KERNEL_VARIANT_VARIABLE).ok().as_deref());
+        let program = build_walk_program(&context, device, &capabilities, SINGLE_PRECISION_HEADER, variant)?;
+        info!("Using the {} kernels", program.variant.name());
+
+        // Print the device info and start operations
+        trace!("Initialized OpenCL components, starting operations...");
+        info!("Using device: {}", device.name().unwrap_or_default());
+
+        // Name the versions of everything that can change the results
+        let backend_version = format!(
+            "path_walker {}; kernels {}; {}; {} (driver {})",
+            env!("CARGO_PKG_VERSION"),
+            &hex::encode(Sha256::digest(KernelProgram::Walk.source().as_bytes()))[..16],
+            platform.name().unwrap_or_default(),
+            device.name().unwrap_or_default(),
+            device.info(DeviceInfo::DriverVersion).map(|version| version.to_string()).unwrap_or_default()
+        );
+
+        // Copy the buffers of every iteration to the host only when debugging the kernels
+        let debug_readback = std::env::var(DEBUG_READBACK_VARIABLE).is_ok_and(|enabled| enabled == "1");
+
+        // Reuse the buffers of the previous requests, up to the capacity of the pool
+        let pool = Arc::new(BufferPool::from_env());
+        info!("Keeping up to {} bytes of idle buffers", pool.capacity());
+
+        // Build the object for the service
+        Ok(SortestPath { platform, device, context, capabilities, program, double_program: OnceLock::new(), integer_program: OnceLock::new(), pagerank_program: OnceLock::new(), maxflow_program: OnceLock::new(), bfs_program: OnceLock::new(), bidirectional_program: OnceLock::new(), queue, backend_version, debug_readback, pool })
+    }
+
+    /// Returns the name of the platform in use
+    pub fn platform_name(&self) -> String {
+        self.platform.name().unwrap_or_default()
+    }
+
+    /// Returns the name of the device in use
+    pub fn device_name(&self) -> String {
+        self.device.name().unwrap_or_default()
+    }
+
+    /// Returns the versions of the server, the kernels, the platform and the device in use
+    pub fn backend_version(&self) -> &str {
+        &self.backend_version
+    }
+
+    /// Returns the bytes of global memory of the device in use, if the driver reports them
+    pub fn global_memory(&self) -> Option<u64> {
+        global_memory(self.device)
+    }
+
+    /// Returns the pool of the buffers reused by the uploads and the walks
+    pub fn buffer_pool(&self) -> &BufferPool {
+        &self.pool
+    }
+
+    /// Check if the device can run double precision walks
+    pub fn supports_double(&self) -> bool {
+        self.capabilities.fp64
+    }
+
+    /// Returns what the device can run, as reported by its driver
+    pub fn capabilities(&self) -> &DeviceCapabilities {
+        &self.capabilities
+    }
+
+    /// Returns the variant of the kernels of the single precision walks
+    pub fn kernel_variant(&self) -> KernelVariant {
+        self.program.variant
+    }
+
+    /// Get the program compiled for a precision
+    ///
+    /// The double precision and integer programs are compiled by the first
+    /// walk that needs them, as only the devices with the `cl_khr_fp64`
+    /// extension can build the double precision one. They are compiled in
+    /// the variant of the single precision program.
+    ///
+    /// # Arguments
+    ///
+    /// * `precision` - The precision of the walk
+    ///
+    /// # Returns
+    ///
+    /// * `Result<&WalkProgram>` - The program, or why the device can't run walks in that precision
+    ///
+    fn program(&self, precision: Precision) -> Result<&WalkProgram> {
+        match precision {
+            Precision::Single => Ok(&self.program),
+            Precision::Double => self.double_program.get_or_init(|| {
+                if !self.supports_double() {
+                    return Err(format!("The device {} doesn't support double precision, as it lacks the {} extension", self.device_name(), FP64_EXTENSION));
+                }
+
+                build_walk_program(&self.context, self.device, &self.capabilities, DOUBLE_PRECISION_HEADER, self.program.variant)
+                    .map_err(|err| format!("The double precision kernels can't be compiled: {}", err))
+            }).as_ref().map_err(|err| err.clone().into()),
+            Precision::Integer => self.integer_program.get_or_init(|| {
+                build_walk_program(&self.context, self.device, &self.capabilities, INTEGER_HEADER, self.program.variant)
+                    .map_err(|err| format!("The integer kernels can't be compiled: {}", err))
+            }).as_ref().map_err(|err| err.clone().into())
+        }
+    }
+
+    /// Check that the device can still compile and run kernels
+    ///
+    /// Builds and runs a tiny sentinel kernel in the device, so a driver
+    /// that has died is detected before routing traffic to it.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Nothing, or the error of the compilation or the kernel
+    ///
+    pub fn probe(&self) -> Result<()> {
+        // Compile the sentinel program from its source, never from the cache, so the compiler is checked too
+        let program = Program::builder()
+            .src(KernelProgram::Sentinel.source()).devices(self.device)
+            .build(&self.context)?;
+
+        // Run the sentinel kernel and read its values back
+        let buffer = Buffer::<i32>::builder()
+            .queue(self.queue.clone()).len(SENTINEL_SIZE)
+            .build()?;
+        let sentinel = Kernel::builder()
+            .program(&program).queue(self.queue.clone())
+            .name("sentinel").global_work_size(SpatialDims::One(SENTINEL_SIZE))
+            .arg(&buffer).build()?;
+
+        let mut values = vec![0; SENTINEL_SIZE];
+
+        unsafe {
+            sentinel.enq()?;
+        }
+
+        buffer.read(&mut values).enq()?;
+
+        // Check that the device has computed the expected values
+        if values.iter().enumerate().all(|(gid, value)| *value == gid as i32 * 2) {
+            Ok(())
+        } else {
+            Err(format!("The sentinel kernel has returned {:?}", values).into())
+        }
+    }
+
+    /// Wait for the commands enqueued in the device to finish
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Nothing, or the error of the device
+    ///
+    pub fn finish(&self) -> Result<()> {
+        self.queue.finish()
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl SortestPath {
+    /// Process the kernel result
+    ///
+    /// This method will process the result of the kernel and return the result
+    /// of the operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The result of the kernel
+    /// * `lambda` - The lambda to execute if the result is ok
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<PathResult<T>>>` - The result of the operation
+    ///
+    fn process_kernel_result<T: Real>(&self, result: Result<()>, lambda: impl FnOnce() -> Result<Vec<PathResult<T>>>) -> Result<Vec<PathResult<T>>> {
+        match result {
+            Ok(_) => lambda(),
+            Err(e) => {
+                // Print the error in console
+                error!("Critical error occurred in kernel: {}", e);
+
+                // Return error code
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the best path for hamiltonian walk
+    ///
+    /// This method will return the best path for the hamiltonian walk
+    /// enumerating elements in step order over the vector result.
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix` - The matrix to walk
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<i32>` - The path of the walk
+    ///
+    pub fn get_sortest_path<T: Real + OclPrm>(&self, matrix: Matrix<T>) -> Result<Vec<PathResult<T>>> {
+        self.get_sortest_path_with_progress(matrix, &Cancellation::default(), |_, _| {})
+    }
+
+    /// Returns the best path for hamiltonian walk reporting the progress
+    ///
+    /// Same as `get_sortest_path`, but the `progress` callback is invoked after
+    /// every iteration of the algorithm with the completed and total iterations,
+    /// and the walk is aborted between iterations when cancelled.
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix` - The matrix to walk
+    /// * `cancellation` - Aborts the walk when its timeout expires or its client goes away
+    /// * `progress` - The callback to notify the progress of the operation
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<i32>` - The path of the walk
+    ///
+    pub fn get_sortest_path_with_progress<T: Real + OclPrm>(&self, matrix: Matrix<T>, cancellation: &Cancellation, progress: impl FnMut(usize, usize)) -> Result<Vec<PathResult<T>>> {
+        let graph = self.upload(&matrix)?;
+        self.walk(&graph, 0, cancellation, progress)
+    }
+
+    /// Upload a matrix to the device
+    ///
+    /// The matrix is copied into a device buffer of the pool, so it can be
+    /// walked many times without transferring it again. Both cells of every edge of an
+    /// undirected matrix are filled, as the kernels read the matrices as
+    /// directed.
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix` - The matrix to upload
+    ///
+    /// # Returns
+    ///
+    /// * `DeviceGraph` - The matrix resident in the device
+    ///
+    pub fn upload<T: Real + OclPrm>(&self, matrix: &Matrix<T>) -> Result<DeviceGraph<T>> {
+        // Print the initialization
+        let _span = debug_span!("upload", width = matrix.width).entered();
+        trace!("Uploading matrix to the device...");
+
+        // Copy the matrix into a buffer of the pool
+        let buffer = self.pool.take::<T>(&self.queue, matrix.data.len())?;
+        buffer.write(&*matrix.directed_data()).enq()?;
+
+        let nonnegative = matrix.data.iter().all(|weight| *weight >= T::default());
+        let graph = DeviceGraph { width: matrix.width, zero_edges: matrix.zero_edges, rounds: matrix.walk_rounds(), nonnegative, deterministic: matrix.deterministic, buffer };
+        add_device_memory(graph.size_in_bytes() as i64);
+
+        Ok(graph)
+    }
+
+    /// Change some cells of a matrix resident in the device
+    ///
+    /// The matrix is copied in the device to a new buffer and only the cells
+    /// given are written to it, in a single write for every run of
+    /// contiguous cells, so a few changed edges are copied to the device
+    /// instead of the whole matrix, while the walks still running on the
+    /// matrix keep reading its weights unchanged. The cells are indexed the
+    /// same way as the weights the kernels read, from `directed_data`.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The matrix resident in the device
+    /// * `cells` - The index and the new weight of every cell to change
+    ///
+    /// # Returns
+    ///
+    /// * `Result<DeviceGraph<T>>` - The matrix with the changed cells, or the error of the device
+    ///
+    pub fn update_edges<T: Real + OclPrm>(&self, graph: &DeviceGraph<T>, cells: &[(usize, T)]) -> Result<DeviceGraph<T>> {
+        let _span = debug_span!("update_edges", width = graph.width, cells = cells.len()).entered();
+
+        // Copy the matrix to a buffer of its own
+        let cells_len = graph.width * graph.width;
+        let buffer = self.pool.take::<T>(&self.queue, cells_len)?;
+        graph.buffer.copy(&*buffer, None, Some(cells_len)).enq()?;
+
+        // Write every run of contiguous cells at once
+        for (offset, weights) in cell_runs(cells) {
+            buffer.write(&weights[..]).offset(offset).enq()?;
+        }
+
+        let changed = DeviceGraph { width: graph.width, zero_edges: graph.zero_edges, rounds: graph.rounds, nonnegative: graph.nonnegative, deterministic: graph.deterministic, buffer };
+        add_device_memory(changed.size_in_bytes() as i64);
+
+        Ok(changed)
+    }
+
+    /// Returns the best path for hamiltonian walk from a source vertex
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The matrix resident in the device
+    /// * `source` - The vertex to start the walk from
+    /// * `cancellation` - Aborts the walk between iterations, releasing its buffers
+    /// * `progress` - The callback to notify the progress of the operation
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<i32>` - The path of the walk
+    ///
+    pub fn walk<T: Real + OclPrm>(&self, graph: &DeviceGraph<T>, source: usize, cancellation: &Cancellation, mut progress: impl FnMut(usize, usize)) -> Result<Vec<PathResult<T>>> {
+        // Print the initialization
+        let _span = debug_span!("walk", width = graph.width, source, precision = ?T::PRECISION).entered();
+        let WalkProgram { program, variant } = self.program(T::PRECISION)?;
+        trace!("Initializing buffers for the kernel...");
+
+        // Count the walk in the queue depth until it finishes
+        let _queued = QueuedWalk::start(graph.width);
+
+        // Keep the shutdown waiting until the walk finishes
+        let _execution = KERNEL_EXECUTIONS.start();
+
+        // Instantiate the result vector
+        let width = graph.width;
+        let matrix_buffer: &Buffer<T> = &graph.buffer;
+        let mut result = vec![T::default(); width];
+        let mut vertex = vec![T::default(); width];
+        let mut distance = vec![T::default(); width];
+        let mut improved = vec![1];
+
+        // Take the four weight and one int buffers of the walk from the pool, which the init kernel fills
+        let setup = debug_span!("buffer_setup", width).entered();
+        let result_buffer = self.pool.take::<T>(&self.queue, width)?;
+        let distance_buffer = self.pool.take::<T>(&self.queue, width)?;
+        let vertex_buffer = self.pool.take::<T>(&self.queue, width)?;
+        let vertex_temp_buffer = self.pool.take::<T>(&self.queue, width)?;
+        let visited_buffer = self.pool.take::<i32>(&self.queue, width)?;
+
+        // Take the flag of the iterations that improve a distance, for the atomic variant
+        let improved_buffer = match variant {
+            KernelVariant::Atomics => Some(self.pool.take::<i32>(&self.queue, 1)?),
+            _ => None
+        };
+
+        // Account the buffers of the walk
+        let scratch_bytes = (4 * result_buffer.size_in_bytes() + visited_buffer.size_in_bytes() + improved_buffer.as_ref().map_or(0, PooledBuffer::size_in_bytes)) as i64;
+        add_device_memory(scratch_bytes);
+
+        let path = unsafe {
+            let improved_buffer = improved_buffer.as_deref();
+
+            // Print the buffers initialization
+            trace!("Buffers initialized, starting kernel...");
+
+            // Instantiate the buffers kernel
+            let initialize_algorithm_buffers = Kernel::builder()
+                .program(program).queue(self.queue.clone())
+                .name("initialize_algorithm_buffers").global_work_size(SpatialDims::One(width))
+                .arg(&*result_buffer).arg(&*distance_buffer).arg(&*visited_buffer).arg(&*vertex_buffer).arg(&*vertex_temp_buffer)
+                .arg(source as i32).arg(graph.zero_edges as i32).build().unwrap();
+
+            // Instantiate the main kernel, with whole work groups of a tile for the tiled variant
+            let mut shortest_path_algorithm = Kernel::builder();
+            shortest_path_algorithm.program(program).queue(self.queue.clone())
+                .arg(&*result_buffer).arg(matrix_buffer).arg(&*distance_buffer).arg(&*visited_buffer).arg(&*vertex_temp_buffer)
+                .arg(width as i32).arg(graph.zero_edges as i32);
+
+            if *variant == KernelVariant::Tiled {
+                let tile = self.capabilities.tile_size();
+                shortest_path_algorithm.name("shortest_path_tiled")
+                    .global_work_size(SpatialDims::One(width.div_ceil(tile) * tile)).local_work_size(SpatialDims::One(tile));
+            } else {
+                shortest_path_algorithm.name("shortest_path_algorithm").global_work_size(SpatialDims::One(width));
+            }
+
+            let shortest_path_algorithm = shortest_path_algorithm.build().unwrap();
+
+            // Instantiate the merge kernel, flagging the improvements for the atomic variant
+            let mut merge_sortest_path = Kernel::builder();
+            merge_sortest_path.program(program).queue(self.queue.clone())
+                .global_work_size(SpatialDims::One(width))
+                .arg(&*result_buffer).arg(&*distance_buffer).arg(&*visited_buffer).arg(&*vertex_buffer).arg(&*vertex_temp_buffer)
+                .arg(source as i32);
+
+            match improved_buffer {
+                Some(improved_buffer) => merge_sortest_path.name("merge_sortest_path_flagged").arg(improved_buffer),
+                None => merge_sortest_path.name("merge_sortest_path")
+            };
+
+            let merge_sortest_path = merge_sortest_path.build().unwrap();
+
+            // Print the kernel start
+            setup.exit();
+            trace!("Kernel started, enqueueing the operation...");
+
+            // Enqueue the initialization, which the in-order queue runs before the iterations
+            let mut init_event = Event::empty();
+
+            self.process_kernel_result(initialize_algorithm_buffers.cmd().enew(&mut init_event).enq(), || {
+                let mut events = vec![(KernelStage::Init, init_event)];
+                let (mut iteration, rounds) = (0, graph.rounds);
+
+                // Run the algorithm, waiting for the device only once per batch of iterations, up to the hops of the paths
+                while iteration < rounds {
+                    // Give up before enqueueing more work when the walk has been cancelled
+                    if let Err(message) = cancellation.check() {
+                        warn!("Aborting the walk at iteration {} of {}: {}", iteration, rounds, message);
+                        return Err(message.into());
+                    }
+
+                    // Clear the flag of the improvements of the previous batch
+                    if let Some(improved_buffer) = improved_buffer {
+                        improved_buffer.write(&[0][..]).enq()?;
+                    }
+
+                    // Enqueue the iterations of the batch, keeping their data in the device
+                    let batch = if self.debug_readback { 1 } else { ITERATIONS_PER_SYNC.min(rounds - iteration) };
+                    let batch_span = debug_span!("batch", iteration, iterations = batch).entered();
+
+                    for _ in 0..batch {
+                        let (mut iterate_event, mut merge_event) = (Event::empty(), Event::empty());
+                        shortest_path_algorithm.cmd().enew(&mut iterate_event).enq().inspect_err(log_kernel_error)?;
+                        merge_sortest_path.cmd().enew(&mut merge_event).enq().inspect_err(log_kernel_error)?;
+                        events.extend([(KernelStage::Iterate, iterate_event), (KernelStage::Merge, merge_event)]);
+                    }
+
+                    // Wait for the batch and account the time its kernels have run in the device
+                    self.queue.finish().inspect_err(log_kernel_error)?;
+                    batch_span.exit();
+                    iteration += batch;
+
+                    for (stage, event) in events.drain(..) {
+                        if let Some(duration) = device_time(&event) {
+                            observe_kernel(stage, duration);
+                        }
+                    }
+
+                    // Copy the buffers to the host after every iteration only when debugging the kernels
+                    if self.debug_readback {
+                        result_buffer.read(&mut result).enq()?;
+                        vertex_buffer.read(&mut vertex).enq()?;
+                        distance_buffer.read(&mut distance).enq()?;
+
+                        trace!("Result: {:?}", result);
+                        trace!("Distance: {:?}", distance);
+                        trace!("Vertex: {:?}", vertex);
+                    }
+
+                    // Once a batch improves no distance the next ones can't either, so the walk is complete
+                    if let Some(improved_buffer) = improved_buffer {
+                        improved_buffer.read(&mut improved).enq()?;
+                    }
+
+                    let settled = improved[0] == 0;
+
+                    // Notify the progress of the operation
+                    progress(if settled { rounds } else { iteration }, rounds);
+
+                    if settled {
+                        trace!("The distances have settled at iteration {} of {}", iteration, rounds);
+                        break;
+                    }
+                }
+
+                // Break the ties of the predecessors by the lowest vertex once the distances have settled
+                if graph.deterministic {
+                    let order_predecessors = Kernel::builder()
+                        .program(program).queue(self.queue.clone())
+                        .name("order_predecessors").global_work_size(SpatialDims::One(width))
+                        .arg(&*distance_buffer).arg(matrix_buffer).arg(&*vertex_buffer)
+                        .arg(width as i32).arg(source as i32).arg(graph.zero_edges as i32).build()?;
+
+                    order_predecessors.cmd().enq().inspect_err(log_kernel_error)?;
+                }
+
+                // Copy the predecessors and the distances to the host once the walk is complete
+                vertex_buffer.read(&mut vertex).enq()?;
+                distance_buffer.read(&mut distance).enq()?;
+
+                // Compute the result path vector
+                let mut result_path = Vec::<PathResult<T>>::new();
+                for x in 0..width {
+                    result_path.push(PathResult(Into::<f64>::into(vertex[x]) as i32, distance[x]));
+                }
+
+                // Print the result of the operation
+                info!("Path to walk {:?}", result_path);
+
+                // Return the result
+                Ok(result_path)
+            })
+        };
+
+        // Release the buffers of the walk from the accounting
+        add_device_memory(-scratch_bytes);
+
+        path
+    }
+}
+
+#[cfg(feature = "opencl")]
+impl SortestPath {
+    /// Walk a graph breadth first from a source vertex, counting the hops instead of adding the weights
+    ///
+    /// Every level of the walk is a kernel launch, where the vertices not
+    /// reached yet join the next level through any edge coming from the
+    /// current one, until a level reaches no vertex. The walk is far faster
+    /// than the weighted one for the graphs whose edges all cost the same,
+    /// as it only runs as many launches as levels. The path has the same
+    /// shape as the weighted walks, with the hops to every vertex as its
+    /// distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The matrix resident in the device
+    /// * `source` - The vertex to start the walk from
+    /// * `cancellation` - Aborts the walk between levels
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<PathResult>>` - The predecessor and the hops of every vertex, or the error of the kernel
+    ///
+    pub fn breadth_first(&self, graph: &DeviceGraph, source: usize, cancellation: &Cancellation) -> Result<Vec<PathResult>> {
+        let _span = debug_span!("breadth_first", width = graph.width, source).entered();
+        let program = self.bfs_program.get_or_init(|| {
+            build_program(&self.context, self.device, KernelProgram::Bfs.source())
+                .map_err(|err| format!("The breadth first kernels can't be compiled: {}", err))
+        }).as_ref().map_err(|err| ocl::Error::from(err.clone()))?;
+
+        // Count the walk in the queue depth until it finishes
+        let _queued = QueuedWalk::start(graph.width);
+
+        // Keep the shutdown waiting until the walk finishes
+        let _execution = KERNEL_EXECUTIONS.start();
+
+        // Account the level, the predecessor and the flag buffers of the walk
+        let width = graph.width;
+        let scratch_bytes = ((2 * width + 1) * std::mem::size_of::<i32>()) as i64;
+        add_device_memory(scratch_bytes);
+
+        // Start from the source as the only vertex of the first level
+        let walk = (|| {
+            let mut level = vec![-1; width];
+            let mut predecessor = vec![source as i32; width];
+            let mut changed = [1];
+            level[source] = 0;
+
+            let level_buffer = Buffer::<i32>::builder()
+                .queue(self.queue.clone()).len(width)
+                .copy_host_slice(&level)
+                .build()?;
+            let predecessor_buffer = Buffer::<i32>::builder()
+                .queue(self.queue.clone()).len(width)
+                .copy_host_slice(&predecessor)
+                .build()?;
+            let changed_buffer = Buffer::<i32>::builder()
+                .queue(self.queue.clone()).len(1)
+                .build()?;
+
+            let breadth_first_level = Kernel::builder()
+                .program(program).queue(self.queue.clone())
+                .name("breadth_first_level").global_work_size(SpatialDims::One(width))
+                .arg(&*graph.buffer).arg(&level_buffer).arg(&predecessor_buffer).arg(&changed_buffer)
+                .arg(width as i32).arg_named("depth", 0i32).arg(graph.zero_edges as i32).build()?;
+
+            let mut depth = 0;
+
+            // Stop at the last level or at the hops of the paths, whatever comes first
+            while changed[0] != 0 && (depth as usize) < graph.rounds {
+                // Give up before enqueueing more work when the walk has been cancelled
+                if let Err(message) = cancellation.check() {
+                    warn!("Aborting the breadth first walk at level {}: {}", depth, message);
+                    return Err(message.into());
+                }
+
+                changed_buffer.write(&[0][..]).enq()?;
+                breadth_first_level.set_arg("depth", depth)?;
+
+                unsafe {
+                    breadth_first_level.enq()?;
+                }
+
+                // Read back if the level has reached any vertex
+                changed_buffer.read(&mut changed[..]).enq()?;
+                depth += 1;
+            }
+
+            // Copy the levels to the host, leaving the unreachable vertices as the weighted walks do
+            level_buffer.read(&mut level).enq()?;
+            predecessor_buffer.read(&mut predecessor).enq()?;
+
+            let unreachable = if graph.zero_edges { f32::MAX } else { 0.0 };
+
+            Ok(level.into_iter().zip(predecessor)
+                .map(|(level, predecessor)| PathResult(predecessor, if level < 0 { unreachable } else { level as f32 }))
+                .collect())
+        })();
+
+        // Release the buffers of the walk from the accounting
+        add_device_memory(-scratch_bytes);
+
+        walk
+    }
+
+    /// Walk a graph from a source to a target searching from both ends at once
+    ///
+    /// A search from the source and a search from the target, following the
+    /// edges backwards, take turns relaxing the edges of their frontiers, one
+    /// kernel launch per turn, and a reduction finds the vertex where their
+    /// distances add up to the shortest route and the closest vertex of both
+    /// frontiers. Without negative weights, no route through the frontiers can
+    /// be shorter than the one found once the closest vertices of both
+    /// frontiers add up to its distance, so the walk stops there, having
+    /// touched far fewer vertices than a walk of the whole graph from the
+    /// source. Only the vertices of the route are sure to have their shortest
+    /// distance in the path, as described in `join_searches`.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The matrix resident in the device, without negative weights
+    /// * `source` - The vertex to start the walk from
+    /// * `target` - The vertex to reach
+    /// * `cancellation` - Aborts the walk between turns
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<PathResult>>` - The predecessor and the distance of every vertex, or the error of the kernel
+    ///
+    pub fn bidirectional(&self, graph: &DeviceGraph, source: usize, target: usize, cancellation: &Cancellation) -> Result<Vec<PathResult>> {
+        let _span = debug_span!("bidirectional", width = graph.width, source, target).entered();
+        let program = self.bidirectional_program.get_or_init(|| {
+            build_program(&self.context, self.device, KernelProgram::Bidirectional.source())
+                .map_err(|err| format!("The bidirectional kernels can't be compiled: {}", err))
+        }).as_ref().map_err(|err| ocl::Error::from(err.clone()))?;
+
+        // Count the walk in the queue depth until it finishes
+        let _queued = QueuedWalk::start(graph.width);
+
+        // Keep the shutdown waiting until the walk finishes
+        let _execution = KERNEL_EXECUTIONS.start();
+
+        // Account the distance, parent and frontier buffers of both searches and the buffers of the reduction
+        let width = graph.width;
+        let groups = width.div_ceil(MEETING_SIZE);
+        let scratch_bytes = ((8 * width + 4 * groups) * std::mem::size_of::<i32>()) as i64;
+        add_device_memory(scratch_bytes);
+
+        let walk = (|| {
+            // Start every search from its end as the only vertex of its frontier
+            let search = |start: usize| -> Result<[Buffer<i32>; 3]> {
+                let mut frontier = vec![0; width];
+                frontier[start] = 1;
+
+                Ok([
+                    Buffer::<i32>::builder().queue(self.queue.clone()).len(width).fill_val(start as i32).build()?,
+                    Buffer::<i32>::builder().queue(self.queue.clone()).len(width).copy_host_slice(&frontier).build()?,
+                    Buffer::<i32>::builder().queue(self.queue.clone()).len(width).build()?
+                ])
+            };
+            let distances = |start: usize| -> Result<Buffer<f32>> {
+                let mut distance = vec![f32::MAX; width];
+                distance[start] = 0.0;
+
+                Buffer::<f32>::builder().queue(self.queue.clone()).len(width).copy_host_slice(&distance).build()
+            };
+
+            let (forward_distance, backward_distance) = (distances(source)?, distances(target)?);
+            let [forward_parent, forward_frontier, forward_next] = search(source)?;
+            let [backward_parent, backward_frontier, backward_next] = search(target)?;
+            let bounds_buffer = Buffer::<f32>::builder()
+                .queue(self.queue.clone()).len(3 * groups)
+                .build()?;
+            let meetings_buffer = Buffer::<i32>::builder()
+                .queue(self.queue.clone()).len(groups)
+                .build()?;
+
+            let relax = |distance: &Buffer<f32>, parent: &Buffer<i32>, frontier: &Buffer<i32>, next: &Buffer<i32>, reverse: bool| {
+                Kernel::builder()
+                    .program(program).queue(self.queue.clone())
+                    .name("relax_frontier").global_work_size(SpatialDims::One(width))
+                    .arg(&*graph.buffer).arg(distance).arg(parent).arg(frontier).arg(next)
+                    .arg(width as i32).arg(reverse as i32).arg(graph.zero_edges as i32).build()
+            };
+            let relax_forward = relax(&forward_distance, &forward_parent, &forward_frontier, &forward_next, false)?;
+            let relax_backward = relax(&backward_distance, &backward_parent, &backward_frontier, &backward_next, true)?;
+            let meeting_point = Kernel::builder()
+                .program(program).queue(self.queue.clone())
+                .name("meeting_point").global_work_size(SpatialDims::One(groups * MEETING_SIZE)).local_work_size(SpatialDims::One(MEETING_SIZE))
+                .arg(&forward_distance).arg(&backward_distance).arg(&forward_frontier).arg(&backward_frontier)
+                .arg(&bounds_buffer).arg(&meetings_buffer).arg(width as i32).build()?;
+
+            let mut bounds = vec![0.0; 3 * groups];
+            let mut meetings = vec![-1; groups];
+            let mut meeting = None;
+
+            // Every search converges in as many turns as vertices, so the turns are bounded by twice that
+            for turn in 0..=2 * width {
+                // Give up before enqueueing more work when the walk has been cancelled
+                if let Err(message) = cancellation.check() {
+                    warn!("Aborting the bidirectional walk at turn {}: {}", turn, message);
+                    return Err(message.into());
+                }
+
+                // Reduce the meeting point and the closest vertex of both frontiers
+                unsafe {
+                    meeting_point.enq()?;
+                }
+
+                bounds_buffer.read(&mut bounds).enq()?;
+                meetings_buffer.read(&mut meetings).enq()?;
+
+                let (mut shortest, mut forward_closest, mut backward_closest) = (f32::MAX, f32::MAX, f32::MAX);
+                for (group, meeting_bounds) in bounds.chunks(3).enumerate() {
+                    if meeting_bounds[0] < shortest {
+                        shortest = meeting_bounds[0];
+                        meeting = Some(meetings[group] as usize);
+                    }
+
+                    forward_closest = forward_closest.min(meeting_bounds[1]);
+                    backward_closest = backward_closest.min(meeting_bounds[2]);
+                }
+
+                // Stop once a search has nowhere to go or no route through the frontiers can be shorter
+                if forward_closest == f32::MAX || backward_closest == f32::MAX || forward_closest + backward_closest >= shortest {
+                    break;
+                }
+
+                // Take the turn of a search, whose next frontier replaces the current one
+                let (kernel, frontier, next) = match turn % 2 {
+                    0 => (&relax_forward, &forward_frontier, &forward_next),
+                    _ => (&relax_backward, &backward_frontier, &backward_next)
+                };
+
+                unsafe {
+                    kernel.enq()?;
+                }
+
+                next.copy(frontier, None, None).enq()?;
+            }
+
+            // Copy both searches to the host and join them on the meeting point
+            let read = |distance: &Buffer<f32>, parent: &Buffer<i32>| -> Result<Vec<PathResult>> {
+                let (mut distances, mut parents) = (vec![0.0; width], vec![0; width]);
+                distance.read(&mut distances).enq()?;
+                parent.read(&mut parents).enq()?;
+
+                Ok(parents.into_iter().zip(distances).map(|(parent, distance)| PathResult(parent, distance)).collect())
+            };
+
+            Ok(join_searches(&read(&forward_distance, &forward_parent)?, &read(&backward_distance, &backward_parent)?, meeting, source, target, graph.zero_edges))
+        })();
+
+        // Release the buffers of the walk from the accounting
+        add_device_memory(-scratch_bytes);
+
+        walk
+    }
+
+    /// Rank the vertices of a graph with PageRank in the device
+    ///
+    /// Runs the power method from equal scores, one kernel launch per
+    /// iteration, until the scores change less than the tolerance in total
+    /// or the iterations run out. The rank of the vertices without edges is
+    /// spread over every vertex between the iterations.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The validated PageRank request
+    /// * `cancellation` - Aborts the ranking between iterations
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PageRank>` - The scores of the vertices, or the error of the kernel
+    ///
+    pub fn pagerank(&self, request: &PageRankRequest, cancellation: &Cancellation) -> Result<PageRank> {
+        let _span = debug_span!("pagerank", width = request.matrix.width).entered();
+        let program = self.pagerank_program.get_or_init(|| {
+            build_program(&self.context, self.device, KernelProgram::Pagerank.source())
+                .map_err(|err| format!("The PageRank kernels can't be compiled: {}", err))
+        }).as_ref().map_err(|err| ocl::Error::from(err.clone()))?;
+
+        // Keep the shutdown waiting until the ranking finishes
+        let _execution = KERNEL_EXECUTIONS.start();
+
+        // Account the transitions and the two score buffers of the ranking
+        let width = request.matrix.width;
+        let scratch_bytes = ((width * width + 2 * width) * std::mem::size_of::<f32>()) as i64;
+        add_device_memory(scratch_bytes);
+
+        // Upload the transitions and start from equal scores
+        let ranking = (|| {
+            let (transitions, dangling) = transition_matrix(&request.matrix);
+            let mut ranks = vec![1.0 / width as f32; width];
+            let mut next = vec![0.0; width];
+
+            let transitions_buffer = Buffer::<f32>::builder()
+                .queue(self.queue.clone()).len(transitions.len())
+                .flags(MemFlags::READ_ONLY).copy_host_slice(&transitions)
+                .build()?;
+            let rank_buffer = Buffer::<f32>::builder()
+                .queue(self.queue.clone()).len(width)
+                .copy_host_slice(&ranks)
+                .build()?;
+            let next_buffer = Buffer::<f32>::builder()
+                .queue(self.queue.clone()).len(width)
+                .build()?;
+
+            let pagerank_iteration = Kernel::builder()
+                .program(program).queue(self.queue.clone())
+                .name("pagerank_iteration").global_work_size(SpatialDims::One(width))
+                .arg(&transitions_buffer).arg(&rank_buffer).arg(&next_buffer)
+                .arg(width as i32).arg(request.damping).arg_named("teleport", 0.0f32).build()?;
+
+            for iteration in 1..=request.max_iterations {
+                // Give up before enqueueing more work when the ranking has been cancelled
+                if let Err(message) = cancellation.check() {
+                    warn!("Aborting the ranking at iteration {} of {}: {}", iteration, request.max_iterations, message);
+                    return Err(message.into());
+                }
+
+                pagerank_iteration.set_arg("teleport", teleport(&ranks, &dangling, request.damping))?;
+
+                unsafe {
+                    pagerank_iteration.enq()?;
+                }
+
+                // Read the new scores back, and feed them to the next iteration
+                next_buffer.read(&mut next).enq()?;
+                rank_buffer.write(&next).enq()?;
+
+                let change: f64 = ranks.iter().zip(&next).map(|(rank, next)| (rank - next).abs() as f64).sum();
+                std::mem::swap(&mut ranks, &mut next);
+
+                if change < request.tolerance as f64 {
+                    return Ok(PageRank { scores: ranks, iterations: iteration, converged: true });
+                }
+            }
+
+            Ok(PageRank { scores: ranks, iterations: request.max_iterations, converged: false })
+        })();
+
+        // Release the buffers of the ranking from the accounting
+        add_device_memory(-scratch_bytes);
+
+        ranking
+    }
+
+    /// Find the maximum flow of a network and its minimum cut in the device
+    ///
+    /// Runs the push-relabel algorithm in synchronous rounds from the preflow
+    /// saturating the edges leaving the source, every round pushing the
+    /// excess of every vertex downhill and then lifting the vertices that
+    /// couldn't push it, until no vertex but the source and the sink keeps
+    /// any excess. The flow is then the excess reaching the sink.
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix` - The validated matrix of capacities
+    /// * `source` - The vertex the flow leaves
+    /// * `sink` - The vertex the flow reaches
+    /// * `cancellation` - Aborts the computation between rounds
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MaxFlow>` - The maximum flow and its minimum cut, or the error of the kernels
+    ///
+    pub fn max_flow(&self, matrix: &Matrix, source: usize, sink: usize, cancellation: &Cancellation) -> Result<MaxFlow> {
+        let _span = debug_span!("max_flow", width = matrix.width).entered();
+        let program = self.maxflow_program.get_or_init(|| {
+            build_program(&self.context, self.device, KernelProgram::Maxflow.source())
+                .map_err(|err| format!("The maximum flow kernels can't be compiled: {}", err))
+        }).as_ref().map_err(|err| ocl::Error::from(err.clone()))?;
+
+        // Keep the shutdown waiting until the computation finishes
+        let _execution = KERNEL_EXECUTIONS.start();
+
+        // Account the residual edges, the pushes and the vectors of the vertices
+        let width = matrix.width;
+        let scratch_bytes = ((2 * width * width + 5 * width) * std::mem::size_of::<f32>()) as i64;
+        add_device_memory(scratch_bytes);
+
+        // Upload the preflow, with the source lifted above every vertex
+        let computation = (|| {
+            let capacities = capacities(matrix);
+            let (mut residual, mut excess) = preflow(&capacities, width, source);
+            let mut height = vec![0i32; width];
+            height[source] = width as i32;
+
+            let residual_buffer = Buffer::<f32>::builder()
+                .queue(self.queue.clone()).len(residual.len())
+                .copy_host_slice(&residual)
+                .build()?;
+            let excess_buffer = Buffer::<f32>::builder()
+                .queue(self.queue.clone()).len(width)
+                .copy_host_slice(&excess)
+                .build()?;
+            let height_buffer = Buffer::<i32>::builder()
+                .queue(self.queue.clone()).len(width)
+                .copy_host_slice(&height)
+                .build()?;
+            let next_height_buffer = Buffer::<i32>::builder()
+                .queue(self.queue.clone()).len(width)
+                .build()?;
+            let pushed_buffer = Buffer::<f32>::builder()
+                .queue(self.queue.clone()).len(width * width)
+                .build()?;
+            let remaining_buffer = Buffer::<f32>::builder()
+                .queue(self.queue.clone()).len(width)
+                .build()?;
+
+            let push_excess = Kernel::builder()
+                .program(program).queue(self.queue.clone())
+                .name("push_excess").global_work_size(SpatialDims::One(width))
+                .arg(&residual_buffer).arg(&excess_buffer).arg(&height_buffer).arg(&pushed_buffer).arg(&remaining_buffer)
+                .arg(width as i32).arg(source as i32).arg(sink as i32).build()?;
+            let apply_pushes = Kernel::builder()
+                .program(program).queue(self.queue.clone())
+                .name("apply_pushes").global_work_size(SpatialDims::One(width))
+                .arg(&residual_buffer).arg(&excess_buffer).arg(&pushed_buffer).arg(&remaining_buffer)
+                .arg(width as i32).build()?;
+            let relabel = Kernel::builder()
+                .program(program).queue(self.queue.clone())
+                .name("relabel").global_work_size(SpatialDims::One(width))
+                .arg(&residual_buffer).arg(&excess_buffer).arg(&height_buffer).arg(&next_height_buffer)
+                .arg(width as i32).arg(source as i32).arg(sink as i32).build()?;
+
+            let active = |excess: &[f32]| excess.iter().enumerate().any(|(vertex, excess)| vertex != source && vertex != sink && *excess > 0.0);
+            let mut rounds = 0;
+
+            while active(&excess) {
+                // Give up before enqueueing more work when the computation has been cancelled
+                if let Err(message) = cancellation.check() {
+                    warn!("Aborting the maximum flow at round {}: {}", rounds, message);
+                    return Err(message.into());
+                }
+
+                unsafe {
+                    push_excess.enq()?;
+                    apply_pushes.enq()?;
+                    relabel.enq()?;
+                }
+
+                // Lift the vertices for the next round, and read the excess left back
+                next_height_buffer.copy(&height_buffer, None, None).enq()?;
+                excess_buffer.read(&mut excess).enq()?;
+                rounds += 1;
+            }
+
+            // Read the residual edges back to find the side of the source
+            residual_buffer.read(&mut residual).enq()?;
+
+            Ok(MaxFlow { flow: excess[sink], cut: min_cut(&capacities, &residual, width, source), rounds })
+        })();
+
+        // Release the buffers of the computation from the accounting
+        add_device_memory(-scratch_bytes);
+
+        computation
+    }
+}
+
+/// The sortest path service, in the builds without OpenCL
+///
+/// There is no device to open, so the walker can't be created and the
+/// servers walk every graph with the CPU backend. It keeps the methods of
+/// the OpenCL walker, so the callers build the same with or without it.
+///
+/// # Fields
+///
+/// * `unavailable` - Makes the walker impossible to create
+///
+#[cfg(not(feature = "opencl"))]
+pub struct SortestPath {
+    unavailable: Infallible,
+}
+
+/// A matrix resident in the device memory, in the builds without OpenCL
+///
+/// # Fields
+///
+/// * `width` - The number of vertices of the matrix
+/// * `zero_edges` - If the zero weights of the matrix are edges
+/// * `rounds` - The iterations of its walks, bounded by the hops of the paths
+/// * `nonnegative` - If the matrix has no negative weights, so its walks can search from both ends
+/// * `deterministic` - If its walks give every vertex the lowest of its shortest predecessors
+/// * `unavailable` - Makes the graph impossible to upload
+/// * `element` - The type of the weights of the matrix
+///
+#[cfg(not(feature = "opencl"))]
+pub struct DeviceGraph<T: OclPrm = f32> {
+    pub width: usize,
+    pub zero_edges: bool,
+    pub rounds: usize,
+    pub nonnegative: bool,
+    pub deterministic: bool,
+    unavailable: Infallible,
+    element: PhantomData<T>,
+}
+
+#[cfg(not(feature = "opencl"))]
+impl<T: OclPrm> DeviceGraph<T> {
+    /// Returns the bytes of device memory used by the matrix
+    pub fn size_in_bytes(&self) -> usize {
+        match self.unavailable {}
+    }
+}
+
+#[cfg(not(feature = "opencl"))]
+impl SortestPath {
+    /// Fail to create the walker, as the build has no OpenCL
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SortestPath, String>` - Why the walker can't be created
+    ///
+    pub fn try_new() -> std::result::Result<SortestPath, String> {
+        Err("The server has been built without OpenCL, only the cpu backend can walk the graphs".to_string())
+    }
+
+    /// Returns the name of the platform in use
+    pub fn platform_name(&self) -> String {
+        match self.unavailable {}
+    }
+
+    /// Returns the name of the device in use
+    pub fn device_name(&self) -> String {
+        match self.unavailable {}
+    }
+
+    /// Returns the versions of the server, the kernels, the platform and the device in use
+    pub fn backend_version(&self) -> &str {
+        match self.unavailable {}
+    }
+
+    /// Returns the bytes of global memory of the device in use
+    pub fn global_memory(&self) -> Option<u64> {
+        match self.unavailable {}
+    }
+
+    /// Returns the pool of the buffers reused by the uploads and the walks
+    pub fn buffer_pool(&self) -> &BufferPool {
+        match self.unavailable {}
+    }
+
+    /// Check if the device can run double precision walks
+    pub fn supports_double(&self) -> bool {
+        match self.unavailable {}
+    }
+
+    /// Returns what the device can run
+    pub fn capabilities(&self) -> &DeviceCapabilities {
+        match self.unavailable {}
+    }
+
+    /// Returns the variant of the kernels of the single precision walks
+    pub fn kernel_variant(&self) -> KernelVariant {
+        match self.unavailable {}
+    }
+
+    /// Check that the device can still compile and run kernels
+    pub fn probe(&self) -> Result<()> {
+        match self.unavailable {}
+    }
+
+    /// Wait for the commands enqueued in the device to finish
+    pub fn finish(&self) -> Result<()> {
+        match self.unavailable {}
+    }
+
+    /// Returns the best path for hamiltonian walk
+    pub fn get_sortest_path<T: Real + OclPrm>(&self, _matrix: Matrix<T>) -> Result<Vec<PathResult<T>>> {
+        match self.unavailable {}
+    }
+
+    /// Returns the best path for hamiltonian walk reporting the progress
+    pub fn get_sortest_path_with_progress<T: Real + OclPrm>(&self, _matrix: Matrix<T>, _cancellation: &Cancellation, _progress: impl FnMut(usize, usize)) -> Result<Vec<PathResult<T>>> {
+        match self.unavailable {}
+    }
+
+    /// Upload a matrix to the device
+    pub fn upload<T: Real + OclPrm>(&self, _matrix: &Matrix<T>) -> Result<DeviceGraph<T>> {
+        match self.unavailable {}
+    }
+
+    /// Change some cells of a matrix resident in the device
+    pub fn update_edges<T: Real + OclPrm>(&self, _graph: &DeviceGraph<T>, _cells: &[(usize, T)]) -> Result<DeviceGraph<T>> {
+        match self.unavailable {}
+    }
+
+    /// Returns the best path for hamiltonian walk from a source vertex
+    pub fn walk<T: Real + OclPrm>(&self, _graph: &DeviceGraph<T>, _source: usize, _cancellation: &Cancellation, _progress: impl FnMut(usize, usize)) -> Result<Vec<PathResult<T>>> {
+        match self.unavailable {}
+    }
+
+    /// Walk a graph breadth first from a source vertex, counting the hops instead of adding the weights
+    pub fn breadth_first(&self, _graph: &DeviceGraph, _source: usize, _cancellation: &Cancellation) -> Result<Vec<PathResult>> {
+        match self.unavailable {}
+    }
+
+    /// Walk a graph from a source to a target searching from both ends at once
+    pub fn bidirectional(&self, _graph: &DeviceGraph, _source: usize, _target: usize, _cancellation: &Cancellation) -> Result<Vec<PathResult>> {
+        match self.unavailable {}
+    }
+
+    /// Rank the vertices of a graph with PageRank
+    pub fn pagerank(&self, _request: &PageRankRequest, _cancellation: &Cancellation) -> Result<PageRank> {
+        match self.unavailable {}
+    }
+
+    /// Find the maximum flow from a source to a sink
+    pub fn max_flow(&self, _matrix: &Matrix, _source: usize, _sink: usize, _cancellation: &Cancellation) -> Result<MaxFlow> {
+        match self.unavailable {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_runs() {
+        // Get the result
+        let runs = cell_runs(&[(1, 1.0), (2, 2.0), (3, 3.0), (7, 7.0), (9, 9.0), (10, 10.0)]);
+
+        // Check if the result is correct
+        assert_eq!(runs, vec![(1, vec![1.0, 2.0, 3.0]), (7, vec![7.0]), (9, vec![9.0, 10.0])]);
+        assert!(cell_runs::<f32>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_route() {
+        // Prepare the path
+        let path = vec![
+            PathResult(0, 0.0),
+            PathResult(2, 3.0),
+            PathResult(0, 2.0),
+            PathResult(1, 8.0),
+            PathResult(0, 0.0)
+        ];
+        let free_path = vec![PathResult(0, 0.0), PathResult(0, 0.0), PathResult(0, f32::MAX)];
+
+        // Check if the result is correct
+        assert_eq!(route(&path, 0, 3, false), Some(vec![0, 2, 1, 3]));
+        assert_eq!(route(&path, 0, 0, false), Some(vec![0]));
+        assert_eq!(route(&path, 0, 4, false), None);
+        assert_eq!(route(&free_path, 0, 1, true), Some(vec![0, 1]));
+        assert_eq!(route(&free_path, 0, 2, true), None);
+    }
+
+    #[test]
+    fn test_is_path_affected() {
+        // Prepare a chain of three vertices with a shortcut from the first to the last, walked from the first one
+        let mut before = Matrix::new(4, 4, vec![0.0; 16]);
+        for (source, target, weight) in [(0, 1, 1.0), (1, 2, 1.0), (0, 2, 5.0), (3, 0, 1.0)] {
+            before.set_edge(source, target, weight);
+        }
+        let path = vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(1, 2.0), PathResult(0, 0.0)];
+        let changed = |source: usize, target: usize, weight: f32| {
+            let mut after = before.clone();
+            after.set_edge(source, target, weight);
+            is_path_affected(&path, 0, &before, &after, &[(source, target)])
+        };
+
+        // Get the result
+        let affected = [changed(1, 2, 3.0), changed(0, 2, 1.5), changed(0, 2, 4.0), changed(3, 0, 9.0), changed(2, 3, 1.0)];
+
+        // Check if the result is correct
+        assert_eq!(affected, [true, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_join_searches() {
+        // Prepare the searches of a chain of four vertices and an isolated one, meeting on the second vertex
+        let unreached = || PathResult(0, f32::MAX);
+        let forward = vec![PathResult(0, 0.0), PathResult(0, 1.0), unreached(), unreached(), unreached()];
+        let backward = vec![unreached(), PathResult(2, 5.0), PathResult(3, 3.0), PathResult(3, 0.0), unreached()];
+
+        // Get the result
+        let path = join_searches(&forward, &backward, Some(1), 0, 3, false);
+        let unreachable = join_searches(&forward, &backward, None, 0, 3, true);
+
+        // Check if the result is correct
+        assert_eq!(path, vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(1, 3.0), PathResult(2, 6.0), PathResult(0, 0.0)]);
+        assert_eq!(route(&path, 0, 3, false), Some(vec![0, 1, 2, 3]));
+        assert_eq!(unreachable[3], PathResult(0, f32::MAX));
+    }
+
+    #[test]
+    #[cfg(feature = "opencl")]
+    fn test_get_sortest_path() {
+        // Prepare the matrix
+        let matrix = Matrix::new(6, 6,vec![
+            01.0, 04.0, 02.0, 00.0, 00.0, 00.0,
+            04.0, 01.0, 01.0, 05.0, 00.0, 00.0,
+            02.0, 01.0, 01.0, 08.0, 10.0, 00.0,
+            00.0, 05.0, 08.0, 01.0, 02.0, 06.0,
+            00.0, 00.0, 10.0, 02.0, 01.0, 02.0,
+            00.0, 00.0, 00.0, 06.0, 02.0, 01.0
+        ]);
+
+        // Prepare the expected result
+        let expected = vec![
+            PathResult(0, 0.0),
+            PathResult(2, 3.0),
+            PathResult(0, 2.0),
+            PathResult(1, 8.0),
+            PathResult(3, 10.0),
+            PathResult(4, 12.0)
+        ];
+
+        // Get the result
+        let result = SortestPath::new().get_sortest_path(matrix);
+
+        // Check if the result is correct
+        assert_eq!(result.unwrap(), expected);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::models::WeightUnit;
+
+/// A unit the costs of the responses can be returned in
+///
+/// # Fields
+///
+/// * `name` - The name of the unit, as written in the requests and the responses
+/// * `measure` - The unit of the weights it can be converted from
+/// * `size` - The size of the unit, in units of the weights
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayUnit {
+    pub name: &'static str,
+    pub measure: WeightUnit,
+    pub size: f32,
+}
+
+/// The units the costs can be returned in
+pub const DISPLAY_UNITS: [DisplayUnit; 6] = [
+    DisplayUnit { name: "m", measure: WeightUnit::Meters, size: 1.0 },
+    DisplayUnit { name: "km", measure: WeightUnit::Meters, size: 1000.0 },
+    DisplayUnit { name: "s", measure: WeightUnit::Seconds, size: 1.0 },
+    DisplayUnit { name: "min", measure: WeightUnit::Seconds, size: 60.0 },
+    DisplayUnit { name: "h", measure: WeightUnit::Seconds, size: 3600.0 },
+    DisplayUnit { name: "custom", measure: WeightUnit::Custom, size: 1.0 }
+];
+
+impl DisplayUnit {
+    /// Find a unit the costs can be returned in by its name
+    pub fn parse(name: &str) -> Option<DisplayUnit> {
+        DISPLAY_UNITS.iter().find(|unit| unit.name == name).copied()
+    }
+
+    /// Convert a cost from the unit of the weights, leaving the maximum of the type as the missing cost it is
+    pub fn convert(&self, cost: f32) -> f32 {
+        if cost == f32::MAX { cost } else { cost / self.size }
+    }
+}
+
+/// Choose the unit the costs of a response are returned in
+///
+/// Without a requested unit, the distances are returned in kilometers and
+/// the times in minutes, which are far easier to read than the meters and
+/// seconds of the weights. The costs of graphs that don't declare the unit
+/// of their weights are returned as they are.
+///
+/// # Arguments
+///
+/// * `declared` - The unit of the weights, if declared
+/// * `requested` - The unit requested by the client, if any
+///
+/// # Returns
+///
+/// * `Result<Option<DisplayUnit>, String>` - The unit of the costs, or the reason why they can't be converted to the requested one
+///
+pub fn display_unit(declared: Option<WeightUnit>, requested: Option<DisplayUnit>) -> Result<Option<DisplayUnit>, String> {
+    match (declared, requested) {
+        (None, None) => Ok(None),
+        (None, Some(requested)) => Err(format!("The graph doesn't declare the unit of its weights, so they can't be returned in {}", requested.name)),
+        (Some(declared), Some(requested)) if requested.measure != declared => {
+            Err(format!("The weights of the graph can't be returned in {}", requested.name))
+        },
+        (Some(_), Some(requested)) => Ok(Some(requested)),
+        (Some(WeightUnit::Meters), None) => Ok(DisplayUnit::parse("km")),
+        (Some(WeightUnit::Seconds), None) => Ok(DisplayUnit::parse("min")),
+        (Some(WeightUnit::Custom), None) => Ok(DisplayUnit::parse("custom"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_unit() {
+        // Prepare the units of the weights and the ones requested
+        let hours = DisplayUnit::parse("h");
+        let meters = DisplayUnit::parse("m");
+
+        // Get the result
+        let automatic = display_unit(Some(WeightUnit::Seconds), None).unwrap().unwrap();
+        let requested = display_unit(Some(WeightUnit::Seconds), hours).unwrap().unwrap();
+
+        // Check if the result is correct
+        assert_eq!(automatic.name, "min");
+        assert_eq!(automatic.convert(90.0), 1.5);
+        assert_eq!(requested.convert(f32::MAX), f32::MAX);
+        assert_eq!(display_unit(None, None), Ok(None));
+        assert_eq!(display_unit(Some(WeightUnit::Seconds), meters), Err("The weights of the graph can't be returned in m".to_string()));
+        assert_eq!(DisplayUnit::parse("furlong"), None);
+    }
+}
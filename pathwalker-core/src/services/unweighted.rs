@@ -0,0 +1,83 @@
+use crate::models::{Matrix, PathResult};
+
+/// Find the weight shared by every edge of a matrix, if they all have the same one
+///
+/// The graphs whose edges all cost the same are walked breadth first, as
+/// their shortest paths are the ones with the fewest hops. The loops are
+/// left out, as they never shorten a path, and the graphs without edges or
+/// whose edges cost nothing are left to the weighted walk.
+///
+/// # Arguments
+///
+/// * `matrix` - The validated matrix
+///
+/// # Returns
+///
+/// * `Option<f32>` - The weight of every edge, if they all have the same positive one
+///
+pub fn uniform_weight(matrix: &Matrix) -> Option<f32> {
+    let mut edges = (0..matrix.width)
+        .flat_map(|source| (0..matrix.width).map(move |target| (source, target)))
+        .filter(|(source, target)| source != target)
+        .filter_map(|(source, target)| matrix.edge(source, target));
+    let weight = edges.next()?;
+
+    (weight > 0.0 && edges.all(|other| other == weight)).then_some(weight)
+}
+
+/// Turn the hop counts of a breadth first walk into the distances of the edges
+///
+/// The distance of every level adds the weight to the one of the previous
+/// level, as the weighted kernels add the weights of the edges, so both
+/// walks return the same distances. The vertices that can't be reached keep
+/// their distance.
+///
+/// # Arguments
+///
+/// * `path` - The path of the breadth first walk, with the hops to every vertex
+/// * `weight` - The weight of every edge
+///
+/// # Returns
+///
+/// * `Vec<PathResult>` - The path with the distances of the edges
+///
+pub fn hop_distances(path: Vec<PathResult>, weight: f32) -> Vec<PathResult> {
+    let depth = path.iter().filter(|hop| hop.1 != f32::MAX).map(|hop| hop.1 as usize).max().unwrap_or_default();
+    let levels: Vec<f32> = (0..depth).scan(0.0, |distance, _| {
+        *distance += weight;
+        Some(*distance)
+    }).collect();
+
+    path.into_iter()
+        .map(|PathResult(predecessor, hops)| match hops as usize {
+            _ if hops == f32::MAX => PathResult(predecessor, hops),
+            0 => PathResult(predecessor, 0.0),
+            hops => PathResult(predecessor, levels[hops - 1])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_weight() {
+        // Prepare a chain of edges of the same weight, and the same chain with a heavier edge
+        let mut matrix = Matrix::new(3, 3, vec![0.0; 9]);
+        matrix.set_edge(0, 1, 0.1);
+        matrix.set_edge(1, 2, 0.1);
+        matrix.set_edge(2, 2, 5.0);
+        let mut heavier = matrix.clone();
+        heavier.set_edge(1, 2, 0.2);
+
+        // Get the result
+        let path = hop_distances(vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(1, 2.0), PathResult(0, f32::MAX)], 0.1);
+
+        // Check if the result is correct
+        assert_eq!(uniform_weight(&matrix), Some(0.1));
+        assert_eq!(uniform_weight(&heavier), None);
+        assert_eq!(uniform_weight(&Matrix::new(2, 2, vec![0.0; 4])), None);
+        assert_eq!(path, vec![PathResult(0, 0.0), PathResult(0, 0.1), PathResult(1, 0.1 + 0.1), PathResult(0, f32::MAX)]);
+    }
+}
@@ -0,0 +1,226 @@
+use log::warn;
+use serde::Serialize;
+
+/// The environment variable with the kernel variant to run, overriding the one chosen for the device
+pub const KERNEL_VARIANT_VARIABLE: &str = "PATHWALKER_KERNEL_VARIANT";
+
+/// The extension of the devices that can run double precision kernels
+pub const FP64_EXTENSION: &str = "cl_khr_fp64";
+
+/// The extension of the OpenCL 1.0 devices with atomic operations on global integers
+const GLOBAL_ATOMICS_EXTENSION: &str = "cl_khr_global_int32_base_atomics";
+
+/// The largest tile of distances loaded into the local memory
+const MAX_TILE_SIZE: usize = 256;
+
+/// The smallest tile worth the barriers of the tiled variant
+const MIN_TILE_SIZE: usize = 16;
+
+/// The bytes of the widest distance kept in a tile, the one of the double precision walks
+const TILE_ELEMENT_BYTES: u64 = 8;
+
+/// The variants of the kernels of the weighted walk
+///
+/// Every variant returns the same paths, so they only differ in how fast
+/// they run on each device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KernelVariant {
+    /// The kernels every device can run, with one iteration per vertex
+    Baseline,
+    /// Flags the iterations that improve a distance with an atomic operation,
+    /// stopping the walk at the first iteration that improves none
+    Atomics,
+    /// Loads the distances into the local memory by tiles, shared by the work group
+    Tiled,
+}
+
+impl KernelVariant {
+    /// Parse the name of a variant
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name, `baseline`, `atomics` or `tiled`
+    ///
+    /// # Returns
+    ///
+    /// * `Option<KernelVariant>` - The variant, if the name is known
+    ///
+    pub fn parse(name: &str) -> Option<KernelVariant> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "baseline" => Some(KernelVariant::Baseline),
+            "atomics" => Some(KernelVariant::Atomics),
+            "tiled" => Some(KernelVariant::Tiled),
+            _ => None
+        }
+    }
+
+    /// Get the name of the variant, as parsed
+    pub fn name(&self) -> &'static str {
+        match self {
+            KernelVariant::Baseline => "baseline",
+            KernelVariant::Atomics => "atomics",
+            KernelVariant::Tiled => "tiled"
+        }
+    }
+
+    /// Get the definitions compiled before the kernels to build the variant
+    ///
+    /// # Arguments
+    ///
+    /// * `capabilities` - The capabilities of the device
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The definitions, empty for the baseline
+    ///
+    pub fn header(&self, capabilities: &DeviceCapabilities) -> String {
+        match self {
+            KernelVariant::Baseline => String::new(),
+            KernelVariant::Atomics => "#define VARIANT_ATOMICS\n".to_string(),
+            KernelVariant::Tiled => format!("#define VARIANT_TILED\n#define TILE_SIZE {}\n", capabilities.tile_size())
+        }
+    }
+}
+
+/// What a device can run, as reported by its driver
+///
+/// # Fields
+///
+/// * `version` - The OpenCL version of the device, as major and minor
+/// * `fp64` - If the device has the `cl_khr_fp64` extension
+/// * `global_atomics` - If the device has atomic operations on global integers
+/// * `max_work_group_size` - The most work items of a work group
+/// * `local_memory` - The bytes of local memory of a work group
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceCapabilities {
+    pub version: (u16, u16),
+    pub fp64: bool,
+    pub global_atomics: bool,
+    pub max_work_group_size: usize,
+    pub local_memory: u64,
+}
+
+impl DeviceCapabilities {
+    /// Find the capabilities from what the driver reports
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - The OpenCL version of the device, as major and minor
+    /// * `extensions` - The extensions of the device, separated by spaces
+    /// * `max_work_group_size` - The most work items of a work group
+    /// * `local_memory` - The bytes of local memory of a work group
+    ///
+    /// # Returns
+    ///
+    /// * `DeviceCapabilities` - The capabilities, with the atomics being core since OpenCL 1.1
+    ///
+    pub fn new(version: (u16, u16), extensions: &str, max_work_group_size: usize, local_memory: u64) -> DeviceCapabilities {
+        let has = |name: &str| extensions.split_whitespace().any(|extension| extension == name);
+
+        DeviceCapabilities {
+            version,
+            fp64: has(FP64_EXTENSION),
+            global_atomics: version >= (1, 1) || has(GLOBAL_ATOMICS_EXTENSION),
+            max_work_group_size,
+            local_memory
+        }
+    }
+
+    /// Get the tile of distances loaded by every work group of the tiled variant
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The largest power of two that fits a work group and its local memory, up to 256
+    ///
+    pub fn tile_size(&self) -> usize {
+        let mut tile = MAX_TILE_SIZE;
+
+        while tile > 1 && (tile > self.max_work_group_size || tile as u64 * TILE_ELEMENT_BYTES > self.local_memory) {
+            tile /= 2;
+        }
+
+        tile
+    }
+
+    /// Check if the device can run a variant
+    pub fn supports(&self, variant: KernelVariant) -> bool {
+        match variant {
+            KernelVariant::Baseline => true,
+            KernelVariant::Atomics => self.global_atomics,
+            KernelVariant::Tiled => self.tile_size() >= MIN_TILE_SIZE
+        }
+    }
+
+    /// Get the variants the device can run, from the fastest
+    pub fn supported(&self) -> Vec<KernelVariant> {
+        [KernelVariant::Atomics, KernelVariant::Tiled, KernelVariant::Baseline].into_iter()
+            .filter(|variant| self.supports(*variant))
+            .collect()
+    }
+}
+
+/// Choose the variant of the kernels to run in a device
+///
+/// The variant asked for is run when the device supports it, and otherwise
+/// the fastest one the device supports. The atomic variant is the fastest,
+/// as it stops once the distances settle instead of after one iteration per
+/// vertex, then the tiled one, which reads the distances from the local
+/// memory.
+///
+/// # Arguments
+///
+/// * `capabilities` - The capabilities of the device
+/// * `requested` - The name of the variant asked for, if any
+///
+/// # Returns
+///
+/// * `KernelVariant` - The variant to run
+///
+pub fn select_variant(capabilities: &DeviceCapabilities, requested: Option<&str>) -> KernelVariant {
+    let best = capabilities.supported()[0];
+
+    match requested.map(|name| (name, KernelVariant::parse(name))) {
+        None => best,
+        Some((_, Some(variant))) if capabilities.supports(variant) => variant,
+        Some((_, Some(variant))) => {
+            warn!("The device can't run the {} kernels, running the {} ones instead", variant.name(), best.name());
+            best
+        },
+        Some((name, None)) => {
+            warn!("The kernel variant {} is not known, running the {} kernels instead", name, best.name());
+            best
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_variant() {
+        // Prepare a modern device, an OpenCL 1.0 device without atomics and one with a tiny local memory
+        let modern = DeviceCapabilities::new((3, 0), "cl_khr_fp64 cl_khr_icd", 1024, 49152);
+        let legacy = DeviceCapabilities::new((1, 0), "cl_khr_icd", 128, 16384);
+        let tiny = DeviceCapabilities::new((1, 0), "", 64, 64);
+
+        // Get the result
+        let variants = [modern.supported(), legacy.supported(), tiny.supported()];
+
+        // Check if the result is correct
+        assert_eq!(variants, [
+            vec![KernelVariant::Atomics, KernelVariant::Tiled, KernelVariant::Baseline],
+            vec![KernelVariant::Tiled, KernelVariant::Baseline],
+            vec![KernelVariant::Baseline]
+        ]);
+        assert!(modern.fp64 && !legacy.fp64);
+        assert_eq!((modern.tile_size(), legacy.tile_size(), tiny.tile_size()), (256, 128, 8));
+        assert_eq!(select_variant(&modern, None), KernelVariant::Atomics);
+        assert_eq!(select_variant(&modern, Some("Tiled")), KernelVariant::Tiled);
+        assert_eq!(select_variant(&legacy, Some("atomics")), KernelVariant::Tiled);
+        assert_eq!(select_variant(&tiny, Some("warp")), KernelVariant::Baseline);
+        assert_eq!(KernelVariant::Tiled.header(&legacy), "#define VARIANT_TILED\n#define TILE_SIZE 128\n");
+    }
+}
@@ -0,0 +1,140 @@
+use crate::models::{Matrix, ValidationRules, WalkRequest};
+use crate::services::generator::SplitMix64;
+use crate::solver::validate_static_matrix;
+
+/// The maximum number of vertices of a corpus of walks
+pub const MAX_WALK_STEPS: usize = 4_194_304;
+
+/// Validate a walk request
+///
+/// # Arguments
+///
+/// * `request` - The walk request
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the request isn't valid, if any
+///
+pub fn validate_walks(request: &WalkRequest) -> Option<&'static str> {
+    let rules = ValidationRules { reject_negative: true, warn_asymmetric: false };
+    let steps = request.matrix.width.saturating_mul(request.walks_per_vertex).saturating_mul(request.length);
+
+    if let Some(message) = validate_static_matrix(&request.matrix) {
+        Some(message)
+    } else if !request.matrix.validate(rules).is_valid() {
+        Some("The weights must be finite non-negative numbers")
+    } else if request.walks_per_vertex == 0 || request.length == 0 {
+        Some("The walks per vertex and their length must be positive")
+    } else if steps > MAX_WALK_STEPS {
+        Some("The walks can't have more than 4194304 vertices in total")
+    } else if !request.p.is_finite() || !request.q.is_finite() || request.p <= 0.0 || request.q <= 0.0 {
+        Some("The return and in-out parameters must be positive numbers")
+    } else {
+        None
+    }
+}
+
+/// Get the weight of the edge going from `source` to `target`, if there is one
+fn edge(matrix: &Matrix, source: usize, target: usize) -> Option<f32> {
+    matrix.edge(source, target).filter(|_| source != target)
+}
+
+/// Generate a corpus of node2vec random walks over a graph
+///
+/// Every vertex starts `walks_per_vertex` walks of up to `length` vertices,
+/// which stop early at the vertices without outgoing edges. The next vertex
+/// is drawn with a probability proportional to the weight of its edge,
+/// divided by `p` when it goes back to the previous vertex, and by `q` when
+/// it goes further away from it, so a low `p` keeps the walks local like a
+/// breadth first search and a low `q` pushes them outwards like a depth first
+/// search. The same seed always gives the same corpus.
+///
+/// # Arguments
+///
+/// * `request` - The validated walk request
+///
+/// # Returns
+///
+/// * `Vec<Vec<usize>>` - The vertices of every walk, grouped by round and ordered by their start
+///
+pub fn random_walks(request: &WalkRequest) -> Vec<Vec<usize>> {
+    let matrix = &request.matrix;
+    let mut random = SplitMix64(request.seed.unwrap_or_default());
+    let mut walks = Vec::with_capacity(matrix.width * request.walks_per_vertex);
+
+    for _ in 0..request.walks_per_vertex {
+        for start in 0..matrix.width {
+            let mut walk = vec![start];
+
+            while walk.len() < request.length {
+                let current = walk[walk.len() - 1];
+                let previous = walk.len().checked_sub(2).map(|index| walk[index]);
+
+                // Bias the weight of every edge leaving the vertex with the previous one
+                let candidates: Vec<(usize, f64)> = (0..matrix.width)
+                    .filter_map(|next| edge(matrix, current, next).map(|weight| (next, weight as f64)))
+                    .map(|(next, weight)| match previous {
+                        Some(previous) if next == previous => (next, weight / request.p),
+                        Some(previous) if edge(matrix, previous, next).is_some() || edge(matrix, next, previous).is_some() => (next, weight),
+                        Some(_) => (next, weight / request.q),
+                        None => (next, weight)
+                    })
+                    .collect();
+                let total: f64 = candidates.iter().map(|(_, weight)| weight).sum();
+
+                if candidates.is_empty() || total <= 0.0 {
+                    break;
+                }
+
+                // Draw the next vertex
+                let mut draw = random.next_f64() * total;
+                let mut next = candidates[candidates.len() - 1].0;
+
+                for (candidate, weight) in &candidates {
+                    if draw < *weight {
+                        next = *candidate;
+                        break;
+                    }
+
+                    draw -= weight;
+                }
+
+                walk.push(next);
+            }
+
+            walks.push(walk);
+        }
+    }
+
+    walks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_walks() {
+        // Prepare a path of three vertices, where the middle one can go back or forward, and a dead end
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        matrix.set_edge(0, 1, 1.0);
+        matrix.set_edge(1, 0, 1.0);
+        matrix.set_edge(1, 2, 1.0);
+        matrix.set_edge(2, 1, 1.0);
+        let request = WalkRequest { matrix, walks_per_vertex: 3, length: 5, p: 1.0, q: 1.0, seed: Some(7) };
+        let returning = WalkRequest { p: 1e-6, ..request.clone() };
+
+        // Get the result
+        let walks = random_walks(&request);
+        let returns = random_walks(&returning);
+
+        // Check if the result is correct
+        assert_eq!(validate_walks(&request), None);
+        assert_eq!(walks.len(), 12);
+        assert_eq!(walks, random_walks(&request));
+        assert!(walks.iter().filter(|walk| walk[0] < 3).all(|walk| walk.len() == 5 && walk.windows(2).all(|hop| hop[0].abs_diff(hop[1]) == 1)));
+        assert_eq!(walks[3], vec![3]);
+        assert_eq!(returns[0], vec![0, 1, 0, 1, 0]);
+        assert_eq!(validate_walks(&WalkRequest { q: 0.0, ..request }), Some("The return and in-out parameters must be positive numbers"));
+    }
+}
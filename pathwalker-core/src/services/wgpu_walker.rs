@@ -0,0 +1,321 @@
+use log::{info, trace, warn};
+use sha2::{Digest, Sha256};
+use tracing::debug_span;
+use wgpu::util::DeviceExt;
+
+use crate::models::{Matrix, PathResult};
+use crate::services::backend::{Backend, BackendKind};
+use crate::services::cancellation::Cancellation;
+use crate::services::metrics::{add_device_memory, QueuedWalk};
+use crate::services::shutdown::KERNEL_EXECUTIONS;
+
+/// The source of the walk shaders
+const WALK_SHADER: &str = include_str!("../kernels/walk.wgsl");
+
+/// The threads of every work group of the shaders
+const WORKGROUP_SIZE: usize = 64;
+
+/// The iterations submitted between the checks of the cancellation
+const ITERATIONS_PER_SYNC: usize = 32;
+
+/// The storage buffers bound to the shaders, after the parameters
+const STORAGE_BINDINGS: [(u32, bool); 6] = [(1, true), (2, false), (3, false), (4, false), (5, false), (6, false)];
+
+/// The walker of the wgpu backend
+///
+/// Runs the weighted walk of the OpenCL kernels as compute shaders, in the
+/// first adapter wgpu finds over Vulkan, Metal or DX12, so the graphs can
+/// be walked in the devices whose vendors don't ship OpenCL drivers. Only
+/// the single precision walks are ported, and the other algorithms stay
+/// in the OpenCL walker.
+///
+/// # Fields
+///
+/// * `device` - The logical device
+/// * `queue` - The queue of the device
+/// * `layout` - The layout of the buffers bound to the shaders
+/// * `initialize` - The pipeline initializing the buffers
+/// * `iterate` - The pipeline relaxing the edges of the vertices not visited
+/// * `merge` - The pipeline merging the relaxed distances
+/// * `device_name` - The name of the adapter
+/// * `backend_version` - The versions of the server, the shaders and the adapter
+///
+pub struct WgpuWalker {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    initialize: wgpu::ComputePipeline,
+    iterate: wgpu::ComputePipeline,
+    merge: wgpu::ComputePipeline,
+    device_name: String,
+    backend_version: String,
+}
+
+impl WgpuWalker {
+    /// Open the first adapter and compile the walk shaders for it
+    ///
+    /// # Returns
+    ///
+    /// * `Result<WgpuWalker, String>` - The walker, or why the adapter can't be set up
+    ///
+    pub fn new() -> Result<WgpuWalker, String> {
+        // Find the adapter, preferring the discrete devices
+        trace!("Initializing wgpu components before operations...");
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            ..Default::default()
+        })).map_err(|err| format!("No wgpu adapter can be found: {}", err))?;
+
+        let adapter_info = adapter.get_info();
+        info!("Using wgpu adapter: {} ({:?})", adapter_info.name, adapter_info.backend);
+
+        // Open the device with the limits of the adapter, so the largest matrices fit in a buffer
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("pathwalker"),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        })).map_err(|err| format!("The wgpu device can't be opened: {}", err))?;
+
+        // Compile the shaders with a layout shared by the three entry points
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("walk"),
+            source: wgpu::ShaderSource::Wgsl(WALK_SHADER.into())
+        });
+
+        let entries = std::iter::once(wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+            count: None
+        }).chain(STORAGE_BINDINGS.iter().map(|&(binding, read_only)| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only }, has_dynamic_offset: false, min_binding_size: None },
+            count: None
+        })).collect::<Vec<_>>();
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { label: Some("walk"), entries: &entries });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("walk"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0
+        });
+
+        let pipeline = |entry_point: &str| device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(entry_point),
+            layout: Some(&pipeline_layout),
+            module: &module,
+            entry_point: Some(entry_point),
+            compilation_options: Default::default(),
+            cache: None
+        });
+
+        let initialize = pipeline("initialize_algorithm_buffers");
+        let iterate = pipeline("shortest_path_algorithm");
+        let merge = pipeline("merge_sortest_path");
+
+        // Name the versions of everything that can change the results
+        let backend_version = format!(
+            "path_walker {}; shaders {}; wgpu {:?}; {} (driver {} {})",
+            env!("CARGO_PKG_VERSION"),
+            &hex::encode(Sha256::digest(WALK_SHADER.as_bytes()))[..16],
+            adapter_info.backend,
+            adapter_info.name,
+            adapter_info.driver,
+            adapter_info.driver_info
+        );
+
+        Ok(WgpuWalker { device, queue, layout, initialize, iterate, merge, device_name: adapter_info.name, backend_version })
+    }
+
+    /// Wait for the work submitted to the device
+    fn wait(&self) -> Result<(), String> {
+        self.device.poll(wgpu::PollType::wait_indefinitely())
+            .map(|_| ())
+            .map_err(|err| format!("The wgpu device has failed: {}", err))
+    }
+
+    /// Submit a pass running some pipelines in order over every vertex
+    ///
+    /// # Arguments
+    ///
+    /// * `bind_group` - The buffers of the walk
+    /// * `pipelines` - The pipelines to run
+    /// * `workgroups` - The work groups covering every vertex
+    ///
+    fn dispatch(&self, bind_group: &wgpu::BindGroup, pipelines: &[&wgpu::ComputePipeline], workgroups: u32) {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("walk") });
+
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("walk"), timestamp_writes: None });
+            pass.set_bind_group(0, bind_group, &[]);
+
+            for pipeline in pipelines {
+                pass.set_pipeline(pipeline);
+                pass.dispatch_workgroups(workgroups, 1, 1);
+            }
+        }
+
+        self.queue.submit([encoder.finish()]);
+    }
+
+    /// Copy a buffer of the device to the host
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer to copy
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>, String>` - The bytes of the buffer, or why they can't be read
+    ///
+    fn read(&self, buffer: &wgpu::Buffer) -> Result<Vec<u8>, String> {
+        // Copy the buffer into a mappable one
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: buffer.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("readback") });
+        encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+        self.queue.submit([encoder.finish()]);
+
+        // Map it once the copy finishes
+        let (sender, receiver) = std::sync::mpsc::channel();
+        staging.slice(..).map_async(wgpu::MapMode::Read, move |mapped| {
+            let _ = sender.send(mapped);
+        });
+
+        self.wait()?;
+        receiver.recv()
+            .map_err(|err| err.to_string())?
+            .map_err(|err| format!("The wgpu buffer can't be read: {}", err))?;
+
+        let bytes = staging.slice(..).get_mapped_range()
+            .map_err(|err| format!("The wgpu buffer can't be read: {}", err))?
+            .to_vec();
+        staging.unmap();
+
+        Ok(bytes)
+    }
+}
+
+impl Backend for WgpuWalker {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Wgpu
+    }
+
+    fn device_name(&self) -> String {
+        self.device_name.clone()
+    }
+
+    fn backend_version(&self) -> &str {
+        &self.backend_version
+    }
+
+    fn probe(&self) -> Result<(), String> {
+        // Walk a chain of two vertices, which runs every shader
+        let path = self.walk_matrix(&Matrix::new(2, 2, vec![0.0, 0.0, 1.0, 0.0]), 0, &Cancellation::default())?;
+
+        if path == [PathResult(0, 0.0), PathResult(0, 1.0)] {
+            Ok(())
+        } else {
+            Err(format!("The walk shaders have returned {:?}", path))
+        }
+    }
+
+    fn walk_matrix(&self, matrix: &Matrix, source: usize, cancellation: &Cancellation) -> Result<Vec<PathResult>, String> {
+        // Print the initialization
+        let width = matrix.width;
+        let _span = debug_span!("wgpu_walk", width, source).entered();
+        trace!("Initializing buffers for the shaders...");
+
+        // Count the walk in the queue depth and keep the shutdown waiting until it finishes
+        let _queued = QueuedWalk::start(width);
+        let _execution = KERNEL_EXECUTIONS.start();
+
+        // Upload the matrix and create the buffers of the walk, which the init shader fills
+        let storage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC;
+        let scratch = |label: &str| self.device.create_buffer(&wgpu::BufferDescriptor { label: Some(label), size: (width * 4) as u64, usage: storage, mapped_at_creation: false });
+
+        let params = [source as u32, matrix.zero_edges as u32, width as u32, 0].iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>();
+        let params_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some("params"), contents: &params, usage: wgpu::BufferUsages::UNIFORM });
+        let matrix_bytes = matrix.directed_data().iter().flat_map(|weight| weight.to_le_bytes()).collect::<Vec<u8>>();
+        let matrix_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some("matrix"), contents: &matrix_bytes, usage: wgpu::BufferUsages::STORAGE });
+        let buffers = [&matrix_buffer, &scratch("result"), &scratch("distance"), &scratch("visited"), &scratch("vertex"), &scratch("vertex_temp")];
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("walk"),
+            layout: &self.layout,
+            entries: &std::iter::once(wgpu::BindGroupEntry { binding: 0, resource: params_buffer.as_entire_binding() })
+                .chain(STORAGE_BINDINGS.iter().zip(buffers).map(|(&(binding, _), buffer)| wgpu::BindGroupEntry { binding, resource: buffer.as_entire_binding() }))
+                .collect::<Vec<_>>()
+        });
+
+        // Account the buffers of the walk
+        let bytes = (matrix_bytes.len() + 5 * width * 4) as i64;
+        add_device_memory(bytes);
+
+        let path = (|| {
+            let workgroups = width.div_ceil(WORKGROUP_SIZE) as u32;
+            self.dispatch(&bind_group, &[&self.initialize], workgroups);
+
+            // Run the algorithm, waiting for the device only once per batch of iterations, up to the hops of the paths
+            let (mut iteration, rounds) = (0, matrix.walk_rounds());
+
+            while iteration < rounds {
+                // Give up before submitting more work when the walk has been cancelled
+                if let Err(message) = cancellation.check() {
+                    warn!("Aborting the walk at iteration {} of {}: {}", iteration, rounds, message);
+                    return Err(message);
+                }
+
+                let batch = ITERATIONS_PER_SYNC.min(rounds - iteration);
+                let pipelines = [&self.iterate, &self.merge].repeat(batch);
+                self.dispatch(&bind_group, &pipelines, workgroups);
+                self.wait()?;
+                iteration += batch;
+            }
+
+            // Copy the predecessors and the distances to the host once the walk is complete
+            let words = |bytes: Vec<u8>| bytes.chunks_exact(4).map(|word| [word[0], word[1], word[2], word[3]]).collect::<Vec<[u8; 4]>>();
+            let vertex = words(self.read(buffers[4])?);
+            let distance = words(self.read(buffers[2])?);
+
+            Ok(vertex.into_iter().zip(distance)
+                .map(|(vertex, distance)| PathResult(i32::from_le_bytes(vertex), f32::from_le_bytes(distance)))
+                .collect::<Vec<PathResult>>())
+        })();
+
+        // Release the buffers of the walk from the accounting
+        add_device_memory(-bytes);
+
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wgpu_walk() {
+        // Prepare a chain of four vertices with a shortcut from the first to the last
+        let walker = WgpuWalker::new().unwrap();
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        for (source, target, weight) in [(0, 1, 1.0), (1, 2, 1.0), (2, 3, 1.0), (0, 3, 5.0)] {
+            matrix.data[target * 4 + source] = weight;
+        }
+
+        // Get the result
+        let path = walker.walk_matrix(&matrix, 0, &Cancellation::default()).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(path, vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(1, 2.0), PathResult(2, 3.0)]);
+        assert!(walker.probe().is_ok());
+    }
+}
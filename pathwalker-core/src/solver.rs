@@ -0,0 +1,366 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "opencl")]
+use ocl::{Device, Platform};
+
+use crate::models::{Matrix, PathResult, Precision, Real, ValidationRules};
+use crate::services::cancellation::Cancellation;
+use crate::services::memory::{check_fit, walk_bytes};
+use crate::services::opencl::OclPrm;
+use crate::services::sortest_path::SortestPath;
+use crate::utils::MAX_ROUND_DECIMALS;
+
+/// The maximum number of vertices of a graph walked in the device by default
+pub const MAX_VERTICES: usize = 128;
+
+/// The maximum number of vertices of a graph walked in the device
+static MAX_VERTICES_LIMIT: AtomicUsize = AtomicUsize::new(MAX_VERTICES);
+
+/// Set the maximum number of vertices of a graph walked in the device
+///
+/// # Arguments
+///
+/// * `vertices` - The maximum number of vertices
+///
+pub fn set_max_vertices(vertices: usize) {
+    MAX_VERTICES_LIMIT.store(vertices, Ordering::Relaxed);
+}
+
+/// Get the maximum number of vertices of a graph walked in the device
+pub fn max_vertices() -> usize {
+    MAX_VERTICES_LIMIT.load(Ordering::Relaxed)
+}
+
+/// A graph, as the square matrix of the weights of its edges
+///
+/// The row of a vertex holds the weights of the edges reaching it, and a
+/// weight of `0` or the maximum of the type means there is no edge. The
+/// weights are `f32` unless the graph is walked in double precision with
+/// `f64` weights, or exactly with `u32` weights.
+pub type Graph<T = f32> = Matrix<T>;
+
+/// The options of a walk
+///
+/// # Fields
+///
+/// * `source` - The vertex to start the walk from
+/// * `round` - The decimals to round the distances to, overriding the ones of the graph
+/// * `cancellation` - Aborts the walk between iterations, unlimited by default
+///
+#[derive(Debug, Clone, Default)]
+pub struct SolveOptions {
+    pub source: usize,
+    pub round: Option<u32>,
+    pub cancellation: Cancellation,
+}
+
+/// Validate a graph before walking it
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to validate
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the matrix is not valid, if any
+///
+pub fn validate_matrix<T: Real>(matrix: &Matrix<T>) -> Option<&'static str> {
+    if matrix.data.is_empty() {
+        // The matrix is empty
+        Some("The matrix is empty")
+    } else if matrix.height != matrix.width {
+        // The matrix is not square
+        Some("The matrix is not square")
+    } else if matrix.height > max_vertices() {
+        // The matrix is too big
+        Some("The matrix is too big")
+    } else if matrix.width * matrix.height != matrix.data.len() {
+        // The matrix size and dimensions are not the same
+        Some("The matrix size and dimensions are not the same")
+    } else if matrix.round.is_some_and(|decimals| decimals > MAX_ROUND_DECIMALS) {
+        // The rounding can't be more precise than the values
+        Some("The rounding decimals are out of range")
+    } else if matrix.forbidden_nodes.iter().chain(matrix.forbidden_edges.iter().flatten()).any(|vertex| *vertex >= matrix.width) {
+        // The constraints name vertices the matrix doesn't have
+        Some("The forbidden nodes and edges must be vertices of the matrix")
+    } else if matrix.max_hops == Some(0) {
+        // The walks must be allowed some edge
+        Some("The maximum hops must be greater than zero")
+    } else if matrix.time_buckets.iter().any(|bucket| bucket.len() != matrix.data.len()) {
+        // The buckets are read the same way as the weights
+        Some("The time buckets must have as many weights as the matrix")
+    } else if matrix.is_time_dependent() && T::PRECISION != Precision::Single {
+        // The time dependent weights are only walked in the CPU
+        Some("The time dependent weights can only be walked in single precision")
+    } else if matrix.deterministic && (matrix.max_hops.is_some() || matrix.is_time_dependent()) {
+        // The shortest predecessors are only ordered once the distances have settled, with the same weights
+        Some("The deterministic walks can't bound the hops nor change the weights with the time")
+    } else {
+        // The matrix is validated
+        None
+    }
+}
+
+/// Validate a graph walked with the same weights from start to end
+///
+/// Only the walks of the sortest path endpoints read the weight of every
+/// edge at the time it's reached, so the rest of the algorithms, and the
+/// graphs stored in the device, refuse the time dependent weights instead
+/// of walking the ones of the departure.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to validate
+///
+/// # Returns
+///
+/// * `Option<&str>` - The reason why the matrix is not valid, if any
+///
+pub fn validate_static_matrix<T: Real>(matrix: &Matrix<T>) -> Option<&'static str> {
+    validate_matrix(matrix).or_else(|| matrix.is_time_dependent().then_some("The time dependent weights can only be walked by the sortest path endpoints"))
+}
+
+/// The shortest paths of a graph, walked in an OpenCL device
+///
+/// The entry point of the library: the device is opened once, and every
+/// call to `solve` uploads a graph and walks it from a source vertex.
+///
+/// # Fields
+///
+/// * `walker` - The walker running the kernels in the device
+///
+pub struct ShortestPath {
+    walker: SortestPath,
+}
+
+impl ShortestPath {
+    /// Open the first device of the first platform
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ShortestPath, String>` - The solver, or the diagnostics of the drivers when there is no device
+    ///
+    pub fn new() -> Result<ShortestPath, String> {
+        SortestPath::try_new().map(|walker| ShortestPath { walker })
+    }
+
+    /// Open a specific device
+    ///
+    /// # Arguments
+    ///
+    /// * `platform` - The platform of the device
+    /// * `device` - The device to run the kernels on
+    ///
+    /// # Returns
+    ///
+    /// * `ShortestPath` - The solver
+    ///
+    #[cfg(feature = "opencl")]
+    pub fn with_device(platform: Platform, device: Device) -> ShortestPath {
+        ShortestPath { walker: SortestPath::with_device(platform, device) }
+    }
+
+    /// Get the versions of the library, the kernels, the platform and the device
+    pub fn backend_version(&self) -> &str {
+        self.walker.backend_version()
+    }
+
+    /// Check if the device can walk graphs with `f64` weights
+    pub fn supports_double(&self) -> bool {
+        self.walker.supports_double()
+    }
+
+    /// Walk a graph from a source vertex
+    ///
+    /// Graphs with `f64` weights are walked in double precision, which fails
+    /// when the device lacks the `cl_khr_fp64` extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The graph to walk
+    /// * `options` - The source, the rounding and the cancellation of the walk
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<PathResult<T>>, String>` - The predecessor and the distance of every vertex, or why the walk failed
+    ///
+    pub fn solve<T: Real + OclPrm>(&self, graph: &Graph<T>, options: SolveOptions) -> Result<Vec<PathResult<T>>, String> {
+        // Reject the graphs the kernels can't walk
+        if let Some(message) = validate_matrix(graph) {
+            return Err(message.to_string());
+        }
+
+        if let Some(error) = graph.validate(ValidationRules::default()).errors.first() {
+            return Err(error.to_string());
+        }
+
+        if options.source >= graph.width {
+            return Err("The source vertex is out of the graph".to_string());
+        }
+
+        if let Err(shortfall) = check_fit(walk_bytes::<T>(graph.width), self.walker.global_memory()) {
+            return Err(format!("The matrix needs {} bytes of device memory, but only {} are available", shortfall.required, shortfall.available));
+        }
+
+        // Walk the graph in the device
+        let device_graph = self.walker.upload(graph).map_err(|err| err.to_string())?;
+        let path = self.walker.walk(&device_graph, options.source, &options.cancellation, |_, _| {})
+            .map_err(|err| err.to_string())?;
+
+        // Round the distances as asked
+        let round = options.round.or(graph.round);
+        Ok(path.iter().map(|hop| hop.round(round)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_matrix() {
+        // Prepare a valid, a rectangular and an oversized graph
+        let valid = Graph::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]);
+        let rectangular = Graph::new(2, 1, vec![0.0, 1.0]);
+        let oversized = Graph::new(MAX_VERTICES + 1, MAX_VERTICES + 1, vec![0.0; (MAX_VERTICES + 1) * (MAX_VERTICES + 1)]);
+
+        // Get the result
+        let results = [validate_matrix(&valid), validate_matrix(&rectangular), validate_matrix(&oversized)];
+
+        // Check if the result is correct
+        assert_eq!(results, [None, Some("The matrix is not square"), Some("The matrix is too big")]);
+    }
+
+    #[test]
+    fn test_validate_cells() {
+        // Prepare a directed graph with a missing, a negative, an infinite and a NaN weight
+        let graph = Graph::new(3, 3, vec![0.0, 1.0, f32::MAX, 2.0, 0.0, -1.0, f32::INFINITY, f32::NAN, 0.0]);
+        let rules = ValidationRules { reject_negative: true, warn_asymmetric: true };
+
+        // Get the result
+        let lenient = graph.validate(ValidationRules::default());
+        let strict = graph.validate(rules);
+
+        // Check if the result is correct
+        assert_eq!(lenient.errors.iter().map(|error| (error.row, error.column)).collect::<Vec<_>>(), vec![(2, 0), (2, 1)]);
+        assert_eq!(lenient.errors[1].to_string(), "The weight is not a number at row 2 and column 1");
+        assert!(lenient.warnings.is_empty());
+        assert_eq!(strict.errors.len(), 3);
+        assert_eq!(strict.warnings.iter().map(|warning| (warning.row, warning.column)).collect::<Vec<_>>(), vec![(0, 1), (0, 2)]);
+        assert!(Graph::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]).validate(rules).is_valid());
+    }
+
+    #[test]
+    fn test_zero_weight_edges() {
+        // Prepare the same cells read with and without zero weight edges
+        let legacy = Graph::new(2, 2, vec![0.0, f32::MAX, 0.0, 3.0]);
+        let free = Graph { zero_edges: true, ..legacy.clone() };
+
+        // Get the result
+        let legacy_edges = [legacy.edge(0, 1), legacy.edge(1, 0), legacy.edge(1, 1)];
+        let free_edges = [free.edge(0, 1), free.edge(1, 0), free.edge(1, 1)];
+
+        // Check if the result is correct
+        assert_eq!(legacy_edges, [None, None, Some(3.0)]);
+        assert_eq!(free_edges, [Some(0.0), None, Some(3.0)]);
+    }
+
+    #[test]
+    fn test_undirected_edges() {
+        // Prepare an undirected triangle with only its upper cells filled, but one edge in both cells
+        let mut graph = Graph { directed: false, ..Graph::new(3, 3, vec![0.0, 1.0, 4.0, 0.0, 0.0, 2.0, 3.0, 0.0, 0.0]) };
+        graph.set_edge(1, 2, 5.0);
+
+        // Get the result
+        let edges = [graph.edge(0, 1), graph.edge(1, 0), graph.edge(2, 0), graph.edge(0, 2), graph.edge(2, 1)];
+
+        // Check if the result is correct
+        assert_eq!(edges, [Some(1.0), Some(1.0), Some(3.0), Some(3.0), Some(5.0)]);
+        assert_eq!(graph.directed_data().as_ref(), [0.0, 1.0, 3.0, 1.0, 0.0, 5.0, 3.0, 5.0, 0.0]);
+        assert_eq!(Graph::new(1, 1, vec![2.0]).directed_data().as_ref(), [2.0]);
+    }
+
+    #[test]
+    fn test_forbidden_edges() {
+        // Prepare an undirected triangle avoiding its last vertex, and one avoiding the edge between the first two
+        let triangle = Graph { directed: false, ..Graph::new(3, 3, vec![0.0, 1.0, 4.0, 1.0, 0.0, 2.0, 4.0, 2.0, 0.0]) };
+        let closed_vertex = Graph { forbidden_nodes: vec![2], ..triangle.clone() };
+        let closed_edge = Graph { forbidden_edges: vec![[1, 0]], ..triangle.clone() };
+
+        // Get the result
+        let edges = [closed_edge.edge(0, 1), closed_edge.edge(1, 0), closed_edge.edge(0, 2)];
+        let outside = Graph { forbidden_edges: vec![[0, 3]], ..triangle.clone() };
+        let no_hops = Graph { max_hops: Some(0), ..triangle.clone() };
+
+        // Check if the result is correct
+        assert_eq!(closed_vertex.directed_data().as_ref(), [0.0, 1.0, f32::MAX, 1.0, 0.0, f32::MAX, f32::MAX, f32::MAX, f32::MAX]);
+        assert_eq!(edges, [None, None, Some(4.0)]);
+        assert_eq!(Graph { max_hops: Some(5), ..triangle }.walk_rounds(), 3);
+        assert_eq!(validate_matrix(&outside), Some("The forbidden nodes and edges must be vertices of the matrix"));
+        assert_eq!(validate_matrix(&no_hops), Some("The maximum hops must be greater than zero"));
+    }
+
+    #[test]
+    fn test_mix_layers() {
+        // Prepare the distance and the time of the edges of two vertices, where the time of one edge is missing,
+        // and the distance and the toll of the same edges, where one of them has no toll
+        let layered = Graph { data_layers: vec![vec![0.0, 2.0, 4.0, 0.0], vec![0.0, 10.0, f32::MAX, 0.0]], coefficients: vec![1.0, 0.5], ..Graph::new(2, 2, Vec::new()) };
+        let mut mixed = layered.clone();
+        let mut both = Graph { data: vec![0.0; 4], ..layered.clone() };
+        let mut short = Graph { data_layers: vec![vec![1.0; 3]], coefficients: vec![1.0], ..layered.clone() };
+
+        // Get the result
+        let result = mixed.mix_layers();
+        let mut tolled = Graph { data_layers: vec![vec![0.0, 2.0, 3.0, 0.0], vec![0.0, 6.0, 0.0, 0.0]], ..layered.clone() };
+        tolled.mix_layers().unwrap();
+
+        // Check if the result is correct
+        assert_eq!(result, Ok(()));
+        assert_eq!(mixed.data, vec![f32::MAX, 7.0, f32::MAX, f32::MAX]);
+        assert!(mixed.data_layers.is_empty() && mixed.coefficients.is_empty());
+        assert_eq!(both.mix_layers(), Err("The matrix can't have both data and data layers".to_string()));
+        assert_eq!(short.mix_layers(), Err("The data layer 0 has 3 weights instead of 4".to_string()));
+        assert!(Graph { coefficients: vec![1.0], ..layered }.mix_layers().is_err());
+        assert_eq!(tolled.data, vec![f32::MAX, 5.0, 3.0, f32::MAX]);
+    }
+
+    #[test]
+    fn test_time_buckets() {
+        // Prepare the hourly weights of two vertices over two hours, departing in the second one
+        let timed: Graph = Graph { time_buckets: vec![vec![0.0, 1.0, 1.0, 0.0], vec![0.0, 3.0, 3.0, 0.0]], departure_time: Some(5400.0), ..Graph::new(2, 2, Vec::new()) };
+        let mut departed = timed.clone();
+        let mut short = Graph { time_buckets: vec![vec![1.0; 3]], ..timed.clone() };
+        let double = Matrix { time_buckets: vec![vec![0.0f64, 1.0, 1.0, 0.0]], ..Matrix::new(2, 2, vec![0.0f64, 1.0, 1.0, 0.0]) };
+
+        // Get the result
+        let result = departed.fill_departure_weights();
+
+        // Check if the result is correct
+        assert_eq!(result, Ok(()));
+        assert_eq!(departed.data, vec![0.0, 3.0, 3.0, 0.0]);
+        assert_eq!(departed.bucket_at(7200.0), 0);
+        assert_eq!(validate_matrix(&departed), None);
+        assert_eq!(validate_static_matrix(&departed), Some("The time dependent weights can only be walked by the sortest path endpoints"));
+        assert_eq!(short.fill_departure_weights(), Err("The time bucket 0 has 3 weights instead of 4".to_string()));
+        assert_eq!(validate_matrix(&double), Some("The time dependent weights can only be walked in single precision"));
+    }
+
+    #[test]
+    fn test_order_predecessors() {
+        // Prepare a diamond where the last vertex is reached through the second and the third at the same distance
+        let mut diamond = Graph { deterministic: true, ..Graph::new(4, 4, vec![0.0; 16]) };
+        for (source, target, weight) in [(0, 2, 1.0), (0, 1, 1.0), (2, 3, 2.0), (1, 3, 2.0)] {
+            diamond.set_edge(source, target, weight);
+        }
+        let mut path = vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(0, 1.0), PathResult(2, 3.0)];
+
+        // Get the result
+        diamond.order_predecessors(&mut path, 0);
+        let bounded = Graph { max_hops: Some(2), ..diamond.clone() };
+
+        // Check if the result is correct
+        assert_eq!(path, vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(0, 1.0), PathResult(1, 3.0)]);
+        assert_eq!(validate_matrix(&diamond), None);
+        assert_eq!(validate_matrix(&bounded), Some("The deterministic walks can't bound the hops nor change the weights with the time"));
+    }
+}
@@ -0,0 +1,55 @@
+/// The maximum number of decimals a response can be rounded to
+pub const MAX_ROUND_DECIMALS: u32 = 7;
+
+/// Round a value to a number of decimals
+///
+/// Responses are rounded with this function, so the outputs of different
+/// backends can be compared without floating point noise.
+///
+/// # Arguments
+///
+/// * `value` - The value to round
+/// * `decimals` - The number of decimals to keep, or `None` to keep the value as is
+///
+/// # Returns
+///
+/// * `f32` - The rounded value
+///
+pub fn round_decimals(value: f32, decimals: Option<u32>) -> f32 {
+    round_decimals_f64(value as f64, decimals) as f32
+}
+
+/// Round a double precision value to a number of decimals
+///
+/// # Arguments
+///
+/// * `value` - The value to round
+/// * `decimals` - The number of decimals to keep, or `None` to keep the value as is
+///
+/// # Returns
+///
+/// * `f64` - The rounded value
+///
+pub fn round_decimals_f64(value: f64, decimals: Option<u32>) -> f64 {
+    match decimals {
+        Some(decimals) if value.is_finite() => {
+            let factor = 10f64.powi(decimals.min(MAX_ROUND_DECIMALS) as i32);
+            (value * factor).round() / factor
+        },
+        _ => value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_decimals() {
+        assert_eq!(round_decimals(9.999999, Some(3)), 10.0);
+        assert_eq!(round_decimals(1.23456, Some(2)), 1.23);
+        assert_eq!(round_decimals(1.23456, None), 1.23456);
+        assert_eq!(round_decimals(f32::MAX, Some(2)), f32::MAX);
+        assert_eq!(round_decimals_f64(1234567.125, Some(2)), 1234567.13);
+    }
+}
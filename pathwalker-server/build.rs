@@ -0,0 +1,9 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the vendored protoc, so the build doesn't need it installed
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+
+    // Compile the protocol buffers of the gRPC interface
+    tonic_build::compile_protos("proto/pathwalker.proto")?;
+
+    Ok(())
+}
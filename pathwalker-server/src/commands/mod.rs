@@ -0,0 +1,289 @@
+use std::fs;
+
+use log::{error, info};
+#[cfg(feature = "opencl")]
+use ocl::{Device, Platform};
+use serde_json::json;
+
+use crate::endpoints::validate_matrix;
+use crate::services::bench::{run_bench, DEFAULT_BENCH_SIZES, DEFAULT_EDGE_PROBABILITY, DEFAULT_REPETITIONS};
+use crate::services::generator::GraphSpec;
+use crate::services::loadtest::{run_load, LoadProfile};
+use crate::services::replay::{bundle_path, read_bundle, replay_bundle};
+use crate::services::sortest_path::SortestPath;
+
+/// Open the device selected to replay the bundles against
+///
+/// # Arguments
+///
+/// * `platform` - The index of the platform, the default one when not given
+/// * `device` - The index of the device of the platform
+///
+/// # Returns
+///
+/// * `Option<SortestPath>` - The walker of the device, if it's available
+///
+#[cfg(feature = "opencl")]
+fn replay_walker(platform: Option<usize>, device: usize) -> Option<SortestPath> {
+    // Find the platform by its index, and the device in it
+    let platform = match platform {
+        Some(index) => match Platform::list().get(index) {
+            Some(platform) => *platform,
+            None => {
+                error!("There is no OpenCL platform with index {}", index);
+                return None;
+            }
+        },
+        None => Platform::default()
+    };
+    let device = match Device::list_all(platform).map(|devices| devices.get(device).copied()) {
+        Ok(Some(device)) => device,
+        _ => {
+            error!("The selected OpenCL device is not available");
+            return None;
+        }
+    };
+
+    Some(SortestPath::with_device(platform, device))
+}
+
+/// Open the device selected to replay the bundles against, in the builds without OpenCL
+#[cfg(not(feature = "opencl"))]
+fn replay_walker(_platform: Option<usize>, _device: usize) -> Option<SortestPath> {
+    error!("The bundles can't be replayed, as the server has been built without OpenCL");
+    None
+}
+
+/// Replay a recorded bundle from the command line
+///
+/// Usage: `path_walker replay <bundle> [--platform <index>] [--device <index>]`
+///
+/// The bundle is given by its path, or by the id returned when it was
+/// recorded, which is looked up in the record directory.
+///
+/// The platform and device indexes select the backend to replay against,
+/// using the first device of the default platform when not given.
+///
+/// # Arguments
+///
+/// * `args` - The arguments after the subcommand name
+///
+/// # Returns
+///
+/// * `i32` - The exit code of the process
+///
+pub fn replay(args: &[String]) -> i32 {
+    // Read the arguments of the subcommand
+    let bundle_path = match args.first() {
+        Some(path) => path,
+        None => {
+            error!("Usage: path_walker replay <bundle> [--platform <index>] [--device <index>]");
+            return 2;
+        }
+    };
+    let option = |name: &str| args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .and_then(|value| value.parse::<usize>().ok());
+
+    // Load the bundle
+    let bundle = match read_bundle(&self::bundle_path(bundle_path)) {
+        Ok(bundle) => bundle,
+        Err(err) => {
+            error!("The bundle {} can't be read: {}", bundle_path, err);
+            return 1;
+        }
+    };
+
+    if let Some(message) = validate_matrix(&bundle.matrix) {
+        error!("The bundle {} has an invalid matrix: {}", bundle_path, message);
+        return 1;
+    }
+
+    // Select the backend to replay against
+    let Some(walker) = replay_walker(option("--platform"), option("--device").unwrap_or(0)) else {
+        return 1;
+    };
+
+    // Print the replay info
+    info!("Replaying bundle recorded on {} ({}) because of {}", bundle.device, bundle.platform, bundle.reason);
+
+    // Replay the bundle and compare the results
+    let replayed = replay_bundle(&walker, &bundle);
+    let matches = replayed.path == bundle.path && replayed.error == bundle.error;
+
+    println!("{}", json!({
+        "matches": matches,
+        "recorded": { "device": bundle.device, "path": bundle.path, "error": bundle.error },
+        "replayed": { "device": replayed.device, "path": replayed.path, "error": replayed.error }
+    }));
+
+    if matches { 0 } else { 1 }
+}
+
+/// Generate a synthetic graph from a specification
+///
+/// Usage: `path_walker generate <spec> [--seed <seed>]`
+///
+/// The specification is a TOML file describing the topology and the weights
+/// of the graph, and the matrix is printed as the body of a `/sortest`
+/// request, so it can be fed to the benchmarks.
+///
+/// # Arguments
+///
+/// * `args` - The arguments after the subcommand name
+///
+/// # Returns
+///
+/// * `i32` - The exit code of the process
+///
+pub fn generate(args: &[String]) -> i32 {
+    // Read the arguments of the subcommand
+    let spec_path = match args.first() {
+        Some(path) => path,
+        None => {
+            error!("Usage: path_walker generate <spec> [--seed <seed>]");
+            return 2;
+        }
+    };
+    let seed = args.iter()
+        .position(|arg| arg == "--seed")
+        .and_then(|index| args.get(index + 1))
+        .and_then(|value| value.parse::<u64>().ok());
+
+    // Load the specification
+    let mut spec: GraphSpec = match fs::read_to_string(spec_path).map_err(|err| err.to_string()).and_then(|text| toml::from_str(&text).map_err(|err| err.to_string())) {
+        Ok(spec) => spec,
+        Err(err) => {
+            error!("The specification {} can't be read: {}", spec_path, err);
+            return 1;
+        }
+    };
+
+    if let Some(seed) = seed {
+        spec.seed = seed;
+    }
+
+    if let Some(message) = spec.validate() {
+        error!("The specification {} is not valid: {}", spec_path, message);
+        return 1;
+    }
+
+    // Generate the graph and print it
+    println!("{}", json!(spec.generate()));
+
+    0
+}
+
+/// Send mixed traffic to a server and report the latencies
+///
+/// Usage: `path_walker loadtest --target <url> --qps <rate> --profile <profile> [--api-key <key>]`
+///
+/// The profile is a YAML file describing the graphs and the share of
+/// uploads, point to point queries and batches of the traffic, and the
+/// report is printed as JSON with the latency percentiles of every kind.
+///
+/// # Arguments
+///
+/// * `args` - The arguments after the subcommand name
+///
+/// # Returns
+///
+/// * `i32` - The exit code of the process
+///
+pub async fn loadtest(args: &[String]) -> i32 {
+    // Read the arguments of the subcommand
+    let option = |name: &str| args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1));
+
+    let (target, qps, profile_path) = match (option("--target"), option("--qps").and_then(|qps| qps.parse::<f64>().ok()), option("--profile")) {
+        (Some(target), Some(qps), Some(profile)) if qps > 0.0 && qps.is_finite() => (target, qps, profile),
+        _ => {
+            error!("Usage: path_walker loadtest --target <url> --qps <rate> --profile <profile> [--api-key <key>]");
+            return 2;
+        }
+    };
+
+    // Load the profile
+    let profile: LoadProfile = match fs::read_to_string(profile_path).map_err(|err| err.to_string()).and_then(|text| serde_norway::from_str(&text).map_err(|err| err.to_string())) {
+        Ok(profile) => profile,
+        Err(err) => {
+            error!("The profile {} can't be read: {}", profile_path, err);
+            return 1;
+        }
+    };
+
+    if let Some(message) = profile.validate() {
+        error!("The profile {} is not valid: {}", profile_path, message);
+        return 1;
+    }
+
+    // Run the load test and print the report
+    match run_load(target, qps, &profile, option("--api-key").map(String::as_str)).await {
+        Ok(report) => {
+            println!("{}", json!(report));
+            0
+        },
+        Err(err) => {
+            error!("{}", err);
+            1
+        }
+    }
+}
+
+/// Benchmark the device against a CPU reference on random graphs
+///
+/// Usage: `path_walker bench [--sizes <vertices,...>] [--repetitions <count>] [--seed <seed>] [--probability <probability>]`
+///
+/// Random graphs of increasing size are walked in the first device of the
+/// first platform and in a CPU implementation of Dijkstra, and the report is
+/// printed as JSON with the timings, the speedups and whether both have
+/// found the same distances. Without a device only the CPU is timed.
+///
+/// # Arguments
+///
+/// * `args` - The arguments after the subcommand name
+///
+/// # Returns
+///
+/// * `i32` - The exit code of the process, failing when there is no device or it disagrees with the CPU
+///
+pub fn bench(args: &[String]) -> i32 {
+    // Read the arguments of the subcommand
+    let option = |name: &str| args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1));
+    let usage = "Usage: path_walker bench [--sizes <vertices,...>] [--repetitions <count>] [--seed <seed>] [--probability <probability>]";
+
+    let sizes = match option("--sizes").map(|sizes| sizes.split(',').map(|size| size.trim().parse::<usize>().ok().filter(|size| *size > 0)).collect::<Option<Vec<_>>>()) {
+        Some(Some(sizes)) if !sizes.is_empty() => sizes,
+        None => DEFAULT_BENCH_SIZES.to_vec(),
+        _ => {
+            error!("{}", usage);
+            return 2;
+        }
+    };
+    let (repetitions, seed, probability) = match (
+        option("--repetitions").map_or(Some(DEFAULT_REPETITIONS), |count| count.parse::<usize>().ok().filter(|count| *count > 0)),
+        option("--seed").map_or(Some(0), |seed| seed.parse::<u64>().ok()),
+        option("--probability").map_or(Some(DEFAULT_EDGE_PROBABILITY), |probability| probability.parse::<f64>().ok().filter(|probability| (0.0..=1.0).contains(probability)))
+    ) {
+        (Some(repetitions), Some(seed), Some(probability)) => (repetitions, seed, probability),
+        _ => {
+            error!("{}", usage);
+            return 2;
+        }
+    };
+
+    // Open the device, benchmarking the CPU alone when there is none
+    let walker = SortestPath::try_new()
+        .inspect_err(|err| error!("The device can't be opened, only the CPU is benchmarked: {}", err))
+        .ok();
+
+    // Run the benchmark and print the report
+    let report = run_bench(walker.as_ref(), &sizes, repetitions, seed, probability);
+    println!("{}", json!(report));
+
+    if report.agrees() { 0 } else { 1 }
+}
@@ -0,0 +1,170 @@
+use std::env;
+use std::fs::{self, DirBuilder};
+use std::io;
+use std::net::TcpListener;
+use std::ops::Range;
+use std::os::fd::{FromRawFd, RawFd};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+
+use socket2::Socket;
+
+/// The environment variable with the process the listeners of systemd are passed to
+pub const LISTEN_PID_VARIABLE: &str = "LISTEN_PID";
+
+/// The environment variable with the number of listeners passed by systemd
+pub const LISTEN_FDS_VARIABLE: &str = "LISTEN_FDS";
+
+/// The first file descriptor of the listeners passed by systemd
+const LISTEN_FDS_START: RawFd = 3;
+
+/// A listener passed by systemd when the server is started by a socket unit
+#[derive(Debug)]
+pub enum ActivatedListener {
+    /// A TCP socket, like a `ListenStream` with a port
+    Tcp(TcpListener),
+    /// A Unix domain socket, like a `ListenStream` with a path
+    Unix(UnixListener),
+}
+
+/// Get the file descriptors of the listeners passed by systemd
+///
+/// systemd passes the listeners of a socket unit from the third file
+/// descriptor on, and names the process they are meant for, so the ones
+/// inherited by a child of the server are never taken twice.
+///
+/// # Arguments
+///
+/// * `pid` - The value of `LISTEN_PID`, if set
+/// * `fds` - The value of `LISTEN_FDS`, if set
+/// * `process` - The id of the server process
+///
+/// # Returns
+///
+/// * `Result<Range<RawFd>, String>` - The file descriptors, or why the listeners can't be taken
+///
+pub fn activated_descriptors(pid: Option<&str>, fds: Option<&str>, process: u32) -> Result<Range<RawFd>, String> {
+    let fds = fds.ok_or("The server hasn't been started by a systemd socket, as LISTEN_FDS is not set")?;
+
+    if let Some(pid) = pid.filter(|pid| pid.trim() != process.to_string()) {
+        return Err(format!("The listeners passed by systemd belong to the process {}", pid.trim()));
+    }
+
+    let count = fds.trim().parse::<RawFd>().ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(|| format!("The number of listeners passed by systemd {} is not valid", fds))?;
+
+    Ok(LISTEN_FDS_START..LISTEN_FDS_START + count)
+}
+
+/// Take the listeners passed by systemd to the server
+///
+/// # Returns
+///
+/// * `Result<Vec<ActivatedListener>, String>` - The listeners, or why they can't be taken
+///
+pub fn activated_listeners() -> Result<Vec<ActivatedListener>, String> {
+    let pid = env::var(LISTEN_PID_VARIABLE).ok();
+    let fds = env::var(LISTEN_FDS_VARIABLE).ok();
+
+    activated_descriptors(pid.as_deref(), fds.as_deref(), std::process::id())?
+        .map(|descriptor| {
+            // The descriptors passed by systemd are owned by the server from now on
+            let socket = unsafe { Socket::from_raw_fd(descriptor) };
+            let address = socket.local_addr().map_err(|err| format!("The listener {} passed by systemd is not a socket: {}", descriptor, err))?;
+
+            Ok(match address.is_unix() {
+                true => ActivatedListener::Unix(socket.into()),
+                false => ActivatedListener::Tcp(socket.into())
+            })
+        })
+        .collect()
+}
+
+/// Bind a Unix domain socket, restricting who can connect to it
+///
+/// The socket left by a previous run is removed first, but any other file
+/// at the path is kept and the bind fails, so a mistyped path never deletes
+/// a file. The socket is bound in a directory only the server can enter,
+/// and moved to its path once its permissions are set, so nobody else can
+/// connect to it in between.
+///
+/// # Arguments
+///
+/// * `path` - The path of the socket
+/// * `mode` - The permissions of the socket, like `0o660` for its owner and group
+///
+/// # Returns
+///
+/// * `io::Result<UnixListener>` - The listener, or the error binding it
+///
+pub fn bind_unix_socket(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+        Ok(_) => return Err(io::Error::other(format!("The path {} of the Unix socket is not a socket", path.display()))),
+        Err(_) => {}
+    }
+
+    // Bind the socket where only the server can reach it until its permissions are set
+    let name = path.file_name().ok_or_else(|| io::Error::other(format!("The path {} of the Unix socket has no file name", path.display())))?;
+    let staging = path.with_file_name(format!(".{}.{}", name.to_string_lossy(), std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    DirBuilder::new().mode(0o700).create(&staging)?;
+
+    let bound = staging.join("socket");
+    let listener = UnixListener::bind(&bound)
+        .and_then(|listener| fs::set_permissions(&bound, fs::Permissions::from_mode(mode)).map(|_| listener))
+        .and_then(|listener| fs::rename(&bound, path).map(|_| listener));
+    let _ = fs::remove_dir_all(&staging);
+
+    listener
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activated_descriptors() {
+        // Get the result
+        let descriptors = activated_descriptors(Some("42"), Some("2"), 42);
+        let unpinned = activated_descriptors(None, Some("1"), 42);
+        let foreign = activated_descriptors(Some("7"), Some("2"), 42);
+        let missing = activated_descriptors(Some("42"), None, 42);
+        let invalid = activated_descriptors(Some("42"), Some("none"), 42);
+
+        // Check if the result is correct
+        assert_eq!(descriptors, Ok(3..5));
+        assert_eq!(unpinned, Ok(3..4));
+        assert_eq!(foreign, Err("The listeners passed by systemd belong to the process 7".to_string()));
+        assert!(missing.is_err() && invalid.is_err());
+    }
+
+    #[test]
+    fn test_bind_unix_socket() {
+        // Prepare a stale socket and a regular file
+        let dir = env::temp_dir().join(format!("pathwalker-socket-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (path, file) = (dir.join("pathwalker.sock"), dir.join("pathwalker.txt"));
+        drop(UnixListener::bind(&path).unwrap());
+        fs::write(&file, "keep").unwrap();
+
+        // Get the result
+        let listener = bind_unix_socket(&path, 0o600);
+        let bound = listener.is_ok();
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        let staged = dir.join(format!(".pathwalker.sock.{}", std::process::id())).exists();
+        let rejected = bind_unix_socket(&file, 0o600);
+        let kept = fs::read_to_string(&file).unwrap();
+        drop(listener);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Check if the result is correct
+        assert!(bound);
+        assert_eq!(mode, 0o600);
+        assert!(!staged);
+        assert!(rejected.is_err());
+        assert_eq!(kept, "keep");
+    }
+}
@@ -0,0 +1,630 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use actix_web::http::{header::HeaderName, Method};
+use clap::Parser;
+use log::LevelFilter;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::models::Precision;
+use crate::services::backend::BackendKind;
+use crate::services::dispatch::{DEFAULT_BIDIRECTIONAL_THRESHOLD, DEFAULT_CPU_THRESHOLD};
+use pathwalker_core::solver::MAX_VERTICES;
+use crate::utils::LogFormat;
+
+pub mod listeners;
+#[cfg(feature = "tls")]
+pub mod tls;
+
+/// The host the servers bind to by default
+pub const DEFAULT_HOST: &str = "0.0.0.0";
+
+/// The port of the REST server by default
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// The port of the gRPC server by default
+pub const DEFAULT_GRPC_PORT: u16 = 50051;
+
+/// The port of the Arrow Flight server by default
+pub const DEFAULT_FLIGHT_PORT: u16 = 50052;
+
+/// The maximum size of a request body by default
+pub const DEFAULT_MAX_PAYLOAD: usize = 16 * 1024 * 1024;
+
+/// The format of the log lines by default
+pub const DEFAULT_LOG_FORMAT: &str = "text";
+
+/// The seconds to wait for the running requests and kernels when shutting down by default
+pub const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30;
+
+/// The seconds a computation can run for by default
+pub const DEFAULT_COMPUTATION_TIMEOUT: u64 = 60;
+
+/// The precision of the computations by default
+pub const DEFAULT_PRECISION: &str = "single";
+
+/// The compute backend of the walks by default, chosen when the server starts
+pub const DEFAULT_BACKEND: &str = "auto";
+
+/// The log level by default
+pub const DEFAULT_LOG_LEVEL: &str = "debug";
+
+/// The encodings the responses can be compressed with by default
+pub const DEFAULT_COMPRESSION: &str = "gzip,br";
+
+/// The permissions of the Unix domain socket by default, for its owner and group
+pub const DEFAULT_UNIX_SOCKET_MODE: u32 = 0o660;
+
+/// The methods the cross-origin requests can use by default
+pub const DEFAULT_CORS_METHODS: &str = "GET,POST,PUT,DELETE";
+
+/// The headers the cross-origin requests can send by default
+pub const DEFAULT_CORS_HEADERS: &str = "authorization,content-type,accept,x-request-id";
+
+/// The seconds the browsers can cache a preflight response by default
+pub const DEFAULT_CORS_MAX_AGE: usize = 3600;
+
+/// The encodings the server can compress the responses with
+pub const COMPRESSION_ENCODINGS: [&str; 3] = ["gzip", "br", "zstd"];
+
+/// The options of the server given in the command line or in the environment
+///
+/// Every option is optional, so the ones left out can be taken from the
+/// configuration file or from the defaults.
+#[derive(Default, Parser)]
+#[command(name = "path_walker", version, about = "Sortest path computation accelerated with OpenCL")]
+pub struct ConfigArgs {
+    /// The path of a TOML configuration file
+    #[arg(long, env = "PATHWALKER_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// The host the servers bind to
+    #[arg(long, env = "PATHWALKER_HOST")]
+    pub host: Option<String>,
+
+    /// The port of the REST server
+    #[arg(long, env = "PATHWALKER_PORT")]
+    pub port: Option<u16>,
+
+    /// The port of the gRPC server
+    #[arg(long, env = "PATHWALKER_GRPC_PORT")]
+    pub grpc_port: Option<u16>,
+
+    /// The port of the Arrow Flight server
+    #[arg(long, env = "PATHWALKER_FLIGHT_PORT")]
+    pub flight_port: Option<u16>,
+
+    /// The number of workers of the REST server, one per physical core when not given
+    #[arg(long, env = "PATHWALKER_WORKERS")]
+    pub workers: Option<usize>,
+
+    /// The maximum size in bytes of a request body
+    #[arg(long, env = "PATHWALKER_MAX_PAYLOAD")]
+    pub max_payload: Option<usize>,
+
+    /// The maximum number of vertices of a graph walked in the device
+    #[arg(long, env = "PATHWALKER_MAX_VERTICES")]
+    pub max_vertices: Option<usize>,
+
+    /// The bytes of device memory a walk can use, all the memory of the device when not given
+    #[arg(long, env = "PATHWALKER_MEMORY_BUDGET")]
+    pub memory_budget: Option<u64>,
+
+    /// The log level, one of off, error, warn, info, debug or trace
+    #[arg(long, env = "PATHWALKER_LOG_LEVEL")]
+    pub log_level: Option<String>,
+
+    /// The format of the log lines, text or json
+    #[arg(long, env = "PATHWALKER_LOG_FORMAT")]
+    pub log_format: Option<String>,
+
+    /// The URL of the OTLP/HTTP traces endpoint of the collector the spans are exported to
+    #[arg(long, env = "PATHWALKER_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
+    /// The path of the PEM certificate chain, serving HTTPS when given with the key
+    #[arg(long, env = "PATHWALKER_TLS_CERT")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// The path of the PEM private key of the certificate
+    #[arg(long, env = "PATHWALKER_TLS_KEY")]
+    pub tls_key: Option<PathBuf>,
+
+    /// The path of a Unix domain socket the REST server listens on too, serving plain HTTP
+    #[arg(long, env = "PATHWALKER_UNIX_SOCKET")]
+    pub unix_socket: Option<PathBuf>,
+
+    /// The permissions of the Unix domain socket, in octal
+    #[arg(long, env = "PATHWALKER_UNIX_SOCKET_MODE")]
+    pub unix_socket_mode: Option<String>,
+
+    /// If the REST server listens on the sockets passed by systemd too, true or false
+    #[arg(long, env = "PATHWALKER_SOCKET_ACTIVATION")]
+    pub socket_activation: Option<bool>,
+
+    /// The API keys allowed to use the server, as comma separated `name:key` pairs
+    #[arg(long, env = "PATHWALKER_API_KEYS", hide_env_values = true)]
+    pub api_keys: Option<String>,
+
+    /// The names of the API keys allowed to use the administration endpoints, comma separated
+    #[arg(long, env = "PATHWALKER_ADMIN_KEYS")]
+    pub admin_keys: Option<String>,
+
+    /// The requests per second of every client, unlimited when not given
+    #[arg(long, env = "PATHWALKER_RATE_LIMIT")]
+    pub rate_limit: Option<f64>,
+
+    /// The requests of every client computed at once, unlimited when not given
+    #[arg(long, env = "PATHWALKER_MAX_IN_FLIGHT")]
+    pub max_in_flight: Option<usize>,
+
+    /// The kernel milliseconds every API key can use in a calendar month, unlimited when not given
+    #[arg(long, env = "PATHWALKER_MONTHLY_QUOTA")]
+    pub monthly_quota: Option<u64>,
+
+    /// The seconds to wait for the running requests and kernels when shutting down
+    #[arg(long, env = "PATHWALKER_SHUTDOWN_TIMEOUT")]
+    pub shutdown_timeout: Option<u64>,
+
+    /// The seconds a computation can run for before being aborted, unlimited when zero
+    #[arg(long, env = "PATHWALKER_COMPUTATION_TIMEOUT")]
+    pub computation_timeout: Option<u64>,
+
+    /// The precision of the computations when the request doesn't choose one, single or double
+    #[arg(long, env = "PATHWALKER_PRECISION")]
+    pub precision: Option<String>,
+
+    /// The compute backend of the weighted walks, auto, opencl, wgpu, cuda or cpu
+    #[arg(long, env = "PATHWALKER_BACKEND")]
+    pub backend: Option<String>,
+
+    /// The vertices below which the graphs are walked in the CPU instead of the device, 0 to never walk them in the CPU
+    #[arg(long, env = "PATHWALKER_CPU_THRESHOLD")]
+    pub cpu_threshold: Option<usize>,
+
+    /// The vertices from which the walks of a stored graph towards a target search from both ends, 0 to never search from both ends
+    #[arg(long, env = "PATHWALKER_BIDIRECTIONAL_THRESHOLD")]
+    pub bidirectional_threshold: Option<usize>,
+
+    /// The encodings the responses can be compressed with, comma separated among gzip, br and zstd, or none
+    #[arg(long, env = "PATHWALKER_COMPRESSION")]
+    pub compression: Option<String>,
+
+    /// The files replacing the embedded kernel programs, as comma separated `program=path` pairs
+    #[arg(long, env = "PATHWALKER_KERNELS")]
+    pub kernels: Option<String>,
+
+    /// The origins allowed to call the REST server from a browser, comma separated, or `*` for any
+    #[arg(long, env = "PATHWALKER_CORS_ORIGINS")]
+    pub cors_origins: Option<String>,
+
+    /// The methods the cross-origin requests can use, comma separated, or `*` for any
+    #[arg(long, env = "PATHWALKER_CORS_METHODS")]
+    pub cors_methods: Option<String>,
+
+    /// The headers the cross-origin requests can send, comma separated, or `*` for any
+    #[arg(long, env = "PATHWALKER_CORS_HEADERS")]
+    pub cors_headers: Option<String>,
+
+    /// The seconds the browsers can cache a preflight response
+    #[arg(long, env = "PATHWALKER_CORS_MAX_AGE")]
+    pub cors_max_age: Option<usize>,
+}
+
+/// The options of the server given in the configuration file
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub grpc_port: Option<u16>,
+    pub flight_port: Option<u16>,
+    pub workers: Option<usize>,
+    pub max_payload: Option<usize>,
+    pub max_vertices: Option<usize>,
+    pub memory_budget: Option<u64>,
+    pub log_level: Option<String>,
+    pub log_format: Option<String>,
+    pub otlp_endpoint: Option<String>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub unix_socket: Option<PathBuf>,
+    pub unix_socket_mode: Option<String>,
+    pub socket_activation: Option<bool>,
+    pub api_keys: Option<BTreeMap<String, String>>,
+    pub admin_keys: Option<Vec<String>>,
+    pub rate_limit: Option<f64>,
+    pub max_in_flight: Option<usize>,
+    pub monthly_quota: Option<u64>,
+    pub shutdown_timeout: Option<u64>,
+    pub computation_timeout: Option<u64>,
+    pub precision: Option<String>,
+    pub backend: Option<String>,
+    pub cpu_threshold: Option<usize>,
+    pub bidirectional_threshold: Option<usize>,
+    pub compression: Option<String>,
+    pub kernels: Option<BTreeMap<String, PathBuf>>,
+    pub cors_origins: Option<Vec<String>>,
+    pub cors_methods: Option<Vec<String>>,
+    pub cors_headers: Option<Vec<String>>,
+    pub cors_max_age: Option<usize>,
+}
+
+/// The configuration of the server
+///
+/// The options are taken from the command line first, then from the
+/// environment, then from the configuration file, and finally from the
+/// defaults.
+///
+/// # Fields
+///
+/// * `host` - The host the servers bind to
+/// * `port` - The port of the REST server
+/// * `grpc_port` - The port of the gRPC server
+/// * `flight_port` - The port of the Arrow Flight server
+/// * `workers` - The number of workers of the REST server, if not left to actix
+/// * `max_payload` - The maximum size in bytes of a request body
+/// * `max_vertices` - The maximum number of vertices of a graph walked in the device
+/// * `memory_budget` - The bytes of device memory a walk can use, if limited below the memory of the device
+/// * `log_level` - The most verbose level that is logged
+/// * `log_format` - The format of the log lines
+/// * `otlp_endpoint` - The URL of the collector the spans are exported to, if any
+/// * `tls` - The paths of the certificate chain and the private key, if serving HTTPS
+/// * `unix_socket` - The path of the Unix domain socket the REST server listens on too, if any
+/// * `unix_socket_mode` - The permissions of the Unix domain socket
+/// * `socket_activation` - If the REST server listens on the sockets passed by systemd too
+/// * `api_keys` - The name of every API key along with the key or its digest
+/// * `admin_keys` - The names of the API keys allowed to use the administration endpoints
+/// * `rate_limit` - The requests per second of every client, if limited
+/// * `max_in_flight` - The requests of every client computed at once, if limited
+/// * `monthly_quota` - The kernel milliseconds every API key can use in a calendar month, if limited
+/// * `shutdown_timeout` - The seconds to wait for the running requests and kernels when shutting down
+/// * `computation_timeout` - The seconds a computation can run for before being aborted, if limited
+/// * `precision` - The precision of the computations when the request doesn't choose one
+/// * `backend` - The compute backend of the weighted walks, chosen when the server starts if not given
+/// * `cpu_threshold` - The vertices below which the graphs are walked in the CPU, never when zero
+/// * `bidirectional_threshold` - The vertices from which the walks towards a target search from both ends, never when zero
+/// * `compression` - The encodings the responses can be compressed with, none when empty
+/// * `kernels` - The name of every kernel program replaced along with the path of its file
+/// * `cors_origins` - The origins allowed to call the REST server from a browser, none when CORS is disabled
+/// * `cors_methods` - The methods the cross-origin requests can use
+/// * `cors_headers` - The headers the cross-origin requests can send
+/// * `cors_max_age` - The seconds the browsers can cache a preflight response
+///
+#[derive(Clone, PartialEq)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub grpc_port: u16,
+    pub flight_port: u16,
+    pub workers: Option<usize>,
+    pub max_payload: usize,
+    pub max_vertices: usize,
+    pub memory_budget: Option<u64>,
+    pub log_level: LevelFilter,
+    pub log_format: LogFormat,
+    pub otlp_endpoint: Option<String>,
+    pub tls: Option<(PathBuf, PathBuf)>,
+    pub unix_socket: Option<PathBuf>,
+    pub unix_socket_mode: u32,
+    pub socket_activation: bool,
+    pub api_keys: Vec<(String, String)>,
+    pub admin_keys: Vec<String>,
+    pub rate_limit: Option<f64>,
+    pub max_in_flight: Option<usize>,
+    pub monthly_quota: Option<u64>,
+    pub shutdown_timeout: u64,
+    pub computation_timeout: Option<u64>,
+    pub precision: Precision,
+    pub backend: Option<BackendKind>,
+    pub cpu_threshold: usize,
+    pub bidirectional_threshold: usize,
+    pub compression: Vec<String>,
+    pub kernels: Vec<(String, PathBuf)>,
+    pub cors_origins: Vec<String>,
+    pub cors_methods: Vec<String>,
+    pub cors_headers: Vec<String>,
+    pub cors_max_age: usize,
+}
+
+impl ServerConfig {
+    /// Read the configuration from the command line, the environment and the configuration file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ServerConfig, String>` - The configuration, or the reason why it can't be read
+    ///
+    pub fn load() -> Result<ServerConfig, String> {
+        let args = ConfigArgs::parse();
+
+        // Read the configuration file, if any
+        let file = match &args.config {
+            Some(path) => {
+                let text = fs::read_to_string(path).map_err(|err| format!("The configuration {} can't be read: {}", path.display(), err))?;
+                toml::from_str(&text).map_err(|err| format!("The configuration {} can't be parsed: {}", path.display(), err))?
+            },
+            None => ConfigFile::default()
+        };
+
+        ServerConfig::resolve(args, file)
+    }
+
+    /// Merge the options of the command line and the environment with the configuration file
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The options of the command line and the environment
+    /// * `file` - The options of the configuration file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ServerConfig, String>` - The configuration, or the reason why it isn't valid
+    ///
+    pub fn resolve(args: ConfigArgs, file: ConfigFile) -> Result<ServerConfig, String> {
+        let log_level = args.log_level.or(file.log_level).unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string());
+        let log_format = args.log_format.or(file.log_format).unwrap_or_else(|| DEFAULT_LOG_FORMAT.to_string());
+        let precision = args.precision.or(file.precision).unwrap_or_else(|| DEFAULT_PRECISION.to_string());
+        let backend = args.backend.or(file.backend).unwrap_or_else(|| DEFAULT_BACKEND.to_string());
+        let compression = args.compression.or(file.compression).unwrap_or_else(|| DEFAULT_COMPRESSION.to_string());
+        let unix_socket_mode = args.unix_socket_mode.or(file.unix_socket_mode);
+
+        let config = ServerConfig {
+            host: args.host.or(file.host).unwrap_or_else(|| DEFAULT_HOST.to_string()),
+            port: args.port.or(file.port).unwrap_or(DEFAULT_PORT),
+            grpc_port: args.grpc_port.or(file.grpc_port).unwrap_or(DEFAULT_GRPC_PORT),
+            flight_port: args.flight_port.or(file.flight_port).unwrap_or(DEFAULT_FLIGHT_PORT),
+            workers: args.workers.or(file.workers),
+            max_payload: args.max_payload.or(file.max_payload).unwrap_or(DEFAULT_MAX_PAYLOAD),
+            max_vertices: args.max_vertices.or(file.max_vertices).unwrap_or(MAX_VERTICES),
+            memory_budget: args.memory_budget.or(file.memory_budget),
+            log_level: LevelFilter::from_str(&log_level).map_err(|_| format!("The log level {} is not valid", log_level))?,
+            log_format: LogFormat::parse(&log_format).ok_or_else(|| format!("The log format {} is not valid", log_format))?,
+            otlp_endpoint: args.otlp_endpoint.or(file.otlp_endpoint),
+            tls: match (args.tls_cert.or(file.tls_cert), args.tls_key.or(file.tls_key)) {
+                (Some(cert), Some(key)) => Some((cert, key)),
+                (None, None) => None,
+                _ => return Err("The TLS certificate and private key must be given together".to_string())
+            },
+            unix_socket: args.unix_socket.or(file.unix_socket),
+            unix_socket_mode: match unix_socket_mode {
+                Some(mode) => u32::from_str_radix(mode.trim().trim_start_matches("0o"), 8).ok()
+                    .filter(|mode| *mode <= 0o777)
+                    .ok_or_else(|| format!("The mode {} of the Unix socket is not valid, it must be octal like 660", mode))?,
+                None => DEFAULT_UNIX_SOCKET_MODE
+            },
+            socket_activation: args.socket_activation.or(file.socket_activation).unwrap_or(false),
+            api_keys: match args.api_keys {
+                Some(pairs) => pairs.split(',')
+                    .filter(|pair| !pair.trim().is_empty())
+                    .map(|pair| pair.trim().split_once(':')
+                        .map(|(name, key)| (name.to_string(), key.to_string()))
+                        .ok_or_else(|| "The API keys must be given as name:key pairs".to_string()))
+                    .collect::<Result<_, String>>()?,
+                None => file.api_keys.unwrap_or_default().into_iter().collect()
+            },
+            admin_keys: match args.admin_keys {
+                Some(names) => names.split(',').map(str::trim).filter(|name| !name.is_empty()).map(str::to_string).collect(),
+                None => file.admin_keys.unwrap_or_default()
+            },
+            rate_limit: args.rate_limit.or(file.rate_limit),
+            max_in_flight: args.max_in_flight.or(file.max_in_flight),
+            monthly_quota: args.monthly_quota.or(file.monthly_quota),
+            shutdown_timeout: args.shutdown_timeout.or(file.shutdown_timeout).unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT),
+            computation_timeout: Some(args.computation_timeout.or(file.computation_timeout).unwrap_or(DEFAULT_COMPUTATION_TIMEOUT)).filter(|timeout| *timeout > 0),
+            precision: Precision::parse(&precision).ok_or_else(|| format!("The precision {} is not valid", precision))?,
+            backend: match backend.trim().to_ascii_lowercase().as_str() {
+                "auto" => None,
+                name => Some(BackendKind::parse(name).ok_or_else(|| format!("The backend {} is not valid, it must be auto, opencl, wgpu, cuda or cpu", backend))?)
+            },
+            cpu_threshold: args.cpu_threshold.or(file.cpu_threshold).unwrap_or(DEFAULT_CPU_THRESHOLD),
+            bidirectional_threshold: args.bidirectional_threshold.or(file.bidirectional_threshold).unwrap_or(DEFAULT_BIDIRECTIONAL_THRESHOLD),
+            compression: match compression.trim().to_ascii_lowercase().as_str() {
+                "none" => vec![],
+                encodings => encodings.split(',').map(str::trim).filter(|encoding| !encoding.is_empty())
+                    .map(|encoding| COMPRESSION_ENCODINGS.contains(&encoding).then(|| encoding.to_string())
+                        .ok_or_else(|| format!("The compression {} is not valid, it must be gzip, br, zstd or none", encoding)))
+                    .collect::<Result<_, String>>()?
+            },
+            kernels: match args.kernels {
+                Some(pairs) => pairs.split(',')
+                    .filter(|pair| !pair.trim().is_empty())
+                    .map(|pair| pair.trim().split_once('=')
+                        .map(|(name, path)| (name.to_string(), PathBuf::from(path)))
+                        .ok_or_else(|| "The kernel files must be given as program=path pairs".to_string()))
+                    .collect::<Result<_, String>>()?,
+                None => file.kernels.unwrap_or_default().into_iter().collect()
+            },
+            cors_origins: args.cors_origins.map(|origins| split_list(&origins)).or(file.cors_origins).unwrap_or_default(),
+            cors_methods: args.cors_methods.map(|methods| split_list(&methods)).or(file.cors_methods).unwrap_or_else(|| split_list(DEFAULT_CORS_METHODS)),
+            cors_headers: args.cors_headers.map(|headers| split_list(&headers)).or(file.cors_headers).unwrap_or_else(|| split_list(DEFAULT_CORS_HEADERS)),
+            cors_max_age: args.cors_max_age.or(file.cors_max_age).unwrap_or(DEFAULT_CORS_MAX_AGE)
+        };
+
+        if config.rate_limit.is_some_and(|rate| !rate.is_finite() || rate < 0.0) {
+            return Err("The rate limit must be a non-negative number".to_string());
+        }
+
+        if config.workers == Some(0) {
+            return Err("The number of workers must be greater than zero".to_string());
+        }
+
+        if config.max_vertices == 0 || config.memory_budget == Some(0) {
+            return Err("The maximum vertices and the memory budget must be greater than zero".to_string());
+        }
+
+        if let Some(backend) = config.backend.filter(|backend| !backend.is_built()) {
+            return Err(format!("The {} backend is not built in, the server must be built with the {} feature", backend.name(), backend.name()));
+        }
+
+        if let Some(method) = config.cors_methods.iter().find(|method| *method != "*" && Method::from_bytes(method.as_bytes()).is_err()) {
+            return Err(format!("The CORS method {} is not valid", method));
+        }
+
+        if let Some(header) = config.cors_headers.iter().find(|header| *header != "*" && HeaderName::from_str(header).is_err()) {
+            return Err(format!("The CORS header {} is not valid", header));
+        }
+
+        Ok(config)
+    }
+
+    /// Get the address of a server
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port of the server
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The address the server binds to
+    ///
+    pub fn address(&self, port: u16) -> String {
+        format!("{}:{}", self.host, port)
+    }
+
+    /// Resolve the socket address of a server
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port of the server
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SocketAddr, String>` - The socket address, or the reason why the host can't be resolved
+    ///
+    pub fn socket_address(&self, port: u16) -> Result<SocketAddr, String> {
+        self.address(port).to_socket_addrs().ok()
+            .and_then(|mut addresses| addresses.next())
+            .ok_or_else(|| format!("The host {} can't be resolved", self.host))
+    }
+
+    /// Describe the configuration without its secrets, for the logs and the administrators
+    ///
+    /// The API keys are listed by name only, and the credentials of the
+    /// collector URL are left out.
+    ///
+    /// # Returns
+    ///
+    /// * `Value` - The resolved options, as a JSON object
+    ///
+    pub fn redacted(&self) -> Value {
+        json!({
+            "host": self.host,
+            "port": self.port,
+            "grpc_port": self.grpc_port,
+            "flight_port": self.flight_port,
+            "workers": self.workers,
+            "max_payload": self.max_payload,
+            "max_vertices": self.max_vertices,
+            "memory_budget": self.memory_budget,
+            "log_level": self.log_level.as_str().to_lowercase(),
+            "log_format": self.log_format.name(),
+            "otlp_endpoint": self.otlp_endpoint.as_deref().map(redact_url),
+            "tls": self.tls.as_ref().map(|(cert, key)| json!({"cert": cert, "key": key})),
+            "unix_socket": self.unix_socket,
+            "unix_socket_mode": format!("{:o}", self.unix_socket_mode),
+            "socket_activation": self.socket_activation,
+            "api_keys": self.api_keys.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            "admin_keys": self.admin_keys,
+            "rate_limit": self.rate_limit,
+            "max_in_flight": self.max_in_flight,
+            "monthly_quota": self.monthly_quota,
+            "shutdown_timeout": self.shutdown_timeout,
+            "computation_timeout": self.computation_timeout,
+            "precision": self.precision,
+            "backend": self.backend.map_or("auto", |backend| backend.name()),
+            "cpu_threshold": self.cpu_threshold,
+            "bidirectional_threshold": self.bidirectional_threshold,
+            "compression": self.compression,
+            "kernels": self.kernels.iter().cloned().collect::<BTreeMap<_, _>>(),
+            "cors": {
+                "origins": self.cors_origins,
+                "methods": self.cors_methods,
+                "headers": self.cors_headers,
+                "max_age": self.cors_max_age
+            }
+        })
+    }
+}
+
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the API keys, even when debugging
+        write!(f, "ServerConfig {}", self.redacted())
+    }
+}
+
+/// Split a comma separated list, leaving out the empty items
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',').map(str::trim).filter(|item| !item.is_empty()).map(str::to_string).collect()
+}
+
+/// Leave the credentials out of a URL
+fn redact_url(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+
+    match rest[..authority_end].rfind('@') {
+        Some(at) if scheme.is_empty() => format!("[redacted]@{}", &rest[at + 1..]),
+        Some(at) => format!("{}://[redacted]@{}", scheme, &rest[at + 1..]),
+        None => url.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_precedence() {
+        // Prepare the options
+        let args = ConfigArgs::try_parse_from(["path_walker", "--port", "9090", "--log-level", "warn", "--api-keys", "robots:s3cr3t,dashboard:sha256:00ff"]).unwrap();
+        let file: ConfigFile = toml::from_str("port = 7070\nhost = \"127.0.0.1\"\nworkers = 4").unwrap();
+
+        // Get the result
+        let config = ServerConfig::resolve(args, file).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(config.port, 9090);
+        assert_eq!(config.host, "127.0.0.1");
+        assert_eq!(config.workers, Some(4));
+        assert_eq!(config.grpc_port, DEFAULT_GRPC_PORT);
+        assert_eq!(config.log_level, LevelFilter::Warn);
+        assert_eq!(config.log_format, LogFormat::Text);
+        assert_eq!(config.computation_timeout, Some(DEFAULT_COMPUTATION_TIMEOUT));
+        assert_eq!(config.precision, Precision::Single);
+        assert_eq!((config.backend, config.cpu_threshold), (None, DEFAULT_CPU_THRESHOLD));
+        assert_eq!(config.bidirectional_threshold, DEFAULT_BIDIRECTIONAL_THRESHOLD);
+        assert_eq!((config.unix_socket_mode, config.socket_activation), (DEFAULT_UNIX_SOCKET_MODE, false));
+        assert_eq!(ServerConfig::resolve(ConfigArgs { unix_socket_mode: Some("0o600".to_string()), ..ConfigArgs::default() }, toml::from_str("unix_socket_mode = \"666\"").unwrap()).unwrap().unix_socket_mode, 0o600);
+        assert!(ServerConfig::resolve(ConfigArgs::default(), toml::from_str("unix_socket_mode = \"999\"").unwrap()).is_err());
+        assert_eq!(ServerConfig::resolve(ConfigArgs { cpu_threshold: Some(0), ..ConfigArgs::default() }, toml::from_str("cpu_threshold = 128").unwrap()).unwrap().cpu_threshold, 0);
+        assert_eq!(ServerConfig::resolve(ConfigArgs { backend: Some("cpu".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).unwrap().backend, Some(BackendKind::Cpu));
+        assert_eq!(ServerConfig::resolve(ConfigArgs { backend: Some("opencl".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).is_ok(), cfg!(feature = "opencl"));
+        assert_eq!(ServerConfig::resolve(ConfigArgs { backend: Some("cuda".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).is_ok(), cfg!(feature = "cuda"));
+        assert_eq!(ServerConfig::resolve(ConfigArgs { backend: Some("wgpu".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).is_ok(), cfg!(feature = "wgpu"));
+        assert!(ServerConfig::resolve(ConfigArgs { backend: Some("metal".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).is_err());
+        assert_eq!(config.compression, vec!["gzip".to_string(), "br".to_string()]);
+        assert_eq!((config.max_vertices, config.memory_budget), (MAX_VERTICES, None));
+        assert_eq!(config.address(config.port), "127.0.0.1:9090");
+        assert_eq!(config.api_keys, vec![("robots".to_string(), "s3cr3t".to_string()), ("dashboard".to_string(), "sha256:00ff".to_string())]);
+        assert!(!format!("{:?}", config).contains("s3cr3t"));
+        assert!(ServerConfig::resolve(ConfigArgs { log_level: Some("loud".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).is_err());
+        assert!(ServerConfig::resolve(ConfigArgs { precision: Some("half".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).is_err());
+        assert_eq!(ServerConfig::resolve(ConfigArgs { compression: Some("none".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).unwrap().compression, Vec::<String>::new());
+        assert!(ServerConfig::resolve(ConfigArgs { compression: Some("gzip,lz4".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).is_err());
+        assert_eq!(ServerConfig::resolve(ConfigArgs::default(), toml::from_str("[kernels]\nwalk = \"tuned/walk.cl\"").unwrap()).unwrap().kernels, vec![("walk".to_string(), PathBuf::from("tuned/walk.cl"))]);
+        assert!(ServerConfig::resolve(ConfigArgs { kernels: Some("tuned/walk.cl".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).is_err());
+        assert_eq!(ServerConfig::resolve(ConfigArgs { max_vertices: Some(4096), ..ConfigArgs::default() }, toml::from_str("memory_budget = 1073741824").unwrap()).unwrap().memory_budget, Some(1073741824));
+        assert!(ServerConfig::resolve(ConfigArgs { max_vertices: Some(0), ..ConfigArgs::default() }, ConfigFile::default()).is_err());
+        assert_eq!(config.cors_origins, Vec::<String>::new());
+        assert_eq!((config.cors_methods.len(), config.cors_max_age), (4, DEFAULT_CORS_MAX_AGE));
+        assert_eq!(ServerConfig::resolve(ConfigArgs { cors_origins: Some("https://visualizer.example, *".to_string()), ..ConfigArgs::default() }, toml::from_str("cors_origins = [\"https://other.example\"]").unwrap()).unwrap().cors_origins, vec!["https://visualizer.example".to_string(), "*".to_string()]);
+        assert!(ServerConfig::resolve(ConfigArgs { cors_methods: Some("GET,BAD METHOD".to_string()), ..ConfigArgs::default() }, ConfigFile::default()).is_err());
+        assert!(ServerConfig::resolve(ConfigArgs { tls_cert: Some(PathBuf::from("cert.pem")), ..ConfigArgs::default() }, ConfigFile::default()).is_err());
+    }
+}
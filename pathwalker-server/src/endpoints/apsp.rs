@@ -0,0 +1,123 @@
+use actix_web::{post, rt, web::{Bytes, Path, Query}, HttpResponse};
+use log::{error, info};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use super::errors::PathWalkerError;
+use crate::models::PathResult;
+use crate::services::cancellation::Cancellation;
+use crate::services::apsp::{all_pairs_blocks, quantize_path, DEFAULT_BLOCK_SIZE};
+use crate::services::entitlement::Feature;
+use crate::utils::spawn_blocking;
+use super::encoding::ndjson_response;
+use super::graphs::{graph_not_found, storage_error};
+use super::{computation_failure, entitlement_rejection, thermal_rejection, ENTITLEMENTS, STORE, THERMAL, with_device};
+
+/// The query parameters of the all-pairs endpoint
+#[derive(Debug, Deserialize)]
+pub struct AllPairsQuery {
+    pub block: Option<usize>,
+    pub round: Option<u32>,
+    pub scale: Option<f32>,
+}
+
+/// The stored graph all-pairs endpoint
+///
+/// Exposes a endpoint that computes the sortest paths from every vertex of a
+/// stored graph, streaming a line of newline delimited JSON for every source
+/// as the blocks of `block` sources are computed. With a `scale`, the
+/// distances are quantized to unsigned 16 bit integers counting steps of the
+/// scale, for clients with little bandwidth
+///
+/// # Arguments
+///
+/// * `name` - The name of the graph
+/// * `query` - The query parameters
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response streaming the rows
+///
+#[post("/graphs/{name}/apsp")]
+pub async fn graph_all_pairs_endpoint(name: Path<String>, query: Query<AllPairsQuery>) -> HttpResponse {
+    // Check that the license enables the features
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs).and_then(|_| ENTITLEMENTS.require(Feature::Apsp)) {
+        return entitlement_rejection(message);
+    }
+
+    // Check that the quantized distances have a scale to count
+    if query.scale.is_some_and(|scale| !scale.is_finite() || scale <= 0.0) {
+        return PathWalkerError::Validation("The scale must be a positive number".to_string()).response();
+    }
+
+    // Check that the graph exists before admitting the job
+    match STORE.info(&name) {
+        Ok(Some(_)) => {},
+        Ok(None) => return graph_not_found(&name),
+        Err(message) => return storage_error(message)
+    }
+
+    // Reject the job right away while the device is too hot to admit it
+    let permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(state) => return thermal_rejection(&state)
+    };
+
+    // Get the graph resident in the device outside the async executor, uploading it again after a restart
+    let graph_name = name.to_string();
+
+    let graph = match spawn_blocking(move || STORE.get(&graph_name, |matrix| with_device(|walker| walker.upload(matrix)))).await.map_err(computation_failure) {
+        Ok(Ok(Some(graph))) => graph,
+        Ok(Ok(None)) => return graph_not_found(&name),
+        Ok(Err(message)) | Err(message) => return storage_error(message)
+    };
+
+    // Print the request
+    info!("Received all-pairs request for graph {} with {} vertices", name, graph.info.vertices);
+
+    // Compute the blocks outside the async executor, waiting for every block
+    // to be sent before computing the next one
+    let (block, round, scale) = (query.block.unwrap_or(DEFAULT_BLOCK_SIZE), query.round, query.scale);
+    let (sender, receiver) = mpsc::channel::<Bytes>(1);
+    let zero_edges = graph.device.zero_edges;
+
+    // Stop the walks as soon as the client disconnects, without the timeout
+    // of the computations, as the stream is expected to run for long
+    let (cancellation, closed) = (Cancellation::with_timeout(None), sender.clone());
+    let disconnection = cancellation.clone();
+
+    rt::spawn(async move {
+        closed.closed().await;
+        disconnection.cancel();
+    });
+
+    spawn_blocking(move || {
+        let _permit = permit;
+        let result = with_device(|walker| all_pairs_blocks(walker, &graph.device, block, &cancellation, |rows| {
+            let lines: String = rows.into_iter()
+                .map(|(source, path)| match scale {
+                    Some(scale) => {
+                        let path = quantize_path(&path, source, scale, zero_edges);
+                        json!({ "source": source, "scale": scale, "path": path }).to_string() + "\n"
+                    },
+                    None => {
+                        let path: Vec<PathResult> = path.iter().map(|hop| hop.round(round)).collect();
+                        json!({ "source": source, "path": path }).to_string() + "\n"
+                    }
+                })
+                .collect();
+
+            sender.blocking_send(Bytes::from(lines)).is_ok()
+        }));
+
+        // Close the stream with the error of the kernels
+        if let Err(err) = result {
+            error!("The all-pairs computation has failed: {}", err);
+            let _ = sender.blocking_send(Bytes::from(PathWalkerError::from(err).body().to_string() + "\n"));
+        }
+    });
+
+    // Stream the blocks as they are computed
+    ndjson_response(receiver)
+}
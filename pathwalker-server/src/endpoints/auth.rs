@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use actix_web::{body::{BoxBody, MessageBody}, dev::{ServiceRequest, ServiceResponse}, http::header, middleware::Next, web::Data, Error, HttpMessage};
+use log::{info, warn};
+use tonic::{service::Interceptor, Request, Status};
+
+use crate::services::auth::ApiKeys;
+use super::errors::PathWalkerError;
+
+/// The header with the API key, for clients that can't send an `Authorization` header
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// The metadata with the API key of the gRPC calls, for clients that can't send an `authorization` metadata
+pub const API_KEY_METADATA: &str = "x-api-key";
+
+/// The paths served without authentication nor limits, so probes and scrapers don't need a key
+const PUBLIC_PATHS: [&str; 5] = ["/healthz", "/readyz", "/metrics", "/openapi.json", "/docs"];
+
+/// The prefix of the paths only served to the administration keys
+pub const ADMIN_PREFIX: &str = "/admin";
+
+/// The name of the API key that authenticated a request
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiKeyName(pub String);
+
+/// Check if a path is served without authentication nor limits
+///
+/// # Arguments
+///
+/// * `path` - The path of the request
+///
+/// # Returns
+///
+/// * `bool` - If the path is public
+///
+pub fn is_public_path(path: &str) -> bool {
+    PUBLIC_PATHS.iter().any(|public| path == *public || path.starts_with(&format!("{}/", public)))
+}
+
+/// Read the API key of a request
+///
+/// The key is taken from the `Authorization: Bearer` header, or from the
+/// `X-API-Key` header otherwise.
+fn request_key(req: &ServiceRequest) -> Option<&str> {
+    let headers = req.headers();
+
+    headers.get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| headers.get(API_KEY_HEADER).and_then(|value| value.to_str().ok()))
+        .map(str::trim)
+}
+
+/// The API key authentication middleware
+///
+/// Rejects the requests without a known API key with `401 Unauthorized`,
+/// except the ones to the probes, the metrics and the documentation, and
+/// logs the name of the key of every authenticated request. The requests
+/// to the administration endpoints without an administration key are
+/// rejected with `403 Forbidden`. When no keys are configured every request
+/// is let through, except the ones to the administration endpoints, which
+/// are never served without an administration key.
+///
+/// # Arguments
+///
+/// * `req` - The request
+/// * `next` - The rest of the services
+///
+/// # Returns
+///
+/// * `Result<ServiceResponse<BoxBody>, Error>` - The response
+///
+pub async fn require_api_key(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, Error> {
+    let keys = req.app_data::<Data<ApiKeys>>().cloned();
+
+    // Never serve the administration endpoints to the deployments without keys
+    if req.path().starts_with(ADMIN_PREFIX) && keys.as_ref().is_none_or(|keys| !keys.is_enabled()) {
+        warn!("Rejected request {} {} as no administration key is configured", req.method(), req.path());
+
+        let error = PathWalkerError::Forbidden("The administration endpoints need an administration API key".to_string());
+        return Ok(req.into_response(error.response()));
+    }
+
+    // Let the public paths and the deployments without keys through
+    if keys.as_ref().is_none_or(|keys| !keys.is_enabled()) || is_public_path(req.path()) {
+        return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+    }
+
+    // Identify the owner of the key
+    match keys.as_ref().and_then(|keys| request_key(&req).and_then(|key| keys.identify(key))) {
+        Some(name) if req.path().starts_with(ADMIN_PREFIX) && !keys.as_ref().is_some_and(|keys| keys.is_admin(name)) => {
+            warn!("Rejected request {} {} from the API key {} without administration rights", req.method(), req.path(), name);
+
+            let error = PathWalkerError::Forbidden("The API key can't use the administration endpoints".to_string());
+            Ok(req.into_response(error.response()))
+        },
+        Some(name) => {
+            info!("Request {} {} authenticated with the API key {}", req.method(), req.path(), name);
+            req.extensions_mut().insert(ApiKeyName(name.to_string()));
+            next.call(req).await.map(ServiceResponse::map_into_boxed_body)
+        },
+        None => {
+            warn!("Rejected unauthenticated request {} {} from {}", req.method(), req.path(), req.connection_info().peer_addr().unwrap_or("unknown"));
+
+            let mut response = PathWalkerError::Unauthorized("A valid API key is required".to_string()).response();
+            response.headers_mut().insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Bearer"));
+            Ok(req.into_response(response))
+        }
+    }
+}
+
+/// The API key authentication of the gRPC and Arrow Flight services
+///
+/// Rejects the calls without a known API key with `UNAUTHENTICATED`, taking
+/// the key from the `authorization: Bearer` metadata, or from the
+/// `x-api-key` metadata otherwise, and keeps the name of the key in the
+/// extensions of the call. When no keys are configured every call is let
+/// through, as the REST middleware does.
+///
+/// # Fields
+///
+/// * `keys` - The API keys allowed to use the server
+///
+#[derive(Clone)]
+pub struct GrpcApiKey {
+    keys: Arc<ApiKeys>,
+}
+
+impl GrpcApiKey {
+    /// Create the authentication of the calls
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - The API keys allowed to use the server, shared with the REST middleware
+    ///
+    /// # Returns
+    ///
+    /// * `GrpcApiKey` - The interceptor
+    ///
+    pub fn new(keys: Arc<ApiKeys>) -> GrpcApiKey {
+        GrpcApiKey { keys }
+    }
+}
+
+impl Interceptor for GrpcApiKey {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        // Let the calls through when the deployment has no keys
+        if !self.keys.is_enabled() {
+            return Ok(request);
+        }
+
+        // Read the key of the call and identify its owner
+        let metadata = request.metadata();
+        let key = metadata.get(header::AUTHORIZATION.as_str())
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or_else(|| metadata.get(API_KEY_METADATA).and_then(|value| value.to_str().ok()))
+            .map(str::trim);
+
+        match key.and_then(|key| self.keys.identify(key)) {
+            Some(name) => {
+                info!("gRPC call authenticated with the API key {}", name);
+                let name = ApiKeyName(name.to_string());
+                request.extensions_mut().insert(name);
+                Ok(request)
+            },
+            None => {
+                warn!("Rejected unauthenticated gRPC call from {}", request.remote_addr().map_or_else(|| "unknown".to_string(), |address| address.to_string()));
+                Err(Status::unauthenticated("A valid API key is required"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{middleware::from_fn, test::{call_service, init_service, TestRequest}, App};
+    use crate::endpoints::health::healthz_endpoint;
+    use crate::endpoints::shadow::shadow_endpoint;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_require_api_key() {
+        // Prepare the keys
+        let keys = ApiKeys::new(&[("robots".to_string(), "s3cr3t".to_string())]).unwrap();
+
+        // Get the result
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(keys))
+                .wrap(from_fn(require_api_key))
+                .service(healthz_endpoint)
+                .service(shadow_endpoint)
+        ).await;
+
+        let anonymous = call_service(&app, TestRequest::get().uri("/shadow").to_request()).await;
+        let wrong = call_service(&app, TestRequest::get().uri("/shadow").insert_header((API_KEY_HEADER, "guess")).to_request()).await;
+        let bearer = call_service(&app, TestRequest::get().uri("/shadow").insert_header((header::AUTHORIZATION, "Bearer s3cr3t")).to_request()).await;
+        let probe = call_service(&app, TestRequest::get().uri("/healthz").to_request()).await;
+        let admin = call_service(&app, TestRequest::get().uri("/admin/soak").insert_header((API_KEY_HEADER, "s3cr3t")).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(anonymous.status(), 401);
+        assert_eq!(wrong.status(), 401);
+        assert_eq!(bearer.status(), 200);
+        assert_eq!(probe.status(), 200);
+        assert_eq!(admin.status(), 403);
+    }
+
+    #[actix_web::test]
+    async fn test_require_api_key_without_keys() {
+        // Get the result
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(ApiKeys::default()))
+                .wrap(from_fn(require_api_key))
+                .service(shadow_endpoint)
+        ).await;
+
+        let anonymous = call_service(&app, TestRequest::get().uri("/shadow").to_request()).await;
+        let admin = call_service(&app, TestRequest::get().uri("/admin/soak").to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(anonymous.status(), 200);
+        assert_eq!(admin.status(), 403);
+    }
+
+    #[test]
+    fn test_grpc_api_key() {
+        // Prepare the interceptors
+        let keys = ApiKeys::new(&[("robots".to_string(), "s3cr3t".to_string())]).unwrap();
+        let mut enabled = GrpcApiKey::new(Arc::new(keys));
+        let mut disabled = GrpcApiKey::new(Arc::new(ApiKeys::default()));
+
+        let mut bearer = Request::new(());
+        bearer.metadata_mut().insert("authorization", "Bearer s3cr3t".parse().unwrap());
+
+        let mut metadata = Request::new(());
+        metadata.metadata_mut().insert(API_KEY_METADATA, "s3cr3t".parse().unwrap());
+
+        let mut wrong = Request::new(());
+        wrong.metadata_mut().insert(API_KEY_METADATA, "guess".parse().unwrap());
+
+        // Get the result
+        let bearer = enabled.call(bearer).unwrap();
+        let metadata = enabled.call(metadata);
+        let wrong = enabled.call(wrong);
+        let anonymous = enabled.call(Request::new(()));
+        let open = disabled.call(Request::new(()));
+
+        // Check if the result is correct
+        assert_eq!(bearer.extensions().get::<ApiKeyName>(), Some(&ApiKeyName("robots".to_string())));
+        assert!(metadata.is_ok());
+        assert_eq!(wrong.unwrap_err().code(), tonic::Code::Unauthenticated);
+        assert_eq!(anonymous.unwrap_err().code(), tonic::Code::Unauthenticated);
+        assert!(open.is_ok());
+    }
+}
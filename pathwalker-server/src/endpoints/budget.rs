@@ -0,0 +1,204 @@
+use actix_web::{post, HttpResponse, web::Json};
+use log::{error, info};
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::models::{BudgetRequest, EvRequest, GridTopology, Transform, ValidationRules};
+use crate::services::budget::{budget_path, ev_route, validate_budget};
+use crate::services::entitlement::Feature;
+use crate::services::postprocess::{postprocess, route_legs, validate_pipeline};
+use crate::utils::{round_decimals, spawn_blocking};
+use super::{entitlement_rejection, invalid_cells, validate_static_matrix, ENTITLEMENTS};
+
+/// The budget constrained path endpoint
+///
+/// Exposes a endpoint that receives a matrix of weights along with the
+/// resource consumed by every edge, like a toll or the battery used, and
+/// returns the cheapest route from the source to the target whose resources
+/// don't exceed the budget
+///
+/// # Arguments
+///
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/sortest/budget")]
+pub async fn budget_endpoint(item: Json<BudgetRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Budget) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and deserialize it
+    let request: BudgetRequest = item.into_inner();
+
+    // Validate the matrix, the resources and the vertices before searching the path
+    let outside = request.source >= request.matrix.width || request.target >= request.matrix.width;
+    let error = validate_static_matrix(&request.matrix)
+        .or_else(|| validate_budget(&request.matrix, &request.resources, request.budget))
+        .or(outside.then_some("The source or the target are outside of the matrix"))
+        .or_else(|| validate_pipeline(&request.postprocess, request.matrix.width, false));
+
+    if let Some(message) = error {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    // The labels of the search can't handle negative weights
+    if let Some(response) = invalid_cells(&request.matrix, ValidationRules { reject_negative: true, ..ValidationRules::default() }) {
+        return response;
+    }
+
+    // Print the request
+    info!("Received budget request from {} to {} with budget {}", request.source, request.target, request.budget);
+
+    if !request.postprocess.is_empty() {
+        info!("Post-processing the route with {}", request.postprocess.iter().map(Transform::name).collect::<Vec<_>>().join(", "));
+    }
+
+    // Search the path outside the async executor
+    let (source, target, round) = (request.source, request.target, request.matrix.round);
+    let result = spawn_blocking(move || {
+        budget_path(&request.matrix, &request.resources, request.budget, request.source, request.target).map(|path| path.map(|path| {
+            let processed = (!request.postprocess.is_empty()).then(|| postprocess(route_legs(&request.matrix, &path.route), &request.postprocess, 0, GridTopology::default()));
+            (path, processed)
+        }))
+    }).await;
+
+    // Return the route within the budget, with the route left by the pipeline when there is one
+    match result {
+        Ok(Ok(Some((path, processed)))) => {
+            let mut body = json!({
+                "status": "ok",
+                "route": path.route,
+                "cost": round_decimals(path.cost, round),
+                "resource": round_decimals(path.resource, round)
+            });
+
+            if let Some(processed) = processed {
+                body["postprocessed"] = json!(processed.round(round));
+            }
+
+            HttpResponse::Ok().json(body)
+        },
+        Ok(Ok(None)) => PathWalkerError::Unprocessable(format!("No route from {} to {} respects the budget", source, target)).response(),
+        Ok(Err(message)) => PathWalkerError::Unprocessable(message).response(),
+        Err(err) => {
+            error!("The budget search has panicked: {}", err);
+            PathWalkerError::Internal("The computation has been aborted".to_string()).response()
+        }
+    }
+}
+
+/// The electric vehicle route endpoint
+///
+/// Exposes a endpoint that receives a matrix of weights along with the
+/// energy consumed by every edge, the battery of the vehicle and the charging
+/// stations, and returns the cheapest route from the source to the target
+/// that never runs out of battery, with the charging stops it has to make
+///
+/// # Arguments
+///
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/sortest/ev")]
+pub async fn ev_endpoint(item: Json<EvRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Ev) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and deserialize it
+    let request: EvRequest = item.into_inner();
+    let charge = request.initial_charge.unwrap_or(request.capacity);
+
+    // Validate the matrix, the battery and the stations before searching the route
+    let width = request.matrix.width;
+    let error = validate_static_matrix(&request.matrix)
+        .or_else(|| validate_budget(&request.matrix, &request.consumption, request.capacity))
+        .or((request.source >= width || request.target >= width).then_some("The source or the target are outside of the matrix"))
+        .or((!(0.0..=request.capacity).contains(&charge)).then_some("The initial charge must be between zero and the capacity"))
+        .or(request.stations.iter()
+            .any(|station| station.vertex >= width || !station.rate.is_finite() || station.rate < 0.0)
+            .then_some("The stations must be inside of the matrix and have non-negative rates"));
+
+    if let Some(message) = error {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    // The labels of the search can't handle negative weights
+    if let Some(response) = invalid_cells(&request.matrix, ValidationRules { reject_negative: true, ..ValidationRules::default() }) {
+        return response;
+    }
+
+    // Print the request
+    info!("Received EV request from {} to {} with {} stations", request.source, request.target, request.stations.len());
+
+    // Search the route outside the async executor
+    let (source, target, round) = (request.source, request.target, request.matrix.round);
+    let result = spawn_blocking(move || {
+        ev_route(&request.matrix, &request.consumption, request.capacity, charge, &request.stations, request.source, request.target)
+    }).await;
+
+    // Return the route with its charging stops
+    match result {
+        Ok(Ok(Some(route))) => HttpResponse::Ok().json(json!({
+            "status": "ok",
+            "route": route.route,
+            "cost": round_decimals(route.cost, round),
+            "stops": route.stops,
+            "remaining_charge": round_decimals(route.remaining_charge, round)
+        })),
+        Ok(Ok(None)) => PathWalkerError::Unprocessable(format!("No route from {} to {} can be driven with the battery and the stations", source, target)).response(),
+        Ok(Err(message)) => PathWalkerError::Unprocessable(message).response(),
+        Err(err) => {
+            error!("The EV search has panicked: {}", err);
+            PathWalkerError::Internal("The computation has been aborted".to_string()).response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use serde_json::Value;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_budget_endpoint() {
+        // Prepare the request, where the direct edge is too expensive in tolls
+        let request: Value = json!({
+            "width": 3,
+            "height": 3,
+            "data": [0, 0, 0, 1, 0, 0, 5, 1, 0],
+            "resources": [0, 0, 0, 1, 0, 0, 9, 1, 0],
+            "budget": 4,
+            "target": 2
+        });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(budget_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/sortest/budget")
+            .set_json(&request)
+            .to_request();
+
+        // Get the response
+        let resp: Value = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp["status"], "ok");
+        assert_eq!(resp["route"], json!([0, 1, 2]));
+        assert_eq!(resp["resource"], 2.0);
+    }
+}
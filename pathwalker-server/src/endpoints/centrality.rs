@@ -0,0 +1,123 @@
+use actix_web::{post, HttpResponse, web::{Json, Query}};
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::models::{Matrix, ValidationRules};
+use crate::services::centrality::{betweenness, validate_centrality};
+use crate::services::entitlement::Feature;
+use crate::services::opencl;
+use crate::services::run_id::{matrix_digest, run_id};
+use crate::utils::{round_decimals_f64, spawn_blocking};
+use super::{computation_cancellation, computation_failure, entitlement_rejection, invalid_cells, thermal_rejection, walk_rejection, WalkRejection, ENTITLEMENTS, THERMAL, with_device};
+
+/// The query parameters of the centrality endpoints
+#[derive(Debug, Deserialize)]
+pub struct CentralityQuery {
+    #[serde(default)]
+    pub normalized: bool,
+}
+
+/// The betweenness centrality endpoint
+///
+/// Exposes a endpoint that receives a matrix of weights, and returns the
+/// betweenness centrality of every vertex, to find the bottlenecks of a
+/// network. The graph is walked from every vertex in the device, and the
+/// shortest paths of the walks are accumulated with the algorithm of Brandes
+///
+/// # Arguments
+///
+/// * `query` - The query parameters with the normalization of the scores
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/centrality/betweenness")]
+pub async fn betweenness_endpoint(query: Query<CentralityQuery>, item: Json<Matrix>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Centrality) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and validate it before walking the graph
+    let matrix: Matrix = item.into_inner();
+
+    if let Some(response) = invalid_cells(&matrix, ValidationRules { reject_negative: true, ..ValidationRules::default() }) {
+        return response;
+    }
+
+    if let Some(message) = validate_centrality(&matrix) {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    // Reject the job right away while the device is too hot to admit it
+    let permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(state) => return thermal_rejection(&state)
+    };
+
+    // Print the request
+    info!("Received betweenness centrality request over {} vertices", matrix.width);
+
+    // Walk the graph from every vertex outside the async executor
+    let (round, normalized) = (matrix.round, query.normalized);
+    let cancellation = computation_cancellation();
+    let (_cancel, walk_cancellation) = (cancellation.on_drop(), cancellation.clone());
+    let walk = spawn_blocking(move || {
+        let _permit = permit;
+        with_device(|walker| {
+            let graph = walker.upload(&matrix)?;
+            let paths = (0..matrix.width)
+                .map(|source| walker.walk(&graph, source, &walk_cancellation, |_, _| {}))
+                .collect::<opencl::Result<Vec<_>>>()?;
+
+            let parameters = [("normalized", normalized.to_string()), ("round", format!("{:?}", round))];
+            let run_id = run_id(&matrix_digest(&matrix), "centrality-betweenness", &parameters, walker.backend_version());
+
+            Ok::<_, opencl::Error>((betweenness(&matrix, &paths, normalized), run_id))
+        })
+    }).await;
+
+    // Return the centrality of every vertex
+    match walk.map_err(computation_failure) {
+        Ok(Ok((Ok(scores), run_id))) => HttpResponse::Ok().json(json!({
+            "status": "ok",
+            "scores": scores.into_iter().map(|score| round_decimals_f64(score, round)).collect::<Vec<f64>>(),
+            "run_id": run_id
+        })),
+        Ok(Ok((Err(message), _))) => PathWalkerError::Unprocessable(message).response(),
+        Ok(Err(err)) if cancellation.is_expired() => walk_rejection(&WalkRejection::Timeout(err.to_string())),
+        Ok(Err(err)) => PathWalkerError::from(err).response(),
+        Err(message) => PathWalkerError::Internal(message).response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_betweenness_endpoint_invalid_matrix() {
+        // Prepare a matrix with a negative weight, and one whose data doesn't fit its size
+        let negative = json!({ "width": 2, "height": 2, "data": [0, -1, 1, 0] });
+        let mismatched = json!({ "width": 2, "height": 2, "data": [0, 1, 1] });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(betweenness_endpoint)
+        ).await;
+
+        // Get the responses
+        let negative: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/centrality/betweenness").set_json(&negative).to_request()).await;
+        let mismatched: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/centrality/betweenness?normalized=true").set_json(&mismatched).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(negative["status"], "error");
+        assert_eq!(negative["errors"][0]["reason"], "The weight is negative, which the algorithm can't handle");
+        assert_eq!(mismatched["message"], "The matrix size and dimensions are not the same");
+    }
+}
@@ -0,0 +1,126 @@
+use actix_web::{delete, post, HttpMessage, HttpRequest, HttpResponse, web::{Bytes, Path, Query}};
+use pathwalker_core::solver::max_vertices;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::services::entitlement::Feature;
+use crate::services::uploads::{ChunkFormat, UploadProgress, UploadShape};
+use super::graphs::store_graph;
+use super::{entitlement_rejection, memory_rejection, ENTITLEMENTS, UPLOADS};
+
+/// The query parameters of the chunks of a graph
+///
+/// The shape of the matrix is given by the first chunk, and the next ones
+/// can leave it out.
+#[derive(Debug, Deserialize)]
+pub struct ChunkQuery {
+    pub width: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+    pub format: Option<String>,
+    #[serde(default)]
+    pub zero_edges: bool,
+    pub directed: Option<bool>,
+}
+
+/// Build the response for a chunk that can't be added to its upload
+fn chunk_rejection(message: impl Into<String>) -> HttpResponse {
+    PathWalkerError::Validation(message.into()).response()
+}
+
+/// The graph chunk upload endpoint
+///
+/// Exposes a endpoint that receives some rows of the matrix of a graph,
+/// starting at the `offset` row, as CSV lines or as little endian floats.
+/// The rows are assembled in the server, and once every row has arrived
+/// the matrix is stored like in `PUT /graphs/{name}`, so matrices too
+/// large for a single request body can be uploaded in pieces.
+///
+/// # Arguments
+///
+/// * `req` - The request, with the content type of the chunk
+/// * `name` - The name of the graph
+/// * `query` - The query parameters with the shape of the matrix and the first row of the chunk
+/// * `body` - The rows of the chunk
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response, `202 Accepted` while some rows are missing
+///
+#[post("/graphs/{name}/chunks")]
+pub async fn graph_chunk_endpoint(req: HttpRequest, name: Path<String>, query: Query<ChunkQuery>, body: Bytes) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs) {
+        return entitlement_rejection(message);
+    }
+
+    // Take the shape of the matrix from the chunk or from the upload it continues
+    let name = name.into_inner();
+    let shape = match (query.width, UPLOADS.shape(&name)) {
+        (Some(width), _) => UploadShape { width, zero_edges: query.zero_edges, directed: query.directed.unwrap_or(true) },
+        (None, Some(shape)) => shape,
+        (None, None) => return chunk_rejection("The first chunk must give the width of the matrix")
+    };
+
+    // Check that the matrix can be walked before assembling it
+    if shape.width == 0 {
+        return chunk_rejection("The matrix is empty");
+    } else if shape.width > max_vertices() {
+        return chunk_rejection("The matrix is too big");
+    }
+
+    if let Some(response) = memory_rejection::<f32>(shape.width) {
+        return response;
+    }
+
+    // Decode the rows, as binary when the format or the content type asks for it
+    let format = match query.format.as_deref() {
+        Some(name) => match ChunkFormat::parse(name) {
+            Some(format) => format,
+            None => return chunk_rejection(format!("The format {} is not valid, it must be csv or binary", name))
+        },
+        None if req.content_type() == "application/octet-stream" => ChunkFormat::Binary,
+        None => ChunkFormat::Csv
+    };
+
+    let weights = match format.decode(&body, shape.width) {
+        Ok(weights) => weights,
+        Err(message) => return chunk_rejection(message)
+    };
+
+    // Store the graph once every row has arrived
+    match UPLOADS.append(&name, shape, query.offset, &weights) {
+        Ok(UploadProgress::Partial(status)) => HttpResponse::Accepted().json(json!({ "status": "ok", "upload": status })),
+        Ok(UploadProgress::Complete(matrix)) => store_graph(&req, name, matrix).await,
+        Err(message) => chunk_rejection(message)
+    }
+}
+
+/// The graph chunk discard endpoint
+///
+/// Exposes a endpoint that discards the rows received of an unfinished
+/// upload, so it can be started again
+///
+/// # Arguments
+///
+/// * `name` - The name of the graph
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response, `404 Not Found` when the graph isn't being uploaded
+///
+#[delete("/graphs/{name}/chunks")]
+pub async fn discard_chunks_endpoint(name: Path<String>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs) {
+        return entitlement_rejection(message);
+    }
+
+    // Discard the rows received, if the graph is being uploaded
+    if UPLOADS.discard(&name) {
+        HttpResponse::Ok().json(json!({ "status": "ok" }))
+    } else {
+        PathWalkerError::NotFound(format!("The graph {} is not being uploaded", name)).response()
+    }
+}
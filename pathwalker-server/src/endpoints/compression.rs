@@ -0,0 +1,108 @@
+use actix_web::{body::MessageBody, dev::{ServiceRequest, ServiceResponse}, http::header::{HeaderValue, ACCEPT_ENCODING}, middleware::Next, web::Data, Error};
+
+/// The encodings the responses can be compressed with
+///
+/// # Fields
+///
+/// * `0` - The names of the encodings, none when the responses aren't compressed
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compression(pub Vec<String>);
+
+/// Leave out of an `Accept-Encoding` header the encodings that aren't allowed
+///
+/// The wildcard is replaced by the allowed encodings, with its quality, and
+/// `identity` is always kept.
+///
+/// # Arguments
+///
+/// * `accept` - The value of the header
+/// * `allowed` - The encodings the responses can be compressed with
+///
+/// # Returns
+///
+/// * `String` - The encodings of the header that are allowed, with their parameters
+///
+pub fn allowed_encodings(accept: &str, allowed: &[String]) -> String {
+    accept.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .flat_map(|item| {
+            let (coding, parameters) = item.split_once(';').unwrap_or((item, ""));
+            let coding = coding.trim().to_ascii_lowercase();
+            let with_parameters = |encoding: &str| if parameters.is_empty() { encoding.to_string() } else { format!("{};{}", encoding, parameters) };
+
+            match coding.as_str() {
+                "*" => allowed.iter().map(|encoding| with_parameters(encoding)).collect(),
+                "identity" => vec![item.to_string()],
+                coding if allowed.iter().any(|encoding| encoding == coding) => vec![item.to_string()],
+                _ => vec![]
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The encoding negotiation middleware
+///
+/// Narrows the `Accept-Encoding` header of the request to the encodings
+/// allowed by the configuration, so the compression middleware after it
+/// only compresses the responses with those, and never when none is allowed.
+///
+/// # Arguments
+///
+/// * `req` - The request
+/// * `next` - The rest of the services
+///
+/// # Returns
+///
+/// * `Result<ServiceResponse<impl MessageBody>, Error>` - The response
+///
+pub async fn negotiate_encoding(mut req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let allowed = req.app_data::<Data<Compression>>().map(|compression| compression.0.clone()).unwrap_or_default();
+    let accept = req.headers().get(ACCEPT_ENCODING).and_then(|accept| accept.to_str().ok()).map(|accept| allowed_encodings(accept, &allowed));
+
+    match accept.and_then(|accept| HeaderValue::from_str(&accept).ok()).filter(|accept| !accept.is_empty()) {
+        Some(accept) => req.headers_mut().insert(ACCEPT_ENCODING, accept),
+        None => req.headers_mut().remove(ACCEPT_ENCODING)
+    };
+
+    next.call(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{get, http::header::CONTENT_ENCODING, middleware::{from_fn, Compress}, test::{call_service, init_service, TestRequest}, App, HttpResponse};
+    use super::*;
+
+    #[get("/large")]
+    async fn large_endpoint() -> HttpResponse {
+        HttpResponse::Ok().body("0.125,".repeat(4096))
+    }
+
+    #[actix_web::test]
+    async fn test_negotiate_encoding() {
+        // Prepare a server compressing with gzip only, and another one never compressing
+        let gzip = init_service(App::new()
+            .wrap(Compress::default())
+            .app_data(Data::new(Compression(vec!["gzip".to_string()])))
+            .wrap(from_fn(negotiate_encoding))
+            .service(large_endpoint)).await;
+        let none = init_service(App::new()
+            .wrap(Compress::default())
+            .app_data(Data::new(Compression(vec![])))
+            .wrap(from_fn(negotiate_encoding))
+            .service(large_endpoint)).await;
+
+        // Get the result
+        let compressed = call_service(&gzip, TestRequest::get().uri("/large").insert_header((ACCEPT_ENCODING, "zstd, br;q=0.9, gzip;q=0.5")).to_request()).await;
+        let refused = call_service(&gzip, TestRequest::get().uri("/large").insert_header((ACCEPT_ENCODING, "br")).to_request()).await;
+        let disabled = call_service(&none, TestRequest::get().uri("/large").insert_header((ACCEPT_ENCODING, "gzip, *")).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(compressed.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert!(refused.headers().get(CONTENT_ENCODING).is_none());
+        assert!(disabled.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(allowed_encodings("br;q=0.9, *;q=0.1, identity", &["gzip".to_string(), "zstd".to_string()]), "gzip;q=0.1, zstd;q=0.1, identity");
+    }
+}
@@ -0,0 +1,114 @@
+use actix_cors::Cors;
+use actix_web::middleware::Condition;
+
+use super::request_id::REQUEST_ID_HEADER;
+
+/// The cross-origin requests allowed to the REST server
+///
+/// # Fields
+///
+/// * `origins` - The origins allowed, `*` for any, or none to disable CORS
+/// * `methods` - The methods allowed, `*` for any
+/// * `headers` - The request headers allowed, `*` for any
+/// * `max_age` - The seconds the browsers can cache a preflight response
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsPolicy {
+    pub origins: Vec<String>,
+    pub methods: Vec<String>,
+    pub headers: Vec<String>,
+    pub max_age: usize,
+}
+
+impl CorsPolicy {
+    /// Returns if any origin is allowed, so the CORS headers are sent
+    pub fn is_enabled(&self) -> bool {
+        !self.origins.is_empty()
+    }
+
+    /// Build the CORS middleware of the policy
+    ///
+    /// The middleware answers the preflight requests before the API keys
+    /// and the rate limits are checked, since the browsers never send the
+    /// credentials with them, and leaves the requests untouched when the
+    /// policy allows no origin. The correlation id of the responses is
+    /// exposed to the scripts of the allowed origins.
+    ///
+    /// # Returns
+    ///
+    /// * `Condition<Cors>` - The middleware, only enabled when some origin is allowed
+    ///
+    pub fn middleware(&self) -> Condition<Cors> {
+        let wildcard = |values: &[String]| values.iter().any(|value| value == "*");
+
+        // Allow the origins, methods and headers of the policy
+        let mut cors = Cors::default()
+            .expose_headers([REQUEST_ID_HEADER])
+            .max_age(self.max_age);
+
+        cors = if wildcard(&self.origins) {
+            cors.allow_any_origin()
+        } else {
+            self.origins.iter().fold(cors, |cors, origin| cors.allowed_origin(origin))
+        };
+
+        cors = if wildcard(&self.methods) {
+            cors.allow_any_method()
+        } else {
+            cors.allowed_methods(self.methods.iter().map(String::as_str))
+        };
+
+        cors = if wildcard(&self.headers) {
+            cors.allow_any_header()
+        } else {
+            cors.allowed_headers(self.headers.iter().map(String::as_str))
+        };
+
+        Condition::new(self.is_enabled(), cors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{get, http::{header, Method}, test::{call_service, init_service, TestRequest}, App, HttpResponse};
+    use super::*;
+
+    #[get("/dashboard")]
+    async fn dashboard_endpoint() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn test_cors_policy() {
+        // Prepare a server allowing the visualizer, and another one without CORS
+        let policy = CorsPolicy {
+            origins: vec!["https://visualizer.example".to_string()],
+            methods: vec!["GET".to_string(), "POST".to_string()],
+            headers: vec!["content-type".to_string(), "authorization".to_string()],
+            max_age: 600
+        };
+        let allowed = init_service(App::new().wrap(policy.middleware()).service(dashboard_endpoint)).await;
+        let disabled = init_service(App::new().wrap(CorsPolicy { origins: vec![], ..policy.clone() }.middleware()).service(dashboard_endpoint)).await;
+        let preflight = |origin: &str| TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/dashboard")
+            .insert_header((header::ORIGIN, origin.to_string()))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type"))
+            .to_request();
+
+        // Get the result
+        let accepted = call_service(&allowed, preflight("https://visualizer.example")).await;
+        let refused = call_service(&allowed, preflight("https://elsewhere.example")).await;
+        let simple = call_service(&allowed, TestRequest::get().uri("/dashboard").insert_header((header::ORIGIN, "https://visualizer.example")).to_request()).await;
+        let untouched = call_service(&disabled, TestRequest::get().uri("/dashboard").insert_header((header::ORIGIN, "https://visualizer.example")).to_request()).await;
+
+        // Check if the result is correct
+        assert!(accepted.status().is_success());
+        assert_eq!(accepted.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "https://visualizer.example");
+        assert_eq!(accepted.headers().get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "600");
+        assert!(refused.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        assert_eq!(simple.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "https://visualizer.example");
+        assert!(untouched.status().is_success() && untouched.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+}
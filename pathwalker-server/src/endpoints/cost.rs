@@ -0,0 +1,131 @@
+use actix_web::{post, HttpRequest, HttpResponse, web::Json};
+use log::info;
+use serde_json::json;
+
+use super::encoding::path_response;
+use super::errors::PathWalkerError;
+use crate::models::{CostRequest, Matrix, PathResult, ValidationRules};
+use crate::services::cost::{apply_cost, CostFunction};
+use crate::services::entitlement::Feature;
+use super::{blocking_sortest_path, entitlement_rejection, invalid_cells, validate_matrix, walk_rejection, CACHE_HEADER, ENTITLEMENTS};
+
+/// Build the response for a request that can't be walked
+fn bad_request(message: &str) -> HttpResponse {
+    PathWalkerError::Validation(message.to_string()).response()
+}
+
+/// The custom cost endpoint
+///
+/// Exposes a endpoint that receives the attributes of the edges of a graph,
+/// as matrices, along with a cost function combining them, and returns the
+/// path of the walk over the weights computed by the cost function
+///
+/// # Arguments
+///
+/// * `req` - The request, asking for comma separated values in the `Accept` header or JSON otherwise
+/// * `item` - The body of the request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/sortest/cost")]
+pub async fn cost_endpoint(req: HttpRequest, item: Json<CostRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Cost) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and check that every attribute has the size of the matrix
+    let request: CostRequest = item.into_inner();
+
+    if request.attributes.is_empty() {
+        return bad_request("The request has no edge attributes");
+    }
+
+    if let Some(name) = request.attributes.iter().find(|(_, values)| values.len() != request.width * request.height).map(|(name, _)| name) {
+        return bad_request(&format!("The size of the attribute {} and the matrix dimensions are not the same", name));
+    }
+
+    // Compile the cost function and compute the weights of the edges
+    let names: Vec<String> = request.attributes.keys().cloned().collect();
+    let values: Vec<&[f32]> = request.attributes.values().map(Vec::as_slice).collect();
+
+    let data = match CostFunction::compile(&request.cost, &names).and_then(|function| apply_cost(&function, request.width, &values)) {
+        Ok(data) => data,
+        Err(message) => return bad_request(&message)
+    };
+
+    // Validate the matrix before computing the path
+    let matrix = Matrix { round: request.round, ..Matrix::new(request.width, request.height, data) };
+
+    if let Some(message) = validate_matrix(&matrix) {
+        return bad_request(message);
+    }
+
+    if let Some(response) = invalid_cells(&matrix, ValidationRules::default()) {
+        return response;
+    }
+
+    // Print the request
+    info!("Received cost request with function {} over {:?}", request.cost, names);
+
+    // Get the path of the walk outside the async executor
+    let (result, cached, _) = match blocking_sortest_path(&matrix).await {
+        Ok(result) => result,
+        Err(rejection) => return walk_rejection(&rejection)
+    };
+
+    // Return the path of the walk
+    match result {
+        Ok(path) => {
+            let path: Vec<PathResult> = path.iter().map(|hop| hop.round(matrix.round)).collect();
+
+            let mut builder = HttpResponse::Ok();
+            builder.insert_header((CACHE_HEADER, if cached { "hit" } else { "miss" }));
+
+            path_response(&req, builder, json!({ "status": "ok", "path": path }))
+        },
+        Err(err) => PathWalkerError::from(err).response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use serde_json::Value;
+    use crate::models::Result;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_cost_endpoint_unknown_attribute() {
+        // Prepare the request
+        let request: Value = json!({
+            "width": 2,
+            "height": 2,
+            "attributes": { "distance": [0, 1, 1, 0] },
+            "cost": "distance * congestion"
+        });
+
+        // Prepare the expected result
+        let expected = "The attribute congestion doesn't exist";
+
+        // Get the result
+        let app = init_service(
+            App::new().service(cost_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/sortest/cost")
+            .set_json(&request)
+            .to_request();
+
+        // Get the response
+        let resp: Result = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "error");
+        assert_eq!(resp.message.unwrap(), expected);
+    }
+}
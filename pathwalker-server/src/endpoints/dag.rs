@@ -0,0 +1,120 @@
+use actix_web::{post, HttpResponse, web::Json};
+use log::{error, info};
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::models::{DagRequest, ValidationRules};
+use crate::services::dag::{dag_paths, validate_dag};
+use crate::services::entitlement::Feature;
+use crate::services::run_id::{matrix_digest, run_id};
+use crate::utils::{round_decimals, spawn_blocking};
+use super::{entitlement_rejection, invalid_cells, ENTITLEMENTS};
+
+/// The directed acyclic graph endpoint
+///
+/// Exposes a endpoint that receives a directed acyclic graph, like the
+/// dependencies of the tasks of a schedule, and returns its topological
+/// order along with the shortest or the longest paths from a source vertex,
+/// as chosen by the `objective`. The paths are found in the CPU, following
+/// the topological order, as the kernels can't find the longest paths
+///
+/// # Arguments
+///
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/dag")]
+pub async fn dag_endpoint(item: Json<DagRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Dag) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and validate it before sorting the graph
+    let request: DagRequest = item.into_inner();
+
+    if let Some(message) = validate_dag(&request) {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    if let Some(response) = invalid_cells(&request.matrix, ValidationRules::default()) {
+        return response;
+    }
+
+    // Print the request
+    info!("Received DAG request over {} vertices for the {:?} paths from {}", request.matrix.width, request.objective, request.source);
+
+    // Find the paths outside the async executor
+    let (round, target) = (request.matrix.round, request.target);
+    let result = spawn_blocking(move || {
+        let parameters = [
+            ("source", request.source.to_string()),
+            ("target", format!("{:?}", request.target)),
+            ("objective", format!("{:?}", request.objective)),
+            ("round", format!("{:?}", round))
+        ];
+        // The paths are found in the CPU, so the device doesn't take part in the backend
+        let backend = format!("path_walker {}; cpu", env!("CARGO_PKG_VERSION"));
+        let run_id = run_id(&matrix_digest(&request.matrix), "dag", &parameters, &backend);
+
+        dag_paths(&request).map(|paths| (paths, run_id))
+    }).await;
+
+    // Return the order and the paths of the graph
+    match result {
+        Ok(Ok((paths, run_id))) => {
+            let distances: Vec<Option<f32>> = paths.distances.iter().map(|distance| distance.map(|distance| round_decimals(distance, round))).collect();
+            let mut body = json!({
+                "status": "ok",
+                "order": paths.order,
+                "distances": distances,
+                "predecessors": paths.predecessors,
+                "run_id": run_id
+            });
+
+            if let Some(target) = target {
+                body["route"] = json!(paths.route(target));
+                body["distance"] = json!(distances[target]);
+            }
+
+            HttpResponse::Ok().json(body)
+        },
+        Ok(Err(message)) => PathWalkerError::Unprocessable(message).response(),
+        Err(err) => {
+            error!("The DAG paths have panicked: {}", err);
+            PathWalkerError::Internal("The computation has been aborted".to_string()).response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_dag_endpoint() {
+        // Prepare a schedule whose critical path goes through the longest task, and a cyclic one
+        let schedule = json!({ "width": 3, "height": 3, "data": [0, 0, 0, 4, 0, 0, 1, 2, 0], "target": 2, "objective": "longest" });
+        let cyclic = json!({ "width": 2, "height": 2, "data": [0, 1, 1, 0] });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(dag_endpoint)
+        ).await;
+
+        // Get the responses
+        let schedule: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/dag").set_json(&schedule).to_request()).await;
+        let cyclic: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/dag").set_json(&cyclic).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(schedule["order"], json!([0, 1, 2]));
+        assert_eq!(schedule["distances"], json!([0.0, 4.0, 6.0]));
+        assert_eq!(schedule["route"], json!([0, 1, 2]));
+        assert_eq!(schedule["distance"], 6.0);
+        assert_eq!(cyclic["message"], "The graph isn't acyclic, vertex 0 is part of a cycle or only reachable through one");
+    }
+}
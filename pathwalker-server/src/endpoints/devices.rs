@@ -0,0 +1,32 @@
+use actix_web::{get, HttpResponse};
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::services::devices::DeviceDiagnostics;
+use crate::utils::spawn_blocking;
+
+/// The OpenCL devices endpoint
+///
+/// Enumerates the installable client drivers, the platforms and the devices
+/// of the host, marking the device the walker runs its kernels in, along
+/// with hints to fix the drivers when no device is available to run the
+/// kernels
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[get("/devices")]
+pub async fn devices_endpoint() -> HttpResponse {
+    // Enumerate the drivers outside the async executor
+    let diagnostics = match spawn_blocking(DeviceDiagnostics::collect).await {
+        Ok(diagnostics) => diagnostics,
+        Err(_) => return PathWalkerError::Internal("The OpenCL drivers can't be enumerated".to_string()).response()
+    };
+
+    if diagnostics.has_devices() {
+        HttpResponse::Ok().json(json!({ "status": "ok", "selected": diagnostics.selected(), "diagnostics": diagnostics }))
+    } else {
+        PathWalkerError::Unavailable(diagnostics.summary()).response_with(json!({ "diagnostics": diagnostics }))
+    }
+}
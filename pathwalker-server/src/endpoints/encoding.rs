@@ -0,0 +1,463 @@
+use actix_web::{http::header, web::Bytes, Error, HttpRequest, HttpResponse, HttpResponseBuilder};
+use futures_util::stream;
+use prost::Message;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use crate::models::{Matrix, PathResult, ProcessedRoute, Real, Result, RoutePipeline};
+use super::grpc::proto;
+
+/// The media type of the JSON bodies
+pub const JSON_MEDIA_TYPE: &str = "application/json";
+
+/// The media type of the bincode bodies
+pub const BINCODE_MEDIA_TYPE: &str = "application/octet-stream";
+
+/// The media type of the protocol buffers bodies
+pub const PROTOBUF_MEDIA_TYPE: &str = "application/x-protobuf";
+
+/// The media type of the newline delimited JSON streams
+pub const NDJSON_MEDIA_TYPE: &str = "application/x-ndjson";
+
+/// The media type of the paths rendered as comma separated values
+pub const CSV_MEDIA_TYPE: &str = "text/csv";
+
+/// The header of the paths rendered as comma separated values
+const CSV_HEADER: &str = "node,predecessor,distance";
+
+/// The encodings supported for the request and response bodies
+///
+/// The paths can also be rendered as comma separated values, but only in
+/// the responses, as the matrices aren't read from them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Json,
+    Bincode,
+    Protobuf,
+    Csv,
+}
+
+impl Encoding {
+    /// Get the encoding for a media type
+    ///
+    /// # Arguments
+    ///
+    /// * `media_type` - The media type, as found in the headers
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Encoding>` - The encoding, if supported
+    ///
+    pub fn from_media_type(media_type: &str) -> Option<Encoding> {
+        match media_type.split(';').next().unwrap_or_default().trim() {
+            JSON_MEDIA_TYPE => Some(Encoding::Json),
+            BINCODE_MEDIA_TYPE => Some(Encoding::Bincode),
+            PROTOBUF_MEDIA_TYPE => Some(Encoding::Protobuf),
+            _ => None
+        }
+    }
+
+    /// Get the encoding of the request body from the `Content-Type` header
+    ///
+    /// Requests without `Content-Type` are considered JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The request
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Encoding>` - The encoding, if supported
+    ///
+    pub fn of_request(req: &HttpRequest) -> Option<Encoding> {
+        match req.headers().get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok()) {
+            Some(content_type) => Encoding::from_media_type(content_type),
+            None => Some(Encoding::Json)
+        }
+    }
+
+    /// Get the encoding of the response body from the `Accept` header
+    ///
+    /// The first supported media type wins, comma separated values included,
+    /// falling back to the encoding of the request when the client doesn't
+    /// ask for any in particular.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The request
+    /// * `fallback` - The encoding to use without a supported `Accept`
+    ///
+    /// # Returns
+    ///
+    /// * `Encoding` - The encoding of the response
+    ///
+    pub fn of_response(req: &HttpRequest, fallback: Encoding) -> Encoding {
+        req.headers().get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|accept| accept.split(',').filter(|media_type| !media_type.contains('*')).find_map(|media_type| {
+                if media_type.split(';').next().unwrap_or_default().trim() == CSV_MEDIA_TYPE {
+                    Some(Encoding::Csv)
+                } else {
+                    Encoding::from_media_type(media_type)
+                }
+            }))
+            .unwrap_or(fallback)
+    }
+
+    /// Decode a matrix from a body in this encoding
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The body of the request
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Matrix, String>` - The matrix or the reason why it can't be decoded
+    ///
+    pub fn decode_matrix(&self, body: &[u8]) -> std::result::Result<Matrix, String> {
+        match self {
+            Encoding::Json => serde_json::from_slice(body).map_err(|err| err.to_string()),
+            Encoding::Bincode => bincode::deserialize(body).map_err(|err| err.to_string()),
+            Encoding::Protobuf => proto::Matrix::decode(body).map(Into::into).map_err(|err| err.to_string()),
+            Encoding::Csv => Err("the matrices can't be read from comma separated values".to_string())
+        }
+    }
+
+    /// Decode a matrix with weights of any precision from a body in this encoding
+    ///
+    /// The protocol buffers messages only have single precision weights, so
+    /// those bodies are rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The body of the request
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Matrix<T>, String>` - The matrix or the reason why it can't be decoded
+    ///
+    pub fn decode_precise_matrix<T: Real>(&self, body: &[u8]) -> std::result::Result<Matrix<T>, String> {
+        match self {
+            Encoding::Json => serde_json::from_slice(body).map_err(|err| err.to_string()),
+            Encoding::Bincode => bincode::deserialize(body).map_err(|err| err.to_string()),
+            Encoding::Protobuf => Err("the protocol buffers matrices only have single precision weights".to_string()),
+            Encoding::Csv => Err("the matrices can't be read from comma separated values".to_string())
+        }
+    }
+
+    /// Build a response with the result in this encoding
+    ///
+    /// The failed results have no path to render as comma separated values,
+    /// so they are sent as JSON instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The response builder with the status already set
+    /// * `result` - The result to send
+    ///
+    /// # Returns
+    ///
+    /// * `HttpResponse` - The response
+    ///
+    pub fn respond(&self, mut builder: HttpResponseBuilder, result: Result) -> HttpResponse {
+        match self {
+            Encoding::Json => builder.json(json_body(result)),
+            Encoding::Bincode => builder
+                .content_type(BINCODE_MEDIA_TYPE)
+                .body(bincode::serialize(&result).unwrap()),
+            Encoding::Protobuf => builder
+                .content_type(PROTOBUF_MEDIA_TYPE)
+                .body(proto::PathResponse {
+                    status: result.status,
+                    path: result.path.unwrap_or_default().into_iter().map(Into::into).collect(),
+                    message: result.message,
+                    run_id: result.run_id,
+                    code: result.code
+                }.encode_to_vec()),
+            Encoding::Csv => match result.path {
+                Some(path) => csv_response(builder, &path),
+                None => builder.json(json_body(result))
+            }
+        }
+    }
+
+    /// Build a response with a result of any precision in this encoding
+    ///
+    /// The protocol buffers messages only have single precision weights, so
+    /// JSON is sent instead of them, and the failed results have no path to
+    /// render as comma separated values.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The response builder with the status already set
+    /// * `result` - The result to send
+    ///
+    /// # Returns
+    ///
+    /// * `HttpResponse` - The response
+    ///
+    pub fn respond_precise<T: Real>(&self, mut builder: HttpResponseBuilder, result: Result<T>) -> HttpResponse {
+        match self {
+            Encoding::Json | Encoding::Protobuf => builder.json(json_body(result)),
+            Encoding::Bincode => builder
+                .content_type(BINCODE_MEDIA_TYPE)
+                .body(bincode::serialize(&result).unwrap()),
+            Encoding::Csv => match result.path {
+                Some(path) => csv_response(builder, &path),
+                None => builder.json(json_body(result))
+            }
+        }
+    }
+}
+
+/// Check if the client asks for newline delimited JSON in the `Accept` header
+///
+/// # Arguments
+///
+/// * `req` - The request
+///
+/// # Returns
+///
+/// * `bool` - If any of the accepted media types is `application/x-ndjson`
+///
+pub fn accepts_ndjson(req: &HttpRequest) -> bool {
+    req.headers().get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.split(',').any(|media_type| media_type.split(';').next().unwrap_or_default().trim() == NDJSON_MEDIA_TYPE))
+}
+
+/// Build the response of an endpoint returning a path, in the encoding the client accepts
+///
+/// The body is sent as JSON, unless the client asks for comma separated
+/// values in the `Accept` header, which render the `path` of the body as a
+/// row per vertex, leaving out the rest of the fields.
+///
+/// # Arguments
+///
+/// * `req` - The request
+/// * `builder` - The response builder with the status and the headers already set
+/// * `body` - The JSON body, with the `path`
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+pub fn path_response(req: &HttpRequest, mut builder: HttpResponseBuilder, body: serde_json::Value) -> HttpResponse {
+    let path = match Encoding::of_response(req, Encoding::Json) {
+        Encoding::Csv => serde_json::from_value::<Vec<PathResult>>(body["path"].clone()).ok(),
+        _ => None
+    };
+
+    match path {
+        Some(path) => csv_response(builder, &path),
+        None => builder.json(body)
+    }
+}
+
+/// Build a response with a path rendered as comma separated values
+///
+/// Every vertex has a `node,predecessor,distance` row, in the order of the
+/// vertices, after the header.
+///
+/// # Arguments
+///
+/// * `builder` - The response builder with the status and the headers already set
+/// * `path` - The path to render
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+fn csv_response<T: Real>(mut builder: HttpResponseBuilder, path: &[PathResult<T>]) -> HttpResponse {
+    let mut body = format!("{}\n", CSV_HEADER);
+
+    for (node, hop) in path.iter().enumerate() {
+        // The distances are written as in JSON, with the shortest digits that read back the same
+        body.push_str(&format!("{},{},{}\n", node, hop.0, serde_json::to_string(&hop.1).unwrap()));
+    }
+
+    builder.content_type(CSV_MEDIA_TYPE).body(body)
+}
+
+/// Build a response streaming the lines of newline delimited JSON as they're sent
+///
+/// The channel should hold a single chunk, so the computation sending the
+/// lines waits for every chunk to be written out before reading back the
+/// next one, and stops once the client goes away and the send fails.
+///
+/// # Arguments
+///
+/// * `receiver` - The receiver of the chunks of lines
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response streaming the lines
+///
+pub fn ndjson_response(receiver: mpsc::Receiver<Bytes>) -> HttpResponse {
+    let body = stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|lines| (Ok::<_, Error>(lines), receiver))
+    });
+
+    HttpResponse::Ok()
+        .content_type(NDJSON_MEDIA_TYPE)
+        .streaming(body)
+}
+
+/// Read the post-processing pipeline of the route to the target from the body of a walk
+///
+/// Only the JSON bodies have a pipeline, next to the fields of the matrix,
+/// and the bodies that aren't JSON are left for the matrix to reject.
+///
+/// # Arguments
+///
+/// * `body` - The body of the request
+/// * `encoding` - The encoding of the request
+///
+/// # Returns
+///
+/// * `std::result::Result<RoutePipeline, String>` - The pipeline, empty when the body has none, or the reason why it isn't valid
+///
+pub fn route_pipeline(body: &[u8], encoding: Encoding) -> std::result::Result<RoutePipeline, String> {
+    if encoding != Encoding::Json {
+        return Ok(RoutePipeline::default());
+    }
+
+    match serde_json::from_slice::<RoutePipeline>(body) {
+        Ok(pipeline) => Ok(pipeline),
+        Err(err) if err.is_data() => Err(format!("The pipeline can't be parsed: {}", err)),
+        Err(_) => Ok(RoutePipeline::default())
+    }
+}
+
+/// Build a response with a result and the route left by its post-processing pipeline
+///
+/// The routes only fit in the JSON bodies, so the response is always JSON,
+/// whatever the encoding asked for.
+///
+/// # Arguments
+///
+/// * `builder` - The response builder with the status already set
+/// * `result` - The result to send
+/// * `processed` - The route left by the pipeline
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+pub fn processed_response(mut builder: HttpResponseBuilder, result: Result, processed: ProcessedRoute) -> HttpResponse {
+    let mut body = json_body(result);
+    body["postprocessed"] = json!(processed);
+
+    builder.json(body)
+}
+
+/// Build the JSON body of a result, leaving out the fields it doesn't have
+///
+/// # Arguments
+///
+/// * `result` - The result to send
+///
+/// # Returns
+///
+/// * `serde_json::Value` - The body
+///
+fn json_body<T: Real>(result: Result<T>) -> serde_json::Value {
+    let mut body = json!({ "status": result.status });
+
+    if let Some(path) = result.path {
+        body["path"] = json!(path);
+    }
+
+    if let Some(message) = result.message {
+        body["message"] = json!(message);
+    }
+
+    if let Some(code) = result.code {
+        body["code"] = json!(code);
+    }
+
+    if let Some(run_id) = result.run_id {
+        body["run_id"] = json!(run_id);
+    }
+
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::to_bytes, test::TestRequest};
+    use super::*;
+
+    #[test]
+    fn test_encoding_negotiation() {
+        // Prepare the request
+        let req = TestRequest::post()
+            .insert_header((header::CONTENT_TYPE, BINCODE_MEDIA_TYPE))
+            .insert_header((header::ACCEPT, "application/x-protobuf, application/json;q=0.9"))
+            .to_http_request();
+
+        // Get the result
+        let request_encoding = Encoding::of_request(&req).unwrap();
+        let response_encoding = Encoding::of_response(&req, request_encoding);
+
+        // Check if the result is correct
+        assert_eq!(request_encoding, Encoding::Bincode);
+        assert_eq!(response_encoding, Encoding::Protobuf);
+        assert!(!accepts_ndjson(&req));
+        assert!(accepts_ndjson(&TestRequest::post().insert_header((header::ACCEPT, "application/json;q=0.5, application/x-ndjson")).to_http_request()));
+    }
+
+    #[actix_web::test]
+    async fn test_csv_response() {
+        // Prepare a request accepting comma separated values and the body of a path
+        let req = TestRequest::post().insert_header((header::ACCEPT, "text/csv")).to_http_request();
+        let body = json!({ "status": "ok", "path": [[0, 0.0], [0, 1.5], [1, 3.25]], "run_id": "run" });
+
+        // Get the result
+        let response = path_response(&req, HttpResponse::Ok(), body.clone());
+        let content_type = response.headers().get(header::CONTENT_TYPE).unwrap().to_str().unwrap().to_string();
+        let csv = to_bytes(response.into_body()).await.unwrap();
+        let json = path_response(&TestRequest::post().to_http_request(), HttpResponse::Ok(), body);
+
+        // Check if the result is correct
+        assert_eq!(content_type, CSV_MEDIA_TYPE);
+        assert_eq!(csv, "node,predecessor,distance\n0,0,0.0\n1,0,1.5\n2,1,3.25\n");
+        assert_eq!(json.headers().get(header::CONTENT_TYPE).unwrap(), JSON_MEDIA_TYPE);
+        assert_eq!(Encoding::of_response(&req, Encoding::Bincode), Encoding::Csv);
+        assert_eq!(Encoding::from_media_type(CSV_MEDIA_TYPE), None);
+    }
+
+    #[test]
+    fn test_decode_missing_edges() {
+        // Prepare a matrix with zero weight edges and explicit missing ones, and one with an unknown weight
+        let body = br#"{"width": 2, "height": 2, "data": [0, null, "inf", 0], "zero_edges": true}"#;
+        let unknown = br#"{"width": 2, "height": 2, "data": [0, "none", 1, 0]}"#;
+
+        // Get the result
+        let matrix = Encoding::Json.decode_matrix(body).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(matrix.data, vec![0.0, f32::MAX, f32::MAX, 0.0]);
+        assert_eq!(matrix.edge(0, 0), Some(0.0));
+        assert_eq!(matrix.edge(1, 0), None);
+        assert!(Encoding::Json.decode_matrix(unknown).is_err());
+    }
+
+    #[test]
+    fn test_decode_data_layers() {
+        // Prepare the distance and the toll of the edges, as JSON and as bincode
+        let body = br#"{"width": 2, "height": 2, "data_layers": [[0, 3, 2, 0], [0, 1, null, 0]], "coefficients": [1.0, 2.0]}"#;
+        let unbalanced = br#"{"width": 2, "height": 2, "data_layers": [[0, 3, 2, 0]], "coefficients": [1.0, 2.0]}"#;
+        let layered: Matrix = Matrix { data_layers: vec![vec![0.0, 3.0, 2.0, 0.0]], coefficients: vec![0.5], ..Matrix::new(2, 2, Vec::new()) };
+
+        // Get the result
+        let matrix = Encoding::Json.decode_matrix(body).unwrap();
+        let binary = Encoding::Bincode.decode_matrix(&bincode::serialize(&layered).unwrap()).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(matrix.data, vec![f32::MAX, 5.0, f32::MAX, f32::MAX]);
+        assert!(matrix.data_layers.is_empty());
+        assert_eq!(binary.data, vec![f32::MAX, 1.5, 1.0, f32::MAX]);
+        assert!(Encoding::Json.decode_matrix(unbalanced).unwrap_err().contains("1 data layers but 2 coefficients"));
+    }
+}
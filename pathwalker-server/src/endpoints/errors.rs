@@ -0,0 +1,222 @@
+use std::fmt::{self, Display};
+
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use serde_json::{json, Value};
+
+use crate::services::opencl;
+
+/// The statuses of the OpenCL calls that have run out of device or host memory
+const OUT_OF_MEMORY_STATUSES: [&str; 3] = ["CL_MEM_OBJECT_ALLOCATION_FAILURE", "CL_OUT_OF_RESOURCES", "CL_OUT_OF_HOST_MEMORY"];
+
+/// Why a request has failed
+///
+/// Every failure is answered with the status code of its kind and a JSON
+/// body with the `status`, a stable machine readable `code` and the
+/// `message`, so the clients can tell the failures apart without parsing
+/// the messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathWalkerError {
+    /// The request isn't valid, answered with `400 Bad Request`
+    Validation(String),
+    /// The request has no valid API key, answered with `401 Unauthorized`
+    Unauthorized(String),
+    /// The request isn't allowed, like a feature disabled by the license, answered with `403 Forbidden`
+    Forbidden(String),
+    /// The resource of the request doesn't exist, like a graph or a session, answered with `404 Not Found`
+    NotFound(String),
+    /// The request clashes with the state of the server, like a soak test already running, answered with `409 Conflict`
+    Conflict(String),
+    /// The body of the request is larger than the limit, answered with `413 Payload Too Large`
+    PayloadTooLarge(String),
+    /// The content type of the request isn't supported, answered with `415 Unsupported Media Type`
+    UnsupportedMediaType(String),
+    /// The request is valid but has no answer, like a goal that can't be reached, answered with `422 Unprocessable Entity`
+    Unprocessable(String),
+    /// The device can't run the request, like a double precision walk without `cl_khr_fp64`, answered with `501 Not Implemented`
+    Unsupported(String),
+    /// The server can't take the request now, like a device too hot or not ready, answered with `503 Service Unavailable`
+    Unavailable(String),
+    /// The device can't be opened or its kernels have failed, answered with `502 Bad Gateway`
+    Device(String),
+    /// The graph doesn't fit in the memory of the device, answered with `507 Insufficient Storage`
+    OutOfMemory(String),
+    /// The computation has run over its timeout, answered with `504 Gateway Timeout`
+    Timeout(String),
+    /// The API key has used its monthly quota, answered with `429 Too Many Requests`
+    QuotaExceeded(String),
+    /// The client has sent more requests than its rate or in-flight limits, answered with `429 Too Many Requests`
+    RateLimited(String),
+    /// The server has failed, like a computation that has panicked, answered with `500 Internal Server Error`
+    Internal(String),
+}
+
+impl PathWalkerError {
+    /// Returns the machine readable code of the error, which never changes between releases
+    pub fn code(&self) -> &'static str {
+        match self {
+            PathWalkerError::Validation(_) => "validation_error",
+            PathWalkerError::Unauthorized(_) => "unauthorized",
+            PathWalkerError::Forbidden(_) => "forbidden",
+            PathWalkerError::NotFound(_) => "not_found",
+            PathWalkerError::Conflict(_) => "conflict",
+            PathWalkerError::PayloadTooLarge(_) => "payload_too_large",
+            PathWalkerError::UnsupportedMediaType(_) => "unsupported_media_type",
+            PathWalkerError::Unprocessable(_) => "unprocessable",
+            PathWalkerError::Unsupported(_) => "unsupported",
+            PathWalkerError::Unavailable(_) => "unavailable",
+            PathWalkerError::Device(_) => "device_error",
+            PathWalkerError::OutOfMemory(_) => "out_of_memory",
+            PathWalkerError::Timeout(_) => "timeout",
+            PathWalkerError::QuotaExceeded(_) => "quota_exceeded",
+            PathWalkerError::RateLimited(_) => "rate_limited",
+            PathWalkerError::Internal(_) => "internal_error"
+        }
+    }
+
+    /// Returns the message of the error
+    pub fn message(&self) -> &str {
+        match self {
+            PathWalkerError::Validation(message)
+            | PathWalkerError::Unauthorized(message)
+            | PathWalkerError::Forbidden(message)
+            | PathWalkerError::NotFound(message)
+            | PathWalkerError::Conflict(message)
+            | PathWalkerError::PayloadTooLarge(message)
+            | PathWalkerError::UnsupportedMediaType(message)
+            | PathWalkerError::Unprocessable(message)
+            | PathWalkerError::Unsupported(message)
+            | PathWalkerError::Unavailable(message)
+            | PathWalkerError::Device(message)
+            | PathWalkerError::OutOfMemory(message)
+            | PathWalkerError::Timeout(message)
+            | PathWalkerError::QuotaExceeded(message)
+            | PathWalkerError::RateLimited(message)
+            | PathWalkerError::Internal(message) => message
+        }
+    }
+
+    /// Build the response of the error
+    ///
+    /// # Returns
+    ///
+    /// * `HttpResponse` - The response, with the status code of the kind of the error
+    ///
+    pub fn response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(self.body())
+    }
+
+    /// Build the response of the error with more fields in its body
+    ///
+    /// # Arguments
+    ///
+    /// * `details` - An object with the fields added to the body, like the invalid cells of a matrix
+    ///
+    /// # Returns
+    ///
+    /// * `HttpResponse` - The response
+    ///
+    pub fn response_with(&self, details: Value) -> HttpResponse {
+        let mut body = self.body();
+
+        if let (Some(body), Value::Object(details)) = (body.as_object_mut(), details) {
+            body.extend(details);
+        }
+
+        HttpResponse::build(self.status_code()).json(body)
+    }
+
+    /// Returns the body of the response of the error, also sent as the error lines of the streams
+    pub fn body(&self) -> Value {
+        json!({
+            "status": "error",
+            "code": self.code(),
+            "message": self.message()
+        })
+    }
+}
+
+impl Display for PathWalkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl ResponseError for PathWalkerError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            PathWalkerError::Validation(_) => StatusCode::BAD_REQUEST,
+            PathWalkerError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            PathWalkerError::Forbidden(_) => StatusCode::FORBIDDEN,
+            PathWalkerError::NotFound(_) => StatusCode::NOT_FOUND,
+            PathWalkerError::Conflict(_) => StatusCode::CONFLICT,
+            PathWalkerError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            PathWalkerError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            PathWalkerError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            PathWalkerError::Unsupported(_) => StatusCode::NOT_IMPLEMENTED,
+            PathWalkerError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            PathWalkerError::Device(_) => StatusCode::BAD_GATEWAY,
+            PathWalkerError::OutOfMemory(_) => StatusCode::INSUFFICIENT_STORAGE,
+            PathWalkerError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            PathWalkerError::QuotaExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+            PathWalkerError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            PathWalkerError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        self.response()
+    }
+}
+
+impl From<opencl::Error> for PathWalkerError {
+    fn from(err: opencl::Error) -> PathWalkerError {
+        // The allocations that fail in the device are told apart by the status of the OpenCL call
+        let out_of_memory = err.api_status().is_some_and(|status| OUT_OF_MEMORY_STATUSES.contains(&status.to_string().as_str()));
+
+        if out_of_memory {
+            PathWalkerError::OutOfMemory(err.to_string())
+        } else {
+            PathWalkerError::Device(err.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_error_response() {
+        // Prepare an error of every kind
+        let errors = [
+            PathWalkerError::Validation("The matrix isn't square".to_string()),
+            PathWalkerError::Unauthorized("The API key isn't valid".to_string()),
+            PathWalkerError::Forbidden("The feature is disabled".to_string()),
+            PathWalkerError::NotFound("The graph doesn't exist".to_string()),
+            PathWalkerError::Conflict("A soak test is already running".to_string()),
+            PathWalkerError::PayloadTooLarge("The request body is too large".to_string()),
+            PathWalkerError::UnsupportedMediaType("The content type is not supported".to_string()),
+            PathWalkerError::Unprocessable("The goal can't be reached".to_string()),
+            PathWalkerError::Unsupported("The device lacks double precision".to_string()),
+            PathWalkerError::Unavailable("The device is too hot".to_string()),
+            PathWalkerError::Device("The kernels have failed".to_string()),
+            PathWalkerError::OutOfMemory("The matrix doesn't fit".to_string()),
+            PathWalkerError::Timeout("The walk has timed out".to_string()),
+            PathWalkerError::QuotaExceeded("The quota has been used".to_string()),
+            PathWalkerError::RateLimited("Too many requests".to_string()),
+            PathWalkerError::Internal("The walk has panicked".to_string())
+        ];
+
+        // Get the result
+        let statuses = errors.clone().map(|error| error.response().status().as_u16());
+        let response = errors[0].response_with(json!({ "errors": [] }));
+        let body: Value = serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(statuses, [400, 401, 403, 404, 409, 413, 415, 422, 501, 503, 502, 507, 504, 429, 429, 500]);
+        assert_eq!(errors.map(|error| error.code()), ["validation_error", "unauthorized", "forbidden", "not_found", "conflict", "payload_too_large", "unsupported_media_type", "unprocessable", "unsupported", "unavailable", "device_error", "out_of_memory", "timeout", "quota_exceeded", "rate_limited", "internal_error"]);
+        assert_eq!(body, json!({ "status": "error", "code": "validation_error", "message": "The matrix isn't square", "errors": [] }));
+        assert_eq!(PathWalkerError::from(opencl::Error::from("The device has failed".to_string())).code(), "device_error");
+    }
+}
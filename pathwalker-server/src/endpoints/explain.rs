@@ -0,0 +1,102 @@
+use actix_web::{post, HttpResponse, web::Json};
+use log::info;
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::models::{ExplainRequest, HopExplanation, ValidationRules};
+use crate::services::entitlement::Feature;
+use crate::services::explain::explain_path;
+use super::{entitlement_rejection, invalid_cells, validate_static_matrix, ENTITLEMENTS};
+
+/// The explain endpoint
+///
+/// Exposes a endpoint that receives a matrix with a path already computed for it
+/// and returns, for every hop of the path, the candidate edges and their costs
+///
+/// # Arguments
+///
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/explain")]
+pub async fn explain_endpoint(item: Json<ExplainRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Explain) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and deserialize it
+    let request: ExplainRequest = item.into_inner();
+
+    // Validate the matrix and the path before explaining it
+    let error = validate_static_matrix(&request.matrix).or_else(|| {
+        if request.path.len() != request.matrix.width {
+            Some("The path size and matrix dimensions are not the same")
+        } else if request.path.iter().any(|hop| hop.0 < 0 || hop.0 as usize >= request.matrix.width) {
+            Some("The path references vertices outside of the matrix")
+        } else {
+            None
+        }
+    });
+
+    if let Some(message) = error {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    if let Some(response) = invalid_cells(&request.matrix, ValidationRules::default()) {
+        return response;
+    }
+
+    // Print the request
+    info!("Received explain request for path: {:?}", request.path);
+
+    // Explain the path and return it
+    let hops: Vec<HopExplanation> = explain_path(&request.matrix, &request.path).iter()
+        .map(|hop| hop.round(request.matrix.round))
+        .collect();
+
+    HttpResponse::Ok().json(json!({ "status": "ok", "hops": hops }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use crate::models::{Matrix, PathResult, Result};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_explain_endpoint_invalid_path() {
+        // Prepare the request
+        let request = ExplainRequest {
+            matrix: Matrix::new(2, 2, vec![
+                00.0, 01.0,
+                01.0, 00.0
+            ]),
+            path: vec![PathResult(0, 0.0)]
+        };
+
+        // Prepare the expected result
+        let expected = "The path size and matrix dimensions are not the same";
+
+        // Get the result
+        let app = init_service(
+            App::new().service(explain_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/explain")
+            .set_json(&request)
+            .to_request();
+
+        // Get the response
+        let resp: Result = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "error");
+        assert_eq!(resp.message.unwrap(), expected);
+    }
+}
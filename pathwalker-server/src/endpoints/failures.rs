@@ -0,0 +1,111 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{post, HttpResponse, web::Json};
+use log::{error, info};
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::models::FailureRequest;
+use crate::services::entitlement::Feature;
+use crate::services::failures::{simulate_failures, validate_failures};
+use crate::utils::{round_decimals_f64, spawn_blocking};
+use super::{computation_cancellation, computation_failure, entitlement_rejection, invalid_cells, thermal_rejection, walk_rejection, WalkRejection, ENTITLEMENTS, THERMAL, with_device};
+
+/// The failure simulation endpoint
+///
+/// Exposes a endpoint that receives a graph with the probabilities of its
+/// edges and vertices failing, and simulates many trials of random failures
+/// in the device, returning how the connectivity and the distances between
+/// its vertices degrade, for the resilience analysis of networks
+///
+/// # Arguments
+///
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/simulate/failures")]
+pub async fn failures_endpoint(item: Json<FailureRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Failures) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and validate it before simulating the failures
+    let mut request: FailureRequest = item.into_inner();
+
+    if let Some(message) = validate_failures(&request) {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    if let Some(response) = invalid_cells(&request.matrix, Default::default()) {
+        return response;
+    }
+
+    // Reject the job right away while the device is too hot to admit it
+    let permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(state) => return thermal_rejection(&state)
+    };
+
+    // Choose a seed when not given, so the simulation can be repeated
+    let seed = *request.seed.get_or_insert_with(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64));
+    info!("Received failure simulation of {} trials over {} vertices with seed {}", request.trials, request.matrix.width, seed);
+
+    // Simulate the trials outside the async executor
+    let round = request.matrix.round;
+    let cancellation = computation_cancellation();
+    let (_cancel, simulation_cancellation) = (cancellation.on_drop(), cancellation.clone());
+    let result = spawn_blocking(move || {
+        let _permit = permit;
+        with_device(|walker| simulate_failures(walker, &request, &simulation_cancellation))
+    }).await.map_err(computation_failure);
+
+    // Return the statistics of the simulation
+    match result {
+        Ok(Ok(stats)) => HttpResponse::Ok().json(json!({
+            "status": "ok",
+            "seed": seed,
+            "trials": stats.trials,
+            "connected_pairs": stats.connected_pairs,
+            "mean_distance": round_decimals_f64(stats.mean_distance, round),
+            "connectivity": stats.connectivity,
+            "stretch": stats.stretch,
+            "disconnected_trials": stats.disconnected_trials
+        })),
+        Ok(Err(err)) if cancellation.is_expired() => walk_rejection(&WalkRejection::Timeout(err.to_string())),
+        Ok(Err(err)) => {
+            error!("The failure simulation has failed: {}", err);
+            PathWalkerError::from(err).response()
+        },
+        Err(message) => PathWalkerError::Device(message).response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_failures_endpoint_invalid_request() {
+        // Prepare a simulation with a probability over one, and one walking too many sources
+        let probability = json!({ "width": 2, "height": 2, "data": [0, 1, 1, 0], "edge_failure": 1.5 });
+        let oversized = json!({ "width": 2, "height": 2, "data": [0, 1, 1, 0], "trials": 40000 });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(failures_endpoint)
+        ).await;
+
+        // Get the responses
+        let probability: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/simulate/failures").set_json(&probability).to_request()).await;
+        let oversized: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/simulate/failures").set_json(&oversized).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(probability["message"], "The failure probabilities must be between zero and one");
+        assert_eq!(oversized["message"], "The trials can't walk the graph from more than 65536 sources in total");
+    }
+}
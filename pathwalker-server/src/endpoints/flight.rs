@@ -0,0 +1,273 @@
+use std::sync::Arc;
+
+use arrow_array::{Array, Float32Array, Int32Array, ListArray, RecordBatch, UInt32Array};
+use arrow_flight::decode::FlightRecordBatchStream;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightInfo, HandshakeRequest,
+    HandshakeResponse, PollInfo, PutResult, SchemaResult, Ticket
+};
+use arrow_schema::{DataType, Field, Schema};
+use futures_util::stream::{BoxStream, StreamExt, TryStreamExt};
+use log::{error, info, warn};
+use tonic::service::interceptor::InterceptedService;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::models::{Matrix, PathResult, ValidationRules};
+use crate::services::usage::{UsageMeter, UsageStats};
+use crate::utils::spawn_blocking;
+use super::auth::{ApiKeyName, GrpcApiKey};
+use super::usage::key_name;
+use super::{cached_sortest_path, computation_cancellation, validate_matrix};
+
+/// The name of the column with the rows of the matrix
+pub const WEIGHTS_COLUMN: &str = "weights";
+
+/// Convert a record batch into a matrix
+///
+/// Every row of the batch is a row of the matrix, with its weights in the
+/// `weights` list column.
+///
+/// # Arguments
+///
+/// * `batch` - The record batch
+///
+/// # Returns
+///
+/// * `Result<Matrix, String>` - The matrix, or the reason why the batch isn't a matrix
+///
+pub fn batch_to_matrix(batch: &RecordBatch) -> Result<Matrix, String> {
+    let rows = batch.column_by_name(WEIGHTS_COLUMN)
+        .and_then(|column| column.as_any().downcast_ref::<ListArray>())
+        .ok_or("The batch has no weights list column")?;
+    let weights = rows.values().as_any().downcast_ref::<Float32Array>()
+        .ok_or("The weights are not 32 bits floats")?;
+
+    // Check that all the rows have the same length
+    let width = if rows.is_empty() { 0 } else { rows.value_length(0) as usize };
+
+    if (0..rows.len()).any(|row| rows.value_length(row) as usize != width) {
+        return Err("The rows of the matrix have different lengths".to_string());
+    }
+
+    // Keep only the weights of the rows, in case the batch is a slice of a bigger one
+    let offsets = rows.value_offsets();
+    let data = weights.values()[offsets[0] as usize..offsets[rows.len()] as usize].to_vec();
+
+    Ok(Matrix::new(width, rows.len(), data))
+}
+
+/// Convert the path of a walk into a distance table
+///
+/// # Arguments
+///
+/// * `path` - The path of the walk
+///
+/// # Returns
+///
+/// * `RecordBatch` - The record batch, with the predecessor and distance of every vertex
+///
+pub fn path_to_batch(path: &[PathResult]) -> RecordBatch {
+    let schema = Schema::new(vec![
+        Field::new("vertex", DataType::UInt32, false),
+        Field::new("predecessor", DataType::Int32, false),
+        Field::new("distance", DataType::Float32, false)
+    ]);
+
+    RecordBatch::try_new(Arc::new(schema), vec![
+        Arc::new(UInt32Array::from_iter_values(0..path.len() as u32)),
+        Arc::new(Int32Array::from_iter_values(path.iter().map(|hop| hop.0))),
+        Arc::new(Float32Array::from_iter_values(path.iter().map(|hop| hop.1)))
+    ]).unwrap()
+}
+
+/// Compute the distance table of a matrix received as a record batch
+///
+/// # Arguments
+///
+/// * `batch` - The record batch with the matrix
+/// * `meter` - The meter of the exchange, accounting the walks to its API key
+///
+/// # Returns
+///
+/// * `Result<RecordBatch, FlightError>` - The distance table
+///
+async fn exchange_batch(batch: RecordBatch, meter: UsageMeter) -> Result<RecordBatch, FlightError> {
+    // Read and validate the matrix
+    let matrix = batch_to_matrix(&batch).map_err(Status::invalid_argument)?;
+
+    if let Some(message) = validate_matrix(&matrix) {
+        return Err(Status::invalid_argument(message).into());
+    }
+
+    if let Some(error) = matrix.validate(ValidationRules::default()).errors.first() {
+        return Err(Status::invalid_argument(error.to_string()).into());
+    }
+
+    // Get the path of the walk outside the async executor
+    let cancellation = computation_cancellation();
+    let (_cancel, walk) = (cancellation.on_drop(), cancellation.clone());
+
+    match meter.scope(async move { spawn_blocking(move || cached_sortest_path(&matrix, &walk)).await }).await {
+        Ok(Ok((Ok(path), _))) => Ok(path_to_batch(&path)),
+        Ok(Ok((Err(err), _))) if cancellation.is_expired() => Err(Status::deadline_exceeded(err.to_string()).into()),
+        Ok(Ok((Err(err), _))) => Err(Status::internal(err.to_string()).into()),
+        Ok(Err(_)) => Err(Status::unavailable("The device is too hot to admit new jobs, retry later").into()),
+        Err(err) => {
+            error!("The Arrow Flight computation has panicked: {}", err);
+            Err(Status::internal("The computation has been aborted").into())
+        }
+    }
+}
+
+/// The Arrow Flight service of the sortest path
+///
+/// Exchanges matrices for distance tables as Arrow record batches, sharing
+/// the services layer with the REST and gRPC interfaces, and accounts the
+/// exchanges to the usage of their API key along with the REST requests
+///
+/// # Fields
+///
+/// * `usage` - The usage of every API key
+///
+pub struct PathWalkerFlightService {
+    usage: Arc<UsageStats>,
+}
+
+impl PathWalkerFlightService {
+    /// Create the server of the Arrow Flight service
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - The usage of every API key, shared with the REST server
+    /// * `auth` - The API key authentication of the calls
+    ///
+    /// # Returns
+    ///
+    /// * `InterceptedService<FlightServiceServer<PathWalkerFlightService>, GrpcApiKey>` - The server
+    ///
+    pub fn server(usage: Arc<UsageStats>, auth: GrpcApiKey) -> InterceptedService<FlightServiceServer<PathWalkerFlightService>, GrpcApiKey> {
+        FlightServiceServer::with_interceptor(PathWalkerFlightService { usage }, auth)
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for PathWalkerFlightService {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoActionStream = BoxStream<'static, Result<arrow_flight::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+
+    /// Walk the matrices received as record batches
+    ///
+    /// Every record batch of the request is a matrix, answered with a record
+    /// batch holding its distance table, in the same order.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The stream of record batches with the matrices
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Response<DoExchangeStream>, Status>` - The stream of distance tables
+    ///
+    async fn do_exchange(&self, request: Request<Streaming<FlightData>>) -> Result<Response<Self::DoExchangeStream>, Status> {
+        // Print the request
+        info!("Received Arrow Flight exchange");
+
+        // Account the exchange to its API key, rejecting the keys over their quota
+        let key = key_name(request.extensions().get::<ApiKeyName>());
+        let meter = UsageMeter::admit(self.usage.clone(), &key).inspect_err(|_| {
+            warn!("Rejected Arrow Flight exchange from the API key {} over its monthly quota", key);
+        }).map_err(Status::resource_exhausted)?;
+
+        // Decode the matrices and walk them in order
+        let batches = FlightRecordBatchStream::new_from_flight_data(request.into_inner().map_err(FlightError::from));
+        let tables = batches.and_then(move |batch| exchange_batch(batch, meter.clone()));
+
+        // Encode the distance tables
+        let output = FlightDataEncoderBuilder::new().build(tables).map_err(Status::from);
+
+        Ok(Response::new(output.boxed()))
+    }
+
+    async fn handshake(&self, _: Request<Streaming<HandshakeRequest>>) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("The handshake is not needed"))
+    }
+
+    async fn list_flights(&self, _: Request<Criteria>) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("Use do_exchange to walk matrices"))
+    }
+
+    async fn get_flight_info(&self, _: Request<FlightDescriptor>) -> Result<Response<FlightInfo>, Status> {
+        Err(Status::unimplemented("Use do_exchange to walk matrices"))
+    }
+
+    async fn poll_flight_info(&self, _: Request<FlightDescriptor>) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("Use do_exchange to walk matrices"))
+    }
+
+    async fn get_schema(&self, _: Request<FlightDescriptor>) -> Result<Response<SchemaResult>, Status> {
+        Err(Status::unimplemented("Use do_exchange to walk matrices"))
+    }
+
+    async fn do_get(&self, _: Request<Ticket>) -> Result<Response<Self::DoGetStream>, Status> {
+        Err(Status::unimplemented("Use do_exchange to walk matrices"))
+    }
+
+    async fn do_put(&self, _: Request<Streaming<FlightData>>) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("Use do_exchange to walk matrices"))
+    }
+
+    async fn do_action(&self, _: Request<Action>) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("There are no actions"))
+    }
+
+    async fn list_actions(&self, _: Request<Empty>) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(futures_util::stream::empty().boxed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::builder::{Float32Builder, ListBuilder};
+    use super::*;
+
+    /// Convert a matrix into a record batch, as the clients send it
+    fn matrix_to_batch(matrix: &Matrix) -> RecordBatch {
+        let mut rows = ListBuilder::new(Float32Builder::new()).with_field(Field::new("item", DataType::Float32, false));
+
+        for row in matrix.data.chunks(matrix.width.max(1)) {
+            rows.values().append_slice(row);
+            rows.append(true);
+        }
+
+        RecordBatch::try_from_iter([(WEIGHTS_COLUMN, Arc::new(rows.finish()) as Arc<dyn Array>)]).unwrap()
+    }
+
+    #[test]
+    fn test_matrix_batch_round_trip() {
+        // Prepare the matrix
+        let matrix = Matrix::new(3, 3, vec![
+            0.0, 4.0, 2.0,
+            4.0, 0.0, 1.0,
+            2.0, 1.0, 0.0
+        ]);
+
+        // Get the result
+        let batch = matrix_to_batch(&matrix);
+        let table = path_to_batch(&[PathResult(0, 0.0), PathResult(2, 3.0), PathResult(0, 2.0)]);
+
+        // Check if the result is correct
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(batch_to_matrix(&batch).unwrap(), matrix);
+        assert_eq!(batch_to_matrix(&batch.slice(1, 2)).unwrap().data, vec![4.0, 0.0, 1.0, 2.0, 1.0, 0.0]);
+        assert_eq!(table.num_rows(), 3);
+        assert_eq!(table.schema().field(2).name(), "distance");
+    }
+}
@@ -0,0 +1,90 @@
+use actix_web::{post, HttpResponse, web::Json};
+use log::{error, info};
+
+use super::errors::PathWalkerError;
+use crate::models::FlowFieldRequest;
+use crate::services::entitlement::Feature;
+use crate::services::flowfield::{encode_flow_field, flow_field, validate_flow_field};
+use crate::utils::spawn_blocking;
+use super::encoding::BINCODE_MEDIA_TYPE;
+use super::{entitlement_rejection, ENTITLEMENTS};
+
+/// The flow field endpoint
+///
+/// Exposes a endpoint that receives a grid or a graph with a goal, and
+/// returns the best next step towards the goal from every cell or vertex,
+/// so crowds of agents can be steered without searching a path for each
+/// of them. The field is returned in its compact binary layout
+///
+/// # Arguments
+///
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/flowfield")]
+pub async fn flow_field_endpoint(item: Json<FlowFieldRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::FlowField) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and validate it before searching the field
+    let request: FlowFieldRequest = item.into_inner();
+
+    if let Some(message) = validate_flow_field(&request) {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    // Print the request
+    match &request {
+        FlowFieldRequest::Grid(grid) => info!("Received {:?} flow field request of {}x{} cells to {:?}", grid.topology, grid.width, grid.height, grid.goal),
+        FlowFieldRequest::Graph(graph) => info!("Received flow field request of {} vertices to {}", graph.matrix.width, graph.target)
+    }
+
+    // Search the field outside the async executor
+    match spawn_blocking(move || encode_flow_field(&flow_field(&request))).await {
+        Ok(field) => HttpResponse::Ok().content_type(BINCODE_MEDIA_TYPE).body(field),
+        Err(err) => {
+            error!("The flow field search has panicked: {}", err);
+            PathWalkerError::Internal("The computation has been aborted".to_string()).response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body, call_service, init_service, TestRequest}, App};
+    use serde_json::json;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_flow_field_endpoint() {
+        // Prepare a hex corridor heading to its last cell, and a request with the goal outside
+        let request = json!({
+            "grid": {
+                "topology": "hex",
+                "width": 3,
+                "height": 1,
+                "costs": [1, 1, 1],
+                "goal": [2, 0]
+            }
+        });
+        let outside = json!({ "graph": { "width": 1, "height": 1, "data": [0], "target": 1 } });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(flow_field_endpoint)
+        ).await;
+
+        // Get the responses
+        let field = call_and_read_body(&app, TestRequest::post().uri("/flowfield").set_json(&request).to_request()).await;
+        let rejected = call_service(&app, TestRequest::post().uri("/flowfield").set_json(&outside).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(field.as_ref(), [3, 0, 0, 0, 1, 0, 0, 0, 0, 0, 254]);
+        assert_eq!(rejected.status(), 400);
+    }
+}
@@ -0,0 +1,576 @@
+use actix_web::{cookie::Cookie, delete, get, patch, post, put, HttpRequest, HttpResponse, web::{Json, Path, Query}};
+use actix_web::http::header::HeaderValue;
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::encoding::path_response;
+use super::errors::PathWalkerError;
+use crate::models::{EdgesPatch, Matrix, PathResult, ValidationRules};
+use crate::services::affinity::Residency;
+use crate::services::entitlement::Feature;
+use crate::services::export::export_graph;
+use crate::services::import::GraphFormat;
+use crate::services::metrics::observe_affinity;
+use crate::services::dispatch::is_bidirectional;
+use crate::services::run_id::run_id;
+use crate::services::sortest_path::route;
+use crate::services::units::{display_unit, DisplayUnit};
+use crate::utils::spawn_blocking;
+use super::{computation_cancellation, computation_failure, entitlement_rejection, invalid_cells, memory_rejection, thermal_rejection, validate_static_matrix, AFFINITY, AFFINITY_COOKIE, AFFINITY_HEADER, CACHE_HEADER, ENTITLEMENTS, STORE, THERMAL, device_failure, device_walker, with_device, walk_rejection, WalkRejection};
+
+/// The query parameters of the stored graph queries
+#[derive(Debug, Deserialize)]
+pub struct GraphQuery {
+    pub source: Option<usize>,
+    pub target: Option<usize>,
+    pub round: Option<u32>,
+    pub unit: Option<String>,
+    pub bidirectional: Option<bool>,
+}
+
+/// The query parameters of the graph export endpoint
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub format: String,
+}
+
+/// Build the response for a graph that doesn't exist
+pub fn graph_not_found(name: &str) -> HttpResponse {
+    PathWalkerError::NotFound(format!("The graph {} doesn't exist", name)).response()
+}
+
+/// Parse the unit requested for the costs of a graph query
+///
+/// # Arguments
+///
+/// * `name` - The name of the unit, if requested
+///
+/// # Returns
+///
+/// * `Result<Option<DisplayUnit>, String>` - The unit, or the reason why it isn't supported
+///
+pub fn requested_unit(name: Option<&str>) -> Result<Option<DisplayUnit>, String> {
+    match name {
+        None => Ok(None),
+        Some(name) => DisplayUnit::parse(name).map(Some).ok_or_else(|| format!("The unit {} is not supported, it must be m, km, s, min, h or custom", name))
+    }
+}
+
+/// Fill the costs of the walk of a graph in a response, in the unit they are returned in
+///
+/// # Arguments
+///
+/// * `body` - The response, with the status and the run id
+/// * `path` - The path of the walk
+/// * `source` - The vertex the walk started from
+/// * `target` - The vertex whose route and distance are returned, if any
+/// * `zero_edges` - If the matrix of the walk has zero weight edges
+/// * `unit` - The unit the costs are returned in, if any
+/// * `round` - The decimals of the costs
+///
+pub fn fill_costs(body: &mut serde_json::Value, path: &[PathResult], source: usize, target: Option<usize>, zero_edges: bool, unit: Option<DisplayUnit>, round: Option<u32>) {
+    let cost = |hop: &PathResult| PathResult(hop.0, unit.map_or(hop.1, |unit| unit.convert(hop.1))).round(round);
+
+    body["path"] = json!(path.iter().map(cost).collect::<Vec<PathResult>>());
+
+    if let Some(target) = target {
+        body["route"] = json!(route(path, source, target, zero_edges));
+        body["distance"] = json!(cost(&path[target]).1);
+    }
+
+    if let Some(unit) = unit {
+        body["unit"] = json!(unit.name);
+    }
+}
+
+/// Build the response for a graph store that can't be read or written
+pub fn storage_error(message: String) -> HttpResponse {
+    PathWalkerError::Internal(message).response()
+}
+
+/// Pin a graph resident in the device of this replica in the response
+///
+/// The token is sent as a header, and as a cookie scoped to the path of
+/// the graph, so every graph can be routed to a different replica.
+///
+/// # Arguments
+///
+/// * `req` - The request to the graph
+/// * `response` - The response
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response with the affinity token
+///
+fn with_affinity(req: &HttpRequest, mut response: HttpResponse) -> HttpResponse {
+    let token = AFFINITY.token();
+    let cookie = Cookie::build(AFFINITY_COOKIE, token)
+        .path(req.path().trim_end_matches("/sortest").trim_end_matches("/chunks").trim_end_matches("/edges").to_string())
+        .http_only(true)
+        .finish();
+
+    if let Ok(value) = HeaderValue::from_str(token) {
+        response.headers_mut().insert(AFFINITY_HEADER.parse().unwrap(), value);
+    }
+
+    let _ = response.add_cookie(&cookie);
+    response
+}
+
+/// The graph upload endpoint
+///
+/// Exposes a endpoint that receives a matrix and keeps it resident in the device
+/// under the given name, replacing the graph with the same name if any
+///
+/// # Arguments
+///
+/// * `name` - The name of the graph
+/// * `item` - The matrix of the graph
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[put("/graphs/{name}")]
+pub async fn put_graph_endpoint(req: HttpRequest, name: Path<String>, item: Json<Matrix>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs) {
+        return entitlement_rejection(message);
+    }
+
+    store_graph(&req, name.into_inner(), item.into_inner()).await
+}
+
+/// Validate a matrix and keep it resident in the device under a name
+///
+/// # Arguments
+///
+/// * `req` - The request uploading the graph
+/// * `name` - The name of the graph
+/// * `matrix` - The matrix of the graph
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response, `201 Created` for new graphs and `200 OK` when a graph is replaced
+///
+pub async fn store_graph(req: &HttpRequest, name: String, matrix: Matrix) -> HttpResponse {
+    // Validate the matrix before uploading it
+    if let Some(message) = validate_static_matrix(&matrix) {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    if let Some(response) = invalid_cells(&matrix, ValidationRules::default()).or_else(|| memory_rejection::<f32>(matrix.width)) {
+        return response;
+    }
+
+    // Reject the upload right away while the device is too hot to admit it
+    let _permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(state) => return thermal_rejection(&state)
+    };
+
+    // Upload the matrix and store it outside the async executor
+    let upload = spawn_blocking(move || with_device(|walker| walker.upload(&matrix)).map(|device| STORE.insert(&name, &matrix, device))).await;
+
+    match upload.map_err(computation_failure) {
+        Ok(Ok(Ok((graph, true)))) => with_affinity(req, HttpResponse::Ok().json(json!({ "status": "ok", "graph": graph }))),
+        Ok(Ok(Ok((graph, false)))) => with_affinity(req, HttpResponse::Created().json(json!({ "status": "ok", "graph": graph }))),
+        Ok(Ok(Err(message))) => storage_error(message),
+        Ok(Err(err)) => PathWalkerError::from(err).response(),
+        Err(message) => PathWalkerError::Internal(message).response()
+    }
+}
+
+/// The graph listing endpoint
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response with the description of the stored graphs
+///
+#[get("/graphs")]
+pub async fn list_graphs_endpoint() -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs) {
+        return entitlement_rejection(message);
+    }
+
+    match STORE.list() {
+        Ok(graphs) => HttpResponse::Ok().json(json!({ "status": "ok", "graphs": graphs })),
+        Err(message) => storage_error(message)
+    }
+}
+
+/// The graph description endpoint
+///
+/// # Arguments
+///
+/// * `name` - The name of the graph
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response with the description of the graph
+///
+#[get("/graphs/{name}")]
+pub async fn get_graph_endpoint(name: Path<String>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs) {
+        return entitlement_rejection(message);
+    }
+
+    match STORE.info(&name) {
+        Ok(Some(graph)) => HttpResponse::Ok().json(json!({ "status": "ok", "graph": graph })),
+        Ok(None) => graph_not_found(&name),
+        Err(message) => storage_error(message)
+    }
+}
+
+/// The graph export endpoint
+///
+/// Exposes a endpoint that serializes a stored graph as GraphML, DOT or a
+/// CSV edge list, with the weights it has after every change of its edges,
+/// so the tools reading those formats can draw the graphs PathWalker walks.
+///
+/// # Arguments
+///
+/// * `name` - The name of the graph
+/// * `query` - The query parameters with the format of the graph
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response with the serialized graph
+///
+#[get("/graphs/{name}/export")]
+pub async fn export_graph_endpoint(name: Path<String>, query: Query<ExportQuery>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs) {
+        return entitlement_rejection(message);
+    }
+
+    let format = match query.format.parse::<GraphFormat>() {
+        Ok(format) => format,
+        Err(message) => return PathWalkerError::Validation(message).response()
+    };
+
+    // Read and serialize the persisted matrix outside the async executor
+    let graph_name = name.to_string();
+    let export = spawn_blocking(move || STORE.matrix(&graph_name)
+        .map(|matrix| matrix.map(|matrix| export_graph(format, &graph_name, &matrix)))).await;
+
+    match export.map_err(computation_failure) {
+        Ok(Ok(Some(text))) => {
+            info!("Exported graph {} as {}", name, query.format);
+            HttpResponse::Ok().content_type(format.media_type()).body(text)
+        },
+        Ok(Ok(None)) => graph_not_found(&name),
+        Ok(Err(message)) => storage_error(message),
+        Err(message) => PathWalkerError::Internal(message).response()
+    }
+}
+
+/// The graph removal endpoint
+///
+/// Removes the graph from the store, freeing its device memory
+///
+/// # Arguments
+///
+/// * `name` - The name of the graph
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[delete("/graphs/{name}")]
+pub async fn delete_graph_endpoint(name: Path<String>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs) {
+        return entitlement_rejection(message);
+    }
+
+    match STORE.remove(&name) {
+        Ok(true) => HttpResponse::Ok().json(json!({ "status": "ok" })),
+        Ok(false) => graph_not_found(&name),
+        Err(message) => storage_error(message)
+    }
+}
+
+/// The stored graph edges endpoint
+///
+/// Exposes a endpoint that changes the weights of some edges of a stored
+/// graph, like after a traffic update, without uploading the whole matrix
+/// again. Only the cells that have changed are written to the matrix
+/// resident in the device, and only the walks of the graph that the change
+/// affects are dropped, so the rest keep being answered without walking
+/// the graph again. A weight of `0` removes the edge, unless the graph has
+/// zero weight edges.
+///
+/// # Arguments
+///
+/// * `name` - The name of the graph
+/// * `item` - The new weights of the edges
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[patch("/graphs/{name}/edges")]
+pub async fn patch_graph_edges_endpoint(req: HttpRequest, name: Path<String>, item: Json<EdgesPatch>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs) {
+        return entitlement_rejection(message);
+    }
+
+    // Get the description of the graph from the store
+    let info = match STORE.info(&name) {
+        Ok(Some(info)) => info,
+        Ok(None) => return graph_not_found(&name),
+        Err(message) => return storage_error(message)
+    };
+
+    // Validate the whole patch before changing anything
+    let edges = item.into_inner().edges;
+
+    if edges.is_empty() {
+        return PathWalkerError::Validation("The patch has no edges".to_string()).response();
+    } else if edges.iter().any(|edge| edge.source >= info.vertices || edge.target >= info.vertices) {
+        return PathWalkerError::Validation("The edges of the patch must be vertices of the graph".to_string()).response();
+    } else if edges.iter().any(|edge| edge.weight.is_nan() || edge.weight < 0.0) {
+        return PathWalkerError::Validation("The weights must be non-negative numbers".to_string()).response();
+    }
+
+    // Change the graph and its buffer in the device outside the async executor
+    let graph_name = name.to_string();
+    let update = spawn_blocking(move || STORE.update_edges(&graph_name, &edges, |graph, cells| with_device(|walker| walker.update_edges(graph, cells)))).await;
+
+    match update.map_err(computation_failure) {
+        Ok(Ok(Some(update))) => {
+            info!("Changed {} cells of graph {}, keeping {} of its walks", update.cells, name, update.kept_paths);
+            with_affinity(&req, HttpResponse::Ok().json(json!({ "status": "ok", "update": update })))
+        },
+        Ok(Ok(None)) => graph_not_found(&name),
+        Ok(Err(message)) => storage_error(message),
+        Err(message) => PathWalkerError::Internal(message).response()
+    }
+}
+
+/// The stored graph sortest path endpoint
+///
+/// Exposes a endpoint that walks a stored graph from the `source` vertex (0 by
+/// default), returning the path of the walk, and the route to the `target`
+/// vertex with its distance when it's given. The walks are kept with the
+/// graph until a change of its edges affects them.
+///
+/// # Arguments
+///
+/// * `name` - The name of the graph
+/// * `query` - The query parameters
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/graphs/{name}/sortest")]
+pub async fn graph_sortest_path_endpoint(req: HttpRequest, name: Path<String>, query: Query<GraphQuery>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs) {
+        return entitlement_rejection(message);
+    }
+
+    // Get the description of the graph from the store
+    let info = match STORE.info(&name) {
+        Ok(Some(info)) => info,
+        Ok(None) => return graph_not_found(&name),
+        Err(message) => return storage_error(message)
+    };
+
+    // Validate the vertices and the unit of the query
+    let source = query.source.unwrap_or(0);
+    let requested = match requested_unit(query.unit.as_deref()) {
+        Ok(requested) => requested,
+        Err(message) => return PathWalkerError::Validation(message).response()
+    };
+
+    if source >= info.vertices || query.target.is_some_and(|target| target >= info.vertices) {
+        return PathWalkerError::Validation("The vertices of the query are outside of the graph".to_string()).response();
+    }
+
+    // Check that the costs can be returned in the requested unit before walking the graph
+    let declared = match STORE.unit(&name) {
+        Ok(Some(declared)) => declared,
+        Ok(None) => return graph_not_found(&name),
+        Err(message) => return storage_error(message)
+    };
+
+    let unit = match display_unit(declared, requested) {
+        Ok(unit) => unit,
+        Err(message) => return PathWalkerError::Validation(message).response()
+    };
+
+    // Reject the job right away while the device is too hot to admit it
+    let permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(state) => return thermal_rejection(&state)
+    };
+
+    // Check if the client was routed to the replica holding the graph
+    let token = req.headers().get(AFFINITY_HEADER)
+        .and_then(|token| token.to_str().ok().map(str::to_string))
+        .or_else(|| req.cookie(AFFINITY_COOKIE).map(|cookie| cookie.value().to_string()));
+
+    match AFFINITY.residency(token.as_deref()) {
+        Residency::Local => observe_affinity(true),
+        Residency::Remote => {
+            info!("The graph {} was pinned to replica {:?}, uploading it to this one", name, token);
+            observe_affinity(false);
+        },
+        Residency::Unknown => {}
+    }
+
+    // Print the request
+    info!("Received request for graph {} from vertex {}", name, source);
+
+    // Get the graph resident in the device, uploading it again after a restart,
+    // and walk it outside the async executor
+    let (graph_name, target, round, bidirectional) = (name.to_string(), query.target, query.round, query.bidirectional);
+    let cancellation = computation_cancellation();
+    let (_cancel, walk_cancellation) = (cancellation.on_drop(), cancellation.clone());
+    let walk = spawn_blocking(move || {
+        let _permit = permit;
+        let graph = match STORE.get(&graph_name, |matrix| with_device(|walker| walker.upload(matrix))) {
+            Ok(Some(graph)) => graph,
+            Ok(None) => return Ok(None),
+            Err(message) => return Err(message)
+        };
+
+        // Search from both ends when only the route to the target of a large graph is wanted,
+        // unless its ties are broken by the lowest vertex, which needs every distance settled
+        let device = &graph.device;
+        let meeting = target.filter(|_| bidirectional != Some(false) && !device.deterministic && is_bidirectional(device.width, device.rounds, device.nonnegative));
+
+        let mut parameters = vec![
+            ("source", source.to_string()),
+            ("target", format!("{:?}", target)),
+            ("round", format!("{:?}", round)),
+            ("unit", format!("{:?}", requested.map(|unit| unit.name)))
+        ];
+        if meeting.is_some() {
+            parameters.push(("search", "bidirectional".to_string()));
+        }
+        let walker = device_walker().map_err(|err| err.to_string())?;
+        let run_id = run_id(&graph.digest, "graph-sortest", &parameters, walker.backend_version());
+
+        // Reuse the walk from the source, unless the edges of the graph have changed it
+        if let Some(path) = graph.path(source) {
+            return Ok(Some((Ok(path), true, false, run_id, device.zero_edges)));
+        }
+
+        // Only the walks of the whole graph are kept, as the bidirectional ones stop at the target
+        if let Some(target) = meeting {
+            let result = walker.bidirectional(device, source, target, &walk_cancellation).inspect_err(|err| device_failure(&walker, err));
+            return Ok(Some((result, false, true, run_id, device.zero_edges)));
+        }
+
+        let result = walker.walk(device, source, &walk_cancellation, |_, _| {}).inspect_err(|err| device_failure(&walker, err));
+
+        if let Ok(path) = &result {
+            graph.keep_path(source, path.clone());
+        }
+
+        Ok(Some((result, false, false, run_id, device.zero_edges)))
+    }).await;
+
+    // Return the path of the walk, with its costs in the unit requested or the one of the weights
+    match walk.map_err(computation_failure) {
+        Ok(Ok(Some((Ok(path), cached, both_ends, run_id, zero_edges)))) => {
+            info!("Walked graph {} as run {}", name, run_id);
+
+            let mut body = json!({
+                "status": "ok",
+                "run_id": run_id
+            });
+            fill_costs(&mut body, &path, source, target, zero_edges, unit, round);
+
+            if both_ends {
+                body["search"] = json!("bidirectional");
+            }
+
+            let mut builder = HttpResponse::Ok();
+            builder.insert_header((CACHE_HEADER, if cached { "hit" } else { "miss" }));
+
+            with_affinity(&req, path_response(&req, builder, body))
+        },
+        Ok(Ok(Some((Err(err), _, _, _, _)))) if cancellation.is_expired() => walk_rejection(&WalkRejection::Timeout(err.to_string())),
+        Ok(Ok(Some((Err(err), _, _, _, _)))) => PathWalkerError::from(err).response(),
+        Ok(Ok(None)) => graph_not_found(&name),
+        Ok(Err(message)) => storage_error(message),
+        Err(message) => PathWalkerError::Internal(message).response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use crate::models::{Result, WeightUnit};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_graph_sortest_path_endpoint_missing_graph() {
+        // Prepare the expected result
+        let expected = "The graph missing doesn't exist";
+
+        // Get the result
+        let app = init_service(
+            App::new().service(graph_sortest_path_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/graphs/missing/sortest?source=0&target=1")
+            .to_request();
+
+        // Get the response
+        let resp: Result = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "error");
+        assert_eq!(resp.message.unwrap(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_export_graph_endpoint_invalid_format() {
+        // Get the result
+        let app = init_service(
+            App::new().service(export_graph_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::get()
+            .uri("/graphs/roads/export?format=xlsx")
+            .to_request();
+
+        // Get the response
+        let resp: Result = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "error");
+        assert_eq!(resp.message.unwrap(), "The format xlsx is not supported");
+    }
+
+    #[test]
+    fn test_fill_costs() {
+        // Prepare the walk of a path of three vertices weighted in seconds
+        let path = vec![PathResult(0, 0.0), PathResult(0, 90.0), PathResult(1, 210.0)];
+        let minutes = display_unit(Some(WeightUnit::Seconds), None).unwrap();
+
+        // Get the result
+        let mut body = json!({ "status": "ok" });
+        fill_costs(&mut body, &path, 0, Some(2), false, minutes, Some(2));
+
+        // Check if the result is correct
+        assert_eq!(body["path"], json!([[0, 0.0], [0, 1.5], [1, 3.5]]));
+        assert_eq!(body["route"], json!([0, 1, 2]));
+        assert_eq!(body["distance"], json!(3.5));
+        assert_eq!(body["unit"], "min");
+        assert_eq!(requested_unit(Some("furlong")), Err("The unit furlong is not supported, it must be m, km, s, min, h or custom".to_string()));
+    }
+}
@@ -0,0 +1,154 @@
+use actix_web::{post, HttpResponse, web::Json};
+use log::{error, info};
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::models::{GridRequest, Transform};
+use crate::services::entitlement::Feature;
+use crate::services::grid::{grid_path, validate_grid};
+use crate::services::postprocess::{grid_legs, postprocess, validate_pipeline};
+use crate::utils::{round_decimals, spawn_blocking};
+use super::{entitlement_rejection, ENTITLEMENTS};
+
+/// The grid path endpoint
+///
+/// Exposes a endpoint that receives a grid of square or hex cells with the
+/// cost of entering every cell, and returns the cheapest path of cells from
+/// the start to the goal, so maps don't have to be converted to matrices
+///
+/// # Arguments
+///
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/grid")]
+pub async fn grid_endpoint(item: Json<GridRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Grid) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and validate it before searching the path
+    let request: GridRequest = item.into_inner();
+
+    let error = validate_grid(&request)
+        .or_else(|| validate_pipeline(&request.postprocess, request.width * request.height, true));
+
+    if let Some(message) = error {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    // Print the request
+    info!("Received {:?} grid request of {}x{} cells from {:?} to {:?}", request.topology, request.width, request.height, request.start, request.goal);
+
+    if !request.postprocess.is_empty() {
+        info!("Post-processing the route with {}", request.postprocess.iter().map(Transform::name).collect::<Vec<_>>().join(", "));
+    }
+
+    // Search the path outside the async executor
+    let (start, goal, round) = (request.start, request.goal, request.round);
+    let result = spawn_blocking(move || grid_path(&request).map(|path| {
+        let processed = (!request.postprocess.is_empty()).then(|| postprocess(grid_legs(&request, &path.cells), &request.postprocess, request.width, request.topology));
+        (path, processed)
+    })).await;
+
+    // Return the cells of the path, with the route left by the pipeline when there is one
+    match result {
+        Ok(Some((path, processed))) => {
+            let mut body = json!({
+                "status": "ok",
+                "cells": path.cells,
+                "cost": round_decimals(path.cost, round),
+                "expanded": path.expanded,
+                "bound": round_decimals(path.bound, round)
+            });
+
+            if let Some(processed) = processed {
+                body["postprocessed"] = json!(processed.round(round));
+            }
+
+            HttpResponse::Ok().json(body)
+        },
+        Ok(None) => PathWalkerError::Unprocessable(format!("The goal {:?} can't be reached from {:?}", goal, start)).response(),
+        Err(err) => {
+            error!("The grid search has panicked: {}", err);
+            PathWalkerError::Internal("The computation has been aborted".to_string()).response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, call_service, init_service, TestRequest}, App};
+    use serde_json::Value;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_grid_endpoint() {
+        // Prepare a hex map where the swamp in the middle is cheaper to go around
+        let request: Value = json!({
+            "topology": "hex",
+            "width": 3,
+            "height": 3,
+            "costs": [1, 1, 1, 1, 9, 1, 1, 1, 1],
+            "start": [0, 1],
+            "goal": [2, 1]
+        });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(grid_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/grid")
+            .set_json(&request)
+            .to_request();
+
+        // Get the response
+        let resp: Value = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp["status"], "ok");
+        assert_eq!(resp["cells"].as_array().unwrap().len(), 4);
+        assert_eq!(resp["cells"][3], json!([2, 1]));
+        assert_eq!(resp["cost"], 3.0);
+        assert_eq!(resp["bound"], 1.0);
+        assert!(resp.get("postprocessed").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_grid_endpoint_postprocess() {
+        // Prepare a straight corridor whose path is simplified to a single leg measured in kilometers
+        let request: Value = json!({
+            "width": 4,
+            "height": 1,
+            "costs": [1, 1, 1, 1],
+            "start": [0, 0],
+            "goal": [3, 0],
+            "postprocess": ["simplify_collinear", { "convert_units": { "factor": 0.5, "unit": "km" } }]
+        });
+        let unknown: Value = json!({ "width": 1, "height": 1, "costs": [1], "start": [0, 0], "goal": [0, 0], "postprocess": ["reverse"] });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(grid_endpoint)
+        ).await;
+
+        // Get the responses
+        let resp: Value = call_and_read_body_json(&app, TestRequest::post().uri("/grid").set_json(&request).to_request()).await;
+        let rejected = call_service(&app, TestRequest::post().uri("/grid").set_json(&unknown).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(resp["postprocessed"], json!({
+            "legs": [{ "from": [0, 0], "to": [3, 0], "cost": 1.5, "steps": 3 }],
+            "cost": 1.5,
+            "unit": "km"
+        }));
+        assert_eq!(rejected.status(), 400);
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::Arc;
+
+use log::{error, info, warn};
+use tonic::metadata::MetadataValue;
+use tonic::service::interceptor::InterceptedService;
+use tonic::{Request, Response, Status};
+
+use crate::models::{Matrix, PathResult, ValidationRules};
+use crate::services::usage::{UsageMeter, UsageStats};
+use crate::utils::spawn_blocking;
+use super::auth::{ApiKeyName, GrpcApiKey};
+use super::usage::key_name;
+use super::{cached_sortest_path, computation_cancellation, validate_matrix, walk_run_id};
+
+/// The metadata key telling if the path has been taken from the cache
+pub const CACHE_METADATA: &str = "x-pathwalker-cache";
+
+/// The messages and service definitions generated from the protocol buffers
+pub mod proto {
+    tonic::include_proto!("pathwalker");
+}
+
+use proto::path_walker_server::{PathWalker, PathWalkerServer};
+
+impl From<proto::Matrix> for Matrix {
+    fn from(matrix: proto::Matrix) -> Self {
+        Matrix {
+            round: matrix.round,
+            zero_edges: matrix.zero_edges.unwrap_or_default(),
+            directed: matrix.directed.unwrap_or(true),
+            forbidden_nodes: matrix.forbidden_nodes.iter().map(|vertex| *vertex as usize).collect(),
+            forbidden_edges: matrix.forbidden_edges.iter().map(|edge| [edge.source as usize, edge.target as usize]).collect(),
+            max_hops: matrix.max_hops.map(|hops| hops as usize),
+            ..Matrix::new(matrix.width as usize, matrix.height as usize, matrix.data)
+        }
+    }
+}
+
+impl From<PathResult> for proto::PathResult {
+    fn from(result: PathResult) -> Self {
+        proto::PathResult { vertex: result.0, distance: result.1 }
+    }
+}
+
+/// The gRPC service of the sortest path
+///
+/// Shares the services layer with the REST endpoint, so both interfaces
+/// answer with the same path for the same matrix, and accounts the calls
+/// to the usage of their API key along with the REST requests
+///
+/// # Fields
+///
+/// * `usage` - The usage of every API key
+///
+pub struct PathWalkerService {
+    usage: Arc<UsageStats>,
+}
+
+impl PathWalkerService {
+    /// Create the gRPC service
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - The usage of every API key, shared with the REST server
+    ///
+    /// # Returns
+    ///
+    /// * `PathWalkerService` - The service
+    ///
+    pub fn new(usage: Arc<UsageStats>) -> PathWalkerService {
+        PathWalkerService { usage }
+    }
+
+    /// Create the server of the gRPC service
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - The usage of every API key, shared with the REST server
+    /// * `auth` - The API key authentication of the calls
+    ///
+    /// # Returns
+    ///
+    /// * `InterceptedService<PathWalkerServer<PathWalkerService>, GrpcApiKey>` - The server to attach to a router
+    ///
+    pub fn server(usage: Arc<UsageStats>, auth: GrpcApiKey) -> InterceptedService<PathWalkerServer<PathWalkerService>, GrpcApiKey> {
+        PathWalkerServer::with_interceptor(PathWalkerService::new(usage), auth)
+    }
+}
+
+#[tonic::async_trait]
+impl PathWalker for PathWalkerService {
+    /// Returns the path of the walk for the given matrix
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request with the matrix
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Response<PathResponse>, Status>` - The response
+    ///
+    async fn shortest_path(&self, request: Request<proto::Matrix>) -> Result<Response<proto::PathResponse>, Status> {
+        // Account the call to its API key, rejecting the keys over their quota
+        let key = key_name(request.extensions().get::<ApiKeyName>());
+        let meter = UsageMeter::admit(self.usage.clone(), &key).inspect_err(|_| {
+            warn!("Rejected gRPC call from the API key {} over its monthly quota", key);
+        }).map_err(Status::resource_exhausted)?;
+
+        // Read the request and convert it
+        let matrix: Matrix = request.into_inner().into();
+
+        // Validate the matrix before computing the path
+        if let Some(message) = validate_matrix(&matrix) {
+            return Err(Status::invalid_argument(message));
+        }
+
+        if let Some(error) = matrix.validate(ValidationRules::default()).errors.first() {
+            return Err(Status::invalid_argument(error.to_string()));
+        }
+
+        // Print the request
+        info!("Received gRPC request for matrix: {:?}", matrix);
+
+        // Get the path of the walk outside the async executor and return it
+        let round = matrix.round;
+        let cancellation = computation_cancellation();
+        let (_cancel, walk) = (cancellation.on_drop(), cancellation.clone());
+
+        let computation = meter.scope(async move {
+            spawn_blocking(move || cached_sortest_path(&matrix, &walk).map(|(result, cached)| (result, cached, walk_run_id(&matrix)))).await
+        });
+
+        match computation.await {
+            Ok(Ok((Ok(path), cached, run_id))) => {
+                if let Some(run_id) = &run_id {
+                    info!("Walked the gRPC matrix as run {}", run_id);
+                }
+
+                let mut response = Response::new(proto::PathResponse {
+                    status: "ok".to_string(),
+                    path: path.iter().map(|hop| hop.round(round).into()).collect(),
+                    message: None,
+                    run_id,
+                    code: None
+                });
+
+                response.metadata_mut().insert(CACHE_METADATA, MetadataValue::from_static(if cached { "hit" } else { "miss" }));
+                Ok(response)
+            },
+            Ok(Ok((Err(err), _, _))) if cancellation.is_expired() => Err(Status::deadline_exceeded(err.to_string())),
+            Ok(Ok((Err(err), _, _))) => Err(Status::internal(err.to_string())),
+            Ok(Err(_)) => Err(Status::unavailable("The device is too hot to admit new jobs, retry later")),
+            Err(err) => {
+                error!("The gRPC computation has panicked: {}", err);
+                Err(Status::internal("The computation has been aborted"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_sortest_path_grpc_invalid_matrix() {
+        // Prepare the matrix
+        let matrix = proto::Matrix {
+            width: 3,
+            height: 3,
+            round: None,
+            zero_edges: None,
+            directed: None,
+            forbidden_nodes: vec![],
+            forbidden_edges: vec![],
+            max_hops: None,
+            data: vec![
+                01.0, 04.0, 02.0, 00.0,
+                04.0, 01.0, 01.0, 05.0
+            ]
+        };
+
+        // Prepare the expected result
+        let expected = "The matrix size and dimensions are not the same";
+
+        // Get the result
+        let status = PathWalkerService::new(Arc::new(UsageStats::new(None))).shortest_path(Request::new(matrix)).await.unwrap_err();
+
+        // Check if the result is correct
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), expected);
+    }
+}
@@ -0,0 +1,95 @@
+use actix_web::{get, HttpResponse};
+use log::error;
+use serde_json::json;
+
+use crate::services::opencl;
+use crate::services::thermal::ThermalLevel;
+
+use crate::utils::spawn_blocking;
+use super::{backend_status, backend_walker, opened_backend, THERMAL};
+use super::errors::PathWalkerError;
+
+/// The liveness probe endpoint
+///
+/// Answers as long as the server is able to serve requests
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[get("/healthz")]
+pub async fn healthz_endpoint() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+/// The readiness probe endpoint
+///
+/// Runs a sentinel kernel in the device of the configured backend, or a
+/// tiny walk in the backends without one, and fails when the device can't
+/// be opened, when the kernel can't be compiled or enqueued, or when the
+/// device is too hot to admit jobs, so the orchestrator stops
+/// routing traffic to this node. A device that can't be opened is tried
+/// again by the probes once the wait after its last failure is over, so
+/// the node becomes ready by itself once the device is back.
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[get("/readyz")]
+pub async fn readyz_endpoint() -> HttpResponse {
+    // Run the sentinel kernel outside the async executor
+    let probe = spawn_blocking(|| backend_walker().and_then(|walker| walker.probe().map(|_| walker.device_name()).map_err(opencl::Error::from))).await;
+
+    let device = match probe {
+        Ok(Ok(device)) => device,
+        Ok(Err(err)) if opened_backend().is_none() => {
+            return PathWalkerError::Unavailable(format!("The device can't be opened: {}", err)).response_with(json!({ "walker": backend_status() }));
+        },
+        Ok(Err(err)) => {
+            error!("The readiness probe has failed: {}", err);
+            return not_ready(&format!("The sentinel kernel has failed: {}", err));
+        },
+        Err(_) => return not_ready("The OpenCL device can't be initialized")
+    };
+
+    // Stop receiving traffic while the device is too hot to admit jobs
+    let thermal = THERMAL.state();
+
+    if thermal.level == ThermalLevel::Critical {
+        return not_ready("The device is too hot to admit new jobs");
+    }
+
+    HttpResponse::Ok().json(json!({ "status": "ok", "device": device, "thermal": thermal }))
+}
+
+/// Build the response for a node that isn't ready
+fn not_ready(message: &str) -> HttpResponse {
+    PathWalkerError::Unavailable(message.to_string()).response()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use crate::models::Result;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_healthz_endpoint() {
+        // Get the result
+        let app = init_service(
+            App::new().service(healthz_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::get()
+            .uri("/healthz")
+            .to_request();
+
+        // Get the response
+        let resp: Result = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "ok");
+    }
+}
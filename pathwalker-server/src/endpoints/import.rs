@@ -0,0 +1,113 @@
+use actix_web::{post, HttpRequest, HttpResponse, web::Query};
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::encoding::path_response;
+use super::errors::PathWalkerError;
+use crate::models::{PathResult, ValidationRules};
+use crate::services::entitlement::Feature;
+use crate::services::import::{import_graph, GraphFormat};
+use super::{blocking_sortest_path, entitlement_rejection, invalid_cells, validate_matrix, walk_rejection, CACHE_HEADER, ENTITLEMENTS};
+
+/// The query parameters of the import endpoint
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    pub format: String,
+    pub round: Option<u32>,
+}
+
+/// The graph import endpoint
+///
+/// Exposes a endpoint that receives a graph serialized as GraphML, DOT or a CSV
+/// edge list, converts it to an adjacency matrix and returns the path of the walk,
+/// along with the names of the vertices in the order used by the path
+///
+/// # Arguments
+///
+/// * `req` - The request, asking for comma separated values in the `Accept` header or JSON otherwise
+/// * `query` - The query parameters with the format of the graph
+/// * `body` - The serialized graph
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/import")]
+pub async fn import_endpoint(req: HttpRequest, query: Query<ImportQuery>, body: String) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Import) {
+        return entitlement_rejection(message);
+    }
+
+    // Parse the graph into a matrix
+    let graph = match query.format.parse::<GraphFormat>().and_then(|format| import_graph(format, &body)) {
+        Ok(graph) => graph,
+        Err(message) => return PathWalkerError::Validation(message).response()
+    };
+
+    // Validate the matrix before computing the path
+    let mut matrix = graph.matrix;
+    matrix.round = query.round;
+
+    if let Some(message) = validate_matrix(&matrix) {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    if let Some(response) = invalid_cells(&matrix, ValidationRules::default()) {
+        return response;
+    }
+
+    // Print the request
+    info!("Received import request for graph with {} vertices", graph.nodes.len());
+
+    // Get the path of the walk outside the async executor
+    let (result, cached, _) = match blocking_sortest_path(&matrix).await {
+        Ok(result) => result,
+        Err(rejection) => return walk_rejection(&rejection)
+    };
+
+    // Return the path of the walk
+    match result {
+        Ok(path) => {
+            let path: Vec<PathResult> = path.iter().map(|hop| hop.round(query.round)).collect();
+
+            let mut builder = HttpResponse::Ok();
+            builder.insert_header((CACHE_HEADER, if cached { "hit" } else { "miss" }));
+
+            path_response(&req, builder, json!({ "status": "ok", "nodes": graph.nodes, "path": path }))
+        },
+        Err(err) => PathWalkerError::from(err).response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use crate::models::Result;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_import_endpoint_unsupported_format() {
+        // Prepare the expected result
+        let expected = "The format gexf is not supported";
+
+        // Get the result
+        let app = init_service(
+            App::new().service(import_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/import?format=gexf")
+            .set_payload("<gexf/>")
+            .to_request();
+
+        // Get the response
+        let resp: Result = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "error");
+        assert_eq!(resp.message.unwrap(), expected);
+    }
+}
@@ -0,0 +1,106 @@
+use actix_web::{post, HttpResponse, web::Json};
+use log::{error, info};
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::models::{EdgeLoadRequest, ValidationRules};
+use crate::services::entitlement::Feature;
+use crate::services::loads::{edge_loads, validate_loads};
+use crate::utils::{round_decimals_f64, spawn_blocking};
+use super::{entitlement_rejection, invalid_cells, ENTITLEMENTS};
+
+/// The edge load endpoint
+///
+/// Exposes a endpoint that receives a matrix of weights along with a set of
+/// demands between origins and destinations, and returns how many of their
+/// shortest paths go through every edge, with the volume of the demands
+/// split evenly between them, which is the load of a traffic assignment
+///
+/// # Arguments
+///
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/sortest/loads")]
+pub async fn loads_endpoint(item: Json<EdgeLoadRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Loads) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and validate it before counting the paths
+    let request: EdgeLoadRequest = item.into_inner();
+
+    if let Some(response) = invalid_cells(&request.matrix, ValidationRules { reject_negative: true, ..ValidationRules::default() }) {
+        return response;
+    }
+
+    if let Some(message) = validate_loads(&request) {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    // Print the request
+    info!("Received edge load request of {} demands over {} vertices", request.demands.len(), request.matrix.width);
+
+    // Count the paths outside the async executor
+    let round = request.matrix.round;
+    let result = spawn_blocking(move || edge_loads(&request)).await;
+
+    // Return the load of every edge
+    match result {
+        Ok(Ok(mut loads)) => {
+            for load in &mut loads.loads {
+                load.load = round_decimals_f64(load.load, round);
+            }
+
+            HttpResponse::Ok().json(json!({
+                "status": "ok",
+                "loads": loads.loads,
+                "unreachable": loads.unreachable
+            }))
+        },
+        Ok(Err(message)) => PathWalkerError::Unprocessable(message).response(),
+        Err(err) => {
+            error!("The edge load count has panicked: {}", err);
+            PathWalkerError::Internal("The computation has been aborted".to_string()).response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, call_service, init_service, TestRequest}, App};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_loads_endpoint() {
+        // Prepare a path of three vertices with two demands, and a request with a demand outside of it
+        let request = json!({
+            "width": 3,
+            "height": 3,
+            "data": [0, 1, 0, 1, 0, 1, 0, 1, 0],
+            "demands": [{ "source": 0, "target": 2, "volume": 3 }, { "source": 1, "target": 2 }]
+        });
+        let outside = json!({ "width": 1, "height": 1, "data": [0], "demands": [{ "source": 0, "target": 1 }] });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(loads_endpoint)
+        ).await;
+
+        // Get the responses
+        let resp: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/sortest/loads").set_json(&request).to_request()).await;
+        let rejected = call_service(&app, TestRequest::post().uri("/sortest/loads").set_json(&outside).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(resp["loads"], json!([
+            { "source": 0, "target": 1, "paths": 1.0, "load": 3.0 },
+            { "source": 1, "target": 2, "paths": 2.0, "load": 4.0 }
+        ]));
+        assert_eq!(resp["unreachable"], json!([]));
+        assert_eq!(rejected.status(), 400);
+    }
+}
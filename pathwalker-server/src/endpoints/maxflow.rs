@@ -0,0 +1,118 @@
+use actix_web::{post, HttpResponse, web::{Json, Query}};
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::models::{CutEdge, Matrix, ValidationRules};
+use crate::services::entitlement::Feature;
+use crate::services::maxflow::validate_maxflow;
+use crate::services::run_id::{matrix_digest, run_id};
+use crate::utils::{round_decimals, spawn_blocking};
+use super::{computation_cancellation, computation_failure, entitlement_rejection, invalid_cells, thermal_rejection, walk_rejection, WalkRejection, ENTITLEMENTS, THERMAL, with_device};
+
+/// The query parameters of the maximum flow endpoint
+#[derive(Debug, Deserialize)]
+pub struct MaxFlowQuery {
+    pub source: usize,
+    pub sink: usize,
+}
+
+/// The maximum flow endpoint
+///
+/// Exposes a endpoint that receives a matrix whose weights are the
+/// capacities of the edges, and returns the maximum flow from the source to
+/// the sink along with the edges of the minimum cut, computed in the device
+/// with the push-relabel algorithm
+///
+/// # Arguments
+///
+/// * `query` - The query parameters with the source and the sink
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/maxflow")]
+pub async fn maxflow_endpoint(query: Query<MaxFlowQuery>, item: Json<Matrix>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::MaxFlow) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and validate it before pushing the flow
+    let matrix: Matrix = item.into_inner();
+    let (source, sink) = (query.source, query.sink);
+
+    if let Some(message) = validate_maxflow(&matrix, source, sink) {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    if let Some(response) = invalid_cells(&matrix, ValidationRules { reject_negative: true, ..ValidationRules::default() }) {
+        return response;
+    }
+
+    // Reject the job right away while the device is too hot to admit it
+    let permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(state) => return thermal_rejection(&state)
+    };
+
+    // Print the request
+    info!("Received maximum flow request over {} vertices from {} to {}", matrix.width, source, sink);
+
+    // Push the flow outside the async executor
+    let round = matrix.round;
+    let cancellation = computation_cancellation();
+    let (_cancel, flow_cancellation) = (cancellation.on_drop(), cancellation.clone());
+    let computation = spawn_blocking(move || {
+        let _permit = permit;
+        with_device(|walker| {
+            let parameters = [("source", source.to_string()), ("sink", sink.to_string()), ("round", format!("{:?}", round))];
+            let run_id = run_id(&matrix_digest(&matrix), "maxflow", &parameters, walker.backend_version());
+
+            walker.max_flow(&matrix, source, sink, &flow_cancellation).map(|flow| (flow, run_id))
+        })
+    }).await;
+
+    // Return the flow and the edges of the cut
+    match computation.map_err(computation_failure) {
+        Ok(Ok((flow, run_id))) => HttpResponse::Ok().json(json!({
+            "status": "ok",
+            "flow": round_decimals(flow.flow, round),
+            "cut": flow.cut.into_iter().map(|edge| CutEdge { capacity: round_decimals(edge.capacity, round), ..edge }).collect::<Vec<CutEdge>>(),
+            "rounds": flow.rounds,
+            "run_id": run_id
+        })),
+        Ok(Err(err)) if cancellation.is_expired() => walk_rejection(&WalkRejection::Timeout(err.to_string())),
+        Ok(Err(err)) => PathWalkerError::from(err).response(),
+        Err(message) => PathWalkerError::Internal(message).response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_maxflow_endpoint_invalid_request() {
+        // Prepare a matrix with a negative capacity, and a valid one to query with the same source and sink
+        let negative = json!({ "width": 2, "height": 2, "data": [0, -1, 1, 0] });
+        let matrix = json!({ "width": 2, "height": 2, "data": [0, 1, 1, 0] });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(maxflow_endpoint)
+        ).await;
+
+        // Get the responses
+        let negative: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/maxflow?source=0&sink=1").set_json(&negative).to_request()).await;
+        let same: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/maxflow?source=1&sink=1").set_json(&matrix).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(negative["errors"][0]["reason"], "The weight is negative, which the algorithm can't handle");
+        assert_eq!(same["message"], "The source and the sink must be different vertices");
+    }
+}
@@ -0,0 +1,20 @@
+use actix_web::{get, HttpResponse};
+
+use crate::services::metrics;
+
+/// The metrics endpoint
+///
+/// Exposes the request counts and latencies, the execution time of every
+/// kernel, the device memory in use and the depth of the device queue in
+/// the Prometheus text format
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[get("/metrics")]
+pub async fn metrics_endpoint() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render())
+}
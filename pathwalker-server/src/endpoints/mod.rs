@@ -0,0 +1,1253 @@
+pub mod apsp;
+pub mod auth;
+pub mod budget;
+pub mod centrality;
+pub mod chunks;
+pub mod compression;
+pub mod config;
+pub mod cors;
+pub mod cost;
+pub mod dag;
+pub mod devices;
+pub mod encoding;
+pub mod errors;
+pub mod explain;
+pub mod failures;
+#[cfg(feature = "flight")]
+pub mod flight;
+pub mod flowfield;
+pub mod graphs;
+pub mod grid;
+pub mod grpc;
+pub mod health;
+pub mod import;
+pub mod loads;
+pub mod maxflow;
+pub mod metrics;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod openapi;
+pub mod pagerank;
+pub mod rate_limit;
+pub mod replan;
+pub mod request_id;
+pub mod shadow;
+pub mod soak;
+pub mod sources;
+pub mod thermal;
+pub mod usage;
+pub mod v1;
+pub mod validate;
+pub mod walks;
+pub mod websocket;
+pub mod whatif;
+
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use actix_web::{post, rt, HttpRequest, HttpResponse, ResponseError, web::{Bytes, Query}};
+use actix_web::http::header::{HeaderValue, RETRY_AFTER};
+use actix_web::dev::ServiceResponse;
+use actix_web::middleware::ErrorHandlerResponse;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::task::JoinError;
+
+use crate::endpoints::errors::PathWalkerError;
+use crate::models::{GridTopology, Matrix, PathResult, Precision, Real, Result, Transform, ValidationRules};
+use crate::services::affinity::Affinity;
+use crate::services::backend::{Backend, BackendKind};
+use crate::services::cache::{CacheKey, ResultCache};
+use crate::services::cancellation::Cancellation;
+use crate::services::cpu_walker::CpuWalker;
+use crate::services::dispatch::dispatch;
+use crate::services::entitlement::Entitlements;
+use crate::services::memory::{check_fit, first_device_memory, walk_bytes};
+use crate::services::opencl::{self, OclPrm};
+use crate::services::postprocess::{postprocess, route_legs, validate_pipeline};
+use crate::services::replay;
+use crate::services::run_id::{matrix_digest, run_id};
+use crate::services::sessions::ReplanSessions;
+use crate::services::shadow::ShadowMirror;
+use crate::services::shutdown::KERNEL_EXECUTIONS;
+use crate::services::soak::SoakTest;
+use crate::services::sockets::SocketSessions;
+use crate::services::unweighted::{hop_distances, uniform_weight};
+use crate::services::sortest_path::{route, SortestPath};
+use crate::services::store::GraphStore;
+use crate::services::thermal::{ThermalMonitor, ThermalState};
+use crate::services::uploads::ChunkedUploads;
+use crate::services::variants::FP64_EXTENSION;
+use crate::services::walker::{LazyWalker, OpenedWalker, WalkerStatus};
+#[cfg(feature = "wgpu")]
+use crate::services::wgpu_walker::WgpuWalker;
+#[cfg(feature = "cuda")]
+use crate::services::cuda_walker::CudaWalker;
+use crate::utils::spawn_blocking;
+use encoding::{processed_response, route_pipeline, Encoding};
+
+pub use pathwalker_core::solver::{validate_matrix, validate_static_matrix};
+
+/// The header asking to record a replay bundle of the computation
+pub const RECORD_HEADER: &str = "X-PathWalker-Record";
+
+/// The header with the id of the recorded replay bundle
+pub const BUNDLE_HEADER: &str = "X-PathWalker-Bundle";
+
+/// The header with the affinity token of a stored graph
+pub const AFFINITY_HEADER: &str = "X-PathWalker-Affinity";
+
+/// The cookie with the affinity token of a stored graph
+pub const AFFINITY_COOKIE: &str = "pathwalker_affinity";
+
+/// The header telling if the path has been taken from the cache
+pub const CACHE_HEADER: &str = "X-PathWalker-Cache";
+
+/// The statuses of the OpenCL calls whose device, or its context, has been lost
+const DEVICE_LOST_STATUSES: [&str; 4] = ["CL_DEVICE_NOT_AVAILABLE", "CL_DEVICE_NOT_FOUND", "CL_INVALID_CONTEXT", "CL_INVALID_COMMAND_QUEUE"];
+
+lazy_static! {
+    static ref WALKER: LazyWalker = LazyWalker::new();
+    static ref THERMAL: ThermalMonitor = ThermalMonitor::new();
+    static ref STORE: GraphStore = GraphStore::new();
+    static ref SHADOW: ShadowMirror = ShadowMirror::new();
+    static ref CACHE: ResultCache = ResultCache::new();
+    static ref AFFINITY: Affinity = Affinity::new();
+    static ref SOAK: SoakTest = SoakTest::new();
+    static ref SESSIONS: ReplanSessions = ReplanSessions::new();
+    static ref SOCKETS: SocketSessions = SocketSessions::new();
+    static ref UPLOADS: ChunkedUploads = ChunkedUploads::new();
+    static ref CPU_WALKER: Arc<CpuWalker> = Arc::new(CpuWalker::new());
+    pub static ref ENTITLEMENTS: Entitlements = Entitlements::from_env().unwrap_or_else(|err| {
+        error!("{}, only the core features are enabled", err);
+        Entitlements::locked()
+    });
+}
+
+#[cfg(feature = "wgpu")]
+lazy_static! {
+    static ref WGPU_WALKER: LazyWalker<WgpuWalker> = LazyWalker::from_env(WgpuWalker::new);
+}
+
+#[cfg(feature = "cuda")]
+lazy_static! {
+    static ref CUDA_WALKER: LazyWalker<CudaWalker> = LazyWalker::from_env(CudaWalker::new);
+}
+
+/// The seconds a client should wait before retrying a job rejected by the thermal monitor
+pub const THERMAL_RETRY_AFTER: u64 = 30;
+
+/// The milliseconds a computation can run for, unlimited when zero
+static COMPUTATION_TIMEOUT: AtomicU64 = AtomicU64::new(0);
+
+/// The precision of the walks whose request doesn't choose one
+static DEFAULT_PRECISION: RwLock<Precision> = RwLock::new(Precision::Single);
+
+/// The compute backend of the single precision weighted walks
+static BACKEND: RwLock<BackendKind> = RwLock::new(BackendKind::OpenCl);
+
+/// The query parameters of the sortest path endpoint
+#[derive(Debug, Deserialize)]
+pub struct SortestQuery {
+    pub precision: Option<String>,
+    #[serde(default)]
+    pub symmetric: bool,
+    #[serde(default)]
+    pub unweighted: bool,
+}
+
+/// Why a walk hasn't been computed
+#[derive(Debug)]
+pub enum WalkRejection {
+    /// The thermal monitor hasn't admitted the walk
+    Thermal(ThermalState),
+    /// The walk has been aborted after its timeout, with the reason
+    Timeout(String),
+    /// The device can't run the walk, like a double precision walk without `cl_khr_fp64`, with the reason
+    Unsupported(String),
+}
+
+/// Set the time a computation can run for before being aborted
+///
+/// # Arguments
+///
+/// * `timeout` - The time a computation can run for, unlimited when not given
+///
+pub fn set_computation_timeout(timeout: Option<Duration>) {
+    COMPUTATION_TIMEOUT.store(timeout.map_or(0, |timeout| timeout.as_millis() as u64), Ordering::Relaxed);
+}
+
+/// Set the precision of the walks whose request doesn't choose one
+///
+/// # Arguments
+///
+/// * `precision` - The precision of the walks
+///
+pub fn set_default_precision(precision: Precision) {
+    *DEFAULT_PRECISION.write().unwrap() = precision;
+}
+
+/// Set the compute backend of the single precision weighted walks
+///
+/// # Arguments
+///
+/// * `backend` - The backend, which must have been built in
+///
+pub fn set_backend(backend: BackendKind) {
+    *BACKEND.write().unwrap() = backend;
+}
+
+/// Start the cancellation of a new computation, with the configured timeout
+pub fn computation_cancellation() -> Cancellation {
+    let timeout = COMPUTATION_TIMEOUT.load(Ordering::Relaxed);
+    Cancellation::with_timeout((timeout > 0).then(|| Duration::from_millis(timeout)))
+}
+
+/// Drain the kernel executions and release the device before exiting
+///
+/// Waits for the kernels still running, like the ones of requests whose
+/// clients have gone away, and then frees the device memory of the stored
+/// graphs. The device is only touched when a kernel has ever run in it.
+///
+/// # Arguments
+///
+/// * `timeout` - The maximum time to wait for the running kernels
+///
+/// # Returns
+///
+/// * `bool` - If every kernel has finished before the timeout
+///
+pub fn drain_device(timeout: Duration) -> bool {
+    // Wait for the running kernels
+    let running = KERNEL_EXECUTIONS.wait_idle(timeout);
+
+    if running > 0 {
+        warn!("{} kernel executions are still running after {:?}, exiting anyway", running, timeout);
+        return false;
+    }
+
+    // Release the device once idle
+    if KERNEL_EXECUTIONS.started() > 0 {
+        if let Some(Err(err)) = WALKER.opened().map(|walker| walker.finish()) {
+            error!("The device queue can't be finished: {}", err);
+        }
+
+        info!("Released {} stored graphs from the device", STORE.release());
+    }
+
+    true
+}
+
+/// Build the response for a job rejected by the thermal monitor
+///
+/// # Arguments
+///
+/// * `state` - The thermal state that rejected the job
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+pub fn thermal_rejection(state: &ThermalState) -> HttpResponse {
+    let mut response = PathWalkerError::Unavailable("The device is too hot to admit new jobs, retry later".to_string()).response_with(json!({ "thermal": state }));
+    response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(THERMAL_RETRY_AFTER));
+    response
+}
+
+/// Build the response for a walk that hasn't been computed
+///
+/// # Arguments
+///
+/// * `rejection` - Why the walk hasn't been computed
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+pub fn walk_rejection(rejection: &WalkRejection) -> HttpResponse {
+    match rejection {
+        WalkRejection::Thermal(state) => thermal_rejection(state),
+        WalkRejection::Timeout(message) => PathWalkerError::Timeout(message.clone()).response(),
+        WalkRejection::Unsupported(message) => PathWalkerError::Unsupported(message.clone()).response()
+    }
+}
+
+/// Get the walker of the device, opening the device when it isn't open yet
+///
+/// # Returns
+///
+/// * `opencl::Result<OpenedWalker<SortestPath>>` - The walker, or why the device can't be opened
+///
+pub fn device_walker() -> opencl::Result<OpenedWalker<SortestPath>> {
+    WALKER.get().map_err(opencl::Error::from)
+}
+
+/// Drop the walker of a call that has failed because its device has been lost
+///
+/// The graphs stored in the lost device are released along with it, and
+/// the next request opens the device again. The calls that fail on a
+/// walker that has already been dropped leave the current one alone.
+///
+/// # Arguments
+///
+/// * `walker` - The walker the call has failed on
+/// * `err` - Why the call has failed
+///
+pub fn device_failure(walker: &OpenedWalker<SortestPath>, err: &opencl::Error) {
+    let lost = err.api_status().is_some_and(|status| DEVICE_LOST_STATUSES.contains(&status.to_string().as_str()));
+
+    if lost && WALKER.reset(walker, err.to_string()) {
+        info!("Released {} stored graphs of the lost device", STORE.release());
+    }
+}
+
+/// Run some work on the walker of the device, opening the device when it isn't open yet
+///
+/// # Arguments
+///
+/// * `work` - The work to run on the walker
+///
+/// # Returns
+///
+/// * `opencl::Result<R>` - The result of the work, or why the device can't be opened
+///
+pub fn with_device<R>(work: impl FnOnce(&SortestPath) -> opencl::Result<R>) -> opencl::Result<R> {
+    let walker = device_walker()?;
+
+    work(&walker).inspect_err(|err| device_failure(&walker, err))
+}
+
+/// Get the walker of the configured backend, opening its device when it isn't open yet
+///
+/// The backends other than OpenCL only walk the single precision weighted
+/// matrices, so every other algorithm takes the OpenCL walker.
+///
+/// # Returns
+///
+/// * `opencl::Result<Arc<dyn Backend>>` - The walker, or why the device can't be opened
+///
+pub fn backend_walker() -> opencl::Result<Arc<dyn Backend>> {
+    match *BACKEND.read().unwrap() {
+        #[cfg(feature = "wgpu")]
+        BackendKind::Wgpu => WGPU_WALKER.get().map(|walker| walker.into_inner() as Arc<dyn Backend>).map_err(opencl::Error::from),
+        #[cfg(feature = "cuda")]
+        BackendKind::Cuda => CUDA_WALKER.get().map(|walker| walker.into_inner() as Arc<dyn Backend>).map_err(opencl::Error::from),
+        BackendKind::Cpu => Ok(CPU_WALKER.clone()),
+        _ => device_walker().map(|walker| walker.into_inner() as Arc<dyn Backend>)
+    }
+}
+
+/// Get the walker of the configured backend only if its device is already open
+pub fn opened_backend() -> Option<Arc<dyn Backend>> {
+    match *BACKEND.read().unwrap() {
+        #[cfg(feature = "wgpu")]
+        BackendKind::Wgpu => WGPU_WALKER.opened().map(|walker| walker.into_inner() as Arc<dyn Backend>),
+        #[cfg(feature = "cuda")]
+        BackendKind::Cuda => CUDA_WALKER.opened().map(|walker| walker.into_inner() as Arc<dyn Backend>),
+        BackendKind::Cpu => Some(CPU_WALKER.clone()),
+        _ => WALKER.opened().map(|walker| walker.into_inner() as Arc<dyn Backend>)
+    }
+}
+
+/// Describe the state of the device of the configured backend, for the readiness probe
+pub fn backend_status() -> WalkerStatus {
+    match *BACKEND.read().unwrap() {
+        #[cfg(feature = "wgpu")]
+        BackendKind::Wgpu => WGPU_WALKER.status(),
+        #[cfg(feature = "cuda")]
+        BackendKind::Cuda => CUDA_WALKER.status(),
+        BackendKind::Cpu => WalkerStatus { ready: true, failures: 0, error: None, retry_in: None },
+        _ => WALKER.status()
+    }
+}
+
+/// Get the run id of the walk of a matrix from its first vertex
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to walk
+///
+/// # Returns
+///
+/// * `Option<String>` - The run id of the walk, if the device could be opened
+///
+pub fn walk_run_id<T: Real>(matrix: &Matrix<T>) -> Option<String> {
+    let mut params = vec![("source", "0".to_string()), ("round", format!("{:?}", matrix.round))];
+
+    // The single precision walks keep the ids they had before the precision could be chosen
+    if T::PRECISION != Precision::Single {
+        params.push(("precision", T::PRECISION.name().to_string()));
+    }
+
+    // Only the single precision walks run in the configured backend, and the small ones in the CPU
+    let backend_version = match (T::PRECISION, dispatch(matrix, *BACKEND.read().unwrap())) {
+        (_, BackendKind::Cpu) => Some(CPU_WALKER.backend_version().to_string()),
+        (Precision::Single, _) => opened_backend().map(|walker| walker.backend_version().to_string()),
+        _ => WALKER.opened().map(|walker| walker.backend_version().to_string())
+    };
+
+    backend_version.map(|backend_version| run_id(&matrix_digest(matrix), "sortest", &params, &backend_version))
+}
+
+/// Get the path of the walk of a matrix, from the cache or from the device
+///
+/// Only the walks missing from the cache run in the device, so only them
+/// have to be admitted by the thermal monitor.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to walk
+/// * `cancellation` - Aborts the walk when its timeout expires or its client goes away
+///
+/// # Returns
+///
+/// * `std::result::Result<(opencl::Result<Vec<PathResult>>, bool), ThermalState>` - The path of the walk and
+///   if it has been taken from the cache, or the thermal state that rejected the walk
+///
+pub fn cached_sortest_path(matrix: &Matrix, cancellation: &Cancellation) -> std::result::Result<(opencl::Result<Vec<PathResult>>, bool), ThermalState> {
+    let key = CacheKey::new(matrix, 0, None);
+
+    if let Some(path) = CACHE.get(&key) {
+        return Ok((Ok(path), true));
+    }
+
+    // Reject the job right away while the device is too hot to admit it
+    let _permit = THERMAL.admit()?;
+
+    // Get the path of the walk and keep it for the next requests
+    let result = single_sortest_path(matrix, cancellation, None);
+
+    if let Ok(path) = &result {
+        CACHE.insert(key, path.clone());
+    }
+
+    Ok((result, false))
+}
+
+/// Walk a single precision matrix from its first vertex
+///
+/// The matrices whose edges all cost the same are walked breadth first,
+/// with their hops turned into the same distances the weighted walk returns,
+/// as it takes a kernel launch per level instead of one per vertex. The
+/// backends other than OpenCL walk every matrix with the weighted walk, and
+/// the matrices smaller than the CPU threshold are walked in the CPU. The
+/// deterministic matrices are always walked with the weighted walk, and the
+/// ties of every backend are broken by the lowest vertex. Only the weighted
+/// walks of OpenCL report their progress, so the walks that ask for it
+/// aren't walked breadth first.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to walk
+/// * `cancellation` - Aborts the walk when its timeout expires or its client goes away
+/// * `progress` - Receives the iterations done and the total of the OpenCL walks, if the caller follows them
+///
+/// # Returns
+///
+/// * `opencl::Result<Vec<PathResult>>` - The path of the walk
+///
+pub fn single_sortest_path(matrix: &Matrix, cancellation: &Cancellation, progress: Option<&mut dyn FnMut(usize, usize)>) -> opencl::Result<Vec<PathResult>> {
+    let backend = match dispatch(matrix, *BACKEND.read().unwrap()) {
+        BackendKind::Cpu => CPU_WALKER.clone(),
+        _ => backend_walker()?
+    };
+
+    // Break the ties of the other backends by the lowest vertex in the host, as the kernels do
+    if backend.kind() != BackendKind::OpenCl {
+        return backend.walk_matrix(matrix, 0, cancellation).map_err(opencl::Error::from).map(|mut path| {
+            if matrix.deterministic {
+                matrix.order_predecessors(&mut path, 0);
+            }
+
+            path
+        });
+    }
+
+    with_device(|walker| match (uniform_weight(matrix).filter(|_| !matrix.deterministic), progress) {
+        (Some(weight), None) => {
+            let graph = walker.upload(matrix)?;
+            walker.breadth_first(&graph, 0, cancellation).map(|path| hop_distances(path, weight))
+        },
+        (_, Some(progress)) => walker.get_sortest_path_with_progress(matrix.clone(), cancellation, progress),
+        (None, None) => walker.get_sortest_path_with_progress(matrix.clone(), cancellation, |_, _| {})
+    })
+}
+
+/// Describe why a computation in the blocking threads hasn't finished
+///
+/// # Arguments
+///
+/// * `err` - The error of the computation
+///
+/// # Returns
+///
+/// * `String` - The reason why the computation hasn't finished
+///
+pub fn computation_failure(err: JoinError) -> String {
+    if !err.is_panic() {
+        return "The computation has been cancelled".to_string();
+    }
+
+    let panic = err.into_panic();
+    let message = panic.downcast_ref::<String>().cloned()
+        .or_else(|| panic.downcast_ref::<&str>().map(|message| message.to_string()))
+        .unwrap_or_else(|| "unknown error".to_string());
+
+    error!("The computation has panicked: {}", message);
+    format!("The computation has failed: {}", message)
+}
+
+/// Get the path of the walk of a matrix in the blocking threads
+///
+/// Same as `cached_sortest_path`, but the kernels run in the blocking thread
+/// pool, so the worker keeps serving other requests while a large matrix is
+/// walked, along with the run id of the walk. The walk is aborted when it
+/// runs over the computation timeout, or when the request is dropped
+/// because its client has gone away.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to walk
+///
+/// # Returns
+///
+/// * `std::result::Result<(opencl::Result<Vec<PathResult>>, bool, Option<String>), WalkRejection>` - The path of the walk,
+///   if it has been taken from the cache and the run id when the device could be opened, or why the walk hasn't been
+///   computed
+///
+pub async fn blocking_sortest_path(matrix: &Matrix) -> std::result::Result<(opencl::Result<Vec<PathResult>>, bool, Option<String>), WalkRejection> {
+    let matrix = matrix.clone();
+    let cancellation = computation_cancellation();
+    let (_cancel, walk) = (cancellation.on_drop(), cancellation.clone());
+
+    match spawn_blocking(move || cached_sortest_path(&matrix, &walk).map(|(result, cached)| (result, cached, walk_run_id(&matrix)))).await {
+        Ok(Ok((Err(_), _, _))) if cancellation.is_expired() => Err(WalkRejection::Timeout(cancellation.check().unwrap_err())),
+        Ok(result) => result.map_err(WalkRejection::Thermal),
+        Err(err) => Ok((Err(computation_failure(err).into()), false, None))
+    }
+}
+
+/// Get the path of the walk of a matrix in any precision in the blocking threads
+///
+/// Same as `blocking_sortest_path`, but the weights keep their type in the
+/// device, like double precision or integer weights. These walks are never
+/// cached.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to walk
+///
+/// # Returns
+///
+/// * `std::result::Result<(opencl::Result<Vec<PathResult<T>>>, Option<String>), WalkRejection>` - The path of the walk
+///   and the run id when the device could be opened, or why the walk hasn't been computed
+///
+pub async fn blocking_precise_sortest_path<T: Real + OclPrm>(matrix: Matrix<T>) -> std::result::Result<(opencl::Result<Vec<PathResult<T>>>, Option<String>), WalkRejection> {
+    let cancellation = computation_cancellation();
+    let (_cancel, walk) = (cancellation.on_drop(), cancellation.clone());
+
+    let result = spawn_blocking(move || {
+        // Reject the job right away while the device is too hot to admit it
+        let _permit = THERMAL.admit().map_err(WalkRejection::Thermal)?;
+
+        let walker = match device_walker() {
+            Ok(walker) => walker,
+            Err(err) => return Ok((Err(err), walk_run_id(&matrix)))
+        };
+
+        // Reject the double precision walks the device can't run before building their kernels
+        if T::PRECISION == Precision::Double && !walker.supports_double() {
+            return Err(WalkRejection::Unsupported(format!("The device {} doesn't support double precision walks, as it lacks the {} extension", walker.device_name(), FP64_EXTENSION)));
+        }
+
+        let result = walker.get_sortest_path_with_progress(matrix.clone(), &walk, |_, _| {}).inspect_err(|err| device_failure(&walker, err));
+
+        Ok((result, walk_run_id(&matrix)))
+    }).await;
+
+    match result {
+        Ok(Ok((Err(_), _))) if cancellation.is_expired() => Err(WalkRejection::Timeout(cancellation.check().unwrap_err())),
+        Ok(result) => result,
+        Err(err) => Ok((Err(computation_failure(err).into()), None))
+    }
+}
+
+/// Build the result of a walk that has failed
+///
+/// # Arguments
+///
+/// * `error` - Why the walk has failed
+/// * `run_id` - The run id of the walk, if the device could be opened
+///
+/// # Returns
+///
+/// * `Result<T>` - The result with the message and the code of the error
+///
+fn error_result<T: Real>(error: &PathWalkerError, run_id: Option<String>) -> Result<T> {
+    Result {
+        path: None,
+        status: "error".to_string(),
+        message: Some(error.message().to_string()),
+        code: Some(error.code().to_string()),
+        run_id
+    }
+}
+
+/// Walk a matrix in a precision other than single for the sortest path endpoint
+///
+/// The matrix is decoded with the weights of the precision, and the walk
+/// skips the cache, the replay bundles and the shadow mirror, which only keep
+/// single precision paths.
+///
+/// # Arguments
+///
+/// * `body` - The body of the request
+/// * `request_encoding` - The encoding of the request
+/// * `response_encoding` - The encoding of the response
+/// * `rules` - What is checked in the cells of the matrix
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+async fn precise_sortest_path<T: Real + OclPrm>(body: &[u8], request_encoding: Encoding, response_encoding: Encoding, rules: ValidationRules) -> HttpResponse {
+    // Read the request and deserialize it
+    let matrix = match request_encoding.decode_precise_matrix::<T>(body) {
+        Ok(matrix) => matrix,
+        Err(err) => return response_encoding.respond_precise(HttpResponse::BadRequest(), error_result::<T>(&PathWalkerError::Validation(format!("The matrix can't be parsed: {}", err)), None))
+    };
+
+    // Validate the matrix before computing the path
+    if let Some(message) = validate_matrix(&matrix) {
+        return response_encoding.respond_precise(HttpResponse::BadRequest(), error_result::<T>(&PathWalkerError::Validation(message.to_string()), None));
+    }
+
+    if let Some(response) = invalid_cells(&matrix, rules).or_else(|| memory_rejection::<T>(matrix.width)) {
+        return response;
+    }
+
+    info!("Received request for {} matrix: {:?}", T::PRECISION.name(), matrix);
+
+    // Get the path of the walk outside the async executor
+    let round = matrix.round;
+    let (result, run_id) = match blocking_precise_sortest_path(matrix).await {
+        Ok(result) => result,
+        Err(rejection) => return walk_rejection(&rejection)
+    };
+
+    // Return the path of the walk
+    match result {
+        Ok(path) => response_encoding.respond_precise(HttpResponse::Ok(), Result {
+            path: Some(path.iter().map(|hop| hop.round(round)).collect()),
+            status: "ok".to_string(),
+            message: None,
+            code: None,
+            run_id
+        }),
+        Err(err) => {
+            let error = PathWalkerError::from(err);
+            response_encoding.respond_precise(HttpResponse::build(error.status_code()), error_result::<T>(&error, run_id))
+        }
+    }
+}
+
+/// Walk a matrix counting the hops instead of adding the weights for the sortest path endpoint
+///
+/// Every edge counts as a hop whatever its weight, so the distances are the
+/// hops to every vertex. The forbidden nodes and edges are masked when the
+/// matrix is uploaded and the levels are bounded by the `max_hops`, as in
+/// the weighted walks. The walk skips the cache, the replay bundles and
+/// the shadow mirror, which only keep weighted paths.
+///
+/// # Arguments
+///
+/// * `matrix` - The validated matrix
+/// * `response_encoding` - The encoding of the response
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+async fn unweighted_sortest_path(matrix: Matrix, response_encoding: Encoding) -> HttpResponse {
+    let cancellation = computation_cancellation();
+    let (_cancel, walk) = (cancellation.on_drop(), cancellation.clone());
+
+    // Get the hops of the walk outside the async executor
+    let result = spawn_blocking(move || {
+        // Reject the job right away while the device is too hot to admit it
+        let _permit = THERMAL.admit()?;
+        let params = [("source", "0".to_string()), ("unweighted", "true".to_string())];
+        let result = with_device(|walker| walker.upload(&matrix).and_then(|graph| walker.breadth_first(&graph, 0, &walk)));
+
+        Ok((result, WALKER.opened().map(|walker| run_id(&matrix_digest(&matrix), "sortest", &params, walker.backend_version()))))
+    }).await;
+
+    let (result, run_id) = match result {
+        Ok(Ok((Err(_), _))) if cancellation.is_expired() => return walk_rejection(&WalkRejection::Timeout(cancellation.check().unwrap_err())),
+        Ok(Ok(result)) => result,
+        Ok(Err(state)) => return walk_rejection(&WalkRejection::Thermal(state)),
+        Err(err) => (Err(computation_failure(err).into()), None)
+    };
+
+    // Return the hops of the walk
+    match result {
+        Ok(path) => response_encoding.respond(HttpResponse::Ok(), Result {
+            path: Some(path),
+            status: "ok".to_string(),
+            message: None,
+            code: None,
+            run_id
+        }),
+        Err(err) => {
+            let error = PathWalkerError::from(err);
+            response_encoding.respond(HttpResponse::build(error.status_code()), error_result(&error, run_id))
+        }
+    }
+}
+
+/// Replace the response of a request whose body is over the size limit
+///
+/// The extractors reject the bodies over the limit with a plain text body,
+/// so the response is replaced with one explaining the limit.
+///
+/// # Arguments
+///
+/// * `res` - The response of the extractor
+/// * `limit` - The maximum size in bytes of a request body
+///
+/// # Returns
+///
+/// * `actix_web::Result<ErrorHandlerResponse<B>>` - The response
+///
+pub fn payload_too_large<B>(res: ServiceResponse<B>, limit: usize) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let (req, _) = res.into_parts();
+    let response = PathWalkerError::PayloadTooLarge(format!("The request body is larger than the limit of {} bytes", limit)).response_with(json!({ "limit": limit }));
+
+    Ok(ErrorHandlerResponse::Response(ServiceResponse::new(req, response).map_into_right_body()))
+}
+
+/// Build the response for a matrix with invalid cells, if it has any
+///
+/// The errors and the warnings of the cells are returned by row and column,
+/// so the client can find them. The warnings of a valid matrix are only
+/// logged.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix, already validated as a whole
+/// * `rules` - What is checked besides the finite weights
+///
+/// # Returns
+///
+/// * `Option<HttpResponse>` - The response, if the matrix has invalid cells
+///
+pub fn invalid_cells<T: Real>(matrix: &Matrix<T>, rules: ValidationRules) -> Option<HttpResponse> {
+    let validation = matrix.validate(rules);
+
+    if let Some(error) = validation.errors.first() {
+        return Some(PathWalkerError::Validation(format!("The matrix has invalid weights: {}", error)).response_with(json!({
+            "errors": validation.errors,
+            "warnings": validation.warnings
+        })));
+    }
+
+    if let Some(warning) = validation.warnings.first() {
+        warn!("The matrix has {} suspicious cells: {}", validation.warnings.len(), warning);
+    }
+
+    None
+}
+
+/// Build the response for a matrix that doesn't fit in the device memory
+///
+/// The memory needed by the matrix and the buffers of its walk is checked
+/// against the memory budget and the global memory of the device, which is
+/// queried without opening it.
+///
+/// # Arguments
+///
+/// * `width` - The vertices of the matrix, already validated
+///
+/// # Returns
+///
+/// * `Option<HttpResponse>` - The response, if the matrix doesn't fit
+///
+pub fn memory_rejection<T>(width: usize) -> Option<HttpResponse> {
+    let shortfall = check_fit(walk_bytes::<T>(width), first_device_memory()).err()?;
+
+    Some(PathWalkerError::OutOfMemory(format!("The matrix needs {} bytes of device memory, but only {} are available", shortfall.required, shortfall.available)).response_with(json!({
+        "required_bytes": shortfall.required,
+        "available_bytes": shortfall.available
+    })))
+}
+
+/// Build the response for a request to a feature disabled by the license
+///
+/// # Arguments
+///
+/// * `message` - The reason why the feature is disabled
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+pub fn entitlement_rejection(message: String) -> HttpResponse {
+    PathWalkerError::Forbidden(message).response()
+}
+
+/// The sortest path endpoint
+///
+/// Exposes a endpoint that receives a matrix with the height and width of the matrix
+/// and the matrix itself and returns the path of the walk. The body can be encoded
+/// as JSON, bincode or protocol buffers, and the response is encoded as asked in the
+/// `Accept` header, or in the same encoding as the request otherwise. The walk is
+/// computed in the precision given in the `precision` query parameter, `single`,
+/// `double` or `integer`, or in the configured one otherwise. The cells that are
+/// not a number or infinite are rejected, and the ones of a matrix expected to
+/// be `symmetric` are checked against their opposite. The matrices whose edges
+/// all cost the same are walked breadth first, and any matrix is when it's
+/// walked `unweighted`, counting the hops instead of adding the weights. The
+/// JSON bodies can have a `target` and a `postprocess` pipeline, returning the
+/// route to the target left by the pipeline along with the path
+///
+/// # Arguments
+///
+/// * `req` - The request
+/// * `query` - The query parameters with the precision of the walk
+/// * `body` - The body of the request
+///
+/// # Returns
+///
+/// * `HttpResponder` - The response
+///
+#[utoipa::path(
+    post,
+    path = "/v1/sortest",
+    params(
+        ("precision" = Option<String>, Query, description = "The precision of the walk, single, double or integer"),
+        ("symmetric" = Option<bool>, Query, description = "If the matrix is expected to be symmetric, warning about the asymmetric cells"),
+        ("unweighted" = Option<bool>, Query, description = "If the walk counts the hops instead of adding the weights")
+    ),
+    request_body(content(
+        (Matrix = "application/json"),
+        (Matrix = "application/octet-stream"),
+        (Matrix = "application/x-protobuf")
+    )),
+    responses(
+        (status = 200, description = "The path of the walk", content((Result = "application/json"), (String = "text/csv"))),
+        (status = 400, description = "The matrix is not valid", body = Result),
+        (status = 415, description = "The content type is not supported"),
+        (status = 500, description = "The walk has panicked", body = Result),
+        (status = 501, description = "The device can't walk the matrix in the precision asked for", body = Result),
+        (status = 502, description = "The kernels have failed", body = Result),
+        (status = 503, description = "The device is too hot to admit new jobs"),
+        (status = 504, description = "The walk has run over its timeout"),
+        (status = 507, description = "The matrix doesn't fit in the device memory")
+    )
+)]
+#[post("/sortest")]
+pub async fn sortest_path_endpoint(req: HttpRequest, query: Query<SortestQuery>, body: Bytes) -> HttpResponse {
+    // Negotiate the encoding of the request and the response
+    let request_encoding = match Encoding::of_request(&req) {
+        Some(encoding) => encoding,
+        None => return PathWalkerError::UnsupportedMediaType("The content type is not supported".to_string()).response()
+    };
+    let response_encoding = Encoding::of_response(&req, request_encoding);
+
+    // Choose the precision of the walk
+    let precision = match query.precision.as_deref() {
+        Some(name) => match Precision::parse(name) {
+            Some(precision) => precision,
+            None => return PathWalkerError::Validation(format!("The precision {} is not valid, it must be single, double or integer", name)).response()
+        },
+        None => *DEFAULT_PRECISION.read().unwrap()
+    };
+
+    if query.unweighted && precision != Precision::Single {
+        return PathWalkerError::Validation("The unweighted walks count the hops in single precision, so they can't have another precision".to_string()).response();
+    }
+
+    // Read the pipeline of the route to the target, which only the JSON bodies have
+    let pipeline = match route_pipeline(&body, request_encoding) {
+        Ok(pipeline) => pipeline,
+        Err(message) => return PathWalkerError::Validation(message).response()
+    };
+
+    if !pipeline.postprocess.is_empty() && (precision != Precision::Single || query.unweighted) {
+        return PathWalkerError::Validation("The routes can only be post-processed for the single precision weighted walks".to_string()).response();
+    }
+
+    let rules = ValidationRules { warn_asymmetric: query.symmetric, ..ValidationRules::default() };
+
+    match precision {
+        Precision::Single => {},
+        Precision::Double => return precise_sortest_path::<f64>(&body, request_encoding, response_encoding, rules).await,
+        Precision::Integer => return precise_sortest_path::<u32>(&body, request_encoding, response_encoding, rules).await
+    }
+
+    // Read the request and deserialize it
+    let matrix: Matrix = match request_encoding.decode_matrix(&body) {
+        Ok(matrix) => matrix,
+        Err(err) => return response_encoding.respond(HttpResponse::BadRequest(), error_result(&PathWalkerError::Validation(format!("The matrix can't be parsed: {}", err)), None))
+    };
+
+    // Validate the matrix before computing the path
+    if let Some(message) = validate_matrix(&matrix) {
+        return response_encoding.respond(HttpResponse::BadRequest(), error_result(&PathWalkerError::Validation(message.to_string()), None));
+    }
+
+    let error = match (&pipeline.postprocess[..], pipeline.target) {
+        ([], _) => None,
+        (_, None) => Some("The post-processed route needs a target"),
+        (_, Some(target)) if target >= matrix.width => Some("The target is outside of the matrix"),
+        (transforms, Some(_)) => validate_pipeline(transforms, matrix.width, false)
+    };
+
+    if let Some(message) = error {
+        return response_encoding.respond(HttpResponse::BadRequest(), error_result(&PathWalkerError::Validation(message.to_string()), None));
+    }
+
+    if let Some(response) = invalid_cells(&matrix, rules).or_else(|| memory_rejection::<f32>(matrix.width)) {
+        return response;
+    }
+
+    // Print the request
+    info!("Received request for matrix: {:?}", matrix);
+
+    if !pipeline.postprocess.is_empty() {
+        info!("Post-processing the route with {}", pipeline.postprocess.iter().map(Transform::name).collect::<Vec<_>>().join(", "));
+    }
+
+    // Count the hops instead of adding the weights when the client asks for it
+    if query.unweighted {
+        return unweighted_sortest_path(matrix, response_encoding).await;
+    }
+
+    // Get the path of the walk outside the async executor
+    let (result, cached, run_id) = match blocking_sortest_path(&matrix).await {
+        Ok(result) => result,
+        Err(rejection) => return walk_rejection(&rejection)
+    };
+
+    if let Some(run_id) = &run_id {
+        info!("Walked the matrix as run {}", run_id);
+    }
+
+    // Record the computation when it fails or when the client asks for it, as long as the device could be opened
+    let reason = match result {
+        _ if run_id.is_none() => None,
+        Err(_) => Some("kernel-error"),
+        Ok(_) if req.headers().contains_key(RECORD_HEADER) => Some("debug-header"),
+        Ok(_) => None
+    };
+    let bundle = reason.zip(WALKER.opened()).and_then(|(reason, walker)| {
+        match replay::record_bundle(&replay::build_bundle(&walker, reason, &matrix, &result)) {
+            Ok(id) => Some(id),
+            Err(err) => {
+                error!("The replay bundle can't be recorded: {}", err);
+                None
+            }
+        }
+    });
+
+    // Mirror the request to the shadow backend without delaying the response
+    if let Ok(path) = &result {
+        if SHADOW.should_mirror() {
+            let (matrix, path) = (matrix.clone(), path.clone());
+            rt::spawn(async move { SHADOW.mirror(matrix, path).await });
+        }
+    }
+
+    // Return the path of the walk
+    let result = result.map_err(PathWalkerError::from);
+    let mut builder = match &result {
+        Ok(_) => HttpResponse::Ok(),
+        Err(error) => HttpResponse::build(error.status_code())
+    };
+
+    if let Some(bundle) = bundle {
+        builder.insert_header((BUNDLE_HEADER, bundle));
+    }
+
+    builder.insert_header((CACHE_HEADER, if cached { "hit" } else { "miss" }));
+
+    let path = match result {
+        Ok(path) => path,
+        Err(error) => return response_encoding.respond(builder, error_result(&error, run_id))
+    };
+
+    let result = Result {
+        path: Some(path.iter().map(|hop| hop.round(matrix.round)).collect()),
+        status: "ok".to_string(),
+        message: None,
+        code: None,
+        run_id
+    };
+
+    // Return the route to the target left by the pipeline along with the path, when there is one
+    match pipeline.target.filter(|_| !pipeline.postprocess.is_empty()) {
+        None => response_encoding.respond(builder, result),
+        Some(target) => match route(&path, 0, target, matrix.zero_edges) {
+            Some(route) => {
+                let processed = postprocess(route_legs(&matrix, &route), &pipeline.postprocess, 0, GridTopology::default());
+                processed_response(builder, result, processed.round(matrix.round))
+            },
+            None => PathWalkerError::Unprocessable(format!("The target {} can't be reached from 0", target)).response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, middleware::ErrorHandlers, test::{call_and_read_body, call_and_read_body_json, call_service, init_service, read_body_json, TestRequest}, web::PayloadConfig, App};
+    use crate::models::PathResult;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_sortest_path_rest_endpoint() {
+        // Prepare the matrix
+        let matrix = Matrix::new(6, 6, vec![
+            01.0, 04.0, 02.0, 00.0, 00.0, 00.0,
+            04.0, 01.0, 01.0, 05.0, 00.0, 00.0,
+            02.0, 01.0, 01.0, 08.0, 10.0, 00.0,
+            00.0, 05.0, 08.0, 01.0, 02.0, 06.0,
+            00.0, 00.0, 10.0, 02.0, 01.0, 02.0,
+            00.0, 00.0, 00.0, 06.0, 02.0, 01.0
+        ]);
+
+        // Prepare the expected result
+        let expected = vec![
+            PathResult(0, 0.0),
+            PathResult(2, 3.0),
+            PathResult(0, 2.0),
+            PathResult(1, 8.0),
+            PathResult(3, 10.0),
+            PathResult(4, 12.0)
+        ];
+
+        // Get the result
+        let app = init_service(
+            App::new().service(sortest_path_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/sortest")
+            .set_json(&matrix)
+            .to_request();
+
+        // Get the response
+        let resp: Result = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "ok");
+        assert_eq!(resp.path.unwrap(), expected);
+        assert_eq!(resp.run_id.map(|run_id| run_id.len()), Some(32));
+    }
+
+    #[actix_web::test]
+    async fn test_sortest_path_rest_endpoint_postprocess() {
+        // Prepare the matrix with a pipeline aggregating the route to the last vertex by the labels of its vertices
+        let matrix = json!({
+            "data": [
+                0.0, 4.0, 2.0, 0.0, 0.0, 0.0,
+                4.0, 0.0, 1.0, 5.0, 0.0, 0.0,
+                2.0, 1.0, 0.0, 8.0, 10.0, 0.0,
+                0.0, 5.0, 8.0, 0.0, 2.0, 6.0,
+                0.0, 0.0, 10.0, 2.0, 0.0, 2.0,
+                0.0, 0.0, 0.0, 6.0, 2.0, 0.0
+            ],
+            "width": 6,
+            "height": 6,
+            "target": 5,
+            "postprocess": [
+                { "aggregate_legs": { "labels": ["city", "city", "city", "road", "road", "road"] } },
+                { "convert_units": { "factor": 0.5, "unit": "h" } }
+            ]
+        });
+        let mut collinear = matrix.clone();
+        collinear["postprocess"] = json!(["simplify_collinear"]);
+
+        // Get the result
+        let app = init_service(
+            App::new().service(sortest_path_endpoint)
+        ).await;
+
+        let resp: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/sortest").set_json(&matrix).to_request()).await;
+        let invalid: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/sortest").set_json(&collinear).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(resp["status"], "ok");
+        assert_eq!(resp["path"].as_array().map(Vec::len), Some(6));
+        assert_eq!(resp["postprocessed"]["legs"].as_array().unwrap().iter().map(|leg| (leg["cost"].as_f64().unwrap(), leg["steps"].as_u64().unwrap())).collect::<Vec<_>>(), vec![(1.5, 2), (4.5, 3)]);
+        assert_eq!(resp["postprocessed"]["cost"], 6.0);
+        assert_eq!(resp["postprocessed"]["unit"], "h");
+        assert_eq!(invalid["message"], "The simplify_collinear transform needs the cells of a grid");
+    }
+
+    #[actix_web::test]
+    async fn test_sortest_path_rest_endpoint_invalid_matrix() {
+        // Prepare the matrix
+        let matrix = Matrix::new(3, 3, vec![
+            -01.0,  10.0,  18.0, -01.0, -01.0, -01.0, -01.0,
+            -01.0, -01.0,  06.0, -01.0,  03.0, -01.0, -01.0,
+            -01.0, -01.0, -01.0,  03.0, -01.0,  20.0, -01.0,
+            -01.0, -01.0,  02.0, -01.0, -01.0, -01.0,  02.0,
+            -01.0, -01.0, -01.0,  08.0, -01.0, -01.0,  10.0,
+            -01.0, -01.0, -01.0, -01.0, -01.0, -01.0, -01.0,
+            -01.0, -01.0, -01.0, -01.0, -01.0,  05.0, -01.0
+        ]);
+
+        // Prepare the expected result
+        let expected = "The matrix size and dimensions are not the same";
+
+        // Get the result
+        let app = init_service(
+            App::new().service(sortest_path_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/sortest")
+            .set_json(&matrix)
+            .to_request();
+
+        // Get the response
+        let resp: Result = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "error");
+        assert_eq!(resp.message.unwrap(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_sortest_path_rest_endpoint_precision() {
+        // Prepare a double precision matrix whose weights don't fit in single precision
+        let matrix = Matrix::<f64>::new(2, 2, vec![0.0, 0.1, 1e300, 0.0]);
+
+        // Get the result
+        let app = init_service(
+            App::new().service(sortest_path_endpoint)
+        ).await;
+
+        // Prepare the requests
+        let invalid = TestRequest::post().uri("/sortest?precision=half").set_json(&matrix).to_request();
+        let protobuf = TestRequest::post()
+            .uri("/sortest?precision=double")
+            .insert_header(("Content-Type", encoding::PROTOBUF_MEDIA_TYPE))
+            .set_payload(Vec::new())
+            .to_request();
+        let unsized_matrix = TestRequest::post()
+            .uri("/sortest?precision=double")
+            .set_json(Matrix::<f64>::new(3, 3, vec![0.0, 1.0]))
+            .to_request();
+        let fractional = TestRequest::post()
+            .uri("/sortest?precision=integer")
+            .set_json(&matrix)
+            .to_request();
+        let unweighted = TestRequest::post()
+            .uri("/sortest?precision=double&unweighted=true")
+            .set_json(&matrix)
+            .to_request();
+
+        // Get the responses
+        let invalid = call_service(&app, invalid).await;
+        let protobuf: Result<f64> = call_and_read_body_json(&app, protobuf).await;
+        let unsized_matrix: Result<f64> = call_and_read_body_json(&app, unsized_matrix).await;
+        let fractional = call_service(&app, fractional).await;
+        let unweighted: serde_json::Value = call_and_read_body_json(&app, unweighted).await;
+
+        // Check if the result is correct
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(protobuf.message.unwrap(), "The matrix can't be parsed: the protocol buffers matrices only have single precision weights");
+        assert_eq!(unsized_matrix.message.unwrap(), "The matrix size and dimensions are not the same");
+        assert_eq!(fractional.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(unweighted["message"], "The unweighted walks count the hops in single precision, so they can't have another precision");
+    }
+
+    #[actix_web::test]
+    async fn test_sortest_path_rest_endpoint_invalid_cells() {
+        // Prepare a matrix with a weight too large for single precision, which becomes infinite
+        let body = r#"{"width": 2, "height": 2, "data": [0, 1, 1e39, 0]}"#;
+
+        // Get the result
+        let app = init_service(
+            App::new().service(sortest_path_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/sortest?symmetric=true")
+            .insert_header(("Content-Type", encoding::JSON_MEDIA_TYPE))
+            .set_payload(body)
+            .to_request();
+
+        // Get the response
+        let resp: serde_json::Value = call_and_read_body_json(&app, req).await;
+
+        // Check if the result is correct
+        assert_eq!(resp["status"], "error");
+        assert_eq!(resp["errors"], json!([{ "row": 1, "column": 0, "reason": "The weight is infinite" }]));
+        assert_eq!(resp["warnings"].as_array().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_sortest_path_rest_endpoint_bincode_invalid_matrix() {
+        // Prepare the matrix
+        let matrix = Matrix::<f32>::new(2, 2, vec![]);
+
+        // Prepare the expected result
+        let expected = "The matrix is empty";
+
+        // Get the result
+        let app = init_service(
+            App::new().service(sortest_path_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/sortest")
+            .insert_header(("Content-Type", encoding::BINCODE_MEDIA_TYPE))
+            .set_payload(bincode::serialize(&matrix).unwrap())
+            .to_request();
+
+        // Get the response
+        let resp: Result = bincode::deserialize(&call_and_read_body(&app, req).await).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "error");
+        assert_eq!(resp.message.unwrap(), expected);
+    }
+
+    #[actix_web::test]
+    async fn test_sortest_path_rest_endpoint_payload_too_large() {
+        // Prepare a matrix larger than the limit
+        let matrix = Matrix::new(32, 32, vec![1.0; 32 * 32]);
+
+        // Get the result
+        let app = init_service(
+            App::new()
+                .wrap(ErrorHandlers::new().handler(StatusCode::PAYLOAD_TOO_LARGE, |res| payload_too_large(res, 1024)))
+                .app_data(PayloadConfig::new(1024))
+                .service(sortest_path_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post()
+            .uri("/sortest")
+            .set_json(&matrix)
+            .to_request();
+
+        // Get the response
+        let resp = call_service(&app, req).await;
+        let status = resp.status();
+        let body: serde_json::Value = read_body_json(resp).await;
+
+        // Check if the result is correct
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["message"], "The request body is larger than the limit of 1024 bytes");
+    }
+}
@@ -0,0 +1,325 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::rt;
+use log::{error, info, warn};
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Matrix, Result, ValidationRules};
+use crate::services::auth::ApiKeys;
+use super::{cached_sortest_path, computation_cancellation, validate_matrix, walk_run_id};
+
+/// The environment variable with the URL of the MQTT broker
+pub const MQTT_URL_VARIABLE: &str = "PATHWALKER_MQTT_URL";
+
+/// The environment variable with the topic of the path requests
+pub const MQTT_TOPIC_VARIABLE: &str = "PATHWALKER_MQTT_TOPIC";
+
+/// The environment variable with the topic of the results
+pub const MQTT_REPLY_TOPIC_VARIABLE: &str = "PATHWALKER_MQTT_REPLY_TOPIC";
+
+/// The topic of the path requests by default
+const DEFAULT_TOPIC: &str = "pathwalker/requests";
+
+/// The topic of the results by default
+const DEFAULT_REPLY_TOPIC: &str = "pathwalker/results";
+
+/// How long to wait before reconnecting to the broker
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// A path request received from the broker
+///
+/// # Fields
+///
+/// * `id` - The identifier of the request, echoed in the reply
+/// * `reply_to` - The topic of the reply, instead of the configured one
+/// * `api_key` - The API key of the client, required when the server has any
+/// * `matrix` - The matrix to walk
+///
+#[derive(Debug, Deserialize)]
+pub struct PathMessage {
+    pub id: Option<String>,
+    pub reply_to: Option<String>,
+    pub api_key: Option<String>,
+    #[serde(flatten)]
+    pub matrix: Matrix,
+}
+
+/// The reply published for a path request
+#[derive(Debug, Serialize)]
+pub struct PathReply {
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub result: Result,
+}
+
+/// Build the reply of a request that can't be walked
+fn error_reply(id: Option<String>, message: String) -> PathReply {
+    PathReply { id, result: Result { path: None, status: "error".to_string(), message: Some(message), code: None, run_id: None } }
+}
+
+/// Answer a path request received from the broker
+///
+/// # Arguments
+///
+/// * `payload` - The payload of the message
+/// * `keys` - The API keys allowed to use the server
+///
+/// # Returns
+///
+/// * `(Option<String>, PathReply)` - The topic asked for the reply, if any, and the reply
+///
+pub async fn answer_message(payload: &[u8], keys: &ApiKeys) -> (Option<String>, PathReply) {
+    // Read the request and validate the matrix
+    let message: PathMessage = match serde_json::from_slice(payload) {
+        Ok(message) => message,
+        Err(err) => return (None, error_reply(None, format!("The request can't be parsed: {}", err)))
+    };
+
+    // Identify the owner of the key when the server has any
+    if keys.is_enabled() {
+        match message.api_key.as_deref().and_then(|key| keys.identify(key)) {
+            Some(name) => info!("MQTT request authenticated with the API key {}", name),
+            None => {
+                warn!("Rejected unauthenticated MQTT request {}", message.id.as_deref().unwrap_or("without identifier"));
+                return (message.reply_to, error_reply(message.id, "A valid API key is required".to_string()));
+            }
+        }
+    }
+
+    if let Some(reason) = validate_matrix(&message.matrix) {
+        return (message.reply_to, error_reply(message.id, reason.to_string()));
+    }
+
+    if let Some(error) = message.matrix.validate(ValidationRules::default()).errors.first() {
+        return (message.reply_to, error_reply(message.id, error.to_string()));
+    }
+
+    // Get the path of the walk outside the async executor
+    let matrix = message.matrix;
+    let round = matrix.round;
+    let cancellation = computation_cancellation();
+    let reply = match rt::task::spawn_blocking(move || cached_sortest_path(&matrix, &cancellation).map(|(result, _)| (result, walk_run_id(&matrix)))).await {
+        Ok(Ok((Ok(path), run_id))) => PathReply {
+            id: message.id,
+            result: Result {
+                path: Some(path.iter().map(|hop| hop.round(round)).collect()),
+                status: "ok".to_string(),
+                message: None,
+                code: None,
+                run_id
+            }
+        },
+        Ok(Ok((Err(err), _))) => error_reply(message.id, err.to_string()),
+        Ok(Err(_)) => error_reply(message.id, "The device is too hot to admit new jobs, retry later".to_string()),
+        Err(err) => {
+            error!("The MQTT computation has panicked: {}", err);
+            error_reply(message.id, "The computation has been aborted".to_string())
+        }
+    };
+
+    (message.reply_to, reply)
+}
+
+/// Check if the topic of a message matches a topic filter
+///
+/// A `+` level of the filter matches any single level of the topic, and a
+/// trailing `#` matches any number of levels, including none. The topics
+/// starting with `$`, like the ones of the broker, are never matched by a
+/// wildcard in the first level.
+///
+/// # Arguments
+///
+/// * `filter` - The topic filter subscribed to
+/// * `topic` - The topic of the message
+///
+/// # Returns
+///
+/// * `bool` - If the message is one of the subscription
+///
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+
+    let (mut filter, mut topic) = (filter.split('/'), topic.split('/'));
+
+    loop {
+        match (filter.next(), topic.next()) {
+            (Some("#"), _) => return filter.next().is_none(),
+            (Some("+"), Some(_)) => {},
+            (Some(expected), Some(level)) if expected == level => {},
+            (None, None) => return true,
+            _ => return false
+        }
+    }
+}
+
+/// The MQTT ingestion of path requests
+///
+/// Subscribes to a topic of the broker and publishes the result of every
+/// request to the reply topic, sharing the services layer with the REST
+/// interface, for clients that can't keep an HTTP connection open.
+///
+/// # Fields
+///
+/// * `options` - The options of the connection to the broker
+/// * `topic` - The topic of the path requests
+/// * `reply_topic` - The topic of the results
+/// * `keys` - The API keys allowed to use the server
+///
+pub struct MqttIngest {
+    options: MqttOptions,
+    topic: String,
+    reply_topic: String,
+    keys: Arc<ApiKeys>,
+}
+
+impl MqttIngest {
+    /// Create the ingestion with the broker configured in the environment
+    ///
+    /// The URL of the broker must name the client, like
+    /// `mqtt://broker:1883?client_id=pathwalker`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<MqttIngest>` - The ingestion, or nothing when no valid broker is configured
+    ///
+    pub fn from_env() -> Option<MqttIngest> {
+        let url = std::env::var(MQTT_URL_VARIABLE).ok().filter(|url| !url.is_empty())?;
+        let options = match MqttOptions::parse_url(url.as_str()) {
+            Ok(options) => options,
+            Err(err) => {
+                error!("The MQTT broker {} is not valid: {}", url, err);
+                return None;
+            }
+        };
+
+        Some(MqttIngest {
+            options,
+            topic: std::env::var(MQTT_TOPIC_VARIABLE).unwrap_or_else(|_| DEFAULT_TOPIC.to_string()),
+            reply_topic: std::env::var(MQTT_REPLY_TOPIC_VARIABLE).unwrap_or_else(|_| DEFAULT_REPLY_TOPIC.to_string()),
+            keys: Arc::new(ApiKeys::default())
+        })
+    }
+
+    /// Require the API keys of the server on every request
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - The API keys allowed to use the server, shared with the other interfaces
+    ///
+    /// # Returns
+    ///
+    /// * `MqttIngest` - The ingestion
+    ///
+    pub fn with_api_keys(mut self, keys: Arc<ApiKeys>) -> MqttIngest {
+        self.keys = keys;
+        self
+    }
+
+    /// Get the topic of the path requests
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Answer the requests of the broker until the server stops
+    ///
+    /// The connection is retried forever, subscribing again on every
+    /// connection, so a flaky link only delays the requests.
+    pub async fn run(self) {
+        let (client, mut eventloop) = AsyncClient::new(self.options, 64);
+
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    // Subscribe again, as the session may have been lost
+                    info!("Connected to the MQTT broker, subscribing to {}", self.topic);
+
+                    if let Err(err) = client.subscribe(self.topic.as_str(), QoS::AtLeastOnce).await {
+                        error!("The MQTT topic {} can't be subscribed: {}", self.topic, err);
+                    }
+                },
+                Ok(Event::Incoming(Packet::Publish(publish))) if topic_matches(&self.topic, &publish.topic) => {
+                    // Answer the request without blocking the connection
+                    let (client, reply_topic, keys) = (client.clone(), self.reply_topic.clone(), self.keys.clone());
+
+                    rt::spawn(async move {
+                        let (reply_to, reply) = answer_message(&publish.payload, &keys).await;
+                        let payload = serde_json::to_vec(&reply).unwrap();
+
+                        if let Err(err) = client.publish(reply_to.unwrap_or(reply_topic), QoS::AtLeastOnce, false, payload).await {
+                            error!("The MQTT reply can't be published: {}", err);
+                        }
+                    });
+                },
+                Ok(_) => {},
+                Err(err) => {
+                    warn!("The MQTT connection has failed, reconnecting: {}", err);
+                    rt::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_matches() {
+        // Prepare the filters and the topics
+        let cases = [
+            ("pathwalker/requests", "pathwalker/requests", true),
+            ("pathwalker/requests", "pathwalker/results", false),
+            ("pathwalker/+/requests", "pathwalker/robot-1/requests", true),
+            ("pathwalker/+/requests", "pathwalker/requests", false),
+            ("pathwalker/#", "pathwalker/robot-1/requests", true),
+            ("pathwalker/#", "pathwalker", true),
+            ("pathwalker/#/requests", "pathwalker/robot-1/requests", false),
+            ("#", "$SYS/broker/load", false)
+        ];
+
+        // Get the result
+        let matches = cases.map(|(filter, topic, _)| topic_matches(filter, topic));
+
+        // Check if the result is correct
+        assert_eq!(matches, cases.map(|(_, _, expected)| expected));
+    }
+
+    #[actix_web::test]
+    async fn test_answer_invalid_message() {
+        // Prepare the messages
+        let malformed = b"{\"id\": \"robot-1\"";
+        let not_square = br#"{"id": "robot-1", "reply_to": "robots/1", "width": 2, "height": 1, "data": [0, 1]}"#;
+
+        // Get the result
+        let (malformed_topic, malformed_reply) = answer_message(malformed, &ApiKeys::default()).await;
+        let (topic, reply) = answer_message(not_square, &ApiKeys::default()).await;
+
+        // Check if the result is correct
+        assert_eq!(malformed_topic, None);
+        assert_eq!(malformed_reply.result.status, "error");
+        assert_eq!(topic, Some("robots/1".to_string()));
+        assert_eq!(reply.id, Some("robot-1".to_string()));
+        assert_eq!(reply.result.message, Some("The matrix is not square".to_string()));
+    }
+
+    #[actix_web::test]
+    async fn test_answer_unauthenticated_message() {
+        // Prepare the keys and the messages
+        let keys = ApiKeys::new(&[("robots".to_string(), "s3cr3t".to_string())]).unwrap();
+        let anonymous = br#"{"id": "robot-1", "width": 2, "height": 1, "data": [0, 1]}"#;
+        let authenticated = br#"{"id": "robot-1", "api_key": "s3cr3t", "width": 2, "height": 1, "data": [0, 1]}"#;
+
+        // Get the result
+        let (_, anonymous) = answer_message(anonymous, &keys).await;
+        let (_, authenticated) = answer_message(authenticated, &keys).await;
+
+        // Check if the result is correct
+        assert_eq!(anonymous.result.message, Some("A valid API key is required".to_string()));
+        assert_eq!(authenticated.result.message, Some("The matrix is not square".to_string()));
+    }
+}
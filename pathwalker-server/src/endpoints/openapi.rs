@@ -0,0 +1,43 @@
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::models::{Matrix, PathResult, Result};
+
+/// The OpenAPI document of the REST interface
+///
+/// Generated from the annotations of the endpoints and the schemas of the
+/// models, so it can't drift from the JSON accepted by the server
+///
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "PathWalker", description = "Sortest path computation accelerated with OpenCL"),
+    paths(super::sortest_path_endpoint),
+    components(schemas(Matrix, PathResult, Result))
+)]
+pub struct ApiDoc;
+
+/// Create the service with the OpenAPI document and the Swagger UI
+///
+/// # Returns
+///
+/// * `SwaggerUi` - The service serving the document at `/openapi.json` and the UI at `/docs`
+///
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new("/docs/{_:.*}").url("/openapi.json", ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_document() {
+        // Get the result
+        let document = ApiDoc::openapi().to_json().unwrap();
+
+        // Check if the result is correct
+        assert!(document.contains("\"/v1/sortest\""));
+        assert!(document.contains("\"Matrix\""));
+        assert!(document.contains("\"PathResult\""));
+    }
+}
@@ -0,0 +1,113 @@
+use actix_web::{post, HttpResponse, web::Json};
+use log::info;
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::models::{PageRankRequest, ValidationRules};
+use crate::services::entitlement::Feature;
+use crate::services::pagerank::validate_pagerank;
+use crate::services::run_id::{matrix_digest, run_id};
+use crate::utils::{round_decimals, spawn_blocking};
+use super::{computation_cancellation, computation_failure, entitlement_rejection, invalid_cells, thermal_rejection, walk_rejection, WalkRejection, ENTITLEMENTS, THERMAL, with_device};
+
+/// The PageRank endpoint
+///
+/// Exposes a endpoint that receives a matrix along with the parameters of
+/// the power method, and returns the PageRank of every vertex, computed in
+/// the device with a kernel launch per iteration, along with the iterations
+/// actually run
+///
+/// # Arguments
+///
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/pagerank")]
+pub async fn pagerank_endpoint(item: Json<PageRankRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::PageRank) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and validate it before ranking the vertices
+    let request: PageRankRequest = item.into_inner();
+
+    if let Some(message) = validate_pagerank(&request) {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    if let Some(response) = invalid_cells(&request.matrix, ValidationRules::default()) {
+        return response;
+    }
+
+    // Reject the job right away while the device is too hot to admit it
+    let permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(state) => return thermal_rejection(&state)
+    };
+
+    // Print the request
+    info!("Received PageRank request over {} vertices with damping {} and up to {} iterations", request.matrix.width, request.damping, request.max_iterations);
+
+    // Rank the vertices outside the async executor
+    let round = request.matrix.round;
+    let cancellation = computation_cancellation();
+    let (_cancel, rank_cancellation) = (cancellation.on_drop(), cancellation.clone());
+    let ranking = spawn_blocking(move || {
+        let _permit = permit;
+        let parameters = [
+            ("damping", request.damping.to_string()),
+            ("tolerance", request.tolerance.to_string()),
+            ("max_iterations", request.max_iterations.to_string()),
+            ("round", format!("{:?}", round))
+        ];
+        with_device(|walker| {
+            let run_id = run_id(&matrix_digest(&request.matrix), "pagerank", &parameters, walker.backend_version());
+
+            walker.pagerank(&request, &rank_cancellation).map(|rank| (rank, run_id))
+        })
+    }).await;
+
+    // Return the score of every vertex
+    match ranking.map_err(computation_failure) {
+        Ok(Ok((rank, run_id))) => HttpResponse::Ok().json(json!({
+            "status": "ok",
+            "scores": rank.scores.into_iter().map(|score| round_decimals(score, round)).collect::<Vec<f32>>(),
+            "iterations": rank.iterations,
+            "converged": rank.converged,
+            "run_id": run_id
+        })),
+        Ok(Err(err)) if cancellation.is_expired() => walk_rejection(&WalkRejection::Timeout(err.to_string())),
+        Ok(Err(err)) => PathWalkerError::from(err).response(),
+        Err(message) => PathWalkerError::Internal(message).response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_pagerank_endpoint_invalid_request() {
+        // Prepare a request with a damping factor of one, and one without iterations
+        let damping = json!({ "width": 2, "height": 2, "data": [0, 1, 1, 0], "damping": 1.0 });
+        let iterations = json!({ "width": 2, "height": 2, "data": [0, 1, 1, 0], "max_iterations": 0 });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(pagerank_endpoint)
+        ).await;
+
+        // Get the responses
+        let damping: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/pagerank").set_json(&damping).to_request()).await;
+        let iterations: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/pagerank").set_json(&iterations).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(damping["message"], "The damping factor must be at least zero and lower than one");
+        assert_eq!(iterations["message"], "There must be between one and 10000 iterations");
+    }
+}
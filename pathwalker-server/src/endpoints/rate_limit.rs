@@ -0,0 +1,137 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_web::{body::{BodySize, BoxBody, MessageBody}, dev::{ServiceRequest, ServiceResponse}, http::header::{HeaderValue, RETRY_AFTER}, middleware::Next, web::{Bytes, Data}, Error, HttpMessage};
+use log::warn;
+
+use crate::services::rate_limit::{ClientPermit, RateLimiter};
+use super::auth::{is_public_path, ApiKeyName};
+use super::errors::PathWalkerError;
+
+/// The body of a response that keeps the permit of its request until it has been sent
+///
+/// The streamed responses keep computing after their handler returns, so
+/// their requests count as in flight until the body ends, or until it's
+/// dropped because the client has gone away.
+///
+/// # Fields
+///
+/// * `body` - The body of the response
+/// * `permit` - The permit of the request, while the body is being sent
+///
+struct PermitBody {
+    body: BoxBody,
+    permit: Option<ClientPermit>,
+}
+
+impl MessageBody for PermitBody {
+    type Error = Box<dyn std::error::Error>;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        let next = Pin::new(&mut this.body).poll_next(cx);
+
+        // Release the permit as soon as the whole body has been sent
+        if let Poll::Ready(None) = next {
+            this.permit = None;
+        }
+
+        next
+    }
+}
+
+/// The rate limiting middleware
+///
+/// Limits the requests per second and the requests computed at once of
+/// every client, named by its API key or by its address otherwise, and
+/// rejects the requests over the limits with `429 Too Many Requests` and a
+/// `Retry-After` header. The probes, the metrics and the documentation are
+/// never limited.
+///
+/// # Arguments
+///
+/// * `req` - The request
+/// * `next` - The rest of the services
+///
+/// # Returns
+///
+/// * `Result<ServiceResponse<BoxBody>, Error>` - The response
+///
+pub async fn limit_clients(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, Error> {
+    let limiter = match req.app_data::<Data<RateLimiter>>() {
+        Some(limiter) if limiter.is_enabled() && !is_public_path(req.path()) => limiter.clone().into_inner(),
+        _ => return next.call(req).await.map(ServiceResponse::map_into_boxed_body)
+    };
+
+    // Name the client by its API key, or by its address without one
+    let key = req.extensions().get::<ApiKeyName>().map(|ApiKeyName(name)| format!("key:{}", name));
+    let client = key.unwrap_or_else(|| format!("ip:{}", req.connection_info().peer_addr().unwrap_or("unknown")));
+
+    // Keep the permit until the body of the response has been sent
+    match limiter.acquire(&client) {
+        Ok(permit) => next.call(req).await.map(|res| res.map_body(|_, body| BoxBody::new(PermitBody { body: BoxBody::new(body), permit: Some(permit) }))),
+        Err(retry_after) => {
+            warn!("Rejected request {} {} from {} over its limits", req.method(), req.path(), client);
+
+            let mut response = PathWalkerError::RateLimited("Too many requests, retry later".to_string()).response();
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(retry_after));
+
+            Ok(req.into_response(response))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{middleware::from_fn, test::{call_service, init_service, read_body, TestRequest}, App};
+    use crate::endpoints::health::healthz_endpoint;
+    use crate::endpoints::shadow::shadow_endpoint;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_limit_clients() {
+        // Get the result
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(RateLimiter::new(Some(1.0), None)))
+                .wrap(from_fn(limit_clients))
+                .service(healthz_endpoint)
+                .service(shadow_endpoint)
+        ).await;
+
+        let first = call_service(&app, TestRequest::get().uri("/shadow").to_request()).await;
+        let second = call_service(&app, TestRequest::get().uri("/shadow").to_request()).await;
+        let probe = call_service(&app, TestRequest::get().uri("/healthz").to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(first.status(), 200);
+        assert_eq!(second.status(), 429);
+        assert_eq!(second.headers().get("Retry-After").unwrap(), "1");
+        assert_eq!(probe.status(), 200);
+    }
+
+    #[actix_web::test]
+    async fn test_limit_clients_in_flight_body() {
+        // Prepare a limit of one request computed at once
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(RateLimiter::new(None, Some(1))))
+                .wrap(from_fn(limit_clients))
+                .service(shadow_endpoint)
+        ).await;
+
+        // Get the result
+        let first = call_service(&app, TestRequest::get().uri("/shadow").to_request()).await;
+        let sending = call_service(&app, TestRequest::get().uri("/shadow").to_request()).await;
+        read_body(first).await;
+        let sent = call_service(&app, TestRequest::get().uri("/shadow").to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(sending.status(), 429);
+        assert_eq!(sent.status(), 200);
+    }
+}
@@ -0,0 +1,204 @@
+use actix_web::{delete, post, HttpResponse, web::{Json, Path}};
+use log::{error, info};
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::models::{Replan, ReplanRequest, ReplanUpdate};
+use crate::services::entitlement::Feature;
+use crate::services::replan::Replanner;
+use crate::utils::spawn_blocking;
+use super::{entitlement_rejection, ENTITLEMENTS, SESSIONS};
+
+/// Build the response for a session that doesn't exist
+fn session_not_found(session: &str) -> HttpResponse {
+    PathWalkerError::NotFound(format!("The replanning session {} doesn't exist or has expired", session)).response()
+}
+
+/// Build the response for a plan of a session
+///
+/// # Arguments
+///
+/// * `session` - The id of the session
+/// * `plan` - The plan, or nothing when the goal can't be reached
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+fn plan_response(session: &str, plan: Option<Replan>) -> HttpResponse {
+    match plan {
+        Some(plan) => HttpResponse::Ok().json(json!({
+            "status": "ok",
+            "session": session,
+            "path": plan.path,
+            "cost": plan.cost,
+            "expanded": plan.expanded
+        })),
+        None => PathWalkerError::Unprocessable("The goal can't be reached from the start".to_string()).response_with(json!({ "session": session }))
+    }
+}
+
+/// Build the response for a planner that has panicked
+fn planner_failure(err: impl std::fmt::Display) -> HttpResponse {
+    error!("The replanning has panicked: {}", err);
+    PathWalkerError::Internal("The computation has been aborted".to_string()).response()
+}
+
+/// The endpoint starting a replanning session
+///
+/// Exposes a endpoint that receives a grid or a graph with the start and the
+/// goal of a robot, and returns the first plan along with the session that
+/// keeps the search, so the plan can be repaired as the robot moves
+///
+/// # Arguments
+///
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/replan")]
+pub async fn start_replan_endpoint(item: Json<ReplanRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Replan) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and validate it before searching the plan
+    let replanner = match Replanner::new(item.into_inner()) {
+        Ok(replanner) => replanner,
+        Err(message) => return PathWalkerError::Validation(message).response()
+    };
+
+    // Keep the planner in a new session, searching the first plan outside the async executor
+    let (session, replanner) = match SESSIONS.create(replanner) {
+        Ok(session) => session,
+        Err(message) => return PathWalkerError::Internal(message).response()
+    };
+    info!("Started replanning session {}", session);
+
+    match spawn_blocking(move || replanner.lock().unwrap().plan()).await {
+        Ok(plan) => plan_response(&session, plan),
+        Err(err) => planner_failure(err)
+    }
+}
+
+/// The endpoint repairing the plan of a session
+///
+/// Exposes a endpoint that receives the new position of the robot and the
+/// costs that have changed since the last plan, and returns the plan
+/// repaired incrementally with D* Lite
+///
+/// # Arguments
+///
+/// * `session` - The id of the session
+/// * `item` - The changes observed by the robot
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/replan/{session}")]
+pub async fn update_replan_endpoint(session: Path<String>, item: Json<ReplanUpdate>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Replan) {
+        return entitlement_rejection(message);
+    }
+
+    // Find the planner of the session
+    let session = session.into_inner();
+    let Some(replanner) = SESSIONS.get(&session) else {
+        return session_not_found(&session);
+    };
+
+    // Apply the changes and repair the plan outside the async executor
+    let update = item.into_inner();
+    let result = spawn_blocking(move || {
+        let mut replanner = replanner.lock().unwrap();
+        replanner.apply(&update).map(|_| replanner.plan())
+    }).await;
+
+    match result {
+        Ok(Ok(plan)) => plan_response(&session, plan),
+        Ok(Err(message)) => PathWalkerError::Validation(message).response(),
+        Err(err) => planner_failure(err)
+    }
+}
+
+/// The endpoint ending a replanning session
+///
+/// # Arguments
+///
+/// * `session` - The id of the session
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[delete("/replan/{session}")]
+pub async fn delete_replan_endpoint(session: Path<String>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Replan) {
+        return entitlement_rejection(message);
+    }
+
+    // Forget the planner of the session
+    if SESSIONS.remove(&session) {
+        info!("Ended replanning session {}", session);
+        HttpResponse::Ok().json(json!({ "status": "ok" }))
+    } else {
+        session_not_found(&session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, call_service, init_service, TestRequest}, App};
+    use serde_json::Value;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_replan_endpoints() {
+        // Prepare a square map where the robot goes along the first row
+        let request: Value = json!({
+            "grid": {
+                "width": 4,
+                "height": 2,
+                "costs": [1, 1, 1, 1, 1, 1, 1, 1],
+                "start": [0, 0],
+                "goal": [3, 0]
+            }
+        });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(start_replan_endpoint).service(update_replan_endpoint).service(delete_replan_endpoint)
+        ).await;
+
+        // Prepare the request
+        let req = TestRequest::post().uri("/replan").set_json(&request).to_request();
+
+        // Get the response
+        let plan: Value = call_and_read_body_json(&app, req).await;
+        let session = plan["session"].as_str().unwrap().to_string();
+
+        // Move the robot and wall the cell in front of it
+        let req = TestRequest::post()
+            .uri(&format!("/replan/{}", session))
+            .set_json(json!({ "start": [1, 0], "cells": [{ "cell": [2, 0], "cost": 0 }] }))
+            .to_request();
+        let repaired: Value = call_and_read_body_json(&app, req).await;
+
+        // End the session
+        let deleted = call_service(&app, TestRequest::delete().uri(&format!("/replan/{}", session)).to_request()).await;
+        let missing = call_service(&app, TestRequest::post().uri(&format!("/replan/{}", session)).set_json(json!({})).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(plan["cost"], 3.0);
+        assert_eq!(repaired["path"], json!([[1, 0], [1, 1], [2, 1], [3, 1], [3, 0]]));
+        assert_eq!(repaired["cost"], 4.0);
+        assert_eq!(deleted.status(), 200);
+        assert_eq!(missing.status(), 404);
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{body::MessageBody, dev::{ServiceRequest, ServiceResponse}, http::header::{HeaderName, HeaderValue}, middleware::Next, Error, HttpMessage};
+use lazy_static::lazy_static;
+use tracing::{field, info_span, Instrument};
+
+use crate::services::generator::SplitMix64;
+
+/// The header with the correlation id of a request
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// The longest correlation id propagated from a client
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+lazy_static! {
+    /// The seed of the correlation ids of this process
+    static ref REQUEST_ID_SEED: u64 = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64) ^ u64::from(std::process::id());
+}
+
+/// The number of correlation ids generated
+static GENERATED_IDS: AtomicU64 = AtomicU64::new(0);
+
+/// The correlation id of a request
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestId(pub String);
+
+/// Generate a new correlation id
+///
+/// Every id comes from a different state of the generator, so the ids of a
+/// process never repeat.
+fn generate_request_id() -> String {
+    let mut random = SplitMix64(REQUEST_ID_SEED.wrapping_add(GENERATED_IDS.fetch_add(1, Ordering::Relaxed).wrapping_mul(2)));
+    format!("{:016x}{:016x}", random.next_u64(), random.next_u64())
+}
+
+/// Read the correlation id sent by the client, if it can be propagated
+fn propagated_request_id(req: &ServiceRequest) -> Option<String> {
+    req.headers().get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LENGTH && id.chars().all(|char| char.is_ascii_graphic()))
+        .map(str::to_string)
+}
+
+/// The correlation id middleware
+///
+/// Propagates the `X-Request-Id` header of the client, or assigns a new id
+/// when it's missing or isn't valid, and serves the request inside a span
+/// with the id, so it's attached to every log line and span of the request,
+/// including the ones of the services run in the blocking threads. The id
+/// is returned in the response header.
+///
+/// # Arguments
+///
+/// * `req` - The request
+/// * `next` - The rest of the services
+///
+/// # Returns
+///
+/// * `Result<ServiceResponse<impl MessageBody>, Error>` - The response
+///
+pub async fn assign_request_id(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = propagated_request_id(&req).unwrap_or_else(generate_request_id);
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    // Serve the request inside its span, and return the id to the client
+    let span = info_span!("http_request", method = %req.method(), path = %req.path(), request_id = %id, status = field::Empty);
+    let mut response = next.call(req).instrument(span.clone()).await?;
+    span.record("status", response.status().as_u16());
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(HeaderName::from_static("x-request-id"), value);
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{get, middleware::from_fn, test::{call_service, init_service, TestRequest}, App, HttpResponse};
+    use super::*;
+
+    #[get("/echo")]
+    async fn echo_endpoint(req: actix_web::HttpRequest) -> HttpResponse {
+        HttpResponse::Ok().body(req.extensions().get::<RequestId>().map(|id| id.0.clone()).unwrap_or_default())
+    }
+
+    #[actix_web::test]
+    async fn test_assign_request_id() {
+        // Get the result
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(assign_request_id))
+                .service(echo_endpoint)
+        ).await;
+
+        let propagated = call_service(&app, TestRequest::get().uri("/echo").insert_header((REQUEST_ID_HEADER, "trace-42")).to_request()).await;
+        let assigned = call_service(&app, TestRequest::get().uri("/echo").to_request()).await;
+        let invalid = call_service(&app, TestRequest::get().uri("/echo").insert_header((REQUEST_ID_HEADER, "a".repeat(200))).to_request()).await;
+
+        let header = |response: &ServiceResponse<_>| response.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+        let (assigned_id, invalid_id) = (header(&assigned), header(&invalid));
+        let body = actix_web::test::read_body(propagated).await;
+
+        // Check if the result is correct
+        assert_eq!(body, "trace-42");
+        assert_eq!(assigned_id.len(), 32);
+        assert_eq!(invalid_id.len(), 32);
+        assert_ne!(assigned_id, invalid_id);
+    }
+}
@@ -0,0 +1,18 @@
+use actix_web::{get, HttpResponse};
+use serde_json::json;
+
+use super::SHADOW;
+
+/// The shadow metrics endpoint
+///
+/// Exposes how many requests have been mirrored to the shadow backend, and
+/// how many of them have matched, diverged from or failed in that backend
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[get("/shadow")]
+pub async fn shadow_endpoint() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok", "shadow": SHADOW.stats() }))
+}
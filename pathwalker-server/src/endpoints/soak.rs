@@ -0,0 +1,140 @@
+use std::thread;
+use std::time::Duration;
+
+use actix_web::{delete, get, post, HttpResponse, web::Json};
+use serde_json::json;
+
+use super::errors::PathWalkerError;
+use crate::services::cancellation::Cancellation;
+use crate::services::generator::{GraphSpec, SplitMix64, Topology, WeightDistribution};
+use crate::services::soak::SoakOptions;
+use super::{with_device, SOAK, THERMAL};
+
+/// Run a random workload in the local backend
+///
+/// Walks a random graph in one of the ways the endpoints do, including
+/// uploads abandoned before walking them, as happens when a request fails
+/// after the upload, since those are the paths that could leak buffers.
+///
+/// # Arguments
+///
+/// * `random` - The generator of random numbers of the soak test
+/// * `vertices` - The vertices of the graph
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Nothing, or the reason why the workload has failed
+///
+fn soak_workload(random: &mut SplitMix64, vertices: usize) -> Result<(), String> {
+    let spec = GraphSpec {
+        vertices,
+        seed: random.next_u64(),
+        directed: random.chance(0.5),
+        topology: Topology::Uniform { probability: random.next_f64() },
+        weights: WeightDistribution::Uniform { min: 1.0, max: 100.0 }
+    };
+    let matrix = spec.generate();
+
+    // Reject the job while the device is too hot, like any other job, pausing a second before the next one
+    let _permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(_) => {
+            thread::sleep(Duration::from_secs(1));
+            return Err("The device is too hot to admit new jobs".to_string());
+        }
+    };
+
+    let result = with_device(|walker| match random.next_u64() % 3 {
+        0 => walker.get_sortest_path(matrix).map(|_| ()),
+        1 => walker.upload(&matrix).and_then(|graph| walker.walk(&graph, random.next_u64() as usize % vertices, &Cancellation::default(), |_, _| {})).map(|_| ()),
+        _ => walker.upload(&matrix).map(drop)
+    });
+
+    result.map_err(|err| err.to_string())
+}
+
+/// The soak test start endpoint
+///
+/// Exposes a endpoint that starts running random workloads in the local
+/// backend while sampling the resident and the device memory, failing the
+/// soak test when any of them grows monotonically
+///
+/// # Arguments
+///
+/// * `item` - The options of the soak test
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/admin/soak")]
+pub async fn start_soak_endpoint(item: Json<SoakOptions>) -> HttpResponse {
+    // Validate the options
+    if let Some(message) = item.validate() {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    // Start the soak test unless another one is running
+    if !SOAK.start(item.into_inner(), soak_workload) {
+        return PathWalkerError::Conflict("A soak test is already running".to_string()).response();
+    }
+
+    HttpResponse::Accepted().json(json!({ "status": "ok", "soak": SOAK.report() }))
+}
+
+/// The soak test report endpoint
+///
+/// Exposes the status, the workloads run and the memory samples of the
+/// current or the last soak test
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[get("/admin/soak")]
+pub async fn soak_report_endpoint() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok", "soak": SOAK.report() }))
+}
+
+/// The soak test stop endpoint
+///
+/// Exposes a endpoint that stops the running soak test
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[delete("/admin/soak")]
+pub async fn stop_soak_endpoint() -> HttpResponse {
+    if !SOAK.stop() {
+        return PathWalkerError::NotFound("There is no soak test running".to_string()).response();
+    }
+
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_service, init_service, TestRequest}, App};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_soak_endpoints() {
+        // Get the result
+        let app = init_service(
+            App::new()
+                .service(start_soak_endpoint)
+                .service(soak_report_endpoint)
+                .service(stop_soak_endpoint)
+        ).await;
+
+        let invalid = call_service(&app, TestRequest::post().uri("/admin/soak").set_json(json!({ "vertices": 1000 })).to_request()).await;
+        let report = call_service(&app, TestRequest::get().uri("/admin/soak").to_request()).await;
+        let stop = call_service(&app, TestRequest::delete().uri("/admin/soak").to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(invalid.status(), 400);
+        assert_eq!(report.status(), 200);
+        assert_eq!(stop.status(), 404);
+    }
+}
@@ -0,0 +1,158 @@
+use actix_web::{post, HttpRequest, HttpResponse, web::{Bytes, Json}};
+use log::{error, info};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use super::errors::PathWalkerError;
+use crate::models::{MultiSourceRequest, PathResult, ValidationRules};
+use crate::services::entitlement::Feature;
+use crate::services::opencl;
+use crate::services::run_id::{matrix_digest, run_id};
+use pathwalker_core::solver::max_vertices;
+use crate::utils::spawn_blocking;
+use super::encoding::{accepts_ndjson, ndjson_response};
+use super::{computation_cancellation, computation_failure, entitlement_rejection, invalid_cells, thermal_rejection, validate_static_matrix, walk_rejection, WalkRejection, ENTITLEMENTS, THERMAL, with_device};
+
+/// The multi-source sortest path endpoint
+///
+/// Exposes a endpoint that receives a matrix along with several source
+/// vertices, like the depots of a fleet, and returns the path of the walk
+/// from every source. The matrix is uploaded once and walked from every
+/// source, which is far cheaper than a request per source. When the client
+/// accepts `application/x-ndjson`, a line is streamed for every source as
+/// soon as its walk is read back from the device
+///
+/// # Arguments
+///
+/// * `req` - The HTTP request
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/sortest/sources")]
+pub async fn multi_source_endpoint(req: HttpRequest, item: Json<MultiSourceRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Sources) {
+        return entitlement_rejection(message);
+    }
+
+    // Read the request and validate it before walking the graph
+    let request: MultiSourceRequest = item.into_inner();
+    let width = request.matrix.width;
+
+    let error = validate_static_matrix(&request.matrix).map(str::to_string)
+        .or_else(|| (request.sources.is_empty() || request.sources.len() > max_vertices()).then(|| format!("There must be between one and {} sources", max_vertices())))
+        .or_else(|| request.sources.iter().any(|source| *source >= width).then(|| "The sources are outside of the matrix".to_string()));
+
+    if let Some(message) = error {
+        return PathWalkerError::Validation(message).response();
+    }
+
+    if let Some(response) = invalid_cells(&request.matrix, ValidationRules::default()) {
+        return response;
+    }
+
+    // Reject the job right away while the device is too hot to admit it
+    let permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(state) => return thermal_rejection(&state)
+    };
+
+    // Print the request
+    info!("Received multi-source request of {} sources over {} vertices", request.sources.len(), width);
+
+    // Upload the matrix once and walk it from every source outside the async executor
+    let round = request.matrix.round;
+    let cancellation = computation_cancellation();
+
+    // Stream the walks as they are read back, waiting for every line to be
+    // sent before walking the next source
+    if accepts_ndjson(&req) {
+        let (sender, receiver) = mpsc::channel::<Bytes>(1);
+
+        spawn_blocking(move || {
+            let _permit = permit;
+            let result = with_device(|walker| {
+                let graph = walker.upload(&request.matrix)?;
+
+                for source in &request.sources {
+                    let path = walker.walk(&graph, *source, &cancellation, |_, _| {})?;
+                    let line = json!({ "source": source, "path": path.iter().map(|hop| hop.round(round)).collect::<Vec<PathResult>>() }).to_string() + "\n";
+
+                    if sender.blocking_send(Bytes::from(line)).is_err() {
+                        break;
+                    }
+                }
+
+                Ok(())
+            });
+
+            // Close the stream with the error of the kernels
+            if let Err(err) = result {
+                error!("The multi-source computation has failed: {}", err);
+                let _ = sender.blocking_send(Bytes::from(PathWalkerError::from(err).body().to_string() + "\n"));
+            }
+        });
+
+        return ndjson_response(receiver);
+    }
+
+    let (_cancel, walk_cancellation) = (cancellation.on_drop(), cancellation.clone());
+    let walk = spawn_blocking(move || {
+        let _permit = permit;
+        with_device(|walker| {
+            let graph = walker.upload(&request.matrix)?;
+
+            let parameters = [("sources", format!("{:?}", request.sources)), ("round", format!("{:?}", round))];
+            let run_id = run_id(&matrix_digest(&request.matrix), "sortest-sources", &parameters, walker.backend_version());
+
+            request.sources.iter()
+                .map(|source| walker.walk(&graph, *source, &walk_cancellation, |_, _| {}).map(|path| json!({
+                    "source": source,
+                    "path": path.iter().map(|hop| hop.round(round)).collect::<Vec<PathResult>>()
+                })))
+                .collect::<opencl::Result<Vec<_>>>()
+                .map(|results| (results, run_id))
+        })
+    }).await;
+
+    // Return the path of the walk from every source, in the order of the sources
+    match walk.map_err(computation_failure) {
+        Ok(Ok((results, run_id))) => HttpResponse::Ok().json(json!({
+            "status": "ok",
+            "results": results,
+            "run_id": run_id
+        })),
+        Ok(Err(err)) if cancellation.is_expired() => walk_rejection(&WalkRejection::Timeout(err.to_string())),
+        Ok(Err(err)) => PathWalkerError::from(err).response(),
+        Err(message) => PathWalkerError::Internal(message).response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_multi_source_endpoint_invalid_sources() {
+        // Prepare a request with a source outside of the matrix, and one without sources
+        let outside = json!({ "width": 2, "height": 2, "data": [0, 1, 1, 0], "sources": [0, 2] });
+        let empty = json!({ "width": 2, "height": 2, "data": [0, 1, 1, 0], "sources": [] });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(multi_source_endpoint)
+        ).await;
+
+        // Get the responses
+        let outside: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/sortest/sources").set_json(&outside).to_request()).await;
+        let empty: serde_json::Value = call_and_read_body_json(&app, TestRequest::post().uri("/sortest/sources").set_json(&empty).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(outside["message"], "The sources are outside of the matrix");
+        assert_eq!(empty["message"], "There must be between one and 128 sources");
+    }
+}
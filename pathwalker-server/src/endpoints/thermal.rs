@@ -0,0 +1,18 @@
+use actix_web::{get, HttpResponse};
+use serde_json::json;
+
+use super::THERMAL;
+
+/// The thermal state endpoint
+///
+/// Exposes the temperature and power readings of the host, and whether new
+/// jobs are being admitted, throttled or rejected because of them
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[get("/thermal")]
+pub async fn thermal_endpoint() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok", "thermal": THERMAL.state() }))
+}
@@ -0,0 +1,155 @@
+use actix_web::{body::{BoxBody, MessageBody}, dev::{ServiceRequest, ServiceResponse}, get, middleware::Next, web::{Data, ReqData}, Error, HttpMessage, HttpResponse};
+use log::warn;
+use serde_json::json;
+
+use crate::services::usage::{UsageMeter, UsageStats, ANONYMOUS_KEY};
+use super::auth::{is_public_path, ApiKeyName, ADMIN_PREFIX};
+use super::errors::PathWalkerError;
+
+/// The path of the usage of the calling key, which is served over the quota
+const USAGE_PATH: &str = "/v1/usage";
+
+/// Get the name the usage of a request is accounted to
+pub fn key_name(key: Option<&ApiKeyName>) -> String {
+    key.map_or_else(|| ANONYMOUS_KEY.to_string(), |ApiKeyName(name)| name.clone())
+}
+
+/// The usage accounting middleware
+///
+/// Accounts every request to its API key, along with the vertices walked
+/// and the execution time of the kernels of its computations, and rejects
+/// the requests of the keys that have used their monthly quota with
+/// `429 Too Many Requests`. The requests without an API key are accounted
+/// as `anonymous`. The probes, the metrics and the documentation are never
+/// accounted, and the usage and administration endpoints are served over
+/// the quota, so a key can find out why it's being rejected.
+///
+/// # Arguments
+///
+/// * `req` - The request
+/// * `next` - The rest of the services
+///
+/// # Returns
+///
+/// * `Result<ServiceResponse<BoxBody>, Error>` - The response
+///
+pub async fn meter_usage(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, Error> {
+    let stats = match req.app_data::<Data<UsageStats>>() {
+        Some(stats) if !is_public_path(req.path()) => stats.clone().into_inner(),
+        _ => return next.call(req).await.map(ServiceResponse::map_into_boxed_body)
+    };
+
+    let key = key_name(req.extensions().get::<ApiKeyName>());
+
+    // Account the request, rejecting the computations of the keys over their quota
+    let meter = if req.path() == USAGE_PATH || req.path().starts_with(ADMIN_PREFIX) {
+        stats.record_request(&key);
+        UsageMeter::new(stats, &key)
+    } else {
+        match UsageMeter::admit(stats, &key) {
+            Ok(meter) => meter,
+            Err(message) => {
+                warn!("Rejected request {} {} from the API key {} over its monthly quota", req.method(), req.path(), key);
+                return Ok(req.into_response(PathWalkerError::QuotaExceeded(message).response()));
+            }
+        }
+    };
+
+    // Account the walks the request runs while it's answered
+    meter.scope(next.call(req)).await.map(ServiceResponse::map_into_boxed_body)
+}
+
+/// The usage endpoint
+///
+/// Exposes the usage of the calling API key in the current calendar month,
+/// in UTC, with the requests, the vertices walked and the milliseconds the
+/// kernels have run, and the kernel milliseconds left of its quota
+///
+/// # Arguments
+///
+/// * `stats` - The usage of every API key
+/// * `key` - The name of the API key of the request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[get("/usage")]
+pub async fn usage_endpoint(stats: Data<UsageStats>, key: Option<ReqData<ApiKeyName>>) -> HttpResponse {
+    let key = key_name(key.as_deref());
+    let (month, usage) = stats.usage(&key);
+
+    HttpResponse::Ok().json(json!({
+        "status": "ok",
+        "key": key,
+        "month": month,
+        "usage": usage,
+        "quota_ms": stats.monthly_quota(),
+        "remaining_ms": stats.remaining(&key)
+    }))
+}
+
+/// The usage administration endpoint
+///
+/// Exposes the usage of every API key in the current calendar month, and
+/// the totals of the server, so the teams can be billed for the time they
+/// have used the device
+///
+/// # Arguments
+///
+/// * `stats` - The usage of every API key
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[get("/admin/usage")]
+pub async fn admin_usage_endpoint(stats: Data<UsageStats>) -> HttpResponse {
+    let (month, keys, total) = stats.report();
+
+    HttpResponse::Ok().json(json!({
+        "status": "ok",
+        "month": month,
+        "quota_ms": stats.monthly_quota(),
+        "keys": keys,
+        "total": total
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{middleware::from_fn, test::{call_and_read_body_json, call_service, init_service, TestRequest}, web::scope, App};
+    use pathwalker_core::services::metrics::WalkUsage;
+    use serde_json::Value;
+    use crate::endpoints::shadow::shadow_endpoint;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_usage_endpoints() {
+        // Prepare a key that has used its quota of 5 kernel milliseconds
+        let stats = Data::new(UsageStats::new(Some(5)));
+        stats.record_walks(ANONYMOUS_KEY, WalkUsage { vertices: 128, kernel_time: Duration::from_millis(6) });
+        let app = init_service(
+            App::new()
+                .app_data(stats.clone())
+                .wrap(from_fn(meter_usage))
+                .service(scope("/v1").service(usage_endpoint).service(shadow_endpoint))
+                .service(admin_usage_endpoint)
+        ).await;
+
+        // Get the result
+        let rejected = call_service(&app, TestRequest::get().uri("/v1/shadow").to_request()).await;
+        let usage: Value = call_and_read_body_json(&app, TestRequest::get().uri("/v1/usage").to_request()).await;
+        let report: Value = call_and_read_body_json(&app, TestRequest::get().uri("/admin/usage").to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(rejected.status(), 429);
+        assert_eq!(usage["key"], ANONYMOUS_KEY);
+        assert_eq!(usage["usage"], json!({ "requests": 1, "vertices": 128, "kernel_ms": 6.0 }));
+        assert_eq!(usage["remaining_ms"], 0.0);
+        assert_eq!(report["total"]["requests"], 2);
+        assert_eq!(report["keys"][ANONYMOUS_KEY]["vertices"], 128);
+    }
+}
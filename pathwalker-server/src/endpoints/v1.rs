@@ -0,0 +1,164 @@
+use actix_web::{body::MessageBody, dev::{ServiceRequest, ServiceResponse}, http::header::{HeaderName, HeaderValue}, middleware::Next, web::ServiceConfig, Error};
+
+use super::sortest_path_endpoint;
+use super::apsp::graph_all_pairs_endpoint;
+use super::budget::{budget_endpoint, ev_endpoint};
+use super::centrality::betweenness_endpoint;
+use super::chunks::{discard_chunks_endpoint, graph_chunk_endpoint};
+use super::cost::cost_endpoint;
+use super::dag::dag_endpoint;
+use super::devices::devices_endpoint;
+use super::explain::explain_endpoint;
+use super::failures::failures_endpoint;
+use super::flowfield::flow_field_endpoint;
+use super::graphs::{delete_graph_endpoint, export_graph_endpoint, get_graph_endpoint, graph_sortest_path_endpoint, list_graphs_endpoint, patch_graph_edges_endpoint, put_graph_endpoint};
+use super::grid::grid_endpoint;
+use super::import::import_endpoint;
+use super::loads::loads_endpoint;
+use super::maxflow::maxflow_endpoint;
+use super::pagerank::pagerank_endpoint;
+use super::replan::{delete_replan_endpoint, start_replan_endpoint, update_replan_endpoint};
+use super::shadow::shadow_endpoint;
+use super::sources::multi_source_endpoint;
+use super::thermal::thermal_endpoint;
+use super::usage::usage_endpoint;
+use super::validate::validate_endpoint;
+use super::walks::walks_endpoint;
+use super::websocket::sortest_path_websocket;
+use super::whatif::graph_what_if_endpoint;
+
+/// The prefix of the paths of the first version of the API
+pub const PREFIX: &str = "/v1";
+
+/// The paths served before the API was versioned, with the path replacing each of them
+/// and when they were deprecated, in seconds since the Unix epoch
+const DEPRECATED_ALIASES: [(&str, &str, i64); 1] = [("/sortest", "/v1/sortest", 1792022400)];
+
+/// Register the endpoints of the first version of the API
+///
+/// The endpoints are registered relative to the scope of the version, so a
+/// later version can register the same handlers along with the ones whose
+/// responses have changed shape, without changing the paths of this one.
+/// The probes, the metrics, the documentation and the administration
+/// endpoints aren't part of the versioned API.
+///
+/// # Arguments
+///
+/// * `cfg` - The configuration of the scope of the version
+///
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(sortest_path_endpoint)
+        .service(sortest_path_websocket)
+        .service(multi_source_endpoint)
+        .service(cost_endpoint)
+        .service(budget_endpoint)
+        .service(ev_endpoint)
+        .service(grid_endpoint)
+        .service(start_replan_endpoint)
+        .service(update_replan_endpoint)
+        .service(delete_replan_endpoint)
+        .service(flow_field_endpoint)
+        .service(walks_endpoint)
+        .service(loads_endpoint)
+        .service(failures_endpoint)
+        .service(betweenness_endpoint)
+        .service(pagerank_endpoint)
+        .service(maxflow_endpoint)
+        .service(dag_endpoint)
+        .service(explain_endpoint)
+        .service(validate_endpoint)
+        .service(import_endpoint)
+        .service(thermal_endpoint)
+        .service(devices_endpoint)
+        .service(usage_endpoint)
+        .service(shadow_endpoint)
+        .service(list_graphs_endpoint)
+        .service(put_graph_endpoint)
+        .service(graph_chunk_endpoint)
+        .service(discard_chunks_endpoint)
+        .service(get_graph_endpoint)
+        .service(export_graph_endpoint)
+        .service(delete_graph_endpoint)
+        .service(patch_graph_edges_endpoint)
+        .service(graph_sortest_path_endpoint)
+        .service(graph_all_pairs_endpoint)
+        .service(graph_what_if_endpoint);
+}
+
+/// Register the paths served before the API was versioned
+///
+/// # Arguments
+///
+/// * `cfg` - The configuration of the application
+///
+pub fn configure_aliases(cfg: &mut ServiceConfig) {
+    cfg.service(sortest_path_endpoint);
+}
+
+/// The deprecation middleware
+///
+/// Marks the responses of the paths served before the API was versioned
+/// with the `Deprecation` header, holding when they were deprecated as an
+/// `@` followed by the seconds since the Unix epoch, as RFC 9745 defines it,
+/// and names the path replacing them in the `Link` header, so the clients
+/// can find out they should move to `/v1`.
+///
+/// # Arguments
+///
+/// * `req` - The request
+/// * `next` - The rest of the services
+///
+/// # Returns
+///
+/// * `Result<ServiceResponse<impl MessageBody>, Error>` - The response
+///
+pub async fn mark_deprecated(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let successor = DEPRECATED_ALIASES.iter().find(|(alias, _, _)| req.path() == *alias).map(|(_, successor, since)| (*successor, *since));
+    let mut response = next.call(req).await?;
+
+    if let Some((successor, since)) = successor {
+        let headers = response.headers_mut();
+
+        if let Ok(deprecation) = HeaderValue::from_str(&format!("@{}", since)) {
+            headers.insert(HeaderName::from_static("deprecation"), deprecation);
+        }
+
+        if let Ok(link) = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor)) {
+            headers.insert(HeaderName::from_static("link"), link);
+        }
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{middleware::from_fn, test::{call_service, init_service, TestRequest}, web::scope, App};
+    use serde_json::json;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_versioned_routes() {
+        // Prepare the application with the first version and the deprecated aliases
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(mark_deprecated))
+                .service(scope(PREFIX).configure(configure))
+                .configure(configure_aliases)
+        ).await;
+        let invalid = json!({ "data": [0, 1, 1], "width": 2, "height": 2 });
+
+        // Get the result
+        let versioned = call_service(&app, TestRequest::post().uri("/v1/sortest").set_json(&invalid).to_request()).await;
+        let alias = call_service(&app, TestRequest::post().uri("/sortest").set_json(&invalid).to_request()).await;
+        let thermal = call_service(&app, TestRequest::get().uri("/v1/thermal").to_request()).await;
+        let unversioned = call_service(&app, TestRequest::get().uri("/thermal").to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!((versioned.status().as_u16(), alias.status().as_u16()), (400, 400));
+        assert!(versioned.headers().get("Deprecation").is_none());
+        assert_eq!(alias.headers().get("Deprecation").unwrap(), "@1792022400");
+        assert_eq!(alias.headers().get("Link").unwrap(), "</v1/sortest>; rel=\"successor-version\"");
+        assert_eq!((thermal.status().as_u16(), unversioned.status().as_u16()), (200, 404));
+    }
+}
@@ -0,0 +1,82 @@
+use actix_web::{post, HttpResponse, web::{Json, Query}};
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::models::{ValidateRequest, ValidationRules};
+use crate::services::memory::first_device_memory;
+use crate::services::preflight::preflight;
+use super::WALKER;
+
+/// The query parameters of the validation endpoint
+///
+/// # Fields
+///
+/// * `symmetric` - If the cells whose weight differs from the one of the opposite edge are warned about
+/// * `reject_negative` - If the negative weights are errors, as in the searches that can't handle them
+///
+#[derive(Debug, Deserialize)]
+pub struct ValidateQuery {
+    #[serde(default)]
+    pub symmetric: bool,
+    #[serde(default)]
+    pub reject_negative: bool,
+}
+
+/// The validation endpoint
+///
+/// Exposes a endpoint that receives a matrix, with the `source` vertex of
+/// the walk (0 by default), and runs every check of the walks on it, the
+/// dimensions, the weights of the cells, the vertices reachable from the
+/// source and the memory of the selected device, without launching any
+/// kernel, so clients can check a graph before paying for the walk
+///
+/// # Arguments
+///
+/// * `query` - The query parameters with the rules of the cells
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/validate")]
+pub async fn validate_endpoint(query: Query<ValidateQuery>, item: Json<ValidateRequest>) -> HttpResponse {
+    let request: ValidateRequest = item.into_inner();
+    let rules = ValidationRules { reject_negative: query.reject_negative, warn_asymmetric: query.symmetric };
+
+    // Check the memory of the device the walker has opened, or of the one it would open
+    let device_memory = WALKER.opened().and_then(|walker| walker.global_memory()).or_else(first_device_memory);
+    let report = preflight(&request.matrix, request.source, rules, device_memory);
+
+    info!("Validated matrix of {} vertices from vertex {}: {}", request.matrix.width, request.source, if report.valid { "valid" } else { "not valid" });
+
+    HttpResponse::Ok().json(json!({ "status": "ok", "validation": report }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_and_read_body_json, init_service, TestRequest}, App};
+    use serde_json::Value;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_validate_endpoint() {
+        // Prepare a chain with an unreachable vertex and a negative weight, and a matrix with a wrong size
+        let chain = json!({ "width": 3, "height": 3, "data": [0, 0, 0, -1, 0, 0, 0, 0, 0], "source": 0 });
+        let wrong = json!({ "width": 2, "height": 2, "data": [0, 1, 1] });
+
+        // Get the result
+        let app = init_service(App::new().service(validate_endpoint)).await;
+        let valid: Value = call_and_read_body_json(&app, TestRequest::post().uri("/validate").set_json(&chain).to_request()).await;
+        let negative: Value = call_and_read_body_json(&app, TestRequest::post().uri("/validate?reject_negative=true").set_json(&chain).to_request()).await;
+        let invalid: Value = call_and_read_body_json(&app, TestRequest::post().uri("/validate").set_json(&wrong).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(valid["validation"]["valid"], true);
+        assert_eq!((valid["validation"]["reachable"].clone(), valid["validation"]["unreachable"].clone()), (json!(2), json!([2])));
+        assert_eq!(negative["validation"]["valid"], false);
+        assert_eq!(negative["validation"]["errors"][0]["row"], 1);
+        assert_eq!(invalid["validation"]["dimensions"], "The matrix size and dimensions are not the same");
+    }
+}
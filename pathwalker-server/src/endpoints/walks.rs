@@ -0,0 +1,167 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{post, HttpResponse, web::{Json, Query}};
+use log::{error, info};
+use serde::Deserialize;
+
+use super::errors::PathWalkerError;
+use crate::models::WalkRequest;
+use crate::services::entitlement::Feature;
+use crate::services::walks::{random_walks, validate_walks};
+use crate::utils::spawn_blocking;
+use super::encoding::NDJSON_MEDIA_TYPE;
+use super::{entitlement_rejection, ENTITLEMENTS};
+
+/// The media type of the Arrow IPC walk corpora
+pub const ARROW_STREAM_MEDIA_TYPE: &str = "application/vnd.apache.arrow.stream";
+
+/// The header with the seed of the corpus, to generate it again
+pub const SEED_HEADER: &str = "X-PathWalker-Seed";
+
+/// The query parameters of the walks endpoint
+#[derive(Debug, Deserialize)]
+pub struct WalksQuery {
+    pub format: Option<String>,
+}
+
+/// Encode the walks as NDJSON, with the vertices of a walk in every line
+///
+/// # Arguments
+///
+/// * `walks` - The vertices of every walk
+///
+/// # Returns
+///
+/// * `Vec<u8>` - The encoded walks
+///
+pub fn walks_to_ndjson(walks: &[Vec<usize>]) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    for walk in walks {
+        serde_json::to_writer(&mut body, walk).unwrap();
+        body.push(b'\n');
+    }
+
+    body
+}
+
+/// Encode the walks as an Arrow IPC stream, with the vertices of a walk in every row of the `walk` list column
+///
+/// # Arguments
+///
+/// * `walks` - The vertices of every walk
+///
+/// # Returns
+///
+/// * `Vec<u8>` - The encoded walks
+///
+#[cfg(feature = "arrow")]
+pub fn walks_to_arrow(walks: &[Vec<usize>]) -> Vec<u8> {
+    use std::sync::Arc;
+
+    use arrow_array::{ListArray, RecordBatch, types::UInt32Type};
+    use arrow_ipc::writer::StreamWriter;
+
+    let column = ListArray::from_iter_primitive::<UInt32Type, _, _>(walks.iter().map(|walk| Some(walk.iter().map(|vertex| Some(*vertex as u32)))));
+    let batch = RecordBatch::try_from_iter([("walk", Arc::new(column) as _)]).unwrap();
+
+    let mut writer = StreamWriter::try_new(Vec::new(), &batch.schema()).unwrap();
+    writer.write(&batch).unwrap();
+    writer.into_inner().unwrap()
+}
+
+/// The random walks endpoint
+///
+/// Exposes a endpoint that receives a graph with the parameters of node2vec,
+/// and returns a corpus of biased random walks over it, to train embeddings
+/// of the vertices. The corpus is encoded as NDJSON, or as an Arrow IPC
+/// stream when asked with `format=arrow`
+///
+/// # Arguments
+///
+/// * `query` - The query parameters with the format of the corpus
+/// * `item` - The request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/walks")]
+pub async fn walks_endpoint(query: Query<WalksQuery>, item: Json<WalkRequest>) -> HttpResponse {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Walks) {
+        return entitlement_rejection(message);
+    }
+
+    // Check the format before generating the corpus
+    let arrow = match query.format.as_deref() {
+        None | Some("ndjson") => false,
+        Some("arrow") if cfg!(feature = "arrow") => true,
+        Some("arrow") => return PathWalkerError::Validation("The Arrow format needs a server built with the arrow feature".to_string()).response(),
+        Some(format) => return PathWalkerError::Validation(format!("The format {} is not supported, it must be ndjson or arrow", format)).response()
+    };
+
+    // Read the request and validate it before walking the graph
+    let mut request: WalkRequest = item.into_inner();
+
+    if let Some(message) = validate_walks(&request) {
+        return PathWalkerError::Validation(message.to_string()).response();
+    }
+
+    // Choose a seed when not given, so the corpus can be generated again
+    let seed = *request.seed.get_or_insert_with(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64));
+    info!("Received walks request of {} walks of {} vertices over {} vertices with seed {}", request.walks_per_vertex, request.length, request.matrix.width, seed);
+
+    // Generate the walks outside the async executor
+    let result = spawn_blocking(move || {
+        let walks = random_walks(&request);
+
+        #[cfg(feature = "arrow")]
+        if arrow {
+            return walks_to_arrow(&walks);
+        }
+
+        walks_to_ndjson(&walks)
+    }).await;
+
+    // Return the corpus
+    match result {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(if arrow { ARROW_STREAM_MEDIA_TYPE } else { NDJSON_MEDIA_TYPE })
+            .insert_header((SEED_HEADER, seed.to_string()))
+            .body(body),
+        Err(err) => {
+            error!("The random walks have panicked: {}", err);
+            PathWalkerError::Internal("The computation has been aborted".to_string()).response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_service, init_service, read_body, TestRequest}, App};
+    use serde_json::json;
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_walks_endpoint() {
+        // Prepare a graph of two vertices going back and forth
+        let request = json!({ "width": 2, "height": 2, "data": [0, 1, 1, 0], "walks_per_vertex": 2, "length": 3, "seed": 1 });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(walks_endpoint)
+        ).await;
+
+        // Get the response
+        let resp = call_service(&app, TestRequest::post().uri("/walks").set_json(&request).to_request()).await;
+        let seed = resp.headers().get(SEED_HEADER).cloned();
+        let body = read_body(resp).await;
+        let unknown = call_service(&app, TestRequest::post().uri("/walks?format=csv").set_json(&request).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(body, "[0,1,0]\n[1,0,1]\n[0,1,0]\n[1,0,1]\n");
+        assert_eq!(seed.unwrap(), "1");
+        assert_eq!(unknown.status(), 400);
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::VecDeque;
+
+use actix_web::{get, rt, web::Payload, Error, HttpRequest, HttpResponse};
+use actix_ws::{Message, Session};
+use log::{error, info};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio::time::{interval_at, Instant};
+use tracing::{Instrument, Span};
+
+use crate::models::{Matrix, PathResult, ValidationRules};
+use crate::services::entitlement::Feature;
+use crate::utils::spawn_blocking;
+use super::errors::PathWalkerError;
+use super::{computation_cancellation, entitlement_rejection, single_sortest_path, validate_matrix, ENTITLEMENTS, SOCKETS, THERMAL};
+
+/// The matrices of a session queued behind the one being walked, at most
+const MAX_PENDING_MATRICES: usize = 16;
+
+/// Queue a matrix message to be walked after the ones received before it
+///
+/// # Arguments
+///
+/// * `pending` - The messages of the session waiting to be walked
+/// * `text` - The text of the message with the matrix
+///
+/// # Returns
+///
+/// * `bool` - If the message has been queued, or the queue of the session is full
+///
+fn queue_matrix(pending: &mut VecDeque<String>, text: String) -> bool {
+    if pending.len() >= MAX_PENDING_MATRICES {
+        return false;
+    }
+
+    pending.push_back(text);
+    true
+}
+
+/// The sortest path websocket endpoint
+///
+/// Exposes a websocket that receives a matrix as a text message and streams
+/// a progress event for every iteration of the algorithm, followed by the
+/// path of the walk once the computation has finished. The matrices are
+/// walked one at a time, in the order they are received, while the client
+/// is pinged every heartbeat, and the session is closed when the client
+/// stays silent for longer than the idle timeout, aborting its walk. The
+/// matrices received while the queue of the session is full are answered
+/// with an error instead of being walked
+///
+/// # Arguments
+///
+/// * `req` - The request
+/// * `body` - The payload of the request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response upgrading the connection
+///
+#[get("/ws/sortest")]
+pub async fn sortest_path_websocket(req: HttpRequest, body: Payload) -> Result<HttpResponse, Error> {
+    // Check that the license enables the feature
+    if let Err(message) = ENTITLEMENTS.require(Feature::Streaming) {
+        return Ok(entitlement_rejection(message));
+    }
+
+    // Upgrade the connection to a websocket
+    let (response, mut session, mut stream) = actix_ws::handle(&req, body)?;
+    let id = SOCKETS.open();
+
+    // Process the messages of the client in background, inside the span of the upgrade
+    rt::spawn(async move {
+        let mut pending = VecDeque::new();
+        let mut walk = None;
+        let mut heartbeat = interval_at(Instant::now() + SOCKETS.heartbeat(), SOCKETS.heartbeat());
+
+        let reason = loop {
+            // Walk the next matrix once the previous one has been answered
+            if walk.is_none() {
+                walk = pending.pop_front().map(|text| Box::pin(process_matrix_message(session.clone(), text, id)));
+            }
+
+            tokio::select! {
+                message = stream.recv() => {
+                    let Some(Ok(message)) = message else {
+                        break None;
+                    };
+
+                    SOCKETS.seen(id);
+
+                    match message {
+                        // Refuse the matrices over the queue, so a client can't hold the memory of the server
+                        Message::Text(text) if !queue_matrix(&mut pending, text.to_string()) && send_error(&mut session, &PathWalkerError::Unavailable("Too many matrices are waiting to be walked, wait for their paths before sending more".to_string())).await.is_err() => break None,
+                        Message::Ping(bytes) if session.pong(&bytes).await.is_err() => break None,
+                        Message::Close(reason) => break reason,
+                        _ => {}
+                    }
+                },
+                result = async { walk.as_mut().unwrap().await }, if walk.is_some() => {
+                    walk = None;
+
+                    // Stop when the client has gone away
+                    if result.is_err() {
+                        break None;
+                    }
+                },
+                _ = heartbeat.tick() => {
+                    // Close the sessions of the clients that have silently gone away
+                    if SOCKETS.is_dead(id) {
+                        info!("Closing websocket session {} silent for over {} seconds", id, SOCKETS.idle_timeout().as_secs());
+                        break None;
+                    }
+
+                    if session.ping(b"").await.is_err() {
+                        break None;
+                    }
+                }
+            }
+        };
+
+        // Abort the walk in progress, releasing its device memory, and close the session
+        drop(walk);
+
+        if let Some(report) = SOCKETS.close(id) {
+            info!("Closed websocket session {} after {} walks", id, report.walks);
+        }
+
+        let _ = session.close(reason).await;
+    }.instrument(Span::current()));
+
+    Ok(response)
+}
+
+/// The websocket sessions endpoint
+///
+/// Exposes the live websocket sessions, with what every one of them is
+/// doing, how long its client has been silent and the device memory held
+/// by its walk, along with the heartbeat and the idle timeout
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[get("/admin/sessions")]
+pub async fn websocket_sessions_endpoint() -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "status": "ok",
+        "heartbeat_seconds": SOCKETS.heartbeat().as_secs(),
+        "idle_timeout_seconds": SOCKETS.idle_timeout().as_secs(),
+        "sessions": SOCKETS.reports()
+    }))
+}
+
+/// Compute the sortest path for a websocket message
+///
+/// # Arguments
+///
+/// * `session` - The websocket session
+/// * `text` - The text of the message with the matrix
+/// * `id` - The id of the session, to track its walk
+///
+/// # Returns
+///
+/// * `Result<(), Closed>` - If the session is still open
+///
+async fn process_matrix_message(mut session: Session, text: String, id: u64) -> Result<(), actix_ws::Closed> {
+    // Deserialize and validate the matrix
+    let matrix: Matrix = match serde_json::from_str(&text) {
+        Ok(matrix) => matrix,
+        Err(err) => return send_error(&mut session, &PathWalkerError::Validation(format!("The matrix can't be parsed: {}", err))).await
+    };
+
+    if let Some(message) = validate_matrix(&matrix) {
+        return send_error(&mut session, &PathWalkerError::Validation(message.to_string())).await;
+    }
+
+    if let Some(error) = matrix.validate(ValidationRules::default()).errors.first() {
+        return send_error(&mut session, &PathWalkerError::Validation(error.to_string())).await;
+    }
+
+    // Reject the job right away while the device is too hot to admit it
+    let _permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(_) => return send_error(&mut session, &PathWalkerError::Unavailable("The device is too hot to admit new jobs, retry later".to_string())).await
+    };
+
+    // Print the request
+    info!("Received websocket request for matrix: {:?}", matrix);
+
+    // Track the matrix and the buffers of the walk as the device memory of the session
+    SOCKETS.start_walk(id, (matrix.data.len() + 5 * matrix.width) * std::mem::size_of::<f32>());
+
+    // Run the algorithm outside the async executor in the backend chosen for the matrix,
+    // forwarding the progress when the backend reports it and aborting it when the client goes away
+    let round = matrix.round;
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let cancellation = computation_cancellation();
+    let _cancel = cancellation.on_drop();
+    let computation = spawn_blocking(move || {
+        let mut progress = |iteration, total| {
+            let _ = sender.send((iteration, total));
+        };
+
+        single_sortest_path(&matrix, &cancellation, Some(&mut progress))
+    });
+
+    // Stream the progress events while the algorithm is running
+    while let Some((iteration, total)) = receiver.recv().await {
+        session.text(json!({
+            "status": "progress",
+            "iteration": iteration,
+            "total": total
+        }).to_string()).await?;
+    }
+
+    // Send the path of the walk
+    let result = computation.await;
+    SOCKETS.finish_walk(id);
+
+    match result {
+        Ok(Ok(path)) => {
+            let path: Vec<PathResult> = path.iter().map(|hop| hop.round(round)).collect();
+            session.text(json!({ "status": "ok", "path": path }).to_string()).await
+        },
+        Ok(Err(err)) => send_error(&mut session, &PathWalkerError::from(err)).await,
+        Err(err) => {
+            error!("The websocket computation has panicked: {}", err);
+            send_error(&mut session, &PathWalkerError::Internal("The computation has been aborted".to_string())).await
+        }
+    }
+}
+
+/// Send an error message over the websocket, with the code of the error
+///
+/// # Arguments
+///
+/// * `session` - The websocket session
+/// * `error` - The error
+///
+/// # Returns
+///
+/// * `Result<(), Closed>` - If the session is still open
+///
+async fn send_error(session: &mut Session, error: &PathWalkerError) -> Result<(), actix_ws::Closed> {
+    session.text(error.body().to_string()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::App;
+    use awc::ws::{Frame, Message as ClientMessage};
+    use futures_util::{SinkExt, StreamExt};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_sortest_path_websocket_invalid_matrix() {
+        // Prepare the matrix
+        let matrix = Matrix::new(3, 3, vec![
+            01.0, 04.0, 02.0, 00.0,
+            04.0, 01.0, 01.0, 05.0
+        ]);
+
+        // Prepare the expected result
+        let expected = "The matrix size and dimensions are not the same";
+
+        // Start the server and connect to the websocket
+        let mut server = actix_test::start(|| App::new().service(sortest_path_websocket));
+        let mut framed = server.ws_at("/ws/sortest").await.unwrap();
+
+        // Send the matrix
+        framed.send(ClientMessage::Text(serde_json::to_string(&matrix).unwrap().into())).await.unwrap();
+
+        // Get the response
+        let frame = framed.next().await.unwrap().unwrap();
+        let resp: crate::models::Result = match frame {
+            Frame::Text(text) => serde_json::from_slice(&text).unwrap(),
+            _ => panic!("Unexpected frame received")
+        };
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "error");
+        assert_eq!(resp.message.unwrap(), expected);
+
+        // Close the connection
+        framed.send(ClientMessage::Close(None)).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_sortest_path_websocket_cpu() {
+        // Prepare a chain of four vertices with a shortcut from the first to the last, small enough for the CPU
+        let mut matrix = Matrix::new(4, 4, vec![0.0; 16]);
+        for (source, target, weight) in [(0, 1, 1.0), (1, 2, 1.0), (2, 3, 1.0), (0, 3, 5.0)] {
+            matrix.data[target * 4 + source] = weight;
+        }
+
+        // Start the server and connect to the websocket
+        let mut server = actix_test::start(|| App::new().service(sortest_path_websocket));
+        let mut framed = server.ws_at("/ws/sortest").await.unwrap();
+
+        // Send the matrix
+        framed.send(ClientMessage::Text(serde_json::to_string(&matrix).unwrap().into())).await.unwrap();
+
+        // Get the response, which has no progress events as the CPU doesn't report them
+        let frame = framed.next().await.unwrap().unwrap();
+        let resp: crate::models::Result = match frame {
+            Frame::Text(text) => serde_json::from_slice(&text).unwrap(),
+            _ => panic!("Unexpected frame received")
+        };
+
+        // Check if the result is correct
+        assert_eq!(resp.status, "ok");
+        assert_eq!(resp.path.unwrap(), vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(1, 2.0), PathResult(2, 3.0)]);
+
+        // Close the connection
+        framed.send(ClientMessage::Close(None)).await.unwrap();
+    }
+
+    #[test]
+    fn test_queue_matrix() {
+        // Prepare a queue with room for one more matrix
+        let mut pending: VecDeque<String> = (1..MAX_PENDING_MATRICES).map(|index| index.to_string()).collect();
+
+        // Get the result
+        let queued = queue_matrix(&mut pending, "last".to_string());
+        let refused = queue_matrix(&mut pending, "over".to_string());
+
+        // Check if the result is correct
+        assert!(queued);
+        assert!(!refused);
+        assert_eq!(pending.len(), MAX_PENDING_MATRICES);
+        assert_eq!(pending.back().map(String::as_str), Some("last"));
+    }
+
+    #[actix_web::test]
+    async fn test_websocket_sessions_endpoint() {
+        // Start the server and connect to the websocket
+        let mut server = actix_test::start(|| App::new().service(sortest_path_websocket).service(websocket_sessions_endpoint));
+        let mut framed = server.ws_at("/ws/sortest").await.unwrap();
+
+        // Get the result
+        let mut response = server.get("/admin/sessions").send().await.unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+
+        // Check if the result is correct
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["idle_timeout_seconds"], 60);
+        assert!(body["sessions"].as_array().unwrap().iter().any(|session| session["state"] == "idle" && session["walks"] == 0));
+
+        // Close the connection
+        framed.send(ClientMessage::Close(None)).await.unwrap();
+    }
+}
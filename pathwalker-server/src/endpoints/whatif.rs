@@ -0,0 +1,144 @@
+use actix_web::{post, HttpRequest, HttpResponse, web::{Json, Path, Query}};
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::encoding::path_response;
+use super::errors::PathWalkerError;
+use crate::models::WhatIfRequest;
+use crate::services::entitlement::Feature;
+use crate::services::overlay::apply_perturbations;
+use crate::services::run_id::{matrix_digest, run_id};
+use crate::services::units::display_unit;
+use crate::utils::spawn_blocking;
+use super::graphs::{fill_costs, graph_not_found, requested_unit, storage_error};
+use super::{computation_cancellation, computation_failure, entitlement_rejection, thermal_rejection, walk_rejection, WalkRejection, ENTITLEMENTS, STORE, THERMAL, with_device};
+
+/// The query parameters of the what-if queries
+#[derive(Debug, Deserialize)]
+pub struct WhatIfQuery {
+    pub round: Option<u32>,
+    pub unit: Option<String>,
+}
+
+/// The stored graph what-if endpoint
+///
+/// Exposes a endpoint that applies some perturbations, like adding an edge,
+/// removing a vertex or scaling the weights of a region, to a temporary copy
+/// of a stored graph, and walks the copy from the source without persisting
+/// it, so the effect of the changes can be explored interactively
+///
+/// # Arguments
+///
+/// * `req` - The request, asking for comma separated values in the `Accept` header or JSON otherwise
+/// * `name` - The name of the graph
+/// * `query` - The query parameters
+/// * `item` - The body of the request
+///
+/// # Returns
+///
+/// * `HttpResponse` - The response
+///
+#[post("/graphs/{name}/whatif")]
+pub async fn graph_what_if_endpoint(req: HttpRequest, name: Path<String>, query: Query<WhatIfQuery>, item: Json<WhatIfRequest>) -> HttpResponse {
+    // Check that the license enables the features
+    if let Err(message) = ENTITLEMENTS.require(Feature::Graphs).and_then(|_| ENTITLEMENTS.require(Feature::WhatIf)) {
+        return entitlement_rejection(message);
+    }
+
+    // Get the matrix of the graph from the store
+    let request: WhatIfRequest = item.into_inner();
+
+    let matrix = match STORE.matrix(&name) {
+        Ok(Some(matrix)) => matrix,
+        Ok(None) => return graph_not_found(&name),
+        Err(message) => return storage_error(message)
+    };
+
+    // Validate the vertices and the unit of the query and change a copy of the matrix
+    let (source, target, round) = (request.source, request.target, query.round);
+
+    if source >= matrix.width || target.is_some_and(|target| target >= matrix.width) {
+        return PathWalkerError::Validation("The vertices of the query are outside of the graph".to_string()).response();
+    }
+
+    let unit = match requested_unit(query.unit.as_deref()).and_then(|requested| display_unit(matrix.unit, requested)) {
+        Ok(unit) => unit,
+        Err(message) => return PathWalkerError::Validation(message).response()
+    };
+
+    let overlay = match apply_perturbations(&matrix, &request.perturbations) {
+        Ok(overlay) => overlay,
+        Err(message) => return PathWalkerError::Validation(message).response()
+    };
+
+    // Reject the job right away while the device is too hot to admit it
+    let permit = match THERMAL.admit() {
+        Ok(permit) => permit,
+        Err(state) => return thermal_rejection(&state)
+    };
+
+    // Print the request
+    info!("Received what-if request for graph {} with {} perturbations from vertex {}", name, request.perturbations.len(), source);
+
+    // Walk the changed copy outside the async executor, freeing it from the device afterwards
+    let cancellation = computation_cancellation();
+    let (_cancel, walk_cancellation) = (cancellation.on_drop(), cancellation.clone());
+    let walk = spawn_blocking(move || {
+        let _permit = permit;
+        with_device(|walker| {
+            let graph = walker.upload(&overlay)?;
+
+            let parameters = [
+                ("source", source.to_string()),
+                ("target", format!("{:?}", target)),
+                ("round", format!("{:?}", round)),
+                ("unit", format!("{:?}", unit.map(|unit| unit.name)))
+            ];
+            let run_id = run_id(&matrix_digest(&overlay), "graph-whatif", &parameters, walker.backend_version());
+
+            walker.walk(&graph, source, &walk_cancellation, |_, _| {}).map(|path| (path, run_id, overlay.zero_edges))
+        })
+    }).await;
+
+    // Return the path of the walk over the changed graph
+    match walk.map_err(computation_failure) {
+        Ok(Ok((path, run_id, zero_edges))) => {
+            info!("Walked what-if of graph {} as run {}", name, run_id);
+
+            let mut body = json!({
+                "status": "ok",
+                "run_id": run_id
+            });
+            fill_costs(&mut body, &path, source, target, zero_edges, unit, round);
+
+            path_response(&req, HttpResponse::Ok(), body)
+        },
+        Ok(Err(err)) if cancellation.is_expired() => walk_rejection(&WalkRejection::Timeout(err.to_string())),
+        Ok(Err(err)) => PathWalkerError::from(err).response(),
+        Err(message) => PathWalkerError::Internal(message).response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::{call_service, init_service, TestRequest}, App};
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_graph_what_if_endpoint_missing_graph() {
+        // Prepare a perturbation of a graph that doesn't exist
+        let request = json!({ "perturbations": [{ "remove_node": { "vertex": 1 } }], "target": 2 });
+
+        // Get the result
+        let app = init_service(
+            App::new().service(graph_what_if_endpoint)
+        ).await;
+
+        // Get the response
+        let resp = call_service(&app, TestRequest::post().uri("/graphs/missing-whatif/whatif").set_json(&request).to_request()).await;
+
+        // Check if the result is correct
+        assert_eq!(resp.status(), 404);
+    }
+}
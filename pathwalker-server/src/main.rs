@@ -0,0 +1,338 @@
+mod commands;
+mod config;
+mod endpoints;
+use pathwalker_core::models;
+mod services;
+mod utils;
+
+use crate::config::ServerConfig;
+use crate::config::listeners::{activated_listeners, bind_unix_socket, ActivatedListener};
+#[cfg(feature = "tls")]
+use crate::config::tls::{reload_on_hangup, ReloadableCertificate};
+use crate::endpoints::{drain_device, payload_too_large, set_backend, set_computation_timeout, set_default_precision, ENTITLEMENTS};
+use crate::endpoints::auth::{require_api_key, GrpcApiKey};
+use crate::endpoints::compression::{negotiate_encoding, Compression};
+use crate::endpoints::config::{config_endpoint, describe_server};
+use crate::endpoints::cors::CorsPolicy;
+#[cfg(feature = "flight")]
+use crate::endpoints::flight::PathWalkerFlightService;
+use crate::endpoints::grpc::PathWalkerService;
+use crate::endpoints::health::{healthz_endpoint, readyz_endpoint};
+use crate::endpoints::metrics::metrics_endpoint;
+#[cfg(feature = "mqtt")]
+use crate::endpoints::mqtt::MqttIngest;
+use crate::endpoints::openapi::swagger_ui;
+use crate::endpoints::rate_limit::limit_clients;
+use crate::endpoints::request_id::assign_request_id;
+use crate::endpoints::soak::{soak_report_endpoint, start_soak_endpoint, stop_soak_endpoint};
+use crate::endpoints::usage::{admin_usage_endpoint, meter_usage};
+use crate::endpoints::v1;
+use crate::endpoints::websocket::websocket_sessions_endpoint;
+use crate::services::auth::ApiKeys;
+use crate::services::backend::BackendKind;
+use crate::services::devices::DeviceDiagnostics;
+use crate::services::dispatch::{set_bidirectional_threshold, set_cpu_threshold};
+use crate::services::entitlement::Feature;
+use crate::services::kernels::override_kernels;
+use crate::services::memory::set_memory_budget;
+use crate::services::metrics::observe_request;
+use crate::services::rate_limit::RateLimiter;
+use crate::services::usage::UsageStats;
+use crate::utils::{init_tracing, LogFormat};
+use pathwalker_core::solver::set_max_vertices;
+
+use std::time::{Duration, Instant};
+
+use actix_web::{dev::Service, http::StatusCode, middleware::{from_fn, Compress, ErrorHandlers}, rt, web::{scope, Data, JsonConfig, PayloadConfig}, App, HttpServer};
+use log::{error, info, warn, LevelFilter};
+use mimalloc::MiMalloc;
+use tonic::transport::Server;
+
+#[global_allocator]
+static GLOBAL: MiMalloc = MiMalloc;
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    // Run the subcommand instead of the server when asked, logging as text
+    let args: Vec<String> = std::env::args().collect();
+
+    if let Some(command) = args.get(1).filter(|command| ["replay", "generate", "loadtest", "bench"].contains(&command.as_str())) {
+        let _telemetry = init_tracing(LevelFilter::Debug, LogFormat::Text, None).map_err(std::io::Error::other)?;
+
+        std::process::exit(match command.as_str() {
+            "replay" => commands::replay(&args[2..]),
+            "generate" => commands::generate(&args[2..]),
+            "bench" => commands::bench(&args[2..]),
+            _ => commands::loadtest(&args[2..]).await
+        });
+    }
+
+    // Read the configuration of the server
+    let config = match ServerConfig::load() {
+        Ok(config) => config,
+        Err(err) => {
+            let _telemetry = init_tracing(LevelFilter::Debug, LogFormat::Text, None);
+            error!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // Configure the tracing before starting the server, exporting the spans when asked
+    let telemetry = match init_tracing(config.log_level, config.log_format, config.otlp_endpoint.as_deref()) {
+        Ok(telemetry) => telemetry,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // Check the license before accepting any request
+    lazy_static::initialize(&ENTITLEMENTS);
+    set_computation_timeout(config.computation_timeout.map(Duration::from_secs));
+    set_default_precision(config.precision);
+    set_max_vertices(config.max_vertices);
+    set_memory_budget(config.memory_budget);
+
+    // Walk in the configured backend, or in the fastest one the host can run
+    let backend = config.backend.unwrap_or_else(BackendKind::detect);
+    info!("Walking the single precision graphs with the {} backend", backend.name());
+    set_backend(backend);
+    set_cpu_threshold(config.cpu_threshold);
+    set_bidirectional_threshold(config.bidirectional_threshold);
+
+    // Replace the kernel programs before the device compiles them
+    if let Err(err) = override_kernels(&config.kernels) {
+        error!("{}", err);
+        std::process::exit(2);
+    }
+
+    // Explain why the kernels can't run when the host has no usable device, unless the build has no OpenCL
+    let diagnostics = DeviceDiagnostics::collect();
+
+    if BackendKind::OpenCl.is_built() && !diagnostics.has_devices() {
+        error!("{}", diagnostics.summary());
+    }
+
+    // Describe the deployment in a single line, so a misconfiguration can be diagnosed from the logs
+    let description = Data::new(describe_server(&config, &diagnostics));
+    info!("Configuration: {}", description.0);
+
+    // Print the server info
+    info!("Starting server at {}... Please wait...", config.address(config.port));
+
+    // Require an API key on every interface when the configuration has any
+    let api_keys = match ApiKeys::new(&config.api_keys).and_then(|keys| keys.with_admins(&config.admin_keys)) {
+        Ok(api_keys) => Data::new(api_keys),
+        Err(err) => {
+            error!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    if !api_keys.is_enabled() {
+        warn!("No API keys are configured, every request is accepted without authentication");
+    }
+
+    // Account the usage of every API key on every interface, rejecting the keys over their monthly quota if any
+    let usage = Data::new(UsageStats::from_env(config.monthly_quota));
+
+    // Start the gRPC server alongside the REST server when the license enables it
+    if ENTITLEMENTS.is_enabled(Feature::Grpc) {
+        info!("Starting gRPC server at {}... Please wait...", config.address(config.grpc_port));
+
+        let address = match config.socket_address(config.grpc_port) {
+            Ok(address) => address,
+            Err(err) => {
+                error!("{}", err);
+                std::process::exit(2);
+            }
+        };
+
+        let (usage, auth) = (usage.clone().into_inner(), GrpcApiKey::new(api_keys.clone().into_inner()));
+
+        rt::spawn(async move {
+            if let Err(err) = Server::builder().add_service(PathWalkerService::server(usage, auth)).serve(address).await {
+                error!("The gRPC server has stopped: {}", err);
+            }
+        });
+    }
+
+    // Start the Arrow Flight server when the build and the license enable it
+    #[cfg(feature = "flight")]
+    if ENTITLEMENTS.is_enabled(Feature::Flight) {
+        info!("Starting Arrow Flight server at {}... Please wait...", config.address(config.flight_port));
+
+        let address = match config.socket_address(config.flight_port) {
+            Ok(address) => address,
+            Err(err) => {
+                error!("{}", err);
+                std::process::exit(2);
+            }
+        };
+
+        let (usage, auth) = (usage.clone().into_inner(), GrpcApiKey::new(api_keys.clone().into_inner()));
+
+        rt::spawn(async move {
+            if let Err(err) = Server::builder().add_service(PathWalkerFlightService::server(usage, auth)).serve(address).await {
+                error!("The Arrow Flight server has stopped: {}", err);
+            }
+        });
+    }
+
+    // Answer the requests of the MQTT broker when the build, the license and the environment enable it
+    #[cfg(feature = "mqtt")]
+    if ENTITLEMENTS.is_enabled(Feature::Mqtt) {
+        if let Some(ingest) = MqttIngest::from_env().map(|ingest| ingest.with_api_keys(api_keys.clone().into_inner())) {
+            info!("Starting MQTT ingestion from topic {}... Please wait...", ingest.topic());
+            rt::spawn(ingest.run());
+        }
+    }
+
+    // Limit the requests of every client when the configuration asks for it
+    let limiter = Data::new(RateLimiter::new(config.rate_limit, config.max_in_flight));
+
+    // Compress the responses with the encodings the configuration allows, if any
+    let compression = Data::new(Compression(config.compression.clone()));
+
+    // Allow the cross-origin requests of the configured origins, if any
+    let cors = CorsPolicy {
+        origins: config.cors_origins.clone(),
+        methods: config.cors_methods.clone(),
+        headers: config.cors_headers.clone(),
+        max_age: config.cors_max_age
+    };
+
+    if cors.is_enabled() {
+        info!("Allowing the cross-origin requests of {}", cors.origins.join(", "));
+    }
+
+    // Start the server
+    let max_payload = config.max_payload;
+    let mut server = HttpServer::new(move || {
+        // Print the worker info
+        info!("Starting worker for wait new connection");
+
+        // Return the app instance
+        App::new()
+            // Account the usage of every API key, once the request is admitted by the limits
+            .app_data(usage.clone())
+            .wrap(from_fn(meter_usage))
+            // Limit the requests of every client, named after the authentication
+            .app_data(limiter.clone())
+            .wrap(from_fn(limit_clients))
+            // Reject the requests without a valid API key
+            .app_data(api_keys.clone())
+            .wrap(from_fn(require_api_key))
+            .app_data(description.clone())
+            // Measure the requests by their route pattern, including the rejected ones
+            .wrap_fn(|req, srv| {
+                let started_at = Instant::now();
+                let response = srv.call(req);
+
+                async move {
+                    let response = response.await?;
+                    let path = response.request().match_pattern().unwrap_or_else(|| "unmatched".to_string());
+
+                    observe_request(response.request().method().as_str(), &path, response.status().as_u16(), started_at.elapsed());
+                    Ok(response)
+                }
+            })
+            // Limit the size of the request bodies, explaining the limit to the clients over it
+            .wrap(ErrorHandlers::new().handler(StatusCode::PAYLOAD_TOO_LARGE, move |res| payload_too_large(res, max_payload)))
+            .app_data(PayloadConfig::new(max_payload))
+            .app_data(JsonConfig::default().limit(max_payload))
+            // Compress the responses with the allowed encodings the client accepts
+            .wrap(Compress::default())
+            .app_data(compression.clone())
+            .wrap(from_fn(negotiate_encoding))
+            // Attach a correlation id to the logs of every request, before any other middleware logs
+            .wrap(from_fn(assign_request_id))
+            // Answer the preflight requests of the browsers before the API keys are required
+            .wrap(cors.middleware())
+            // Mark the responses of the paths served before the API was versioned
+            .wrap(from_fn(v1::mark_deprecated))
+            .service(scope(v1::PREFIX).configure(v1::configure))
+            .service(metrics_endpoint)
+            .service(healthz_endpoint)
+            .service(readyz_endpoint)
+            .service(start_soak_endpoint)
+            .service(soak_report_endpoint)
+            .service(stop_soak_endpoint)
+            .service(admin_usage_endpoint)
+            .service(websocket_sessions_endpoint)
+            .service(config_endpoint)
+            .service(swagger_ui())
+            .configure(v1::configure_aliases)
+    });
+
+    if let Some(workers) = config.workers {
+        server = server.workers(workers);
+    }
+
+    // Stop accepting connections on SIGTERM or SIGINT, letting the running requests finish
+    server = server.shutdown_timeout(config.shutdown_timeout);
+
+    // Serve HTTPS when a certificate is configured, reloading it on SIGHUP, on every TCP socket
+    #[cfg(feature = "tls")]
+    let mut tls_listeners = None;
+
+    let mut server = match &config.tls {
+        #[cfg(feature = "tls")]
+        Some((cert_path, key_path)) => {
+            let certificate = ReloadableCertificate::load(cert_path, key_path).map_err(std::io::Error::other)?;
+            let tls_config = certificate.tls_config().map_err(std::io::Error::other)?;
+
+            info!("Serving HTTPS with the certificate {}", cert_path.display());
+            rt::spawn(reload_on_hangup(certificate));
+            tls_listeners = Some(tls_config.clone());
+
+            server.bind_rustls_0_23(config.address(config.port), tls_config)?
+        },
+        #[cfg(not(feature = "tls"))]
+        Some(_) => return Err(std::io::Error::other("The server has been built without TLS support, rebuild it with the tls feature")),
+        None => server.bind(config.address(config.port))?
+    };
+
+    // Listen on the Unix domain socket too, for the reverse proxies of the same host
+    if let Some(path) = &config.unix_socket {
+        info!("Listening on the Unix socket {} with mode {:o}", path.display(), config.unix_socket_mode);
+        server = server.listen_uds(bind_unix_socket(path, config.unix_socket_mode)?)?;
+    }
+
+    // Listen on the sockets passed by systemd too, when started by a socket unit
+    if config.socket_activation {
+        for listener in activated_listeners().map_err(std::io::Error::other)? {
+            info!("Listening on the socket passed by systemd {:?}", listener);
+
+            server = match listener {
+                ActivatedListener::Tcp(listener) => {
+                    // Never downgrade the TCP sockets to plain HTTP when the server is configured with HTTPS
+                    #[cfg(feature = "tls")]
+                    if let Some(tls_config) = &tls_listeners {
+                        server = server.listen_rustls_0_23(listener, tls_config.clone())?;
+                        continue;
+                    }
+
+                    server.listen(listener)?
+                },
+                ActivatedListener::Unix(listener) => server.listen_uds(listener)?
+            };
+        }
+    }
+
+    server.run().await?;
+
+    // Wait for the kernels that outlived their requests before releasing the device
+    info!("Server stopped, waiting for the running kernels... Please wait...");
+
+    let timeout = Duration::from_secs(config.shutdown_timeout);
+
+    if !rt::task::spawn_blocking(move || drain_device(timeout)).await.unwrap_or(false) {
+        drop(telemetry);
+        std::process::exit(1);
+    }
+
+    info!("Shutdown completed");
+    drop(telemetry);
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use log::info;
+
+/// The environment variable with the identifier of this replica
+pub const REPLICA_VARIABLE: &str = "PATHWALKER_REPLICA_ID";
+
+/// Where a graph is resident, according to the affinity token of a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Residency {
+    Unknown,
+    Local,
+    Remote,
+}
+
+/// The affinity of the stored graphs to this replica
+///
+/// The graphs live in the device memory of the replica that uploaded them,
+/// so the responses carry a token naming the replica, which a load balancer
+/// can use to route the follow-up queries of the graph to the same replica.
+///
+/// # Fields
+///
+/// * `replica` - The identifier of this replica
+///
+pub struct Affinity {
+    replica: String,
+}
+
+impl Affinity {
+    /// Create the affinity of this replica
+    ///
+    /// # Returns
+    ///
+    /// * `Affinity` - The affinity, identified by `PATHWALKER_REPLICA_ID`, the host name or the process id
+    ///
+    pub fn new() -> Affinity {
+        let replica = std::env::var(REPLICA_VARIABLE).ok()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .filter(|replica| !replica.is_empty())
+            .unwrap_or_else(|| format!("pathwalker-{}", std::process::id()));
+
+        info!("Issuing affinity tokens for replica {}", replica);
+
+        Affinity::with_replica(&replica)
+    }
+
+    /// Create the affinity of a replica
+    ///
+    /// # Arguments
+    ///
+    /// * `replica` - The identifier of the replica
+    ///
+    /// # Returns
+    ///
+    /// * `Affinity` - The affinity
+    ///
+    pub fn with_replica(replica: &str) -> Affinity {
+        Affinity { replica: replica.to_string() }
+    }
+
+    /// Get the token routing to this replica
+    pub fn token(&self) -> &str {
+        &self.replica
+    }
+
+    /// Check where the graph of a request is resident
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The affinity token sent by the client, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Residency` - If the graph was pinned to this replica or to another one
+    ///
+    pub fn residency(&self, token: Option<&str>) -> Residency {
+        match token {
+            Some(token) if token == self.replica => Residency::Local,
+            Some(_) => Residency::Remote,
+            None => Residency::Unknown
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_affinity_residency() {
+        // Prepare the affinity
+        let affinity = Affinity::with_replica("replica-a");
+
+        // Check if the result is correct
+        assert_eq!(affinity.token(), "replica-a");
+        assert_eq!(affinity.residency(Some("replica-a")), Residency::Local);
+        assert_eq!(affinity.residency(Some("replica-b")), Residency::Remote);
+        assert_eq!(affinity.residency(None), Residency::Unknown);
+    }
+}
@@ -0,0 +1,134 @@
+use sha2::{Digest, Sha256};
+
+/// The prefix of the keys given as their SHA-256 digest in hexadecimal
+pub const DIGEST_PREFIX: &str = "sha256:";
+
+/// The API keys allowed to use the server
+///
+/// Only the SHA-256 digests of the keys are kept, and the keys of the
+/// requests are compared by their digests, so the configuration can hold
+/// the digests instead of the keys themselves.
+///
+/// # Fields
+///
+/// * `keys` - The name and the digest of every key
+/// * `admins` - The names of the keys allowed to use the administration endpoints
+///
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    keys: Vec<(String, [u8; 32])>,
+    admins: Vec<String>,
+}
+
+impl ApiKeys {
+    /// Create the API keys from their names and values
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The name of every key along with the key, or its digest prefixed with `sha256:`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ApiKeys, String>` - The API keys, or the reason why a key isn't valid
+    ///
+    pub fn new(entries: &[(String, String)]) -> Result<ApiKeys, String> {
+        let keys = entries.iter().map(|(name, key)| {
+            let digest = match key.strip_prefix(DIGEST_PREFIX) {
+                Some(digest) => hex::decode(digest).ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| format!("The digest of the API key {} is not valid", name))?,
+                None if key.is_empty() => return Err(format!("The API key {} is empty", name)),
+                None => Sha256::digest(key.as_bytes()).into()
+            };
+
+            Ok((name.clone(), digest))
+        }).collect::<Result<_, String>>()?;
+
+        Ok(ApiKeys { keys, admins: vec![] })
+    }
+
+    /// Allow some keys to use the administration endpoints
+    ///
+    /// # Arguments
+    ///
+    /// * `names` - The names of the keys
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ApiKeys, String>` - The API keys, or the reason why a name isn't valid
+    ///
+    pub fn with_admins(mut self, names: &[String]) -> Result<ApiKeys, String> {
+        if let Some(name) = names.iter().find(|name| !self.keys.iter().any(|(known, _)| known == *name)) {
+            return Err(format!("The administration key {} is not an API key", name));
+        }
+
+        self.admins = names.to_vec();
+        Ok(self)
+    }
+
+    /// Check if a key can use the administration endpoints
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the key
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If the key is allowed
+    ///
+    pub fn is_admin(&self, name: &str) -> bool {
+        self.admins.iter().any(|admin| admin == name)
+    }
+
+    /// Check if the requests have to be authenticated
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Identify the owner of a key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the request
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` - The name of the key, if it's allowed
+    ///
+    pub fn identify(&self, key: &str) -> Option<&str> {
+        let digest: [u8; 32] = Sha256::digest(key.as_bytes()).into();
+
+        // Compare every byte, so the time taken doesn't tell how much of the digest matched
+        self.keys.iter()
+            .find(|(_, known)| known.iter().zip(digest.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identify_api_key() {
+        // Prepare the keys, one of them given as its digest
+        let keys = ApiKeys::new(&[
+            ("robots".to_string(), "s3cr3t".to_string()),
+            ("dashboard".to_string(), format!("{}{}", DIGEST_PREFIX, hex::encode(Sha256::digest(b"d4shb0ard"))))
+        ]).unwrap();
+
+        // Get the result
+        let robots = keys.identify("s3cr3t");
+        let dashboard = keys.identify("d4shb0ard");
+        let unknown = keys.identify("guess");
+
+        // Check if the result is correct
+        assert_eq!(robots, Some("robots"));
+        assert_eq!(dashboard, Some("dashboard"));
+        assert_eq!(unknown, None);
+        assert!(ApiKeys::new(&[("broken".to_string(), "sha256:abcd".to_string())]).is_err());
+        assert!(keys.clone().with_admins(&["robots".to_string()]).unwrap().is_admin("robots"));
+        assert!(keys.with_admins(&["nobody".to_string()]).is_err());
+        assert!(!ApiKeys::default().is_enabled());
+    }
+}
@@ -0,0 +1,274 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::models::{Matrix, PathResult};
+use crate::services::generator::{GraphSpec, Topology, WeightDistribution};
+use crate::services::sortest_path::SortestPath;
+
+/// The vertices of the graphs benchmarked by default, in increasing size
+pub const DEFAULT_BENCH_SIZES: [usize; 6] = [16, 32, 64, 128, 256, 512];
+
+/// The times every graph is walked by default, keeping the median
+pub const DEFAULT_REPETITIONS: usize = 3;
+
+/// The probability of an edge between two vertices of the graphs by default
+pub const DEFAULT_EDGE_PROBABILITY: f64 = 0.1;
+
+/// The relative difference between the distances of both backends still considered equal
+const DISTANCE_TOLERANCE: f64 = 1e-4;
+
+/// The results of the walks of a graph in both backends
+///
+/// # Fields
+///
+/// * `vertices` - The vertices of the graph
+/// * `edges` - The edges of the graph
+/// * `cpu_ms` - The median milliseconds of the walk in the CPU reference
+/// * `gpu_ms` - The median milliseconds of the upload, the walk and the read back in the device, if it's available
+/// * `speedup` - How many times faster the device is than the CPU reference, if it's available
+/// * `agrees` - If the device has found the same distances as the CPU reference, if it's available
+/// * `error` - Why the device couldn't walk the graph, if it couldn't
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchCase {
+    pub vertices: usize,
+    pub edges: usize,
+    pub cpu_ms: f64,
+    pub gpu_ms: Option<f64>,
+    pub speedup: Option<f64>,
+    pub agrees: Option<bool>,
+    pub error: Option<String>,
+}
+
+/// The results of a benchmark
+///
+/// # Fields
+///
+/// * `device` - The device the kernels have run on, if it's available
+/// * `backend_version` - The versions of the server, the kernels, the platform and the device, if it's available
+/// * `seed` - The seed of the random graphs
+/// * `edge_probability` - The probability of an edge between two vertices
+/// * `repetitions` - The times every graph has been walked
+/// * `cases` - The results of every graph, in increasing size
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchReport {
+    pub device: Option<String>,
+    pub backend_version: Option<String>,
+    pub seed: u64,
+    pub edge_probability: f64,
+    pub repetitions: usize,
+    pub cases: Vec<BenchCase>,
+}
+
+impl BenchReport {
+    /// Check if the device has found the same distances as the CPU reference in every graph
+    pub fn agrees(&self) -> bool {
+        self.cases.iter().all(|case| case.agrees == Some(true))
+    }
+}
+
+/// A vertex waiting in the queue of the reference walk
+#[derive(Debug, PartialEq)]
+struct Open {
+    distance: f64,
+    vertex: usize,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance).then_with(|| other.vertex.cmp(&self.vertex))
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Walk a matrix from a source vertex in the CPU, as the reference of the device
+///
+/// Searches the distances with Dijkstra, and returns them the same way the
+/// kernels do: the predecessor and the distance of every vertex, with the
+/// source as the predecessor and a zero distance for the vertices that
+/// can't be reached.
+///
+/// # Arguments
+///
+/// * `matrix` - The matrix to walk, without negative weights
+/// * `source` - The vertex to walk from
+///
+/// # Returns
+///
+/// * `Vec<PathResult>` - The predecessor and the distance of every vertex
+///
+pub fn reference_walk(matrix: &Matrix, source: usize) -> Vec<PathResult> {
+    let mut distances = vec![f64::INFINITY; matrix.width];
+    let mut predecessors = vec![source as i32; matrix.width];
+    let mut open = BinaryHeap::from([Open { distance: 0.0, vertex: source }]);
+    distances[source] = 0.0;
+
+    while let Some(Open { distance, vertex }) = open.pop() {
+        if distance > distances[vertex] {
+            continue;
+        }
+
+        for target in 0..matrix.width {
+            let Some(weight) = matrix.edge(vertex, target) else { continue };
+            let candidate = distance + weight as f64;
+
+            if candidate < distances[target] {
+                distances[target] = candidate;
+                predecessors[target] = vertex as i32;
+                open.push(Open { distance: candidate, vertex: target });
+            }
+        }
+    }
+
+    distances.into_iter().zip(predecessors)
+        .map(|(distance, predecessor)| PathResult(predecessor, if distance.is_finite() { distance as f32 } else { 0.0 }))
+        .collect()
+}
+
+/// Generate the random graph benchmarked for a size
+///
+/// # Arguments
+///
+/// * `vertices` - The vertices of the graph
+/// * `seed` - The seed of the random numbers
+/// * `edge_probability` - The probability of an edge between two vertices
+///
+/// # Returns
+///
+/// * `Matrix` - A directed graph with weights between 1 and 10
+///
+pub fn bench_graph(vertices: usize, seed: u64, edge_probability: f64) -> Matrix {
+    GraphSpec {
+        vertices,
+        seed,
+        directed: true,
+        topology: Topology::Uniform { probability: edge_probability },
+        weights: WeightDistribution::Uniform { min: 1.0, max: 10.0 }
+    }.generate()
+}
+
+/// Check if the device has found the same distances as the reference
+fn same_distances(device: &[PathResult], reference: &[PathResult]) -> bool {
+    device.len() == reference.len() && device.iter().zip(reference).all(|(found, expected)| {
+        let (found, expected) = (found.1 as f64, expected.1 as f64);
+        (found - expected).abs() <= DISTANCE_TOLERANCE * expected.abs().max(1.0)
+    })
+}
+
+/// Time a function, keeping the median of some repetitions and the last result
+fn median_time<R>(repetitions: usize, mut function: impl FnMut() -> R) -> (Duration, R) {
+    let mut times = vec![];
+    let mut result = None;
+
+    for _ in 0..repetitions.max(1) {
+        let started_at = Instant::now();
+        result = Some(function());
+        times.push(started_at.elapsed());
+    }
+
+    times.sort();
+    (times[times.len() / 2], result.unwrap())
+}
+
+/// Benchmark the device against the CPU reference on random graphs of increasing size
+///
+/// Every graph is walked from its first vertex, after a walk of the
+/// smallest one to warm the device up. The device times include the upload
+/// of the matrix and the read back of the paths, as a request would.
+///
+/// # Arguments
+///
+/// * `walker` - The walker of the device, or none to time the CPU reference only
+/// * `sizes` - The vertices of every graph
+/// * `repetitions` - The times every graph is walked, keeping the median
+/// * `seed` - The seed of the random graphs
+/// * `edge_probability` - The probability of an edge between two vertices
+///
+/// # Returns
+///
+/// * `BenchReport` - The timings and the speedups of every graph
+///
+pub fn run_bench(walker: Option<&SortestPath>, sizes: &[usize], repetitions: usize, seed: u64, edge_probability: f64) -> BenchReport {
+    // Warm the device up, so the first graph doesn't pay for the first allocations
+    if let (Some(walker), Some(smallest)) = (walker, sizes.iter().min()) {
+        let _ = walker.get_sortest_path(bench_graph(*smallest, seed, edge_probability));
+    }
+
+    let cases = sizes.iter().map(|vertices| {
+        let matrix = bench_graph(*vertices, seed, edge_probability);
+        let edges = matrix.data.iter().filter(|weight| **weight != 0.0).count();
+
+        // Walk the graph in the CPU reference and in the device
+        let (cpu_time, reference) = median_time(repetitions, || reference_walk(&matrix, 0));
+        let device = walker.map(|walker| median_time(repetitions, || walker.get_sortest_path(matrix.clone())));
+
+        let case = match device {
+            Some((gpu_time, Ok(path))) => BenchCase {
+                vertices: *vertices,
+                edges,
+                cpu_ms: cpu_time.as_secs_f64() * 1000.0,
+                gpu_ms: Some(gpu_time.as_secs_f64() * 1000.0),
+                speedup: Some(cpu_time.as_secs_f64() / gpu_time.as_secs_f64().max(f64::EPSILON)),
+                agrees: Some(same_distances(&path, &reference)),
+                error: None
+            },
+            Some((_, Err(err))) => BenchCase { vertices: *vertices, edges, cpu_ms: cpu_time.as_secs_f64() * 1000.0, gpu_ms: None, speedup: None, agrees: Some(false), error: Some(err.to_string()) },
+            None => BenchCase { vertices: *vertices, edges, cpu_ms: cpu_time.as_secs_f64() * 1000.0, gpu_ms: None, speedup: None, agrees: None, error: None }
+        };
+
+        // Print the progress, as the largest graphs can take a while
+        match (&case.gpu_ms, &case.error) {
+            (Some(gpu_ms), _) => info!("Benchmarked {} vertices: {:.3} ms in the CPU, {:.3} ms in the device", vertices, case.cpu_ms, gpu_ms),
+            (None, Some(err)) => warn!("The device can't walk {} vertices: {}", vertices, err),
+            (None, None) => info!("Benchmarked {} vertices: {:.3} ms in the CPU", vertices, case.cpu_ms)
+        }
+
+        case
+    }).collect();
+
+    BenchReport {
+        device: walker.map(SortestPath::device_name),
+        backend_version: walker.map(|walker| walker.backend_version().to_string()),
+        seed,
+        edge_probability,
+        repetitions,
+        cases
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_walk() {
+        // Prepare a triangle, and a graph with a vertex that can't be reached
+        let triangle = Matrix::new(3, 3, vec![0.0, 1.0, 4.0, 1.0, 0.0, 2.0, 4.0, 2.0, 0.0]);
+        let island = Matrix::new(3, 3, vec![0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+
+        // Get the result
+        let path = reference_walk(&triangle, 0);
+        let unreachable = reference_walk(&island, 0);
+        let report = run_bench(None, &[8, 16], 1, 7, 0.5);
+
+        // Check if the result is correct
+        assert_eq!(path, vec![PathResult(0, 0.0), PathResult(0, 1.0), PathResult(1, 3.0)]);
+        assert_eq!(unreachable, vec![PathResult(0, 0.0), PathResult(0, 2.0), PathResult(0, 0.0)]);
+        assert!(same_distances(&[PathResult(0, 0.0), PathResult(0, 1.00001)], &[PathResult(0, 0.0), PathResult(0, 1.0)]));
+        assert_eq!(report.cases.iter().map(|case| case.vertices).collect::<Vec<_>>(), vec![8, 16]);
+        assert!(report.cases.iter().all(|case| case.gpu_ms.is_none() && case.agrees.is_none()));
+        assert!(!report.agrees());
+    }
+}
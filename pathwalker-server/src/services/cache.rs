@@ -0,0 +1,222 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::info;
+use lru::LruCache;
+
+use crate::models::{Matrix, PathResult};
+
+/// The environment variable with the number of results kept in the cache
+pub const CACHE_CAPACITY_VARIABLE: &str = "PATHWALKER_CACHE_CAPACITY";
+
+/// The environment variable with the seconds a result is kept in the cache
+pub const CACHE_TTL_VARIABLE: &str = "PATHWALKER_CACHE_TTL_SECONDS";
+
+/// The number of results kept in the cache by default
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// The seconds a result is kept in the cache by default
+const DEFAULT_TTL_SECONDS: u64 = 300;
+
+/// The query whose result is cached
+///
+/// # Fields
+///
+/// * `width` - The width of the matrix
+/// * `zero_edges` - If the zero weights of the matrix are edges
+/// * `directed` - If the matrix is directed
+/// * `data` - The bits of the weights of the matrix
+/// * `constraints` - The forbidden vertices and edges and the maximum hops of the paths
+/// * `schedule` - The bits of the weights of the time buckets, their seconds and the departure time
+/// * `deterministic` - If the ties of the predecessors are broken by the lowest vertex
+/// * `source` - The vertex the walk starts from
+/// * `target` - The vertex the walk goes to, if any
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    width: usize,
+    zero_edges: bool,
+    directed: bool,
+    data: Vec<u32>,
+    constraints: (Vec<usize>, Vec<[usize; 2]>, Option<usize>),
+    schedule: (Vec<Vec<u32>>, Option<u64>, Option<u64>),
+    deterministic: bool,
+    source: usize,
+    target: Option<usize>,
+}
+
+impl CacheKey {
+    /// Create the key of a query
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix` - The matrix of the query
+    /// * `source` - The vertex the walk starts from
+    /// * `target` - The vertex the walk goes to, if any
+    ///
+    /// # Returns
+    ///
+    /// * `CacheKey` - The key
+    ///
+    pub fn new(matrix: &Matrix, source: usize, target: Option<usize>) -> CacheKey {
+        CacheKey {
+            width: matrix.width,
+            zero_edges: matrix.zero_edges,
+            directed: matrix.directed,
+            data: matrix.data.iter().map(|weight| weight.to_bits()).collect(),
+            constraints: (matrix.forbidden_nodes.clone(), matrix.forbidden_edges.clone(), matrix.max_hops),
+            schedule: (
+                matrix.time_buckets.iter().map(|bucket| bucket.iter().map(|weight| weight.to_bits()).collect()).collect(),
+                matrix.bucket_seconds.map(f64::to_bits),
+                matrix.departure_time.map(f64::to_bits)
+            ),
+            deterministic: matrix.deterministic,
+            source,
+            target
+        }
+    }
+
+    /// Get the hash of the key, which indexes the cache
+    fn digest(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// A result kept in the cache, with the key to tell apart colliding hashes
+struct CacheEntry {
+    key: CacheKey,
+    path: Vec<PathResult>,
+    inserted_at: Instant,
+}
+
+/// The cache of the results of the walks
+///
+/// Keeps the most recently used results, indexed by the hash of the query,
+/// for a limited time. A capacity of zero disables the cache.
+///
+/// # Fields
+///
+/// * `entries` - The cached results
+/// * `ttl` - How long a result is kept
+///
+pub struct ResultCache {
+    entries: Option<Mutex<LruCache<u64, CacheEntry>>>,
+    ttl: Duration,
+}
+
+impl ResultCache {
+    /// Create a cache with the capacity and TTL configured in the environment
+    ///
+    /// # Returns
+    ///
+    /// * `ResultCache` - The cache, with 1024 results kept for 300 seconds by default
+    ///
+    pub fn new() -> ResultCache {
+        let capacity = std::env::var(CACHE_CAPACITY_VARIABLE).ok()
+            .and_then(|capacity| capacity.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+        let ttl = std::env::var(CACHE_TTL_VARIABLE).ok()
+            .and_then(|ttl| ttl.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECONDS);
+
+        info!("Caching up to {} results for {} seconds", capacity, ttl);
+
+        ResultCache::with_capacity(capacity, Duration::from_secs(ttl))
+    }
+
+    /// Create a cache with a capacity and TTL
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The number of results kept
+    /// * `ttl` - How long a result is kept
+    ///
+    /// # Returns
+    ///
+    /// * `ResultCache` - The cache
+    ///
+    pub fn with_capacity(capacity: usize, ttl: Duration) -> ResultCache {
+        ResultCache { entries: NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity))), ttl }
+    }
+
+    /// Get the cached result of a query
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The query
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<PathResult>>` - The path, if it's cached and hasn't expired
+    ///
+    pub fn get(&self, key: &CacheKey) -> Option<Vec<PathResult>> {
+        let mut entries = self.entries.as_ref()?.lock().unwrap();
+        let digest = key.digest();
+
+        match entries.get(&digest) {
+            Some(entry) if entry.inserted_at.elapsed() >= self.ttl => {
+                entries.pop(&digest);
+                None
+            },
+            Some(entry) if entry.key == *key => Some(entry.path.clone()),
+            _ => None
+        }
+    }
+
+    /// Cache the result of a query
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The query
+    /// * `path` - The path of the walk
+    ///
+    pub fn insert(&self, key: CacheKey, path: Vec<PathResult>) {
+        if let Some(entries) = &self.entries {
+            entries.lock().unwrap().put(key.digest(), CacheEntry { key, path, inserted_at: Instant::now() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_cache() {
+        // Prepare the queries
+        let first = CacheKey::new(&Matrix::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]), 0, None);
+        let second = CacheKey::new(&Matrix::new(2, 2, vec![0.0, 2.0, 2.0, 0.0]), 0, None);
+        let third = CacheKey::new(&Matrix::new(2, 2, vec![0.0, 2.0, 2.0, 0.0]), 1, Some(0));
+
+        // Get the result, evicting the least recently used query
+        let cache = ResultCache::with_capacity(2, Duration::from_secs(60));
+        cache.insert(first.clone(), vec![PathResult(0, 0.0), PathResult(0, 1.0)]);
+        cache.insert(second.clone(), vec![PathResult(0, 0.0), PathResult(0, 2.0)]);
+        cache.get(&first);
+        cache.insert(third.clone(), vec![PathResult(1, 2.0), PathResult(1, 0.0)]);
+
+        // Check if the result is correct
+        assert_eq!(cache.get(&first), Some(vec![PathResult(0, 0.0), PathResult(0, 1.0)]));
+        assert_eq!(cache.get(&second), None);
+        assert_eq!(cache.get(&third), Some(vec![PathResult(1, 2.0), PathResult(1, 0.0)]));
+    }
+
+    #[test]
+    fn test_result_cache_expired() {
+        // Prepare the query
+        let key = CacheKey::new(&Matrix::new(1, 1, vec![0.0]), 0, None);
+
+        // Get the result
+        let cache = ResultCache::with_capacity(8, Duration::ZERO);
+        cache.insert(key.clone(), vec![PathResult(0, 0.0)]);
+
+        // Check if the result is correct
+        assert_eq!(cache.get(&key), None);
+        assert_eq!(ResultCache::with_capacity(0, Duration::from_secs(60)).get(&key), None);
+    }
+}
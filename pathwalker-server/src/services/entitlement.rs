@@ -0,0 +1,276 @@
+use std::fs;
+
+use chrono::Utc;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+/// The environment variable with the path of the license file
+pub const LICENSE_VARIABLE: &str = "PATHWALKER_LICENSE";
+
+/// The public key that verifies the signature of the licenses
+///
+/// The signing key never leaves the vendor, so licenses can be verified
+/// offline in air-gapped deployments.
+const LICENSE_PUBLIC_KEY: &str = "4e7e2a4f7d84d6224655b8bbef07ee7dfa6c59572cb94dd7d152871a34ee4201";
+
+/// The feature sets that a license can enable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Streaming,
+    Explain,
+    Import,
+    Graphs,
+    Grpc,
+    Apsp,
+    Cost,
+    Budget,
+    Ev,
+    Grid,
+    Replan,
+    FlowField,
+    Walks,
+    Loads,
+    Failures,
+    WhatIf,
+    Centrality,
+    PageRank,
+    MaxFlow,
+    Dag,
+    Sources,
+    #[cfg(feature = "flight")]
+    Flight,
+    #[cfg(feature = "mqtt")]
+    Mqtt,
+}
+
+impl Feature {
+    /// Every feature of this build, in the order of the licenses
+    pub const ALL: &'static [Feature] = &[
+        Feature::Streaming, Feature::Explain, Feature::Import, Feature::Graphs, Feature::Grpc, Feature::Apsp,
+        Feature::Cost, Feature::Budget, Feature::Ev, Feature::Grid, Feature::Replan, Feature::FlowField,
+        Feature::Walks, Feature::Loads, Feature::Failures, Feature::WhatIf, Feature::Centrality, Feature::PageRank,
+        Feature::MaxFlow, Feature::Dag, Feature::Sources,
+        #[cfg(feature = "flight")]
+        Feature::Flight,
+        #[cfg(feature = "mqtt")]
+        Feature::Mqtt,
+    ];
+
+    /// Get the name of the feature, as written in the licenses
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::Streaming => "streaming",
+            Feature::Explain => "explain",
+            Feature::Import => "import",
+            Feature::Graphs => "graphs",
+            Feature::Grpc => "grpc",
+            Feature::Apsp => "apsp",
+            Feature::Cost => "cost",
+            Feature::Budget => "budget",
+            Feature::Ev => "ev",
+            Feature::Grid => "grid",
+            Feature::Replan => "replan",
+            Feature::FlowField => "flowfield",
+            Feature::Walks => "walks",
+            Feature::Loads => "loads",
+            Feature::Failures => "failures",
+            Feature::WhatIf => "whatif",
+            Feature::Centrality => "centrality",
+            Feature::PageRank => "pagerank",
+            Feature::MaxFlow => "maxflow",
+            Feature::Dag => "dag",
+            Feature::Sources => "sources",
+            #[cfg(feature = "flight")]
+            Feature::Flight => "flight",
+            #[cfg(feature = "mqtt")]
+            Feature::Mqtt => "mqtt"
+        }
+    }
+}
+
+/// The claims of a license, which are the signed part of the license file
+///
+/// The features are kept as names, so a license listing features unknown
+/// to this version of the server is still valid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LicenseClaims {
+    pub customer: String,
+    pub expires_at: Option<i64>,
+    pub features: Vec<String>,
+}
+
+/// The license file, with the claims and their signature in hexadecimal
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LicenseFile {
+    pub claims: LicenseClaims,
+    pub signature: String,
+}
+
+/// The features enabled in this deployment
+///
+/// Without a license every feature is enabled, while a license restricts
+/// the features to the ones it lists until it expires.
+///
+/// # Fields
+///
+/// * `license` - The claims of the verified license, if any
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entitlements {
+    license: Option<LicenseClaims>,
+}
+
+impl Entitlements {
+    /// Create the entitlements of a deployment without license
+    pub fn unrestricted() -> Entitlements {
+        Entitlements { license: None }
+    }
+
+    /// Create the entitlements of a deployment with a license that can't be verified
+    pub fn locked() -> Entitlements {
+        Entitlements { license: Some(LicenseClaims { customer: String::new(), expires_at: Some(0), features: vec![] }) }
+    }
+
+    /// Read the license configured in the environment
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Entitlements, String>` - The entitlements, unrestricted when no license is configured,
+    ///   or the reason why the license can't be verified
+    ///
+    pub fn from_env() -> Result<Entitlements, String> {
+        let path = match std::env::var(LICENSE_VARIABLE) {
+            Ok(path) => path,
+            Err(_) => return Ok(Entitlements::unrestricted())
+        };
+
+        let text = fs::read_to_string(&path).map_err(|err| format!("The license {} can't be read: {}", path, err))?;
+        let key = hex::decode(LICENSE_PUBLIC_KEY).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .and_then(|bytes: [u8; 32]| VerifyingKey::from_bytes(&bytes).ok())
+            .expect("The license public key is not valid");
+
+        Entitlements::from_license(&text, &key)
+    }
+
+    /// Verify a license and read its entitlements
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The license file
+    /// * `key` - The public key that verifies the signature
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Entitlements, String>` - The entitlements, or the reason why the license can't be verified
+    ///
+    pub fn from_license(text: &str, key: &VerifyingKey) -> Result<Entitlements, String> {
+        let license: LicenseFile = serde_json::from_str(text).map_err(|err| format!("The license can't be parsed: {}", err))?;
+
+        // Verify the signature of the claims
+        let signature = hex::decode(&license.signature).ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or("The license signature is malformed")?;
+        let claims = serde_json::to_vec(&license.claims).map_err(|err| err.to_string())?;
+
+        key.verify(&claims, &signature).map_err(|_| "The license signature is not valid")?;
+
+        // Print the license info
+        info!("Loaded license for {} with features {:?}", license.claims.customer, license.claims.features);
+
+        if license.claims.expires_at.is_some_and(|expires_at| expires_at <= Utc::now().timestamp_millis()) {
+            warn!("The license for {} has expired", license.claims.customer);
+        }
+
+        Ok(Entitlements { license: Some(license.claims) })
+    }
+
+    /// Check if a feature can be used
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - The feature to check
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Nothing if the feature is enabled, or the reason why it's disabled
+    ///
+    pub fn require(&self, feature: Feature) -> Result<(), String> {
+        let license = match &self.license {
+            Some(license) => license,
+            None => return Ok(())
+        };
+
+        if license.expires_at.is_some_and(|expires_at| expires_at <= Utc::now().timestamp_millis()) {
+            Err(format!("The feature {} is disabled because the license has expired", feature.name()))
+        } else if !license.features.iter().any(|name| name == feature.name()) {
+            Err(format!("The feature {} is not enabled by the license", feature.name()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check if a feature is enabled
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - The feature to check
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If the feature can be used
+    ///
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        self.require(feature).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer, SigningKey};
+    use super::*;
+
+    /// Sign the claims of a license with a key only known by the tests
+    fn sign_license(key: &SigningKey, claims: LicenseClaims) -> String {
+        let signature = key.sign(&serde_json::to_vec(&claims).unwrap());
+        serde_json::to_string(&LicenseFile { claims, signature: hex::encode(signature.to_bytes()) }).unwrap()
+    }
+
+    #[test]
+    fn test_license_entitlements() {
+        // Prepare the license
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let license = sign_license(&key, LicenseClaims {
+            customer: "ACME".to_string(),
+            expires_at: None,
+            features: vec!["explain".to_string(), "clustering".to_string()]
+        });
+
+        // Get the result
+        let entitlements = Entitlements::from_license(&license, &key.verifying_key()).unwrap();
+
+        // Check if the result is correct
+        assert!(entitlements.is_enabled(Feature::Explain));
+        assert_eq!(entitlements.require(Feature::Import), Err("The feature import is not enabled by the license".to_string()));
+        assert!(Entitlements::unrestricted().is_enabled(Feature::Import));
+        assert!(!Entitlements::locked().is_enabled(Feature::Explain));
+    }
+
+    #[test]
+    fn test_license_tampered() {
+        // Prepare the license, enabling one more feature after signing it
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let license = sign_license(&key, LicenseClaims {
+            customer: "ACME".to_string(),
+            expires_at: Some(0),
+            features: vec!["explain".to_string()]
+        }).replace("[\"explain\"]", "[\"explain\",\"grpc\"]");
+
+        // Get the result
+        let result = Entitlements::from_license(&license, &key.verifying_key());
+
+        // Check if the result is correct
+        assert_eq!(result, Err("The license signature is not valid".to_string()));
+    }
+}
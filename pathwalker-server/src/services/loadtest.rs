@@ -0,0 +1,434 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use actix_web::{http::{header, Method}, rt, web::Bytes};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::services::generator::{GraphSpec, SplitMix64};
+
+/// The time a request of the load test can take before counting as failed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The largest response body read by the load test
+const MAX_RESPONSE_SIZE: usize = 64 * 1024 * 1024;
+
+/// The prefix of the names of the graphs uploaded by the load test
+pub const GRAPH_PREFIX: &str = "loadtest-";
+
+/// The kind of a request of the load test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestKind {
+    /// Uploads a graph with `PUT /v1/graphs/{name}`
+    Upload,
+    /// Walks a stored graph between two vertices with `POST /v1/graphs/{name}/sortest`
+    Query,
+    /// Walks a stored graph from every vertex with `POST /v1/graphs/{name}/apsp`
+    Batch,
+}
+
+/// The share of every kind of request in the traffic, as relative weights
+///
+/// # Fields
+///
+/// * `upload` - The weight of the uploads
+/// * `query` - The weight of the point to point queries
+/// * `batch` - The weight of the all-pairs batches
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrafficMix {
+    #[serde(default)]
+    pub upload: u32,
+    #[serde(default)]
+    pub query: u32,
+    #[serde(default)]
+    pub batch: u32,
+}
+
+impl Default for TrafficMix {
+    fn default() -> Self {
+        TrafficMix { upload: 1, query: 8, batch: 1 }
+    }
+}
+
+/// The profile of the traffic of a load test
+///
+/// # Fields
+///
+/// * `duration` - The seconds the traffic is sent for
+/// * `graphs` - The number of graphs uploaded and queried
+/// * `block` - The sources walked at once by the batches
+/// * `graph` - The specification of the graphs, each one generated with the next seed
+/// * `mix` - The share of every kind of request
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoadProfile {
+    #[serde(default = "default_duration")]
+    pub duration: u64,
+    #[serde(default = "default_graphs")]
+    pub graphs: usize,
+    pub block: Option<usize>,
+    pub graph: GraphSpec,
+    #[serde(default)]
+    pub mix: TrafficMix,
+}
+
+/// The seconds of traffic of a profile by default
+fn default_duration() -> u64 {
+    30
+}
+
+/// The graphs of a profile by default
+fn default_graphs() -> usize {
+    4
+}
+
+/// A request of the load test, before being sent
+///
+/// # Fields
+///
+/// * `kind` - The kind of the request
+/// * `method` - The method of the request
+/// * `path` - The path and query of the request
+/// * `graph` - The graph of the request, whose matrix is the body of the uploads
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedRequest {
+    pub kind: RequestKind,
+    pub method: Method,
+    pub path: String,
+    pub graph: usize,
+}
+
+/// The latency percentiles of a set of requests, in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// The results of a kind of request, or of every request
+///
+/// # Fields
+///
+/// * `requests` - The requests sent
+/// * `errors` - The requests that failed or weren't answered with a success
+/// * `statuses` - The requests answered with every status, or `failed` when not answered
+/// * `latency` - The latency percentiles of the requests
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RequestReport {
+    pub requests: usize,
+    pub errors: usize,
+    pub statuses: BTreeMap<String, usize>,
+    pub latency: LatencySummary,
+}
+
+/// The results of a load test
+///
+/// # Fields
+///
+/// * `duration_seconds` - The time taken to send the traffic and receive every response
+/// * `achieved_qps` - The requests answered per second
+/// * `total` - The results of every request
+/// * `kinds` - The results of every kind of request
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadReport {
+    pub duration_seconds: f64,
+    pub achieved_qps: f64,
+    pub total: RequestReport,
+    pub kinds: BTreeMap<RequestKind, RequestReport>,
+}
+
+/// The outcome of a request of the load test
+///
+/// # Fields
+///
+/// * `kind` - The kind of the request
+/// * `status` - The status of the response, if answered
+/// * `latency` - The time until the whole response has been read
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub kind: RequestKind,
+    pub status: Option<u16>,
+    pub latency: Duration,
+}
+
+impl LoadProfile {
+    /// Validate the profile
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` - The reason why the profile isn't valid, if any
+    ///
+    pub fn validate(&self) -> Option<&'static str> {
+        if self.duration == 0 {
+            Some("The duration must be greater than zero")
+        } else if self.graphs == 0 {
+            Some("The profile must have graphs")
+        } else if self.graph.vertices > 128 {
+            Some("The graphs can't have more than 128 vertices")
+        } else if self.mix.upload + self.mix.query + self.mix.batch == 0 {
+            Some("The mix must have some requests")
+        } else {
+            self.graph.validate()
+        }
+    }
+
+    /// Generate the matrices of the graphs of the profile
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Bytes>` - The JSON body uploading every graph
+    ///
+    pub fn graph_bodies(&self) -> Vec<Bytes> {
+        (0..self.graphs).map(|graph| {
+            let spec = GraphSpec { seed: self.graph.seed.wrapping_add(graph as u64), ..self.graph.clone() };
+            Bytes::from(serde_json::to_vec(&spec.generate()).unwrap())
+        }).collect()
+    }
+
+    /// Plan the next request of the traffic
+    ///
+    /// # Arguments
+    ///
+    /// * `random` - The generator of random numbers of the traffic
+    ///
+    /// # Returns
+    ///
+    /// * `PlannedRequest` - The request
+    ///
+    pub fn plan(&self, random: &mut SplitMix64) -> PlannedRequest {
+        let TrafficMix { upload, query, batch } = self.mix;
+        let draw = random.next_u64() % u64::from(upload + query + batch);
+        let graph = (random.next_u64() % self.graphs as u64) as usize;
+        let name = format!("{}{}", GRAPH_PREFIX, graph);
+
+        // Pick the kind in proportion to its weight
+        if draw < u64::from(upload) {
+            PlannedRequest { kind: RequestKind::Upload, method: Method::PUT, path: format!("/v1/graphs/{}", name), graph }
+        } else if draw < u64::from(upload + query) {
+            let vertices = self.graph.vertices as u64;
+            let (source, target) = (random.next_u64() % vertices, random.next_u64() % vertices);
+            PlannedRequest { kind: RequestKind::Query, method: Method::POST, path: format!("/v1/graphs/{}/sortest?source={}&target={}", name, source, target), graph }
+        } else {
+            let block = self.block.map(|block| format!("?block={}", block)).unwrap_or_default();
+            PlannedRequest { kind: RequestKind::Batch, method: Method::POST, path: format!("/v1/graphs/{}/apsp{}", name, block), graph }
+        }
+    }
+}
+
+/// Get a percentile of sorted latencies, by the nearest rank
+fn percentile(sorted: &[Duration], percentile: f64) -> f64 {
+    let rank = ((percentile * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1].as_secs_f64() * 1000.0
+}
+
+impl RequestReport {
+    /// Summarize the outcome of some requests
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - The outcome of the requests
+    ///
+    /// # Returns
+    ///
+    /// * `RequestReport` - The results
+    ///
+    pub fn summarize<'a>(samples: impl Iterator<Item = &'a Sample>) -> RequestReport {
+        let mut report = RequestReport::default();
+        let mut latencies = vec![];
+
+        for sample in samples {
+            let status = sample.status.map_or_else(|| "failed".to_string(), |status| status.to_string());
+
+            report.requests += 1;
+            report.errors += usize::from(!sample.status.is_some_and(|status| (200..300).contains(&status)));
+            *report.statuses.entry(status).or_default() += 1;
+            latencies.push(sample.latency);
+        }
+
+        if !latencies.is_empty() {
+            latencies.sort();
+            report.latency = LatencySummary {
+                p50_ms: percentile(&latencies, 0.50),
+                p90_ms: percentile(&latencies, 0.90),
+                p99_ms: percentile(&latencies, 0.99),
+                max_ms: percentile(&latencies, 1.0)
+            };
+        }
+
+        report
+    }
+}
+
+impl LoadReport {
+    /// Summarize the outcome of a load test
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - The outcome of every request
+    /// * `elapsed` - The time taken by the load test
+    ///
+    /// # Returns
+    ///
+    /// * `LoadReport` - The results
+    ///
+    pub fn summarize(samples: &[Sample], elapsed: Duration) -> LoadReport {
+        let mut kinds: BTreeMap<RequestKind, Vec<&Sample>> = BTreeMap::new();
+
+        for sample in samples {
+            kinds.entry(sample.kind).or_default().push(sample);
+        }
+
+        let total = RequestReport::summarize(samples.iter());
+
+        LoadReport {
+            duration_seconds: elapsed.as_secs_f64(),
+            achieved_qps: (total.requests - total.errors) as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            total,
+            kinds: kinds.into_iter().map(|(kind, samples)| (kind, RequestReport::summarize(samples.into_iter()))).collect()
+        }
+    }
+}
+
+/// Send a request of the load test and measure it
+async fn send(client: &awc::Client, target: &str, request: &PlannedRequest, body: Bytes, api_key: Option<&str>) -> Sample {
+    let started_at = Instant::now();
+    let mut builder = client.request(request.method.clone(), format!("{}{}", target, request.path));
+
+    if let Some(key) = api_key {
+        builder = builder.insert_header((header::AUTHORIZATION, format!("Bearer {}", key)));
+    }
+
+    // Only the uploads carry the matrix
+    let response = if request.kind == RequestKind::Upload {
+        builder.content_type("application/json").send_body(body).await
+    } else {
+        builder.send().await
+    };
+
+    // Read the whole response, as the batches stream their rows
+    let status = match response {
+        Ok(mut response) => response.body().limit(MAX_RESPONSE_SIZE).await.ok().map(|_| response.status().as_u16()),
+        Err(_) => None
+    };
+
+    Sample { kind: request.kind, status, latency: started_at.elapsed() }
+}
+
+/// Run a load test against a server
+///
+/// Uploads the graphs of the profile, and then sends the mixed traffic at
+/// a constant rate, without waiting for the responses, so a slow server
+/// shows up in the latencies instead of lowering the rate.
+///
+/// # Arguments
+///
+/// * `target` - The base URL of the server
+/// * `qps` - The requests sent per second
+/// * `profile` - The profile of the traffic
+/// * `api_key` - The API key of the requests, if the server requires one
+///
+/// # Returns
+///
+/// * `Result<LoadReport, String>` - The results, or the reason why the graphs can't be uploaded
+///
+pub async fn run_load(target: &str, qps: f64, profile: &LoadProfile, api_key: Option<&str>) -> Result<LoadReport, String> {
+    let target = target.trim_end_matches('/').to_string();
+    let client = awc::Client::builder().timeout(REQUEST_TIMEOUT).finish();
+    let bodies = profile.graph_bodies();
+
+    // Upload the graphs before querying them
+    for (graph, body) in bodies.iter().enumerate() {
+        let upload = PlannedRequest { kind: RequestKind::Upload, method: Method::PUT, path: format!("/v1/graphs/{}{}", GRAPH_PREFIX, graph), graph };
+        let sample = send(&client, &target, &upload, body.clone(), api_key).await;
+
+        match sample.status {
+            Some(status) if (200..300).contains(&status) => {},
+            Some(status) => return Err(format!("The graph {}{} has been rejected with status {}", GRAPH_PREFIX, graph, status)),
+            None => return Err(format!("The server at {} can't be reached", target))
+        }
+    }
+
+    info!("Uploaded {} graphs, sending {} requests per second for {} seconds", profile.graphs, qps, profile.duration);
+
+    // Send the traffic at a constant rate
+    let samples = Rc::new(RefCell::new(vec![]));
+    let mut random = SplitMix64(profile.graph.seed);
+    let mut ticks = rt::time::interval(Duration::from_secs_f64(1.0 / qps));
+    let mut tasks = vec![];
+    let started_at = Instant::now();
+
+    for _ in 0..(qps * profile.duration as f64).ceil() as usize {
+        ticks.tick().await;
+
+        let request = profile.plan(&mut random);
+        let (client, target, body, samples) = (client.clone(), target.clone(), bodies[request.graph].clone(), samples.clone());
+        let api_key = api_key.map(str::to_string);
+
+        tasks.push(rt::spawn(async move {
+            let sample = send(&client, &target, &request, body, api_key.as_deref()).await;
+            samples.borrow_mut().push(sample);
+        }));
+    }
+
+    // Wait for the responses still in flight
+    for task in tasks {
+        if task.await.is_err() {
+            warn!("A request of the load test has been aborted");
+        }
+    }
+
+    let samples = samples.borrow();
+    Ok(LoadReport::summarize(&samples, started_at.elapsed()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_profile() {
+        // Prepare the profile
+        let profile: LoadProfile = serde_norway::from_str(r#"
+            duration: 10
+            graphs: 2
+            graph:
+              vertices: 16
+              topology: { kind: uniform, probability: 0.5 }
+            mix: { upload: 1, query: 3 }
+        "#).unwrap();
+
+        let samples: Vec<Sample> = (1..=100).map(|millis| Sample {
+            kind: if millis % 4 == 0 { RequestKind::Upload } else { RequestKind::Query },
+            status: Some(if millis == 100 { 503 } else { 200 }),
+            latency: Duration::from_millis(millis)
+        }).collect();
+
+        // Get the result
+        let mut random = SplitMix64(1);
+        let requests: Vec<PlannedRequest> = (0..400).map(|_| profile.plan(&mut random)).collect();
+        let report = LoadReport::summarize(&samples, Duration::from_secs(1));
+
+        // Check if the result is correct
+        assert_eq!(profile.validate(), None);
+        assert_eq!(profile.graph_bodies().len(), 2);
+        assert!(requests.iter().all(|request| request.kind != RequestKind::Batch && request.graph < 2));
+        assert!((250..350).contains(&requests.iter().filter(|request| request.kind == RequestKind::Query).count()));
+        assert_eq!(report.total.requests, 100);
+        assert_eq!(report.total.errors, 1);
+        assert_eq!(report.total.latency, LatencySummary { p50_ms: 50.0, p90_ms: 90.0, p99_ms: 99.0, max_ms: 100.0 });
+        assert_eq!(report.kinds[&RequestKind::Upload].requests, 25);
+        assert_eq!(report.achieved_qps, 99.0);
+    }
+}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use pathwalker_core::services::metrics::{register, REGISTRY};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, TextEncoder};
+
+pub use pathwalker_core::services::metrics::device_memory;
+
+lazy_static! {
+    static ref HTTP_REQUESTS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("pathwalker_http_requests_total", "The number of HTTP requests served"),
+        &["method", "path", "status"]
+    ).unwrap());
+
+    static ref HTTP_DURATION: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new("pathwalker_http_request_duration_seconds", "The latency of the HTTP requests"),
+        &["method", "path"]
+    ).unwrap());
+
+    static ref AFFINITY_REQUESTS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("pathwalker_affinity_requests_total", "The graph queries with an affinity token, by the replica it names"),
+        &["replica"]
+    ).unwrap());
+}
+
+/// Record a served HTTP request
+///
+/// # Arguments
+///
+/// * `method` - The method of the request
+/// * `path` - The route pattern of the request, to keep the cardinality bounded
+/// * `status` - The status code of the response
+/// * `elapsed` - How long it took to serve the request
+///
+pub fn observe_request(method: &str, path: &str, status: u16, elapsed: Duration) {
+    HTTP_REQUESTS.with_label_values(&[method, path, &status.to_string()]).inc();
+    HTTP_DURATION.with_label_values(&[method, path]).observe(elapsed.as_secs_f64());
+}
+
+/// Record a graph query carrying an affinity token
+///
+/// # Arguments
+///
+/// * `local` - If the token names this replica
+///
+pub fn observe_affinity(local: bool) {
+    AFFINITY_REQUESTS.with_label_values(&[if local { "local" } else { "remote" }]).inc();
+}
+
+/// Render the metrics in the Prometheus text format
+///
+/// # Returns
+///
+/// * `String` - The metrics
+///
+pub fn render() -> String {
+    // Register the metrics that haven't been observed yet
+    lazy_static::initialize(&HTTP_REQUESTS);
+    lazy_static::initialize(&HTTP_DURATION);
+    lazy_static::initialize(&AFFINITY_REQUESTS);
+    pathwalker_core::services::metrics::initialize();
+
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use pathwalker_core::services::metrics::{time_kernel, KernelStage};
+
+    use super::*;
+
+    #[test]
+    fn test_render_metrics() {
+        // Prepare the metrics
+        observe_request("POST", "/sortest", 200, Duration::from_millis(12));
+        time_kernel(KernelStage::Merge, || ());
+
+        // Get the result
+        let text = render();
+
+        // Check if the result is correct
+        assert!(text.contains("pathwalker_http_requests_total{method=\"POST\",path=\"/sortest\",status=\"200\"}"));
+        assert!(text.contains("pathwalker_kernel_duration_seconds_count{kernel=\"merge\"}"));
+        assert!(text.contains("# TYPE pathwalker_queue_depth gauge"));
+    }
+}
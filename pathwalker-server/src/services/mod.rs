@@ -0,0 +1,26 @@
+pub mod affinity;
+pub mod auth;
+pub mod bench;
+pub mod cache;
+pub mod entitlement;
+pub mod loadtest;
+pub mod metrics;
+pub mod rate_limit;
+pub mod replay;
+pub mod repository;
+pub mod sessions;
+pub mod shadow;
+pub mod soak;
+pub mod sockets;
+pub mod store;
+pub mod thermal;
+pub mod usage;
+pub mod uploads;
+pub mod walker;
+
+pub use pathwalker_core::services::{apsp, backend, budget, cancellation, centrality, cost, cpu_walker, dag, devices, dispatch, explain, export, failures, flowfield, generator, grid, import, kernels, loads, maxflow, memory, opencl, overlay, pagerank, postprocess, preflight, program_cache, replan, run_id, shutdown, sortest_path, units, unweighted, variants, walks};
+
+#[cfg(feature = "cuda")]
+pub use pathwalker_core::services::cuda_walker;
+#[cfg(feature = "wgpu")]
+pub use pathwalker_core::services::wgpu_walker;
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// The most clients tracked at once, forgetting the idle ones to track new clients
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// The seconds a client should wait when it has too many requests in flight
+const IN_FLIGHT_RETRY_AFTER: u64 = 1;
+
+/// The usage of the server by a client
+///
+/// # Fields
+///
+/// * `tokens` - The requests the client can still make right away
+/// * `updated_at` - When the tokens have been refilled last
+/// * `in_flight` - The requests of the client being computed
+///
+struct ClientUsage {
+    tokens: f64,
+    updated_at: Instant,
+    in_flight: usize,
+}
+
+/// The limits of the requests of every client
+///
+/// Every client, named by its API key or its address, has a token bucket
+/// refilled at the configured requests per second, up to a burst of one
+/// second of requests, and a maximum number of requests computed at once.
+///
+/// # Fields
+///
+/// * `rate` - The requests per second of a client, if limited
+/// * `max_in_flight` - The requests of a client computed at once, if limited
+/// * `clients` - The usage of every client
+///
+pub struct RateLimiter {
+    rate: Option<f64>,
+    max_in_flight: Option<usize>,
+    clients: Mutex<HashMap<String, ClientUsage>>,
+}
+
+/// The permission of a request to be computed
+///
+/// The request stops counting as in flight when the permit is dropped.
+pub struct ClientPermit {
+    limiter: Arc<RateLimiter>,
+    client: String,
+}
+
+impl Drop for ClientPermit {
+    fn drop(&mut self) {
+        if let Some(usage) = self.limiter.clients.lock().unwrap().get_mut(&self.client) {
+            usage.in_flight = usage.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Create the limits of the requests of every client
+    ///
+    /// # Arguments
+    ///
+    /// * `rate` - The requests per second of a client, if limited
+    /// * `max_in_flight` - The requests of a client computed at once, if limited
+    ///
+    /// # Returns
+    ///
+    /// * `RateLimiter` - The limits
+    ///
+    pub fn new(rate: Option<f64>, max_in_flight: Option<usize>) -> RateLimiter {
+        RateLimiter {
+            rate: rate.filter(|rate| *rate > 0.0),
+            max_in_flight: max_in_flight.filter(|max| *max > 0),
+            clients: Mutex::new(HashMap::new())
+        }
+    }
+
+    /// Check if any limit is enforced
+    pub fn is_enabled(&self) -> bool {
+        self.rate.is_some() || self.max_in_flight.is_some()
+    }
+
+    /// Admit a request of a client
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The name of the client
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ClientPermit, u64>` - The permit of the request, or the seconds to wait before retrying
+    ///
+    pub fn acquire(self: &Arc<Self>, client: &str) -> Result<ClientPermit, u64> {
+        let now = Instant::now();
+        let burst = self.rate.map_or(0.0, |rate| rate.max(1.0));
+        let mut clients = self.clients.lock().unwrap();
+
+        // Forget the idle clients before tracking a new one, and the least recently seen one if none is idle
+        if clients.len() >= MAX_TRACKED_CLIENTS && !clients.contains_key(client) {
+            clients.retain(|_, usage| usage.in_flight > 0 || usage.tokens + now.duration_since(usage.updated_at).as_secs_f64() * self.rate.unwrap_or(0.0) < burst);
+
+            if clients.len() >= MAX_TRACKED_CLIENTS {
+                let oldest = clients.iter()
+                    .filter(|(_, usage)| usage.in_flight == 0)
+                    .min_by_key(|(_, usage)| usage.updated_at)
+                    .map(|(name, _)| name.clone());
+
+                // Turn the new client away while every tracked one has requests in flight
+                match oldest {
+                    Some(name) => clients.remove(&name),
+                    None => return Err(IN_FLIGHT_RETRY_AFTER)
+                };
+            }
+        }
+
+        let usage = clients.entry(client.to_string())
+            .or_insert(ClientUsage { tokens: burst, updated_at: now, in_flight: 0 });
+
+        if self.max_in_flight.is_some_and(|max| usage.in_flight >= max) {
+            return Err(IN_FLIGHT_RETRY_AFTER);
+        }
+
+        // Refill the tokens of the time passed and take one
+        if let Some(rate) = self.rate {
+            usage.tokens = (usage.tokens + now.duration_since(usage.updated_at).as_secs_f64() * rate).min(burst);
+            usage.updated_at = now;
+
+            if usage.tokens < 1.0 {
+                return Err(((1.0 - usage.tokens) / rate).ceil().max(1.0) as u64);
+            }
+
+            usage.tokens -= 1.0;
+        }
+
+        usage.in_flight += 1;
+        Ok(ClientPermit { limiter: self.clone(), client: client.to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        // Prepare the limiter
+        let limiter = Arc::new(RateLimiter::new(Some(2.0), Some(1)));
+
+        // Get the result
+        let first = limiter.acquire("robots");
+        let concurrent = limiter.acquire("robots").map(|_| ());
+        let other = limiter.acquire("dashboard").map(|_| ());
+
+        drop(first);
+        let second = limiter.acquire("robots").map(|_| ());
+        let third = limiter.acquire("robots").map(|_| ());
+
+        // Check if the result is correct
+        assert_eq!(concurrent, Err(1));
+        assert!(other.is_ok());
+        assert!(second.is_ok());
+        assert_eq!(third, Err(1));
+        assert!(!RateLimiter::new(Some(0.0), None).is_enabled());
+    }
+
+    #[test]
+    fn test_rate_limiter_max_clients() {
+        // Prepare the limiter with every client it can track computing a request
+        let limiter = Arc::new(RateLimiter::new(Some(1.0), Some(1)));
+        let mut permits: Vec<ClientPermit> = (0..MAX_TRACKED_CLIENTS).map(|index| limiter.acquire(&index.to_string()).ok().unwrap()).collect();
+
+        // Get the result
+        let busy = limiter.acquire("new").map(|_| ());
+        permits.truncate(1);
+        let evicted = limiter.acquire("new").map(|_| ());
+        let tracked = limiter.clients.lock().unwrap().len();
+
+        // Check if the result is correct
+        assert_eq!(busy, Err(IN_FLIGHT_RETRY_AFTER));
+        assert!(evicted.is_ok());
+        assert!(tracked <= MAX_TRACKED_CLIENTS);
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use log::info;
+
+use crate::models::{Matrix, PathResult, ReplayBundle};
+use crate::services::opencl;
+use crate::services::sortest_path::SortestPath;
+
+/// The environment variable with the directory where the bundles are written
+pub const RECORD_DIR_VARIABLE: &str = "PATHWALKER_RECORD_DIR";
+
+/// The directory where the bundles are written by default
+pub const DEFAULT_RECORD_DIR: &str = "bundles";
+
+/// Build the bundle of a computation
+///
+/// # Arguments
+///
+/// * `walker` - The walker that ran the computation
+/// * `reason` - Why the computation is recorded
+/// * `matrix` - The matrix that was walked
+/// * `result` - The result of the computation
+///
+/// # Returns
+///
+/// * `ReplayBundle` - The bundle reproducing the computation
+///
+pub fn build_bundle(walker: &SortestPath, reason: &str, matrix: &Matrix, result: &opencl::Result<Vec<PathResult>>) -> ReplayBundle {
+    ReplayBundle {
+        reason: reason.to_string(),
+        timestamp: Utc::now().timestamp_millis(),
+        platform: walker.platform_name(),
+        device: walker.device_name(),
+        kernels: SortestPath::kernel_launches(matrix),
+        matrix: matrix.clone(),
+        path: result.as_ref().ok().cloned(),
+        error: result.as_ref().err().map(|err| err.to_string())
+    }
+}
+
+/// Get the directory where the bundles are written, from `PATHWALKER_RECORD_DIR` or `bundles` when it isn't set
+fn record_dir() -> PathBuf {
+    PathBuf::from(std::env::var(RECORD_DIR_VARIABLE).unwrap_or_else(|_| DEFAULT_RECORD_DIR.to_string()))
+}
+
+/// Write a bundle in the record directory
+///
+/// The directory is created if it doesn't exist yet, and only the id of the
+/// bundle is returned, so the clients aren't told where the server keeps it.
+///
+/// # Arguments
+///
+/// * `bundle` - The bundle to write
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The id of the written bundle, the name of its file without the extension
+///
+pub fn record_bundle(bundle: &ReplayBundle) -> io::Result<String> {
+    let path = write_bundle(&record_dir(), bundle)?;
+    Ok(path.file_stem().unwrap_or_default().to_string_lossy().into_owned())
+}
+
+/// Find the file of a bundle, given its path or the id returned when it was recorded
+///
+/// # Arguments
+///
+/// * `bundle` - The path of the bundle, or its id in the record directory
+///
+/// # Returns
+///
+/// * `PathBuf` - The path of the bundle
+///
+pub fn bundle_path(bundle: &str) -> PathBuf {
+    let path = PathBuf::from(bundle);
+
+    if path.exists() {
+        path
+    } else {
+        record_dir().join(format!("{}.json", bundle))
+    }
+}
+
+/// Write a bundle in a directory
+///
+/// # Arguments
+///
+/// * `directory` - The directory to write the bundle into
+/// * `bundle` - The bundle to write
+///
+/// # Returns
+///
+/// * `io::Result<PathBuf>` - The path of the written bundle
+///
+pub fn write_bundle(directory: &Path, bundle: &ReplayBundle) -> io::Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+
+    // Name the bundle after the moment and the reason of the recording, with a random
+    // suffix so the bundles recorded in the same millisecond don't overwrite each other
+    let mut suffix = [0u8; 8];
+    getrandom::fill(&mut suffix).map_err(|err| io::Error::other(format!("The id of the bundle can't be drawn: {}", err)))?;
+
+    let path = directory.join(format!("bundle-{}-{}-{}.json", bundle.timestamp, bundle.reason, hex::encode(suffix)));
+    fs::write(&path, serde_json::to_vec_pretty(bundle)?)?;
+
+    // Print the location of the bundle
+    info!("Recorded replay bundle at {}", path.display());
+
+    Ok(path)
+}
+
+/// Read a bundle from a file
+///
+/// # Arguments
+///
+/// * `path` - The path of the bundle
+///
+/// # Returns
+///
+/// * `io::Result<ReplayBundle>` - The bundle
+///
+pub fn read_bundle(path: &Path) -> io::Result<ReplayBundle> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// Replay a bundle against a walker
+///
+/// # Arguments
+///
+/// * `walker` - The walker to run the computation on
+/// * `bundle` - The bundle to replay
+///
+/// # Returns
+///
+/// * `ReplayBundle` - The bundle of the new computation, to compare with the recorded one
+///
+pub fn replay_bundle(walker: &SortestPath, bundle: &ReplayBundle) -> ReplayBundle {
+    let result = walker.get_sortest_path(bundle.matrix.clone());
+    build_bundle(walker, "replay", &bundle.matrix, &result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read_bundle() {
+        // Prepare the bundle
+        let matrix = Matrix::new(2, 2, vec![
+            00.0, 01.0,
+            01.0, 00.0
+        ]);
+        let bundle = ReplayBundle {
+            reason: "kernel-error".to_string(),
+            timestamp: 1,
+            platform: "Portable Computing Language".to_string(),
+            device: "cpu".to_string(),
+            kernels: SortestPath::kernel_launches(&matrix),
+            matrix,
+            path: None,
+            error: Some("CL_OUT_OF_RESOURCES".to_string())
+        };
+
+        // Get the result
+        let directory = std::env::temp_dir().join("path_walker_test_bundles");
+        let path = write_bundle(&directory, &bundle).unwrap();
+        let other = write_bundle(&directory, &bundle).unwrap();
+        let result = read_bundle(&bundle_path(path.to_str().unwrap())).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(result.matrix.data, bundle.matrix.data);
+        assert_eq!(result.kernels, bundle.kernels);
+        assert_eq!(result.error, bundle.error);
+        assert_ne!(path, other);
+    }
+}
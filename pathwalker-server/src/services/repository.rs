@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{GraphInfo, Matrix, WeightUnit};
+
+/// The environment variable with the directory where the graphs are persisted
+pub const GRAPH_DIR_VARIABLE: &str = "PATHWALKER_GRAPH_DIR";
+
+/// The directory where the graphs are persisted by default
+pub const DEFAULT_GRAPH_DIR: &str = "graphs";
+
+/// Get the directory where the graphs are persisted when the environment doesn't set one
+///
+/// The tests keep their database in a directory of their own under the
+/// temporary directory, instead of the source tree.
+fn default_graph_dir() -> PathBuf {
+    if cfg!(test) {
+        std::env::temp_dir().join(format!("path_walker_test_store_{}", std::process::id()))
+    } else {
+        PathBuf::from(DEFAULT_GRAPH_DIR)
+    }
+}
+
+/// A graph as it's persisted, with its description and its matrix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersistedGraph {
+    pub info: GraphInfo,
+    pub matrix: Matrix,
+}
+
+/// A graph as it was persisted before the matrices could have zero weight edges
+type LegacyGraph = (GraphInfo, usize, usize, Vec<f32>, Option<u32>);
+
+/// A graph as it was persisted before the matrices could be undirected
+type DirectedGraph = (GraphInfo, usize, usize, Vec<f32>, Option<u32>, bool);
+
+/// A graph as it was persisted before the weights could declare their unit
+type UnitlessGraph = (GraphInfo, usize, usize, Vec<f32>, Option<u32>, bool, bool);
+
+/// A graph as it was persisted before the paths of the walks could be constrained
+type UnconstrainedGraph = (GraphInfo, usize, usize, Vec<f32>, Option<u32>, bool, bool, Option<WeightUnit>);
+
+/// A graph as it was persisted before the weights could be given in layers
+type UnlayeredGraph = (GraphInfo, usize, usize, Vec<f32>, Option<u32>, bool, bool, Option<WeightUnit>, Vec<usize>, Vec<[usize; 2]>, Option<usize>);
+
+/// A graph as it was persisted before the weights could change with the time
+type UnscheduledGraph = (GraphInfo, usize, usize, Vec<f32>, Option<u32>, bool, bool, Option<WeightUnit>, Vec<usize>, Vec<[usize; 2]>, Option<usize>, Vec<Vec<f32>>, Vec<f64>);
+
+/// A graph as it was persisted before the ties of its walks could be broken by the lowest vertex
+type UnorderedGraph = (GraphInfo, usize, usize, Vec<f32>, Option<u32>, bool, bool, Option<WeightUnit>, Vec<usize>, Vec<[usize; 2]>, Option<usize>, Vec<Vec<f32>>, Vec<f64>, Vec<Vec<f32>>, Option<f64>, Option<f64>);
+
+/// Decode a persisted graph, including the ones persisted by older servers
+///
+/// # Arguments
+///
+/// * `value` - The encoded graph
+///
+/// # Returns
+///
+/// * `Result<PersistedGraph, String>` - The graph, or the reason why it can't be decoded
+///
+fn decode_graph(value: &[u8]) -> Result<PersistedGraph, String> {
+    bincode::deserialize::<PersistedGraph>(value).or_else(|err| {
+        // The older graphs end at the fields their matrices had back then
+        if let Ok((info, width, height, data, round, zero_edges, directed, unit, forbidden_nodes, forbidden_edges, max_hops, _, _, time_buckets, bucket_seconds, departure_time)) = bincode::deserialize::<UnorderedGraph>(value) {
+            return Ok(PersistedGraph { info, matrix: Matrix { round, zero_edges, directed, unit, forbidden_nodes, forbidden_edges, max_hops, time_buckets, bucket_seconds, departure_time, ..Matrix::new(width, height, data) } });
+        }
+
+        if let Ok((info, width, height, data, round, zero_edges, directed, unit, forbidden_nodes, forbidden_edges, max_hops, _, _)) = bincode::deserialize::<UnscheduledGraph>(value) {
+            return Ok(PersistedGraph { info, matrix: Matrix { round, zero_edges, directed, unit, forbidden_nodes, forbidden_edges, max_hops, ..Matrix::new(width, height, data) } });
+        }
+
+        if let Ok((info, width, height, data, round, zero_edges, directed, unit, forbidden_nodes, forbidden_edges, max_hops)) = bincode::deserialize::<UnlayeredGraph>(value) {
+            return Ok(PersistedGraph { info, matrix: Matrix { round, zero_edges, directed, unit, forbidden_nodes, forbidden_edges, max_hops, ..Matrix::new(width, height, data) } });
+        }
+
+        if let Ok((info, width, height, data, round, zero_edges, directed, unit)) = bincode::deserialize::<UnconstrainedGraph>(value) {
+            return Ok(PersistedGraph { info, matrix: Matrix { round, zero_edges, directed, unit, ..Matrix::new(width, height, data) } });
+        }
+
+        if let Ok((info, width, height, data, round, zero_edges, directed)) = bincode::deserialize::<UnitlessGraph>(value) {
+            return Ok(PersistedGraph { info, matrix: Matrix { round, zero_edges, directed, ..Matrix::new(width, height, data) } });
+        }
+
+        if let Ok((info, width, height, data, round, zero_edges)) = bincode::deserialize::<DirectedGraph>(value) {
+            return Ok(PersistedGraph { info, matrix: Matrix { round, zero_edges, ..Matrix::new(width, height, data) } });
+        }
+
+        let (info, width, height, data, round) = bincode::deserialize::<LegacyGraph>(value).map_err(|_| err.to_string())?;
+        Ok(PersistedGraph { info, matrix: Matrix { round, ..Matrix::new(width, height, data) } })
+    })
+}
+
+/// The storage where the uploaded graphs outlive the server
+pub trait GraphRepository: Send + Sync {
+    /// Persist a graph, replacing the one with the same name
+    fn save(&self, graph: &PersistedGraph) -> Result<(), String>;
+
+    /// Load a graph by name
+    fn load(&self, name: &str) -> Result<Option<PersistedGraph>, String>;
+
+    /// Delete a graph by name, returning if it existed
+    fn delete(&self, name: &str) -> Result<bool, String>;
+
+    /// List the description of the persisted graphs
+    fn list(&self) -> Result<Vec<GraphInfo>, String>;
+}
+
+/// A repository keeping the graphs in a sled database
+pub struct SledRepository {
+    database: sled::Db,
+}
+
+impl SledRepository {
+    /// Open the repository in the directory configured in the environment
+    ///
+    /// The directory is taken from `PATHWALKER_GRAPH_DIR`, or `graphs` when
+    /// the variable isn't set, and it is created if it doesn't exist yet.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SledRepository, String>` - The repository, or the reason why it can't be opened
+    ///
+    pub fn from_env() -> Result<SledRepository, String> {
+        let directory = std::env::var(GRAPH_DIR_VARIABLE).map(PathBuf::from).unwrap_or_else(|_| default_graph_dir());
+        SledRepository::open(&directory)
+    }
+
+    /// Open the repository in a directory
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory of the database
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SledRepository, String>` - The repository, or the reason why it can't be opened
+    ///
+    pub fn open(directory: &Path) -> Result<SledRepository, String> {
+        let database = sled::open(directory)
+            .map_err(|err| format!("The graph database {} can't be opened: {}", directory.display(), err))?;
+
+        Ok(SledRepository { database })
+    }
+}
+
+impl GraphRepository for SledRepository {
+    fn save(&self, graph: &PersistedGraph) -> Result<(), String> {
+        let value = bincode::serialize(graph).map_err(|err| err.to_string())?;
+
+        self.database.insert(graph.info.name.as_bytes(), value).map_err(|err| err.to_string())?;
+        self.database.flush().map_err(|err| err.to_string())?;
+
+        Ok(())
+    }
+
+    fn load(&self, name: &str) -> Result<Option<PersistedGraph>, String> {
+        match self.database.get(name.as_bytes()).map_err(|err| err.to_string())? {
+            Some(value) => decode_graph(&value).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn delete(&self, name: &str) -> Result<bool, String> {
+        let removed = self.database.remove(name.as_bytes()).map_err(|err| err.to_string())?.is_some();
+        self.database.flush().map_err(|err| err.to_string())?;
+
+        Ok(removed)
+    }
+
+    fn list(&self) -> Result<Vec<GraphInfo>, String> {
+        self.database.iter()
+            .values()
+            .map(|value| {
+                let value = value.map_err(|err| err.to_string())?;
+                decode_graph(&value).map(|graph| graph.info)
+            })
+            .collect()
+    }
+}
+
+/// A repository keeping the graphs in memory, used when the database can't be opened
+#[derive(Default)]
+pub struct MemoryRepository {
+    graphs: RwLock<HashMap<String, PersistedGraph>>,
+}
+
+impl GraphRepository for MemoryRepository {
+    fn save(&self, graph: &PersistedGraph) -> Result<(), String> {
+        self.graphs.write().unwrap().insert(graph.info.name.clone(), graph.clone());
+        Ok(())
+    }
+
+    fn load(&self, name: &str) -> Result<Option<PersistedGraph>, String> {
+        Ok(self.graphs.read().unwrap().get(name).cloned())
+    }
+
+    fn delete(&self, name: &str) -> Result<bool, String> {
+        Ok(self.graphs.write().unwrap().remove(name).is_some())
+    }
+
+    fn list(&self) -> Result<Vec<GraphInfo>, String> {
+        Ok(self.graphs.read().unwrap().values().map(|graph| graph.info.clone()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sled_repository() {
+        // Prepare the graph
+        let directory = std::env::temp_dir().join(format!("path_walker_test_graphs_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let graph = PersistedGraph {
+            info: GraphInfo { name: "roads".to_string(), vertices: 2, device_bytes: 16, created_at: 1684000000000 },
+            matrix: Matrix::new(2, 2, vec![0.0, 1.0, 2.0, 0.0])
+        };
+        let legacy = bincode::serialize(&(&graph.info, 2usize, 2usize, &graph.matrix.data, graph.matrix.round)).unwrap();
+        let directed = bincode::serialize(&(&graph.info, 2usize, 2usize, &graph.matrix.data, graph.matrix.round, false)).unwrap();
+        let unitless = bincode::serialize(&(&graph.info, 2usize, 2usize, &graph.matrix.data, graph.matrix.round, false, true)).unwrap();
+        let unconstrained = bincode::serialize(&(&graph.info, 2usize, 2usize, &graph.matrix.data, graph.matrix.round, false, true, graph.matrix.unit)).unwrap();
+        let unscheduled = bincode::serialize(&(&graph.info, 2usize, 2usize, &graph.matrix.data, graph.matrix.round, false, true, graph.matrix.unit, Vec::<usize>::new(), Vec::<[usize; 2]>::new(), None::<usize>, Vec::<Vec<f32>>::new(), Vec::<f64>::new())).unwrap();
+        let unordered = bincode::serialize(&(&graph.info, 2usize, 2usize, &graph.matrix.data, graph.matrix.round, false, true, graph.matrix.unit, Vec::<usize>::new(), Vec::<[usize; 2]>::new(), None::<usize>, Vec::<Vec<f32>>::new(), Vec::<f64>::new(), Vec::<Vec<f32>>::new(), None::<f64>, None::<f64>)).unwrap();
+        let unlayered = bincode::serialize(&(&graph.info, 2usize, 2usize, &graph.matrix.data, graph.matrix.round, false, true, graph.matrix.unit, Vec::<usize>::new(), Vec::<[usize; 2]>::new(), None::<usize>)).unwrap();
+
+        // Get the result, reopening the database as a restart would
+        SledRepository::open(&directory).unwrap().save(&graph).unwrap();
+        let repository = SledRepository::open(&directory).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(repository.load("roads").unwrap(), Some(graph.clone()));
+        assert_eq!(decode_graph(&legacy), Ok(graph.clone()));
+        assert_eq!(decode_graph(&directed), Ok(graph.clone()));
+        assert_eq!(decode_graph(&unitless), Ok(graph.clone()));
+        assert_eq!(decode_graph(&unconstrained), Ok(graph.clone()));
+        assert_eq!(decode_graph(&unlayered), Ok(graph.clone()));
+        assert_eq!(decode_graph(&unscheduled), Ok(graph.clone()));
+        assert_eq!(decode_graph(&unordered), Ok(graph.clone()));
+        assert_eq!(repository.list().unwrap(), vec![graph.info]);
+        assert!(repository.delete("roads").unwrap());
+        assert_eq!(repository.load("roads").unwrap(), None);
+    }
+}
@@ -0,0 +1,175 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::info;
+use lru::LruCache;
+
+use crate::services::replan::Replanner;
+
+/// The environment variable with the number of replanning sessions kept
+pub const SESSIONS_CAPACITY_VARIABLE: &str = "PATHWALKER_REPLAN_SESSIONS";
+
+/// The environment variable with the seconds an idle replanning session is kept
+pub const SESSIONS_TTL_VARIABLE: &str = "PATHWALKER_REPLAN_TTL_SECONDS";
+
+/// The number of replanning sessions kept by default
+const DEFAULT_CAPACITY: usize = 256;
+
+/// The seconds an idle replanning session is kept by default
+const DEFAULT_TTL_SECONDS: u64 = 600;
+
+/// A replanning session, with the last time it was used
+struct Session {
+    replanner: Arc<Mutex<Replanner>>,
+    used_at: Instant,
+}
+
+/// The replanning sessions of the robots
+///
+/// Keeps the planners of the most recently used sessions, expiring the ones
+/// that haven't been used for a while, so the robots that go away don't
+/// hold memory forever. The ids of the sessions are read from the
+/// randomness of the operating system, as holding an id is enough to change
+/// or close its session.
+///
+/// # Fields
+///
+/// * `sessions` - The planners, by the id of their session
+/// * `ttl` - How long an idle session is kept
+///
+pub struct ReplanSessions {
+    sessions: Mutex<LruCache<String, Session>>,
+    ttl: Duration,
+}
+
+impl ReplanSessions {
+    /// Create the sessions with the capacity and TTL configured in the environment
+    ///
+    /// # Returns
+    ///
+    /// * `ReplanSessions` - The sessions, with 256 sessions kept for 600 idle seconds by default
+    ///
+    pub fn new() -> ReplanSessions {
+        let capacity = std::env::var(SESSIONS_CAPACITY_VARIABLE).ok()
+            .and_then(|capacity| capacity.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+        let ttl = std::env::var(SESSIONS_TTL_VARIABLE).ok()
+            .and_then(|ttl| ttl.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECONDS);
+
+        info!("Keeping up to {} replanning sessions for {} idle seconds", capacity, ttl);
+
+        ReplanSessions::with_capacity(capacity, Duration::from_secs(ttl))
+    }
+
+    /// Create the sessions with a capacity and TTL
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The number of sessions kept, at least one
+    /// * `ttl` - How long an idle session is kept
+    ///
+    /// # Returns
+    ///
+    /// * `ReplanSessions` - The sessions
+    ///
+    pub fn with_capacity(capacity: usize, ttl: Duration) -> ReplanSessions {
+        ReplanSessions {
+            sessions: Mutex::new(LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN))),
+            ttl
+        }
+    }
+
+    /// Start a session, evicting the least recently used one when full
+    ///
+    /// # Arguments
+    ///
+    /// * `replanner` - The planner of the session
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(String, Arc<Mutex<Replanner>>), String>` - The id of the session and its planner, or why no id could be drawn
+    ///
+    pub fn create(&self, replanner: Replanner) -> Result<(String, Arc<Mutex<Replanner>>), String> {
+        let mut bytes = [0u8; 16];
+        getrandom::fill(&mut bytes).map_err(|err| format!("The id of the session can't be drawn: {}", err))?;
+
+        let id = hex::encode(bytes);
+        let replanner = Arc::new(Mutex::new(replanner));
+
+        self.sessions.lock().unwrap().put(id.clone(), Session { replanner: replanner.clone(), used_at: Instant::now() });
+        Ok((id, replanner))
+    }
+
+    /// Get the planner of a session
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the session
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Arc<Mutex<Replanner>>>` - The planner, if the session exists and hasn't expired
+    ///
+    pub fn get(&self, id: &str) -> Option<Arc<Mutex<Replanner>>> {
+        let mut sessions = self.sessions.lock().unwrap();
+
+        match sessions.get_mut(id) {
+            Some(session) if session.used_at.elapsed() >= self.ttl => {
+                sessions.pop(id);
+                None
+            },
+            Some(session) => {
+                session.used_at = Instant::now();
+                Some(session.replanner.clone())
+            },
+            None => None
+        }
+    }
+
+    /// End a session
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the session
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If the session existed
+    ///
+    pub fn remove(&self, id: &str) -> bool {
+        self.sessions.lock().unwrap().pop(id).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{GridRequest, GridTopology, ReplanRequest};
+    use super::*;
+
+    #[test]
+    fn test_replan_sessions() {
+        // Prepare the planners of three robots in sessions that keep two
+        let planner = || Replanner::new(ReplanRequest::Grid(GridRequest {
+            topology: GridTopology::Square, width: 2, height: 1, costs: vec![1.0, 1.0], start: [0, 0], goal: [1, 0], weight: None, round: None, postprocess: vec![]
+        })).unwrap();
+        let sessions = ReplanSessions::with_capacity(2, Duration::from_secs(60));
+        let expiring = ReplanSessions::with_capacity(2, Duration::ZERO);
+
+        // Get the result
+        let (first, _) = sessions.create(planner()).unwrap();
+        let (second, _) = sessions.create(planner()).unwrap();
+        let (third, _) = sessions.create(planner()).unwrap();
+        let (expired, _) = expiring.create(planner()).unwrap();
+
+        // Check if the result is correct
+        assert_ne!(second, third);
+        assert_eq!(first.len(), 32);
+        assert!(sessions.get(&first).is_none());
+        assert!(sessions.get(&second).is_some());
+        assert!(sessions.remove(&third));
+        assert!(!sessions.remove(&third));
+        assert!(expiring.get(&expired).is_none());
+    }
+}
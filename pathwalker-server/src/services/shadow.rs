@@ -0,0 +1,268 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::{error, info, warn};
+use serde::Serialize;
+
+use crate::models::{Matrix, PathResult, Result};
+
+/// The environment variable with the URL of the shadow backend
+pub const SHADOW_URL_VARIABLE: &str = "PATHWALKER_SHADOW_URL";
+
+/// The environment variable with the percentage of requests mirrored to the shadow backend
+pub const SHADOW_PERCENT_VARIABLE: &str = "PATHWALKER_SHADOW_PERCENT";
+
+/// How long to wait for the shadow backend
+const SHADOW_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The difference under which two distances are considered the same
+const DISTANCE_TOLERANCE: f32 = 1e-4;
+
+/// The differences between the path of the primary and the shadow backends
+///
+/// Predecessors are counted apart from distances, because paths with the
+/// same cost can be broken differently by each backend.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Divergence {
+    pub distances: usize,
+    pub predecessors: usize,
+    pub max_distance_delta: f32,
+}
+
+/// The metrics of the requests mirrored to the shadow backend
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShadowStats {
+    pub url: Option<String>,
+    pub percent: u32,
+    pub mirrored: u64,
+    pub matched: u64,
+    pub diverged: u64,
+    pub failed: u64,
+    pub predecessor_mismatches: u64,
+    pub max_distance_delta: f32,
+}
+
+/// The shadow mirror
+///
+/// Mirrors a percentage of the requests to a secondary backend, like a new
+/// version of the kernels or a reference implementation, and compares its
+/// results with the ones already returned to the client.
+///
+/// # Fields
+///
+/// * `url` - The base URL of the shadow backend
+/// * `percent` - The percentage of requests to mirror
+/// * `requests` - The number of requests seen
+/// * `stats` - The metrics of the mirrored requests
+///
+pub struct ShadowMirror {
+    url: Option<String>,
+    percent: u32,
+    requests: AtomicU64,
+    stats: Mutex<ShadowStats>,
+}
+
+impl ShadowMirror {
+    /// Create a mirror with the backend configured in the environment
+    ///
+    /// # Returns
+    ///
+    /// * `ShadowMirror` - The mirror, disabled when no backend is configured
+    ///
+    pub fn new() -> ShadowMirror {
+        let url = std::env::var(SHADOW_URL_VARIABLE).ok().filter(|url| !url.is_empty());
+        let percent = std::env::var(SHADOW_PERCENT_VARIABLE).ok()
+            .and_then(|percent| percent.parse::<u32>().ok())
+            .unwrap_or(100);
+
+        if let Some(url) = &url {
+            info!("Mirroring {}% of the requests to {}", percent.min(100), url);
+        }
+
+        ShadowMirror::with_backend(url, percent)
+    }
+
+    /// Create a mirror with a backend
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The base URL of the shadow backend, if any
+    /// * `percent` - The percentage of requests to mirror
+    ///
+    /// # Returns
+    ///
+    /// * `ShadowMirror` - The mirror
+    ///
+    pub fn with_backend(url: Option<String>, percent: u32) -> ShadowMirror {
+        let percent = percent.min(100);
+        let stats = ShadowStats {
+            url: url.clone(),
+            percent,
+            mirrored: 0,
+            matched: 0,
+            diverged: 0,
+            failed: 0,
+            predecessor_mismatches: 0,
+            max_distance_delta: 0.0
+        };
+
+        ShadowMirror { url, percent, requests: AtomicU64::new(0), stats: Mutex::new(stats) }
+    }
+
+    /// Decide if a request is mirrored
+    ///
+    /// The mirrored requests are spread evenly, so exactly `percent` out of
+    /// every hundred requests are mirrored.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If the request should be mirrored
+    ///
+    pub fn should_mirror(&self) -> bool {
+        if self.url.is_none() || self.percent == 0 {
+            return false;
+        }
+
+        let request = self.requests.fetch_add(1, Ordering::Relaxed);
+        (request * self.percent as u64) / 100 != ((request + 1) * self.percent as u64) / 100
+    }
+
+    /// Mirror a request to the shadow backend and compare its result
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix` - The matrix of the request
+    /// * `primary` - The path returned by this backend
+    ///
+    pub async fn mirror(&self, matrix: Matrix, primary: Vec<PathResult>) {
+        let url = match &self.url {
+            Some(url) => format!("{}/sortest", url.trim_end_matches('/')),
+            None => return
+        };
+
+        // Ask the shadow backend for the unrounded path
+        let matrix = Matrix { round: None, ..matrix };
+        let client = awc::Client::builder().timeout(SHADOW_TIMEOUT).finish();
+
+        let shadow = match client.post(&url).send_json(&matrix).await {
+            Ok(mut response) => response.json::<Result>().await
+                .map_err(|err| err.to_string())
+                .and_then(|result| result.path.ok_or(result.message.unwrap_or(result.status))),
+            Err(err) => Err(err.to_string())
+        };
+
+        self.record(&primary, shadow);
+    }
+
+    /// Record the result of a mirrored request
+    ///
+    /// # Arguments
+    ///
+    /// * `primary` - The path returned by this backend
+    /// * `shadow` - The path returned by the shadow backend, or the reason why it failed
+    ///
+    pub fn record(&self, primary: &[PathResult], shadow: std::result::Result<Vec<PathResult>, String>) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.mirrored += 1;
+
+        let shadow = match shadow {
+            Ok(shadow) => shadow,
+            Err(err) => {
+                error!("The shadow backend has failed: {}", err);
+                stats.failed += 1;
+                return;
+            }
+        };
+
+        // Compare the paths of both backends
+        let divergence = compare_paths(primary, &shadow);
+
+        if divergence.distances > 0 {
+            warn!("The shadow backend has diverged in {} distances: {:?}", divergence.distances, divergence);
+            stats.diverged += 1;
+        } else {
+            stats.matched += 1;
+        }
+
+        if divergence.predecessors > 0 {
+            stats.predecessor_mismatches += 1;
+        }
+
+        stats.max_distance_delta = stats.max_distance_delta.max(divergence.max_distance_delta);
+    }
+
+    /// Get the metrics of the mirrored requests
+    pub fn stats(&self) -> ShadowStats {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+/// Compare the paths of the primary and the shadow backends
+///
+/// # Arguments
+///
+/// * `primary` - The path returned by this backend
+/// * `shadow` - The path returned by the shadow backend
+///
+/// # Returns
+///
+/// * `Divergence` - The differences between both paths, where missing vertices count as different
+///
+pub fn compare_paths(primary: &[PathResult], shadow: &[PathResult]) -> Divergence {
+    let missing = primary.len().abs_diff(shadow.len());
+    let mut divergence = Divergence { distances: missing, predecessors: missing, max_distance_delta: 0.0 };
+
+    for (primary, shadow) in primary.iter().zip(shadow) {
+        let delta = (primary.1 - shadow.1).abs();
+
+        if delta > DISTANCE_TOLERANCE {
+            divergence.distances += 1;
+        }
+
+        if primary.0 != shadow.0 {
+            divergence.predecessors += 1;
+        }
+
+        divergence.max_distance_delta = divergence.max_distance_delta.max(delta);
+    }
+
+    divergence
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadow_sampling() {
+        // Prepare the mirror
+        let mirror = ShadowMirror::with_backend(Some("http://canary:8080".to_string()), 25);
+
+        // Get the result
+        let mirrored = (0..100).filter(|_| mirror.should_mirror()).count();
+
+        // Check if the result is correct
+        assert_eq!(mirrored, 25);
+        assert!(!ShadowMirror::with_backend(None, 100).should_mirror());
+    }
+
+    #[test]
+    fn test_shadow_divergence() {
+        // Prepare the paths
+        let primary = vec![PathResult(0, 0.0), PathResult(2, 3.0), PathResult(0, 2.0)];
+        let shadow = vec![PathResult(0, 0.0), PathResult(0, 3.0), PathResult(0, 2.5)];
+
+        // Get the result
+        let mirror = ShadowMirror::with_backend(Some("http://canary:8080".to_string()), 100);
+        mirror.record(&primary, Ok(shadow.clone()));
+        mirror.record(&primary, Err("timeout".to_string()));
+
+        // Check if the result is correct
+        assert_eq!(compare_paths(&primary, &shadow), Divergence { distances: 1, predecessors: 1, max_distance_delta: 0.5 });
+        assert_eq!(mirror.stats().mirrored, 2);
+        assert_eq!(mirror.stats().diverged, 1);
+        assert_eq!(mirror.stats().failed, 1);
+        assert_eq!(mirror.stats().max_distance_delta, 0.5);
+    }
+}
@@ -0,0 +1,329 @@
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::services::generator::SplitMix64;
+use crate::services::metrics::device_memory;
+
+/// The samples of memory kept by a soak test, dropping every other one when full
+const MAX_SAMPLES: usize = 1_000;
+
+/// The options of a soak test
+///
+/// # Fields
+///
+/// * `duration` - The seconds the soak test runs for, until stopped when not given
+/// * `vertices` - The maximum number of vertices of the random graphs
+/// * `seed` - The seed of the random workloads
+/// * `sample_interval` - The seconds between samples of the memory
+/// * `window` - The consecutive samples that must grow to suspect a leak
+/// * `min_growth` - The bytes the memory must grow over the window to suspect a leak
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SoakOptions {
+    pub duration: Option<u64>,
+    #[serde(default = "default_vertices")]
+    pub vertices: usize,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default = "default_sample_interval")]
+    pub sample_interval: u64,
+    #[serde(default = "default_window")]
+    pub window: usize,
+    #[serde(default = "default_min_growth")]
+    pub min_growth: u64,
+}
+
+/// The maximum vertices of the random graphs by default
+fn default_vertices() -> usize {
+    64
+}
+
+/// The seconds between samples by default
+fn default_sample_interval() -> u64 {
+    10
+}
+
+/// The samples of the leak window by default
+fn default_window() -> usize {
+    30
+}
+
+/// The growth of a leak by default, 1 MiB
+fn default_min_growth() -> u64 {
+    1024 * 1024
+}
+
+impl Default for SoakOptions {
+    fn default() -> Self {
+        SoakOptions {
+            duration: None,
+            vertices: default_vertices(),
+            seed: 0,
+            sample_interval: default_sample_interval(),
+            window: default_window(),
+            min_growth: default_min_growth()
+        }
+    }
+}
+
+impl SoakOptions {
+    /// Validate the options
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` - The reason why the options aren't valid, if any
+    ///
+    pub fn validate(&self) -> Option<&'static str> {
+        if !(2..=128).contains(&self.vertices) {
+            Some("The vertices must be between 2 and 128")
+        } else if self.sample_interval == 0 {
+            Some("The sample interval must be greater than zero")
+        } else if self.window < 2 {
+            Some("The window must have at least two samples")
+        } else {
+            None
+        }
+    }
+}
+
+/// The status of a soak test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SoakStatus {
+    Idle,
+    Running,
+    Passed,
+    Stopped,
+    Failed,
+}
+
+/// A sample of the memory used by the process
+///
+/// # Fields
+///
+/// * `elapsed` - The seconds since the soak test started
+/// * `rss_bytes` - The resident memory of the process, if it can be read
+/// * `device_bytes` - The device memory used by the buffers
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MemorySample {
+    pub elapsed: u64,
+    pub rss_bytes: Option<u64>,
+    pub device_bytes: i64,
+}
+
+/// The report of a soak test
+///
+/// # Fields
+///
+/// * `status` - The status of the soak test
+/// * `options` - The options of the soak test, if it has ever run
+/// * `iterations` - The workloads run
+/// * `errors` - The workloads that have failed
+/// * `message` - Why the soak test has failed, if it has
+/// * `samples` - The samples of the memory
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SoakReport {
+    pub status: SoakStatus,
+    pub options: Option<SoakOptions>,
+    pub iterations: u64,
+    pub errors: u64,
+    pub message: Option<String>,
+    pub samples: Vec<MemorySample>,
+}
+
+/// Check if some samples show a leak
+///
+/// A leak is suspected when the memory has never gone down over the last
+/// `window` samples and has grown at least `min_growth` bytes over them,
+/// as the memory of a healthy process goes up and down with its workload.
+///
+/// # Arguments
+///
+/// * `samples` - The samples of the memory, from the oldest
+/// * `window` - The consecutive samples that must grow
+/// * `min_growth` - The bytes the memory must grow over the window
+///
+/// # Returns
+///
+/// * `Option<String>` - The description of the leak, if any
+///
+pub fn detect_leak(samples: &[MemorySample], window: usize, min_growth: u64) -> Option<String> {
+    let recent = samples.get(samples.len().checked_sub(window)?..)?;
+    let grows = |values: Vec<i64>| values.windows(2).all(|pair| pair[0] <= pair[1]) && values[values.len() - 1] - values[0] >= min_growth as i64;
+
+    let rss: Option<Vec<i64>> = recent.iter().map(|sample| sample.rss_bytes.map(|bytes| bytes as i64)).collect();
+    let device: Vec<i64> = recent.iter().map(|sample| sample.device_bytes).collect();
+    let (first, last) = (recent[0], recent[recent.len() - 1]);
+
+    if rss.is_some_and(grows) {
+        Some(format!("The resident memory has grown from {} to {} bytes over the last {} samples", first.rss_bytes.unwrap_or(0), last.rss_bytes.unwrap_or(0), window))
+    } else if grows(device) {
+        Some(format!("The device memory has grown from {} to {} bytes over the last {} samples", first.device_bytes, last.device_bytes, window))
+    } else {
+        None
+    }
+}
+
+/// Read the resident memory of the process
+fn read_rss() -> Option<u64> {
+    fs::read_to_string("/proc/self/status").ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .map(|kilobytes| kilobytes * 1024)
+}
+
+/// The soak test of the local backend
+///
+/// Runs randomized workloads in a dedicated thread while sampling the
+/// resident and the device memory, and fails loudly when any of them
+/// grows monotonically, which points to buffers leaked by some path.
+///
+/// # Fields
+///
+/// * `report` - The report of the current or the last soak test
+/// * `stop` - Asks the running soak test to stop
+///
+pub struct SoakTest {
+    report: Arc<Mutex<SoakReport>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl SoakTest {
+    /// Create an idle soak test
+    pub fn new() -> SoakTest {
+        SoakTest {
+            report: Arc::new(Mutex::new(SoakReport { status: SoakStatus::Idle, options: None, iterations: 0, errors: 0, message: None, samples: vec![] })),
+            stop: Arc::new(AtomicBool::new(false))
+        }
+    }
+
+    /// Get the report of the current or the last soak test
+    pub fn report(&self) -> SoakReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Ask the running soak test to stop
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If a soak test was running
+    ///
+    pub fn stop(&self) -> bool {
+        let running = self.report.lock().unwrap().status == SoakStatus::Running;
+        self.stop.store(running, Ordering::SeqCst);
+        running
+    }
+
+    /// Start a soak test, unless one is running
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options of the soak test
+    /// * `workload` - Runs a random workload, returning the reason why it has failed, if it has
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If the soak test has been started
+    ///
+    pub fn start(&self, options: SoakOptions, mut workload: impl FnMut(&mut SplitMix64, usize) -> Result<(), String> + Send + 'static) -> bool {
+        {
+            let mut report = self.report.lock().unwrap();
+
+            if report.status == SoakStatus::Running {
+                return false;
+            }
+
+            *report = SoakReport { status: SoakStatus::Running, options: Some(options.clone()), iterations: 0, errors: 0, message: None, samples: vec![] };
+            self.stop.store(false, Ordering::SeqCst);
+        }
+
+        info!("Starting soak test with {:?}", options);
+
+        let (report, stop) = (self.report.clone(), self.stop.clone());
+
+        thread::spawn(move || {
+            let mut random = SplitMix64(options.seed);
+            let started_at = Instant::now();
+            let mut next_sample = started_at;
+
+            let status = loop {
+                // Sample the memory every interval and look for a leak
+                if Instant::now() >= next_sample {
+                    next_sample += Duration::from_secs(options.sample_interval);
+
+                    let mut report = report.lock().unwrap();
+                    let sample = MemorySample { elapsed: started_at.elapsed().as_secs(), rss_bytes: read_rss(), device_bytes: device_memory() };
+
+                    if report.samples.len() >= MAX_SAMPLES {
+                        let mut index = 0;
+                        report.samples.retain(|_| { index += 1; index % 2 == 0 });
+                    }
+
+                    report.samples.push(sample);
+
+                    if let Some(message) = detect_leak(&report.samples, options.window, options.min_growth) {
+                        error!("The soak test has found a memory leak: {}", message);
+                        report.message = Some(message);
+                        break SoakStatus::Failed;
+                    }
+                }
+
+                if stop.load(Ordering::SeqCst) {
+                    break SoakStatus::Stopped;
+                }
+
+                if options.duration.is_some_and(|duration| started_at.elapsed() >= Duration::from_secs(duration)) {
+                    break SoakStatus::Passed;
+                }
+
+                // Run the next workload
+                let vertices = 2 + (random.next_u64() % (options.vertices as u64 - 1)) as usize;
+                let result = workload(&mut random, vertices);
+                let mut report = report.lock().unwrap();
+
+                report.iterations += 1;
+                report.errors += u64::from(result.is_err());
+            };
+
+            let mut report = report.lock().unwrap();
+            report.status = status;
+            info!("The soak test has finished as {:?} after {} iterations", status, report.iterations);
+        });
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_leak() {
+        // Prepare the samples of a healthy and a leaking process
+        let sample = |elapsed: u64, rss: u64, device: i64| MemorySample { elapsed, rss_bytes: Some(rss), device_bytes: device };
+        let healthy: Vec<MemorySample> = (0..10).map(|index| sample(index, 100_000 + (index % 3) * 5_000, 4_096)).collect();
+        let leaking: Vec<MemorySample> = (0..10).map(|index| sample(index, 100_000, 4_096 * index as i64)).collect();
+
+        // Get the result
+        let healthy_leak = detect_leak(&healthy, 5, 1_000);
+        let device_leak = detect_leak(&leaking, 5, 1_000);
+        let short = detect_leak(&leaking[..3], 5, 1_000);
+
+        // Check if the result is correct
+        assert_eq!(healthy_leak, None);
+        assert_eq!(device_leak, Some("The device memory has grown from 20480 to 36864 bytes over the last 5 samples".to_string()));
+        assert_eq!(short, None);
+        assert_eq!(SoakOptions::default().validate(), None);
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::info;
+use serde::Serialize;
+
+/// The environment variable with the seconds between the pings sent to the websocket clients
+pub const HEARTBEAT_VARIABLE: &str = "PATHWALKER_WS_HEARTBEAT_SECONDS";
+
+/// The environment variable with the seconds a websocket client can stay silent before its session is closed
+pub const IDLE_TIMEOUT_VARIABLE: &str = "PATHWALKER_WS_IDLE_TIMEOUT_SECONDS";
+
+/// The seconds between the pings sent to the websocket clients by default
+const DEFAULT_HEARTBEAT_SECONDS: u64 = 15;
+
+/// The seconds a websocket client can stay silent by default
+const DEFAULT_IDLE_TIMEOUT_SECONDS: u64 = 60;
+
+/// What a websocket session is doing
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SocketState {
+    /// Waiting for a matrix
+    Idle,
+    /// Walking a matrix in the device
+    Walking,
+}
+
+/// A websocket session, with the last time its client was heard from
+struct Socket {
+    connected_at: Instant,
+    seen_at: Instant,
+    state: SocketState,
+    walks: usize,
+    device_bytes: usize,
+}
+
+/// The state of a websocket session, as reported to the administrators
+///
+/// # Fields
+///
+/// * `id` - The id of the session
+/// * `state` - What the session is doing
+/// * `connected_seconds` - The seconds since the client connected
+/// * `idle_seconds` - The seconds since the client was last heard from
+/// * `walks` - The matrices walked by the session
+/// * `device_bytes` - The device memory held by the walk of the session
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SocketReport {
+    pub id: u64,
+    pub state: SocketState,
+    pub connected_seconds: u64,
+    pub idle_seconds: u64,
+    pub walks: usize,
+    pub device_bytes: usize,
+}
+
+/// The live websocket sessions
+///
+/// Keeps when every client was last heard from, so the sessions whose
+/// clients have silently gone away can be closed, aborting their walks and
+/// releasing their device memory, instead of holding the device forever.
+///
+/// # Fields
+///
+/// * `sockets` - The sessions, by their id
+/// * `next_id` - The id of the next session
+/// * `heartbeat` - The time between the pings sent to the clients
+/// * `idle_timeout` - How long a client can stay silent
+///
+pub struct SocketSessions {
+    sockets: Mutex<BTreeMap<u64, Socket>>,
+    next_id: AtomicU64,
+    heartbeat: Duration,
+    idle_timeout: Duration,
+}
+
+impl SocketSessions {
+    /// Create the sessions with the heartbeat and the idle timeout configured in the environment
+    ///
+    /// # Returns
+    ///
+    /// * `SocketSessions` - The sessions, pinged every 15 seconds and closed after 60 silent seconds by default
+    ///
+    pub fn new() -> SocketSessions {
+        let seconds = |variable: &str, default: u64| std::env::var(variable).ok()
+            .and_then(|seconds| seconds.parse::<u64>().ok())
+            .filter(|seconds| *seconds > 0)
+            .unwrap_or(default);
+        let heartbeat = seconds(HEARTBEAT_VARIABLE, DEFAULT_HEARTBEAT_SECONDS);
+        let idle_timeout = seconds(IDLE_TIMEOUT_VARIABLE, DEFAULT_IDLE_TIMEOUT_SECONDS);
+
+        info!("Pinging the websocket clients every {} seconds, closing them after {} silent seconds", heartbeat, idle_timeout);
+
+        SocketSessions::with_timeouts(Duration::from_secs(heartbeat), Duration::from_secs(idle_timeout))
+    }
+
+    /// Create the sessions with a heartbeat and an idle timeout
+    ///
+    /// # Arguments
+    ///
+    /// * `heartbeat` - The time between the pings sent to the clients
+    /// * `idle_timeout` - How long a client can stay silent
+    ///
+    /// # Returns
+    ///
+    /// * `SocketSessions` - The sessions
+    ///
+    pub fn with_timeouts(heartbeat: Duration, idle_timeout: Duration) -> SocketSessions {
+        SocketSessions { sockets: Mutex::new(BTreeMap::new()), next_id: AtomicU64::new(1), heartbeat, idle_timeout }
+    }
+
+    /// Get the time between the pings sent to the clients
+    pub fn heartbeat(&self) -> Duration {
+        self.heartbeat
+    }
+
+    /// Get how long a client can stay silent before its session is closed
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Start tracking the session of a client that has just connected
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The id of the session
+    ///
+    pub fn open(&self) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+
+        self.sockets.lock().unwrap().insert(id, Socket { connected_at: now, seen_at: now, state: SocketState::Idle, walks: 0, device_bytes: 0 });
+        id
+    }
+
+    /// Record that the client of a session has been heard from
+    pub fn seen(&self, id: u64) {
+        if let Some(socket) = self.sockets.lock().unwrap().get_mut(&id) {
+            socket.seen_at = Instant::now();
+        }
+    }
+
+    /// Record that a session has started walking a matrix
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the session
+    /// * `device_bytes` - The device memory held by the walk
+    ///
+    pub fn start_walk(&self, id: u64, device_bytes: usize) {
+        if let Some(socket) = self.sockets.lock().unwrap().get_mut(&id) {
+            socket.state = SocketState::Walking;
+            socket.walks += 1;
+            socket.device_bytes = device_bytes;
+        }
+    }
+
+    /// Record that the walk of a session has finished, releasing its device memory
+    pub fn finish_walk(&self, id: u64) {
+        if let Some(socket) = self.sockets.lock().unwrap().get_mut(&id) {
+            socket.state = SocketState::Idle;
+            socket.device_bytes = 0;
+        }
+    }
+
+    /// Check if the client of a session has been silent for longer than the idle timeout
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the session
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If the session should be closed, which it also should when it isn't tracked
+    ///
+    pub fn is_dead(&self, id: u64) -> bool {
+        self.sockets.lock().unwrap().get(&id).is_none_or(|socket| socket.seen_at.elapsed() >= self.idle_timeout)
+    }
+
+    /// Stop tracking a session
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the session
+    ///
+    /// # Returns
+    ///
+    /// * `Option<SocketReport>` - The last state of the session, if it was tracked
+    ///
+    pub fn close(&self, id: u64) -> Option<SocketReport> {
+        self.sockets.lock().unwrap().remove(&id).map(|socket| report(id, &socket))
+    }
+
+    /// Get the state of every live session
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<SocketReport>` - The sessions, from the oldest
+    ///
+    pub fn reports(&self) -> Vec<SocketReport> {
+        self.sockets.lock().unwrap().iter().map(|(id, socket)| report(*id, socket)).collect()
+    }
+}
+
+impl Default for SocketSessions {
+    fn default() -> SocketSessions {
+        SocketSessions::new()
+    }
+}
+
+/// Describe the state of a session
+fn report(id: u64, socket: &Socket) -> SocketReport {
+    SocketReport {
+        id,
+        state: socket.state,
+        connected_seconds: socket.connected_at.elapsed().as_secs(),
+        idle_seconds: socket.seen_at.elapsed().as_secs(),
+        walks: socket.walks,
+        device_bytes: socket.device_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_sessions() {
+        // Prepare the sessions of two clients, and the ones of a server that closes them right away
+        let sockets = SocketSessions::with_timeouts(Duration::from_secs(15), Duration::from_secs(60));
+        let impatient = SocketSessions::with_timeouts(Duration::from_secs(15), Duration::ZERO);
+        let (walking, idle) = (sockets.open(), sockets.open());
+        let silent = impatient.open();
+
+        // Get the result
+        sockets.start_walk(walking, 4096);
+        let reports = sockets.reports();
+        sockets.finish_walk(walking);
+        let closed = sockets.close(walking).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(reports.iter().map(|report| (report.id, report.state, report.device_bytes)).collect::<Vec<_>>(), vec![
+            (walking, SocketState::Walking, 4096),
+            (idle, SocketState::Idle, 0)
+        ]);
+        assert_eq!((closed.walks, closed.device_bytes), (1, 0));
+        assert!(!sockets.is_dead(idle));
+        assert!(sockets.is_dead(walking));
+        assert!(impatient.is_dead(silent));
+    }
+}
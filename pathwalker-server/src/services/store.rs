@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::Serialize;
+
+use crate::models::{EdgeChange, GraphInfo, Matrix, PathResult, WeightUnit};
+use crate::services::opencl;
+use crate::services::repository::{GraphRepository, MemoryRepository, PersistedGraph, SledRepository};
+use crate::services::run_id::matrix_digest;
+use crate::services::sortest_path::{is_path_affected, DeviceGraph};
+
+/// A graph resident in the device
+///
+/// # Fields
+///
+/// * `info` - The description of the graph
+/// * `device` - The matrix resident in the device
+/// * `digest` - The digest of the matrix, naming the input of the queries
+/// * `unit` - The unit of the weights of the matrix, if declared
+/// * `paths` - The walks of the graph by source, at most one per vertex
+///
+pub struct StoredGraph {
+    pub info: GraphInfo,
+    pub device: Arc<DeviceGraph>,
+    pub digest: [u8; 32],
+    pub unit: Option<WeightUnit>,
+    paths: Mutex<HashMap<usize, Vec<PathResult>>>,
+}
+
+impl StoredGraph {
+    /// Create a graph resident in the device, without walks yet
+    fn new(info: GraphInfo, device: Arc<DeviceGraph>, matrix: &Matrix) -> StoredGraph {
+        StoredGraph { info, device, digest: matrix_digest(matrix), unit: matrix.unit, paths: Mutex::new(HashMap::new()) }
+    }
+
+    /// Get the walk of the graph from a source, if it has been kept
+    pub fn path(&self, source: usize) -> Option<Vec<PathResult>> {
+        self.paths.lock().unwrap().get(&source).cloned()
+    }
+
+    /// Keep the walk of the graph from a source, until a change of its edges affects it
+    pub fn keep_path(&self, source: usize, path: Vec<PathResult>) {
+        self.paths.lock().unwrap().insert(source, path);
+    }
+}
+
+/// The result of changing the weights of some edges of a graph
+///
+/// # Fields
+///
+/// * `graph` - The description of the graph
+/// * `cells` - The cells of the matrix that have changed
+/// * `resident` - If the changed cells have been written to the graph resident in the device
+/// * `kept_paths` - The walks of the graph kept, as the change doesn't affect them
+/// * `invalidated_paths` - The walks of the graph dropped, as the change affects them
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EdgesUpdate {
+    pub graph: GraphInfo,
+    pub cells: usize,
+    pub resident: bool,
+    pub kept_paths: usize,
+    pub invalidated_paths: usize,
+}
+
+/// The registry of named graphs
+///
+/// Graphs are persisted in the repository, so they survive a restart, and
+/// kept resident in the device once uploaded, so they can be queried many
+/// times without transferring the matrix on every request. After a restart
+/// the graphs are uploaded again to the device on their first query.
+///
+pub struct GraphStore {
+    repository: Box<dyn GraphRepository>,
+    graphs: RwLock<HashMap<String, Arc<StoredGraph>>>,
+}
+
+impl GraphStore {
+    /// Create a store persisting the graphs in the directory configured in the environment
+    ///
+    /// # Returns
+    ///
+    /// * `GraphStore` - The store, keeping the graphs in memory when the database can't be opened
+    ///
+    pub fn new() -> GraphStore {
+        match SledRepository::from_env() {
+            Ok(repository) => GraphStore::with_repository(Box::new(repository)),
+            Err(err) => {
+                error!("{}, the graphs won't outlive the server", err);
+                GraphStore::with_repository(Box::new(MemoryRepository::default()))
+            }
+        }
+    }
+
+    /// Create a store persisting the graphs in a repository
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of the graphs
+    ///
+    /// # Returns
+    ///
+    /// * `GraphStore` - The store
+    ///
+    pub fn with_repository(repository: Box<dyn GraphRepository>) -> GraphStore {
+        GraphStore { repository, graphs: RwLock::new(HashMap::new()) }
+    }
+
+    /// Store a graph, replacing the one with the same name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the graph
+    /// * `matrix` - The matrix of the graph
+    /// * `device` - The matrix resident in the device
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(GraphInfo, bool), String>` - The description of the graph and if it replaced another one,
+    ///   or the reason why it can't be persisted
+    ///
+    pub fn insert(&self, name: &str, matrix: &Matrix, device: DeviceGraph) -> Result<(GraphInfo, bool), String> {
+        let info = GraphInfo {
+            name: name.to_string(),
+            vertices: matrix.width,
+            device_bytes: device.size_in_bytes(),
+            created_at: Utc::now().timestamp_millis()
+        };
+
+        // Print the upload of the graph
+        info!("Storing graph {} with {} vertices", name, info.vertices);
+
+        // Persist the graph before making it available
+        let replaced = self.repository.load(name)?.is_some();
+        self.repository.save(&PersistedGraph { info: info.clone(), matrix: matrix.clone() })?;
+
+        let graph = Arc::new(StoredGraph::new(info.clone(), Arc::new(device), matrix));
+        self.graphs.write().unwrap().insert(name.to_string(), graph);
+
+        Ok((info, replaced))
+    }
+
+    /// Get the description of a graph by name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the graph
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<GraphInfo>, String>` - The description of the graph, if it exists
+    ///
+    pub fn info(&self, name: &str) -> Result<Option<GraphInfo>, String> {
+        match self.graphs.read().unwrap().get(name) {
+            Some(graph) => Ok(Some(graph.info.clone())),
+            None => Ok(self.repository.load(name)?.map(|graph| graph.info))
+        }
+    }
+
+    /// Get the unit of the weights of a graph by name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the graph
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Option<WeightUnit>>, String>` - The unit of the weights, if declared, when the graph exists
+    ///
+    pub fn unit(&self, name: &str) -> Result<Option<Option<WeightUnit>>, String> {
+        match self.graphs.read().unwrap().get(name) {
+            Some(graph) => Ok(Some(graph.unit)),
+            None => Ok(self.repository.load(name)?.map(|graph| graph.matrix.unit))
+        }
+    }
+
+    /// Get the matrix of a graph by name, as it's persisted
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the graph
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Matrix>, String>` - The matrix of the graph, if it exists
+    ///
+    pub fn matrix(&self, name: &str) -> Result<Option<Matrix>, String> {
+        Ok(self.repository.load(name)?.map(|graph| graph.matrix))
+    }
+
+    /// Get a graph resident in the device by name
+    ///
+    /// When the graph is persisted but it isn't in the device yet, as happens
+    /// after a restart, the matrix is uploaded again with `upload`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the graph
+    /// * `upload` - How to upload the matrix to the device
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Arc<StoredGraph>>, String>` - The graph, if it exists
+    ///
+    pub fn get(&self, name: &str, upload: impl FnOnce(&Matrix) -> opencl::Result<DeviceGraph>) -> Result<Option<Arc<StoredGraph>>, String> {
+        if let Some(graph) = self.graphs.read().unwrap().get(name) {
+            return Ok(Some(graph.clone()));
+        }
+
+        // Upload the persisted graph to the device
+        let persisted = match self.repository.load(name)? {
+            Some(persisted) => persisted,
+            None => return Ok(None)
+        };
+
+        info!("Uploading persisted graph {} to the device", name);
+
+        let device = upload(&persisted.matrix).map_err(|err| err.to_string())?;
+        let graph = Arc::new(StoredGraph::new(persisted.info, Arc::new(device), &persisted.matrix));
+
+        Ok(Some(self.graphs.write().unwrap().entry(name.to_string()).or_insert(graph).clone()))
+    }
+
+    /// Change the weights of some edges of a graph
+    ///
+    /// The matrix is persisted with the new weights, and when the graph is
+    /// resident in the device `write` copies its matrix in the device with
+    /// only the cells that have changed written, instead of uploading the
+    /// whole matrix again. The copy replaces the matrix of the graph, so the
+    /// queries running on the old one finish with the weights they started
+    /// with. The walks kept for the graph survive the change unless it
+    /// affects them. When the cells can't be written the graph is released
+    /// from the device, so its next query uploads the persisted matrix.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the graph
+    /// * `edges` - The new weights of the edges, with vertices of the graph
+    /// * `write` - How to copy the matrix resident in the device with the changed cells written
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<EdgesUpdate>, String>` - What has changed, if the graph exists
+    ///
+    pub fn update_edges(&self, name: &str, edges: &[EdgeChange], write: impl FnOnce(&DeviceGraph, &[(usize, f32)]) -> opencl::Result<DeviceGraph>) -> Result<Option<EdgesUpdate>, String> {
+        // Keep the uploads and the other changes out until the graph is changed
+        let mut graphs = self.graphs.write().unwrap();
+
+        let mut persisted = match self.repository.load(name)? {
+            Some(persisted) => persisted,
+            None => return Ok(None)
+        };
+
+        // Change the matrix, finding the cells the kernels read that have changed
+        let before = persisted.matrix.clone();
+        edges.iter().for_each(|edge| persisted.matrix.set_edge(edge.source, edge.target, edge.weight));
+
+        let (old, new) = (before.directed_data(), persisted.matrix.directed_data());
+        let cells: Vec<(usize, f32)> = new.iter().enumerate()
+            .filter(|(index, weight)| old[*index].to_bits() != weight.to_bits())
+            .map(|(index, weight)| (index, *weight))
+            .collect();
+
+        info!("Changing {} edges and {} cells of graph {}", edges.len(), cells.len(), name);
+        self.repository.save(&persisted)?;
+
+        let mut update = EdgesUpdate { graph: persisted.info.clone(), cells: cells.len(), resident: false, kept_paths: 0, invalidated_paths: 0 };
+
+        let Some(graph) = graphs.get(name).cloned() else {
+            return Ok(Some(update));
+        };
+
+        // Write the changed cells to a copy of the matrix, or release the graph so it's uploaded again
+        let device = match write(&graph.device, &cells) {
+            Ok(device) => Arc::new(device),
+            Err(err) => {
+                warn!("The edges of graph {} can't be written to the device, releasing it: {}", name, err);
+                graphs.remove(name);
+                return Ok(Some(update));
+            }
+        };
+
+        // Keep the walks that the changed edges, both ways, don't affect
+        let pairs: Vec<(usize, usize)> = edges.iter().flat_map(|edge| [(edge.source, edge.target), (edge.target, edge.source)]).collect();
+        let walks = graph.paths.lock().unwrap();
+        let kept: HashMap<usize, Vec<PathResult>> = walks.iter()
+            .filter(|(source, path)| !is_path_affected(path, **source, &before, &persisted.matrix, &pairs))
+            .map(|(source, path)| (*source, path.clone()))
+            .collect();
+
+        update.resident = true;
+        update.kept_paths = kept.len();
+        update.invalidated_paths = walks.len() - kept.len();
+
+        let changed = StoredGraph { paths: Mutex::new(kept), ..StoredGraph::new(graph.info.clone(), device, &persisted.matrix) };
+        graphs.insert(name.to_string(), Arc::new(changed));
+
+        Ok(Some(update))
+    }
+
+    /// Remove a graph, freeing its device memory once the running queries finish
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the graph
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, String>` - If the graph existed
+    ///
+    pub fn remove(&self, name: &str) -> Result<bool, String> {
+        info!("Removing graph {}", name);
+
+        let removed = self.repository.delete(name)?;
+        let cached = self.graphs.write().unwrap().remove(name).is_some();
+
+        Ok(removed || cached)
+    }
+
+    /// Free the device memory of every graph, keeping them persisted
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The number of graphs released from the device
+    ///
+    pub fn release(&self) -> usize {
+        let mut graphs = self.graphs.write().unwrap();
+        let released = graphs.len();
+
+        graphs.clear();
+        released
+    }
+
+    /// List the graphs of the store
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<GraphInfo>, String>` - The description of the graphs, sorted by name
+    ///
+    pub fn list(&self) -> Result<Vec<GraphInfo>, String> {
+        let mut graphs = self.repository.list()?;
+        graphs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(graphs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_edges() {
+        // Prepare an undirected graph persisted but not resident in the device
+        let store = GraphStore::with_repository(Box::new(MemoryRepository::default()));
+        let info = GraphInfo { name: "roads".to_string(), vertices: 3, device_bytes: 36, created_at: 1684000000000 };
+        let matrix = Matrix { directed: false, ..Matrix::new(3, 3, vec![0.0, 1.0, 0.0, 1.0, 0.0, 2.0, 0.0, 2.0, 0.0]) };
+        store.repository.save(&PersistedGraph { info: info.clone(), matrix }).unwrap();
+        let edges = [EdgeChange { source: 0, target: 2, weight: 4.0 }, EdgeChange { source: 1, target: 2, weight: 2.0 }];
+
+        // Get the result
+        let update = store.update_edges("roads", &edges, |_, _| panic!("The graph isn't resident")).unwrap();
+        let missing = store.update_edges("missing", &edges, |_, _| panic!("The graph doesn't exist")).unwrap();
+
+        // Check if the result is correct
+        assert_eq!(update, Some(EdgesUpdate { graph: info, cells: 2, resident: false, kept_paths: 0, invalidated_paths: 0 }));
+        assert_eq!(store.matrix("roads").unwrap().unwrap().data, vec![0.0, 1.0, 4.0, 1.0, 0.0, 2.0, 4.0, 2.0, 0.0]);
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn test_unit() {
+        // Prepare a graph weighted in seconds persisted but not resident in the device
+        let store = GraphStore::with_repository(Box::new(MemoryRepository::default()));
+        let info = GraphInfo { name: "roads".to_string(), vertices: 2, device_bytes: 16, created_at: 1684000000000 };
+        let matrix = Matrix { unit: Some(WeightUnit::Seconds), ..Matrix::new(2, 2, vec![0.0, 1.0, 1.0, 0.0]) };
+        store.repository.save(&PersistedGraph { info, matrix }).unwrap();
+
+        // Get the result
+        let unit = store.unit("roads").unwrap();
+        let missing = store.unit("missing").unwrap();
+
+        // Check if the result is correct
+        assert_eq!(unit, Some(Some(WeightUnit::Seconds)));
+        assert_eq!(missing, None);
+    }
+}
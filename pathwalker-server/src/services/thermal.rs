@@ -0,0 +1,259 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::warn;
+use serde::Serialize;
+
+/// The environment variable with the temperature to start throttling at
+pub const THROTTLE_CELSIUS_VARIABLE: &str = "PATHWALKER_THERMAL_THROTTLE_CELSIUS";
+
+/// The environment variable with the temperature to stop admitting jobs at
+pub const CRITICAL_CELSIUS_VARIABLE: &str = "PATHWALKER_THERMAL_CRITICAL_CELSIUS";
+
+/// The environment variable with the power draw to start throttling at
+pub const POWER_LIMIT_WATTS_VARIABLE: &str = "PATHWALKER_POWER_LIMIT_WATTS";
+
+/// How long a reading of the sensors is reused
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A reading of the sensors, with the moment, temperature and power
+type Sample = (Instant, Option<f32>, Option<f32>);
+
+/// The thermal level of the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThermalLevel {
+    Normal,
+    Throttled,
+    Critical,
+}
+
+/// The thermal state of the host, as read from the sensors
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThermalState {
+    pub level: ThermalLevel,
+    pub temperature: Option<f32>,
+    pub power: Option<f32>,
+    pub in_flight: usize,
+}
+
+/// The thresholds that drive the admission of jobs
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThermalLimits {
+    pub throttle_celsius: f32,
+    pub critical_celsius: f32,
+    pub power_watts: Option<f32>,
+}
+
+impl ThermalLimits {
+    /// Read the limits from the environment
+    ///
+    /// # Returns
+    ///
+    /// * `ThermalLimits` - The limits, with 80ºC and 90ºC as default temperatures
+    ///
+    pub fn from_env() -> ThermalLimits {
+        let read = |name: &str| std::env::var(name).ok().and_then(|value| value.parse::<f32>().ok());
+
+        ThermalLimits {
+            throttle_celsius: read(THROTTLE_CELSIUS_VARIABLE).unwrap_or(80.0),
+            critical_celsius: read(CRITICAL_CELSIUS_VARIABLE).unwrap_or(90.0),
+            power_watts: read(POWER_LIMIT_WATTS_VARIABLE)
+        }
+    }
+}
+
+/// The thermal monitor
+///
+/// Reads the temperature and power sensors exposed by the kernel in sysfs,
+/// and decides if new jobs can be admitted: any number of them while the host
+/// is cool, only one at a time while it's throttled, and none when it's critical.
+///
+/// # Fields
+///
+/// * `root` - The sysfs class directory to read the sensors from
+/// * `limits` - The thresholds of the levels
+/// * `sample` - The last reading of the sensors
+/// * `in_flight` - The number of admitted jobs still running
+///
+pub struct ThermalMonitor {
+    root: PathBuf,
+    limits: ThermalLimits,
+    sample: Mutex<Option<Sample>>,
+    in_flight: AtomicUsize,
+}
+
+/// A job admitted by the thermal monitor, released when dropped
+pub struct AdmissionPermit<'a> {
+    monitor: &'a ThermalMonitor,
+}
+
+impl Drop for AdmissionPermit<'_> {
+    fn drop(&mut self) {
+        self.monitor.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ThermalMonitor {
+    /// Create a monitor reading the sensors of the host
+    ///
+    /// # Returns
+    ///
+    /// * `ThermalMonitor` - The monitor, with the limits from the environment
+    ///
+    pub fn new() -> ThermalMonitor {
+        ThermalMonitor::with_root(Path::new("/sys/class"), ThermalLimits::from_env())
+    }
+
+    /// Create a monitor reading the sensors from another directory
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory with the `thermal` and `hwmon` classes
+    /// * `limits` - The thresholds of the levels
+    ///
+    /// # Returns
+    ///
+    /// * `ThermalMonitor` - The monitor
+    ///
+    pub fn with_root(root: &Path, limits: ThermalLimits) -> ThermalMonitor {
+        ThermalMonitor { root: root.to_path_buf(), limits, sample: Mutex::new(None), in_flight: AtomicUsize::new(0) }
+    }
+
+    /// Get the current thermal state
+    ///
+    /// # Returns
+    ///
+    /// * `ThermalState` - The state, with the sensors read at most once per second
+    ///
+    pub fn state(&self) -> ThermalState {
+        let (temperature, power) = {
+            let mut sample = self.sample.lock().unwrap();
+
+            match *sample {
+                Some((sampled_at, temperature, power)) if sampled_at.elapsed() < SAMPLE_INTERVAL => (temperature, power),
+                _ => {
+                    let (temperature, power) = (self.read_temperature(), self.read_power());
+                    *sample = Some((Instant::now(), temperature, power));
+                    (temperature, power)
+                }
+            }
+        };
+
+        // Classify the readings with the limits
+        let level = if temperature.is_some_and(|celsius| celsius >= self.limits.critical_celsius) {
+            ThermalLevel::Critical
+        } else if temperature.is_some_and(|celsius| celsius >= self.limits.throttle_celsius)
+            || power.zip(self.limits.power_watts).is_some_and(|(watts, limit)| watts >= limit) {
+            ThermalLevel::Throttled
+        } else {
+            ThermalLevel::Normal
+        };
+
+        ThermalState { level, temperature, power, in_flight: self.in_flight.load(Ordering::SeqCst) }
+    }
+
+    /// Try to admit a new job
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AdmissionPermit, ThermalState>` - The permit to run the job, or the state that rejected it
+    ///
+    pub fn admit(&self) -> Result<AdmissionPermit<'_>, ThermalState> {
+        let state = self.state();
+        let limit = match state.level {
+            ThermalLevel::Normal => usize::MAX,
+            ThermalLevel::Throttled => 1,
+            ThermalLevel::Critical => 0
+        };
+
+        // Reserve a slot only if there is one free
+        let admitted = self.in_flight
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |in_flight| (in_flight < limit).then_some(in_flight + 1))
+            .is_ok();
+
+        if admitted {
+            Ok(AdmissionPermit { monitor: self })
+        } else {
+            warn!("Rejecting job because the host is {:?} at {:?}ºC", state.level, state.temperature);
+            Err(state)
+        }
+    }
+
+    /// Read the hottest thermal zone, in celsius
+    fn read_temperature(&self) -> Option<f32> {
+        read_sensors(&self.root.join("thermal"), "thermal_zone", |name| name == "temp")
+            .into_iter()
+            .map(|millicelsius| millicelsius / 1000.0)
+            .reduce(f32::max)
+    }
+
+    /// Read the power draw of all the monitors, in watts
+    fn read_power(&self) -> Option<f32> {
+        read_sensors(&self.root.join("hwmon"), "hwmon", |name| name.starts_with("power") && (name.ends_with("_input") || name.ends_with("_average")))
+            .into_iter()
+            .map(|microwatts| microwatts / 1_000_000.0)
+            .reduce(|a, b| a + b)
+    }
+}
+
+/// Read the values of the sensors of a sysfs class
+///
+/// # Arguments
+///
+/// * `class` - The directory of the class
+/// * `prefix` - The prefix of the devices to read
+/// * `filter` - Which files of the devices are sensors
+///
+/// # Returns
+///
+/// * `Vec<f32>` - The values of the sensors
+///
+fn read_sensors(class: &Path, prefix: &str, filter: impl Fn(&str) -> bool) -> Vec<f32> {
+    let devices = match fs::read_dir(class) {
+        Ok(devices) => devices,
+        Err(_) => return vec![]
+    };
+
+    devices.flatten()
+        .filter(|device| device.file_name().to_string_lossy().starts_with(prefix))
+        .filter_map(|device| fs::read_dir(device.path()).ok())
+        .flat_map(|files| files.flatten())
+        .filter(|file| filter(&file.file_name().to_string_lossy()))
+        .filter_map(|file| fs::read_to_string(file.path()).ok())
+        .filter_map(|value| value.trim().parse::<f32>().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thermal_admission() {
+        // Prepare the sensors
+        let root = std::env::temp_dir().join("path_walker_test_sysfs");
+        fs::create_dir_all(root.join("thermal/thermal_zone0")).unwrap();
+        fs::create_dir_all(root.join("thermal/thermal_zone1")).unwrap();
+        fs::write(root.join("thermal/thermal_zone0/temp"), "45000\n").unwrap();
+        fs::write(root.join("thermal/thermal_zone1/temp"), "85000\n").unwrap();
+
+        // Get the result
+        let monitor = ThermalMonitor::with_root(&root, ThermalLimits { throttle_celsius: 80.0, critical_celsius: 90.0, power_watts: None });
+        let first = monitor.admit();
+        let second = monitor.admit();
+
+        // Check if the result is correct
+        assert_eq!(monitor.state().temperature, Some(85.0));
+        assert_eq!(monitor.state().level, ThermalLevel::Throttled);
+        assert!(first.is_ok());
+        assert!(second.is_err());
+
+        // Check if the permit is released
+        drop(first);
+        assert!(monitor.admit().is_ok());
+    }
+}
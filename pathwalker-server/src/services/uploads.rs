@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::info;
+use serde::Serialize;
+
+use crate::models::Matrix;
+
+/// The environment variable with the seconds an unfinished upload is kept without receiving chunks
+pub const UPLOAD_IDLE_TIMEOUT_VARIABLE: &str = "PATHWALKER_UPLOAD_IDLE_TIMEOUT_SECONDS";
+
+/// The seconds an unfinished upload is kept without receiving chunks by default
+const DEFAULT_UPLOAD_IDLE_TIMEOUT_SECONDS: u64 = 600;
+
+/// The environment variable with the most uploads kept unfinished at once
+pub const MAX_PENDING_UPLOADS_VARIABLE: &str = "PATHWALKER_MAX_PENDING_UPLOADS";
+
+/// The most uploads kept unfinished at once by default
+const DEFAULT_MAX_PENDING_UPLOADS: usize = 16;
+
+/// The encodings of the rows of a chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkFormat {
+    /// A line of comma separated weights for every row, with an empty cell or `inf` for a missing edge
+    Csv,
+    /// The weights of the rows as little endian 32 bit floats, one after the other
+    Binary,
+}
+
+impl ChunkFormat {
+    /// Parse the name of a format
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name, `csv` or `binary`
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ChunkFormat>` - The format, if the name is known
+    ///
+    pub fn parse(name: &str) -> Option<ChunkFormat> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "csv" => Some(ChunkFormat::Csv),
+            "binary" => Some(ChunkFormat::Binary),
+            _ => None
+        }
+    }
+
+    /// Decode the rows of a chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The body of the chunk
+    /// * `width` - The vertices of the matrix, and so the weights of every row
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<f32>, String>` - The weights of the rows, or why the chunk can't be decoded
+    ///
+    pub fn decode(&self, body: &[u8], width: usize) -> Result<Vec<f32>, String> {
+        let weights = match self {
+            ChunkFormat::Csv => {
+                let text = std::str::from_utf8(body).map_err(|_| "The chunk is not valid UTF-8".to_string())?;
+
+                text.lines().enumerate()
+                    .filter(|(_, line)| !line.trim().is_empty())
+                    .map(|(line, text)| {
+                        let row = text.split(',').map(|cell| match cell.trim() {
+                            "" => Ok(f32::MAX),
+                            cell if cell.eq_ignore_ascii_case("inf") => Ok(f32::MAX),
+                            cell => cell.parse::<f32>().map_err(|_| format!("The weight {} at line {} is not a number", cell, line + 1))
+                        }).collect::<Result<Vec<f32>, String>>()?;
+
+                        if row.len() == width {
+                            Ok(row)
+                        } else {
+                            Err(format!("The line {} has {} weights instead of {}", line + 1, row.len(), width))
+                        }
+                    })
+                    .collect::<Result<Vec<Vec<f32>>, String>>()?
+                    .concat()
+            },
+            ChunkFormat::Binary => {
+                if !body.len().is_multiple_of(width * 4) {
+                    return Err(format!("The chunk has {} bytes, which are not whole rows of {} weights", body.len(), width));
+                }
+
+                body.chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect()
+            }
+        };
+
+        if weights.is_empty() {
+            Err("The chunk has no rows".to_string())
+        } else {
+            Ok(weights)
+        }
+    }
+}
+
+/// The shape of a matrix uploaded in chunks, fixed by its first chunk
+///
+/// # Fields
+///
+/// * `width` - The vertices of the matrix
+/// * `zero_edges` - If the zero weights of the matrix are edges
+/// * `directed` - If the edges of the matrix only go from the source to the target
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadShape {
+    pub width: usize,
+    pub zero_edges: bool,
+    pub directed: bool,
+}
+
+/// A matrix being uploaded in chunks
+///
+/// # Fields
+///
+/// * `shape` - The shape of the matrix
+/// * `data` - The weights of the matrix, filled as the chunks arrive
+/// * `received` - If every row has been received
+/// * `rows` - The rows received
+/// * `updated_at` - When the last chunk arrived
+///
+struct PendingUpload {
+    shape: UploadShape,
+    data: Vec<f32>,
+    received: Vec<bool>,
+    rows: usize,
+    updated_at: Instant,
+}
+
+/// The progress of an upload after receiving a chunk
+#[derive(Debug, Clone, PartialEq)]
+pub enum UploadProgress {
+    /// Some rows are still missing
+    Partial(UploadStatus),
+    /// Every row has been received, so the matrix is complete
+    Complete(Matrix),
+}
+
+/// The rows received of an unfinished upload
+///
+/// # Fields
+///
+/// * `width` - The vertices of the matrix
+/// * `received_rows` - The rows received
+/// * `missing_rows` - The rows still missing
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct UploadStatus {
+    pub width: usize,
+    pub received_rows: usize,
+    pub missing_rows: usize,
+}
+
+/// The matrices being uploaded in chunks, by the name of their graph
+///
+/// The rows of a matrix can arrive in any order and a row sent again
+/// replaces the previous one, so a chunk that failed can be retried. The
+/// uploads that receive no chunk for the idle timeout are discarded when
+/// the next chunk of any upload arrives, releasing their memory. As every
+/// upload holds a whole matrix, only a few of them are kept unfinished at
+/// once, and the first chunks of the next ones are refused until some of
+/// them finish or are discarded.
+///
+/// # Fields
+///
+/// * `uploads` - The unfinished uploads, by the name of their graph
+/// * `idle_timeout` - How long an upload is kept without receiving chunks
+/// * `max_pending` - The most uploads kept unfinished at once
+///
+pub struct ChunkedUploads {
+    uploads: Mutex<HashMap<String, PendingUpload>>,
+    idle_timeout: Duration,
+    max_pending: usize,
+}
+
+impl Default for ChunkedUploads {
+    fn default() -> ChunkedUploads {
+        ChunkedUploads::new()
+    }
+}
+
+impl ChunkedUploads {
+    /// Create the registry with the idle timeout and the most unfinished uploads configured in the environment
+    pub fn new() -> ChunkedUploads {
+        let seconds = std::env::var(UPLOAD_IDLE_TIMEOUT_VARIABLE).ok()
+            .and_then(|seconds| seconds.parse().ok())
+            .unwrap_or(DEFAULT_UPLOAD_IDLE_TIMEOUT_SECONDS);
+        let max_pending = std::env::var(MAX_PENDING_UPLOADS_VARIABLE).ok()
+            .and_then(|uploads| uploads.parse().ok())
+            .unwrap_or(DEFAULT_MAX_PENDING_UPLOADS);
+
+        ChunkedUploads::with_idle_timeout(Duration::from_secs(seconds)).with_max_pending(max_pending)
+    }
+
+    /// Create the registry with an idle timeout
+    ///
+    /// # Arguments
+    ///
+    /// * `idle_timeout` - How long an upload is kept without receiving chunks
+    ///
+    /// # Returns
+    ///
+    /// * `ChunkedUploads` - The registry, without uploads
+    ///
+    pub fn with_idle_timeout(idle_timeout: Duration) -> ChunkedUploads {
+        ChunkedUploads { uploads: Mutex::new(HashMap::new()), idle_timeout, max_pending: DEFAULT_MAX_PENDING_UPLOADS }
+    }
+
+    /// Limit the uploads kept unfinished at once
+    ///
+    /// # Arguments
+    ///
+    /// * `max_pending` - The most uploads kept unfinished at once
+    ///
+    /// # Returns
+    ///
+    /// * `ChunkedUploads` - The registry, refusing the uploads over the limit
+    ///
+    pub fn with_max_pending(mut self, max_pending: usize) -> ChunkedUploads {
+        self.max_pending = max_pending;
+        self
+    }
+
+    /// Get the shape of an unfinished upload
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the graph
+    ///
+    /// # Returns
+    ///
+    /// * `Option<UploadShape>` - The shape, if the graph is being uploaded
+    ///
+    pub fn shape(&self, name: &str) -> Option<UploadShape> {
+        self.uploads.lock().unwrap().get(name).map(|upload| upload.shape)
+    }
+
+    /// Add some rows to the upload of a graph, starting it when it's the first chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the graph
+    /// * `shape` - The shape of the matrix, which must be the same of the first chunk
+    /// * `offset` - The first row of the chunk
+    /// * `weights` - The weights of the rows of the chunk
+    ///
+    /// # Returns
+    ///
+    /// * `Result<UploadProgress, String>` - The rows still missing or the complete matrix, or why the chunk doesn't fit
+    ///
+    pub fn append(&self, name: &str, shape: UploadShape, offset: usize, weights: &[f32]) -> Result<UploadProgress, String> {
+        // Check that the rows of the chunk fit in the matrix, before holding the uploads
+        let rows = weights.len() / shape.width.max(1);
+
+        if shape.width == 0 || !weights.len().is_multiple_of(shape.width) || offset >= shape.width || rows > shape.width - offset {
+            return Err(format!("The rows {} to {} are outside of the matrix of {} vertices", offset, offset.saturating_add(rows.max(1) - 1), shape.width));
+        }
+
+        let mut uploads = self.uploads.lock().unwrap();
+
+        // Discard the uploads whose clients have gone away
+        let idle_timeout = self.idle_timeout;
+        uploads.retain(|name, upload| {
+            let alive = upload.updated_at.elapsed() < idle_timeout;
+
+            if !alive {
+                info!("Discarding the upload of graph {} after {:?} without chunks", name, idle_timeout);
+            }
+
+            alive
+        });
+
+        // Check that the chunk belongs to the upload, or that there is room for a new one
+        if let Some(upload) = uploads.get(name).filter(|upload| upload.shape != shape) {
+            return Err(format!("The chunk doesn't match the upload of graph {}, which has {} vertices", name, upload.shape.width));
+        } else if !uploads.contains_key(name) && uploads.len() >= self.max_pending {
+            return Err(format!("There are already {} unfinished uploads, finish or discard some of them first", uploads.len()));
+        }
+
+        // Copy the rows into the matrix
+        let upload = uploads.entry(name.to_string()).or_insert_with(|| PendingUpload {
+            shape,
+            data: vec![0.0; shape.width * shape.width],
+            received: vec![false; shape.width],
+            rows: 0,
+            updated_at: Instant::now()
+        });
+
+        upload.data[offset * shape.width..(offset + rows) * shape.width].copy_from_slice(weights);
+        upload.updated_at = Instant::now();
+
+        for received in &mut upload.received[offset..offset + rows] {
+            upload.rows += usize::from(!*received);
+            *received = true;
+        }
+
+        if upload.rows < shape.width {
+            return Ok(UploadProgress::Partial(UploadStatus { width: shape.width, received_rows: upload.rows, missing_rows: shape.width - upload.rows }));
+        }
+
+        // Hand the complete matrix over, finishing the upload
+        let upload = uploads.remove(name).unwrap();
+
+        Ok(UploadProgress::Complete(Matrix {
+            zero_edges: shape.zero_edges,
+            directed: shape.directed,
+            ..Matrix::new(shape.width, shape.width, upload.data)
+        }))
+    }
+
+    /// Discard the unfinished upload of a graph
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the graph
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If the graph was being uploaded
+    ///
+    pub fn discard(&self, name: &str) -> bool {
+        self.uploads.lock().unwrap().remove(name).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunked_upload() {
+        // Prepare a triangle split in a CSV chunk and a binary chunk, sent out of order
+        let uploads = ChunkedUploads::with_idle_timeout(Duration::from_secs(60));
+        let shape = UploadShape { width: 3, zero_edges: false, directed: true };
+        let last = [4.0f32, 2.0, 0.0].iter().flat_map(|weight| weight.to_le_bytes()).collect::<Vec<u8>>();
+
+        // Get the result
+        let first = uploads.append("triangle", shape, 2, &ChunkFormat::Binary.decode(&last, 3).unwrap());
+        let mismatched = uploads.append("triangle", UploadShape { width: 4, ..shape }, 0, &[0.0; 4]);
+        let outside = uploads.append("triangle", shape, 2, &[0.0; 6]);
+        let overflowing = uploads.append("triangle", shape, usize::MAX, &[0.0; 3]);
+        let crowded = ChunkedUploads::with_idle_timeout(Duration::from_secs(60)).with_max_pending(1);
+        let pending = [crowded.append("first", shape, 0, &[0.0; 3]).is_ok(), crowded.append("second", shape, 0, &[0.0; 3]).is_ok()];
+        let second = uploads.append("triangle", shape, 0, &ChunkFormat::Csv.decode(b"0,1,4\n1,0,inf\n", 3).unwrap());
+
+        // Check if the result is correct
+        assert_eq!(first, Ok(UploadProgress::Partial(UploadStatus { width: 3, received_rows: 1, missing_rows: 2 })));
+        assert!(mismatched.is_err() && outside.is_err() && overflowing.is_err());
+        assert_eq!(pending, [true, false]);
+        assert_eq!(second, Ok(UploadProgress::Complete(Matrix::new(3, 3, vec![0.0, 1.0, 4.0, 1.0, 0.0, f32::MAX, 4.0, 2.0, 0.0]))));
+        assert_eq!(uploads.shape("triangle"), None);
+        assert!(ChunkFormat::Csv.decode(b"0,1\n", 3).is_err());
+        assert!(ChunkFormat::Binary.decode(&[0; 5], 3).is_err());
+    }
+}
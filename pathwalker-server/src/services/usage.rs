@@ -0,0 +1,418 @@
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use pathwalker_core::services::metrics::WalkUsage;
+
+/// The name the usage of the requests without an API key is accounted to
+pub const ANONYMOUS_KEY: &str = "anonymous";
+
+/// The environment variable with the directory where the usage is persisted
+pub const USAGE_DIR_VARIABLE: &str = "PATHWALKER_USAGE_DIR";
+
+/// The directory where the usage is persisted by default
+pub const DEFAULT_USAGE_DIR: &str = "usage";
+
+tokio::task_local! {
+    /// The meter of the request being answered by the task
+    static REQUEST_METER: UsageMeter;
+}
+
+/// The usage of the server by an API key in a month
+///
+/// # Fields
+///
+/// * `requests` - The requests answered
+/// * `vertices` - The vertices of the graphs walked
+/// * `kernel_ms` - The milliseconds the kernels have run in the device
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyUsage {
+    pub requests: u64,
+    pub vertices: u64,
+    pub kernel_ms: f64,
+}
+
+impl KeyUsage {
+    /// Add the usage of another key, for the totals
+    fn add(&mut self, other: &KeyUsage) {
+        self.requests += other.requests;
+        self.vertices += other.vertices;
+        self.kernel_ms += other.kernel_ms;
+    }
+}
+
+/// The usage of every API key in the current month
+///
+/// # Fields
+///
+/// * `month` - The calendar month being accounted, like `2026-10`
+/// * `keys` - The usage of every API key in the month
+///
+struct MonthUsage {
+    month: String,
+    keys: HashMap<String, KeyUsage>,
+}
+
+/// The usage of the server by every API key
+///
+/// The requests, the vertices walked and the execution time of the kernels
+/// are accounted to the API key of the request, and reset when a calendar
+/// month starts, in UTC. When a monthly quota of kernel milliseconds is
+/// configured, the keys that have used it are rejected until the month ends.
+/// The usage is persisted in a database when there is one, so a restart
+/// neither forgets the bills nor gives the keys their quota back.
+///
+/// # Fields
+///
+/// * `monthly_quota` - The kernel milliseconds of every key in a month, if limited
+/// * `usage` - The usage of the current month
+/// * `database` - The database where the usage of every key is persisted, if any
+///
+pub struct UsageStats {
+    monthly_quota: Option<u64>,
+    usage: Mutex<MonthUsage>,
+    database: Option<sled::Db>,
+}
+
+/// The account of the walks of a request in the usage of its API key
+///
+/// # Fields
+///
+/// * `stats` - The usage of every API key
+/// * `key` - The name of the API key of the request
+///
+#[derive(Clone)]
+pub struct UsageMeter {
+    stats: Arc<UsageStats>,
+    key: String,
+}
+
+impl UsageMeter {
+    /// Create the meter of a request
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - The usage of every API key
+    /// * `key` - The name of the API key of the request
+    ///
+    /// # Returns
+    ///
+    /// * `UsageMeter` - The meter
+    ///
+    pub fn new(stats: Arc<UsageStats>, key: &str) -> UsageMeter {
+        UsageMeter { stats, key: key.to_string() }
+    }
+
+    /// Account a request to its API key and create its meter, unless the key is over its quota
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - The usage of every API key
+    /// * `key` - The name of the API key of the request
+    ///
+    /// # Returns
+    ///
+    /// * `Result<UsageMeter, String>` - The meter, or the reason why the request must be rejected
+    ///
+    pub fn admit(stats: Arc<UsageStats>, key: &str) -> Result<UsageMeter, String> {
+        if stats.is_over_quota(key) {
+            return Err(format!("The API key {} has used its monthly quota of {} kernel milliseconds", key, stats.monthly_quota().unwrap_or_default()));
+        }
+
+        stats.record_request(key);
+        Ok(UsageMeter::new(stats, key))
+    }
+
+    /// Account the work of walks to the API key of the request
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - The vertices walked and the execution time of the kernels
+    ///
+    pub fn record(&self, usage: WalkUsage) {
+        self.stats.record_walks(&self.key, usage);
+    }
+
+    /// Run a future with the meter as the one of the current request
+    ///
+    /// # Arguments
+    ///
+    /// * `future` - The future answering the request
+    ///
+    /// # Returns
+    ///
+    /// * `F::Output` - The output of the future
+    ///
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        REQUEST_METER.scope(self, future).await
+    }
+
+    /// Get the meter of the request being answered by the current task, if any
+    pub fn current() -> Option<UsageMeter> {
+        REQUEST_METER.try_with(UsageMeter::clone).ok()
+    }
+}
+
+/// Get the calendar month of the current time, in UTC
+fn current_month() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+impl UsageStats {
+    /// Create the usage of every API key
+    ///
+    /// # Arguments
+    ///
+    /// * `monthly_quota` - The kernel milliseconds of every key in a month, if limited
+    ///
+    /// # Returns
+    ///
+    /// * `UsageStats` - The usage, empty
+    ///
+    pub fn new(monthly_quota: Option<u64>) -> UsageStats {
+        UsageStats {
+            monthly_quota: monthly_quota.filter(|quota| *quota > 0),
+            usage: Mutex::new(MonthUsage { month: current_month(), keys: HashMap::new() }),
+            database: None
+        }
+    }
+
+    /// Create the usage of every API key persisted in the directory configured in the environment
+    ///
+    /// The directory is taken from `PATHWALKER_USAGE_DIR`, or `usage` when
+    /// the variable isn't set, and it is created if it doesn't exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `monthly_quota` - The kernel milliseconds of every key in a month, if limited
+    ///
+    /// # Returns
+    ///
+    /// * `UsageStats` - The usage, kept in memory only when the database can't be opened
+    ///
+    pub fn from_env(monthly_quota: Option<u64>) -> UsageStats {
+        let directory = std::env::var(USAGE_DIR_VARIABLE).unwrap_or_else(|_| DEFAULT_USAGE_DIR.to_string());
+
+        UsageStats::open(monthly_quota, Path::new(&directory)).unwrap_or_else(|err| {
+            error!("{}, the usage won't outlive the server", err);
+            UsageStats::new(monthly_quota)
+        })
+    }
+
+    /// Create the usage of every API key persisted in a directory
+    ///
+    /// The usage of the current month already persisted is loaded, so the
+    /// keys go on from where they were before the restart.
+    ///
+    /// # Arguments
+    ///
+    /// * `monthly_quota` - The kernel milliseconds of every key in a month, if limited
+    /// * `directory` - The directory of the database
+    ///
+    /// # Returns
+    ///
+    /// * `Result<UsageStats, String>` - The usage, or the reason why the database can't be opened
+    ///
+    pub fn open(monthly_quota: Option<u64>, directory: &Path) -> Result<UsageStats, String> {
+        let database = sled::open(directory)
+            .map_err(|err| format!("The usage database {} can't be opened: {}", directory.display(), err))?;
+
+        // Load the usage of the current month, the older months are only kept for the records
+        let month = current_month();
+        let keys = database.scan_prefix(format!("{}/", month).as_bytes())
+            .map(|entry| {
+                let (key, value) = entry.map_err(|err| err.to_string())?;
+                let key = String::from_utf8_lossy(&key[month.len() + 1..]).to_string();
+                let usage = bincode::deserialize::<KeyUsage>(&value).map_err(|err| err.to_string())?;
+
+                Ok((key, usage))
+            })
+            .collect::<Result<HashMap<_, _>, String>>()
+            .map_err(|err| format!("The usage database {} can't be read: {}", directory.display(), err))?;
+
+        Ok(UsageStats {
+            database: Some(database),
+            usage: Mutex::new(MonthUsage { month, keys }),
+            ..UsageStats::new(monthly_quota)
+        })
+    }
+
+    /// Get the kernel milliseconds of every key in a month, if limited
+    pub fn monthly_quota(&self) -> Option<u64> {
+        self.monthly_quota
+    }
+
+    /// Update the usage of an API key in the current month
+    fn update(&self, key: &str, update: impl FnOnce(&mut KeyUsage)) {
+        let mut usage = self.usage.lock().unwrap();
+        let month = current_month();
+
+        // Start the account of a new month
+        if usage.month != month {
+            *usage = MonthUsage { month, keys: HashMap::new() };
+        }
+
+        let entry = format!("{}/{}", usage.month, key);
+        let key_usage = usage.keys.entry(key.to_string()).or_default();
+        update(key_usage);
+
+        // Persist the usage of the key, the database flushes it in the background
+        if let Some(database) = &self.database {
+            if let Err(err) = bincode::serialize(key_usage).map_err(|err| err.to_string())
+                .and_then(|value| database.insert(entry.as_bytes(), value).map_err(|err| err.to_string())) {
+                error!("The usage of the API key {} can't be persisted: {}", key, err);
+            }
+        }
+    }
+
+    /// Account a request to an API key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the API key
+    ///
+    pub fn record_request(&self, key: &str) {
+        self.update(key, |usage| usage.requests += 1);
+    }
+
+    /// Account the work of walks to an API key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the API key
+    /// * `walks` - The vertices walked and the execution time of the kernels
+    ///
+    pub fn record_walks(&self, key: &str, walks: WalkUsage) {
+        if walks != WalkUsage::default() {
+            self.update(key, |usage| {
+                usage.vertices += walks.vertices;
+                usage.kernel_ms += walks.kernel_time.as_secs_f64() * 1000.0;
+            });
+        }
+    }
+
+    /// Get the month being accounted and the usage of an API key in it
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the API key
+    ///
+    /// # Returns
+    ///
+    /// * `(String, KeyUsage)` - The month, like `2026-10`, and the usage of the key
+    ///
+    pub fn usage(&self, key: &str) -> (String, KeyUsage) {
+        let month = current_month();
+        let usage = self.usage.lock().unwrap();
+        let key_usage = if usage.month == month { usage.keys.get(key).copied().unwrap_or_default() } else { KeyUsage::default() };
+
+        (month, key_usage)
+    }
+
+    /// Get the month being accounted, the usage of every API key in it and the totals
+    ///
+    /// # Returns
+    ///
+    /// * `(String, BTreeMap<String, KeyUsage>, KeyUsage)` - The month, the usage of every key by name and the total
+    ///
+    pub fn report(&self) -> (String, BTreeMap<String, KeyUsage>, KeyUsage) {
+        let month = current_month();
+        let usage = self.usage.lock().unwrap();
+        let keys: BTreeMap<String, KeyUsage> = if usage.month == month {
+            usage.keys.iter().map(|(key, usage)| (key.clone(), *usage)).collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        let mut total = KeyUsage::default();
+        keys.values().for_each(|usage| total.add(usage));
+
+        (month, keys, total)
+    }
+
+    /// Get the kernel milliseconds an API key can still use this month
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the API key
+    ///
+    /// # Returns
+    ///
+    /// * `Option<f64>` - The milliseconds left, never negative, or nothing when unlimited
+    ///
+    pub fn remaining(&self, key: &str) -> Option<f64> {
+        let (_, usage) = self.usage(key);
+        self.monthly_quota.map(|quota| (quota as f64 - usage.kernel_ms).max(0.0))
+    }
+
+    /// Check if an API key has used its quota this month
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the API key
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If the requests of the key must be rejected until the month ends
+    ///
+    pub fn is_over_quota(&self, key: &str) -> bool {
+        self.remaining(key).is_some_and(|remaining| remaining <= 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+
+    #[test]
+    fn test_usage_stats() {
+        // Prepare the usage of two keys with a quota of 10 milliseconds
+        let stats = UsageStats::new(Some(10));
+        stats.record_request("robots");
+        stats.record_walks("robots", WalkUsage { vertices: 64, kernel_time: Duration::from_millis(12) });
+        stats.record_request("dashboard");
+        stats.record_walks("dashboard", WalkUsage { vertices: 8, kernel_time: Duration::from_millis(4) });
+
+        // Get the result
+        let (_, robots) = stats.usage("robots");
+        let (month, keys, total) = stats.report();
+
+        // Check if the result is correct
+        assert_eq!(month, current_month());
+        assert_eq!(robots, KeyUsage { requests: 1, vertices: 64, kernel_ms: 12.0 });
+        assert_eq!(keys.len(), 2);
+        assert_eq!(total, KeyUsage { requests: 2, vertices: 72, kernel_ms: 16.0 });
+        assert!(stats.is_over_quota("robots"));
+        assert_eq!(stats.remaining("dashboard"), Some(6.0));
+        assert!(!UsageStats::new(None).is_over_quota("robots"));
+    }
+
+    #[test]
+    fn test_persisted_usage_stats() {
+        // Prepare the usage of a key persisted in a database
+        let directory = std::env::temp_dir().join(format!("path_walker_test_usage_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let stats = UsageStats::open(Some(10), &directory).unwrap();
+        stats.record_request("robots");
+        stats.record_walks("robots", WalkUsage { vertices: 64, kernel_time: Duration::from_millis(12) });
+        drop(stats);
+
+        // Get the result, reopening the database as a restart would
+        let stats = UsageStats::open(Some(10), &directory).unwrap();
+        let (_, robots) = stats.usage("robots");
+
+        // Check if the result is correct
+        assert_eq!(robots, KeyUsage { requests: 1, vertices: 64, kernel_ms: 12.0 });
+        assert!(stats.is_over_quota("robots"));
+
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,350 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::services::sortest_path::SortestPath;
+
+/// The environment variable with the seconds to wait before the first retry of a device that can't be opened
+pub const WALKER_RETRY_VARIABLE: &str = "PATHWALKER_DEVICE_RETRY_SECONDS";
+
+/// The environment variable with the most seconds to wait between the retries
+pub const WALKER_MAX_RETRY_VARIABLE: &str = "PATHWALKER_DEVICE_MAX_RETRY_SECONDS";
+
+/// The seconds to wait before the first retry by default
+const DEFAULT_RETRY_SECONDS: u64 = 1;
+
+/// The most seconds to wait between the retries by default
+const DEFAULT_MAX_RETRY_SECONDS: u64 = 60;
+
+/// The failed attempts to open the device
+///
+/// # Fields
+///
+/// * `failures` - The attempts that have failed since the last success
+/// * `error` - Why the last attempt has failed, if any has
+/// * `retry_at` - When the device can be tried again
+/// * `generation` - The times the device has been opened
+///
+#[derive(Debug, Default)]
+struct Attempts {
+    failures: u32,
+    error: Option<String>,
+    retry_at: Option<Instant>,
+    generation: u64,
+}
+
+/// A walker opened by a `LazyWalker`, shared by the requests walking on it
+///
+/// The walker is freed once the device is lost and the last request
+/// walking on it is done.
+///
+/// # Fields
+///
+/// * `generation` - The opening of the device the walker belongs to
+/// * `walker` - The walker
+///
+pub struct OpenedWalker<T> {
+    generation: u64,
+    walker: Arc<T>,
+}
+
+impl<T> Clone for OpenedWalker<T> {
+    fn clone(&self) -> OpenedWalker<T> {
+        OpenedWalker { generation: self.generation, walker: self.walker.clone() }
+    }
+}
+
+impl<T> Deref for OpenedWalker<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.walker
+    }
+}
+
+impl<T> OpenedWalker<T> {
+    /// Get the walker, leaving out the opening of the device it belongs to
+    pub fn into_inner(self) -> Arc<T> {
+        self.walker
+    }
+}
+
+/// The state of the device, for the readiness probe
+///
+/// # Fields
+///
+/// * `ready` - If the device has been opened
+/// * `failures` - The attempts that have failed to open it
+/// * `error` - Why the last attempt has failed, if the device isn't open
+/// * `retry_in` - The seconds until the device is tried again, if it isn't open
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WalkerStatus {
+    pub ready: bool,
+    pub failures: u32,
+    pub error: Option<String>,
+    pub retry_in: Option<f64>,
+}
+
+/// The walker of the server, opened by the first request that needs the device
+///
+/// Opening the device fails while it's busy or its driver is being reset,
+/// so a failure isn't kept forever. The requests that come before the next
+/// attempt fail fast with the reason of the last failure, and the waits
+/// between the attempts double up to a maximum, so a missing device isn't
+/// probed on every request. The first attempt after the device is back
+/// opens it, and then it's kept open until the device is lost.
+///
+/// A lost device is dropped by `reset`, so the next request opens it
+/// again, while the requests still walking on the lost one keep its walker
+/// until they fail on their own.
+///
+/// # Fields
+///
+/// * `walker` - The walker, while the device is open
+/// * `attempts` - The failed attempts, locked while the device is being opened
+/// * `open` - How the device is opened
+/// * `retry` - The wait before the first retry
+/// * `max_retry` - The longest wait between the retries
+///
+pub struct LazyWalker<T = SortestPath> {
+    walker: RwLock<Option<OpenedWalker<T>>>,
+    attempts: Mutex<Attempts>,
+    open: fn() -> Result<T, String>,
+    retry: Duration,
+    max_retry: Duration,
+}
+
+impl Default for LazyWalker {
+    fn default() -> LazyWalker {
+        LazyWalker::new()
+    }
+}
+
+impl LazyWalker {
+    /// Create the walker of the first OpenCL device, with the waits configured in the environment
+    pub fn new() -> LazyWalker {
+        LazyWalker::from_env(SortestPath::try_new)
+    }
+}
+
+impl<T> LazyWalker<T> {
+    /// Create the walker with the way to open the device, and the waits configured in the environment
+    ///
+    /// # Arguments
+    ///
+    /// * `open` - How the device is opened
+    ///
+    /// # Returns
+    ///
+    /// * `LazyWalker<T>` - The walker, not opened yet
+    ///
+    pub fn from_env(open: fn() -> Result<T, String>) -> LazyWalker<T> {
+        let seconds = |variable: &str, default: u64| std::env::var(variable).ok()
+            .and_then(|seconds| seconds.parse().ok())
+            .unwrap_or(default);
+
+        LazyWalker::with_opener(
+            open,
+            Duration::from_secs(seconds(WALKER_RETRY_VARIABLE, DEFAULT_RETRY_SECONDS)),
+            Duration::from_secs(seconds(WALKER_MAX_RETRY_VARIABLE, DEFAULT_MAX_RETRY_SECONDS))
+        )
+    }
+
+    /// Create the walker with the way to open the device and the waits between the attempts
+    ///
+    /// # Arguments
+    ///
+    /// * `open` - How the device is opened
+    /// * `retry` - The wait before the first retry
+    /// * `max_retry` - The longest wait between the retries
+    ///
+    /// # Returns
+    ///
+    /// * `LazyWalker<T>` - The walker, not opened yet
+    ///
+    pub fn with_opener(open: fn() -> Result<T, String>, retry: Duration, max_retry: Duration) -> LazyWalker<T> {
+        LazyWalker { walker: RwLock::new(None), attempts: Mutex::new(Attempts::default()), open, retry, max_retry: max_retry.max(retry) }
+    }
+
+    /// Get the wait after some failed attempts
+    ///
+    /// # Arguments
+    ///
+    /// * `failures` - The attempts that have failed
+    ///
+    /// # Returns
+    ///
+    /// * `Duration` - The wait before the next attempt
+    ///
+    fn backoff(&self, failures: u32) -> Duration {
+        self.retry.saturating_mul(1 << failures.saturating_sub(1).min(16)).min(self.max_retry)
+    }
+
+    /// Get the walker if the device is open
+    ///
+    /// # Returns
+    ///
+    /// * `Option<OpenedWalker<T>>` - The walker, if the device is open
+    ///
+    fn current(&self) -> Option<OpenedWalker<T>> {
+        self.walker.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Get the walker, opening the device when it isn't open and the wait after the last failure is over
+    ///
+    /// The requests that come while another one opens the device wait for it.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<OpenedWalker<T>, String>` - The walker, or why the device can't be opened
+    ///
+    pub fn get(&self) -> Result<OpenedWalker<T>, String> {
+        if let Some(walker) = self.current() {
+            return Ok(walker);
+        }
+
+        let mut attempts = self.attempts.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // Another request may have opened the device while this one waited
+        if let Some(walker) = self.current() {
+            return Ok(walker);
+        }
+
+        // Fail fast until the wait after the last failure is over
+        if let (Some(retry_at), Some(error)) = (attempts.retry_at, &attempts.error) {
+            let now = Instant::now();
+
+            if retry_at > now {
+                return Err(format!("{} (retrying in {:.0} seconds)", error, (retry_at - now).as_secs_f64().ceil()));
+            }
+        }
+
+        // Open the device, containing the panics of broken drivers
+        let opened = catch_unwind(AssertUnwindSafe(self.open)).unwrap_or_else(|panic| Err(panic.downcast_ref::<String>().cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|message| message.to_string()))
+            .unwrap_or_else(|| "The driver has crashed while opening the device".to_string())));
+
+        match opened {
+            Ok(walker) => {
+                if attempts.failures > 0 {
+                    info!("The device has been opened after {} failed attempts", attempts.failures);
+                }
+
+                *attempts = Attempts { generation: attempts.generation + 1, ..Attempts::default() };
+                let walker = OpenedWalker { generation: attempts.generation, walker: Arc::new(walker) };
+                *self.walker.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(walker.clone());
+                Ok(walker)
+            },
+            Err(error) => {
+                attempts.failures += 1;
+                let wait = self.backoff(attempts.failures);
+                warn!("The device can't be opened, retrying in {:?}: {}", wait, error);
+
+                attempts.retry_at = Some(Instant::now() + wait);
+                attempts.error = Some(error.clone());
+                Err(error)
+            }
+        }
+    }
+
+    /// Get the walker only if the device is already open, without trying to open it
+    pub fn opened(&self) -> Option<OpenedWalker<T>> {
+        self.current()
+    }
+
+    /// Drop the walker of a lost device, so the next request opens the device again
+    ///
+    /// # Arguments
+    ///
+    /// * `failed` - The walker whose device has been lost
+    /// * `error` - Why the device has been lost
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - If the walker was the current one, so this call has dropped it
+    ///
+    pub fn reset(&self, failed: &OpenedWalker<T>, error: String) -> bool {
+        let mut attempts = self.attempts.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut walker = self.walker.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // Only the first request failing on the lost device drops it, and never the device opened after it
+        if walker.as_ref().is_none_or(|walker| walker.generation != failed.generation) {
+            return false;
+        }
+
+        *walker = None;
+
+        warn!("The device has been lost, opening it again on the next request: {}", error);
+
+        // Reopen it right away, backing off only if that fails
+        *attempts = Attempts { error: Some(error), generation: attempts.generation, ..Attempts::default() };
+        true
+    }
+
+    /// Describe the state of the device, for the readiness probe
+    pub fn status(&self) -> WalkerStatus {
+        let attempts = self.attempts.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let ready = self.current().is_some();
+
+        WalkerStatus {
+            ready,
+            failures: attempts.failures,
+            error: attempts.error.clone().filter(|_| !ready),
+            retry_in: attempts.retry_at.filter(|_| !ready).map(|retry_at| retry_at.saturating_duration_since(Instant::now()).as_secs_f64())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_walker_backoff() {
+        // Prepare a walker whose device is never available
+        let walker = LazyWalker::<SortestPath>::with_opener(|| Err("The device is being reset".to_string()), Duration::from_millis(50), Duration::from_millis(150));
+
+        // Get the result
+        let first = walker.get().err();
+        let waiting = walker.get().err();
+        let failures_before_retry = walker.status().failures;
+        std::thread::sleep(Duration::from_millis(60));
+        let retried = walker.get().err();
+        let status = walker.status();
+
+        // Check if the result is correct
+        assert_eq!(first.as_deref(), Some("The device is being reset"));
+        assert!(waiting.is_some_and(|error| error.contains("retrying in")));
+        assert_eq!((failures_before_retry, status.failures), (1, 2));
+        assert_eq!(retried.as_deref(), Some("The device is being reset"));
+        assert!(!status.ready && status.retry_in.is_some());
+        assert!(walker.opened().is_none());
+        assert_eq!([1, 2, 3, 10].map(|failures| walker.backoff(failures)), [50, 100, 150, 150].map(Duration::from_millis));
+    }
+
+    #[test]
+    fn test_lazy_walker_reset() {
+        // Prepare a walker whose device is always available
+        let walker = LazyWalker::<Vec<u32>>::with_opener(|| Ok(vec![1, 2, 3]), Duration::from_secs(60), Duration::from_secs(60));
+        let lost = walker.get().unwrap();
+
+        // Get the result
+        let reset = walker.reset(&lost, "CL_DEVICE_NOT_AVAILABLE".to_string());
+        let status = walker.status();
+        let reopened = walker.get().unwrap();
+        let reset_reopened = walker.reset(&lost, "CL_INVALID_CONTEXT".to_string());
+
+        // Check if the result is correct
+        assert!(reset && !reset_reopened);
+        assert_eq!(status, WalkerStatus { ready: false, failures: 0, error: Some("CL_DEVICE_NOT_AVAILABLE".to_string()), retry_in: None });
+        assert_eq!((lost.generation, reopened.generation), (1, 2));
+        assert!(!Arc::ptr_eq(&lost.walker, &reopened.walker));
+        assert!(walker.opened().is_some_and(|opened| Arc::ptr_eq(&opened.walker, &reopened.walker)));
+        assert_eq!(*reopened, [1, 2, 3]);
+    }
+}
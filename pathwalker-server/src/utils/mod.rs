@@ -0,0 +1,197 @@
+pub use pathwalker_core::utils::{round_decimals, round_decimals_f64};
+use pathwalker_core::services::metrics::take_walk_usage;
+use tokio::task::JoinHandle;
+use tracing::Span;
+use tracing_subscriber::{filter::LevelFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt, Layer};
+use crate::services::usage::UsageMeter;
+#[cfg(feature = "otlp")]
+use opentelemetry::trace::TracerProvider;
+#[cfg(feature = "otlp")]
+use opentelemetry_otlp::WithExportConfig;
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::trace::SdkTracerProvider;
+
+/// The name of the service in the exported traces
+#[cfg(feature = "otlp")]
+const SERVICE_NAME: &str = "path_walker";
+
+/// The format of the log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// A line of text with the spans of the event
+    Text,
+    /// A JSON object per line, for log collectors
+    Json,
+}
+
+impl LogFormat {
+    /// Parse the name of a log format
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the format, `text` or `json`
+    ///
+    /// # Returns
+    ///
+    /// * `Option<LogFormat>` - The format, if the name is known
+    ///
+    pub fn parse(name: &str) -> Option<LogFormat> {
+        match name.to_lowercase().as_str() {
+            "text" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None
+        }
+    }
+
+    /// Get the name of the format, as parsed
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json"
+        }
+    }
+}
+
+/// Convert a log level of the configuration to a level of the tracing subscriber
+fn level_filter(level: log::LevelFilter) -> LevelFilter {
+    match level {
+        log::LevelFilter::Off => LevelFilter::OFF,
+        log::LevelFilter::Error => LevelFilter::ERROR,
+        log::LevelFilter::Warn => LevelFilter::WARN,
+        log::LevelFilter::Info => LevelFilter::INFO,
+        log::LevelFilter::Debug => LevelFilter::DEBUG,
+        log::LevelFilter::Trace => LevelFilter::TRACE
+    }
+}
+
+/// The tracing pipeline of the process
+///
+/// Dropping it flushes the spans not exported yet, so it must live until
+/// the process exits.
+///
+/// # Fields
+///
+/// * `provider` - The provider exporting the spans over OTLP, if any
+///
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(err) = provider.shutdown() {
+                eprintln!("The pending spans can't be exported: {}", err);
+            }
+        }
+    }
+}
+
+/// Install the tracing subscriber of the process
+///
+/// The events are written to the standard output in the given format,
+/// along with the spans they happen in, and the events of the `log` crate
+/// are forwarded to the subscriber too. When an OTLP endpoint is given, the
+/// spans are exported to it as well, regardless of the log level.
+///
+/// # Arguments
+///
+/// * `level` - The most verbose level that is written
+/// * `format` - The format of the lines
+/// * `otlp_endpoint` - The URL of the OTLP/HTTP traces endpoint of the collector, if any
+///
+/// # Returns
+///
+/// * `Result<Telemetry, String>` - The tracing pipeline, or the reason why it can't be installed
+///
+pub fn init_tracing(level: log::LevelFilter, format: LogFormat, otlp_endpoint: Option<&str>) -> Result<Telemetry, String> {
+    let lines = match format {
+        LogFormat::Text => fmt::layer().boxed(),
+        LogFormat::Json => fmt::layer().json().flatten_event(true).with_span_list(true).boxed()
+    };
+
+    // Export the spans of the requests and the kernels when a collector is configured
+    #[cfg(feature = "otlp")]
+    let (provider, traces) = match otlp_endpoint {
+        Some(endpoint) => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http().with_endpoint(endpoint)
+                .build().map_err(|err| format!("The OTLP exporter can't be created: {}", err))?;
+            let provider = SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(opentelemetry_sdk::Resource::builder().with_service_name(SERVICE_NAME).build())
+                .build();
+            let traces = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)).with_filter(LevelFilter::DEBUG);
+
+            (Some(provider), Some(traces))
+        },
+        None => (None, None)
+    };
+
+    #[cfg(not(feature = "otlp"))]
+    let traces: Option<LevelFilter> = match otlp_endpoint {
+        Some(_) => return Err("The server has been built without OTLP support, rebuild it with the otlp feature".to_string()),
+        None => None
+    };
+
+    tracing_subscriber::registry()
+        .with(lines.with_filter(level_filter(level)))
+        .with(traces)
+        .try_init()
+        .map_err(|err| format!("The tracing subscriber can't be installed: {}", err))?;
+
+    Ok(Telemetry {
+        #[cfg(feature = "otlp")]
+        provider
+    })
+}
+
+/// Run a blocking function in the blocking thread pool, inside the current span
+///
+/// Same as `tokio::task::spawn_blocking`, but the events and the spans of the
+/// function belong to the span of the request that spawned it, and the
+/// walks run by the function are accounted to the API key of the request.
+///
+/// # Arguments
+///
+/// * `function` - The function to run
+///
+/// # Returns
+///
+/// * `JoinHandle<R>` - The handle of the result of the function
+///
+pub fn spawn_blocking<F, R>(function: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static
+{
+    let span = Span::current();
+    let meter = UsageMeter::current();
+
+    tokio::task::spawn_blocking(move || {
+        // Leave out the work of the functions run before in the thread
+        take_walk_usage();
+        let result = span.in_scope(function);
+
+        if let Some(meter) = meter {
+            meter.record(take_walk_usage());
+        }
+
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_format() {
+        assert_eq!(LogFormat::parse("JSON"), Some(LogFormat::Json));
+        assert_eq!(LogFormat::parse("text"), Some(LogFormat::Text));
+        assert_eq!(LogFormat::parse("xml"), None);
+        assert_eq!(level_filter(log::LevelFilter::Warn), LevelFilter::WARN);
+    }
+}
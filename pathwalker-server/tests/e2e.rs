@@ -0,0 +1,324 @@
+//! End-to-end tests of the `path_walker` server against a real OpenCL device
+//!
+//! Every test starts the server binary on free ports, with an empty graph
+//! directory and none of the `PATHWALKER_*` variables of the environment
+//! except the kernel variant, and talks to it over HTTP and WebSocket like
+//! any client. They run the kernels in the first device of the first
+//! platform, which in CI is a software implementation like PoCL, never
+//! routing the small graphs to the CPU walker, so they are opt-in: they are
+//! skipped unless `PATHWALKER_E2E=1` is set, and fail when it is set and the
+//! host has no device.
+
+use std::fs;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use awc::{ws, Client};
+use futures_util::{future::join_all, SinkExt, StreamExt};
+use pathwalker_core::services::devices::DeviceDiagnostics;
+use serde_json::{json, Value};
+
+/// The environment variable that enables the end-to-end tests
+const E2E_VARIABLE: &str = "PATHWALKER_E2E";
+
+/// The variables of the environment passed to the server, so the suite can run every kernel variant
+const KEPT_VARIABLES: [&str; 1] = ["PATHWALKER_KERNEL_VARIANT"];
+
+/// How long the server can take to start answering
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A server started for a test, stopped and cleaned up when dropped
+struct TestServer {
+    child: Child,
+    base: String,
+    dir: PathBuf,
+}
+
+/// Find a port that nothing listens on
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+impl TestServer {
+    /// Start a server, unless the end-to-end tests are disabled
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The options of the command line added to the ones of the test
+    ///
+    /// # Returns
+    ///
+    /// * `Option<TestServer>` - The server answering requests, or none when the tests are disabled
+    ///
+    async fn start(args: &[&str]) -> Option<TestServer> {
+        if std::env::var(E2E_VARIABLE).map_or(true, |enabled| enabled != "1") {
+            eprintln!("Skipping the end-to-end test, set {}=1 to run it", E2E_VARIABLE);
+            return None;
+        }
+
+        // The tests need a device, like the CPU one of PoCL
+        let diagnostics = DeviceDiagnostics::collect();
+        assert!(diagnostics.has_devices(), "The end-to-end tests need an OpenCL device: {}", diagnostics.summary());
+
+        let port = free_port();
+        let dir = std::env::temp_dir().join(format!("pathwalker-e2e-{}-{}", std::process::id(), port));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_path_walker"));
+        command.current_dir(&dir)
+            .args(["--host", "127.0.0.1", "--log-level", "warn", "--cpu-threshold", "0"])
+            .args(["--port", &port.to_string(), "--grpc-port", &free_port().to_string(), "--flight-port", &free_port().to_string()])
+            .args(args)
+            .env("PATHWALKER_GRAPH_DIR", dir.join("graphs"))
+            .stdout(Stdio::null());
+
+        for (name, _) in std::env::vars().filter(|(name, _)| name.starts_with("PATHWALKER_") && !KEPT_VARIABLES.contains(&name.as_str())) {
+            command.env_remove(name);
+        }
+
+        let mut server = TestServer { child: command.spawn().unwrap(), base: format!("http://127.0.0.1:{}", port), dir };
+
+        // Wait for the server to answer the liveness probe
+        let started_at = Instant::now();
+
+        while Client::default().get(server.url("/healthz")).send().await.is_err() {
+            assert!(server.child.try_wait().unwrap().is_none(), "The server has exited while starting");
+            assert!(started_at.elapsed() < STARTUP_TIMEOUT, "The server hasn't started in time");
+            actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        Some(server)
+    }
+
+    /// Get the URL of a path of the server
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    /// Send a request with a JSON body, or without a body when it's null
+    ///
+    /// # Returns
+    ///
+    /// * `(u16, Value)` - The status code and the body of the response, or null when it isn't JSON
+    ///
+    async fn send(&self, method: &str, path: &str, body: Value) -> (u16, Value) {
+        let request = Client::default()
+            .request(method.parse().unwrap(), self.url(path))
+            .timeout(Duration::from_secs(120));
+        let mut response = if body.is_null() { request.send().await } else { request.send_json(&body).await }.unwrap();
+        let bytes = response.body().limit(64 * 1024 * 1024).await.unwrap();
+
+        (response.status().as_u16(), serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A chain of vertices, each one joined to the next by an edge of its index plus one
+fn chain(width: usize) -> Value {
+    let mut data = vec![0.0; width * width];
+
+    for vertex in 1..width {
+        data[vertex * width + vertex - 1] = vertex as f32;
+    }
+
+    json!({ "data": data, "width": width, "height": width })
+}
+
+/// The distances along a chain from its first vertex
+fn chain_distances(width: usize) -> Vec<f64> {
+    (0..width).map(|vertex| (vertex * (vertex + 1) / 2) as f64).collect()
+}
+
+/// Read the distances of a path
+fn distances(path: &Value) -> Vec<f64> {
+    path.as_array().unwrap().iter().map(|hop| hop[1].as_f64().unwrap()).collect()
+}
+
+#[actix_web::test]
+async fn test_every_endpoint() {
+    let Some(server) = TestServer::start(&[]).await else { return };
+    let triangle = json!({ "data": [0, 1, 4, 1, 0, 2, 4, 2, 0], "width": 3, "height": 3 });
+    let square = json!({ "data": [0, 0, 0, 0, 5, 0, 0, 0, 1, 0, 0, 0, 0, 2, 4, 0], "width": 4, "height": 4 });
+    let grid = json!({ "width": 3, "height": 3, "costs": [1, 1, 1, 1, 9, 1, 1, 1, 1], "start": [0, 0], "goal": [2, 2] });
+
+    // Prepare a valid and an invalid request for every endpoint, with their status codes
+    let cases = [
+        ("GET", "/healthz", Value::Null, 200),
+        ("GET", "/readyz", Value::Null, 200),
+        ("GET", "/v1/devices", Value::Null, 200),
+        ("GET", "/v1/thermal", Value::Null, 200),
+        ("GET", "/metrics", Value::Null, 200),
+        ("GET", "/openapi.json", Value::Null, 200),
+        ("GET", "/admin/config", Value::Null, 403),
+        ("GET", "/admin/sessions", Value::Null, 403),
+        ("GET", "/admin/soak", Value::Null, 403),
+        ("GET", "/missing", Value::Null, 404),
+        ("POST", "/v1/sortest", triangle.clone(), 200),
+        ("POST", "/v1/sortest?precision=integer", triangle.clone(), 200),
+        ("POST", "/v1/sortest?unweighted=true", triangle.clone(), 200),
+        ("POST", "/v1/sortest", json!({ "data": [0, 1, 1], "width": 2, "height": 2 }), 400),
+        ("POST", "/v1/sortest", json!({ "data": [0, 1, 1, 0], "width": 2, "height": 2, "round": 9 }), 400),
+        ("POST", "/v1/sortest/sources", json!({ "data": [0, 1, 4, 1, 0, 2, 4, 2, 0], "width": 3, "height": 3, "sources": [0, 2] }), 200),
+        ("POST", "/v1/sortest/sources", json!({ "data": [0, 1, 4, 1, 0, 2, 4, 2, 0], "width": 3, "height": 3, "sources": [7] }), 400),
+        ("POST", "/v1/sortest/cost", json!({ "width": 3, "height": 3, "attributes": { "distance": [0, 4, 1, 4, 0, 2, 1, 2, 0] }, "cost": "distance * 2" }), 200),
+        ("POST", "/v1/sortest/cost", json!({ "width": 3, "height": 3, "attributes": { "distance": [0, 4, 1, 4, 0, 2, 1, 2, 0] }, "cost": "distance +" }), 400),
+        ("POST", "/v1/sortest/budget", json!({ "data": [0, 0, 0, 1, 0, 0, 5, 1, 0], "resources": [0, 0, 0, 1, 0, 0, 9, 1, 0], "width": 3, "height": 3, "budget": 4, "target": 2 }), 200),
+        ("POST", "/v1/sortest/budget", json!({ "data": [0, 0, 0, 1, 0, 0, 5, 1, 0], "resources": [0, 0, 0, 9, 0, 0, 9, 9, 0], "width": 3, "height": 3, "budget": 4, "target": 2 }), 422),
+        ("POST", "/v1/sortest/ev", json!({ "data": [0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0], "consumption": [0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0], "width": 4, "height": 4, "capacity": 2, "stations": [{ "vertex": 1, "rate": 0.5 }], "target": 3 }), 200),
+        ("POST", "/v1/sortest/loads", json!({ "width": 3, "height": 3, "data": [0, 1, 0, 1, 0, 1, 0, 1, 0], "demands": [{ "source": 0, "target": 2, "volume": 3 }] }), 200),
+        ("POST", "/v1/grid", grid.clone(), 200),
+        ("POST", "/v1/grid", json!({ "width": 3, "height": 3, "costs": [1, 1, 1], "start": [0, 0], "goal": [2, 2] }), 400),
+        ("POST", "/v1/flowfield", json!({ "grid": { "width": 3, "height": 3, "costs": [1, 1, 1, 1, 9, 1, 1, 1, 1], "goal": [2, 2] } }), 200),
+        ("POST", "/v1/walks", json!({ "data": [0, 1, 1, 0], "width": 2, "height": 2, "walks_per_vertex": 2, "length": 4, "seed": 42 }), 200),
+        ("POST", "/v1/simulate/failures", json!({ "data": [0, 1, 4, 1, 0, 2, 4, 2, 0], "width": 3, "height": 3, "trials": 4, "edge_failure": 0.5, "seed": 7 }), 200),
+        ("POST", "/v1/centrality/betweenness?normalized=true", square.clone(), 200),
+        ("POST", "/v1/pagerank", square.clone(), 200),
+        ("POST", "/v1/maxflow?source=0&sink=3", square.clone(), 200),
+        ("POST", "/v1/maxflow?source=2&sink=2", square.clone(), 400),
+        ("POST", "/v1/dag", json!({ "width": 3, "height": 3, "data": [0, 0, 0, 4, 0, 0, 1, 2, 0], "target": 2, "objective": "longest" }), 200),
+        ("POST", "/v1/dag", json!({ "width": 2, "height": 2, "data": [0, 1, 1, 0] }), 422),
+        ("POST", "/v1/explain", json!({ "data": [0, 4, 1, 4, 0, 2, 1, 2, 0], "width": 3, "height": 3, "path": [[0, 0], [2, 3], [0, 1]] }), 200),
+    ];
+
+    // Get the result
+    for (method, path, body, expected) in cases {
+        let (status, response) = server.send(method, path, body).await;
+
+        // Check if the result is correct
+        assert_eq!(status, expected, "{} {} answered {}", method, path, response);
+    }
+
+    let (_, sortest) = server.send("POST", "/v1/sortest", triangle.clone()).await;
+    assert_eq!(sortest["path"], json!([[0, 0], [0, 1], [1, 3]]));
+
+    let alias = Client::default().post(server.url("/sortest")).send_json(&triangle).await.unwrap();
+    assert_eq!((alias.status().as_u16(), alias.headers().get("Deprecation").and_then(|value| value.to_str().ok())), (200, Some("@1792022400")));
+
+    let mut streamed = Client::default().post(server.url("/v1/sortest/sources"))
+        .insert_header(("Accept", "application/x-ndjson"))
+        .send_json(&json!({ "data": [0, 1, 4, 1, 0, 2, 4, 2, 0], "width": 3, "height": 3, "sources": [0, 2] })).await.unwrap();
+    let lines: Vec<Value> = streamed.body().await.unwrap().split(|byte| *byte == b'\n').filter(|line| !line.is_empty()).map(|line| serde_json::from_slice(line).unwrap()).collect();
+    assert_eq!(lines.iter().map(|line| line["source"].clone()).collect::<Vec<_>>(), [json!(0), json!(2)]);
+}
+
+#[actix_web::test]
+async fn test_graph_lifecycle() {
+    let Some(server) = TestServer::start(&[]).await else { return };
+
+    // Get the result
+    let (created, _) = server.send("PUT", "/v1/graphs/chain", chain(6)).await;
+    let (replaced, _) = server.send("PUT", "/v1/graphs/chain", chain(8)).await;
+    let (_, listed) = server.send("GET", "/v1/graphs", Value::Null).await;
+    let (_, walked) = server.send("POST", "/v1/graphs/chain/sortest?source=0&target=7", Value::Null).await;
+    let (_, changed) = server.send("POST", "/v1/graphs/chain/whatif", json!({ "perturbations": [{ "remove_edge": { "source": 3, "target": 4 } }], "target": 7 })).await;
+    let mut apsp = Client::default().post(server.url("/v1/graphs/chain/apsp?block=3")).send().await.unwrap();
+    let lines = apsp.body().await.unwrap().split(|byte| *byte == b'\n').filter(|line| !line.is_empty()).count();
+    let (deleted, _) = server.send("DELETE", "/v1/graphs/chain", Value::Null).await;
+    let (missing, _) = server.send("POST", "/v1/graphs/chain/sortest", Value::Null).await;
+
+    // Upload the same chain in two chunks of CSV rows, the last ones first
+    let rows = |rows: std::ops::Range<usize>| rows.map(|row| (0..8).map(|column| if column + 1 == row { row.to_string() } else { "0".to_string() }).collect::<Vec<_>>().join(",") + "\n").collect::<String>();
+    let partial = Client::default().post(server.url("/v1/graphs/chunked/chunks?width=8&offset=4")).send_body(rows(4..8)).await.unwrap().status().as_u16();
+    let complete = Client::default().post(server.url("/v1/graphs/chunked/chunks?offset=0")).send_body(rows(0..4)).await.unwrap().status().as_u16();
+    let (_, chunked) = server.send("POST", "/v1/graphs/chunked/sortest?source=0", Value::Null).await;
+
+    // Check if the result is correct
+    assert_eq!((created, replaced, deleted, missing), (201, 200, 200, 404));
+    assert_eq!((partial, complete), (202, 201));
+    assert_eq!(distances(&chunked["path"]), chain_distances(8));
+    assert_eq!(listed["graphs"].as_array().unwrap().len(), 1);
+    assert_eq!(distances(&walked["path"]), chain_distances(8));
+    assert_eq!(walked["route"], json!([0, 1, 2, 3, 4, 5, 6, 7]));
+    assert_eq!(changed["route"], Value::Null);
+    assert_eq!(lines, 8);
+}
+
+#[actix_web::test]
+async fn test_replan_session() {
+    let Some(server) = TestServer::start(&[]).await else { return };
+
+    // Get the result
+    let (_, plan) = server.send("POST", "/v1/replan", json!({ "grid": { "width": 4, "height": 2, "costs": [1, 1, 1, 1, 1, 1, 1, 1], "start": [0, 0], "goal": [3, 0] } })).await;
+    let session = plan["session"].as_str().unwrap().to_string();
+    let (repaired, _) = server.send("POST", &format!("/v1/replan/{}", session), json!({ "start": [1, 0], "cells": [{ "cell": [2, 0], "cost": 0 }] })).await;
+    let (invalid, _) = server.send("POST", &format!("/v1/replan/{}", session), json!({ "cells": [{ "cell": [9, 9], "cost": 1 }] })).await;
+    let (ended, _) = server.send("DELETE", &format!("/v1/replan/{}", session), Value::Null).await;
+    let (missing, _) = server.send("DELETE", &format!("/v1/replan/{}", session), Value::Null).await;
+
+    // Check if the result is correct
+    assert_eq!(plan["cost"], 3.0);
+    assert_eq!((repaired, invalid, ended, missing), (200, 400, 200, 404));
+}
+
+#[actix_web::test]
+async fn test_limits() {
+    let Some(server) = TestServer::start(&["--max-payload", "1024", "--memory-budget", "1024"]).await else { return };
+
+    // Get the result
+    let (too_large, body) = server.send("POST", "/v1/sortest", chain(64)).await;
+    let (over_budget, shortfall) = server.send("POST", "/v1/sortest", chain(8)).await;
+    let (malformed, _) = {
+        let mut response = Client::default().post(server.url("/v1/sortest")).content_type("application/json").send_body("{\"data\": [").await.unwrap();
+        (response.status().as_u16(), response.body().await.unwrap())
+    };
+
+    // Check if the result is correct
+    assert_eq!(too_large, 413);
+    assert_eq!(body["limit"], 1024);
+    assert_eq!(malformed, 400);
+    assert_eq!(over_budget, 507);
+    assert_eq!((shortfall["required_bytes"].as_u64(), shortfall["available_bytes"].as_u64()), (Some(1792), Some(1024)));
+}
+
+#[actix_web::test]
+async fn test_concurrent_walks() {
+    let Some(server) = TestServer::start(&[]).await else { return };
+
+    // Get the result, walking chains of different widths at once
+    let widths: Vec<usize> = (0..32).map(|index| 2 + index % 24).collect();
+    let responses = join_all(widths.iter().map(|width| server.send("POST", "/v1/sortest", chain(*width)))).await;
+
+    // Check if the result is correct
+    for (width, (status, response)) in widths.iter().zip(responses) {
+        assert_eq!(status, 200, "The chain of {} vertices answered {}", width, response);
+        assert_eq!(distances(&response["path"]), chain_distances(*width));
+    }
+}
+
+#[actix_web::test]
+async fn test_websocket_walk() {
+    let Some(server) = TestServer::start(&[]).await else { return };
+
+    // Send a matrix and an invalid one over the same session
+    let (_, mut framed) = Client::default().ws(server.url("/v1/ws/sortest")).connect().await.unwrap();
+    framed.send(ws::Message::Text(chain(5).to_string().into())).await.unwrap();
+    framed.send(ws::Message::Text(json!({ "data": [0, 1, 1], "width": 2, "height": 2 }).to_string().into())).await.unwrap();
+
+    // Get the result, until both matrices are answered
+    let mut messages = vec![];
+
+    while messages.iter().filter(|message: &&Value| message["status"] != "progress").count() < 2 {
+        match framed.next().await.unwrap().unwrap() {
+            ws::Frame::Text(text) => messages.push(serde_json::from_slice(&text).unwrap()),
+            ws::Frame::Ping(payload) => framed.send(ws::Message::Pong(payload)).await.unwrap(),
+            _ => {}
+        }
+    }
+
+    let answers: Vec<&Value> = messages.iter().filter(|message| message["status"] != "progress").collect();
+
+    // Check if the result is correct
+    assert_eq!(messages[0]["status"], "progress");
+    assert_eq!(answers[0]["status"], "ok");
+    assert_eq!(distances(&answers[0]["path"]), chain_distances(5));
+    assert_eq!(answers[1]["status"], "error");
+}
@@ -1 +0,0 @@
-pub mod sortest_path;
\ No newline at end of file